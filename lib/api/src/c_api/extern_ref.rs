@@ -15,6 +15,17 @@ impl ExternRef {
         unimplemented!("ExternRef is not yet supported with wasm_c_api");
     }
 
+    pub fn new_with_finalizer<T>(
+        _store: &mut impl AsStoreMut,
+        _value: T,
+        _finalizer: Box<dyn FnOnce() + Send + Sync + 'static>,
+    ) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+    {
+        unimplemented!("ExternRef is not yet supported with wasm_c_api");
+    }
+
     pub fn downcast<'a, T>(&self, _store: &'a impl AsStoreRef) -> Option<&'a T>
     where
         T: Any + Send + Sync + 'static + Sized,
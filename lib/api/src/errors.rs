@@ -177,6 +177,13 @@ impl RuntimeError {
         &self.inner.wasm_trace
     }
 
+    /// Returns the wasm functions that this trap went through, starting with
+    /// the one where it happened (see [`Trap::wasm_frames`]).
+    #[cfg(feature = "sys")]
+    pub fn wasm_frames(&self) -> &[wasmer_vm::WasmFrame] {
+        self.inner.source.wasm_frames()
+    }
+
     /// Returns trap code, if it's a Trap
     pub fn to_trap(self) -> Option<TrapCode> {
         self.inner.trap_code
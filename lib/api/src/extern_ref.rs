@@ -26,6 +26,27 @@ impl ExternRef {
         Self(extern_ref_imp::ExternRef::new(store, value))
     }
 
+    /// Make a new extern reference that runs `finalizer` once the last
+    /// reference to it is dropped.
+    ///
+    /// The `ExternRef` handles (including the ones obtained back from Wasm
+    /// while the value is still referenced) are counted, and the finalizer
+    /// runs exactly once when the last of them goes away. References held
+    /// only by Wasm (in tables, globals or locals) are not counted, so keep
+    /// a handle alive for as long as Wasm may use the value.
+    pub fn new_with_finalizer<T>(
+        store: &mut impl AsStoreMut,
+        value: T,
+        finalizer: Box<dyn FnOnce() + Send + Sync + 'static>,
+    ) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+    {
+        Self(extern_ref_imp::ExternRef::new_with_finalizer(
+            store, value, finalizer,
+        ))
+    }
+
     /// Try to downcast to the given value.
     pub fn downcast<'a, T>(&self, store: &'a impl AsStoreRef) -> Option<&'a T>
     where
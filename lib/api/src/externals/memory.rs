@@ -15,7 +15,8 @@ use crate::MemoryAccessError;
 use crate::MemoryType;
 use crate::{AtomicsError, Extern};
 use std::mem::MaybeUninit;
-use wasmer_types::{MemoryError, Pages};
+use std::ops::Range;
+use wasmer_types::{MemoryError, MemoryProtection, Pages};
 
 /// A WebAssembly `memory` instance.
 ///
@@ -134,6 +135,27 @@ impl Memory {
         self.0.grow_at_least(store, min_size)
     }
 
+    /// Changes the access that guest code has to the bytes in `range`, for
+    /// example making them read-only so that writes to them trap.
+    ///
+    /// The range must start on a host page boundary and lie within the
+    /// current size of the memory. Only the `sys` backend supports this.
+    ///
+    /// Host accesses through a [`MemoryView`] or a [`WasmSlice`](crate::WasmSlice)
+    /// are checked against the protection and fail with
+    /// [`MemoryAccessError::Protected`], a view only sees the protection at
+    /// the time it was created though. Raw pointers such as
+    /// [`MemoryView::data_unchecked_mut`] are not checked, touching protected
+    /// bytes through them traps inside the host which skips its destructors.
+    pub fn protect(
+        &self,
+        store: &mut impl AsStoreMut,
+        range: Range<u64>,
+        protection: MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        self.0.protect(store, range, protection)
+    }
+
     /// Resets the memory back to zero length
     pub fn reset(&self, store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
         self.0.reset(store)?;
@@ -363,4 +385,9 @@ impl<'a> MemoryBuffer<'a> {
     pub(crate) fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
         self.0.write(offset, data)
     }
+
+    #[allow(unused)]
+    pub(crate) fn check_writable(&self, offset: u64, len: u64) -> Result<(), MemoryAccessError> {
+        self.0.check_writable(offset, len)
+    }
 }
@@ -78,6 +78,29 @@ impl Imports {
         self.map.keys().any(|(k, _)| (k == name))
     }
 
+    /// Returns every import whose module and name satisfy `pred`, sorted by
+    /// module and name.
+    ///
+    /// This is useful to resolve imports against namespaces that differ
+    /// slightly from the ones a module was compiled against.
+    ///
+    /// # Usage
+    /// ```no_run
+    /// # use wasmer::Imports;
+    /// let import_object = Imports::new();
+    /// let env_imports = import_object.resolve_by(|module, _name| module == "env");
+    /// ```
+    pub fn resolve_by<F: Fn(&str, &str) -> bool>(&self, pred: F) -> Vec<(String, String, Extern)> {
+        let mut ret: Vec<(String, String, Extern)> = self
+            .map
+            .iter()
+            .filter(|((module, name), _)| pred(module, name))
+            .map(|((module, name), e)| (module.clone(), name.clone(), e.clone()))
+            .collect();
+        ret.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        ret
+    }
+
     /// Register a list of externs into a namespace.
     ///
     /// # Usage:
@@ -331,6 +354,49 @@ mod test {
         });
     }
 
+    #[test]
+    fn resolve_by() {
+        let mut store = Store::default();
+        let g1 = Global::new(&mut store, Value::I32(0));
+        let g2 = Global::new(&mut store, Value::I32(1));
+        let g3 = Global::new(&mut store, Value::I32(2));
+        let imports = imports! {
+            "env" => {
+                "_foo" => g1,
+                "bar" => g2,
+            },
+            "wasi" => {
+                "_baz" => g3,
+            },
+        };
+        let names = |pred: &dyn Fn(&str, &str) -> bool| -> Vec<(String, String)> {
+            imports
+                .resolve_by(pred)
+                .into_iter()
+                .map(|(module, name, _)| (module, name))
+                .collect()
+        };
+
+        assert!(names(&|module, _| module == "missing").is_empty());
+        assert_eq!(
+            names(&|_, name| name.starts_with('_')),
+            [
+                ("env".to_string(), "_foo".to_string()),
+                ("wasi".to_string(), "_baz".to_string())
+            ]
+        );
+        assert_eq!(names(&|_, _| true).len(), 3);
+
+        let resolved = imports.resolve_by(|module, name| module == "env" && name == "bar");
+        assert!(
+            if let [(_, _, Extern::Global(global))] = resolved.as_slice() {
+                global.get(&mut store) == Value::I32(1)
+            } else {
+                false
+            }
+        );
+    }
+
     #[test]
     fn imports_macro_allows_trailing_comma_and_none() {
         use crate::Function;
@@ -155,6 +155,17 @@ impl Memory {
         Ok(())
     }
 
+    pub fn protect(
+        &self,
+        _store: &mut impl AsStoreMut,
+        _range: std::ops::Range<u64>,
+        _protection: wasmer_types::MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        Err(MemoryError::UnsupportedOperation {
+            message: "protect() is not supported".to_string(),
+        })
+    }
+
     pub fn reset(&self, _store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
         Ok(())
     }
@@ -296,6 +307,11 @@ impl<'a> MemoryBuffer<'a> {
         }
         Ok(())
     }
+
+    pub(crate) fn check_writable(&self, _offset: u64, _len: u64) -> Result<(), MemoryAccessError> {
+        // Memory can not be protected on this backend
+        Ok(())
+    }
 }
 
 // We can't use a normal memcpy here because it has undefined behavior if the
@@ -25,6 +25,10 @@ pub enum MemoryAccessError {
     /// String is not valid UTF-8.
     #[error("string is not valid utf-8")]
     NonUtf8String,
+    /// Memory access to bytes that are protected against it, see
+    /// [`Memory::protect`](crate::Memory::protect).
+    #[error("memory access to protected memory")]
+    Protected,
 }
 
 impl From<MemoryAccessError> for RuntimeError {
@@ -242,6 +246,19 @@ impl<'a, T: ValueType> WasmSlice<'a, T> {
         WasmSliceAccess::new(self)
     }
 
+    /// Gains direct access to the memory of this slice in order to write to
+    /// it, unlike [`Self::access`] this fails if any of the slice is protected
+    /// against writes (see [`Memory::protect`](crate::Memory::protect))
+    #[inline]
+    pub fn access_mut(self) -> Result<WasmSliceAccess<'a, T>, MemoryAccessError> {
+        let total_len = self
+            .len
+            .checked_mul(mem::size_of::<T>() as u64)
+            .ok_or(MemoryAccessError::Overflow)?;
+        self.buffer.check_writable(self.offset, total_len)?;
+        WasmSliceAccess::new(self)
+    }
+
     /// Reads an element of this slice.
     #[inline]
     pub fn read(self, idx: u64) -> Result<T, MemoryAccessError> {
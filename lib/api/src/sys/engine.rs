@@ -2,11 +2,12 @@ use std::{path::Path, sync::Arc};
 
 use shared_buffer::OwnedBuffer;
 pub use wasmer_compiler::{
-    types::target::Target, Artifact, BaseTunables, CompilerConfig, Engine, EngineBuilder, Tunables,
+    types::target::Target, Artifact, BaseTunables, CompilerConfig, Engine, EngineBuilder,
+    PinnedArtifact, Tunables,
 };
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
-use wasmer_types::{DeserializeError, HashAlgorithm};
+use wasmer_types::{CompileError, DeserializeError, HashAlgorithm};
 
 /// Get the default config for the sys Engine
 #[allow(unreachable_code)]
@@ -103,6 +104,17 @@ pub trait NativeEngineExt {
         &self,
         file_ref: &Path,
     ) -> Result<crate::Module, DeserializeError>;
+
+    /// Keeps the compiled code of a module resident in memory until the
+    /// returned [`PinnedArtifact`] (and all its clones) are dropped.
+    ///
+    /// A [`crate::Module`] can be created from the pinned artifact with
+    /// `Module::from(&pinned)`.
+    fn pin_module(&self, module: &crate::Module) -> Result<PinnedArtifact, CompileError>;
+
+    /// Returns the number of distinct function signatures registered by the
+    /// modules of this engine, they are released when the engine is dropped.
+    fn signature_count(&self) -> usize;
 }
 
 impl NativeEngineExt for crate::engine::Engine {
@@ -160,4 +172,12 @@ impl NativeEngineExt for crate::engine::Engine {
     fn set_hash_algorithm(&mut self, hash_algorithm: Option<HashAlgorithm>) {
         self.0.set_hash_algorithm(hash_algorithm)
     }
+
+    fn pin_module(&self, module: &crate::Module) -> Result<PinnedArtifact, CompileError> {
+        self.0.pin_artifact(module.0.artifact().clone())
+    }
+
+    fn signature_count(&self) -> usize {
+        self.0.signature_count()
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{LinkError, RuntimeError};
-use wasmer_vm::Trap;
+use wasmer_vm::{Trap, WasmFrame};
 
 impl From<wasmer_compiler::LinkError> for LinkError {
     fn from(other: wasmer_compiler::LinkError) -> Self {
@@ -19,6 +19,8 @@ impl From<Trap> for RuntimeError {
             return trap.downcast::<Self>().unwrap();
         }
         let (wasm_trace, trap_code) = wasmer_compiler::get_trace_and_trapcode(&trap);
+        let mut trap = trap;
+        trap.set_wasm_frames(wasm_trace.iter().map(WasmFrame::from).collect());
         Self::new_from_source(trap, wasm_trace, trap_code)
     }
 }
@@ -1,13 +1,14 @@
 use std::any::Any;
 use wasmer_vm::VMExternRef;
-use wasmer_vm::{StoreHandle, VMExternObj};
+use wasmer_vm::{StoreHandle, VMExternObj, VMExternRefGuard};
 
 use crate::store::{AsStoreMut, AsStoreRef};
 
 #[derive(Debug, Clone)]
-#[repr(transparent)]
 pub struct ExternRef {
     handle: StoreHandle<VMExternObj>,
+    // Keeps the finalizer of the value from running while this handle lives
+    guard: Option<VMExternRefGuard>,
 }
 
 impl ExternRef {
@@ -17,6 +18,22 @@ impl ExternRef {
     {
         Self {
             handle: StoreHandle::new(store.objects_mut(), VMExternObj::new(value)),
+            guard: None,
+        }
+    }
+
+    pub fn new_with_finalizer<T>(
+        store: &mut impl AsStoreMut,
+        value: T,
+        finalizer: Box<dyn FnOnce() + Send + Sync + 'static>,
+    ) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+    {
+        let (obj, guard) = VMExternObj::new_with_finalizer(value, finalizer);
+        Self {
+            handle: StoreHandle::new(store.objects_mut(), obj),
+            guard: Some(guard),
         }
     }
 
@@ -38,9 +55,9 @@ impl ExternRef {
         store: &mut impl AsStoreMut,
         vm_externref: VMExternRef,
     ) -> Self {
-        Self {
-            handle: StoreHandle::from_internal(store.objects_mut().id(), vm_externref.0),
-        }
+        let handle = StoreHandle::from_internal(store.objects_mut().id(), vm_externref.0);
+        let guard = handle.get(store.as_store_ref().objects()).guard();
+        Self { handle, guard }
     }
 
     pub fn is_from_store(&self, store: &impl AsStoreRef) -> bool {
@@ -6,9 +6,10 @@ use std::{
 };
 
 use tracing::warn;
-use wasmer_types::Pages;
+use wasmer_types::{MemoryProtection, Pages};
 use wasmer_vm::{
-    LinearMemory, MemoryError, StoreHandle, ThreadConditionsHandle, VMExtern, VMMemory,
+    LinearMemory, MemoryError, ProtectedPages, StoreHandle, ThreadConditionsHandle, VMExtern,
+    VMMemory,
 };
 
 use crate::{
@@ -66,6 +67,17 @@ impl Memory {
             .grow_at_least(min_size)
     }
 
+    pub fn protect(
+        &self,
+        store: &mut impl AsStoreMut,
+        range: std::ops::Range<u64>,
+        protection: MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        self.handle
+            .get_mut(store.objects_mut())
+            .protect(range, protection)
+    }
+
     pub fn reset(&self, store: &mut impl AsStoreMut) -> Result<(), MemoryError> {
         self.handle.get_mut(store.objects_mut()).reset()?;
         Ok(())
@@ -182,10 +194,38 @@ impl std::cmp::Eq for Memory {}
 pub(crate) struct MemoryBuffer<'a> {
     pub(crate) base: *mut u8,
     pub(crate) len: usize,
+    // Pages that must not be touched directly, as the host would fault
+    pub(crate) protected: Option<&'a ProtectedPages>,
     pub(crate) marker: PhantomData<&'a MemoryView<'a>>,
 }
 
 impl<'a> MemoryBuffer<'a> {
+    /// Fails if the bytes in `offset..end` are protected against the access
+    pub(crate) fn check_protection(
+        &self,
+        offset: u64,
+        end: u64,
+        write: bool,
+    ) -> Result<(), MemoryAccessError> {
+        match self.protected {
+            Some(protected) if !protected.allows(offset..end, write) => {
+                warn!(
+                    "attempted to {} ({} bytes) protected memory at {}",
+                    if write { "write" } else { "read" },
+                    end - offset,
+                    offset
+                );
+                Err(MemoryAccessError::Protected)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_writable(&self, offset: u64, len: u64) -> Result<(), MemoryAccessError> {
+        let end = offset.checked_add(len).ok_or(MemoryAccessError::Overflow)?;
+        self.check_protection(offset, end, true)
+    }
+
     pub(crate) fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
         let end = offset
             .checked_add(buf.len() as u64)
@@ -199,6 +239,7 @@ impl<'a> MemoryBuffer<'a> {
             );
             return Err(MemoryAccessError::HeapOutOfBounds);
         }
+        self.check_protection(offset, end, false)?;
         unsafe {
             volatile_memcpy_read(self.base.add(offset as usize), buf.as_mut_ptr(), buf.len());
         }
@@ -222,6 +263,7 @@ impl<'a> MemoryBuffer<'a> {
             );
             return Err(MemoryAccessError::HeapOutOfBounds);
         }
+        self.check_protection(offset, end, false)?;
         let buf_ptr = buf.as_mut_ptr() as *mut u8;
         unsafe {
             volatile_memcpy_read(self.base.add(offset as usize), buf_ptr, buf.len());
@@ -243,6 +285,7 @@ impl<'a> MemoryBuffer<'a> {
             );
             return Err(MemoryAccessError::HeapOutOfBounds);
         }
+        self.check_protection(offset, end, true)?;
         unsafe {
             volatile_memcpy_write(data.as_ptr(), self.base.add(offset as usize), data.len());
         }
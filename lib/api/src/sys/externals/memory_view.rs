@@ -3,9 +3,10 @@ use crate::MemoryAccessError;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 use std::slice;
+use std::sync::Arc;
 use std::{convert::TryInto, ops::Range};
 use wasmer_types::Pages;
-use wasmer_vm::LinearMemory;
+use wasmer_vm::{LinearMemory, ProtectedPages};
 
 use super::memory::{Memory, MemoryBuffer};
 
@@ -17,13 +18,19 @@ use super::memory::{Memory, MemoryBuffer};
 /// created using the Memory.grow() method.
 #[derive(Debug)]
 pub struct MemoryView<'a> {
+    // Does not know about the protected pages, go through `buffer()`
     pub(crate) buffer: MemoryBuffer<'a>,
+    pub(crate) protected: Option<Arc<ProtectedPages>>,
     pub(crate) size: Pages,
 }
 
 impl<'a> MemoryView<'a> {
     pub(crate) fn new(memory: &Memory, store: &'a (impl AsStoreRef + ?Sized)) -> Self {
         let size = memory.handle.get(store.as_store_ref().objects()).size();
+        let protected = memory
+            .handle
+            .get(store.as_store_ref().objects())
+            .protected_pages();
 
         let definition = memory.handle.get(store.as_store_ref().objects()).vmmemory();
         let def = unsafe { definition.as_ref() };
@@ -32,8 +39,10 @@ impl<'a> MemoryView<'a> {
             buffer: MemoryBuffer {
                 base: def.base,
                 len: def.current_length,
+                protected: None,
                 marker: PhantomData,
             },
+            protected,
             size,
         }
     }
@@ -96,8 +105,13 @@ impl<'a> MemoryView<'a> {
     }
 
     #[inline]
-    pub(crate) fn buffer(&'a self) -> MemoryBuffer<'a> {
-        self.buffer
+    pub(crate) fn buffer(&self) -> MemoryBuffer<'_> {
+        MemoryBuffer {
+            base: self.buffer.base,
+            len: self.buffer.len,
+            protected: self.protected.as_deref(),
+            marker: PhantomData,
+        }
     }
 
     /// Safely reads bytes from the memory at the given offset.
@@ -108,7 +122,7 @@ impl<'a> MemoryView<'a> {
     /// This method is guaranteed to be safe (from the host side) in the face of
     /// concurrent writes.
     pub fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
-        self.buffer.read(offset, buf)
+        self.buffer().read(offset, buf)
     }
 
     /// Safely reads a single byte from memory at the given offset
@@ -136,7 +150,7 @@ impl<'a> MemoryView<'a> {
         offset: u64,
         buf: &'b mut [MaybeUninit<u8>],
     ) -> Result<&'b mut [u8], MemoryAccessError> {
-        self.buffer.read_uninit(offset, buf)
+        self.buffer().read_uninit(offset, buf)
     }
 
     /// Safely writes bytes to the memory at the given offset.
@@ -147,7 +161,7 @@ impl<'a> MemoryView<'a> {
     /// This method is guaranteed to be safe (from the host side) in the face of
     /// concurrent reads/writes.
     pub fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
-        self.buffer.write(offset, data)
+        self.buffer().write(offset, data)
     }
 
     /// Safely reads a single byte from memory at the given offset
@@ -27,6 +27,18 @@ where
             );
             return Err(MemoryAccessError::HeapOutOfBounds);
         }
+        slice.buffer.0.check_protection(slice.offset, end, false)?;
+        if let Some(protected) = slice.buffer.0.protected {
+            if !protected.allows(slice.offset..end, true) {
+                // Read-only memory is handed out as a copy, writing the copy
+                // back when the access is dropped fails (use
+                // `WasmSlice::access_mut` to find out up front)
+                return Ok(Self {
+                    slice,
+                    buf: SliceCow::Owned(slice.read_to_vec()?, false),
+                });
+            }
+        }
         let buf = unsafe {
             let buf_ptr: *mut u8 = slice.buffer.0.base.add(slice.offset as usize);
             let buf_ptr: *mut T = std::mem::transmute(buf_ptr);
@@ -58,6 +70,8 @@ where
             );
             return Err(MemoryAccessError::HeapOutOfBounds);
         }
+        // The value is handed out mutably, so it must be writable
+        ptr.buffer.0.check_protection(ptr.offset, end, true)?;
         let val = unsafe {
             let val_ptr: *mut u8 = ptr.buffer.0.base.add(ptr.offset as usize);
             let val_ptr: *mut T = std::mem::transmute(val_ptr);
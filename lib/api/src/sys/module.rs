@@ -2,7 +2,7 @@ use std::path::Path;
 use std::sync::Arc;
 
 use bytes::Bytes;
-use wasmer_compiler::{Artifact, ArtifactCreate};
+use wasmer_compiler::{Artifact, ArtifactCreate, PinnedArtifact};
 use wasmer_types::{
     CompileError, DeserializeError, ExportsIterator, ImportsIterator, ModuleInfo, SerializeError,
 };
@@ -128,6 +128,10 @@ impl Module {
         Self { artifact }
     }
 
+    pub(super) fn artifact(&self) -> &Arc<Artifact> {
+        &self.artifact
+    }
+
     #[allow(clippy::result_large_err)]
     pub(crate) fn instantiate(
         &self,
@@ -200,3 +204,9 @@ impl Module {
         self.artifact.module_info()
     }
 }
+
+impl From<&PinnedArtifact> for crate::Module {
+    fn from(pinned: &PinnedArtifact) -> Self {
+        Self(Module::from_artifact(pinned.artifact().clone()))
+    }
+}
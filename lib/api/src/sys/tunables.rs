@@ -24,13 +24,17 @@ mod tests {
             static_memory_bound: Pages(2048),
             static_memory_offset_guard_size: 128,
             dynamic_memory_offset_guard_size: 256,
+            dynamic_memory_growth_granularity: Pages(1),
+            zeroing_strategy: Default::default(),
         };
 
         // No maximum
         let requested = MemoryType::new(3, None, true);
         let style = tunables.memory_style(&requested);
         match style {
-            MemoryStyle::Dynamic { offset_guard_size } => assert_eq!(offset_guard_size, 256),
+            MemoryStyle::Dynamic {
+                offset_guard_size, ..
+            } => assert_eq!(offset_guard_size, 256),
             s => panic!("Unexpected memory style: {:?}", s),
         }
 
@@ -38,7 +42,9 @@ mod tests {
         let requested = MemoryType::new(3, Some(5_000_000), true);
         let style = tunables.memory_style(&requested);
         match style {
-            MemoryStyle::Dynamic { offset_guard_size } => assert_eq!(offset_guard_size, 256),
+            MemoryStyle::Dynamic {
+                offset_guard_size, ..
+            } => assert_eq!(offset_guard_size, 256),
             s => panic!("Unexpected memory style: {:?}", s),
         }
 
@@ -303,6 +309,109 @@ mod tests {
         Ok(())
     }
 
+    /// Tunables that turn down memories larger than a fixed budget
+    struct BudgetTunables {
+        base: BaseTunables,
+        budget: usize,
+    }
+
+    impl BudgetTunables {
+        fn check_budget(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+            let requested = ty.minimum.bytes().0;
+            if requested > self.budget {
+                return Err(MemoryError::InsufficientMemory {
+                    requested,
+                    reason: format!("the budget is {} bytes", self.budget),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    impl Tunables for BudgetTunables {
+        fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+            self.base.memory_style(memory)
+        }
+
+        fn table_style(&self, table: &TableType) -> TableStyle {
+            self.base.table_style(table)
+        }
+
+        fn create_host_memory(
+            &self,
+            ty: &MemoryType,
+            style: &MemoryStyle,
+        ) -> Result<VMMemory, MemoryError> {
+            self.check_budget(ty)?;
+            self.base.create_host_memory(ty, style)
+        }
+
+        unsafe fn create_vm_memory(
+            &self,
+            ty: &MemoryType,
+            style: &MemoryStyle,
+            vm_definition_location: NonNull<VMMemoryDefinition>,
+        ) -> Result<VMMemory, MemoryError> {
+            self.check_budget(ty)?;
+            self.base
+                .create_vm_memory(ty, style, vm_definition_location)
+        }
+
+        fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<VMTable, String> {
+            self.base.create_host_table(ty, style)
+        }
+
+        unsafe fn create_vm_table(
+            &self,
+            ty: &TableType,
+            style: &TableStyle,
+            vm_definition_location: NonNull<VMTableDefinition>,
+        ) -> Result<VMTable, String> {
+            self.base.create_vm_table(ty, style, vm_definition_location)
+        }
+    }
+
+    #[test]
+    fn check_insufficient_memory() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{
+            imports, wat2wasm, Engine, Instance, InstantiationError, LinkError, Module, Store,
+            Target,
+        };
+
+        // The whole 4GiB that a 32-bit memory can address
+        let wasm_bytes = wat2wasm(br#"(module (memory (;0;) 65536))"#)?;
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "singlepass")] {
+                let compiler =  wasmer_compiler_singlepass::Singlepass::default();
+            } else if #[cfg(feature = "llvm")] {
+                let compiler =  wasmer_compiler_llvm::LLVM::default();
+            } else {
+                let compiler =  wasmer_compiler_cranelift::Cranelift::default();
+            }
+        }
+
+        let tunables = BudgetTunables {
+            base: BaseTunables::for_target(&Target::default()),
+            budget: 16 * WASM_PAGE_SIZE,
+        };
+        #[allow(deprecated)]
+        let mut engine = Engine::new(compiler.into(), Default::default(), Default::default());
+        engine.set_tunables(tunables);
+        let mut store = Store::new(engine);
+        let module = Module::new(&store, wasm_bytes)?;
+
+        match Instance::new(&mut store, &module, &imports! {}) {
+            Err(InstantiationError::Link(LinkError::Resource(message))) => {
+                assert!(message.contains("Insufficient memory"), "{message}");
+            }
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("the memory should not fit in the budget"),
+        }
+
+        Ok(())
+    }
+
     #[test]
     #[cfg(all(
         feature = "singlepass",
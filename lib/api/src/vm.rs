@@ -44,6 +44,10 @@ pub use wasmer_vm::{
     VMMemoryDefinition,
     VMTableDefinition,
 };
+// Signatures are registered per engine, the registry can be inspected for
+// observability
+#[cfg(feature = "sys")]
+pub use wasmer_vm::SignatureRegistry;
 
 // Deprecated exports
-pub use wasmer_types::{MemoryError, MemoryStyle, TableStyle};
+pub use wasmer_types::{MemoryError, MemoryStyle, TableStyle, ZeroingStrategy};
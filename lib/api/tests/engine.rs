@@ -0,0 +1,66 @@
+#[cfg(feature = "sys")]
+#[test]
+fn signatures_are_released_with_their_engines() -> Result<(), String> {
+    use wasmer::{
+        imports, sys::NativeEngineExt, vm::SignatureRegistry, Engine, Instance, Module, Store,
+    };
+
+    // The number of live signatures is counted for the whole process, so
+    // the test runs again in a process of its own where no other test can
+    // register signatures while it is measuring
+    const CHILD: &str = "WASMER_TEST_SIGNATURES_CHILD";
+    if std::env::var_os(CHILD).is_none() {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        let status = std::process::Command::new(exe)
+            .args([
+                "--exact",
+                "signatures_are_released_with_their_engines",
+                "--test-threads=1",
+            ])
+            .env(CHILD, "1")
+            .status()
+            .map_err(|e| e.to_string())?;
+        assert!(status.success(), "the test failed in its own process");
+        return Ok(());
+    }
+
+    let baseline = SignatureRegistry::live_signatures();
+    let compile = |engine: &Engine, params: usize| {
+        let params = "i32 ".repeat(params);
+        let wat = format!(
+            r#"(module
+                (func (export "a") (param {params}))
+                (func (export "b") (param {params}) (result i64) i64.const 0)
+            )"#
+        );
+        Module::new(engine, wat).map_err(|e| format!("{e:?}"))
+    };
+
+    for params in 0..32 {
+        let engine = Engine::default();
+        let module = compile(&engine, params)?;
+        assert_eq!(engine.signature_count(), 2);
+        assert_eq!(SignatureRegistry::live_signatures(), baseline + 2);
+
+        // The instances of a module keep its signatures registered
+        let mut store = Store::new(engine.clone());
+        let instance =
+            Instance::new(&mut store, &module, &imports! {}).map_err(|e| format!("{e:?}"))?;
+        drop(module);
+        drop(instance);
+        assert_eq!(engine.signature_count(), 2);
+        drop(store);
+        assert_eq!(engine.signature_count(), 0);
+
+        // A module that outlives its engine releases the signatures once
+        // it is dropped as well
+        let module = compile(&engine, params + 1)?;
+        assert_eq!(SignatureRegistry::live_signatures(), baseline + 2);
+        drop(engine);
+        drop(module);
+        assert_eq!(SignatureRegistry::live_signatures(), baseline);
+    }
+
+    assert_eq!(SignatureRegistry::live_signatures(), baseline);
+    Ok(())
+}
@@ -130,6 +130,31 @@ fn table_get() -> Result<(), String> {
     Ok(())
 }
 
+#[cfg(feature = "sys")]
+#[test]
+fn extern_ref_finalizer_runs_with_the_last_reference() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let finalized = Arc::new(AtomicBool::new(false));
+    let mut store = Store::default();
+    let extern_ref = ExternRef::new_with_finalizer(&mut store, 7u32, {
+        let finalized = finalized.clone();
+        Box::new(move || assert!(!finalized.swap(true, Ordering::SeqCst)))
+    });
+    assert_eq!(extern_ref.downcast::<u32>(&store), Some(&7u32));
+
+    // Dropping a clone leaves the value alive
+    drop(extern_ref.clone());
+    assert!(!finalized.load(Ordering::SeqCst));
+
+    drop(extern_ref);
+    assert!(finalized.load(Ordering::SeqCst));
+
+    // Dropping the store afterwards does not run it again
+    drop(store);
+}
+
 #[universal_test]
 fn table_set() -> Result<(), String> {
     // Table set not yet tested
@@ -291,3 +291,70 @@ fn module_custom_sections() -> Result<(), String> {
     );
     Ok(())
 }
+
+#[cfg(feature = "sys")]
+#[test]
+fn pinned_module_instantiates() -> Result<(), String> {
+    let mut store = Store::default();
+    let wat = r#"(module
+(func (export "answer") (result i32) i32.const 42)
+)"#;
+    let module = Module::new(&store, wat).map_err(|e| format!("{e:?}"))?;
+    let pinned = store
+        .engine()
+        .pin_module(&module)
+        .map_err(|e| format!("{e:?}"))?;
+
+    // Every instance runs the same resident code
+    for _ in 0..2 {
+        let module = Module::from(&pinned);
+        let instance =
+            Instance::new(&mut store, &module, &imports! {}).map_err(|e| format!("{e:?}"))?;
+        let answer: TypedFunction<(), i32> = instance
+            .exports
+            .get_typed_function(&store, "answer")
+            .map_err(|e| format!("{e:?}"))?;
+        assert_eq!(answer.call(&mut store).map_err(|e| format!("{e:?}"))?, 42);
+    }
+
+    // The code is unpinned once the last clone goes away, and a second pin
+    // of the same module keeps it pinned until that one goes away too
+    let clone = pinned.clone();
+    let again = store
+        .engine()
+        .pin_module(&module)
+        .map_err(|e| format!("{e:?}"))?;
+    #[cfg(target_os = "linux")]
+    let locked = locked_memory();
+    drop(pinned);
+    drop(clone);
+    #[cfg(target_os = "linux")]
+    assert_eq!(locked_memory(), locked);
+    drop(again);
+    #[cfg(target_os = "linux")]
+    assert!(locked_memory() < locked);
+    Ok(())
+}
+
+/// Returns the amount of memory that the process has locked (in KiB)
+#[cfg(all(feature = "sys", target_os = "linux"))]
+fn locked_memory() -> u64 {
+    std::fs::read_to_string("/proc/self/status")
+        .unwrap()
+        .lines()
+        .find_map(|line| line.strip_prefix("VmLck:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+        .unwrap()
+}
+
+#[cfg(feature = "sys")]
+#[test]
+fn pinning_a_module_of_another_engine_fails() -> Result<(), String> {
+    let store = Store::default();
+    let module =
+        Module::new(&store, "(module (func (export \"f\")))").map_err(|e| format!("{e:?}"))?;
+
+    let other = Store::default();
+    assert!(other.engine().pin_module(&module).is_err());
+    Ok(())
+}
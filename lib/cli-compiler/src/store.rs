@@ -76,6 +76,7 @@ impl SubsetTunables {
         } else {
             MemoryStyle::Dynamic {
                 offset_guard_size: self.dynamic_memory_offset_guard_size,
+                growth_granularity: Pages(1),
             }
         }
     }
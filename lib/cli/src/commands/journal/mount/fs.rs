@@ -20,7 +20,7 @@ use shared_buffer::OwnedBuffer;
 use tokio::runtime::Handle;
 use virtual_fs::{
     mem_fs::{self, OffloadBackingStore},
-    AsyncReadExt, AsyncSeekExt, AsyncWriteExt, FileOpener, FileSystem, FsError,
+    AsyncReadExt, AsyncSeekExt, AsyncWriteExt, CopyTreeOpts, FileOpener, FileSystem, FsError,
 };
 use wasmer_wasix::{
     fs::WasiFdSeed,
@@ -310,6 +310,29 @@ impl WritableJournal for MutexState {
                         .await
                 })?;
             }
+            JournalEntry::PathCopyTreeV1 {
+                old_path,
+                new_path,
+                follow_symlinks,
+                preserve_times,
+                ..
+            } => {
+                let handle = state.handle.clone();
+                let opts = CopyTreeOpts {
+                    preserve_times,
+                    follow_symlinks,
+                };
+                handle.block_on(async {
+                    state
+                        .mem_fs
+                        .copy_tree(
+                            &Path::new(old_path.as_ref()),
+                            &Path::new(new_path.as_ref()),
+                            opts,
+                        )
+                        .await
+                })?;
+            }
             JournalEntry::SocketOpenV1 { fd, .. } => {
                 state.seed.clip_val(fd + 1);
             }
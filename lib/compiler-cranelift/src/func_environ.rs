@@ -1156,7 +1156,9 @@ impl<'module_environment> BaseFuncEnvironment for FuncEnvironment<'module_enviro
         // If we have a declared maximum, we can make this a "static" heap, which is
         // allocated up front and never moved.
         let (offset_guard_size, heap_style, readonly_base) = match self.memory_styles[index] {
-            MemoryStyle::Dynamic { offset_guard_size } => {
+            MemoryStyle::Dynamic {
+                offset_guard_size, ..
+            } => {
                 let heap_bound = func.create_global_value(ir::GlobalValueData::Load {
                     base: ptr,
                     offset: Offset32::new(current_length_offset),
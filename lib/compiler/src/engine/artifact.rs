@@ -39,8 +39,9 @@ use wasmer_types::{
 };
 
 use wasmer_vm::{
-    FunctionBodyPtr, InstanceAllocator, MemoryStyle, StoreObjects, TableStyle, TrapHandlerFn,
-    VMConfig, VMExtern, VMInstance, VMSharedSignatureIndex, VMTrampoline,
+    FunctionBodyPtr, InstanceAllocator, MemoryStyle, SignatureRegistration, StoreObjects,
+    TableStyle, TrapHandlerFn, VMConfig, VMExtern, VMInstance, VMSharedSignatureIndex,
+    VMTrampoline,
 };
 
 #[cfg_attr(feature = "artifact-size", derive(loupe::MemoryUsage))]
@@ -60,7 +61,13 @@ pub struct AllocatedArtifact {
     finished_function_call_trampolines: BoxedSlice<SignatureIndex, VMTrampoline>,
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+    // Unregisters `signatures` once the artifact and its instances are gone
+    #[cfg_attr(feature = "artifact-size", loupe(skip))]
+    signature_registration: Arc<SignatureRegistration>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    // The address and length of the code memory that holds the functions,
+    // `None` when the code is not owned by the engine (static artifacts)
+    code_range: Option<(usize, usize)>,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -172,6 +179,15 @@ impl Artifact {
         self.allocated.is_some()
     }
 
+    /// Returns the address and the length of the memory that holds the
+    /// compiled code of this artifact, in case it is allocated in the
+    /// code memory of the engine.
+    pub fn code_range(&self) -> Option<(usize, usize)> {
+        self.allocated
+            .as_ref()
+            .and_then(|allocated| allocated.code_range)
+    }
+
     /// A unique identifier for this object.
     ///
     /// This exists to allow us to compare two Artifacts for equality. Otherwise,
@@ -323,6 +339,7 @@ impl Artifact {
                 a.get_custom_sections_ref().values(),
             )?,
         };
+        let code_range = engine_inner.last_code_range();
 
         match &artifact {
             ArtifactBuildVariant::Plain(p) => link_module(
@@ -354,14 +371,15 @@ impl Artifact {
         };
 
         // Compute indices into the shared signature table.
-        let signatures = {
-            let signature_registry = engine_inner.signatures();
-            module_info
-                .signatures
-                .values()
-                .map(|sig| signature_registry.register(sig))
-                .collect::<PrimaryMap<_, _>>()
-        };
+        let signature_registration = Arc::new(SignatureRegistration::new(
+            engine_inner.signatures(),
+            module_info.signatures.values(),
+        ));
+        let signatures = signature_registration
+            .indices()
+            .iter()
+            .copied()
+            .collect::<PrimaryMap<_, _>>();
 
         let debug_ref = match &artifact {
             // Why clone? See comment at the top of ./lib/types/src/indexes.rs.
@@ -417,7 +435,9 @@ impl Artifact {
                 finished_function_call_trampolines,
                 finished_dynamic_function_trampolines,
                 signatures,
+                signature_registration,
                 finished_function_lengths,
+                code_range,
             }),
         };
 
@@ -745,6 +765,16 @@ impl Artifact {
             .signatures
     }
 
+    /// Returns the registration that keeps the VM signatures of this
+    /// `Artifact` alive, instances hold on to it as well.
+    pub fn signature_registration(&self) -> &Arc<SignatureRegistration> {
+        &self
+            .allocated
+            .as_ref()
+            .expect("It must be allocated")
+            .signature_registration
+    }
+
     /// Do preinstantiation logic that is executed before instantiating
     #[allow(clippy::result_large_err)]
     pub fn preinstantiate(&self) -> Result<(), InstantiationError> {
@@ -825,6 +855,7 @@ impl Artifact {
             finished_globals,
             imports,
             self.signatures().clone(),
+            self.signature_registration().clone(),
         )
         .map_err(InstantiationError::Start)?;
         Ok(handle)
@@ -1110,15 +1141,15 @@ impl Artifact {
         }
 
         // We register all the signatures
-        let signatures = {
-            metadata
-                .compile_info
-                .module
-                .signatures
-                .values()
-                .map(|sig| signature_registry.register(sig))
-                .collect::<PrimaryMap<_, _>>()
-        };
+        let signature_registration = Arc::new(SignatureRegistration::new(
+            signature_registry,
+            metadata.compile_info.module.signatures.values(),
+        ));
+        let signatures = signature_registration
+            .indices()
+            .iter()
+            .copied()
+            .collect::<PrimaryMap<_, _>>();
 
         // read trampolines in order
         let mut finished_function_call_trampolines = PrimaryMap::new();
@@ -1181,7 +1212,9 @@ impl Artifact {
                 finished_dynamic_function_trampolines: finished_dynamic_function_trampolines
                     .into_boxed_slice(),
                 signatures: signatures.into_boxed_slice(),
+                signature_registration,
                 finished_function_lengths,
+                code_range: None,
             }),
         })
     }
@@ -234,6 +234,12 @@ impl CodeMemory {
     pub fn register_frame_info(&mut self, frame_info: GlobalFrameInfoRegistration) {
         self.frame_info_registration = Some(frame_info);
     }
+
+    /// Returns the address and the length of the memory that holds the
+    /// functions and the custom sections.
+    pub fn code_range(&self) -> (usize, usize) {
+        (self.mmap.as_ptr() as usize, self.mmap.len())
+    }
 }
 
 fn round_up(size: usize, multiple: usize) -> usize {
@@ -65,7 +65,7 @@ impl Engine {
                 #[cfg(not(target_arch = "wasm32"))]
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
-                signatures: SignatureRegistry::new(),
+                signatures: Arc::new(SignatureRegistry::new()),
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
@@ -135,7 +135,7 @@ impl Engine {
                 #[cfg(not(target_arch = "wasm32"))]
                 code_memory: vec![],
                 #[cfg(not(target_arch = "wasm32"))]
-                signatures: SignatureRegistry::new(),
+                signatures: Arc::new(SignatureRegistry::new()),
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
@@ -175,6 +175,15 @@ impl Engine {
         compiler.signatures().lookup(sig)
     }
 
+    /// Returns the number of distinct signatures registered by the modules
+    /// of this engine, they are released once a module and all of its
+    /// instances are dropped
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn signature_count(&self) -> usize {
+        let compiler = self.inner();
+        compiler.signatures().len()
+    }
+
     /// Validates a WebAssembly module
     #[cfg(feature = "compiler")]
     pub fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
@@ -317,7 +326,7 @@ pub struct EngineInner {
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
     #[cfg(not(target_arch = "wasm32"))]
-    signatures: SignatureRegistry,
+    signatures: Arc<SignatureRegistry>,
 }
 
 impl EngineInner {
@@ -444,6 +453,22 @@ impl EngineInner {
         ))
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Address and length of the code memory that was allocated last.
+    pub(crate) fn last_code_range(&self) -> Option<(usize, usize)> {
+        self.code_memory.last().map(CodeMemory::code_range)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Returns true if the memory at `start` extending for `len` bytes was
+    /// allocated by this engine to hold compiled code.
+    pub(crate) fn owns_code_range(&self, start: usize, len: usize) -> bool {
+        self.code_memory.iter().any(|code| {
+            let (code_start, code_len) = code.code_range();
+            start >= code_start && start + len <= code_start + code_len
+        })
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     /// Make memory containing compiled code executable.
     pub(crate) fn publish_compiled_code(&mut self) {
@@ -466,7 +491,7 @@ impl EngineInner {
 
     /// Shared signature registry.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn signatures(&self) -> &SignatureRegistry {
+    pub fn signatures(&self) -> &Arc<SignatureRegistry> {
         &self.signatures
     }
 
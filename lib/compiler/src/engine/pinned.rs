@@ -0,0 +1,124 @@
+//! Keep the compiled code of an `Artifact` resident in memory.
+
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use wasmer_types::CompileError;
+
+use crate::{Artifact, Engine};
+
+lazy_static::lazy_static! {
+    /// The number of pins that hold each range of code memory (keyed by its
+    /// address) locked. Locking memory does not nest, so the range is only
+    /// unlocked again once the last pin is dropped.
+    static ref PINS: Mutex<HashMap<usize, (usize, usize)>> = Default::default();
+}
+
+/// An [`Artifact`] whose compiled code has been faulted in and locked into
+/// physical memory, so that instantiating it does not have to page the code
+/// in first.
+///
+/// Cloning a `PinnedArtifact` is cheap and the code stays pinned until the
+/// last clone (and every other pin of the same artifact) is dropped.
+#[derive(Clone)]
+pub struct PinnedArtifact {
+    inner: Arc<PinnedArtifactInner>,
+}
+
+struct PinnedArtifactInner {
+    start: usize,
+    artifact: Arc<Artifact>,
+    // Keeps the code memory alive until it has been unlocked
+    _engine: Engine,
+}
+
+impl Drop for PinnedArtifactInner {
+    fn drop(&mut self) {
+        let mut pins = PINS.lock().unwrap();
+        if let Some((count, len)) = pins.get_mut(&self.start) {
+            *count -= 1;
+            if *count == 0 {
+                let len = *len;
+                pins.remove(&self.start);
+                region::unlock(self.start as *const u8, len).ok();
+            }
+        }
+    }
+}
+
+impl PinnedArtifact {
+    /// The artifact that is pinned.
+    pub fn artifact(&self) -> &Arc<Artifact> {
+        &self.inner.artifact
+    }
+}
+
+impl Deref for PinnedArtifact {
+    type Target = Artifact;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner.artifact
+    }
+}
+
+impl std::fmt::Debug for PinnedArtifact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PinnedArtifact")
+            .field("artifact", &self.inner.artifact)
+            .finish()
+    }
+}
+
+impl Engine {
+    /// Pins the compiled code of an artifact that was created by this engine
+    /// so that it stays resident in memory.
+    ///
+    /// The pages are faulted in straight away and then locked (with `mlock`
+    /// or `VirtualLock`), which can fail when the limit of locked memory for
+    /// the process is reached. Pinning an artifact that is already pinned
+    /// only adds another reference to the existing lock.
+    pub fn pin_artifact(&self, artifact: Arc<Artifact>) -> Result<PinnedArtifact, CompileError> {
+        let (start, len) = artifact.code_range().ok_or_else(|| {
+            CompileError::UnsupportedTarget(
+                "The artifact has no code allocated by the engine so it can not be pinned"
+                    .to_string(),
+            )
+        })?;
+        if !self.inner().owns_code_range(start, len) {
+            return Err(CompileError::Validate(
+                "The artifact was not created by this engine".to_string(),
+            ));
+        }
+
+        let mut pins = PINS.lock().unwrap();
+        if let Some((count, _)) = pins.get_mut(&start) {
+            *count += 1;
+        } else {
+            // Touch every page so that it is resident before it gets locked
+            let page_size = region::page::size();
+            for offset in (0..len).step_by(page_size) {
+                unsafe {
+                    std::ptr::read_volatile((start + offset) as *const u8);
+                }
+            }
+
+            let lock = region::lock(start as *const u8, len).map_err(|err| {
+                CompileError::Resource(format!("failed to pin the compiled code: {}", err))
+            })?;
+            // The lock is released by the last pin instead
+            std::mem::forget(lock);
+            pins.insert(start, (1, len));
+        }
+
+        Ok(PinnedArtifact {
+            inner: Arc::new(PinnedArtifactInner {
+                start,
+                artifact,
+                _engine: self.clone(),
+            }),
+        })
+    }
+}
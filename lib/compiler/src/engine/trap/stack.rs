@@ -16,6 +16,7 @@ pub fn get_trace_and_trapcode(trap: &Trap) -> (Vec<FrameInfo>, Option<TrapCode>)
             pc,
             signal_trap,
             backtrace,
+            ..
         } => {
             let trap_code = info
                 .lookup_trap_info(*pc)
@@ -29,6 +30,7 @@ pub fn get_trace_and_trapcode(trap: &Trap) -> (Vec<FrameInfo>, Option<TrapCode>)
         Trap::Lib {
             trap_code,
             backtrace,
+            ..
         } => (wasm_trace(&info, None, backtrace), Some(*trap_code)),
     }
 }
@@ -7,7 +7,7 @@ use wasmer_types::{
     ModuleInfo, Pages, TableIndex, TableType,
 };
 use wasmer_vm::{InternalStoreHandle, MemoryError, StoreObjects};
-use wasmer_vm::{MemoryStyle, TableStyle};
+use wasmer_vm::{LinearMemory, MemoryStyle, TableStyle, ZeroingStrategy};
 use wasmer_vm::{VMConfig, VMGlobal, VMMemory, VMTable};
 use wasmer_vm::{VMMemoryDefinition, VMTableDefinition};
 
@@ -177,6 +177,12 @@ pub struct BaseTunables {
 
     /// The size in bytes of the offset guard for dynamic heaps.
     pub dynamic_memory_offset_guard_size: u64,
+
+    /// The number of pages that dynamic heaps grow by at least.
+    pub dynamic_memory_growth_granularity: Pages,
+
+    /// How memories created with these tunables are zeroed when reset.
+    pub zeroing_strategy: ZeroingStrategy,
 }
 
 impl BaseTunables {
@@ -211,8 +217,23 @@ impl BaseTunables {
             static_memory_bound,
             static_memory_offset_guard_size,
             dynamic_memory_offset_guard_size,
+            dynamic_memory_growth_granularity: Pages(1),
+            zeroing_strategy: ZeroingStrategy::default(),
         }
     }
+
+    /// Sets the number of pages that dynamic heaps grow by at least, so that
+    /// many small `memory.grow` calls don't each remap the memory.
+    pub fn with_dynamic_memory_growth_granularity(mut self, granularity: Pages) -> Self {
+        self.dynamic_memory_growth_granularity = granularity;
+        self
+    }
+
+    /// Sets how memories created with these tunables are zeroed when reset.
+    pub fn with_zeroing_strategy(mut self, zeroing_strategy: ZeroingStrategy) -> Self {
+        self.zeroing_strategy = zeroing_strategy;
+        self
+    }
 }
 
 impl Tunables for BaseTunables {
@@ -232,6 +253,7 @@ impl Tunables for BaseTunables {
         } else {
             MemoryStyle::Dynamic {
                 offset_guard_size: self.dynamic_memory_offset_guard_size,
+                growth_granularity: self.dynamic_memory_growth_granularity,
             }
         }
     }
@@ -247,7 +269,9 @@ impl Tunables for BaseTunables {
         ty: &MemoryType,
         style: &MemoryStyle,
     ) -> Result<VMMemory, MemoryError> {
-        VMMemory::new(ty, style)
+        let mut memory = VMMemory::new(ty, style)?;
+        memory.set_zeroing_strategy(self.zeroing_strategy);
+        Ok(memory)
     }
 
     /// Create a memory owned by the VM given a [`MemoryType`] and a [`MemoryStyle`].
@@ -261,7 +285,9 @@ impl Tunables for BaseTunables {
         style: &MemoryStyle,
         vm_definition_location: NonNull<VMMemoryDefinition>,
     ) -> Result<VMMemory, MemoryError> {
-        VMMemory::from_definition(ty, style, vm_definition_location)
+        let mut memory = VMMemory::from_definition(ty, style, vm_definition_location)?;
+        memory.set_zeroing_strategy(self.zeroing_strategy);
+        Ok(memory)
     }
 
     /// Create a table owned by the host given a [`TableType`] and a [`TableStyle`].
@@ -11,6 +11,11 @@ use super::*;
 
 pub const JOURNAL_MAGIC_NUMBER: u64 = 0x310d6dd027362979;
 pub const JOURNAL_MAGIC_NUMBER_BYTES: [u8; 8] = JOURNAL_MAGIC_NUMBER.to_be_bytes();
+/// Journals that start with this magic number frame each of their records
+/// with a checksum
+pub const JOURNAL_CHECKSUMMED_MAGIC_NUMBER: u64 = 0x310d6dd02736297a;
+pub const JOURNAL_CHECKSUMMED_MAGIC_NUMBER_BYTES: [u8; 8] =
+    JOURNAL_CHECKSUMMED_MAGIC_NUMBER.to_be_bytes();
 
 #[repr(u16)]
 #[derive(
@@ -86,6 +91,7 @@ pub enum JournalEntryRecordType {
     SocketShutdownV1 = 58,
     SnapshotV1 = 59,
     ClearEtherealV1 = 60,
+    PathCopyTreeV1 = 61,
 }
 
 impl JournalEntryRecordType {
@@ -174,6 +180,9 @@ impl JournalEntryRecordType {
             JournalEntryRecordType::PathRenameV1 => {
                 ArchivedJournalEntry::PathRenameV1(rkyv::access_unchecked(data))
             }
+            JournalEntryRecordType::PathCopyTreeV1 => {
+                ArchivedJournalEntry::PathCopyTreeV1(rkyv::access_unchecked(data))
+            }
             JournalEntryRecordType::ChangeDirectoryV1 => {
                 ArchivedJournalEntry::ChangeDirectoryV1(rkyv::access_unchecked(data))
             }
@@ -313,6 +322,7 @@ impl<'a> JournalEntry<'a> {
             Self::CreateSymbolicLinkV1 { .. } => JournalEntryRecordType::CreateSymbolicLinkV1,
             Self::UnlinkFileV1 { .. } => JournalEntryRecordType::UnlinkFileV1,
             Self::PathRenameV1 { .. } => JournalEntryRecordType::PathRenameV1,
+            Self::PathCopyTreeV1 { .. } => JournalEntryRecordType::PathCopyTreeV1,
             Self::ChangeDirectoryV1 { .. } => JournalEntryRecordType::ChangeDirectoryV1,
             Self::EpollCreateV1 { .. } => JournalEntryRecordType::EpollCreateV1,
             Self::EpollCtlV1 { .. } => JournalEntryRecordType::EpollCtlV1,
@@ -619,6 +629,22 @@ impl<'a> JournalEntry<'a> {
                 },
                 serializer,
             ),
+            JournalEntry::PathCopyTreeV1 {
+                fd,
+                old_path,
+                new_path,
+                follow_symlinks,
+                preserve_times,
+            } => serialize_using(
+                &JournalEntryPathCopyTreeV1 {
+                    fd,
+                    old_path: old_path.into(),
+                    new_path: new_path.into(),
+                    follow_symlinks,
+                    preserve_times,
+                },
+                serializer,
+            ),
             JournalEntry::ChangeDirectoryV1 { path } => serialize_using(
                 &JournalEntryChangeDirectoryV1 { path: path.into() },
                 serializer,
@@ -935,6 +961,7 @@ pub enum ArchivedJournalEntry<'a> {
     CreateSymbolicLinkV1(&'a ArchivedJournalEntryCreateSymbolicLinkV1<'a>),
     UnlinkFileV1(&'a ArchivedJournalEntryUnlinkFileV1<'a>),
     PathRenameV1(&'a ArchivedJournalEntryPathRenameV1<'a>),
+    PathCopyTreeV1(&'a ArchivedJournalEntryPathCopyTreeV1<'a>),
     ChangeDirectoryV1(&'a ArchivedJournalEntryChangeDirectoryV1<'a>),
     EpollCreateV1(&'a ArchivedJournalEntryEpollCreateV1),
     EpollCtlV1(&'a ArchivedJournalEntryEpollCtlV1),
@@ -1235,6 +1262,18 @@ pub struct JournalEntryPathRenameV1<'a> {
     pub new_path: AlignedCowStr<'a>,
 }
 
+#[repr(C)]
+#[repr(align(8))]
+#[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
+#[rkyv(attr(repr(align(8))))]
+pub struct JournalEntryPathCopyTreeV1<'a> {
+    pub fd: u32,
+    pub old_path: AlignedCowStr<'a>,
+    pub new_path: AlignedCowStr<'a>,
+    pub follow_symlinks: bool,
+    pub preserve_times: bool,
+}
+
 #[repr(C)]
 #[repr(align(8))]
 #[derive(Debug, Clone, RkyvSerialize, RkyvDeserialize, Archive)]
@@ -1751,6 +1790,7 @@ pub enum JournalSockoptionV1 {
     MulticastTtlV4,
     Type,
     Proto,
+    UserTimeout,
 }
 
 #[repr(C)]
@@ -1775,6 +1815,7 @@ pub enum JournalTimeTypeV1 {
     ConnectTimeout,
     BindTimeout,
     Linger,
+    UserTimeout,
 }
 
 #[repr(C)]
@@ -428,6 +428,7 @@ impl From<wasi::Sockoption> for JournalSockoptionV1 {
             wasi::Sockoption::MulticastTtlV4 => JournalSockoptionV1::MulticastTtlV4,
             wasi::Sockoption::Type => JournalSockoptionV1::Type,
             wasi::Sockoption::Proto => JournalSockoptionV1::Proto,
+            wasi::Sockoption::UserTimeout => JournalSockoptionV1::UserTimeout,
         }
     }
 }
@@ -462,6 +463,7 @@ impl From<JournalSockoptionV1> for wasi::Sockoption {
             JournalSockoptionV1::MulticastTtlV4 => wasi::Sockoption::MulticastTtlV4,
             JournalSockoptionV1::Type => wasi::Sockoption::Type,
             JournalSockoptionV1::Proto => wasi::Sockoption::Proto,
+            JournalSockoptionV1::UserTimeout => wasi::Sockoption::UserTimeout,
         }
     }
 }
@@ -496,6 +498,7 @@ impl From<&'_ ArchivedJournalSockoptionV1> for wasi::Sockoption {
             ArchivedJournalSockoptionV1::MulticastTtlV4 => wasi::Sockoption::MulticastTtlV4,
             ArchivedJournalSockoptionV1::Type => wasi::Sockoption::Type,
             ArchivedJournalSockoptionV1::Proto => wasi::Sockoption::Proto,
+            ArchivedJournalSockoptionV1::UserTimeout => wasi::Sockoption::UserTimeout,
         }
     }
 }
@@ -509,6 +512,7 @@ impl From<SocketOptTimeType> for JournalTimeTypeV1 {
             SocketOptTimeType::ConnectTimeout => JournalTimeTypeV1::ConnectTimeout,
             SocketOptTimeType::BindTimeout => JournalTimeTypeV1::BindTimeout,
             SocketOptTimeType::Linger => JournalTimeTypeV1::Linger,
+            SocketOptTimeType::UserTimeout => JournalTimeTypeV1::UserTimeout,
         }
     }
 }
@@ -522,6 +526,7 @@ impl From<JournalTimeTypeV1> for SocketOptTimeType {
             JournalTimeTypeV1::ConnectTimeout => SocketOptTimeType::ConnectTimeout,
             JournalTimeTypeV1::BindTimeout => SocketOptTimeType::BindTimeout,
             JournalTimeTypeV1::Linger => SocketOptTimeType::Linger,
+            JournalTimeTypeV1::UserTimeout => SocketOptTimeType::UserTimeout,
         }
     }
 }
@@ -535,6 +540,7 @@ impl From<&'_ ArchivedJournalTimeTypeV1> for SocketOptTimeType {
             ArchivedJournalTimeTypeV1::ConnectTimeout => SocketOptTimeType::ConnectTimeout,
             ArchivedJournalTimeTypeV1::BindTimeout => SocketOptTimeType::BindTimeout,
             ArchivedJournalTimeTypeV1::Linger => SocketOptTimeType::Linger,
+            ArchivedJournalTimeTypeV1::UserTimeout => SocketOptTimeType::UserTimeout,
         }
     }
 }
@@ -763,6 +769,19 @@ impl<'a> TryFrom<ArchivedJournalEntry<'a>> for JournalEntry<'a> {
                 new_fd: new_fd.to_native(),
                 new_path: String::from_utf8_lossy(new_path.as_ref()),
             },
+            ArchivedJournalEntry::PathCopyTreeV1(ArchivedJournalEntryPathCopyTreeV1 {
+                fd,
+                old_path,
+                new_path,
+                follow_symlinks,
+                preserve_times,
+            }) => Self::PathCopyTreeV1 {
+                fd: fd.to_native(),
+                old_path: String::from_utf8_lossy(old_path.as_ref()),
+                new_path: String::from_utf8_lossy(new_path.as_ref()),
+                follow_symlinks: *follow_symlinks,
+                preserve_times: *preserve_times,
+            },
             ArchivedJournalEntry::SnapshotV1(ArchivedJournalEntrySnapshotV1 {
                 since_epoch,
                 ref trigger,
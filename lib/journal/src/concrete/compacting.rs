@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ops::{DerefMut, Range},
     sync::{Arc, Mutex},
 };
@@ -31,11 +31,23 @@ struct SubGroupOfevents {
     /// The write map allows the ccompacted to only keep the
     /// events relevant to the final outcome of a compacted
     /// journal rather than written regions that are later
-    /// overridden. This is a crude write map that does not
-    /// deal with overlapping writes (they still remain)
-    /// However in the majority of cases this will remove
-    /// duplicates while retaining a simple implementation
-    write_map: HashMap<MemoryRange, usize>,
+    /// overridden.
+    write_map: WriteMap,
+    /// Events that replace a piece of state on a file descriptor
+    /// (flags, rights and times) only need their last occurrence
+    /// to be retained
+    state_map: HashMap<(Fd, DescriptorState), usize>,
+}
+
+/// Piece of file descriptor state that is entirely replaced every
+/// time it is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DescriptorState {
+    Flags,
+    Rights,
+    /// Times are keyed by their flags as setting only one of the
+    /// timestamps does not supersede an earlier write of the other
+    Times(u16),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -52,6 +64,62 @@ impl From<Range<u64>> for MemoryRange {
     }
 }
 
+/// Tracks which event last wrote each byte of an address range so that
+/// writes which are completely overwritten by later writes can be dropped.
+///
+/// Writes that are only partially overwritten are retained, the events are
+/// replayed in their original order hence the later write still wins.
+#[derive(Debug, Default)]
+struct WriteMap {
+    /// Non-overlapping segments and the event that last wrote them
+    segments: BTreeMap<MemoryRange, usize>,
+}
+
+impl WriteMap {
+    fn insert(&mut self, range: MemoryRange, event_index: usize) {
+        if range.start >= range.end {
+            return;
+        }
+
+        // Segments are ordered by their start and never overlap, thus walking
+        // backwards from the end of the write finds everything it touches
+        let overlapping = self
+            .segments
+            .range(
+                ..MemoryRange {
+                    start: range.end,
+                    end: 0,
+                },
+            )
+            .rev()
+            .take_while(|(segment, _)| segment.end > range.start)
+            .map(|(segment, index)| (*segment, *index))
+            .collect::<Vec<_>>();
+
+        // Whatever is not covered by the new write still belongs to the
+        // event that wrote it before
+        for (segment, index) in overlapping {
+            self.segments.remove(&segment);
+            if segment.start < range.start {
+                self.segments
+                    .insert((segment.start..range.start).into(), index);
+            }
+            if segment.end > range.end {
+                self.segments.insert((range.end..segment.end).into(), index);
+            }
+        }
+        self.segments.insert(range, event_index);
+    }
+
+    fn events(&self) -> impl Iterator<Item = &usize> {
+        self.segments.values()
+    }
+
+    fn clear(&mut self) {
+        self.segments.clear();
+    }
+}
+
 /// Index of a group of subevents in the journal which relate to a particular
 /// collective impact. For example. Creating a new file which may consist of
 /// an event to open a file, the events for writing the file data and the
@@ -72,7 +140,7 @@ struct State {
     /// The descriptor seed is used generate descriptor lookups
     descriptor_seed: u64,
     // We maintain a memory map of the events that are significant
-    memory_map: HashMap<MemoryRange, usize>,
+    memory_map: WriteMap,
     // List of all the snapshots
     snapshots: Vec<usize>,
     // Last tty event thats been set
@@ -162,7 +230,7 @@ impl State {
             .chain(self.process_exit.as_ref().into_iter())
             .chain(self.init_module.as_ref().into_iter())
             .chain(self.snapshots.iter())
-            .chain(self.memory_map.values())
+            .chain(self.memory_map.events())
             .chain(self.thread_map.values())
             .chain(self.remove_directory.values())
             .chain(self.unlink_file.values())
@@ -223,7 +291,10 @@ impl State {
             for e in d.events.iter() {
                 filter.add_event_to_whitelist(*e);
             }
-            for e in d.write_map.values() {
+            for e in d.write_map.events() {
+                filter.add_event_to_whitelist(*e);
+            }
+            for e in d.state_map.values() {
                 filter.add_event_to_whitelist(*e);
             }
         }
@@ -592,16 +663,30 @@ impl WritableJournal for CompactingJournalTx {
                     .find_sub_events(fd)
                     .and_then(|lookup| state.sub_events.get_mut(&lookup))
                 {
-                    if let JournalEntry::FileDescriptorWriteV1 { offset, data, .. } = &entry {
-                        state.write_map.insert(
-                            MemoryRange {
-                                start: *offset,
-                                end: *offset + data.len() as u64,
-                            },
-                            event_index,
-                        );
-                    } else {
-                        state.events.push(event_index);
+                    match &entry {
+                        JournalEntry::FileDescriptorWriteV1 { offset, data, .. } => {
+                            state.write_map.insert(
+                                MemoryRange {
+                                    start: *offset,
+                                    end: *offset + data.len() as u64,
+                                },
+                                event_index,
+                            );
+                        }
+                        JournalEntry::FileDescriptorSetTimesV1 { fst_flags, .. } => {
+                            state.state_map.insert(
+                                (*fd, DescriptorState::Times(fst_flags.bits())),
+                                event_index,
+                            );
+                        }
+                        JournalEntry::FileDescriptorSetRightsV1 { .. } => {
+                            state
+                                .state_map
+                                .insert((*fd, DescriptorState::Rights), event_index);
+                        }
+                        _ => {
+                            state.events.push(event_index);
+                        }
                     }
                 }
             }
@@ -613,7 +698,18 @@ impl WritableJournal for CompactingJournalTx {
                     let lookup = state.insert_new_sub_events(event_index);
                     state.stdio_descriptors.insert(*fd, lookup);
                 }
-                state.find_sub_events_and_append(fd, event_index);
+                if let JournalEntry::FileDescriptorSetFlagsV1 { .. } = &entry {
+                    if let Some(state) = state
+                        .find_sub_events(fd)
+                        .and_then(|lookup| state.sub_events.get_mut(&lookup))
+                    {
+                        state
+                            .state_map
+                            .insert((*fd, DescriptorState::Flags), event_index);
+                    }
+                } else {
+                    state.find_sub_events_and_append(fd, event_index);
+                }
             }
             // We keep non-mutable events for file descriptors that are suspect
             JournalEntry::SocketBindV1 { fd, .. }
@@ -723,6 +819,11 @@ impl WritableJournal for CompactingJournalTx {
                 state.cancel_sub_events_by_path(new_path.as_ref());
                 state.whitelist.insert(event_index);
             }
+            // Copies read the old path, so the events that shaped it are kept
+            JournalEntry::PathCopyTreeV1 { old_path, .. } => {
+                state.solidify_sub_events_by_path(old_path.as_ref());
+                state.whitelist.insert(event_index);
+            }
             // Update all the directory operations
             JournalEntry::PathSetTimesV1 { path, .. } => {
                 let path = path.to_string();
@@ -1529,4 +1630,181 @@ mod tests {
         )
         .unwrap()
     }
+
+    fn apply_memory_write(memory: &mut [u8], record: &JournalEntry<'_>) {
+        if let JournalEntry::UpdateMemoryRegionV1 {
+            region,
+            compressed_data,
+        } = record
+        {
+            memory[region.start as usize..region.end as usize].copy_from_slice(compressed_data);
+        }
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_compact_superseded_memory_writes() {
+        const PAGE: u64 = 65536;
+
+        // Every pass rewrites the same page as two overlapping halves whose
+        // boundaries move around, so no two writes share the same range
+        let mut records = Vec::new();
+        for i in 0..100u64 {
+            let split = PAGE / 2 + i;
+            records.push(JournalEntry::UpdateMemoryRegionV1 {
+                region: 0..split,
+                compressed_data: vec![i as u8; split as usize].into(),
+            });
+            let split = PAGE / 2 - i;
+            records.push(JournalEntry::UpdateMemoryRegionV1 {
+                region: split..PAGE,
+                compressed_data: vec![(i + 100) as u8; (PAGE - split) as usize].into(),
+            });
+        }
+
+        let mut expected = vec![0u8; PAGE as usize];
+        let mut compacting_journal = CompactingJournal::new(BufferedJournal::default()).unwrap();
+        for record in records {
+            apply_memory_write(&mut expected, &record);
+            compacting_journal.write(record).unwrap();
+        }
+        let result = compacting_journal
+            .compact_to(BufferedJournal::default())
+            .unwrap();
+
+        let mut memory = vec![0u8; PAGE as usize];
+        let mut total_events = 0;
+        let new_records = compacting_journal.as_restarted().unwrap();
+        while let Some(record) = new_records.read().unwrap() {
+            apply_memory_write(&mut memory, &record.record);
+            total_events += 1;
+        }
+
+        assert_eq!(total_events, 2);
+        assert_eq!(result.total_events, 2);
+        assert!(memory == expected, "replay produced different memory");
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_compact_keep_partially_overwritten_memory() {
+        run_test(
+            vec![
+                JournalEntry::UpdateMemoryRegionV1 {
+                    region: 0..16,
+                    compressed_data: [11u8; 16].to_vec().into(),
+                },
+                JournalEntry::UpdateMemoryRegionV1 {
+                    region: 8..24,
+                    compressed_data: [22u8; 16].to_vec().into(),
+                },
+                JournalEntry::UpdateMemoryRegionV1 {
+                    region: 4..12,
+                    compressed_data: [33u8; 8].to_vec().into(),
+                },
+                JournalEntry::UpdateMemoryRegionV1 {
+                    region: 0..4,
+                    compressed_data: [44u8; 4].to_vec().into(),
+                },
+            ],
+            vec![
+                JournalEntry::UpdateMemoryRegionV1 {
+                    region: 8..24,
+                    compressed_data: [22u8; 16].to_vec().into(),
+                },
+                JournalEntry::UpdateMemoryRegionV1 {
+                    region: 4..12,
+                    compressed_data: [33u8; 8].to_vec().into(),
+                },
+                JournalEntry::UpdateMemoryRegionV1 {
+                    region: 0..4,
+                    compressed_data: [44u8; 4].to_vec().into(),
+                },
+            ],
+        )
+        .unwrap()
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_compact_keeps_side_effect_order() {
+        let fd = 512;
+        let connected = JournalEntry::SocketConnectedV1 {
+            fd,
+            local_addr: "127.0.0.1:3333".parse().unwrap(),
+            peer_addr: "127.0.0.1:9999".parse().unwrap(),
+        };
+        let send = |data: &'static [u8]| JournalEntry::SocketSendV1 {
+            fd,
+            data: Cow::Borrowed(data),
+            flags: Default::default(),
+            is_64bit: false,
+        };
+        let write = |value: u8| JournalEntry::UpdateMemoryRegionV1 {
+            region: 0..16,
+            compressed_data: [value; 16].to_vec().into(),
+        };
+        run_test(
+            vec![
+                connected.clone(),
+                write(11),
+                send(b"123"),
+                write(22),
+                send(b"456"),
+            ],
+            vec![connected, send(b"123"), write(22), send(b"456")],
+        )
+        .unwrap()
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_compact_descriptor_state_keeps_last_write() {
+        let fd = 10;
+        let set_times =
+            |time: u64, fst_flags: wasi::Fstflags| JournalEntry::FileDescriptorSetTimesV1 {
+                fd,
+                st_atim: time,
+                st_mtim: time,
+                fst_flags,
+            };
+        let open = JournalEntry::OpenFileDescriptorV1 {
+            fd,
+            dirfd: 3,
+            dirflags: 0,
+            path: "/file".into(),
+            o_flags: wasi::Oflags::CREATE,
+            fs_rights_base: wasi::Rights::all(),
+            fs_rights_inheriting: wasi::Rights::all(),
+            fs_flags: wasi::Fdflags::empty(),
+        };
+        run_test(
+            vec![
+                open.clone(),
+                JournalEntry::FileDescriptorSetFlagsV1 {
+                    fd,
+                    flags: wasi::Fdflags::APPEND,
+                },
+                set_times(1, wasi::Fstflags::SET_ATIM),
+                JournalEntry::FileDescriptorSetFlagsV1 {
+                    fd,
+                    flags: wasi::Fdflags::NONBLOCK,
+                },
+                set_times(2, wasi::Fstflags::SET_MTIM),
+                set_times(3, wasi::Fstflags::SET_ATIM),
+                JournalEntry::CloseFileDescriptorV1 { fd },
+            ],
+            vec![
+                open,
+                JournalEntry::FileDescriptorSetFlagsV1 {
+                    fd,
+                    flags: wasi::Fdflags::NONBLOCK,
+                },
+                set_times(2, wasi::Fstflags::SET_MTIM),
+                set_times(3, wasi::Fstflags::SET_ATIM),
+                JournalEntry::CloseFileDescriptorV1 { fd },
+            ],
+        )
+        .unwrap()
+    }
 }
@@ -300,6 +300,7 @@ impl<W: WritableJournal> WritableJournal for FilteredJournalTx<W> {
             JournalEntry::RemoveDirectoryV1 { .. }
             | JournalEntry::UnlinkFileV1 { .. }
             | JournalEntry::PathRenameV1 { .. }
+            | JournalEntry::PathCopyTreeV1 { .. }
             | JournalEntry::CreateDirectoryV1 { .. }
             | JournalEntry::PathSetTimesV1 { .. }
             | JournalEntry::CreateHardLinkV1 { .. }
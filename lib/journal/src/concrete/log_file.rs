@@ -1,4 +1,3 @@
-use bytes::Buf;
 use rkyv::{
     api::high::HighSerializer,
     rancor::Strategy,
@@ -13,10 +12,11 @@ use shared_buffer::OwnedBuffer;
 use std::{
     fs::File,
     io::{Seek, SeekFrom, Write},
+    ops::{DerefMut, Range},
     path::Path,
     sync::{Arc, Mutex},
 };
-use virtual_fs::mem_fs::OffloadBackingStore;
+use virtual_fs::{mem_fs::OffloadBackingStore, Crc32c};
 
 use super::*;
 
@@ -27,10 +27,12 @@ use super::*;
 ///
 /// When opening an existing journal file that was previously saved
 /// then new entries will be added to the end regardless of if
-/// its been read.
+/// its been read. Any partially written record at the end of the
+/// file (e.g. the process was killed mid-write) is truncated first.
 ///
-/// The logfile snapshot capturer uses a 64bit number as a entry encoding
-/// delimiter.
+/// Each entry is prefixed by a 64bit header holding its type and
+/// length followed by a CRC32C checksum of the entry, which lets
+/// readers detect entries that were torn or corrupted.
 #[derive(Debug)]
 pub struct LogFileJournal {
     tx: LogFileJournalTx,
@@ -49,6 +51,10 @@ struct TxState {
 
     /// The latest position in the file the serializator got to
     pos: usize,
+
+    /// Whether the records are framed with a checksum, journals
+    /// created before checksums existed keep their original framing
+    checksummed: bool,
 }
 
 impl TxState {
@@ -67,9 +73,9 @@ impl TxState {
         )
     }
 
-    fn to_high<'a>(
-        serializer: &'a mut Serializer<IoWriter<&'a File>, ArenaHandle<'a>, Share>,
-    ) -> &'a mut HighSerializer<IoWriter<&'a File>, ArenaHandle<'a>, rkyv::rancor::Error> {
+    fn to_high<'a, W>(
+        serializer: &'a mut Serializer<W, ArenaHandle<'a>, Share>,
+    ) -> &'a mut HighSerializer<W, ArenaHandle<'a>, rkyv::rancor::Error> {
         Strategy::wrap(serializer)
     }
 }
@@ -82,6 +88,151 @@ impl std::fmt::Debug for TxState {
     }
 }
 
+/// Passes the serialized bytes of a record through to the file while
+/// computing their checksum
+struct ChecksummingWriter<'a> {
+    file: &'a File,
+    checksum: &'a mut Crc32c,
+}
+
+impl Write for ChecksummingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let amt = self.file.write(buf)?;
+        self.checksum.update(&buf[..amt]);
+        Ok(amt)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Walks the records of a journal log file and validates their framing.
+///
+/// Reading stops cleanly at the first record that is incomplete or fails
+/// its checksum, which is what a crash in the middle of writing a record
+/// leaves behind, rather than failing the load of the entire journal.
+#[derive(Debug)]
+pub struct JournalReader {
+    buffer: OwnedBuffer,
+    pos: usize,
+    checksummed: bool,
+    valid_records: usize,
+    corrupted: bool,
+}
+
+impl JournalReader {
+    pub fn new(buffer: OwnedBuffer) -> Self {
+        Self {
+            buffer,
+            pos: 0,
+            checksummed: false,
+            valid_records: 0,
+            corrupted: false,
+        }
+    }
+
+    /// Moves past the next valid record and returns its type along
+    /// with the range of the buffer that holds its data
+    pub fn next_record(&mut self) -> Option<(JournalEntryRecordType, Range<usize>)> {
+        loop {
+            let b = &self.buffer.as_ref()[self.pos..];
+            if b.is_empty() {
+                return None;
+            }
+            if b.len() < 8 {
+                tracing::debug!("journal ends with a partial header - the journal stops here");
+                self.corrupted = true;
+                return None;
+            }
+
+            // If the next header is the magic itself then skip it.
+            // You may be wondering how a magic could appear later
+            // in the journal itself. This can happen if someone
+            // concat's multiple journals together to make a combined
+            // journal
+            if b[0..8] == JOURNAL_MAGIC_NUMBER_BYTES[0..8] {
+                self.checksummed = false;
+                self.pos += 8;
+                continue;
+            }
+            if b[0..8] == JOURNAL_CHECKSUMMED_MAGIC_NUMBER_BYTES[0..8] {
+                self.checksummed = true;
+                self.pos += 8;
+                continue;
+            }
+
+            // Otherwise we decode the header
+            let header = JournalEntryHeader {
+                record_type: u16::from_be_bytes([b[0], b[1]]),
+                record_size: u64::from_be_bytes([0u8, 0u8, b[2], b[3], b[4], b[5], b[6], b[7]]),
+            };
+            let record_type: JournalEntryRecordType = match header.record_type.try_into() {
+                Ok(t) => t,
+                Err(_) => {
+                    // Most likely written by a newer version, which is not
+                    // a reason to throw it away
+                    tracing::debug!(
+                        "unknown journal entry type ({}) - the journal stops here",
+                        header.record_type
+                    );
+                    return None;
+                }
+            };
+
+            // The checksum sits between the header and the record
+            let prefix = if self.checksummed { 16 } else { 8 };
+            let record_size = header.record_size as usize;
+            if b.len() < prefix || b.len() - prefix < record_size {
+                tracing::debug!(
+                    "journal entry ({:?}) is incomplete - the journal stops here",
+                    record_type
+                );
+                self.corrupted = true;
+                return None;
+            }
+            let data = &b[prefix..(prefix + record_size)];
+
+            if self.checksummed {
+                let expected = u32::from_be_bytes([b[8], b[9], b[10], b[11]]);
+                let mut checksum = Crc32c::new();
+                checksum.update(data);
+                checksum.update(&b[0..8]);
+                if checksum.digest() != expected {
+                    tracing::debug!(
+                        "journal entry ({:?}) failed its checksum - the journal stops here",
+                        record_type
+                    );
+                    self.corrupted = true;
+                    return None;
+                }
+            }
+
+            let record_start = self.pos + prefix;
+            self.pos = record_start + record_size;
+            self.valid_records += 1;
+            return Some((record_type, record_start..self.pos));
+        }
+    }
+
+    /// Number of valid records that have been read so far
+    pub fn valid_records(&self) -> usize {
+        self.valid_records
+    }
+
+    /// Offset of the end of the last valid record that was read
+    pub fn valid_len(&self) -> u64 {
+        self.pos as u64
+    }
+
+    /// Returns true if reading stopped at a record that was incomplete
+    /// or corrupt rather than at the end of the journal, records of an
+    /// unknown type also stop the reading but are not corrupt
+    pub fn is_corrupted(&self) -> bool {
+        self.corrupted
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LogFileJournalTx {
     state: Arc<Mutex<TxState>>,
@@ -90,7 +241,7 @@ pub struct LogFileJournalTx {
 #[derive(Debug)]
 pub struct LogFileJournalRx {
     tx: Option<LogFileJournalTx>,
-    buffer_pos: Mutex<usize>,
+    reader: Mutex<JournalReader>,
     buffer: OwnedBuffer,
     store: OffloadBackingStore,
 }
@@ -103,6 +254,17 @@ impl LogFileJournalRx {
     pub fn backing_store(&self) -> OffloadBackingStore {
         self.store.clone()
     }
+
+    /// Number of valid records that have been read so far
+    pub fn valid_records(&self) -> usize {
+        self.reader.lock().unwrap().valid_records()
+    }
+
+    /// Returns true if reading stopped at a record that was incomplete
+    /// or corrupt rather than at the end of the journal
+    pub fn is_corrupted(&self) -> bool {
+        self.reader.lock().unwrap().is_corrupted()
+    }
 }
 
 impl LogFileJournalTx {
@@ -114,26 +276,23 @@ impl LogFileJournalTx {
         let buffer = store.owned_buffer();
 
         // If the buffer exists we valid the magic number
-        let mut buffer_pos = 0;
-        let mut buffer_ptr = buffer.as_ref();
+        let buffer_ptr = buffer.as_ref();
         if buffer_ptr.len() >= 8 {
             let magic = u64::from_be_bytes(buffer_ptr[0..8].try_into().unwrap());
-            if magic != JOURNAL_MAGIC_NUMBER {
+            if magic != JOURNAL_MAGIC_NUMBER && magic != JOURNAL_CHECKSUMMED_MAGIC_NUMBER {
                 return Err(anyhow::format_err!(
                     "invalid magic number of journal ({} vs {})",
                     magic,
-                    JOURNAL_MAGIC_NUMBER
+                    JOURNAL_CHECKSUMMED_MAGIC_NUMBER
                 ));
             }
-            buffer_ptr.advance(8);
-            buffer_pos += 8;
         } else {
             tracing::trace!("journal has no magic (could be empty?)");
         }
 
         Ok(LogFileJournalRx {
             tx: Some(self.clone()),
-            buffer_pos: Mutex::new(buffer_pos),
+            reader: Mutex::new(JournalReader::new(buffer.clone())),
             buffer,
             store,
         })
@@ -153,7 +312,7 @@ impl LogFileJournal {
 
     pub fn new_readonly(path: impl AsRef<Path>) -> anyhow::Result<Self> {
         let file = std::fs::File::options().read(true).open(path)?;
-        Self::from_file(file)
+        Self::from_file_with_repair(file, false)
     }
 
     pub fn owned_buffer(&self) -> OwnedBuffer {
@@ -165,26 +324,67 @@ impl LogFileJournal {
     }
 
     /// Create a new journal from a file
-    pub fn from_file(mut file: std::fs::File) -> anyhow::Result<Self> {
-        // Move to the end of the file and write the
-        // magic if one is needed
+    pub fn from_file(file: std::fs::File) -> anyhow::Result<Self> {
+        Self::from_file_with_repair(file, true)
+    }
+
+    fn from_file_with_repair(mut file: std::fs::File, repair: bool) -> anyhow::Result<Self> {
         let underlying_file = file.try_clone()?;
         let arena = Arena::new();
 
-        let end_pos = file.seek(SeekFrom::End(0))?;
+        // Find where the valid records end so that anything left behind
+        // by a torn write is not hidden in between the new entries
+        let mut end_pos = file.seek(SeekFrom::End(0))?;
+        let mut checksummed = true;
+        let buffer = if end_pos > 0 {
+            Some(OwnedBuffer::from_file(&file)?)
+        } else {
+            None
+        };
+        if let Some(buffer) = buffer.filter(|b| {
+            let b: &[u8] = b.as_ref();
+            b.starts_with(&JOURNAL_MAGIC_NUMBER_BYTES)
+                || b.starts_with(&JOURNAL_CHECKSUMMED_MAGIC_NUMBER_BYTES)
+        }) {
+            let mut reader = JournalReader::new(buffer);
+            while reader.next_record().is_some() {}
+            checksummed = reader.checksummed;
+            let corrupted = reader.is_corrupted();
+            let valid_records = reader.valid_records();
+            let valid_len = reader.valid_len();
+
+            // The file is still mapped by the reader, which must be gone
+            // before the file shrinks underneath it
+            drop(reader);
+
+            if repair && corrupted {
+                tracing::warn!(
+                    valid_records,
+                    "truncating the journal after its last valid record ({} of {} bytes)",
+                    valid_len,
+                    end_pos
+                );
+                end_pos = valid_len;
+                file.set_len(end_pos)?;
+                file.seek(SeekFrom::Start(end_pos))?;
+            }
+        }
 
+        // Move to the end of the file and write the
+        // magic if one is needed
         let mut tx = TxState {
             underlying_file,
             arena,
             file,
             pos: end_pos as usize,
+            checksummed,
         };
 
         let mut serializer = tx.get_serializer();
         let serializer = TxState::to_high(&mut serializer);
 
         if serializer.pos() == 0 {
-            let magic = JOURNAL_MAGIC_NUMBER;
+            let magic = JOURNAL_CHECKSUMMED_MAGIC_NUMBER;
             let magic = magic.to_be_bytes();
             serializer.write(&magic)?;
         }
@@ -213,7 +413,7 @@ impl LogFileJournal {
         // Create the rx
         let rx = LogFileJournalRx {
             tx: None,
-            buffer_pos: Mutex::new(0),
+            reader: Mutex::new(JournalReader::new(buffer.clone())),
             buffer: buffer.clone(),
             store: OffloadBackingStore::from_buffer(buffer),
         };
@@ -231,27 +431,42 @@ impl WritableJournal for LogFileJournalTx {
         tracing::debug!("journal event: {:?}", entry);
 
         let mut state = self.state.lock().unwrap();
+        let state = state.deref_mut();
 
-        // Write the header (with a record size of zero)
+        // Write the header (with a record size of zero) followed by
+        // room for the checksum
         let record_type: JournalEntryRecordType = entry.archive_record_type();
-        let mut serializer = state.get_serializer();
-        let serializer = TxState::to_high(&mut serializer);
-        let offset_header = serializer.pos() as u64;
+        let prefix: &[u8] = if state.checksummed {
+            &[0u8; 16]
+        } else {
+            &[0u8; 8]
+        };
+        let offset_header = state.pos as u64;
         tracing::trace!("serpos is {offset_header}");
-        serializer.write(&[0u8; 8])?;
+        state.file.write_all(prefix)?;
 
         // Now serialize the actual data to the log
-        let offset_start = serializer.pos() as u64;
-        entry.serialize_archive(serializer)?;
-        let offset_end = serializer.pos() as u64;
+        let offset_start = offset_header + prefix.len() as u64;
+        let mut checksum = Crc32c::new();
+        let offset_end = {
+            let writer = ChecksummingWriter {
+                file: &state.file,
+                checksum: &mut checksum,
+            };
+            let mut serializer = Serializer::new(
+                IoWriter::with_pos(writer, offset_start as usize),
+                state.arena.acquire(),
+                Share::new(),
+            );
+            let serializer = TxState::to_high(&mut serializer);
+            entry.serialize_archive(serializer)?;
+            serializer.pos() as u64
+        };
         let record_size = offset_end - offset_start;
         tracing::trace!(
             "delimiter header={offset_header},start={offset_start},record_size={record_size}"
         );
 
-        let last_pos = serializer.pos();
-        let _ = serializer;
-
         // Write the record and then move back to the end again
         state.underlying_file.seek(SeekFrom::Start(offset_header))?;
         let header_bytes = {
@@ -260,10 +475,16 @@ impl WritableJournal for LogFileJournalTx {
             [a[0], a[1], b[0], b[1], b[2], b[3], b[4], b[5]]
         };
         state.underlying_file.write_all(&header_bytes)?;
+        if state.checksummed {
+            checksum.update(&header_bytes);
+            state
+                .underlying_file
+                .write_all(&checksum.digest().to_be_bytes())?;
+        }
         state.underlying_file.seek(SeekFrom::Start(offset_end))?;
 
         state.arena.shrink();
-        state.pos = last_pos;
+        state.pos = offset_end as usize;
 
         // Now write the actual data and update the offsets
         Ok(LogWriteResult {
@@ -283,69 +504,22 @@ impl ReadableJournal for LogFileJournalRx {
     /// UNSAFE: This method uses unsafe operations to remove the need to zero
     /// the buffer before its read the log entries into it
     fn read(&self) -> anyhow::Result<Option<LogReadResult<'_>>> {
-        let mut buffer_pos = self.buffer_pos.lock().unwrap();
+        let mut reader = self.reader.lock().unwrap();
 
         // Get a memory reference to the data on the disk at
-        // the current read location
-        let mut buffer_ptr = self.buffer.as_ref();
-        buffer_ptr.advance(*buffer_pos);
-        loop {
-            // Read the headers and advance
-            if buffer_ptr.len() < 8 {
-                return Ok(None);
-            }
-
-            let record_type: JournalEntryRecordType;
-            let header = {
-                let b = buffer_ptr;
-
-                // If the next header is the magic itself then skip it.
-                // You may be wondering how a magic could appear later
-                // in the journal itself. This can happen if someone
-                // concat's multiple journals together to make a combined
-                // journal
-                if b[0..8] == JOURNAL_MAGIC_NUMBER_BYTES[0..8] {
-                    buffer_ptr.advance(8);
-                    *buffer_pos += 8;
-                    continue;
-                }
-
-                // Otherwise we decode the header
-                let header = JournalEntryHeader {
-                    record_type: u16::from_be_bytes([b[0], b[1]]),
-                    record_size: u64::from_be_bytes([0u8, 0u8, b[2], b[3], b[4], b[5], b[6], b[7]]),
-                };
-
-                // Now we read the entry
-                record_type = match header.record_type.try_into() {
-                    Ok(t) => t,
-                    Err(_) => {
-                        tracing::debug!(
-                            "unknown journal entry type ({}) - the journal stops here",
-                            header.record_type
-                        );
-                        return Ok(None);
-                    }
-                };
-
-                buffer_ptr.advance(8);
-                *buffer_pos += 8;
-                header
-            };
-            let record_start = *buffer_pos as u64;
-
-            // Move the buffer position forward past the record
-            let entry = &buffer_ptr[..(header.record_size as usize)];
-            buffer_ptr.advance(header.record_size as usize);
-            *buffer_pos += header.record_size as usize;
-
-            let record = unsafe { record_type.deserialize_archive(entry)? };
-            return Ok(Some(LogReadResult {
-                record_start,
-                record_end: *buffer_pos as u64,
-                record,
-            }));
-        }
+        // the location of the next valid record
+        let (record_type, range) = match reader.next_record() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        let entry = &self.buffer.as_ref()[range.clone()];
+
+        let record = unsafe { record_type.deserialize_archive(entry)? };
+        Ok(Some(LogReadResult {
+            record_start: range.start as u64,
+            record_end: range.end as u64,
+            record,
+        }))
     }
 
     fn as_restarted(&self) -> anyhow::Result<Box<DynReadableJournal>> {
@@ -355,7 +529,7 @@ impl ReadableJournal for LogFileJournalRx {
         } else {
             Ok(Box::new(LogFileJournalRx {
                 tx: None,
-                buffer_pos: Mutex::new(0),
+                reader: Mutex::new(JournalReader::new(self.buffer.clone())),
                 buffer: self.buffer.clone(),
                 store: self.store.clone(),
             }))
@@ -575,4 +749,127 @@ mod tests {
         );
         assert_eq!(event6, None);
     }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_replay_stops_at_torn_record() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        // Write some events and then cut the last one in half, just like a
+        // process being killed in the middle of writing to its journal
+        let journal = LogFileJournal::from_file(file.as_file().try_clone().unwrap()).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 })
+            .unwrap();
+        journal.write(JournalEntry::PortAddrClearV1).unwrap();
+        let torn = journal
+            .write(JournalEntry::SocketSendV1 {
+                fd: 1234,
+                data: [12; 1024].to_vec().into(),
+                flags: 123,
+                is_64bit: true,
+            })
+            .unwrap();
+        drop(journal);
+        file.as_file()
+            .set_len(torn.record_start + torn.record_size() / 2)
+            .unwrap();
+
+        // The reader reports the records before the torn one
+        let mut reader = JournalReader::new(OwnedBuffer::from_file(file.as_file()).unwrap());
+        while reader.next_record().is_some() {}
+        assert_eq!(reader.valid_records(), 2);
+        assert!(reader.is_corrupted());
+
+        let journal = LogFileJournal::new_readonly(file.path()).unwrap();
+        let event1 = journal.read().unwrap().map(LogReadResult::into_inner);
+        let event2 = journal.read().unwrap().map(LogReadResult::into_inner);
+        let event3 = journal.read().unwrap().map(LogReadResult::into_inner);
+        assert_eq!(event1, Some(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 }));
+        assert_eq!(event2, Some(JournalEntry::PortAddrClearV1));
+        assert_eq!(event3, None);
+
+        // Opening the journal for writing drops the torn record so that
+        // new events are appended straight after the valid ones
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 3, fd2: 4 })
+            .unwrap();
+        drop(journal);
+
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        let event1 = journal.read().unwrap().map(LogReadResult::into_inner);
+        let event2 = journal.read().unwrap().map(LogReadResult::into_inner);
+        let event3 = journal.read().unwrap().map(LogReadResult::into_inner);
+        let event4 = journal.read().unwrap().map(LogReadResult::into_inner);
+        assert_eq!(event1, Some(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 }));
+        assert_eq!(event2, Some(JournalEntry::PortAddrClearV1));
+        assert_eq!(event3, Some(JournalEntry::CreatePipeV1 { fd1: 3, fd2: 4 }));
+        assert_eq!(event4, None);
+        assert!(!journal.rx.is_corrupted());
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_unknown_record_type_is_not_truncated() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let journal = LogFileJournal::from_file(file.as_file().try_clone().unwrap()).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 })
+            .unwrap();
+        let newer = journal.write(JournalEntry::PortAddrClearV1).unwrap();
+        drop(journal);
+
+        // Pretend the second record was written by a newer version with a
+        // record type this one does not know about
+        let mut data = std::fs::read(file.path()).unwrap();
+        let header = (newer.record_start - 16) as usize;
+        data[header..header + 2].copy_from_slice(&u16::MAX.to_be_bytes());
+        std::fs::write(file.path(), &data).unwrap();
+
+        let journal = LogFileJournal::new(file.path()).unwrap();
+        let event1 = journal.read().unwrap().map(LogReadResult::into_inner);
+        let event2 = journal.read().unwrap().map(LogReadResult::into_inner);
+        assert_eq!(event1, Some(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 }));
+        assert_eq!(event2, None);
+        assert!(!journal.rx.is_corrupted());
+        drop(journal);
+
+        assert_eq!(std::fs::read(file.path()).unwrap(), data);
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_replay_stops_at_bad_checksum() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let journal = LogFileJournal::from_file(file.as_file().try_clone().unwrap()).unwrap();
+        journal
+            .write(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 })
+            .unwrap();
+        let corrupt = journal
+            .write(JournalEntry::SocketSendV1 {
+                fd: 1234,
+                data: [12; 1024].to_vec().into(),
+                flags: 123,
+                is_64bit: true,
+            })
+            .unwrap();
+        journal.write(JournalEntry::PortAddrClearV1).unwrap();
+        drop(journal);
+
+        // Flip a bit in the middle of the second record
+        let mut data = std::fs::read(file.path()).unwrap();
+        data[(corrupt.record_start + corrupt.record_size() / 2) as usize] ^= 0x01;
+        std::fs::write(file.path(), data).unwrap();
+
+        let journal = LogFileJournal::new_readonly(file.path()).unwrap();
+        let event1 = journal.read().unwrap().map(LogReadResult::into_inner);
+        let event2 = journal.read().unwrap().map(LogReadResult::into_inner);
+        assert_eq!(event1, Some(JournalEntry::CreatePipeV1 { fd1: 1, fd2: 2 }));
+        assert_eq!(event2, None);
+        assert_eq!(journal.rx.valid_records(), 1);
+        assert!(journal.rx.is_corrupted());
+    }
 }
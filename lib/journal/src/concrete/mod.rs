@@ -19,6 +19,8 @@ mod null;
 mod pipe;
 mod printing;
 mod recombined;
+#[cfg(feature = "log-file")]
+mod rotating_log_file;
 #[cfg(test)]
 mod tests;
 mod transaction;
@@ -44,5 +46,7 @@ pub use null::*;
 pub use pipe::*;
 pub use printing::*;
 pub use recombined::*;
+#[cfg(feature = "log-file")]
+pub use rotating_log_file::*;
 pub use transaction::*;
 pub use unsupported::*;
@@ -216,6 +216,13 @@ impl<'a> fmt::Display for JournalEntry<'a> {
                 "path-rename (old-path={}, new-path={})",
                 old_path, new_path
             ),
+            JournalEntry::PathCopyTreeV1 {
+                old_path, new_path, ..
+            } => write!(
+                f,
+                "path-copy-tree (old-path={}, new-path={})",
+                old_path, new_path
+            ),
             JournalEntry::ChangeDirectoryV1 { path } => write!(f, "chdir (path={})", path),
             JournalEntry::EpollCreateV1 { fd } => write!(f, "epoll-create (fd={})", fd),
             JournalEntry::EpollCtlV1 { epfd, op, fd, .. } => {
@@ -0,0 +1,443 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use super::*;
+
+/// Determines what happens to the segments of a [`RotatingLogFileJournal`]
+/// once they are no longer the active segment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SegmentRetention {
+    /// All the segments are kept
+    #[default]
+    KeepAll,
+    /// Only the most recent segments are kept while the older ones are
+    /// deleted (which means their events are lost during replay)
+    KeepLast(usize),
+    /// The inactive segments are compacted together into a single segment
+    Compact,
+}
+
+#[derive(Debug)]
+struct State {
+    path: PathBuf,
+    active: LogFileJournal,
+    active_size: u64,
+    max_segment_size: Option<u64>,
+    retention: SegmentRetention,
+}
+
+/// The rotating log file journal writes to an active log file until it
+/// grows beyond a configured size, at which point it is renamed to a
+/// numbered segment (e.g. `journal.1`, `journal.2`) and a new active log
+/// file is started.
+///
+/// Reading the journal replays all the segments from oldest to newest
+/// followed by the active log file.
+#[derive(Debug)]
+pub struct RotatingLogFileJournal {
+    tx: RotatingLogFileJournalTx,
+    rx: RotatingLogFileJournalRx,
+}
+
+#[derive(Debug, Clone)]
+pub struct RotatingLogFileJournalTx {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+pub struct RotatingLogFileJournalRx {
+    tx: Option<RotatingLogFileJournalTx>,
+    segments: Vec<Box<DynReadableJournal>>,
+    index: Mutex<usize>,
+}
+
+impl RotatingLogFileJournal {
+    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let active = LogFileJournal::new(&path)?;
+        let active_size = std::fs::metadata(&path)?.len();
+
+        let tx = RotatingLogFileJournalTx {
+            state: Arc::new(Mutex::new(State {
+                path,
+                active,
+                active_size,
+                max_segment_size: None,
+                retention: SegmentRetention::default(),
+            })),
+        };
+        let rx = tx.as_rx()?;
+
+        Ok(Self { tx, rx })
+    }
+
+    /// Starts a new segment whenever the active one grows beyond this size
+    pub fn with_max_segment_size(self, max_segment_size: u64) -> Self {
+        self.tx
+            .state
+            .lock()
+            .unwrap()
+            .max_segment_size
+            .replace(max_segment_size);
+        self
+    }
+
+    pub fn with_retention(self, retention: SegmentRetention) -> Self {
+        self.tx.state.lock().unwrap().retention = retention;
+        self
+    }
+
+    /// Returns the paths of the inactive segments from oldest to newest
+    pub fn segments(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let state = self.tx.state.lock().unwrap();
+        Ok(list_segments(&state.path)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect())
+    }
+
+    pub fn rotate_now(&self) -> anyhow::Result<()> {
+        self.tx.rotate_now()
+    }
+}
+
+impl RotatingLogFileJournalTx {
+    pub fn as_rx(&self) -> anyhow::Result<RotatingLogFileJournalRx> {
+        let state = self.state.lock().unwrap();
+
+        let mut segments = Vec::new();
+        for (_, path) in list_segments(&state.path)? {
+            let (_, rx) = LogFileJournal::new_readonly(path)?.split();
+            segments.push(rx);
+        }
+        segments.push(state.active.as_restarted()?);
+
+        Ok(RotatingLogFileJournalRx {
+            tx: Some(self.clone()),
+            segments,
+            index: Mutex::new(0),
+        })
+    }
+
+    pub fn rotate_now(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        Self::rotate(&mut state)
+    }
+
+    fn rotate(state: &mut State) -> anyhow::Result<()> {
+        state.active.flush()?;
+
+        // The active log file becomes the newest segment
+        let next = list_segments(&state.path)?
+            .last()
+            .map(|(n, _)| *n + 1)
+            .unwrap_or(1);
+        std::fs::rename(&state.path, segment_path(&state.path, next))?;
+
+        match state.retention {
+            SegmentRetention::KeepAll => {}
+            SegmentRetention::KeepLast(n) => {
+                let segments = list_segments(&state.path)?;
+                let excess = segments.len().saturating_sub(n);
+                for (_, path) in segments.into_iter().take(excess) {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            SegmentRetention::Compact => compact_segments(&state.path)?,
+        }
+
+        // Start again with a fresh log file
+        state.active = LogFileJournal::new(&state.path)?;
+        state.active_size = std::fs::metadata(&state.path)?.len();
+        Ok(())
+    }
+}
+
+/// Returns the path of a numbered segment of the journal
+fn segment_path(path: &Path, n: u64) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Finds the inactive segments of the journal ordered from oldest to newest
+fn list_segments(path: &Path) -> anyhow::Result<Vec<(u64, PathBuf)>> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| {
+            anyhow::format_err!(
+                "The path is not a valid filename - {}",
+                path.to_string_lossy()
+            )
+        })?
+        .to_string_lossy()
+        .to_string();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut segments = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let n = file_name
+            .strip_prefix(name.as_str())
+            .and_then(|rest| rest.strip_prefix('.'))
+            .and_then(|n| n.parse::<u64>().ok());
+        if let Some(n) = n {
+            segments.push((n, entry.path()));
+        }
+    }
+    segments.sort_by_key(|(n, _)| *n);
+    Ok(segments)
+}
+
+/// Compacts all the inactive segments into the newest segment
+fn compact_segments(path: &Path) -> anyhow::Result<()> {
+    let segments = list_segments(path)?;
+    let Some(newest) = segments.last().map(|(n, _)| *n) else {
+        return Ok(());
+    };
+    let target = segment_path(path, newest);
+    let mut readers = Vec::new();
+    for (_, segment) in segments.iter() {
+        let (_, rx) = LogFileJournal::new_readonly(segment)?.split();
+        readers.push(rx);
+    }
+    let rx = RotatingLogFileJournalRx {
+        tx: None,
+        segments: readers,
+        index: Mutex::new(0),
+    };
+
+    // Feed all the segments into a compactor so that it learns the records
+    let mut compacting =
+        CompactingJournal::new(RecombinedJournal::new(NullJournal::default(), rx))?;
+    let replay = compacting.as_restarted()?;
+    copy_journal(&replay, &compacting)?;
+
+    let mut temp_path = path.as_os_str().to_os_string();
+    temp_path.push(".compacting");
+    let temp_path = PathBuf::from(temp_path);
+    std::fs::remove_file(&temp_path).ok();
+    compacting.compact_to(LogFileJournal::new(&temp_path)?)?;
+    std::fs::File::open(&temp_path)?.sync_all()?;
+
+    // The compacted segment replaces the newest segment before the others
+    // are deleted, if that is interrupted the leftover segments are replayed
+    // before the compacted one which then supersedes them
+    std::fs::rename(&temp_path, &target)?;
+    sync_dir(path)?;
+    for (n, segment) in segments {
+        if n != newest {
+            std::fs::remove_file(segment)?;
+        }
+    }
+    Ok(())
+}
+
+/// Makes the renames and deletes within the directory of the journal durable
+fn sync_dir(path: &Path) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+impl WritableJournal for RotatingLogFileJournalTx {
+    fn write<'a>(&'a self, entry: JournalEntry<'a>) -> anyhow::Result<LogWriteResult> {
+        let mut state = self.state.lock().unwrap();
+        let res = state.active.write(entry)?;
+
+        state.active_size = res.record_end;
+        if let Some(max) = state.max_segment_size {
+            if state.active_size >= max {
+                Self::rotate(&mut state)?;
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        self.state.lock().unwrap().active.flush()
+    }
+
+    fn commit(&self) -> anyhow::Result<usize> {
+        self.state.lock().unwrap().active.commit()
+    }
+
+    fn rollback(&self) -> anyhow::Result<usize> {
+        self.state.lock().unwrap().active.rollback()
+    }
+}
+
+impl ReadableJournal for RotatingLogFileJournalRx {
+    fn read(&self) -> anyhow::Result<Option<LogReadResult<'_>>> {
+        let mut index = self.index.lock().unwrap();
+        while let Some(segment) = self.segments.get(*index) {
+            if let Some(res) = segment.read()? {
+                return Ok(Some(res));
+            }
+            *index += 1;
+        }
+        Ok(None)
+    }
+
+    fn as_restarted(&self) -> anyhow::Result<Box<DynReadableJournal>> {
+        if let Some(tx) = &self.tx {
+            return Ok(Box::new(tx.as_rx()?));
+        }
+
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for segment in self.segments.iter() {
+            segments.push(segment.as_restarted()?);
+        }
+        Ok(Box::new(RotatingLogFileJournalRx {
+            tx: None,
+            segments,
+            index: Mutex::new(0),
+        }))
+    }
+}
+
+impl WritableJournal for RotatingLogFileJournal {
+    fn write<'a>(&'a self, entry: JournalEntry<'a>) -> anyhow::Result<LogWriteResult> {
+        self.tx.write(entry)
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        self.tx.flush()
+    }
+
+    fn commit(&self) -> anyhow::Result<usize> {
+        self.tx.commit()
+    }
+
+    fn rollback(&self) -> anyhow::Result<usize> {
+        self.tx.rollback()
+    }
+}
+
+impl ReadableJournal for RotatingLogFileJournal {
+    fn read(&self) -> anyhow::Result<Option<LogReadResult<'_>>> {
+        self.rx.read()
+    }
+
+    fn as_restarted(&self) -> anyhow::Result<Box<DynReadableJournal>> {
+        self.rx.as_restarted()
+    }
+}
+
+impl Journal for RotatingLogFileJournal {
+    fn split(self) -> (Box<DynWritableJournal>, Box<DynReadableJournal>) {
+        (Box::new(self.tx), Box::new(self.rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<JournalEntry<'static>> {
+        (0..8)
+            .map(|n| JournalEntry::SocketSendV1 {
+                fd: n,
+                data: vec![n as u8; 1000].into(),
+                flags: 0,
+                is_64bit: false,
+            })
+            .collect()
+    }
+
+    fn replay(journal: &impl ReadableJournal) -> Vec<JournalEntry<'static>> {
+        let journal = journal.as_restarted().unwrap();
+        let mut ret = Vec::new();
+        while let Some(entry) = journal.read().unwrap() {
+            ret.push(entry.into_inner().into_owned());
+        }
+        ret
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_rotated_segments_replay_like_a_single_log() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let baseline = LogFileJournal::new(dir.path().join("baseline")).unwrap();
+        let rotating = RotatingLogFileJournal::new(dir.path().join("journal"))
+            .unwrap()
+            .with_max_segment_size(3000);
+        for entry in entries() {
+            baseline.write(entry.clone()).unwrap();
+            rotating.write(entry).unwrap();
+        }
+
+        // Every third record starts a new segment
+        assert_eq!(
+            rotating.segments().unwrap(),
+            vec![dir.path().join("journal.1"), dir.path().join("journal.2")]
+        );
+
+        let expected = replay(&baseline);
+        assert_eq!(expected, entries());
+        assert_eq!(replay(&rotating), expected);
+
+        // Reopening the journal picks up the existing segments
+        drop(rotating);
+        let rotating = RotatingLogFileJournal::new(dir.path().join("journal")).unwrap();
+        assert_eq!(replay(&rotating), expected);
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_retention_prunes_old_segments() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let rotating = RotatingLogFileJournal::new(dir.path().join("journal"))
+            .unwrap()
+            .with_max_segment_size(3000)
+            .with_retention(SegmentRetention::KeepLast(1));
+        for entry in entries() {
+            rotating.write(entry).unwrap();
+        }
+
+        // Only the newest segment survives so the first segment is lost
+        assert_eq!(
+            rotating.segments().unwrap(),
+            vec![dir.path().join("journal.2")]
+        );
+        assert_eq!(replay(&rotating), entries()[3..].to_vec());
+    }
+
+    #[tracing_test::traced_test]
+    #[test]
+    pub fn test_compaction_replaces_the_newest_segment() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let rotating = RotatingLogFileJournal::new(dir.path().join("journal"))
+            .unwrap()
+            .with_max_segment_size(3000)
+            .with_retention(SegmentRetention::Compact);
+        for entry in entries() {
+            rotating.write(entry).unwrap();
+        }
+
+        // The compacted segment took the place of the newest one
+        assert_eq!(
+            rotating.segments().unwrap(),
+            vec![dir.path().join("journal.2")]
+        );
+        assert!(!dir.path().join("journal.compacting").exists());
+    }
+}
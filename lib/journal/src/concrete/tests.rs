@@ -306,6 +306,18 @@ pub fn test_record_path_rename() {
     });
 }
 
+#[tracing_test::traced_test]
+#[test]
+pub fn test_record_path_copy_tree() {
+    run_test(JournalEntry::PathCopyTreeV1 {
+        fd: 32451345,
+        old_path: "/asdfasdfas/asdfasdf".into(),
+        new_path: "/ahgfdfghdfghdfgh".into(),
+        follow_symlinks: true,
+        preserve_times: false,
+    });
+}
+
 #[tracing_test::traced_test]
 #[test]
 pub fn test_record_change_directory() {
@@ -686,6 +698,7 @@ pub fn test_record_alignment() {
     assert_eq!(std::mem::align_of::<JournalEntryCreateSymbolicLinkV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryUnlinkFileV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryPathRenameV1>(), 8);
+    assert_eq!(std::mem::align_of::<JournalEntryPathCopyTreeV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryChangeDirectoryV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryEpollCreateV1>(), 8);
     assert_eq!(std::mem::align_of::<JournalEntryEpollCtlV1>(), 8);
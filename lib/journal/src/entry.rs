@@ -73,6 +73,7 @@ pub enum SocketOptTimeType {
     ConnectTimeout,
     BindTimeout,
     Linger,
+    UserTimeout,
 }
 
 /// Represents a log entry in a snapshot log stream that represents the total
@@ -221,6 +222,13 @@ pub enum JournalEntry<'a> {
         new_fd: Fd,
         new_path: Cow<'a, str>,
     },
+    PathCopyTreeV1 {
+        fd: Fd,
+        old_path: Cow<'a, str>,
+        new_path: Cow<'a, str>,
+        follow_symlinks: bool,
+        preserve_times: bool,
+    },
     ChangeDirectoryV1 {
         path: Cow<'a, str>,
     },
@@ -547,6 +555,19 @@ impl<'a> JournalEntry<'a> {
                 new_fd,
                 new_path: new_path.into_owned().into(),
             },
+            Self::PathCopyTreeV1 {
+                fd,
+                old_path,
+                new_path,
+                follow_symlinks,
+                preserve_times,
+            } => JournalEntry::PathCopyTreeV1 {
+                fd,
+                old_path: old_path.into_owned().into(),
+                new_path: new_path.into_owned().into(),
+                follow_symlinks,
+                preserve_times,
+            },
             Self::ChangeDirectoryV1 { path } => JournalEntry::ChangeDirectoryV1 {
                 path: path.into_owned().into(),
             },
@@ -755,6 +776,9 @@ impl<'a> JournalEntry<'a> {
             JournalEntry::PathRenameV1 {
                 old_path, new_path, ..
             } => base_size + old_path.as_bytes().len() + new_path.as_bytes().len(),
+            JournalEntry::PathCopyTreeV1 {
+                old_path, new_path, ..
+            } => base_size + old_path.as_bytes().len() + new_path.as_bytes().len(),
             JournalEntry::ChangeDirectoryV1 { path } => base_size + path.as_bytes().len(),
             JournalEntry::EpollCreateV1 { .. } => base_size,
             JournalEntry::EpollCtlV1 { .. } => base_size,
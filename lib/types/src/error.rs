@@ -96,6 +96,14 @@ pub enum MemoryError {
     /// The memory does not support atomic operations.
     #[error("The memory does not support atomic operations")]
     AtomicsNotSupported,
+    /// The host could not provide the memory that was requested.
+    #[error("Insufficient memory: could not allocate {requested} bytes: {reason}")]
+    InsufficientMemory {
+        /// The number of bytes that were requested (including guard pages).
+        requested: usize,
+        /// Why the memory could not be allocated.
+        reason: String,
+    },
     /// A user defined error value, used for error cases not listed above.
     #[error("A user-defined error occurred: {0}")]
     Generic(String),
@@ -100,7 +100,7 @@ pub use types::{
 pub use value::{RawValue, ValueType};
 
 pub use crate::libcalls::LibCall;
-pub use crate::memory::MemoryStyle;
+pub use crate::memory::{MemoryProtection, MemoryStyle, ZeroingStrategy};
 pub use crate::table::TableStyle;
 pub use serialize::MetadataHeader;
 // TODO: OnCalledAction is needed for asyncify. It will be refactored with https://github.com/wasmerio/wasmer/issues/3451
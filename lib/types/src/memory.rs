@@ -21,6 +21,13 @@ pub enum MemoryStyle {
         /// It represents the size in bytes of extra guard pages after the end
         /// to optimize loads and stores with constant offsets.
         offset_guard_size: u64,
+        /// The number of pages that the underlying allocation grows by at
+        /// least, a small `memory.grow` is rounded up to this (within the
+        /// maximum of the memory) so that a guest growing its memory page by
+        /// page doesn't remap the memory every time.
+        ///
+        /// The size reported to the guest is not affected by this.
+        growth_granularity: Pages,
     },
     /// Address space is allocated up front.
     Static {
@@ -38,12 +45,64 @@ impl MemoryStyle {
     /// Returns the offset-guard size
     pub fn offset_guard_size(&self) -> u64 {
         match self {
-            Self::Dynamic { offset_guard_size } => *offset_guard_size,
+            Self::Dynamic {
+                offset_guard_size, ..
+            } => *offset_guard_size,
             Self::Static {
                 offset_guard_size, ..
             } => *offset_guard_size,
         }
     }
+
+    /// Returns the number of pages that the underlying allocation grows by
+    /// at least
+    pub fn growth_granularity(&self) -> Pages {
+        match self {
+            Self::Dynamic {
+                growth_granularity, ..
+            } => *growth_granularity,
+            Self::Static { .. } => Pages(1),
+        }
+    }
+}
+
+/// How linear memory pages are zeroed when a memory is allocated or reset.
+///
+/// This matters when memories are reused across instances (for example in
+/// a pool): without zeroing, data written by a previous guest would be
+/// visible to the next one.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, RkyvSerialize, RkyvDeserialize, Archive,
+)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "artifact-size", derive(loupe::MemoryUsage))]
+#[rkyv(derive(Debug), compare(PartialEq))]
+#[repr(u8)]
+pub enum ZeroingStrategy {
+    /// Memory is zeroed when it is allocated, a reset keeps the old
+    /// contents around (this is the historic behavior).
+    #[default]
+    OnAllocate,
+    /// Pages are handed back to the operating system on reset, which
+    /// provides zero-filled pages again the next time they are touched.
+    Lazy,
+    /// On reset the resident pages that hold data are zeroed in place, the
+    /// remaining pages (never written, or swapped out) are handed back to
+    /// the operating system.
+    OnReuse,
+}
+
+/// The access that guest code has to a range of a linear memory, see
+/// `LinearMemory::protect`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MemoryProtection {
+    /// Any access to the range traps.
+    None,
+    /// The range can be read, writing to it traps.
+    ReadOnly,
+    /// The range can be read and written (this is how memory starts out).
+    #[default]
+    ReadWrite,
 }
 
 /// Trait for the `Memory32` and `Memory64` marker types.
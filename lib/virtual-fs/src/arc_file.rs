@@ -139,6 +139,10 @@ where
         let inner = self.inner.lock().unwrap();
         inner.get_special_fd()
     }
+    fn bytes_available(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.bytes_available()
+    }
     fn poll_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let mut inner = self.inner.lock().unwrap();
         let inner = Pin::new(inner.as_mut());
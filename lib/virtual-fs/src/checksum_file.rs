@@ -0,0 +1,238 @@
+//! Wraps a [`VirtualFile`] and computes a running CRC32C checksum of the
+//! bytes that pass through it, which allows the integrity of whatever was
+//! written to be verified without reading the file back
+
+use super::*;
+
+use crate::VirtualFile;
+
+/// Lookup table for the CRC32C (Castagnoli) polynomial
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+            k += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+};
+
+/// Running CRC32C checksum that can be fed data incrementally
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32c {
+    state: u32,
+}
+
+impl Default for Crc32c {
+    fn default() -> Self {
+        Self { state: !0 }
+    }
+}
+
+impl Crc32c {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let mut crc = self.state;
+        for byte in data {
+            crc = CRC32C_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+        self.state = crc;
+    }
+
+    /// Returns the checksum of all the data fed in so far
+    pub fn digest(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// Wraps a [`VirtualFile`] and maintains a CRC32C checksum of all the
+/// bytes written to it (and optionally all the bytes read from it).
+///
+/// Writes made with `write_from_mmap` can not be observed and hence are
+/// rejected, which makes callers fall back to regular writes.
+#[derive(Debug)]
+pub struct ChecksummingFile {
+    inner: Box<dyn VirtualFile + Send + Sync + 'static>,
+    checksum: Crc32c,
+    bytes_written: u64,
+    bytes_read: u64,
+    include_reads: bool,
+}
+
+impl ChecksummingFile {
+    pub fn new(inner: Box<dyn VirtualFile + Send + Sync + 'static>) -> Self {
+        Self {
+            inner,
+            checksum: Crc32c::new(),
+            bytes_written: 0,
+            bytes_read: 0,
+            include_reads: false,
+        }
+    }
+
+    /// Also feeds the bytes that are read from the file into the checksum
+    pub fn with_reads(mut self) -> Self {
+        self.include_reads = true;
+        self
+    }
+
+    /// Returns the CRC32C checksum of all the bytes seen so far
+    pub fn digest(&self) -> u32 {
+        self.checksum.digest()
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    pub fn into_inner(self) -> Box<dyn VirtualFile + Send + Sync + 'static> {
+        self.inner
+    }
+}
+
+impl VirtualFile for ChecksummingFile {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
+        self.inner.set_times(atime, mtime)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> crate::Result<()> {
+        self.inner.set_len(new_size)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        self.inner.unlink()
+    }
+
+    fn is_open(&self) -> bool {
+        self.inner.is_open()
+    }
+
+    fn get_special_fd(&self) -> Option<u32> {
+        self.inner.get_special_fd()
+    }
+
+    fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.as_mut()).poll_write_ready(cx)
+    }
+}
+
+impl AsyncWrite for ChecksummingFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match Pin::new(&mut self.inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(amt)) => {
+                self.checksum.update(&buf[..amt]);
+                self.bytes_written += amt as u64;
+                Poll::Ready(Ok(amt))
+            }
+            res => res,
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl AsyncRead for ChecksummingFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = res {
+            let read = &buf.filled()[before..];
+            self.bytes_read += read.len() as u64;
+            if self.include_reads {
+                self.checksum.update(read);
+            }
+        }
+        res
+    }
+}
+
+impl AsyncSeek for ChecksummingFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[test]
+    fn crc32c_check_value() {
+        // The standard check value of CRC-32C
+        let mut crc = Crc32c::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.digest(), 0xE306_9283);
+    }
+
+    #[tokio::test]
+    async fn checksum_spans_partial_writes() {
+        let mut file = ChecksummingFile::new(Box::<BufferFile>::default());
+
+        file.write_all(b"1234").await.unwrap();
+        file.write_all(b"5").await.unwrap();
+        file.write_all(b"6789").await.unwrap();
+
+        assert_eq!(file.bytes_written(), 9);
+        assert_eq!(file.digest(), 0xE306_9283);
+    }
+}
@@ -46,6 +46,10 @@ impl VirtualFile for CombineFile {
         self.tx.unlink()
     }
 
+    fn bytes_available(&self) -> usize {
+        self.rx.bytes_available()
+    }
+
     fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         Pin::new(self.rx.as_mut()).poll_read_ready(cx)
     }
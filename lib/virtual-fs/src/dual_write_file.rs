@@ -54,6 +54,10 @@ impl VirtualFile for DualWriteFile {
         self.inner.unlink()
     }
 
+    fn bytes_available(&self) -> usize {
+        self.inner.bytes_available()
+    }
+
     fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         Pin::new(self.inner.as_mut()).poll_read_ready(cx)
     }
@@ -1,6 +1,6 @@
 use crate::{
-    DirEntry, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result,
-    VirtualFile,
+    Advice, CopyTreeOpts, DirEntry, FileType, FsError, FsStat, Metadata, OpenOptions,
+    OpenOptionsConfig, ReadDir, Result, VirtualFile,
 };
 use bytes::{Buf, Bytes};
 use futures::future::BoxFuture;
@@ -15,7 +15,7 @@ use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs as tfs;
-use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, AsyncWriteExt, ReadBuf};
 use tokio::runtime::Handle;
 
 #[derive(Debug, Clone)]
@@ -152,6 +152,17 @@ impl crate::FileSystem for FileSystem {
         fs::remove_dir(path).map_err(Into::into)
     }
 
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        let path = self.prepare_path(path);
+
+        if path.parent().is_none() {
+            return Err(FsError::BaseNotDirectory);
+        }
+
+        // Symbolic links are removed rather than followed
+        fs::remove_dir_all(path).map_err(Into::into)
+    }
+
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>> {
         Box::pin(async move {
             use filetime::{set_file_mtime, FileTime};
@@ -206,6 +217,74 @@ impl crate::FileSystem for FileSystem {
         })
     }
 
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            use std::io::Write;
+
+            let path = self.prepare_path(path);
+            if path.parent().is_none() {
+                return Err(FsError::BaseNotDirectory);
+            }
+            let temp = crate::atomic_temp_path(&path)?;
+
+            let result = (|| -> io::Result<()> {
+                let mut file = fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&temp)?;
+                file.write_all(contents)?;
+                file.sync_all()?;
+                drop(file);
+                fs::rename(&temp, &path)?;
+
+                // The rename itself is only durable once the directory is synced
+                #[cfg(unix)]
+                if let Some(parent) = path.parent() {
+                    fs::File::open(parent)?.sync_all()?;
+                }
+                Ok(())
+            })();
+            if result.is_err() {
+                fs::remove_file(&temp).ok();
+            }
+            result.map_err(Into::into)
+        })
+    }
+
+    fn copy_file<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(async move {
+            let from = self.prepare_path(from);
+            let to = self.prepare_path(to);
+            if to.parent().is_none() {
+                return Err(FsError::BaseNotDirectory);
+            }
+            copy_file(&from, &to).map_err(Into::into)
+        })
+    }
+
+    fn copy_tree<'a>(
+        &'a self,
+        from: &'a Path,
+        to: &'a Path,
+        opts: CopyTreeOpts,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let from = self.prepare_path(from);
+            let to = self.prepare_path(to);
+            if to.parent().is_none() {
+                return Err(FsError::BaseNotDirectory);
+            }
+            if to.starts_with(&from) {
+                return Err(FsError::InvalidInput);
+            }
+            self.handle
+                .spawn_blocking(move || copy_tree(&from, &to, opts, &mut Vec::new()))
+                .await
+                .map_err(|_| FsError::UnknownError)?
+                .map_err(Into::into)
+        })
+    }
+
     fn remove_file(&self, path: &Path) -> Result<()> {
         let path = self.prepare_path(path);
 
@@ -228,6 +307,44 @@ impl crate::FileSystem for FileSystem {
             .map_err(Into::into)
     }
 
+    #[cfg(unix)]
+    fn statvfs(&self, path: &Path) -> Result<FsStat> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = self.prepare_path(path);
+        let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| FsError::InvalidInput)?;
+
+        let mut stat = std::mem::MaybeUninit::<libc::statvfs>::zeroed();
+        let ret = unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let stat = unsafe { stat.assume_init() };
+
+        let fragment_size = stat.f_frsize as u64;
+        Ok(FsStat {
+            block_size: stat.f_bsize as u64,
+            total_bytes: stat.f_blocks as u64 * fragment_size,
+            free_bytes: stat.f_bfree as u64 * fragment_size,
+            available_bytes: stat.f_bavail as u64 * fragment_size,
+            total_inodes: stat.f_files as u64,
+            free_inodes: stat.f_ffree as u64,
+        })
+    }
+
+    fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        let path = self.prepare_path(path);
+
+        match (atime.map(file_time), mtime.map(file_time)) {
+            (Some(atime), Some(mtime)) => filetime::set_file_times(path, atime, mtime),
+            (Some(atime), None) => filetime::set_file_atime(path, atime),
+            (None, Some(mtime)) => filetime::set_file_mtime(path, mtime),
+            (None, None) => Ok(()),
+        }
+        .map_err(Into::into)
+    }
+
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         let path = self.prepare_path(path);
 
@@ -246,6 +363,188 @@ impl crate::FileSystem for FileSystem {
     }
 }
 
+/// Converts a UNIX timestamp in nanoseconds into a [`filetime::FileTime`]
+fn file_time(nanos: u64) -> filetime::FileTime {
+    filetime::FileTime::from_unix_time(
+        (nanos / 1_000_000_000) as i64,
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+/// Copies the file `from` to `to` on the host, see
+/// [`crate::FileSystem::copy_file`]
+///
+/// The file is cloned by reference when the host file system supports it
+/// and copied byte by byte otherwise.
+fn copy_file(from: &Path, to: &Path) -> io::Result<u64> {
+    // Opening the destination truncates it, which would lose the contents
+    // when both paths are the same file
+    #[cfg(unix)]
+    if let Ok(dst) = fs::metadata(to) {
+        use std::os::unix::fs::MetadataExt;
+        let src = fs::metadata(from)?;
+        if (src.dev(), src.ino()) == (dst.dev(), dst.ino()) {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+    }
+
+    match reflink(from, to) {
+        Ok(len) => return Ok(len),
+        Err(err) if reflink_unsupported(&err) => {
+            tracing::trace!(
+                from=%from.display(),
+                to=%to.display(),
+                error=%err,
+                "Unable to reflink, copying the file instead",
+            );
+        }
+        Err(err) => return Err(err),
+    }
+    fs::copy(from, to)
+}
+
+/// Clones `from` to `to` by reference with the `FICLONE` ioctl (btrfs,
+/// XFS and other file systems that share extents)
+#[cfg(target_os = "linux")]
+fn reflink(from: &Path, to: &Path) -> io::Result<u64> {
+    use std::os::fd::AsRawFd;
+
+    let src = fs::File::open(from)?;
+    let metadata = src.metadata()?;
+    if !metadata.is_file() {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    // The old contents are only dropped once the clone succeeded, so they
+    // are still there for the fallback copy otherwise
+    let dst = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(to)?;
+    let ret = unsafe { libc::ioctl(dst.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    dst.set_len(metadata.len())?;
+    // Keeps the permissions just like `fs::copy`
+    dst.set_permissions(metadata.permissions())?;
+    Ok(metadata.len())
+}
+
+/// Clones `from` to `to` by reference with `clonefile` (APFS), which only
+/// works when `to` does not exist yet
+#[cfg(target_os = "macos")]
+fn reflink(from: &Path, to: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let metadata = fs::metadata(from)?;
+    if !metadata.is_file() {
+        return Err(io::ErrorKind::InvalidInput.into());
+    }
+    let src = CString::new(from.as_os_str().as_bytes())?;
+    let dst = CString::new(to.as_os_str().as_bytes())?;
+    let ret = unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(metadata.len())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_from: &Path, _to: &Path) -> io::Result<u64> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Whether a failed [`reflink`] should fall back to copying the bytes
+///
+/// File systems without support for the ioctl (FUSE, NFS, ...) report it
+/// in various ways, so any error of the clone itself falls back.
+fn reflink_unsupported(err: &io::Error) -> bool {
+    if err.kind() == io::ErrorKind::Unsupported {
+        return true;
+    }
+    #[cfg(unix)]
+    return matches!(
+        err.raw_os_error(),
+        Some(code) if code == libc::EXDEV
+            || code == libc::EOPNOTSUPP
+            || code == libc::ENOTSUP
+            || code == libc::ENOTTY
+            || code == libc::EINVAL
+            || code == libc::ENOSYS
+            || code == libc::EEXIST
+    );
+    #[cfg(not(unix))]
+    false
+}
+
+/// Recursively copies `from` to `to` on the host, see
+/// [`crate::FileSystem::copy_tree`]
+///
+/// `ancestors` holds the directories that are being copied above `from`,
+/// following a link back into one of them fails instead of recursing forever.
+fn copy_tree(
+    from: &Path,
+    to: &Path,
+    opts: CopyTreeOpts,
+    ancestors: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let metadata = match opts.follow_symlinks {
+        true => fs::metadata(from)?,
+        false => fs::symlink_metadata(from)?,
+    };
+
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)?;
+        #[cfg(not(unix))]
+        return Err(io::ErrorKind::Unsupported.into());
+    } else if file_type.is_dir() {
+        // Without following links the walk can not come back to an ancestor
+        let dir = match opts.follow_symlinks {
+            true => Some(fs::canonicalize(from)?),
+            false => None,
+        };
+        if let Some(dir) = dir {
+            if ancestors.contains(&dir) {
+                return Err(FsError::SymlinkLoop.into());
+            }
+            ancestors.push(dir);
+        }
+
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()), opts, ancestors)?;
+        }
+        if opts.follow_symlinks {
+            ancestors.pop();
+        }
+        // Only restricted once all the children are in place
+        fs::set_permissions(to, metadata.permissions())?;
+    } else {
+        if fs::symlink_metadata(to).is_ok() {
+            return Err(io::ErrorKind::AlreadyExists.into());
+        }
+        // This clones the file by reference where the host file system
+        // supports it (`copy_file_range` on Linux, `fclonefileat` on macOS)
+        // and copies the permissions along with the contents
+        fs::copy(from, to)?;
+    }
+
+    if opts.preserve_times {
+        use filetime::{set_symlink_file_times, FileTime};
+        set_symlink_file_times(
+            to,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        )?;
+    }
+    Ok(())
+}
+
 impl TryInto<Metadata> for std::fs::Metadata {
     type Error = io::Error;
 
@@ -329,8 +628,9 @@ impl crate::FileOpener for FileSystem {
             .create_new(conf.create_new())
             .create(conf.create())
             .append(append)
-            .truncate(conf.truncate())
-            .open(&path)
+            .truncate(conf.truncate());
+
+        open_no_atime(&oo, &path, conf.no_atime())
             .map_err(Into::into)
             .map(|file| {
                 Box::new(File::new(
@@ -345,6 +645,28 @@ impl crate::FileOpener for FileSystem {
     }
 }
 
+/// Opens a file with `O_NOATIME` when asked to, falling back to a normal
+/// open if the process is not allowed to use the flag on this file (only
+/// the owner of a file may open it with `O_NOATIME`)
+#[cfg(target_os = "linux")]
+fn open_no_atime(oo: &fs::OpenOptions, path: &Path, no_atime: bool) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    if no_atime {
+        let mut no_atime_oo = oo.clone();
+        match no_atime_oo.custom_flags(libc::O_NOATIME).open(path) {
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {}
+            res => return res,
+        }
+    }
+    oo.open(path)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_no_atime(oo: &fs::OpenOptions, path: &Path, _no_atime: bool) -> io::Result<fs::File> {
+    oo.open(path)
+}
+
 /// A thin wrapper around `std::fs::File`
 #[derive(Debug)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize))]
@@ -528,8 +850,8 @@ impl VirtualFile for File {
     }
 
     fn set_times(&mut self, atime: Option<u64>, mtime: Option<u64>) -> crate::Result<()> {
-        let atime = atime.map(|t| filetime::FileTime::from_unix_time(t as i64, 0));
-        let mtime = mtime.map(|t| filetime::FileTime::from_unix_time(t as i64, 0));
+        let atime = atime.map(file_time);
+        let mtime = mtime.map(file_time);
 
         filetime::set_file_handle_times(&self.inner_std, atime, mtime)
             .map_err(|_| crate::FsError::IOError)
@@ -551,6 +873,37 @@ impl VirtualFile for File {
         None
     }
 
+    fn readahead(&mut self, offset: u64, len: u64) -> BoxFuture<'_, io::Result<()>> {
+        let res = readahead(&self.inner_std, offset, len);
+        Box::pin(async move { res })
+    }
+
+    fn read_at<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        offset: u64,
+    ) -> BoxFuture<'a, io::Result<usize>> {
+        Box::pin(async move {
+            // Writes that are still in flight on the async handle have to land first
+            self.inner.flush().await?;
+            read_at(&self.inner_std, buf, offset)
+        })
+    }
+
+    fn write_at<'a>(&'a mut self, buf: &'a [u8], offset: u64) -> BoxFuture<'a, io::Result<usize>> {
+        Box::pin(async move {
+            self.inner.flush().await?;
+            write_at(&self.inner_std, buf, offset)
+        })
+    }
+
+    fn advise(&mut self, offset: u64, len: u64, advice: Advice) -> io::Result<()> {
+        match advice {
+            Advice::DontNeed => drop_cached_pages(&self.inner_std, offset, len),
+            _ => Ok(()),
+        }
+    }
+
     fn poll_read_ready(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let cursor = match self.inner_std.stream_position() {
             Ok(a) => a,
@@ -571,6 +924,91 @@ impl VirtualFile for File {
     }
 }
 
+/// Asks the operating system to load a range of the file into the page cache
+#[cfg(target_os = "linux")]
+fn readahead(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let len = usize::try_from(len).unwrap_or(usize::MAX);
+    let ret = unsafe { libc::readahead(file.as_raw_fd(), offset as libc::off64_t, len) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Asks the operating system to load a range of the file into the page cache
+#[cfg(target_os = "macos")]
+fn readahead(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let mut advice = libc::radvisory {
+        ra_offset: offset as libc::off_t,
+        ra_count: len.min(libc::c_int::MAX as u64) as libc::c_int,
+    };
+    let ret = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_RDADVISE, &mut advice) };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Readahead is only a hint so on other platforms it does nothing
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn readahead(_file: &fs::File, _offset: u64, _len: u64) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_at(file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(unix)]
+fn write_at(file: &fs::File, buf: &[u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::write_at(file, buf, offset)
+}
+
+// Unlike `pread`/`pwrite` these move the cursor of the host file (which the
+// async handle shares), so it is put back afterwards
+#[cfg(windows)]
+fn read_at(mut file: &fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    let cursor = file.stream_position()?;
+    let res = std::os::windows::fs::FileExt::seek_read(file, buf, offset);
+    file.seek(io::SeekFrom::Start(cursor))?;
+    res
+}
+
+#[cfg(windows)]
+fn write_at(mut file: &fs::File, buf: &[u8], offset: u64) -> io::Result<usize> {
+    let cursor = file.stream_position()?;
+    let res = std::os::windows::fs::FileExt::seek_write(file, buf, offset);
+    file.seek(io::SeekFrom::Start(cursor))?;
+    res
+}
+
+/// Asks the operating system to drop a range of the file from the page cache
+#[cfg(target_os = "linux")]
+fn drop_cached_pages(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // A length of zero means up to the end of the file
+    let len = libc::off_t::try_from(len).unwrap_or(0);
+    let offset = libc::off_t::try_from(offset).map_err(|_| io::ErrorKind::InvalidInput)?;
+    let ret =
+        unsafe { libc::posix_fadvise(file.as_raw_fd(), offset, len, libc::POSIX_FADV_DONTNEED) };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(ret));
+    }
+    Ok(())
+}
+
+/// Dropping cached pages is only a hint so on other platforms it does nothing
+#[cfg(not(target_os = "linux"))]
+fn drop_cached_pages(_file: &fs::File, _offset: u64, _len: u64) -> io::Result<()> {
+    Ok(())
+}
+
 impl AsyncRead for File {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -1027,6 +1465,10 @@ impl VirtualFile for Stdin {
     fn get_special_fd(&self) -> Option<u32> {
         Some(0)
     }
+    fn bytes_available(&self) -> usize {
+        let read_buffer = self.read_buffer.lock().unwrap();
+        read_buffer.as_ref().map(|buf| buf.len()).unwrap_or(0)
+    }
     fn poll_read_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         {
             let read_buffer = self.read_buffer.lock().unwrap();
@@ -1068,7 +1510,7 @@ mod tests {
 
     use super::FileSystem;
     use crate::FileSystem as FileSystemTrait;
-    use crate::FsError;
+    use crate::{Advice, CopyTreeOpts, FsError};
     use std::path::Path;
 
     #[tokio::test]
@@ -1090,6 +1532,174 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_readahead() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("file.txt"), vec![7u8; 64 * 1024]).unwrap();
+
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open(Path::new("/file.txt"))
+            .unwrap();
+
+        assert!(file.readahead(0, 64 * 1024).await.is_ok());
+        // Ranges past the end of the file are not an error
+        assert!(file.readahead(32 * 1024, 1024 * 1024).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_advise() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("file.txt"), vec![7u8; 64 * 1024]).unwrap();
+
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open(Path::new("/file.txt"))
+            .unwrap();
+
+        assert!(file.advise(0, 64 * 1024, Advice::DontNeed).is_ok());
+        assert!(file.advise(0, 0, Advice::DontNeed).is_ok());
+        assert!(file.advise(0, 64 * 1024, Advice::Sequential).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_positional_reads() {
+        use std::sync::Arc;
+        use tokio::io::AsyncReadExt;
+
+        let contents = (0..64 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("file.txt"), &contents).unwrap();
+
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+        let file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .open(Path::new("/file.txt"))
+            .unwrap();
+        let file = Arc::new(tokio::sync::Mutex::new(file));
+        let contents = Arc::new(contents);
+
+        // Positional readers interleave with a reader that goes through the
+        // file sequentially, neither may observe the cursor of the other
+        let mut tasks = (0..8u64)
+            .map(|task| {
+                let file = file.clone();
+                let contents = contents.clone();
+                tokio::spawn(async move {
+                    for i in 0..32u64 {
+                        let offset = (task * 7919 + i * 1031) % (64 * 1024 - 16);
+                        let mut buf = [0u8; 16];
+                        let read = file.lock().await.read_at(&mut buf, offset).await.unwrap();
+                        assert_eq!(read, buf.len());
+                        let offset = offset as usize;
+                        assert_eq!(buf[..], contents[offset..offset + 16]);
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        tasks.push(tokio::spawn({
+            let file = file.clone();
+            let contents = contents.clone();
+            async move {
+                for chunk in contents.chunks(1024) {
+                    let mut buf = [0u8; 1024];
+                    file.lock().await.read_exact(&mut buf).await.unwrap();
+                    assert_eq!(buf[..], chunk[..]);
+                    tokio::task::yield_now().await;
+                }
+            }
+        }));
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // Positional writes do not move the cursor either
+        let mut file = file.lock().await;
+        assert_eq!(file.write_at(b"hello", 10).await.unwrap(), 5);
+        let mut buf = [0u8; 5];
+        assert_eq!(file.read_at(&mut buf, 10).await.unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_copy_file_falls_back_without_reflink() {
+        // tmpfs can not clone files by reference
+        let Ok(temp) = TempDir::new_in("/dev/shm") else {
+            return;
+        };
+        std::fs::write(temp.path().join("from.txt"), b"some contents").unwrap();
+        std::fs::write(temp.path().join("to.txt"), b"to be replaced by the copy").unwrap();
+
+        let err =
+            super::reflink(&temp.path().join("from.txt"), &temp.path().join("to.txt")).unwrap_err();
+        assert!(super::reflink_unsupported(&err), "{err}");
+        // A failed clone leaves the destination alone
+        assert_eq!(
+            std::fs::read(temp.path().join("to.txt")).unwrap(),
+            b"to be replaced by the copy"
+        );
+
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+        assert_eq!(
+            fs.copy_file(Path::new("/from.txt"), Path::new("/to.txt"))
+                .await,
+            Ok(13)
+        );
+        assert_eq!(
+            std::fs::read(temp.path().join("to.txt")).unwrap(),
+            b"some contents"
+        );
+        assert_eq!(
+            fs.copy_file(Path::new("/from.txt"), Path::new("/new.txt"))
+                .await,
+            Ok(13)
+        );
+        assert_eq!(
+            std::fs::read(temp.path().join("new.txt")).unwrap(),
+            b"some contents"
+        );
+
+        // Copying a file onto itself would truncate it
+        assert_eq!(
+            fs.copy_file(Path::new("/from.txt"), Path::new("/from.txt"))
+                .await,
+            Err(FsError::InvalidInput)
+        );
+        assert_eq!(
+            std::fs::read(temp.path().join("from.txt")).unwrap(),
+            b"some contents"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_statvfs() {
+        let temp = TempDir::new().unwrap();
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+
+        let stat = fs.statvfs(Path::new("/")).unwrap();
+        assert!(stat.block_size > 0);
+        assert!(stat.total_bytes > 0);
+        assert!(stat.free_bytes <= stat.total_bytes);
+        assert!(stat.available_bytes <= stat.free_bytes);
+
+        assert_eq!(
+            fs.statvfs(Path::new("/does-not-exist")),
+            Err(FsError::EntryNotFound)
+        );
+    }
+
     #[tokio::test]
     async fn test_create_dir() {
         let temp: TempDir = TempDir::new().unwrap();
@@ -1149,6 +1759,108 @@ mod tests {
         );
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_remove_dir_all() {
+        let temp: TempDir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("tree/a/b")).unwrap();
+        std::fs::write(temp.path().join("tree/file.txt"), b"").unwrap();
+        std::fs::write(temp.path().join("tree/a/b/deep.txt"), b"").unwrap();
+        std::fs::create_dir(temp.path().join("outside")).unwrap();
+        std::fs::write(temp.path().join("outside/kept.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(temp.path().join("outside"), temp.path().join("tree/link"))
+            .unwrap();
+
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+
+        assert_eq!(
+            fs.remove_dir_all(Path::new("/tree")),
+            Ok(()),
+            "removing a directory that has children",
+        );
+
+        assert!(
+            !temp.path().join("tree").exists(),
+            "the whole subtree is gone"
+        );
+        assert!(
+            temp.path().join("outside/kept.txt").exists(),
+            "the target of a symlink survives",
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_copy_tree() {
+        let temp: TempDir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("src/a/b")).unwrap();
+        std::fs::write(temp.path().join("src/top.txt"), b"top").unwrap();
+        std::fs::write(temp.path().join("src/a/b/deep.txt"), b"deep").unwrap();
+        std::os::unix::fs::symlink("a/b/deep.txt", temp.path().join("src/link")).unwrap();
+
+        let fs = FileSystem::new(Handle::current(), temp.path()).expect("get filesystem");
+        let opts = CopyTreeOpts {
+            preserve_times: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fs.copy_tree(Path::new("/src"), Path::new("/dst"), opts)
+                .await,
+            Ok(())
+        );
+
+        let dst = temp.path().join("dst");
+        assert_eq!(std::fs::read(dst.join("top.txt")).unwrap(), b"top");
+        assert_eq!(std::fs::read(dst.join("a/b/deep.txt")).unwrap(), b"deep");
+        assert!(
+            std::fs::symlink_metadata(dst.join("link"))
+                .unwrap()
+                .file_type()
+                .is_symlink(),
+            "symlinks are copied as links"
+        );
+        assert_eq!(
+            std::fs::read_link(dst.join("link")).unwrap(),
+            Path::new("a/b/deep.txt")
+        );
+        assert_eq!(
+            std::fs::metadata(dst.join("top.txt"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            std::fs::metadata(temp.path().join("src/top.txt"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            "timestamps are preserved"
+        );
+
+        assert_eq!(
+            fs.copy_tree(Path::new("/src"), Path::new("/dst"), opts)
+                .await,
+            Err(FsError::AlreadyExists),
+            "the destination must not exist yet"
+        );
+        assert_eq!(
+            fs.copy_tree(Path::new("/src"), Path::new("/src/a/copy"), opts)
+                .await,
+            Err(FsError::InvalidInput),
+            "a tree can not be copied into itself"
+        );
+
+        std::os::unix::fs::symlink("../..", temp.path().join("src/a/b/up")).unwrap();
+        let opts = CopyTreeOpts {
+            follow_symlinks: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fs.copy_tree(Path::new("/src"), Path::new("/loop"), opts)
+                .await,
+            Err(FsError::SymlinkLoop),
+            "links back to an ancestor are not followed forever"
+        );
+    }
+
     #[tokio::test]
     async fn test_remove_dir() {
         let temp: TempDir = TempDir::new().unwrap();
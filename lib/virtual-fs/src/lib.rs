@@ -21,6 +21,7 @@ pub mod arc_file;
 pub mod arc_fs;
 pub mod buffer_file;
 pub mod builder;
+pub mod checksum_file;
 pub mod combine_file;
 pub mod cow_file;
 pub mod dual_write_file;
@@ -31,7 +32,10 @@ pub mod mem_fs;
 pub mod null_file;
 pub mod passthru_fs;
 pub mod random_file;
+pub mod range_lock;
+pub mod slice_file;
 pub mod special_file;
+pub mod sysctl_fs;
 pub mod tmp_fs;
 pub mod union_fs;
 pub mod zero_file;
@@ -54,6 +58,7 @@ pub use arc_file::*;
 pub use arc_fs::*;
 pub use buffer_file::*;
 pub use builder::*;
+pub use checksum_file::*;
 pub use combine_file::*;
 pub use cow_file::*;
 pub use dual_write_file::*;
@@ -63,8 +68,10 @@ pub use null_file::*;
 pub use overlay_fs::OverlayFileSystem;
 pub use passthru_fs::*;
 pub use pipe::*;
+pub use slice_file::*;
 pub use special_file::*;
 pub use static_file::StaticFile;
+pub use sysctl_fs::*;
 pub use tmp_fs::*;
 pub use trace_fs::TraceFileSystem;
 pub use union_fs::*;
@@ -89,8 +96,135 @@ pub trait FileSystem: fmt::Debug + Send + Sync + 'static + Upcastable {
     fn read_dir(&self, path: &Path) -> Result<ReadDir>;
     fn create_dir(&self, path: &Path) -> Result<()>;
     fn remove_dir(&self, path: &Path) -> Result<()>;
+    /// Removes a directory along with everything inside of it (like `rm -rf`).
+    ///
+    /// Symbolic links are removed instead of being followed so nothing outside
+    /// of the directory is deleted.
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        for entry in self.read_dir(path)? {
+            let entry = entry?;
+            let metadata = self.symlink_metadata(&entry.path)?;
+            if metadata.is_dir() && !metadata.ft.is_symlink() {
+                self.remove_dir_all(&entry.path)?;
+            } else {
+                self.remove_file(&entry.path)?;
+            }
+        }
+        self.remove_dir(path)
+    }
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>>;
+    /// Replaces the whole contents of a file in one step, readers will either
+    /// see the old contents (or no file at all) or the new contents but never
+    /// a partially written file.
+    ///
+    /// The default implementation writes the contents to a temporary sibling
+    /// of `path`, flushes it and then renames it over `path`. The temporary
+    /// file is removed again when any of these steps fail.
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let temp = atomic_temp_path(path)?;
+            let result = async {
+                let mut file = self
+                    .new_open_options()
+                    .write(true)
+                    .create_new(true)
+                    .open(&temp)?;
+                file.write_all(contents).await?;
+                file.flush().await?;
+                drop(file);
+                self.rename(&temp, path).await
+            }
+            .await;
+            if result.is_err() {
+                self.remove_file(&temp).ok();
+            }
+            result
+        })
+    }
+    /// Copies the contents of the file at `from` to `to`, which is created
+    /// or replaced, and returns the number of bytes that were copied.
+    ///
+    /// The default implementation streams the bytes from one file to the
+    /// other, file systems that can clone files by reference override this.
+    fn copy_file<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(async move {
+            if from == to {
+                return Err(FsError::InvalidInput);
+            }
+            let mut src = self.new_open_options().read(true).open(from)?;
+            let mut dst = self
+                .new_open_options()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(to)?;
+            let copied = tokio::io::copy(&mut src, &mut dst).await?;
+            dst.flush().await?;
+            Ok(copied)
+        })
+    }
+    /// Recursively copies the file or directory at `from` to `to`, which must
+    /// not exist yet (like `cp -R`).
+    ///
+    /// The default implementation creates every directory and copies every
+    /// file through [`VirtualFile::copy_reference`], so files are only cloned
+    /// by reference when the file system supports it. Symbolic links are
+    /// copied as links unless [`CopyTreeOpts::follow_symlinks`] is set, file
+    /// systems that can not create links fail with [`FsError::Unsupported`].
+    fn copy_tree<'a>(
+        &'a self,
+        from: &'a Path,
+        to: &'a Path,
+        opts: CopyTreeOpts,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if to.starts_with(from) {
+                return Err(FsError::InvalidInput);
+            }
+            let metadata = match opts.follow_symlinks {
+                true => self.metadata(from)?,
+                false => self.symlink_metadata(from)?,
+            };
+            if metadata.ft.is_symlink() {
+                return Err(FsError::Unsupported);
+            }
+
+            if metadata.is_dir() {
+                self.create_dir(to)?;
+                for entry in self.read_dir(from)? {
+                    let entry = entry?;
+                    let name = entry.path.file_name().ok_or(FsError::InvalidInput)?;
+                    self.copy_tree(&entry.path, &to.join(name), opts).await?;
+                }
+            } else {
+                let src = self.new_open_options().read(true).open(from)?;
+                let mut dst = self
+                    .new_open_options()
+                    .write(true)
+                    .create_new(true)
+                    .open(to)?;
+                dst.copy_reference(src).await?;
+                if opts.preserve_times {
+                    dst.set_times(Some(metadata.accessed), Some(metadata.modified))?;
+                }
+            }
+            Ok(())
+        })
+    }
     fn metadata(&self, path: &Path) -> Result<Metadata>;
+    /// Returns statistics about the file system that contains `path`, such
+    /// as its size and how much of it is still free (like `statvfs`).
+    fn statvfs(&self, path: &Path) -> Result<FsStat> {
+        let _ = path;
+        Err(FsError::Unsupported)
+    }
+    /// Sets the last accessed and last modified times (in nanoseconds as a
+    /// UNIX timestamp) of the file or directory at `path`, a time that is
+    /// `None` is left unchanged (like `utimensat` with `UTIME_OMIT`).
+    fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        let _ = (path, atime, mtime);
+        Err(FsError::Unsupported)
+    }
     /// This method gets metadata without following symlinks in the path.
     /// Currently identical to `metadata` because symlinks aren't implemented
     /// yet.
@@ -103,6 +237,19 @@ pub trait FileSystem: fmt::Debug + Send + Sync + 'static + Upcastable {
         -> Result<()>;
 }
 
+/// Picks the name of the temporary file that [`FileSystem::write_atomic`]
+/// writes to before it is renamed over `path`
+pub(crate) fn atomic_temp_path(path: &Path) -> Result<PathBuf> {
+    static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+    let name = path.file_name().ok_or(FsError::InvalidInput)?;
+    let n = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".{}.{}.tmp", std::process::id(), n));
+    Ok(path.with_file_name(temp_name))
+}
+
 impl dyn FileSystem + 'static {
     #[inline]
     pub fn downcast_ref<T: 'static>(&'_ self) -> Option<&'_ T> {
@@ -136,14 +283,43 @@ where
         (**self).remove_dir(path)
     }
 
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        (**self).remove_dir_all(path)
+    }
+
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>> {
         Box::pin(async { (**self).rename(from, to).await })
     }
 
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { (**self).write_atomic(path, contents).await })
+    }
+
+    fn copy_file<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(async { (**self).copy_file(from, to).await })
+    }
+
+    fn copy_tree<'a>(
+        &'a self,
+        from: &'a Path,
+        to: &'a Path,
+        opts: CopyTreeOpts,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { (**self).copy_tree(from, to, opts).await })
+    }
+
     fn metadata(&self, path: &Path) -> Result<Metadata> {
         (**self).metadata(path)
     }
 
+    fn statvfs(&self, path: &Path) -> Result<FsStat> {
+        (**self).statvfs(path)
+    }
+
+    fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        (**self).set_times(path, atime, mtime)
+    }
+
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         (**self).symlink_metadata(path)
     }
@@ -182,6 +358,8 @@ pub struct OpenOptionsConfig {
     pub create: bool,
     pub append: bool,
     pub truncate: bool,
+    /// Reads through the opened file do not update its access time
+    pub no_atime: bool,
 }
 
 impl OpenOptionsConfig {
@@ -194,6 +372,7 @@ impl OpenOptionsConfig {
             create: parent_rights.create && self.create,
             append: parent_rights.append && self.append,
             truncate: parent_rights.truncate && self.truncate,
+            no_atime: self.no_atime,
         }
     }
 
@@ -221,6 +400,10 @@ impl OpenOptionsConfig {
         self.truncate
     }
 
+    pub const fn no_atime(&self) -> bool {
+        self.no_atime
+    }
+
     /// Would a file opened with this [`OpenOptionsConfig`] change files on the
     /// filesystem.
     pub const fn would_mutate(&self) -> bool {
@@ -231,6 +414,7 @@ impl OpenOptionsConfig {
             create,
             append,
             truncate,
+            no_atime: _,
         } = *self;
         append || write || create || create_new || truncate
     }
@@ -258,6 +442,7 @@ impl<'a> OpenOptions<'a> {
                 create: false,
                 append: false,
                 truncate: false,
+                no_atime: false,
             },
         }
     }
@@ -327,6 +512,13 @@ impl<'a> OpenOptions<'a> {
         self
     }
 
+    /// Sets the option to not update the access time of the file when it
+    /// is read (the equivalent of `O_NOATIME`).
+    pub fn no_atime(&mut self, no_atime: bool) -> &mut Self {
+        self.conf.no_atime = no_atime;
+        self
+    }
+
     pub fn open<P: AsRef<Path>>(
         &mut self,
         path: P,
@@ -398,6 +590,62 @@ pub trait VirtualFile:
         })
     }
 
+    /// Reads into `buf` from `offset` without moving the cursor of the file
+    /// (like `pread`). The default implementation seeks to `offset`, reads and
+    /// then seeks back, file systems that support positional reads should
+    /// override it.
+    fn read_at<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        offset: u64,
+    ) -> BoxFuture<'a, std::io::Result<usize>> {
+        Box::pin(async move {
+            let cursor = self.stream_position().await?;
+            self.seek(io::SeekFrom::Start(offset)).await?;
+            let res = self.read(buf).await;
+            self.seek(io::SeekFrom::Start(cursor)).await?;
+            res
+        })
+    }
+
+    /// Writes `buf` at `offset` without moving the cursor of the file (like
+    /// `pwrite`). The default implementation seeks to `offset`, writes and
+    /// then seeks back, file systems that support positional writes should
+    /// override it.
+    fn write_at<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+        offset: u64,
+    ) -> BoxFuture<'a, std::io::Result<usize>> {
+        Box::pin(async move {
+            let cursor = self.stream_position().await?;
+            self.seek(io::SeekFrom::Start(offset)).await?;
+            let res = self.write(buf).await;
+            self.seek(io::SeekFrom::Start(cursor)).await?;
+            res
+        })
+    }
+
+    /// Hints that the range starting at `offset` and extending for `len` bytes
+    /// is about to be read sequentially so that it can be loaded ahead of time,
+    /// the default implementation does nothing
+    fn readahead(&mut self, _offset: u64, _len: u64) -> BoxFuture<'_, std::io::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Advises how the `len` bytes starting at `offset` are going to be used
+    /// (see `posix_fadvise`), the default implementation ignores the advice
+    fn advise(&mut self, _offset: u64, _len: u64, _advice: Advice) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns the number of bytes that can be read right away, unlike
+    /// `poll_read_ready` this does not register for a wake up. The default
+    /// implementation reports that nothing is buffered
+    fn bytes_available(&self) -> usize {
+        0
+    }
+
     /// Polls the file for when there is data to be read
     fn poll_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>>;
 
@@ -428,6 +676,24 @@ impl<T: Any + fmt::Debug + 'static> Upcastable for T {
     }
 }
 
+/// How a range of a file is going to be accessed (see [`VirtualFile::advise`])
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Advice {
+    /// No particular access pattern
+    Normal,
+    /// The range is read sequentially from lower to higher offsets
+    Sequential,
+    /// The range is read in a random order
+    Random,
+    /// The range is going to be accessed in the near future
+    WillNeed,
+    /// The range is not going to be accessed in the near future, so any
+    /// cached pages of it can be dropped
+    DontNeed,
+    /// The range is accessed once and then not reused
+    NoReuse,
+}
+
 /// Determines the mode that stdio handlers will operate in
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum StdioMode {
@@ -525,10 +791,20 @@ pub enum FsError {
     /// Operation is not supported on this filesystem
     #[error("unsupported")]
     Unsupported,
+    /// Too many levels of symbolic links were followed
+    #[error("too many levels of symbolic links")]
+    SymlinkLoop,
 }
 
 impl From<io::Error> for FsError {
     fn from(io_error: io::Error) -> Self {
+        // Errors that were converted from an `FsError` are turned back as is
+        if let Some(err) = io_error
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<FsError>())
+        {
+            return *err;
+        }
         match io_error.kind() {
             io::ErrorKind::AddrInUse => FsError::AddressInUse,
             io::ErrorKind::AddrNotAvailable => FsError::AddressNotAvailable,
@@ -584,8 +860,10 @@ impl From<FsError> for io::Error {
             FsError::NoDevice => io::ErrorKind::Other,
             FsError::DirectoryNotEmpty => io::ErrorKind::Other,
             FsError::UnknownError => io::ErrorKind::Other,
-            FsError::StorageFull => io::ErrorKind::Other,
+            // Keeps the error around so that it survives a round trip
+            FsError::StorageFull => return io::Error::new(io::ErrorKind::Other, val),
             FsError::Unsupported => io::ErrorKind::Unsupported,
+            FsError::SymlinkLoop => return io::Error::new(io::ErrorKind::Other, val),
             // NOTE: Add this once the "io_error_more" Rust feature is stabilized
             // FsError::StorageFull => io::ErrorKind::StorageFull,
         };
@@ -642,6 +920,39 @@ impl DirEntry {
     }
 }
 
+/// Statistics about a file system, see [`FileSystem::statvfs`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FsStat {
+    /// Preferred size of a block of the file system
+    pub block_size: u64,
+    /// Total size of the file system in bytes
+    pub total_bytes: u64,
+    /// Number of bytes that are still free
+    pub free_bytes: u64,
+    /// Number of bytes that are still free for unprivileged users
+    pub available_bytes: u64,
+    /// Total number of inodes
+    pub total_inodes: u64,
+    /// Number of inodes that are still free
+    pub free_inodes: u64,
+}
+
+impl FsStat {
+    /// Size that is reported for file systems without a size limit, it is
+    /// large but leaves enough headroom that guests can safely do arithmetic
+    /// with it
+    pub const UNLIMITED: u64 = 1 << 60;
+}
+
+/// Options of [`FileSystem::copy_tree`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CopyTreeOpts {
+    /// Copies the access and modification times of the source entries
+    pub preserve_times: bool,
+    /// Copies what symbolic links point to rather than the links themselves
+    pub follow_symlinks: bool,
+}
+
 #[allow(clippy::len_without_is_empty)] // Clippy thinks it's an iterator.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 // TODO: review this, proper solution would probably use a trait object internally
@@ -746,3 +1057,155 @@ impl Iterator for ReadDir {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem_fs::FileSystem as MemFS;
+
+    /// Uses the default [`FileSystem::write_atomic`] but fails every rename,
+    /// as if the process was interrupted right before it
+    #[derive(Debug, Default)]
+    struct InterruptedFileSystem(MemFS);
+
+    impl FileSystem for InterruptedFileSystem {
+        fn readlink(&self, path: &Path) -> Result<PathBuf> {
+            self.0.readlink(path)
+        }
+        fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+            self.0.read_dir(path)
+        }
+        fn create_dir(&self, path: &Path) -> Result<()> {
+            self.0.create_dir(path)
+        }
+        fn remove_dir(&self, path: &Path) -> Result<()> {
+            self.0.remove_dir(path)
+        }
+        fn rename<'a>(&'a self, _from: &'a Path, _to: &'a Path) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async { Err(FsError::Interrupted) })
+        }
+        fn metadata(&self, path: &Path) -> Result<Metadata> {
+            self.0.metadata(path)
+        }
+        fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+            self.0.symlink_metadata(path)
+        }
+        fn remove_file(&self, path: &Path) -> Result<()> {
+            self.0.remove_file(path)
+        }
+        fn new_open_options(&self) -> OpenOptions {
+            self.0.new_open_options()
+        }
+        fn mount(
+            &self,
+            name: String,
+            path: &Path,
+            fs: Box<dyn FileSystem + Send + Sync>,
+        ) -> Result<()> {
+            FileSystem::mount(&self.0, name, path, fs)
+        }
+    }
+
+    fn entries(fs: &impl FileSystem, path: &str) -> Vec<PathBuf> {
+        fs.read_dir(Path::new(path))
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn write_atomic_interrupted_keeps_old_contents() {
+        let fs = InterruptedFileSystem::default();
+        ops::write(&fs.0, "/file.txt", b"old contents")
+            .await
+            .unwrap();
+
+        let result = fs
+            .write_atomic(Path::new("/file.txt"), b"new contents that never land")
+            .await;
+
+        assert_eq!(result, Err(FsError::Interrupted));
+        assert_eq!(
+            ops::read_to_string(&fs.0, "/file.txt").await.unwrap(),
+            "old contents"
+        );
+        assert_eq!(entries(&fs, "/"), vec![PathBuf::from("/file.txt")]);
+    }
+
+    #[tokio::test]
+    async fn write_atomic_interrupted_leaves_nothing() {
+        let fs = InterruptedFileSystem::default();
+
+        let result = fs
+            .write_atomic(Path::new("/file.txt"), b"new contents that never land")
+            .await;
+
+        assert_eq!(result, Err(FsError::Interrupted));
+        assert!(!ops::exists(&fs, "/file.txt"));
+        assert!(entries(&fs, "/").is_empty());
+    }
+
+    #[tokio::test]
+    async fn copy_file_default() {
+        let fs = MemFS::default();
+        ops::write(&fs, "/src.txt", b"some contents").await.unwrap();
+        ops::write(&fs, "/dst.txt", b"to be replaced by the copy")
+            .await
+            .unwrap();
+
+        let copied = fs
+            .copy_file(Path::new("/src.txt"), Path::new("/dst.txt"))
+            .await
+            .unwrap();
+
+        assert_eq!(copied, 13);
+        assert_eq!(
+            ops::read_to_string(&fs, "/dst.txt").await.unwrap(),
+            "some contents"
+        );
+        assert_eq!(
+            fs.copy_file(Path::new("/src.txt"), Path::new("/src.txt"))
+                .await,
+            Err(FsError::InvalidInput)
+        );
+        assert_eq!(
+            fs.copy_file(Path::new("/missing.txt"), Path::new("/dst.txt"))
+                .await,
+            Err(FsError::EntryNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn copy_tree_default() {
+        let fs = MemFS::default();
+        ops::create_dir_all(&fs, "/src/a/b").unwrap();
+        ops::write(&fs, "/src/top.txt", b"top").await.unwrap();
+        ops::write(&fs, "/src/a/b/deep.txt", b"deep").await.unwrap();
+
+        fs.copy_tree(
+            Path::new("/src"),
+            Path::new("/dst"),
+            CopyTreeOpts::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            entries(&fs, "/dst"),
+            vec![PathBuf::from("/dst/a"), PathBuf::from("/dst/top.txt")]
+        );
+        assert_eq!(
+            ops::read_to_string(&fs, "/dst/top.txt").await.unwrap(),
+            "top"
+        );
+        assert_eq!(
+            ops::read_to_string(&fs, "/dst/a/b/deep.txt").await.unwrap(),
+            "deep"
+        );
+        // The source is left untouched
+        assert_eq!(
+            ops::read_to_string(&fs, "/src/top.txt").await.unwrap(),
+            "top"
+        );
+    }
+}
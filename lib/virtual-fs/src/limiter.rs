@@ -8,6 +8,12 @@ pub use self::tracked_vec::TrackedVec;
 pub trait FsMemoryLimiter: Send + Sync + std::fmt::Debug {
     fn on_grow(&self, grown_bytes: usize) -> std::result::Result<(), FsError>;
     fn on_shrink(&self, shrunk_bytes: usize);
+    /// Maximum number of bytes that the file system may use, if the limiter
+    /// enforces one (this is what [`crate::FileSystem::statvfs`] reports as
+    /// the size of the file system)
+    fn limit(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub type DynFsMemoryLimiter = Arc<dyn FsMemoryLimiter + Send + Sync>;
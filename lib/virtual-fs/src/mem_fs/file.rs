@@ -198,6 +198,7 @@ impl VirtualFile for FileHandle {
 
     fn set_len(&mut self, new_size: u64) -> Result<()> {
         let mut fs = self.filesystem.inner.write().map_err(|_| FsError::Lock)?;
+        fs.check_file_capacity(self.inode, |_| new_size)?;
 
         let inode = fs.storage.get_mut(self.inode);
         match inode {
@@ -482,6 +483,7 @@ mod test_virtual_file {
     use crate::{mem_fs::*, FileSystem as FS};
     use std::thread::sleep;
     use std::time::Duration;
+    use tokio::io::AsyncReadExt;
 
     macro_rules! path {
         ($path:expr) => {
@@ -489,6 +491,21 @@ mod test_virtual_file {
         };
     }
 
+    #[tokio::test]
+    async fn test_advise_is_a_no_op() {
+        let fs = FileSystem::default();
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to create a new file");
+
+        assert!(file.advise(0, 0, crate::Advice::DontNeed).is_ok());
+        assert!(file.advise(0, 16, crate::Advice::Random).is_ok());
+    }
+
     #[tokio::test]
     async fn test_last_accessed() {
         let fs = FileSystem::default();
@@ -518,6 +535,50 @@ mod test_virtual_file {
         );
     }
 
+    #[tokio::test]
+    async fn test_no_atime() {
+        let fs = FileSystem::default();
+
+        let file = fs
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to create a new file");
+        let created_accessed_time = file.last_accessed();
+
+        sleep(Duration::from_millis(10));
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .no_atime(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to open a file");
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).await.unwrap();
+
+        assert_eq!(
+            file.last_accessed(),
+            created_accessed_time,
+            "the last accessed time is not updated"
+        );
+
+        sleep(Duration::from_millis(10));
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to open a file");
+        file.read_to_end(&mut buf).await.unwrap();
+
+        assert!(
+            file.last_accessed() > created_accessed_time,
+            "the last accessed time is updated"
+        );
+    }
+
     #[tokio::test]
     async fn test_last_modified() {
         let fs = FileSystem::default();
@@ -885,20 +946,34 @@ impl AsyncWrite for FileHandle {
             )));
         }
 
+        // Appends always go to the current end of the file, which is looked
+        // up while holding the lock of the file system so that concurrent
+        // appends from other handles can not overwrite each other
+        let append_mode = self.append_mode;
         let mut cursor = self.cursor;
         let bytes_written = {
             let mut fs = self.filesystem.inner.write().map_err(|_| {
                 io::Error::new(io::ErrorKind::Other, "failed to acquire a write lock")
             })?;
+            fs.check_file_capacity(self.inode, |len| {
+                let start = if append_mode { len } else { cursor };
+                len.max(start + buf.len() as u64)
+            })?;
 
             let inode = fs.storage.get_mut(self.inode);
             match inode {
                 Some(Node::File(node)) => {
+                    if append_mode {
+                        cursor = node.file.len() as u64;
+                    }
                     let bytes_written = node.file.write(buf, &mut cursor)?;
                     node.metadata.len = node.file.len().try_into().unwrap();
                     bytes_written
                 }
                 Some(Node::OffloadedFile(node)) => {
+                    if append_mode {
+                        cursor = node.file.len();
+                    }
                     let bytes_written = node.file.write(OffloadWrite::Buffer(buf), &mut cursor)?;
                     node.metadata.len = node.file.len();
                     bytes_written
@@ -910,9 +985,12 @@ impl AsyncWrite for FileHandle {
                 }
                 Some(Node::CustomFile(node)) => {
                     let mut guard = node.file.lock().unwrap();
+                    if append_mode {
+                        cursor = guard.size();
+                    }
 
                     let file = Pin::new(guard.as_mut());
-                    if let Err(err) = file.start_seek(io::SeekFrom::Start(self.cursor)) {
+                    if let Err(err) = file.start_seek(io::SeekFrom::Start(cursor)) {
                         return Poll::Ready(Err(err));
                     }
 
@@ -961,11 +1039,18 @@ impl AsyncWrite for FileHandle {
         cx: &mut Context<'_>,
         bufs: &[io::IoSlice<'_>],
     ) -> Poll<io::Result<usize>> {
+        let append_mode = self.append_mode;
         let mut cursor = self.cursor;
         let ret = {
             let mut fs = self.filesystem.inner.write().map_err(|_| {
                 io::Error::new(io::ErrorKind::Other, "failed to acquire a write lock")
             })?;
+            // Only the first non-empty buffer is written
+            let buf_len = bufs.iter().find(|b| !b.is_empty()).map_or(0, |b| b.len());
+            fs.check_file_capacity(self.inode, |len| {
+                let start = if append_mode { len } else { cursor };
+                len.max(start + buf_len as u64)
+            })?;
 
             let inode = fs.storage.get_mut(self.inode);
             match inode {
@@ -974,6 +1059,9 @@ impl AsyncWrite for FileHandle {
                         .iter()
                         .find(|b| !b.is_empty())
                         .map_or(&[][..], |b| &**b);
+                    if append_mode {
+                        cursor = node.file.len() as u64;
+                    }
                     let bytes_written = node.file.write(buf, &mut cursor)?;
                     node.metadata.len = node.file.buffer.len() as u64;
                     Poll::Ready(Ok(bytes_written))
@@ -983,6 +1071,9 @@ impl AsyncWrite for FileHandle {
                         .iter()
                         .find(|b| !b.is_empty())
                         .map_or(&[][..], |b| &**b);
+                    if append_mode {
+                        cursor = node.file.len();
+                    }
                     let bytes_written = node.file.write(OffloadWrite::Buffer(buf), &mut cursor)?;
                     node.metadata.len = node.file.len();
                     Poll::Ready(Ok(bytes_written))
@@ -1406,6 +1497,86 @@ mod test_read_write_seek {
             "failing to read an exact buffer",
         );
     }
+
+    #[tokio::test]
+    async fn test_positional_reads_and_writes_keep_the_cursor() {
+        let fs = FileSystem::default();
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to create a new file");
+        file.write_all(b"foobarbaz").await.unwrap();
+        file.seek(io::SeekFrom::Start(3)).await.unwrap();
+
+        let mut buf = [0u8; 3];
+        assert_eq!(file.read_at(&mut buf, 6).await.unwrap(), 3);
+        assert_eq!(&buf, b"baz");
+        assert_eq!(file.write_at(b"qux", 0).await.unwrap(), 3);
+
+        let mut string = String::new();
+        file.read_to_string(&mut string).await.unwrap();
+        assert_eq!(string, "barbaz");
+        file.seek(io::SeekFrom::Start(0)).await.unwrap();
+        string.clear();
+        file.read_to_string(&mut string).await.unwrap();
+        assert_eq!(string, "quxbarbaz");
+    }
+
+    #[test]
+    fn test_concurrent_appends_from_several_handles() {
+        const THREADS: usize = 8;
+        const RECORDS: usize = 64;
+
+        let fs = FileSystem::default();
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/log"))
+            .expect("failed to create a new file");
+
+        let threads = (0..THREADS)
+            .map(|thread| {
+                let fs = fs.clone();
+                std::thread::spawn(move || {
+                    let mut file = fs
+                        .new_open_options()
+                        .write(true)
+                        .append(true)
+                        .open(path!("/log"))
+                        .expect("failed to open the file for appending");
+                    for record in 0..RECORDS {
+                        let record = format!("[{thread:02}:{record:04}]\n");
+                        let written =
+                            futures::executor::block_on(file.write(record.as_bytes())).unwrap();
+                        assert_eq!(written, record.len());
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open(path!("/log"))
+            .unwrap();
+        let mut contents = String::new();
+        futures::executor::block_on(file.read_to_string(&mut contents)).unwrap();
+
+        // Every record is intact and appears exactly once
+        let mut records = contents.split_terminator('\n').collect::<Vec<_>>();
+        records.sort_unstable();
+        let expected = (0..THREADS)
+            .flat_map(|thread| (0..RECORDS).map(move |record| format!("[{thread:02}:{record:04}]")))
+            .collect::<Vec<_>>();
+        assert_eq!(records, expected);
+    }
 }
 
 impl fmt::Debug for FileHandle {
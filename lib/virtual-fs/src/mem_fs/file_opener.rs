@@ -335,6 +335,7 @@ impl crate::FileOpener for FileSystem {
         let mut truncate = conf.truncate();
         let mut create = conf.create();
         let create_new = conf.create_new();
+        let no_atime = conf.no_atime();
 
         // If `create_new` is used, `create` and `truncate ` are ignored.
         if create_new {
@@ -388,7 +389,9 @@ impl crate::FileOpener for FileSystem {
                 match inode {
                     Some(Node::File(FileNode { metadata, file, .. })) => {
                         // Update the accessed time.
-                        metadata.accessed = time();
+                        if !no_atime {
+                            metadata.accessed = time();
+                        }
 
                         // Truncate if needed.
                         if truncate {
@@ -404,7 +407,9 @@ impl crate::FileOpener for FileSystem {
 
                     Some(Node::OffloadedFile(OffloadedFileNode { metadata, file, .. })) => {
                         // Update the accessed time.
-                        metadata.accessed = time();
+                        if !no_atime {
+                            metadata.accessed = time();
+                        }
 
                         // Truncate if needed.
                         if truncate {
@@ -420,7 +425,9 @@ impl crate::FileOpener for FileSystem {
 
                     Some(Node::ReadOnlyFile(node)) => {
                         // Update the accessed time.
-                        node.metadata.accessed = time();
+                        if !no_atime {
+                            node.metadata.accessed = time();
+                        }
 
                         // Truncate if needed.
                         if truncate || append {
@@ -430,7 +437,9 @@ impl crate::FileOpener for FileSystem {
 
                     Some(Node::CustomFile(node)) => {
                         // Update the accessed time.
-                        node.metadata.accessed = time();
+                        if !no_atime {
+                            node.metadata.accessed = time();
+                        }
 
                         // Truncate if needed.
                         let mut file = node.file.lock().unwrap();
@@ -447,7 +456,9 @@ impl crate::FileOpener for FileSystem {
 
                     Some(Node::ArcFile(node)) => {
                         // Update the accessed time.
-                        node.metadata.accessed = time();
+                        if !no_atime {
+                            node.metadata.accessed = time();
+                        }
 
                         let mut file = node
                             .fs
@@ -458,6 +469,7 @@ impl crate::FileOpener for FileSystem {
                             .truncate(truncate)
                             .create(create)
                             .create_new(create_new)
+                            .no_atime(no_atime)
                             .open(node.path.as_path())?;
 
                         // Truncate if needed.
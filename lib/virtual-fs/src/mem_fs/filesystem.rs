@@ -3,7 +3,7 @@
 use self::offloaded_file::OffloadBackingStore;
 
 use super::*;
-use crate::{DirEntry, FileType, FsError, Metadata, OpenOptions, ReadDir, Result};
+use crate::{DirEntry, FileType, FsError, FsStat, Metadata, OpenOptions, ReadDir, Result};
 use futures::future::{BoxFuture, Either};
 use slab::Slab;
 use std::collections::VecDeque;
@@ -27,6 +27,13 @@ impl FileSystem {
         self.inner.write().unwrap().limiter = Some(limiter);
     }
 
+    /// Caps the total size of the data of all the files of this file system
+    /// to `capacity` bytes, writes and resizes that would go beyond it fail
+    /// with [`FsError::StorageFull`]. `None` removes the cap.
+    pub fn set_capacity(&self, capacity: Option<u64>) {
+        self.inner.write().unwrap().capacity = capacity;
+    }
+
     pub fn new_open_options_ext(&self) -> &FileSystem {
         self
     }
@@ -51,6 +58,27 @@ impl FileSystem {
         lock.canonicalize_without_inode(path)
     }
 
+    /// Returns `true` when both paths live on the same file system, which
+    /// is not the case when only one of them is below a mount point or when
+    /// they are below mount points of different file systems.
+    pub fn is_same_mount(&self, a: &Path, b: &Path) -> Result<bool> {
+        let lock = self.inner.read().map_err(|_| FsError::Lock)?;
+        let mount_of = |path: &Path| -> Result<Option<Arc<dyn crate::FileSystem + Send + Sync>>> {
+            let path = lock.canonicalize_without_inode(path)?;
+            let parent = path.parent().unwrap_or(&path);
+            Ok(match lock.inode_of_parent(parent)? {
+                InodeResolution::Found(_) => None,
+                InodeResolution::Redirect(fs, _) => Some(fs),
+            })
+        };
+
+        Ok(match (mount_of(a)?, mount_of(b)?) {
+            (None, None) => true,
+            (Some(a), Some(b)) => std::ptr::addr_eq(Arc::as_ptr(&a), Arc::as_ptr(&b)),
+            _ => false,
+        })
+    }
+
     /// Merge all items from a given source path (directory) of a different file
     /// system into this file system.
     ///
@@ -284,7 +312,7 @@ impl crate::FileSystem for FileSystem {
 
         // Check it's a directory and fetch the immediate children as `DirEntry`.
         let inode = guard.storage.get(inode_of_directory);
-        let children = match inode {
+        let mut children: Vec<DirEntry> = match inode {
             Some(Node::Directory(DirectoryNode { children, .. })) => children
                 .iter()
                 .filter_map(|inode| guard.storage.get(*inode))
@@ -306,11 +334,16 @@ impl crate::FileSystem for FileSystem {
             _ => return Err(FsError::InvalidInput),
         };
 
+        // Entries are returned in lexical order of their names so that the
+        // listing does not depend on the order in which they were created.
+        children.sort_by(|a, b| a.path.cmp(&b.path));
+
         Ok(ReadDir::new(children))
     }
 
     fn create_dir(&self, path: &Path) -> Result<()> {
-        if self.read_dir(path).is_ok() {
+        // Anything that already exists at this path (file or directory) is a conflict
+        if self.metadata(path).is_ok() {
             return Err(FsError::AlreadyExists);
         }
 
@@ -344,7 +377,7 @@ impl crate::FileSystem for FileSystem {
             (inode_of_parent, name_of_directory)
         };
 
-        if self.read_dir(path).is_ok() {
+        if self.metadata(path).is_ok() {
             return Err(FsError::AlreadyExists);
         }
 
@@ -446,6 +479,63 @@ impl crate::FileSystem for FileSystem {
         Ok(())
     }
 
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        // Write lock, the whole subtree is removed in one go.
+        let mut fs = self.inner.write().map_err(|_| FsError::Lock)?;
+
+        // Canonicalize the path.
+        let path = fs.canonicalize_without_inode(path)?;
+
+        // Check the path has a parent.
+        let parent_of_path = path.parent().ok_or(FsError::BaseNotDirectory)?;
+
+        // Check the directory name.
+        let name_of_directory = path
+            .file_name()
+            .ok_or(FsError::InvalidInput)?
+            .to_os_string();
+
+        // Find the parent inode.
+        let inode_of_parent = match fs.inode_of_parent(parent_of_path)? {
+            InodeResolution::Found(a) => a,
+            InodeResolution::Redirect(fs_of_parent, mut parent_path) => {
+                drop(fs);
+                parent_path.push(name_of_directory);
+                return fs_of_parent.remove_dir_all(parent_path.as_path());
+            }
+        };
+
+        // Find the directory in its parent. A mounted directory is only
+        // unlinked, its content is never traversed.
+        let (position, inode_of_directory) = match fs.storage.get(inode_of_parent) {
+            Some(Node::Directory(DirectoryNode { children, .. })) => children
+                .iter()
+                .enumerate()
+                .filter_map(|(nth, inode)| fs.storage.get(*inode).map(|node| (nth, node)))
+                .find(|(_, node)| node.name() == name_of_directory.as_os_str())
+                .map(|(nth, node)| match node {
+                    Node::Directory(_) | Node::ArcDirectory(_) => Ok((nth, node.inode())),
+                    _ => Err(FsError::BaseNotDirectory),
+                })
+                .ok_or(FsError::EntryNotFound)??,
+            _ => return Err(FsError::BaseNotDirectory),
+        };
+
+        // Remove every node of the subtree from the storage.
+        let mut pending = vec![inode_of_directory];
+        while let Some(inode) = pending.pop() {
+            if let Some(Node::Directory(DirectoryNode { children, .. })) = fs.storage.get(inode) {
+                pending.extend(children.iter().copied());
+            }
+            fs.storage.remove(inode);
+        }
+
+        // Remove the child from the parent directory.
+        fs.remove_child_from_node(inode_of_parent, position)?;
+
+        Ok(())
+    }
+
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>> {
         Box::pin(async move {
             let name_of_to;
@@ -605,6 +695,15 @@ impl crate::FileSystem for FileSystem {
         })
     }
 
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match self.write_atomic_locked(path, contents)? {
+                InodeResolution::Found(_) => Ok(()),
+                InodeResolution::Redirect(fs, path) => fs.write_atomic(&path, contents).await,
+            }
+        })
+    }
+
     fn metadata(&self, path: &Path) -> Result<Metadata> {
         // Read lock.
         let guard = self.inner.read().map_err(|_| FsError::Lock)?;
@@ -622,6 +721,59 @@ impl crate::FileSystem for FileSystem {
         }
     }
 
+    fn statvfs(&self, path: &Path) -> Result<FsStat> {
+        // Read lock.
+        let guard = self.inner.read().map_err(|_| FsError::Lock)?;
+        if let InodeResolution::Redirect(fs, path) = guard.inode_of(path)? {
+            drop(guard);
+            return fs.statvfs(path.as_path());
+        }
+
+        let used_bytes = guard.used_bytes();
+        let total_bytes = guard
+            .limiter
+            .as_ref()
+            .and_then(|limiter| limiter.limit())
+            .map(|limit| limit as u64)
+            .unwrap_or(FsStat::UNLIMITED)
+            .min(guard.capacity.unwrap_or(FsStat::UNLIMITED));
+        let free_bytes = total_bytes.saturating_sub(used_bytes);
+
+        Ok(FsStat {
+            block_size: 4096,
+            total_bytes,
+            free_bytes,
+            available_bytes: free_bytes,
+            total_inodes: FsStat::UNLIMITED,
+            free_inodes: FsStat::UNLIMITED - guard.storage.len() as u64,
+        })
+    }
+
+    fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        // Write lock.
+        let mut guard = self.inner.write().map_err(|_| FsError::Lock)?;
+        match guard.inode_of(path)? {
+            InodeResolution::Found(inode) => {
+                let metadata = guard
+                    .storage
+                    .get_mut(inode)
+                    .ok_or(FsError::UnknownError)?
+                    .metadata_mut();
+                if let Some(atime) = atime {
+                    metadata.accessed = atime;
+                }
+                if let Some(mtime) = mtime {
+                    metadata.modified = mtime;
+                }
+                Ok(())
+            }
+            InodeResolution::Redirect(fs, path) => {
+                drop(guard);
+                fs.set_times(path.as_path(), atime, mtime)
+            }
+        }
+    }
+
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         // Read lock.
         let guard = self.inner.read().map_err(|_| FsError::Lock)?;
@@ -711,6 +863,89 @@ impl crate::FileSystem for FileSystem {
     }
 }
 
+impl FileSystem {
+    /// Replaces a file with a new one holding `contents` while the write lock
+    /// is held, so that no one can observe a partially written file. When the
+    /// file lives on a mounted file system the redirect is returned instead.
+    fn write_atomic_locked(&self, path: &Path, contents: &[u8]) -> Result<InodeResolution> {
+        // Write lock.
+        let mut fs = self.inner.write().map_err(|_| FsError::Lock)?;
+
+        // Canonicalize the path.
+        let path = fs.canonicalize_without_inode(path)?;
+
+        // Check the path has a parent.
+        let parent_of_path = path.parent().ok_or(FsError::BaseNotDirectory)?;
+
+        // Check the file name.
+        let name_of_file = path
+            .file_name()
+            .ok_or(FsError::InvalidInput)?
+            .to_os_string();
+
+        // Find the parent inode.
+        let inode_of_parent = match fs.inode_of_parent(parent_of_path)? {
+            InodeResolution::Found(a) => a,
+            InodeResolution::Redirect(fs, mut parent_path) => {
+                parent_path.push(name_of_file);
+                return Ok(InodeResolution::Redirect(fs, parent_path));
+            }
+        };
+
+        // Find the entry that is going to be replaced, if any.
+        let existing = match fs.as_parent_get_position_and_inode(inode_of_parent, &name_of_file)? {
+            Some((position, InodeResolution::Found(inode))) => {
+                if let Some(Node::Directory(_) | Node::ArcDirectory(_)) = fs.storage.get(inode) {
+                    return Err(FsError::NotAFile);
+                }
+                Some((position, inode))
+            }
+            Some((_, redirect @ InodeResolution::Redirect(..))) => return Ok(redirect),
+            None => None,
+        };
+
+        // The data of the replaced file is given back
+        let replaced_bytes = match existing.and_then(|(_, inode)| fs.storage.get(inode)) {
+            Some(Node::File(FileNode { file, .. })) => file.len() as u64,
+            Some(Node::OffloadedFile(OffloadedFileNode { file, .. })) => file.len(),
+            _ => 0,
+        };
+        fs.check_capacity(replaced_bytes, contents.len() as u64)?;
+
+        // Fill the new file before anything is changed, if this fails (for
+        // instance because of the memory limiter) the old file is untouched.
+        let mut file = File::new(fs.limiter.clone());
+        file.write(contents, &mut 0)?;
+
+        // Swap the old entry for the new file.
+        if let Some((position, inode)) = existing {
+            fs.storage.remove(inode);
+            fs.remove_child_from_node(inode_of_parent, position)?;
+        }
+
+        let time = time();
+        let inode_of_file = fs.storage.vacant_entry().key();
+        fs.storage.insert(Node::File(FileNode {
+            inode: inode_of_file,
+            name: name_of_file,
+            file,
+            metadata: Metadata {
+                ft: FileType {
+                    file: true,
+                    ..Default::default()
+                },
+                accessed: time,
+                created: time,
+                modified: time,
+                len: contents.len() as u64,
+            },
+        }));
+        fs.add_child_to_node(inode_of_parent, inode_of_file)?;
+
+        Ok(InodeResolution::Found(inode_of_file))
+    }
+}
+
 impl fmt::Debug for FileSystem {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         let fs: &FileSystemInner = &self.inner.read().unwrap();
@@ -725,6 +960,7 @@ pub(super) struct FileSystemInner {
     pub(super) storage: Slab<Node>,
     pub(super) backing_offload: Option<OffloadBackingStore>,
     pub(super) limiter: Option<crate::limiter::DynFsMemoryLimiter>,
+    pub(super) capacity: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -746,6 +982,51 @@ impl InodeResolution {
 }
 
 impl FileSystemInner {
+    /// The number of bytes used by the data of the files, only the data held
+    /// in memory counts against the limits.
+    pub(super) fn used_bytes(&self) -> u64 {
+        self.storage
+            .iter()
+            .map(|(_, node)| match node {
+                Node::File(FileNode { file, .. }) => file.len() as u64,
+                Node::OffloadedFile(OffloadedFileNode { file, .. }) => file.len(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Fails with [`FsError::StorageFull`] when replacing `freed_bytes` of
+    /// file data with `needed_bytes` would go beyond the capacity.
+    pub(super) fn check_capacity(&self, freed_bytes: u64, needed_bytes: u64) -> Result<()> {
+        match self.capacity {
+            Some(capacity)
+                if needed_bytes > freed_bytes
+                    && self.used_bytes().saturating_sub(freed_bytes) + needed_bytes > capacity =>
+            {
+                Err(FsError::StorageFull)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the file behind `inode` can be resized, `new_len` gets the
+    /// current length of the file and returns the length after the change.
+    pub(super) fn check_file_capacity(
+        &self,
+        inode: Inode,
+        new_len: impl FnOnce(u64) -> u64,
+    ) -> Result<()> {
+        if self.capacity.is_none() {
+            return Ok(());
+        }
+        let len = match self.storage.get(inode) {
+            Some(Node::File(FileNode { file, .. })) => file.len() as u64,
+            Some(Node::OffloadedFile(OffloadedFileNode { file, .. })) => file.len(),
+            _ => return Ok(()),
+        };
+        self.check_capacity(len, new_len(len))
+    }
+
     /// Get the inode associated to a path if it exists.
     pub(super) fn inode_of(&self, path: &Path) -> Result<InodeResolution> {
         // SAFETY: The root node always exists, so it's safe to unwrap here.
@@ -1129,6 +1410,7 @@ impl Default for FileSystemInner {
             storage: slab,
             backing_offload: None,
             limiter: None,
+            capacity: None,
         }
     }
 }
@@ -1153,9 +1435,9 @@ impl DirectoryMustBeEmpty {
 mod test_filesystem {
     use std::{borrow::Cow, path::Path};
 
-    use tokio::io::AsyncReadExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-    use crate::{mem_fs::*, ops, DirEntry, FileSystem as FS, FileType, FsError};
+    use crate::{mem_fs::*, ops, DirEntry, FileSystem as FS, FileType, FsError, VirtualFile};
 
     macro_rules! path {
         ($path:expr) => {
@@ -1282,6 +1564,26 @@ mod test_filesystem {
                 "the new directory is well-defined",
             );
         }
+
+        assert_eq!(
+            fs.create_dir(path!("/foo/bar")),
+            Err(FsError::AlreadyExists),
+            "creating a directory that already exists",
+        );
+
+        assert!(
+            fs.new_open_options()
+                .write(true)
+                .create_new(true)
+                .open(path!("/foo/baz.txt"))
+                .is_ok(),
+            "creating a new file (`baz.txt`)",
+        );
+        assert_eq!(
+            fs.create_dir(path!("/foo/baz.txt")),
+            Err(FsError::AlreadyExists),
+            "creating a directory over an existing file",
+        );
     }
 
     #[tokio::test]
@@ -1341,6 +1643,54 @@ mod test_filesystem {
         }
     }
 
+    #[tokio::test]
+    async fn test_remove_dir_all() {
+        let fs = FileSystem::default();
+
+        let outside = FileSystem::default();
+        outside.create_dir(path!("/kept")).unwrap();
+        let outside: Arc<dyn crate::FileSystem + Send + Sync> = Arc::new(outside);
+
+        assert_eq!(fs.create_dir(path!("/tree")), Ok(()));
+        assert_eq!(fs.create_dir(path!("/tree/a")), Ok(()));
+        assert_eq!(fs.create_dir(path!("/tree/a/b")), Ok(()));
+        for file in [path!("/tree/file.txt"), path!("/tree/a/b/deep.txt")] {
+            fs.new_open_options()
+                .write(true)
+                .create_new(true)
+                .open(file)
+                .unwrap();
+        }
+        fs.mount(path!(buf "/tree/link"), &outside, path!(buf "/"))
+            .unwrap();
+
+        assert_eq!(
+            fs.remove_dir_all(path!("/tree/file.txt")),
+            Err(FsError::BaseNotDirectory),
+            "removing a file",
+        );
+
+        assert_eq!(
+            fs.remove_dir_all(path!("/tree")),
+            Ok(()),
+            "removing a directory that has children",
+        );
+
+        assert_eq!(
+            fs.metadata(path!("/tree")),
+            Err(FsError::EntryNotFound),
+            "the whole subtree is gone",
+        );
+        {
+            let fs_inner = fs.inner.read().unwrap();
+            assert_eq!(fs_inner.storage.len(), 1, "storage only has the root");
+        }
+        assert!(
+            outside.metadata(path!("/kept")).is_ok(),
+            "the content of a mounted directory survives",
+        );
+    }
+
     #[tokio::test]
     async fn test_rename() {
         let fs = FileSystem::default();
@@ -1581,6 +1931,59 @@ mod test_filesystem {
         }
     }
 
+    #[tokio::test]
+    async fn test_write_atomic() {
+        let fs = FileSystem::default();
+        assert_eq!(fs.create_dir(path!("/foo")), Ok(()));
+
+        assert_eq!(
+            fs.write_atomic(path!("/foo/bar.txt"), b"first").await,
+            Ok(()),
+            "writing a new file",
+        );
+        assert_eq!(
+            ops::read_to_string(&fs, "/foo/bar.txt").await.unwrap(),
+            "first"
+        );
+
+        assert_eq!(
+            fs.write_atomic(path!("/foo/bar.txt"), b"second and longer")
+                .await,
+            Ok(()),
+            "replacing an existing file",
+        );
+        assert_eq!(
+            ops::read_to_string(&fs, "/foo/bar.txt").await.unwrap(),
+            "second and longer"
+        );
+        assert_eq!(
+            fs.metadata(path!("/foo/bar.txt")).unwrap().len,
+            "second and longer".len() as u64
+        );
+
+        let entries: Vec<_> = fs
+            .read_dir(path!("/foo"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect();
+        assert_eq!(
+            entries,
+            vec![path!(buf "/foo/bar.txt")],
+            "the file is replaced and nothing is left behind",
+        );
+
+        assert_eq!(
+            fs.write_atomic(path!("/foo"), b"data").await,
+            Err(FsError::NotAFile),
+            "a directory can not be replaced",
+        );
+        assert_eq!(
+            fs.write_atomic(path!("/baz/bar.txt"), b"data").await,
+            Err(FsError::EntryNotFound),
+            "the parent directory must exist",
+        );
+    }
+
     #[tokio::test]
     async fn test_metadata() {
         use std::thread::sleep;
@@ -1768,7 +2171,7 @@ mod test_filesystem {
                     path,
                     metadata: Ok(Metadata { ft, .. }),
                 }))
-                    if path == path!(buf "/foo") && ft.is_dir()
+                    if path == path!(buf "/a.txt") && ft.is_file()
             ),
             "checking entry #1",
         );
@@ -1779,7 +2182,7 @@ mod test_filesystem {
                     path,
                     metadata: Ok(Metadata { ft, .. }),
                 }))
-                    if path == path!(buf "/bar") && ft.is_dir()
+                    if path == path!(buf "/b.txt") && ft.is_file()
             ),
             "checking entry #2",
         );
@@ -1790,7 +2193,7 @@ mod test_filesystem {
                     path,
                     metadata: Ok(Metadata { ft, .. }),
                 }))
-                    if path == path!(buf "/baz") && ft.is_dir()
+                    if path == path!(buf "/bar") && ft.is_dir()
             ),
             "checking entry #3",
         );
@@ -1801,7 +2204,7 @@ mod test_filesystem {
                     path,
                     metadata: Ok(Metadata { ft, .. }),
                 }))
-                    if path == path!(buf "/a.txt") && ft.is_file()
+                    if path == path!(buf "/baz") && ft.is_dir()
             ),
             "checking entry #4",
         );
@@ -1812,13 +2215,39 @@ mod test_filesystem {
                     path,
                     metadata: Ok(Metadata { ft, .. }),
                 }))
-                    if path == path!(buf "/b.txt") && ft.is_file()
+                    if path == path!(buf "/foo") && ft.is_dir()
             ),
             "checking entry #5",
         );
         assert!(readdir.next().is_none(), "no more entries");
     }
 
+    #[tokio::test]
+    async fn test_readdir_is_sorted() {
+        let fs = FileSystem::default();
+
+        for name in ["delta", "alpha", "echo", "charlie", "bravo"] {
+            let path = path!(buf format!("/{name}"));
+            assert!(
+                fs.new_open_options()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .is_ok(),
+                "creating `{name}`",
+            );
+        }
+
+        for _ in 0..3 {
+            let names = fs
+                .read_dir(path!("/"))
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["alpha", "bravo", "charlie", "delta", "echo"]);
+        }
+    }
+
     #[tokio::test]
     async fn test_canonicalize() {
         let fs = FileSystem::default();
@@ -1970,4 +2399,106 @@ mod test_filesystem {
 
         assert_eq!(buf, b"a");
     }
+
+    #[tokio::test]
+    async fn test_statvfs() {
+        #[derive(Debug)]
+        struct FixedLimit(usize);
+
+        impl crate::limiter::FsMemoryLimiter for FixedLimit {
+            fn on_grow(&self, _grown_bytes: usize) -> std::result::Result<(), FsError> {
+                Ok(())
+            }
+            fn on_shrink(&self, _shrunk_bytes: usize) {}
+            fn limit(&self) -> Option<usize> {
+                Some(self.0)
+            }
+        }
+
+        let fs = FileSystem::default();
+        let stat = fs.statvfs(path!("/")).unwrap();
+        assert_eq!(stat.total_bytes, crate::FsStat::UNLIMITED);
+        assert_eq!(stat.free_bytes, crate::FsStat::UNLIMITED);
+
+        let limit = 1024 * 1024;
+        fs.set_memory_limiter(std::sync::Arc::new(FixedLimit(limit)));
+        fs.write_atomic(path!("/foo.txt"), &[1u8; 1000])
+            .await
+            .unwrap();
+
+        let stat = fs.statvfs(path!("/foo.txt")).unwrap();
+        assert_eq!(stat.total_bytes, limit as u64);
+        assert_eq!(stat.free_bytes, limit as u64 - 1000);
+        assert_eq!(stat.available_bytes, stat.free_bytes);
+
+        assert_eq!(
+            fs.statvfs(path!("/bar.txt")),
+            Err(FsError::EntryNotFound),
+            "statvfs of a missing entry",
+        );
+    }
+
+    #[tokio::test]
+    async fn test_capacity() {
+        let fs = FileSystem::default();
+        fs.set_capacity(Some(100));
+
+        let open = |path: &str| {
+            fs.new_open_options()
+                .write(true)
+                .create(true)
+                .open(path!(path))
+                .unwrap()
+        };
+
+        // The limit is shared by all the files
+        let mut a = open("/a.txt");
+        a.write_all(&[1u8; 60]).await.unwrap();
+        let mut b = open("/b.txt");
+        b.write_all(&[2u8; 40]).await.unwrap();
+        assert_eq!(fs.statvfs(path!("/")).unwrap().free_bytes, 0);
+
+        let err = b.write_all(&[3u8; 1]).await.unwrap_err();
+        assert_eq!(FsError::from(err), FsError::StorageFull);
+        assert_eq!(b.set_len(41), Err(FsError::StorageFull));
+        assert_eq!(
+            fs.write_atomic(path!("/c.txt"), &[4u8; 1]).await,
+            Err(FsError::StorageFull)
+        );
+
+        // Shrinking and overwriting within the file are fine
+        b.set_len(30).unwrap();
+        b.write_all(&[5u8; 10]).await.unwrap();
+        assert_eq!(b.size(), 40);
+
+        // Removing a file gives its space back
+        drop(a);
+        fs.remove_file(path!("/a.txt")).unwrap();
+        fs.write_atomic(path!("/c.txt"), &[4u8; 60]).await.unwrap();
+        b.set_len(0).unwrap();
+        b.write_all(&[6u8; 40]).await.unwrap();
+        assert_eq!(fs.statvfs(path!("/")).unwrap().free_bytes, 0);
+    }
+
+    #[test]
+    fn test_set_times() {
+        let fs = FileSystem::default();
+        fs.create_dir(path!("/foo")).unwrap();
+        fs.set_times(path!("/foo"), Some(1), Some(2)).unwrap();
+
+        // A time that is not given is left unchanged
+        fs.set_times(path!("/foo"), None, Some(3)).unwrap();
+        let metadata = fs.metadata(path!("/foo")).unwrap();
+        assert_eq!((metadata.accessed, metadata.modified), (1, 3));
+
+        fs.set_times(path!("/foo"), Some(4), None).unwrap();
+        let metadata = fs.metadata(path!("/foo")).unwrap();
+        assert_eq!((metadata.accessed, metadata.modified), (4, 3));
+
+        assert_eq!(
+            fs.set_times(path!("/bar"), Some(5), Some(6)),
+            Err(FsError::EntryNotFound),
+            "setting the times of a missing entry",
+        );
+    }
 }
@@ -117,6 +117,40 @@ where
     }
 }
 
+/// Name of the file that marks a directory as opaque, the contents of the
+/// same directory in the secondary file systems are then hidden
+static OPAQUE_MARKER: &str = ".wh..wh..opq";
+
+/// Marks a directory in the primary as opaque so that the contents of the
+/// same directory in the secondary file systems are hidden
+pub fn create_opaque<F>(fs: &F, path: impl AsRef<Path>) -> Result<(), FsError>
+where
+    F: FileSystem + ?Sized,
+{
+    fs.new_open_options()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path.as_ref().join(OPAQUE_MARKER))?;
+    Ok(())
+}
+
+/// Removes the marker that makes a directory opaque
+pub fn remove_opaque<F>(fs: &F, path: impl AsRef<Path>)
+where
+    F: FileSystem + ?Sized,
+{
+    fs.remove_file(&path.as_ref().join(OPAQUE_MARKER)).ok();
+}
+
+/// Returns true if the directory has been marked as opaque
+pub fn is_opaque<F>(fs: &F, path: impl AsRef<Path>) -> bool
+where
+    F: FileSystem + ?Sized,
+{
+    fs.metadata(&path.as_ref().join(OPAQUE_MARKER)).is_ok()
+}
+
 /// Returns true if the path is a whiteout file
 pub fn is_white_out(path: impl AsRef<Path>) -> Option<PathBuf> {
     if let Some(filename) = path.as_ref().file_name() {
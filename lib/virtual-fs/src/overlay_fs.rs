@@ -13,8 +13,8 @@ use replace_with::replace_with_or_abort;
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
 
 use crate::{
-    ops, FileOpener, FileSystem, FileSystems, FsError, Metadata, OpenOptions, OpenOptionsConfig,
-    ReadDir, VirtualFile,
+    ops, FileOpener, FileSystem, FileSystems, FsError, FsStat, Metadata, OpenOptions,
+    OpenOptionsConfig, ReadDir, VirtualFile,
 };
 
 /// A primary filesystem and chain of secondary filesystems that are overlayed
@@ -96,7 +96,27 @@ where
         &mut self.secondaries
     }
 
+    /// Returns true if the path, or any of the directories that contain it,
+    /// has been deleted from the secondaries by a whiteout in the primary,
+    /// or if it is inside a directory that the primary marks as opaque.
+    ///
+    /// The whiteouts are regular files in the primary so this also holds
+    /// for an overlay that is rebuilt on top of the same primary.
+    fn is_whited_out(&self, path: &Path) -> bool {
+        path.ancestors()
+            .any(|path| ops::has_white_out(&self.primary, path))
+            || path
+                .ancestors()
+                .skip(1)
+                .any(|path| ops::is_opaque(&self.primary, path))
+    }
+
     fn permission_error_or_not_found(&self, path: &Path) -> Result<(), FsError> {
+        // Anything that was deleted is gone even if it is still in the secondaries
+        if self.is_whited_out(path) {
+            return Err(FsError::EntryNotFound);
+        }
+
         for fs in self.secondaries.filesystems() {
             if ops::exists(fs, path) {
                 return Err(FsError::PermissionDenied);
@@ -127,7 +147,7 @@ where
         }
 
         // There might be a whiteout, search for this
-        if ops::has_white_out(&self.primary, path) {
+        if self.is_whited_out(path) {
             return Err(FsError::EntryNotFound);
         }
 
@@ -147,8 +167,15 @@ where
         let mut had_at_least_one_success = false;
         let mut white_outs = HashSet::new();
 
-        let filesystems = std::iter::once(&self.primary as &(dyn FileSystem + Send))
-            .chain(self.secondaries().filesystems());
+        // The secondaries are skipped entirely for a directory that was deleted
+        // or that was created again afterwards (which makes it opaque)
+        let whited_out = self.is_whited_out(path) || ops::is_opaque(&self.primary, path);
+        let filesystems = std::iter::once(&self.primary as &(dyn FileSystem + Send)).chain(
+            self.secondaries()
+                .filesystems()
+                .into_iter()
+                .filter(|_| !whited_out),
+        );
 
         for fs in filesystems {
             match fs.read_dir(path) {
@@ -204,7 +231,9 @@ where
         }
 
         // It could be the case that the directory was earlier hidden in the secondaries
-        // by a whiteout file, hence we need to make sure those are cleared out.
+        // by a whiteout file, hence we need to make sure those are cleared out. The new
+        // directory is then marked as opaque so the old contents stay hidden.
+        let was_whited_out = ops::has_white_out(&self.primary, path);
         ops::remove_white_out(self.primary.as_ref(), path);
 
         // Make sure the parent tree is in place on the primary, this is to cover the
@@ -218,6 +247,7 @@ where
 
         // Create the directory in the primary
         match self.primary.create_dir(path) {
+            Ok(()) if was_whited_out => return ops::create_opaque(&self.primary, path),
             Err(e) if should_continue(e) => {}
             other => return other,
         }
@@ -239,14 +269,26 @@ where
         // If the directory is contained in a secondary file system then we need to create a
         // whiteout file so that it is suppressed and is no longer returned in `readdir` calls.
 
+        let whited_out = self.is_whited_out(path);
         let had_at_least_one_success = self.secondaries.filesystems().into_iter().any(|fs| {
-            fs.read_dir(path).is_ok() && ops::create_white_out(&self.primary, path).is_ok()
+            !whited_out
+                && fs.read_dir(path).is_ok()
+                && ops::create_white_out(&self.primary, path).is_ok()
         });
 
         // Attempt to remove it from the primary, if this succeeds then we may have also
         // added the whiteout file in the earlier step, but are required in this case to
-        // properly delete the directory.
+        // properly delete the directory. An opaque directory first needs its marker
+        // removed, which is put back if the directory can not be removed.
+        let opaque = ops::is_opaque(&self.primary, path);
+        if opaque {
+            ops::remove_opaque(&self.primary, path);
+        }
         match self.primary.remove_dir(path) {
+            Err(e) if opaque => {
+                ops::create_opaque(&self.primary, path).ok();
+                return Err(e);
+            }
             Err(e) if should_continue(e) => {}
             other => return other,
         }
@@ -342,7 +384,7 @@ where
         }
 
         // There might be a whiteout, search for this
-        if ops::has_white_out(&self.primary, path) {
+        if self.is_whited_out(path) {
             return Err(FsError::EntryNotFound);
         }
 
@@ -357,6 +399,25 @@ where
         Err(FsError::EntryNotFound)
     }
 
+    fn statvfs(&self, path: &Path) -> crate::Result<FsStat> {
+        // New files are written to the primary so its statistics are the
+        // ones that matter, the secondaries are only asked when it has none
+        match self.primary.statvfs(path) {
+            Ok(stat) => return Ok(stat),
+            Err(e) if should_continue(e) || e == FsError::Unsupported => {}
+            Err(e) => return Err(e),
+        }
+
+        for fs in self.secondaries.filesystems() {
+            match fs.statvfs(path) {
+                Err(e) if should_continue(e) || e == FsError::Unsupported => continue,
+                other => return other,
+            }
+        }
+
+        Err(FsError::Unsupported)
+    }
+
     fn symlink_metadata(&self, path: &Path) -> crate::Result<Metadata> {
         // Whiteout files can not be read, they are just markers
         if ops::is_white_out(path).is_some() {
@@ -371,7 +432,7 @@ where
         }
 
         // There might be a whiteout, search for this
-        if ops::has_white_out(&self.primary, path) {
+        if self.is_whited_out(path) {
             return Err(FsError::EntryNotFound);
         }
 
@@ -395,8 +456,11 @@ where
 
         // If the file is contained in a secondary then then we need to create a
         // whiteout file so that it is suppressed.
+        let whited_out = self.is_whited_out(path);
         let had_at_least_one_success = self.secondaries.filesystems().into_iter().any(|fs| {
-            fs.metadata(path).is_ok() && ops::create_white_out(&self.primary, path).is_ok()
+            !whited_out
+                && fs.metadata(path).is_ok()
+                && ops::create_white_out(&self.primary, path).is_ok()
         });
 
         // Attempt to remove it from the primary
@@ -490,7 +554,7 @@ where
         // we are done as the secondary file or directory has been earlier
         // deleted via a white out (when the create flag is set then
         // the white out marker is ignored)
-        if !conf.create && ops::has_white_out(&self.primary, path) {
+        if !conf.create && self.is_whited_out(path) {
             tracing::trace!(
                 path=%path.display(),
                 "The file has been whited out",
@@ -502,7 +566,7 @@ where
         let require_mutations = conf.append || conf.write || conf.create_new | conf.truncate;
 
         // If the file is on a secondary then we should open it
-        if !ops::has_white_out(&self.primary, path) {
+        if !self.is_whited_out(path) {
             for fs in self.secondaries.filesystems() {
                 let mut sub_conf = conf.clone();
                 sub_conf.create = false;
@@ -1123,6 +1187,22 @@ mod tests {
     use crate::mem_fs::FileSystem as MemFS;
     use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 
+    #[test]
+    fn statvfs_is_taken_from_the_primary() {
+        let primary = MemFS::default();
+        primary.set_capacity(Some(4096));
+        let secondary = MemFS::default();
+        ops::touch(&secondary, "/secondary.txt").unwrap();
+        let fs = OverlayFileSystem::new(primary, [secondary]);
+
+        assert_eq!(fs.statvfs(Path::new("/")).unwrap().total_bytes, 4096);
+        // Entries that only the secondaries have are reported by them
+        assert_eq!(
+            fs.statvfs(Path::new("/secondary.txt")).unwrap().total_bytes,
+            FsStat::UNLIMITED
+        );
+    }
+
     #[tokio::test]
     async fn remove_directory() {
         let primary = MemFS::default();
@@ -1490,6 +1570,123 @@ mod tests {
         assert!(ops::is_dir(&fs.secondaries[0], "/first/secondary"));
     }
 
+    #[tokio::test]
+    async fn unlinked_file_stays_gone_after_rebuilding_the_overlay() {
+        let primary = MemFS::default();
+        let secondary = MemFS::default();
+        ops::create_dir_all(&secondary, "/secondary").unwrap();
+        ops::write(&secondary, "/secondary/file.txt", b"Hello, World!")
+            .await
+            .unwrap();
+        ops::write(&secondary, "/secondary/other.txt", b"Hello, World!")
+            .await
+            .unwrap();
+
+        let fs = OverlayFileSystem::new(primary.clone(), [secondary.clone()]);
+        fs.remove_file(Path::new("/secondary/file.txt")).unwrap();
+        drop(fs);
+
+        // A new overlay on top of the same layers still sees the deletion
+        let fs = OverlayFileSystem::new(primary, [secondary]);
+        assert_eq!(
+            fs.metadata(Path::new("/secondary/file.txt")).unwrap_err(),
+            FsError::EntryNotFound
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open(Path::new("/secondary/file.txt"))
+                .unwrap_err(),
+            FsError::EntryNotFound
+        );
+        let entries: Vec<_> = fs
+            .read_dir(Path::new("/secondary"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect();
+        assert_eq!(entries, vec![PathBuf::from("/secondary/other.txt")]);
+
+        // Deleting it again is an error as it no longer exists
+        assert_eq!(
+            fs.remove_file(Path::new("/secondary/file.txt")),
+            Err(FsError::EntryNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn removed_dir_stays_gone_after_rebuilding_the_overlay() {
+        let primary = MemFS::default();
+        let secondary = MemFS::default();
+        ops::create_dir_all(&secondary, "/first/secondary").unwrap();
+        ops::write(&secondary, "/first/secondary/file.txt", b"Hello, World!")
+            .await
+            .unwrap();
+
+        let fs = OverlayFileSystem::new(primary.clone(), [secondary.clone()]);
+        fs.remove_dir(Path::new("/first/secondary")).unwrap();
+        drop(fs);
+
+        // Neither the directory nor anything that was in it comes back
+        let fs = OverlayFileSystem::new(primary, [secondary]);
+        assert!(!ops::exists(&fs, "/first/secondary"));
+        assert!(!ops::exists(&fs, "/first/secondary/file.txt"));
+        assert!(fs.read_dir(Path::new("/first/secondary")).is_err());
+        assert_eq!(fs.read_dir(Path::new("/first")).unwrap().count(), 0);
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open(Path::new("/first/secondary/file.txt"))
+                .unwrap_err(),
+            FsError::EntryNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn recreated_dir_hides_the_old_contents() {
+        let primary = MemFS::default();
+        let secondary = MemFS::default();
+        ops::create_dir_all(&secondary, "/first/secondary").unwrap();
+        ops::write(&secondary, "/first/secondary/file.txt", b"Hello, World!")
+            .await
+            .unwrap();
+
+        let fs = OverlayFileSystem::new(primary.clone(), [secondary.clone()]);
+        fs.remove_dir(Path::new("/first/secondary")).unwrap();
+        fs.create_dir(Path::new("/first/secondary")).unwrap();
+
+        // The directory is back but it starts out empty
+        assert!(ops::is_dir(&fs, "/first/secondary"));
+        assert_eq!(
+            fs.read_dir(Path::new("/first/secondary")).unwrap().count(),
+            0
+        );
+        assert!(!ops::exists(&fs, "/first/secondary/file.txt"));
+        ops::touch(&fs, "/first/secondary/new.txt").unwrap();
+        drop(fs);
+
+        // Even for an overlay that is rebuilt on top of the same layers
+        let fs = OverlayFileSystem::new(primary, [secondary]);
+        let entries: Vec<_> = fs
+            .read_dir(Path::new("/first/secondary"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path)
+            .collect();
+        assert_eq!(entries, vec![PathBuf::from("/first/secondary/new.txt")]);
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open(Path::new("/first/secondary/file.txt"))
+                .unwrap_err(),
+            FsError::EntryNotFound
+        );
+
+        // It can be removed again once it is empty
+        fs.remove_file(Path::new("/first/secondary/new.txt"))
+            .unwrap();
+        fs.remove_dir(Path::new("/first/secondary")).unwrap();
+        assert!(!ops::exists(&fs, "/first/secondary"));
+    }
+
     #[tokio::test]
     async fn create_new_secondary_fs_without_cow() {
         let primary = MemFS::default();
@@ -34,14 +34,43 @@ impl FileSystem for PassthruFileSystem {
         self.fs.remove_dir(path)
     }
 
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        self.fs.remove_dir_all(path)
+    }
+
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>> {
         Box::pin(async { self.fs.rename(from, to).await })
     }
 
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { self.fs.write_atomic(path, contents).await })
+    }
+
+    fn copy_file<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(async { self.fs.copy_file(from, to).await })
+    }
+
+    fn copy_tree<'a>(
+        &'a self,
+        from: &'a Path,
+        to: &'a Path,
+        opts: CopyTreeOpts,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { self.fs.copy_tree(from, to, opts).await })
+    }
+
     fn metadata(&self, path: &Path) -> Result<Metadata> {
         self.fs.metadata(path)
     }
 
+    fn statvfs(&self, path: &Path) -> Result<FsStat> {
+        self.fs.statvfs(path)
+    }
+
+    fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        self.fs.set_times(path, atime, mtime)
+    }
+
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         self.fs.symlink_metadata(path)
     }
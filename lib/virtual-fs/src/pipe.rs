@@ -1,4 +1,4 @@
-use bytes::{Buf, Bytes};
+use bytes::{Buf, Bytes, BytesMut};
 #[cfg(feature = "futures")]
 use futures::Future;
 use std::io::IoSlice;
@@ -9,6 +9,7 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::task::Context;
 use std::task::Poll;
+use std::task::Waker;
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
 use tokio::sync::{mpsc, mpsc::error::TryRecvError};
 
@@ -26,6 +27,8 @@ pub struct Pipe {
 pub struct PipeTx {
     /// Sends bytes down the pipe
     tx: Arc<Mutex<mpsc::UnboundedSender<Vec<u8>>>>,
+    /// Limits how much data can be buffered in the pipe (if bounded)
+    limit: Option<Arc<PipeLimit>>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +55,7 @@ impl PipeRx {
                             Err(_) => return None,
                         };
                         read_buffer.advance(read);
+                        rx.consumed(read);
                         return Some(read);
                     }
                 }
@@ -70,34 +74,182 @@ impl PipeRx {
             rx.buffer.replace(Bytes::from(data));
         }
     }
+
+    fn bytes_available(&self) -> usize {
+        let mut rx = self.rx.lock().unwrap();
+
+        // Everything that was written so far is moved into the read buffer
+        // so that it can be counted without waiting on the channel
+        let mut buffer = BytesMut::new();
+        if let Some(data) = rx.buffer.take() {
+            buffer.extend_from_slice(&data);
+        }
+        while let Ok(data) = rx.chan.try_recv() {
+            buffer.extend_from_slice(&data);
+        }
+        let len = buffer.len();
+        rx.buffer.replace(buffer.freeze());
+        len
+    }
 }
 
 #[derive(Debug)]
 struct PipeReceiver {
     chan: mpsc::UnboundedReceiver<Vec<u8>>,
     buffer: Option<Bytes>,
+    limit: Option<Arc<PipeLimit>>,
+}
+
+impl PipeReceiver {
+    /// Frees up the space of the bytes that were read
+    fn consumed(&self, read: usize) {
+        if let Some(limit) = self.limit.as_ref() {
+            limit.release(read);
+        }
+    }
+}
+
+impl Drop for PipeReceiver {
+    fn drop(&mut self) {
+        // Writers that wait for space would otherwise wait forever
+        if let Some(limit) = self.limit.as_ref() {
+            limit.close();
+        }
+    }
+}
+
+/// Keeps track of the data buffered in a bounded pipe, writers wait for the
+/// reader to drain the pipe once it holds `capacity` bytes.
+#[derive(Debug)]
+struct PipeLimit {
+    capacity: usize,
+    state: Mutex<PipeLimitState>,
+}
+
+#[derive(Debug, Default)]
+struct PipeLimitState {
+    buffered: usize,
+    closed: bool,
+    writers: Vec<Waker>,
+}
+
+impl PipeLimitState {
+    fn register(&mut self, waker: &Waker) {
+        if !self.writers.iter().any(|w| w.will_wake(waker)) {
+            self.writers.push(waker.clone());
+        }
+    }
+}
+
+impl PipeLimit {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Default::default(),
+        }
+    }
+
+    /// Reserves the space for up to `len` bytes and returns how much of it
+    /// was reserved, when the pipe is full the writer waits (or gets a
+    /// `WouldBlock` error when there is nothing to wake it up).
+    fn poll_reserve(&self, cx: Option<&mut Context<'_>>, len: usize) -> Poll<io::Result<usize>> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        let free = self.capacity.saturating_sub(state.buffered);
+        if free == 0 && len > 0 {
+            return match cx {
+                Some(cx) => {
+                    state.register(cx.waker());
+                    Poll::Pending
+                }
+                None => Poll::Ready(Err(io::ErrorKind::WouldBlock.into())),
+            };
+        }
+        let len = len.min(free);
+        state.buffered += len;
+        Poll::Ready(Ok(len))
+    }
+
+    /// Returns how many bytes can be written without waiting
+    fn poll_free(&self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Poll::Ready(Ok(0));
+        }
+        match self.capacity.saturating_sub(state.buffered) {
+            0 => {
+                state.register(cx.waker());
+                Poll::Pending
+            }
+            free => Poll::Ready(Ok(free)),
+        }
+    }
+
+    /// Waits until the reader has drained everything that was written
+    fn poll_drained(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.state.lock().unwrap();
+        if state.buffered == 0 {
+            Poll::Ready(Ok(()))
+        } else if state.closed {
+            Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+        } else {
+            state.register(cx.waker());
+            Poll::Pending
+        }
+    }
+
+    fn release(&self, len: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.buffered = state.buffered.saturating_sub(len);
+        if len > 0 {
+            state.writers.drain(..).for_each(Waker::wake);
+        }
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.writers.drain(..).for_each(Waker::wake);
+    }
 }
 
 impl Pipe {
-    fn new() -> Self {
+    fn new(capacity: Option<usize>) -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let limit = capacity.map(|capacity| Arc::new(PipeLimit::new(capacity)));
 
         Pipe {
             send: PipeTx {
                 tx: Arc::new(Mutex::new(tx)),
+                limit: limit.clone(),
             },
             recv: PipeRx {
                 rx: Arc::new(Mutex::new(PipeReceiver {
                     chan: rx,
                     buffer: None,
+                    limit,
                 })),
             },
         }
     }
 
     pub fn channel() -> (Pipe, Pipe) {
-        let (tx1, rx1) = Pipe::new().split();
-        let (tx2, rx2) = Pipe::new().split();
+        Self::channel_with_capacity(None)
+    }
+
+    /// Same as [`Pipe::channel`] but each direction buffers at most
+    /// `capacity` bytes, writing to a full pipe waits until the other end
+    /// reads from it (the blocking `Write` impl fails with `WouldBlock`)
+    /// and flushing waits until everything was read.
+    pub fn bounded(capacity: usize) -> (Pipe, Pipe) {
+        Self::channel_with_capacity(Some(capacity))
+    }
+
+    fn channel_with_capacity(capacity: Option<usize>) -> (Pipe, Pipe) {
+        let (tx1, rx1) = Pipe::new(capacity).split();
+        let (tx2, rx2) = Pipe::new(capacity).split();
 
         let end1 = Pipe::combine(tx1, rx2);
         let end2 = Pipe::combine(tx2, rx1);
@@ -115,6 +267,12 @@ impl Pipe {
     pub fn try_read(&mut self, buf: &mut [u8]) -> Option<usize> {
         self.recv.try_read(buf)
     }
+
+    /// Returns the number of bytes that can be read right away, unlike
+    /// `poll_read_ready` this does not register for a wake up
+    pub fn bytes_available(&self) -> usize {
+        self.recv.bytes_available()
+    }
 }
 
 impl From<Pipe> for PipeTx {
@@ -184,6 +342,7 @@ impl Read for PipeRx {
                         let mut inner_buf = &read_buffer[..read];
                         read = Read::read(&mut inner_buf, buf)?;
                         read_buffer.advance(read);
+                        rx.consumed(read);
                         return Ok(read);
                     }
                 }
@@ -225,6 +384,14 @@ impl std::io::Write for Pipe {
 
 impl std::io::Write for PipeTx {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let buf = match self.limit.as_ref() {
+            Some(limit) => match limit.poll_reserve(None, buf.len()) {
+                Poll::Ready(Ok(len)) => &buf[..len],
+                Poll::Ready(Err(err)) => return Err(err),
+                Poll::Pending => return Err(std::io::ErrorKind::WouldBlock.into()),
+            },
+            None => buf,
+        };
         let tx = self.tx.lock().unwrap();
         tx.send(buf.to_vec())
             .map_err(|_| Into::<std::io::Error>::into(std::io::ErrorKind::BrokenPipe))?;
@@ -302,9 +469,17 @@ impl AsyncWrite for Pipe {
 impl AsyncWrite for PipeTx {
     fn poll_write(
         self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
+        cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
+        let buf = match self.limit.as_ref() {
+            Some(limit) => match limit.poll_reserve(Some(cx), buf.len()) {
+                Poll::Ready(Ok(len)) => &buf[..len],
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            },
+            None => buf,
+        };
         let guard = self.tx.lock().unwrap();
         match guard.send(buf.to_vec()) {
             Ok(()) => Poll::Ready(Ok(buf.len())),
@@ -314,8 +489,11 @@ impl AsyncWrite for PipeTx {
         }
     }
 
-    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.limit.as_ref() {
+            Some(limit) => limit.poll_drained(cx),
+            None => Poll::Ready(Ok(())),
+        }
     }
 
     fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
@@ -350,6 +528,7 @@ impl AsyncRead for PipeRx {
                         let read = buf_len.min(buf.remaining());
                         buf.put_slice(&inner_buf[..read]);
                         inner_buf.advance(read);
+                        rx.consumed(read);
                         return Poll::Ready(Ok(()));
                     }
                 }
@@ -408,6 +587,10 @@ impl VirtualFile for Pipe {
             .unwrap_or_else(|_| true)
     }
 
+    fn bytes_available(&self) -> usize {
+        self.recv.bytes_available()
+    }
+
     /// Polls the file for when there is data to be read
     fn poll_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let mut rx = self.recv.rx.lock().unwrap();
@@ -433,10 +616,12 @@ impl VirtualFile for Pipe {
     }
 
     /// Polls the file for when it is available for writing
-    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+    fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let tx = self.send.tx.lock().unwrap();
         if tx.is_closed() {
             Poll::Ready(Ok(0))
+        } else if let Some(limit) = self.send.limit.as_ref() {
+            limit.poll_free(cx)
         } else {
             Poll::Ready(Ok(8192))
         }
@@ -506,3 +691,54 @@ impl DuplexPipe {
 /// Shared version of BidiPipe for situations where you need
 /// to emulate the old behaviour of `Pipe` (both send and recv on one channel).
 pub type WasiBidirectionalSharedPipePair = ArcFile<DuplexPipe>;
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::Pipe;
+
+    #[test]
+    fn test_bounded_pipe_would_block() {
+        use std::io::Write;
+
+        let (mut tx, mut rx) = Pipe::bounded(4);
+
+        assert_eq!(tx.write(b"abcdef").unwrap(), 4);
+        assert_eq!(
+            tx.write(b"ef").unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        let mut buf = [0u8; 2];
+        assert_eq!(rx.try_read(&mut buf), Some(2));
+        assert_eq!(&buf, b"ab");
+        assert_eq!(tx.write(b"efgh").unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_pipe_interleaves_writes_and_reads() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut tx, mut rx) = Pipe::bounded(3);
+        let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+
+        let writer = async {
+            tx.write_all(&data).await.unwrap();
+            tx.flush().await.unwrap();
+            tx.close();
+        };
+        let reader = async {
+            let mut received = Vec::new();
+            let mut buf = [0u8; 7];
+            while received.len() < data.len() {
+                let read = rx.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..read]);
+            }
+            received
+        };
+
+        let ((), received) = tokio::join!(writer, reader);
+        assert_eq!(received, data);
+    }
+}
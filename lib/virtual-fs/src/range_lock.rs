@@ -0,0 +1,152 @@
+//! Advisory byte-range locks (like `fcntl(F_SETLK)`) on a single file.
+//!
+//! Locks are held by owners, which are typically the open file descriptions
+//! of the file. An owner never conflicts with its own locks.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{FsError, Result};
+
+/// Identifies the owner of a lock.
+pub type LockOwner = u64;
+
+/// Returns an owner that has never been handed out before.
+pub fn next_lock_owner() -> LockOwner {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Converts an `offset` and `len` into the bounds of a locked range, a `len`
+/// of zero extends the range up to the end of the file.
+pub fn lock_bounds(offset: u64, len: u64) -> Result<(u64, u64)> {
+    match len {
+        0 => Ok((offset, u64::MAX)),
+        len => Ok((
+            offset,
+            offset.checked_add(len).ok_or(FsError::InvalidInput)?,
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RangeLock {
+    owner: LockOwner,
+    start: u64,
+    // Exclusive end of the range, `u64::MAX` locks up to the end of the file
+    // no matter how much it grows.
+    end: u64,
+    exclusive: bool,
+}
+
+/// The range locks of a file.
+#[derive(Debug, Default)]
+pub struct RangeLocks {
+    locks: Vec<RangeLock>,
+}
+
+impl RangeLocks {
+    /// Takes a lock on `start..end` unless it conflicts with a lock of
+    /// another owner, an exclusive lock conflicts with every overlapping lock
+    /// while shared locks only conflict with exclusive ones. Returns whether
+    /// the lock was taken.
+    ///
+    /// Locks of the same owner never conflict, the new lock replaces them
+    /// where they overlap (which is how a lock is upgraded or downgraded).
+    pub fn try_lock(&mut self, owner: LockOwner, start: u64, end: u64, exclusive: bool) -> bool {
+        let conflict = self.locks.iter().any(|lock| {
+            lock.owner != owner
+                && lock.start < end
+                && start < lock.end
+                && (exclusive || lock.exclusive)
+        });
+        if conflict {
+            return false;
+        }
+
+        self.remove_range(owner, start, end);
+        self.locks.push(RangeLock {
+            owner,
+            start,
+            end,
+            exclusive,
+        });
+        true
+    }
+
+    /// Releases the locks of `owner` within `start..end`, locks that only
+    /// partly overlap the range are shrunk (or split in two).
+    pub fn unlock(&mut self, owner: LockOwner, start: u64, end: u64) {
+        self.remove_range(owner, start, end);
+    }
+
+    /// Releases all the locks that `owner` holds.
+    pub fn unlock_all(&mut self, owner: LockOwner) {
+        self.remove_range(owner, 0, u64::MAX);
+    }
+
+    /// Whether no lock is held at all.
+    pub fn is_empty(&self) -> bool {
+        self.locks.is_empty()
+    }
+
+    fn remove_range(&mut self, owner: LockOwner, start: u64, end: u64) {
+        let mut kept = Vec::with_capacity(self.locks.len());
+        for lock in self.locks.drain(..) {
+            if lock.owner != owner || lock.end <= start || end <= lock.start {
+                kept.push(lock);
+                continue;
+            }
+            if lock.start < start {
+                kept.push(RangeLock { end: start, ..lock });
+            }
+            if end < lock.end {
+                kept.push(RangeLock { start: end, ..lock });
+            }
+        }
+        self.locks = kept;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicting_ranges() {
+        let mut locks = RangeLocks::default();
+        assert!(locks.try_lock(1, 0, 10, true));
+        assert!(locks.try_lock(2, 20, 30, true));
+        assert!(!locks.try_lock(2, 5, 15, true));
+        assert!(!locks.try_lock(2, 5, 15, false));
+
+        locks.unlock(1, 0, 10);
+        assert!(locks.try_lock(2, 5, 15, true));
+        assert!(!locks.try_lock(1, 0, u64::MAX, false));
+
+        locks.unlock_all(2);
+        assert!(locks.is_empty());
+        assert!(locks.try_lock(1, 0, u64::MAX, true));
+    }
+
+    #[test]
+    fn shared_locks_and_splitting() {
+        let mut locks = RangeLocks::default();
+        assert!(locks.try_lock(1, 0, 100, false));
+        assert!(locks.try_lock(2, 50, 150, false));
+        assert!(!locks.try_lock(3, 90, 95, true));
+
+        // Unlocking the middle of a lock keeps both of its ends
+        locks.unlock(1, 40, 60);
+        locks.unlock_all(2);
+        assert!(locks.try_lock(3, 40, 60, true));
+        assert!(!locks.try_lock(3, 39, 60, true));
+        assert!(!locks.try_lock(3, 40, 61, true));
+    }
+
+    #[test]
+    fn lock_bounds_to_the_end_of_the_file() {
+        assert_eq!(lock_bounds(10, 0).unwrap(), (10, u64::MAX));
+        assert_eq!(lock_bounds(10, 5).unwrap(), (10, 15));
+        assert!(lock_bounds(u64::MAX, 1).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Exposes a shared, immutable region of memory as a read-only file, the
+//! bytes are never copied so the same data can back many files at once
+
+use std::{
+    io::{self, SeekFrom},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
+
+use crate::{FsError, VirtualFile};
+
+/// A read-only file backed by a shared [`Bytes`] buffer. Clones share the
+/// same buffer but each has its own cursor.
+#[derive(Debug, Clone)]
+pub struct SliceFile {
+    data: Bytes,
+    pos: u64,
+}
+
+impl SliceFile {
+    pub fn new(data: impl Into<Bytes>) -> Self {
+        Self {
+            data: data.into(),
+            pos: 0,
+        }
+    }
+
+    /// Access the underlying buffer.
+    pub fn contents(&self) -> &Bytes {
+        &self.data
+    }
+
+    fn remaining(&self) -> &[u8] {
+        let start = self.pos.min(self.data.len() as u64) as usize;
+        &self.data[start..]
+    }
+}
+
+impl VirtualFile for SliceFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+
+    fn last_modified(&self) -> u64 {
+        0
+    }
+
+    fn created_time(&self) -> u64 {
+        0
+    }
+
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> crate::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> crate::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(self.remaining().len()))
+    }
+
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::ErrorKind::PermissionDenied.into()))
+    }
+}
+
+impl AsyncRead for SliceFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let remaining = self.remaining();
+        let amt = remaining.len().min(buf.remaining());
+        buf.put_slice(&remaining[..amt]);
+        self.pos += amt as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for SliceFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::ErrorKind::PermissionDenied.into()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for SliceFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let pos = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.size().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn read_a_sub_range() {
+        let mut file = SliceFile::new(Bytes::from_static(b"Hello, World!"));
+        assert_eq!(file.size(), 13);
+
+        file.seek(SeekFrom::Start(7)).await.unwrap();
+        let mut buf = [0u8; 5];
+        file.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"World");
+
+        file.seek(SeekFrom::End(-1)).await.unwrap();
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"!");
+
+        let err = file.write_all(b"nope").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_buffer() {
+        let file = SliceFile::new(vec![42u8; 1024]);
+        let mut other = file.clone();
+
+        assert_eq!(file.contents().as_ptr(), other.contents().as_ptr());
+
+        // Each clone has its own cursor
+        other.seek(SeekFrom::Start(1000)).await.unwrap();
+        assert_eq!(file.pos, 0);
+    }
+}
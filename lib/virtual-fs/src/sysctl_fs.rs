@@ -0,0 +1,247 @@
+//! A read-only file system that serves a table of sysctl-like values in the
+//! same layout as `/proc/sys`, the key `kernel.ostype` is served as the file
+//! `kernel/ostype` and every prefix of a key is a directory.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, Result, SliceFile, VirtualFile,
+};
+
+/// Serves sysctl values as files, it is usually mounted at `/proc/sys`.
+///
+/// Just like on Linux every file holds its value followed by a newline.
+#[derive(Debug, Clone, Default)]
+pub struct SysctlFileSystem {
+    values: Arc<BTreeMap<String, String>>,
+}
+
+impl SysctlFileSystem {
+    pub fn new(values: BTreeMap<String, String>) -> Self {
+        Self {
+            values: Arc::new(values),
+        }
+    }
+
+    /// Looks up a value by its dotted key (e.g. `kernel.ostype`).
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.as_str())
+    }
+
+    /// Converts a path within this file system into a dotted key, the root
+    /// directory is the empty key.
+    fn key_of(path: &Path) -> Result<String> {
+        let mut parts = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::RootDir | Component::CurDir => {}
+                Component::Normal(part) => parts.push(part.to_str().ok_or(FsError::InvalidInput)?),
+                Component::ParentDir | Component::Prefix(_) => return Err(FsError::InvalidInput),
+            }
+        }
+        Ok(parts.join("."))
+    }
+
+    fn is_dir(&self, key: &str) -> bool {
+        key.is_empty() || {
+            let prefix = format!("{key}.");
+            self.values.keys().any(|k| k.starts_with(&prefix))
+        }
+    }
+
+    fn file_metadata(value: &str) -> Metadata {
+        Metadata {
+            ft: FileType::new_file(),
+            accessed: 0,
+            created: 0,
+            modified: 0,
+            len: value.len() as u64 + 1,
+        }
+    }
+
+    fn dir_metadata() -> Metadata {
+        Metadata {
+            ft: FileType::new_dir(),
+            accessed: 0,
+            created: 0,
+            modified: 0,
+            len: 0,
+        }
+    }
+}
+
+impl FileSystem for SysctlFileSystem {
+    fn readlink(&self, _path: &Path) -> Result<PathBuf> {
+        Err(FsError::InvalidInput)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let key = Self::key_of(path)?;
+        if !self.is_dir(&key) {
+            return Err(match self.values.contains_key(&key) {
+                true => FsError::BaseNotDirectory,
+                false => FsError::EntryNotFound,
+            });
+        }
+
+        let prefix = match key.is_empty() {
+            true => String::new(),
+            false => format!("{key}."),
+        };
+        let children: BTreeSet<&str> = self
+            .values
+            .keys()
+            .filter_map(|k| k.strip_prefix(prefix.as_str()))
+            .map(|rest| rest.split('.').next().unwrap_or(rest))
+            .collect();
+
+        let entries = children
+            .into_iter()
+            .map(|name| {
+                let path = path.join(name);
+                let metadata = self.metadata(&path);
+                DirEntry { path, metadata }
+            })
+            .collect();
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename<'a>(&'a self, _from: &'a Path, _to: &'a Path) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Err(FsError::PermissionDenied) })
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        let key = Self::key_of(path)?;
+        if let Some(value) = self.values.get(&key) {
+            Ok(Self::file_metadata(value))
+        } else if self.is_dir(&key) {
+            Ok(Self::dir_metadata())
+        } else {
+            Err(FsError::EntryNotFound)
+        }
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        self.metadata(path)
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+
+    fn mount(
+        &self,
+        _name: String,
+        _path: &Path,
+        _fs: Box<dyn FileSystem + Send + Sync>,
+    ) -> Result<()> {
+        Err(FsError::Unsupported)
+    }
+}
+
+impl FileOpener for SysctlFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let key = Self::key_of(path)?;
+        let value = match self.values.get(&key) {
+            Some(value) => value,
+            None if self.is_dir(&key) => return Err(FsError::NotAFile),
+            None => return Err(FsError::EntryNotFound),
+        };
+        if conf.would_mutate() {
+            return Err(FsError::PermissionDenied);
+        }
+
+        Ok(Box::new(SliceFile::new(format!("{value}\n"))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    fn sysctls() -> SysctlFileSystem {
+        SysctlFileSystem::new(BTreeMap::from([
+            ("kernel.ostype".to_string(), "WASIX".to_string()),
+            ("kernel.osrelease".to_string(), "1.0".to_string()),
+            ("vm.overcommit_memory".to_string(), "0".to_string()),
+        ]))
+    }
+
+    #[tokio::test]
+    async fn read_a_value() {
+        let fs = sysctls();
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open("/kernel/ostype")
+            .unwrap();
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "WASIX\n");
+
+        assert_eq!(
+            fs.new_open_options()
+                .read(true)
+                .open("/kernel/missing")
+                .unwrap_err(),
+            FsError::EntryNotFound
+        );
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .open("/kernel/ostype")
+                .unwrap_err(),
+            FsError::PermissionDenied
+        );
+    }
+
+    #[test]
+    fn list_the_keys() {
+        let fs = sysctls();
+        let names = |path: &str| -> Vec<PathBuf> {
+            fs.read_dir(Path::new(path))
+                .unwrap()
+                .map(|entry| entry.unwrap().path)
+                .collect()
+        };
+
+        assert_eq!(names("/"), [PathBuf::from("/kernel"), PathBuf::from("/vm")]);
+        assert_eq!(
+            names("/kernel"),
+            [
+                PathBuf::from("/kernel/osrelease"),
+                PathBuf::from("/kernel/ostype")
+            ]
+        );
+        assert!(fs.metadata(Path::new("/vm")).unwrap().is_dir());
+        assert_eq!(
+            fs.metadata(Path::new("/vm/overcommit_memory")).unwrap().len,
+            2
+        );
+    }
+}
@@ -8,8 +8,8 @@ use std::{
 };
 
 use crate::{
-    limiter::DynFsMemoryLimiter, mem_fs, BoxFuture, FileSystem, Metadata, OpenOptions, ReadDir,
-    Result,
+    limiter::DynFsMemoryLimiter, mem_fs, BoxFuture, CopyTreeOpts, FileSystem, FsStat, Metadata,
+    OpenOptions, ReadDir, Result,
 };
 
 #[derive(Debug, Default, Clone)]
@@ -54,6 +54,11 @@ impl TmpFileSystem {
         self.fs.mount(src_path, other, dst_path)
     }
 
+    /// See [`mem_fs::FileSystem::is_same_mount`].
+    pub fn is_same_mount(&self, a: &Path, b: &Path) -> Result<bool> {
+        self.fs.is_same_mount(a, b)
+    }
+
     /// Canonicalize a path without validating that it actually exists.
     pub fn canonicalize_unchecked(&self, path: &Path) -> Result<PathBuf> {
         self.fs.canonicalize_unchecked(path)
@@ -77,14 +82,43 @@ impl FileSystem for TmpFileSystem {
         self.fs.remove_dir(path)
     }
 
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        self.fs.remove_dir_all(path)
+    }
+
     fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<()>> {
         Box::pin(async { self.fs.rename(from, to).await })
     }
 
+    fn write_atomic<'a>(&'a self, path: &'a Path, contents: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { self.fs.write_atomic(path, contents).await })
+    }
+
+    fn copy_file<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, Result<u64>> {
+        Box::pin(async { self.fs.copy_file(from, to).await })
+    }
+
+    fn copy_tree<'a>(
+        &'a self,
+        from: &'a Path,
+        to: &'a Path,
+        opts: CopyTreeOpts,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { self.fs.copy_tree(from, to, opts).await })
+    }
+
     fn metadata(&self, path: &Path) -> Result<Metadata> {
         self.fs.metadata(path)
     }
 
+    fn statvfs(&self, path: &Path) -> Result<FsStat> {
+        self.fs.statvfs(path)
+    }
+
+    fn set_times(&self, path: &Path, atime: Option<u64>, mtime: Option<u64>) -> Result<()> {
+        self.fs.set_times(path, atime, mtime)
+    }
+
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         self.fs.symlink_metadata(path)
     }
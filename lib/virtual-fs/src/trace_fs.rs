@@ -59,6 +59,11 @@ where
         self.0.remove_dir(path)
     }
 
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    fn remove_dir_all(&self, path: &std::path::Path) -> crate::Result<()> {
+        self.0.remove_dir_all(path)
+    }
+
     #[tracing::instrument(level = "trace", skip(self), err)]
     fn rename<'a>(
         &'a self,
@@ -68,11 +73,54 @@ where
         Box::pin(async { self.0.rename(from, to).await })
     }
 
+    #[tracing::instrument(level = "trace", skip(self, contents), fields(len = contents.len()))]
+    fn write_atomic<'a>(
+        &'a self,
+        path: &'a std::path::Path,
+        contents: &'a [u8],
+    ) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async { self.0.write_atomic(path, contents).await })
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn copy_file<'a>(
+        &'a self,
+        from: &'a std::path::Path,
+        to: &'a std::path::Path,
+    ) -> BoxFuture<'a, crate::Result<u64>> {
+        Box::pin(async { self.0.copy_file(from, to).await })
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn copy_tree<'a>(
+        &'a self,
+        from: &'a std::path::Path,
+        to: &'a std::path::Path,
+        opts: crate::CopyTreeOpts,
+    ) -> BoxFuture<'a, crate::Result<()>> {
+        Box::pin(async move { self.0.copy_tree(from, to, opts).await })
+    }
+
     #[tracing::instrument(level = "trace", skip(self), err)]
     fn metadata(&self, path: &std::path::Path) -> crate::Result<crate::Metadata> {
         self.0.metadata(path)
     }
 
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    fn statvfs(&self, path: &std::path::Path) -> crate::Result<crate::FsStat> {
+        self.0.statvfs(path)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    fn set_times(
+        &self,
+        path: &std::path::Path,
+        atime: Option<u64>,
+        mtime: Option<u64>,
+    ) -> crate::Result<()> {
+        self.0.set_times(path, atime, mtime)
+    }
+
     #[tracing::instrument(level = "trace", skip(self), err)]
     fn symlink_metadata(&self, path: &std::path::Path) -> crate::Result<crate::Metadata> {
         self.0.symlink_metadata(path)
@@ -158,6 +206,10 @@ impl VirtualFile for TraceFile {
         self.file.unlink()
     }
 
+    fn bytes_available(&self) -> usize {
+        self.file.bytes_available()
+    }
+
     #[tracing::instrument(level = "trace", skip_all, fields(path=%self.path.display()))]
     fn poll_read_ready(
         mut self: Pin<&mut Self>,
@@ -198,6 +198,15 @@ impl FileSystem for UnionFileSystem {
             Err(FsError::EntryNotFound)
         }
     }
+    fn statvfs(&self, path: &Path) -> Result<FsStat> {
+        let path = self.prepare_path(path);
+
+        if let Some((_, path, fs)) = self.find_mount(path.to_owned()) {
+            fs.statvfs(&path)
+        } else {
+            Err(FsError::Unsupported)
+        }
+    }
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         let path = self.prepare_path(path);
 
@@ -399,6 +408,7 @@ mod tests {
                 create: true,
                 append: false,
                 truncate: false,
+                no_atime: false,
             },
         )
         .unwrap();
@@ -412,6 +422,7 @@ mod tests {
                 create: true,
                 append: false,
                 truncate: false,
+                no_atime: false,
             },
         )
         .unwrap();
@@ -434,6 +445,22 @@ mod tests {
         union
     }
 
+    #[test]
+    fn test_statvfs_is_forwarded_to_the_mount() {
+        let union = UnionFileSystem::new();
+        let fs = mem_fs::FileSystem::default();
+        fs.set_capacity(Some(4096));
+        union
+            .mount("mem_fs".to_string(), Path::new("/mnt"), Box::new(fs))
+            .unwrap();
+
+        assert_eq!(union.statvfs(Path::new("/mnt")).unwrap().total_bytes, 4096);
+        assert_eq!(
+            union.statvfs(Path::new("/elsewhere")),
+            Err(FsError::Unsupported)
+        );
+    }
+
     #[tokio::test]
     async fn test_nested_read_dir() {
         let fs = gen_nested_filesystem();
@@ -1,13 +1,19 @@
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::future::Future;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::RwLock;
 use std::task::Context;
 use std::task::Poll;
 use std::task::RawWaker;
@@ -19,8 +25,6 @@ use bytes::Buf;
 use bytes::BytesMut;
 use futures_util::future::BoxFuture;
 use futures_util::stream::FuturesOrdered;
-use futures_util::Sink;
-use futures_util::Stream;
 use futures_util::StreamExt;
 #[cfg(feature = "hyper")]
 use hyper_util::rt::tokio::TokioIo;
@@ -39,7 +43,6 @@ use tokio_serde::formats::SymmetricalMessagePack;
 use tokio_serde::SymmetricallyFramed;
 use tokio_util::codec::FramedRead;
 use tokio_util::codec::FramedWrite;
-use tokio_util::codec::LengthDelimitedCodec;
 use virtual_mio::InlineWaker;
 use virtual_mio::InterestType;
 
@@ -64,9 +67,13 @@ use crate::VirtualTcpListener;
 use crate::VirtualTcpSocket;
 use crate::VirtualUdpSocket;
 
+use crate::rx_tx::frame_codec;
 use crate::rx_tx::RemoteRx;
+use crate::rx_tx::RemoteStream;
 use crate::rx_tx::RemoteTx;
 use crate::rx_tx::RemoteTxWakers;
+use crate::rx_tx::StreamSink;
+use crate::rx_tx::DEFAULT_MAX_FRAME_SIZE;
 use crate::Result;
 
 #[derive(Debug, Clone)]
@@ -81,7 +88,7 @@ impl RemoteNetworkingClient {
         rx_work: mpsc::UnboundedReceiver<BoxFuture<'static, ()>>,
     ) -> (Self, RemoteNetworkingClientDriver) {
         let common = RemoteCommon {
-            tx,
+            tx: RwLock::new(tx),
             rx: Mutex::new(rx),
             request_seed: AtomicU64::new(1),
             requests: Default::default(),
@@ -91,6 +98,8 @@ impl RemoteNetworkingClient {
             accept_tx: Default::default(),
             sent_tx: Default::default(),
             handlers: Default::default(),
+            reset: Default::default(),
+            reconnecting: AtomicBool::new(false),
             stall: Default::default(),
         };
         let common = Arc::new(common);
@@ -99,6 +108,8 @@ impl RemoteNetworkingClient {
             more_work: rx_work,
             tasks: Default::default(),
             common: common.clone(),
+            reconnect: None,
+            reconnecting: None,
         };
         let networking = Self { common };
 
@@ -111,20 +122,7 @@ impl RemoteNetworkingClient {
         tx: mpsc::Sender<MessageRequest>,
         rx: mpsc::Receiver<MessageResponse>,
     ) -> (Self, RemoteNetworkingClientDriver) {
-        let (tx_work, rx_work) = mpsc::unbounded_channel();
-        let tx_wakers = RemoteTxWakers::default();
-
-        let tx = RemoteTx::Mpsc {
-            tx,
-            work: tx_work,
-            wakers: tx_wakers.clone(),
-        };
-        let rx = RemoteRx::Mpsc {
-            rx,
-            wakers: tx_wakers,
-        };
-
-        Self::new(tx, rx, rx_work)
+        Self::new_from_transport(RemoteTransport::Mpsc { tx, rx })
     }
 
     /// Creates a new interface on the remote location using
@@ -141,58 +139,35 @@ impl RemoteNetworkingClient {
         TX: AsyncWrite + Send + 'static,
         RX: AsyncRead + Send + 'static,
     {
-        let tx = FramedWrite::new(tx, LengthDelimitedCodec::new());
-        let tx: Pin<Box<dyn Sink<MessageRequest, Error = std::io::Error> + Send + 'static>> =
-            match format {
-                FrameSerializationFormat::Bincode => {
-                    Box::pin(SymmetricallyFramed::new(tx, SymmetricalBincode::default()))
-                }
-                #[cfg(feature = "json")]
-                FrameSerializationFormat::Json => {
-                    Box::pin(SymmetricallyFramed::new(tx, SymmetricalJson::default()))
-                }
-                #[cfg(feature = "messagepack")]
-                FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
-                    tx,
-                    SymmetricalMessagePack::default(),
-                )),
-                #[cfg(feature = "cbor")]
-                FrameSerializationFormat::Cbor => {
-                    Box::pin(SymmetricallyFramed::new(tx, SymmetricalCbor::default()))
-                }
-            };
+        Self::new_from_async_io_with_max_frame_size(tx, rx, format, DEFAULT_MAX_FRAME_SIZE)
+    }
 
-        let rx = FramedRead::new(rx, LengthDelimitedCodec::new());
-        let rx: Pin<Box<dyn Stream<Item = std::io::Result<MessageResponse>> + Send + 'static>> =
-            match format {
-                FrameSerializationFormat::Bincode => {
-                    Box::pin(SymmetricallyFramed::new(rx, SymmetricalBincode::default()))
-                }
-                #[cfg(feature = "json")]
-                FrameSerializationFormat::Json => {
-                    Box::pin(SymmetricallyFramed::new(rx, SymmetricalJson::default()))
-                }
-                #[cfg(feature = "messagepack")]
-                FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
-                    rx,
-                    SymmetricalMessagePack::default(),
-                )),
-                #[cfg(feature = "cbor")]
-                FrameSerializationFormat::Cbor => {
-                    Box::pin(SymmetricallyFramed::new(rx, SymmetricalCbor::default()))
-                }
-            };
+    /// Same as [`Self::new_from_async_io`] but frames from the server that
+    /// are larger than `max_frame_size` close the connection (instead of
+    /// those larger than [`DEFAULT_MAX_FRAME_SIZE`])
+    pub fn new_from_async_io_with_max_frame_size<TX, RX>(
+        tx: TX,
+        rx: RX,
+        format: FrameSerializationFormat,
+        max_frame_size: usize,
+    ) -> (Self, RemoteNetworkingClientDriver)
+    where
+        TX: AsyncWrite + Send + 'static,
+        RX: AsyncRead + Send + 'static,
+    {
+        Self::new_from_transport(RemoteTransport::AsyncIo {
+            tx: Box::pin(tx),
+            rx: Box::pin(rx),
+            format,
+            max_frame_size,
+        })
+    }
 
+    /// Creates a new interface on the remote location over the
+    /// given transport
+    pub fn new_from_transport(transport: RemoteTransport) -> (Self, RemoteNetworkingClientDriver) {
         let (tx_work, rx_work) = mpsc::unbounded_channel();
-        let tx_wakers = RemoteTxWakers::default();
-
-        let tx = RemoteTx::Stream {
-            tx: Arc::new(tokio::sync::Mutex::new(tx)),
-            work: tx_work,
-            wakers: tx_wakers,
-        };
-        let rx = RemoteRx::Stream { rx };
-
+        let (tx, rx) = transport.into_parts(tx_work);
         Self::new(tx, rx, rx_work)
     }
 
@@ -280,12 +255,200 @@ impl RemoteNetworkingClient {
     }
 }
 
+/// Connection to a [`crate::RemoteNetworkingServer`], see
+/// [`RemoteNetworkingClient::new_from_transport`] and
+/// [`RemoteNetworkingClientDriver::with_reconnect`]
+#[derive(derive_more::Debug)]
+pub enum RemoteTransport {
+    /// Pair of channels to the server
+    Mpsc {
+        tx: mpsc::Sender<MessageRequest>,
+        rx: mpsc::Receiver<MessageResponse>,
+    },
+    /// Pair of byte streams to the server that carry length prefixed
+    /// frames in the given format
+    AsyncIo {
+        #[debug(ignore)]
+        tx: Pin<Box<dyn AsyncWrite + Send + 'static>>,
+        #[debug(ignore)]
+        rx: Pin<Box<dyn AsyncRead + Send + 'static>>,
+        format: FrameSerializationFormat,
+        max_frame_size: usize,
+    },
+}
+
+impl RemoteTransport {
+    fn into_parts(
+        self,
+        work: mpsc::UnboundedSender<BoxFuture<'static, ()>>,
+    ) -> (RemoteTx<MessageRequest>, RemoteRx<MessageResponse>) {
+        match self {
+            RemoteTransport::Mpsc { tx, rx } => {
+                let tx_wakers = RemoteTxWakers::default();
+
+                let tx = RemoteTx::Mpsc {
+                    tx,
+                    work,
+                    wakers: tx_wakers.clone(),
+                };
+                let rx = RemoteRx::Mpsc {
+                    rx,
+                    wakers: tx_wakers,
+                };
+                (tx, rx)
+            }
+            RemoteTransport::AsyncIo {
+                tx,
+                rx,
+                format,
+                max_frame_size,
+            } => {
+                let tx = FramedWrite::new(tx, frame_codec(max_frame_size));
+                let tx: StreamSink<MessageRequest> = match format {
+                    FrameSerializationFormat::Bincode => {
+                        Box::pin(SymmetricallyFramed::new(tx, SymmetricalBincode::default()))
+                    }
+                    #[cfg(feature = "json")]
+                    FrameSerializationFormat::Json => {
+                        Box::pin(SymmetricallyFramed::new(tx, SymmetricalJson::default()))
+                    }
+                    #[cfg(feature = "messagepack")]
+                    FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
+                        tx,
+                        SymmetricalMessagePack::default(),
+                    )),
+                    #[cfg(feature = "cbor")]
+                    FrameSerializationFormat::Cbor => {
+                        Box::pin(SymmetricallyFramed::new(tx, SymmetricalCbor::default()))
+                    }
+                };
+
+                let rx = FramedRead::new(rx, frame_codec(max_frame_size));
+                let rx: RemoteStream<MessageResponse> = match format {
+                    FrameSerializationFormat::Bincode => {
+                        Box::pin(SymmetricallyFramed::new(rx, SymmetricalBincode::default()))
+                    }
+                    #[cfg(feature = "json")]
+                    FrameSerializationFormat::Json => {
+                        Box::pin(SymmetricallyFramed::new(rx, SymmetricalJson::default()))
+                    }
+                    #[cfg(feature = "messagepack")]
+                    FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
+                        rx,
+                        SymmetricalMessagePack::default(),
+                    )),
+                    #[cfg(feature = "cbor")]
+                    FrameSerializationFormat::Cbor => {
+                        Box::pin(SymmetricallyFramed::new(rx, SymmetricalCbor::default()))
+                    }
+                };
+
+                let tx = RemoteTx::Stream {
+                    tx: Arc::new(tokio::sync::Mutex::new(tx)),
+                    work,
+                    wakers: RemoteTxWakers::default(),
+                };
+                let rx = RemoteRx::Stream { rx };
+                (tx, rx)
+            }
+        }
+    }
+}
+
+/// How the client tries to connect to the server again after the
+/// connection dropped, the delay before each attempt starts at `base` and
+/// doubles up to `max` with a random amount of up to `jitter` on top
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub base: Duration,
+    pub max: Duration,
+    pub jitter: Duration,
+    /// Gives up after this many failed attempts, `None` never gives up
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+            jitter: Duration::from_millis(100),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max);
+        if self.jitter.is_zero() {
+            return delay;
+        }
+        let random = RandomState::new().build_hasher().finish();
+        delay + Duration::from_nanos(random % self.jitter.as_nanos().max(1) as u64)
+    }
+}
+
+type ConnectFn = Box<dyn Fn() -> BoxFuture<'static, Result<RemoteTransport>> + Send + Sync>;
+
+struct Reconnect {
+    policy: ReconnectPolicy,
+    connect: ConnectFn,
+}
+
+/// Connects to the server again, waiting longer after every failed
+/// attempt, until it succeeds or the policy gives up
+async fn reconnect_with_backoff(reconnect: Arc<Reconnect>) -> Option<RemoteTransport> {
+    let mut attempt = 0u32;
+    loop {
+        tokio::time::sleep(reconnect.policy.delay(attempt)).await;
+        match (reconnect.connect)().await {
+            Ok(transport) => return Some(transport),
+            Err(err) => tracing::debug!(attempt, "failed to reconnect to the server - {err}"),
+        }
+        attempt = attempt.saturating_add(1);
+        if matches!(reconnect.policy.max_attempts, Some(max) if attempt >= max) {
+            return None;
+        }
+    }
+}
+
 pin_project_lite::pin_project! {
     pub struct RemoteNetworkingClientDriver {
         common: Arc<RemoteCommon>,
         more_work: mpsc::UnboundedReceiver<BoxFuture<'static, ()>>,
         #[pin]
         tasks: FuturesOrdered<BoxFuture<'static, ()>>,
+        reconnect: Option<Arc<Reconnect>>,
+        reconnecting: Option<BoxFuture<'static, Option<RemoteTransport>>>,
+    }
+}
+
+impl RemoteNetworkingClientDriver {
+    /// Instead of finishing when the connection to the server drops, the
+    /// driver calls `connect` (with the backoff of `policy`) to connect
+    /// again. The sockets that are still open are announced on the new
+    /// connection and the ones the server no longer has fail with
+    /// [`NetworkError::ConnectionReset`], if the policy gives up they all
+    /// do.
+    ///
+    /// While reconnecting, operations on sockets fail with
+    /// [`NetworkError::WouldBlock`] and requests that were in flight fail
+    /// with [`NetworkError::ConnectionAborted`]. Data the server sent while
+    /// the connection was down is lost.
+    pub fn with_reconnect<F, Fut>(mut self, policy: ReconnectPolicy, connect: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<RemoteTransport>> + Send + 'static,
+    {
+        self.reconnect.replace(Arc::new(Reconnect {
+            policy,
+            connect: Box::new(move || Box::pin(connect())),
+        }));
+        self
     }
 }
 
@@ -323,6 +486,27 @@ impl Future for RemoteNetworkingClientDriver {
                 Poll::Pending => {}
             };
 
+            // Nothing can be read while the connection is being re-established
+            if let Some(reconnecting) = self.reconnecting.as_mut() {
+                match reconnecting.as_mut().poll(cx) {
+                    Poll::Ready(Some(transport)) => {
+                        self.reconnecting.take();
+                        let work = self.common.tx().work().clone();
+                        let (tx, rx) = transport.into_parts(work);
+                        self.common.resume(tx, rx);
+                        continue;
+                    }
+                    Poll::Ready(None) => {
+                        tracing::debug!("giving up on reconnecting to the server");
+                        self.reconnecting.take();
+                        let sockets = self.common.open_sockets();
+                        self.common.reset_sockets(sockets);
+                        return Poll::Ready(());
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
             // We grab the next message sent by the server to us
             let msg = {
                 let mut rx_guard = self.common.rx.lock().unwrap();
@@ -448,10 +632,22 @@ impl Future for RemoteNetworkingClientDriver {
                                 request.try_send(res).ok();
                             }
                         }
+                        MessageResponse::Reconnected { lost } => {
+                            self.common.reset_sockets(lost);
+                        }
                     }
                     continue;
                 }
-                Poll::Ready(None) => Poll::Ready(()),
+                Poll::Ready(None) => match self.reconnect.clone() {
+                    Some(reconnect) => {
+                        tracing::debug!("connection to the server dropped, reconnecting");
+                        self.common.disconnected();
+                        self.reconnecting
+                            .replace(Box::pin(reconnect_with_backoff(reconnect)));
+                        continue;
+                    }
+                    None => Poll::Ready(()),
+                },
                 Poll::Pending => Poll::Pending,
             };
         }
@@ -532,7 +728,7 @@ type SocketMap<T> = HashMap<SocketId, T>;
 #[derive(derive_more::Debug)]
 struct RemoteCommon {
     #[debug(ignore)]
-    tx: RemoteTx<MessageRequest>,
+    tx: RwLock<RemoteTx<MessageRequest>>,
     #[debug(ignore)]
     rx: Mutex<RemoteRx<MessageResponse>>,
     request_seed: AtomicU64,
@@ -544,6 +740,9 @@ struct RemoteCommon {
     sent_tx: Mutex<SocketMap<mpsc::Sender<u64>>>,
     #[debug(ignore)]
     handlers: Mutex<SocketMap<Box<dyn virtual_mio::InterestHandler + Send + Sync>>>,
+    // Sockets the server no longer had after a reconnect
+    reset: Mutex<HashSet<SocketId>>,
+    reconnecting: AtomicBool,
 
     // The stall guard will prevent reads while its held and there are background tasks running
     // (the idea behind this is to create back pressure so that the task list infinitely grow)
@@ -551,6 +750,70 @@ struct RemoteCommon {
 }
 
 impl RemoteCommon {
+    fn tx(&self) -> RemoteTx<MessageRequest> {
+        self.tx.read().unwrap().clone()
+    }
+
+    /// Sockets the client holds (pending accepts have channels in
+    /// `recv_tx` but not in `recv_with_addr_tx`)
+    fn open_sockets(&self) -> Vec<SocketId> {
+        self.recv_with_addr_tx
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    fn disconnected(&self) {
+        // The flag is raised before the requests are failed so that a
+        // request is either failed here or sees the flag (see `io_iface`)
+        self.reconnecting.store(true, Ordering::SeqCst);
+        let requests = std::mem::take(&mut *self.requests.lock().unwrap());
+        for (_, request) in requests {
+            request
+                .try_send(ResponseType::Err(NetworkError::ConnectionAborted))
+                .ok();
+        }
+    }
+
+    fn resume(&self, tx: RemoteTx<MessageRequest>, rx: RemoteRx<MessageResponse>) {
+        *self.rx.lock().unwrap() = rx;
+        *self.tx.write().unwrap() = tx;
+
+        // The announcement has to be the first message on the connection
+        let sockets = self.open_sockets();
+        if let Err(err) = self
+            .tx()
+            .send_with_driver(MessageRequest::Reconnect { sockets })
+        {
+            tracing::debug!("failed to announce the sockets to the server - {err}");
+        }
+        self.reconnecting.store(false, Ordering::SeqCst);
+
+        // Wake up everything that was waiting for the connection to come back
+        let mut guard = self.handlers.lock().unwrap();
+        for (_, handler) in guard.iter_mut() {
+            handler.push_interest(InterestType::Writable);
+        }
+    }
+
+    fn reset_sockets(&self, sockets: impl IntoIterator<Item = SocketId>) {
+        let mut reset = self.reset.lock().unwrap();
+        for socket_id in sockets {
+            reset.insert(socket_id);
+
+            // Dropping the channels ends what the socket is waiting for
+            self.recv_tx.lock().unwrap().remove(&socket_id);
+            self.recv_with_addr_tx.lock().unwrap().remove(&socket_id);
+            self.accept_tx.lock().unwrap().remove(&socket_id);
+            self.sent_tx.lock().unwrap().remove(&socket_id);
+            if let Some(h) = self.handlers.lock().unwrap().get_mut(&socket_id) {
+                h.push_interest(InterestType::Closed)
+            }
+        }
+    }
+
     async fn io_iface(&self, req: RequestType) -> ResponseType {
         let req_id = self.request_seed.fetch_add(1, Ordering::SeqCst);
         let mut req_rx = {
@@ -559,8 +822,12 @@ impl RemoteCommon {
             guard.insert(req_id, RequestTx { tx });
             rx
         };
+        if self.reconnecting.load(Ordering::SeqCst) {
+            self.requests.lock().unwrap().remove(&req_id);
+            return ResponseType::Err(NetworkError::ConnectionAborted);
+        }
         if let Err(err) = self
-            .tx
+            .tx()
             .send(MessageRequest::Interface {
                 req_id: Some(req_id),
                 req,
@@ -573,7 +840,7 @@ impl RemoteCommon {
     }
 
     fn io_iface_fire_and_forget(&self, req: RequestType) -> Result<()> {
-        self.tx
+        self.tx()
             .send_with_driver(MessageRequest::Interface { req_id: None, req })
     }
 }
@@ -886,10 +1153,32 @@ impl Drop for RemoteSocket {
             .lock()
             .unwrap()
             .remove(&self.socket_id);
+        self.common.reset.lock().unwrap().remove(&self.socket_id);
     }
 }
 
 impl RemoteSocket {
+    /// Connection to send the requests of this socket on, there is none
+    /// while reconnecting or when the socket did not survive a reconnect
+    fn tx(&self) -> Result<RemoteTx<MessageRequest>> {
+        if self.common.reset.lock().unwrap().contains(&self.socket_id) {
+            return Err(NetworkError::ConnectionReset);
+        }
+        if self.common.reconnecting.load(Ordering::SeqCst) {
+            return Err(NetworkError::WouldBlock);
+        }
+        Ok(self.common.tx())
+    }
+
+    /// Error for when the channels of the socket were closed
+    fn closed_error(&self) -> NetworkError {
+        if self.common.reset.lock().unwrap().contains(&self.socket_id) {
+            NetworkError::ConnectionReset
+        } else {
+            NetworkError::ConnectionAborted
+        }
+    }
+
     async fn io_socket(&self, req: RequestType) -> ResponseType {
         let req_id = self.common.request_seed.fetch_add(1, Ordering::SeqCst);
         let mut req_rx = {
@@ -898,9 +1187,14 @@ impl RemoteSocket {
             guard.insert(req_id, RequestTx { tx });
             rx
         };
-        if let Err(err) = self
-            .common
-            .tx
+        let tx = match self.tx() {
+            Ok(tx) => tx,
+            Err(err) => {
+                self.common.requests.lock().unwrap().remove(&req_id);
+                return ResponseType::Err(err);
+            }
+        };
+        if let Err(err) = tx
             .send(MessageRequest::Socket {
                 socket: self.socket_id,
                 req_id: Some(req_id),
@@ -914,7 +1208,7 @@ impl RemoteSocket {
     }
 
     fn io_socket_fire_and_forget(&self, req: RequestType) -> Result<()> {
-        self.common.tx.send_with_driver(MessageRequest::Socket {
+        self.tx()?.send_with_driver(MessageRequest::Socket {
             socket: self.socket_id,
             req_id: None,
             req,
@@ -1046,6 +1340,25 @@ impl VirtualSocket for RemoteSocket {
             .insert(self.socket_id, handler);
         Ok(())
     }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        // Whatever already arrived is moved into the buffers, unlike
+        // `poll_read_ready` this does not register for a wake up
+        while let Ok(data) = self.rx_recv.try_recv() {
+            self.rx_buffer.extend_from_slice(&data);
+        }
+        if !self.rx_buffer.is_empty() {
+            return Ok(self.rx_buffer.len());
+        }
+        while let Ok(data) = self.rx_recv_with_addr.try_recv() {
+            self.buffer_recv_with_addr.push_back(data);
+        }
+        Ok(self
+            .buffer_recv_with_addr
+            .front()
+            .map(|data| data.data.len())
+            .unwrap_or(0))
+    }
 }
 
 impl VirtualTcpListener for RemoteSocket {
@@ -1159,7 +1472,7 @@ impl VirtualTcpListener for RemoteSocket {
 impl VirtualRawSocket for RemoteSocket {
     fn try_send(&mut self, data: &[u8]) -> Result<usize> {
         let mut cx = Context::from_waker(&self.tx_waker);
-        match self.common.tx.poll_send(
+        match self.tx()?.poll_send(
             &mut cx,
             MessageRequest::Send {
                 socket: self.socket_id,
@@ -1178,7 +1491,7 @@ impl VirtualRawSocket for RemoteSocket {
 
     fn try_flush(&mut self) -> Result<()> {
         let mut cx = Context::from_waker(&self.tx_waker);
-        match self.common.tx.poll_send(
+        match self.tx()?.poll_send(
             &mut cx,
             MessageRequest::Socket {
                 socket: self.socket_id,
@@ -1206,7 +1519,7 @@ impl VirtualRawSocket for RemoteSocket {
             }
             match self.rx_recv.try_recv() {
                 Ok(data) => self.rx_buffer.extend_from_slice(&data),
-                Err(TryRecvError::Disconnected) => return Err(NetworkError::ConnectionAborted),
+                Err(TryRecvError::Disconnected) => return Err(self.closed_error()),
                 Err(TryRecvError::Empty) => return Err(NetworkError::WouldBlock),
             }
         }
@@ -1232,7 +1545,7 @@ impl VirtualConnectionlessSocket for RemoteSocket {
     fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
         let req_id = self.common.request_seed.fetch_add(1, Ordering::SeqCst);
         let mut cx = Context::from_waker(&self.tx_waker);
-        match self.common.tx.poll_send(
+        match self.tx()?.poll_send(
             &mut cx,
             MessageRequest::SendTo {
                 socket: self.socket_id,
@@ -1254,14 +1567,22 @@ impl VirtualConnectionlessSocket for RemoteSocket {
         &mut self,
         buf: &mut [std::mem::MaybeUninit<u8>],
     ) -> Result<(usize, SocketAddr)> {
+        self.try_recv_from_with_size(buf)
+            .map(|(amt, _, addr)| (amt, addr))
+    }
+
+    fn try_recv_from_with_size(
+        &mut self,
+        buf: &mut [std::mem::MaybeUninit<u8>],
+    ) -> Result<(usize, usize, SocketAddr)> {
         match self.rx_recv_with_addr.try_recv() {
             Ok(received) => {
                 let amt = buf.len().min(received.data.len());
                 let buf: &mut [u8] = unsafe { std::mem::transmute(buf) };
                 buf[..amt].copy_from_slice(&received.data[..amt]);
-                Ok((amt, received.addr))
+                Ok((amt, received.data.len(), received.addr))
             }
-            Err(TryRecvError::Disconnected) => Err(NetworkError::ConnectionAborted),
+            Err(TryRecvError::Disconnected) => Err(self.closed_error()),
             Err(TryRecvError::Empty) => Err(NetworkError::WouldBlock),
         }
     }
@@ -1387,7 +1708,7 @@ impl VirtualConnectedSocket for RemoteSocket {
     fn try_send(&mut self, data: &[u8]) -> Result<usize> {
         let req_id = self.common.request_seed.fetch_add(1, Ordering::SeqCst);
         let mut cx = Context::from_waker(&self.tx_waker);
-        match self.common.tx.poll_send(
+        match self.tx()?.poll_send(
             &mut cx,
             MessageRequest::Send {
                 socket: self.socket_id,
@@ -1403,7 +1724,7 @@ impl VirtualConnectedSocket for RemoteSocket {
 
     fn try_flush(&mut self) -> Result<()> {
         let mut cx = Context::from_waker(&self.tx_waker);
-        match self.common.tx.poll_send(
+        match self.tx()?.poll_send(
             &mut cx,
             MessageRequest::Socket {
                 socket: self.socket_id,
@@ -1432,7 +1753,7 @@ impl VirtualConnectedSocket for RemoteSocket {
             }
             match self.rx_recv.try_recv() {
                 Ok(data) => self.rx_buffer.extend_from_slice(&data),
-                Err(TryRecvError::Disconnected) => return Err(NetworkError::ConnectionAborted),
+                Err(TryRecvError::Disconnected) => return Err(self.closed_error()),
                 Err(TryRecvError::Empty) => return Err(NetworkError::WouldBlock),
             }
         }
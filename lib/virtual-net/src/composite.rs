@@ -4,14 +4,48 @@ use std::task::{Context, Poll};
 use crate::{Ipv4Addr, Ipv6Addr, NetworkError, VirtualIoSource, VirtualTcpListener};
 use virtual_mio::ArcInterestHandler;
 
+/// Decides which of the listeners of a [`CompositeTcpListener`] gets to
+/// accept a connection first when several have connections waiting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AcceptPolicy {
+    /// The listeners are always tried in the order they were added, so the
+    /// first one is serviced until it runs out of connections
+    #[default]
+    Priority,
+    /// Every accept starts with the listener after the one that accepted
+    /// the last connection, so that no listener can starve the others
+    RoundRobin,
+}
+
 #[derive(Debug)]
 pub struct CompositeTcpListener {
     ports: Vec<Box<dyn VirtualTcpListener + Sync>>,
+    policy: AcceptPolicy,
+    /// The index of the listener that the next accept starts with
+    next: usize,
 }
 
 impl CompositeTcpListener {
     pub fn new() -> Self {
-        Self { ports: Vec::new() }
+        Self::with_policy(AcceptPolicy::default())
+    }
+
+    pub fn with_policy(policy: AcceptPolicy) -> Self {
+        Self {
+            ports: Vec::new(),
+            policy,
+            next: 0,
+        }
+    }
+
+    /// The indices of the listeners in the order they should be tried
+    fn order(&self) -> impl Iterator<Item = usize> {
+        let len = self.ports.len();
+        let start = match self.policy {
+            AcceptPolicy::Priority => 0,
+            AcceptPolicy::RoundRobin => self.next % len.max(1),
+        };
+        (0..len).map(move |i| (start + i) % len)
     }
 
     pub fn add_port(&mut self, port: Box<dyn VirtualTcpListener + Sync>) {
@@ -33,8 +67,8 @@ impl VirtualIoSource for CompositeTcpListener {
     }
 
     fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<crate::Result<usize>> {
-        for port in self.ports.iter_mut() {
-            if let Poll::Ready(ready) = port.poll_read_ready(cx) {
+        for index in self.order().collect::<Vec<_>>() {
+            if let Poll::Ready(ready) = self.ports[index].poll_read_ready(cx) {
                 return Poll::Ready(ready);
             }
         }
@@ -59,9 +93,12 @@ impl VirtualTcpListener for CompositeTcpListener {
         &mut self,
     ) -> crate::Result<(Box<dyn crate::VirtualTcpSocket + Sync>, SocketAddr)> {
         let mut ret = NetworkError::Unsupported;
-        for port in self.ports.iter_mut() {
-            match port.try_accept() {
-                Ok(ret) => return Ok(ret),
+        for index in self.order().collect::<Vec<_>>() {
+            match self.ports[index].try_accept() {
+                Ok(ret) => {
+                    self.next = index + 1;
+                    return Ok(ret);
+                }
                 Err(err) => {
                     ret = err;
                 }
@@ -115,4 +152,11 @@ impl VirtualTcpListener for CompositeTcpListener {
         }
         Ok(ret)
     }
+
+    fn set_fastopen(&mut self, queue_len: u32) -> crate::Result<()> {
+        for port in self.ports.iter_mut() {
+            port.set_fastopen(queue_len)?;
+        }
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
 #![allow(unused_variables)]
-use crate::ruleset::{Direction, Ruleset};
+use crate::ruleset::{Direction, Protocol, Ruleset};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::VirtualConnectedSocketExt;
 use crate::{io_err_into_net_error, VirtualIoSource};
 #[allow(unused_imports)]
 use crate::{
@@ -8,8 +10,8 @@ use crate::{
     VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
 };
 use bytes::{Buf, BytesMut};
-use std::collections::VecDeque;
-use std::io::{self, Read, Write};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
 use std::mem::MaybeUninit;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
 #[cfg(not(target_os = "windows"))]
@@ -19,9 +21,9 @@ use std::os::fd::RawFd;
 #[cfg(windows)]
 use std::os::windows::io::AsRawSocket;
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::runtime::Handle;
 #[allow(unused_imports, dead_code)]
 use tracing::{debug, error, info, trace, warn};
@@ -29,19 +31,37 @@ use virtual_mio::{
     state_as_waker_map, HandlerGuardState, InterestGuard, InterestHandler, InterestType, Selector,
 };
 
+/// Largest possible payload of a UDP datagram
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// Looks up the addresses of host names, by default [`LocalNetworking`]
+/// uses the resolver of the host.
+#[async_trait::async_trait]
+pub trait DnsResolver: std::fmt::Debug + Send + Sync {
+    async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>>;
+}
+
 #[derive(Debug)]
 pub struct LocalNetworking {
     selector: Arc<Selector>,
     handle: Handle,
     ruleset: Option<Ruleset>,
+    dns_cache: Option<DnsCache>,
+    resolver: Option<Arc<dyn DnsResolver>>,
 }
 
 impl LocalNetworking {
+    /// Default time for which [`LocalNetworking::with_dns_cache`] keeps the
+    /// addresses a host name resolved to
+    pub const DEFAULT_DNS_CACHE_TTL: Duration = Duration::from_secs(30);
+
     pub fn new() -> Self {
         Self {
             selector: Selector::new(),
             handle: Handle::current(),
             ruleset: None,
+            dns_cache: None,
+            resolver: None,
         }
     }
 
@@ -50,8 +70,244 @@ impl LocalNetworking {
             selector: Selector::new(),
             handle: Handle::current(),
             ruleset: Some(ruleset),
+            dns_cache: None,
+            resolver: None,
+        }
+    }
+
+    /// Caches the addresses that host names resolve to for `ttl`, at most
+    /// `max_entries` host names are kept (the least recently used ones are
+    /// evicted first).
+    pub fn with_dns_cache(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            selector: Selector::new(),
+            handle: Handle::current(),
+            ruleset: None,
+            dns_cache: Some(DnsCache::new(ttl, max_entries)),
+            resolver: None,
+        }
+    }
+
+    /// Replaces the resolver of the host that is used to look up host names
+    pub fn set_resolver(&mut self, resolver: Arc<dyn DnsResolver>) {
+        self.resolver = Some(resolver);
+    }
+
+    async fn lookup_host(&self, host: &str, port: Option<u16>) -> Result<Vec<IpAddr>> {
+        if let Some(addrs) = self.dns_cache.as_ref().and_then(|c| c.get(host, port)) {
+            return Ok(addrs);
+        }
+
+        let host_to_lookup = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:{}", host, port.unwrap_or(0))
+        };
+        let addrs = match self.resolver.as_ref() {
+            Some(resolver) => resolver.lookup(&host_to_lookup).await,
+            None => self
+                .handle
+                .spawn(tokio::net::lookup_host(host_to_lookup))
+                .await
+                .map_err(|_| NetworkError::IOError)?
+                .map(|a| a.map(|a| a.ip()).collect::<Vec<_>>()),
+        }
+        .map_err(io_err_into_net_error)?;
+
+        if let Some(dns_cache) = self.dns_cache.as_ref() {
+            dns_cache.insert(host, port, &addrs);
+        }
+        Ok(addrs)
+    }
+}
+
+/// Addresses that host names resolved to, keyed by the host name and port
+#[derive(Debug)]
+struct DnsCache {
+    ttl: Duration,
+    max_entries: usize,
+    state: Mutex<DnsCacheState>,
+}
+
+#[derive(Debug, Default)]
+struct DnsCacheState {
+    entries: HashMap<(String, Option<u16>), DnsCacheEntry>,
+    /// Incremented on every use of the cache to find the least recently
+    /// used entry
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct DnsCacheEntry {
+    addrs: Vec<IpAddr>,
+    expires: Instant,
+    last_used: u64,
+}
+
+impl DnsCache {
+    fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            state: Default::default(),
+        }
+    }
+
+    fn get(&self, host: &str, port: Option<u16>) -> Option<Vec<IpAddr>> {
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let clock = state.clock;
+        let key = (host.to_string(), port);
+        match state.entries.get_mut(&key) {
+            Some(entry) if entry.expires > Instant::now() => {
+                entry.last_used = clock;
+                Some(entry.addrs.clone())
+            }
+            Some(_) => {
+                state.entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, host: &str, port: Option<u16>, addrs: &[IpAddr]) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let now = Instant::now();
+        state.entries.retain(|_, entry| entry.expires > now);
+        while state.entries.len() >= self.max_entries {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+        let last_used = state.clock;
+        state.entries.insert(
+            (host.to_string(), port),
+            DnsCacheEntry {
+                addrs: addrs.to_vec(),
+                expires: now + self.ttl,
+                last_used,
+            },
+        );
+    }
+}
+
+/// An address that is assigned to one of the network interfaces of the host
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Debug)]
+struct HostInterfaceAddr {
+    up: bool,
+    loopback: bool,
+    addr: HostAddr,
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Debug)]
+enum HostAddr {
+    Ip(IpCidr),
+    Mac([u8; 6]),
+}
+
+/// Enumerates the addresses of the network interfaces of the host, this is
+/// best-effort and fails with [`io::ErrorKind::Unsupported`] on platforms
+/// where it is not available.
+#[cfg(unix)]
+fn host_interface_addrs() -> io::Result<Vec<HostInterfaceAddr>> {
+    let mut ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut ifaddrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut ret = Vec::new();
+    let mut cur = ifaddrs;
+    while let Some(ifa) = unsafe { cur.as_ref() } {
+        cur = ifa.ifa_next;
+        if ifa.ifa_addr.is_null() {
+            continue;
         }
+        let addr = match unsafe { (*ifa.ifa_addr).sa_family } as libc::c_int {
+            libc::AF_INET => {
+                let addr = unsafe { *(ifa.ifa_addr as *const libc::sockaddr_in) };
+                let prefix = match ifa.ifa_netmask.is_null() {
+                    true => 32,
+                    false => {
+                        let mask = unsafe { *(ifa.ifa_netmask as *const libc::sockaddr_in) };
+                        mask.sin_addr.s_addr.count_ones() as u8
+                    }
+                };
+                HostAddr::Ip(IpCidr {
+                    ip: Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)).into(),
+                    prefix,
+                })
+            }
+            libc::AF_INET6 => {
+                let addr = unsafe { *(ifa.ifa_addr as *const libc::sockaddr_in6) };
+                let prefix = match ifa.ifa_netmask.is_null() {
+                    true => 128,
+                    false => {
+                        let mask = unsafe { *(ifa.ifa_netmask as *const libc::sockaddr_in6) };
+                        mask.sin6_addr
+                            .s6_addr
+                            .iter()
+                            .map(|b| b.count_ones() as u8)
+                            .sum()
+                    }
+                };
+                HostAddr::Ip(IpCidr {
+                    ip: Ipv6Addr::from(addr.sin6_addr.s6_addr).into(),
+                    prefix,
+                })
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            libc::AF_PACKET => {
+                let addr = unsafe { *(ifa.ifa_addr as *const libc::sockaddr_ll) };
+                if addr.sll_halen != 6 {
+                    continue;
+                }
+                let mut mac = [0u8; 6];
+                mac.copy_from_slice(&addr.sll_addr[..6]);
+                HostAddr::Mac(mac)
+            }
+            #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+            libc::AF_LINK => {
+                let addr = unsafe { &*(ifa.ifa_addr as *const libc::sockaddr_dl) };
+                if addr.sdl_alen != 6 {
+                    continue;
+                }
+                // The link layer address follows the name of the interface
+                let mut mac = [0u8; 6];
+                unsafe {
+                    let data = addr.sdl_data.as_ptr().add(addr.sdl_nlen as usize) as *const u8;
+                    std::ptr::copy_nonoverlapping(data, mac.as_mut_ptr(), mac.len());
+                }
+                HostAddr::Mac(mac)
+            }
+            _ => continue,
+        };
+        ret.push(HostInterfaceAddr {
+            up: ifa.ifa_flags & libc::IFF_UP as libc::c_uint != 0,
+            loopback: ifa.ifa_flags & libc::IFF_LOOPBACK as libc::c_uint != 0,
+            addr,
+        });
     }
+
+    unsafe { libc::freeifaddrs(ifaddrs) };
+    Ok(ret)
+}
+
+#[cfg(not(unix))]
+fn host_interface_addrs() -> io::Result<Vec<HostInterfaceAddr>> {
+    Err(io::ErrorKind::Unsupported.into())
 }
 
 impl Drop for LocalNetworking {
@@ -69,6 +325,60 @@ impl Default for LocalNetworking {
 #[async_trait::async_trait]
 #[allow(unused_variables)]
 impl VirtualNetworking for LocalNetworking {
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        let mut ips = Vec::new();
+        for addr in host_interface_addrs().map_err(io_err_into_net_error)? {
+            if let HostAddr::Ip(cidr) = addr.addr {
+                if !ips.contains(&cidr) {
+                    ips.push(cidr);
+                }
+            }
+        }
+        Ok(ips)
+    }
+
+    /// Returns the MAC address of the first interface of the host that is up
+    /// and is not a loopback interface
+    async fn mac(&self) -> Result<[u8; 6]> {
+        host_interface_addrs()
+            .map_err(io_err_into_net_error)?
+            .into_iter()
+            .filter(|addr| addr.up && !addr.loopback)
+            .find_map(|addr| match addr.addr {
+                HostAddr::Mac(mac) if mac != [0; 6] => Some(mac),
+                _ => None,
+            })
+            .ok_or(NetworkError::Unsupported)
+    }
+
+    /// The routing table of the host is not read, only the networks that the
+    /// interfaces of the host are directly connected to are listed (with the
+    /// address of the interface as the router)
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        let mut routes: Vec<IpRoute> = Vec::new();
+        for addr in host_interface_addrs().map_err(io_err_into_net_error)? {
+            let HostAddr::Ip(cidr) = addr.addr else {
+                continue;
+            };
+            let Ok(network) = ipnet::IpNet::new(cidr.ip, cidr.prefix) else {
+                continue;
+            };
+            let network = IpCidr {
+                ip: network.network(),
+                prefix: cidr.prefix,
+            };
+            if routes.iter().all(|route| route.cidr != network) {
+                routes.push(IpRoute {
+                    cidr: network,
+                    via_router: cidr.ip,
+                    preferred_until: None,
+                    expires_at: None,
+                });
+            }
+        }
+        Ok(routes)
+    }
+
     async fn listen_tcp(
         &self,
         addr: SocketAddr,
@@ -77,7 +387,7 @@ impl VirtualNetworking for LocalNetworking {
         reuse_addr: bool,
     ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
         if let Some(ruleset) = self.ruleset.as_ref() {
-            if !ruleset.allows_socket(addr, Direction::Inbound) {
+            if !ruleset.allows_connection(addr, Direction::Inbound, Protocol::Tcp) {
                 tracing::warn!(%addr, "listen_tcp blocked by firewall rule");
                 return Err(NetworkError::PermissionDenied);
             }
@@ -107,7 +417,7 @@ impl VirtualNetworking for LocalNetworking {
         _reuse_addr: bool,
     ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
         if let Some(ruleset) = self.ruleset.as_ref() {
-            if !ruleset.allows_socket(addr, Direction::Inbound) {
+            if !ruleset.allows_connection(addr, Direction::Inbound, Protocol::Udp) {
                 tracing::warn!(%addr, "bind_udp blocked by firewall rule");
                 return Err(NetworkError::PermissionDenied);
             }
@@ -123,6 +433,7 @@ impl VirtualNetworking for LocalNetworking {
             handler_guard: HandlerGuardState::None,
             backlog: Default::default(),
             ruleset: self.ruleset.clone(),
+            datagram: Vec::new(),
         };
 
         // In windows we can not poll the socket as it is not supported and hence
@@ -145,7 +456,7 @@ impl VirtualNetworking for LocalNetworking {
         mut peer: SocketAddr,
     ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
         if let Some(ruleset) = self.ruleset.as_ref() {
-            if !ruleset.allows_socket(peer, Direction::Outbound) {
+            if !ruleset.allows_connection(peer, Direction::Outbound, Protocol::Tcp) {
                 tracing::warn!(%peer, "connect_tcp blocked by firewall rule");
                 return Err(NetworkError::PermissionDenied);
             }
@@ -160,6 +471,63 @@ impl VirtualNetworking for LocalNetworking {
         Ok(socket)
     }
 
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    async fn connect_tcp_with_data(
+        &self,
+        _addr: SocketAddr,
+        mut peer: SocketAddr,
+        data: &[u8],
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        if let Some(ruleset) = self.ruleset.as_ref() {
+            if !ruleset.allows_connection(peer, Direction::Outbound, Protocol::Tcp) {
+                tracing::warn!(%peer, "connect_tcp_with_data blocked by firewall rule");
+                return Err(NetworkError::PermissionDenied);
+            }
+        }
+
+        let socket = socket2::Socket::new(
+            socket2::Domain::for_address(peer),
+            socket2::Type::STREAM,
+            Some(socket2::Protocol::TCP),
+        )
+        .map_err(io_err_into_net_error)?;
+        socket
+            .set_nonblocking(true)
+            .map_err(io_err_into_net_error)?;
+
+        // The kernel only puts the data in the SYN when it holds a fast open
+        // cookie for the peer, otherwise it just starts the connection (and
+        // asks for a cookie) so the data has to be sent once it is established
+        let peer_addr = socket2::SockAddr::from(peer);
+        let mut sent = match socket.send_to_with_flags(data, &peer_addr, libc::MSG_FASTOPEN) {
+            Ok(amt) => amt,
+            Err(err) if err.raw_os_error() == Some(libc::EINPROGRESS) => 0,
+            Err(err) if err.raw_os_error() == Some(libc::EOPNOTSUPP) => {
+                // Fast open is switched off on this host
+                match socket.connect(&peer_addr) {
+                    Ok(()) => {}
+                    Err(err) if err.raw_os_error() == Some(libc::EINPROGRESS) => {}
+                    Err(err) => return Err(io_err_into_net_error(err)),
+                }
+                0
+            }
+            Err(err) => return Err(io_err_into_net_error(err)),
+        };
+
+        let stream = mio::net::TcpStream::from_std(socket.into());
+        if let Ok(p) = stream.peer_addr() {
+            peer = p;
+        }
+        let mut socket = LocalTcpStream::new(self.selector.clone(), stream, peer);
+        while sent < data.len() {
+            match socket.send(&data[sent..]).await? {
+                0 => return Err(NetworkError::ConnectionReset),
+                amt => sent += amt,
+            }
+        }
+        Ok(Box::new(socket))
+    }
+
     async fn resolve(
         &self,
         host: &str,
@@ -173,18 +541,7 @@ impl VirtualNetworking for LocalNetworking {
             }
         }
 
-        let host_to_lookup = if host.contains(':') {
-            host.to_string()
-        } else {
-            format!("{}:{}", host, port.unwrap_or(0))
-        };
-        let addrs = self
-            .handle
-            .spawn(tokio::net::lookup_host(host_to_lookup))
-            .await
-            .map_err(|_| NetworkError::IOError)?
-            .map(|a| a.map(|a| a.ip()).collect::<Vec<_>>())
-            .map_err(io_err_into_net_error)?;
+        let addrs = self.lookup_host(host, port).await?;
 
         if let Some(ruleset) = self.ruleset.as_ref() {
             if let Err(e) = ruleset.expand_domain(host, &addrs) {
@@ -214,7 +571,7 @@ impl LocalTcpListener {
         match self.stream.accept().map_err(io_err_into_net_error) {
             Ok((stream, addr)) => {
                 if let Some(ruleset) = self.ruleset.as_ref() {
-                    if !ruleset.allows_socket(addr, Direction::Outbound) {
+                    if !ruleset.allows_connection(addr, Direction::Outbound, Protocol::Tcp) {
                         tracing::warn!(%addr, "try_accept blocked by firewall rule");
                         return Err(NetworkError::PermissionDenied);
                     }
@@ -291,6 +648,25 @@ impl VirtualTcpListener for LocalTcpListener {
             .map(|ttl| ttl as u8)
             .map_err(io_err_into_net_error)
     }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn set_fastopen(&mut self, queue_len: u32) -> Result<()> {
+        let val = queue_len as libc::c_int;
+        let payload = &val as *const libc::c_int as *const libc::c_void;
+        let err = unsafe {
+            libc::setsockopt(
+                self.stream.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_FASTOPEN,
+                payload,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if err == -1 {
+            return Err(io_err_into_net_error(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
 }
 
 impl LocalTcpListener {
@@ -499,6 +875,18 @@ impl VirtualTcpSocket for LocalTcpStream {
         Err(NetworkError::Unsupported)
     }
 
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn set_user_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.with_sock_ref(|s| s.set_tcp_user_timeout(timeout))
+            .map_err(io_err_into_net_error)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn user_timeout(&self) -> Result<Option<Duration>> {
+        self.with_sock_ref(|s| s.tcp_user_timeout())
+            .map_err(io_err_into_net_error)
+    }
+
     fn addr_peer(&self) -> Result<SocketAddr> {
         Ok(self.addr)
     }
@@ -558,6 +946,47 @@ impl VirtualConnectedSocket for LocalTcpStream {
 
         self.stream.read(buf).map_err(io_err_into_net_error)
     }
+
+    fn try_send_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        let ret = self
+            .stream
+            .write_vectored(bufs)
+            .map_err(io_err_into_net_error);
+        match &ret {
+            Ok(0) | Err(NetworkError::WouldBlock) => {
+                if let HandlerGuardState::WakerMap(_, map) = &mut self.handler_guard {
+                    map.pop(InterestType::Writable);
+                }
+            }
+            _ => {}
+        }
+        ret
+    }
+
+    fn try_recv_vectored(&mut self, bufs: &mut [&mut [MaybeUninit<u8>]]) -> Result<usize> {
+        if !self.buffer.is_empty() {
+            let mut total = 0;
+            for buf in bufs.iter_mut() {
+                let buf: &mut [u8] = unsafe { std::mem::transmute(&mut **buf) };
+                let amt = buf.len().min(self.buffer.len());
+                buf[..amt].copy_from_slice(&self.buffer[..amt]);
+                self.buffer.advance(amt);
+                total += amt;
+                if self.buffer.is_empty() {
+                    break;
+                }
+            }
+            return Ok(total);
+        }
+
+        let mut slices: Vec<IoSliceMut<'_>> = bufs
+            .iter_mut()
+            .map(|buf| IoSliceMut::new(unsafe { std::mem::transmute(&mut **buf) }))
+            .collect();
+        self.stream
+            .read_vectored(&mut slices)
+            .map_err(io_err_into_net_error)
+    }
 }
 
 impl VirtualSocket for LocalTcpStream {
@@ -602,6 +1031,21 @@ impl VirtualSocket for LocalTcpStream {
 
         Ok(())
     }
+
+    fn take_error(&mut self) -> Result<Option<NetworkError>> {
+        self.with_sock_ref(|s| s.take_error())
+            .map(|err| err.map(io_err_into_net_error))
+            .map_err(io_err_into_net_error)
+    }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        #[cfg(not(target_os = "windows"))]
+        let queued =
+            libc_bytes_available(self.stream.as_raw_fd()).map_err(io_err_into_net_error)?;
+        #[cfg(target_os = "windows")]
+        let queued = 0;
+        Ok(self.buffer.len() + queued)
+    }
 }
 
 impl LocalTcpStream {
@@ -714,6 +1158,16 @@ fn libc_poll(fd: RawFd, events: libc::c_short) -> Option<libc::c_short> {
     }
 }
 
+#[cfg(not(target_os = "windows"))]
+fn libc_bytes_available(fd: RawFd) -> io::Result<usize> {
+    let mut amt: libc::c_int = 0;
+    let ret = unsafe { libc::ioctl(fd, libc::FIONREAD, &mut amt) };
+    match ret {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(amt as usize),
+    }
+}
+
 #[derive(Debug)]
 pub struct LocalUdpSocket {
     socket: mio::net::UdpSocket,
@@ -723,6 +1177,9 @@ pub struct LocalUdpSocket {
     handler_guard: HandlerGuardState,
     backlog: VecDeque<(BytesMut, SocketAddr)>,
     ruleset: Option<Ruleset>,
+    /// Scratch buffer for receiving datagrams that may not fit into the
+    /// caller's buffer, it is allocated on first use and then reused
+    datagram: Vec<u8>,
 }
 
 impl LocalUdpSocket {
@@ -824,7 +1281,7 @@ impl VirtualUdpSocket for LocalUdpSocket {
 impl VirtualConnectionlessSocket for LocalUdpSocket {
     fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
         if let Some(ruleset) = self.ruleset.as_ref() {
-            if !ruleset.allows_socket(addr, Direction::Outbound) {
+            if !ruleset.allows_connection(addr, Direction::Outbound, Protocol::Udp) {
                 tracing::warn!(%addr, "try_send blocked by firewall rule");
                 return Err(NetworkError::PermissionDenied);
             }
@@ -849,6 +1306,67 @@ impl VirtualConnectionlessSocket for LocalUdpSocket {
         let buf: &mut [u8] = unsafe { std::mem::transmute(buf) };
         self.socket.recv_from(buf).map_err(io_err_into_net_error)
     }
+
+    fn try_recv_from_with_size(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> Result<(usize, usize, SocketAddr)> {
+        if buf.len() >= MAX_DATAGRAM_SIZE {
+            let (amt, addr) = self.try_recv_from(buf)?;
+            return Ok((amt, amt, addr));
+        }
+
+        // The operating system silently drops whatever does not fit into
+        // the buffer, so receive into one that fits any datagram instead
+        if self.datagram.is_empty() {
+            self.datagram.resize(MAX_DATAGRAM_SIZE, 0);
+        }
+        let (size, addr) = self
+            .socket
+            .recv_from(&mut self.datagram)
+            .map_err(io_err_into_net_error)?;
+        let amt = size.min(buf.len());
+        let buf: &mut [u8] = unsafe { std::mem::transmute(&mut buf[..amt]) };
+        buf.copy_from_slice(&self.datagram[..amt]);
+        Ok((amt, size, addr))
+    }
+
+    fn try_send_to_vectored(&mut self, bufs: &[IoSlice<'_>], addr: SocketAddr) -> Result<usize> {
+        if let Some(ruleset) = self.ruleset.as_ref() {
+            if !ruleset.allows_connection(addr, Direction::Outbound, Protocol::Udp) {
+                tracing::warn!(%addr, "try_send blocked by firewall rule");
+                return Err(NetworkError::PermissionDenied);
+            }
+        }
+
+        let ret = self
+            .with_sock_ref(|s| s.send_to_vectored(bufs, &addr.into()))
+            .map_err(io_err_into_net_error);
+        match &ret {
+            Ok(0) | Err(NetworkError::WouldBlock) => {
+                if let HandlerGuardState::WakerMap(_, map) = &mut self.handler_guard {
+                    map.pop(InterestType::Writable);
+                }
+            }
+            _ => {}
+        }
+        ret
+    }
+
+    fn try_recv_from_vectored(
+        &mut self,
+        bufs: &mut [&mut [MaybeUninit<u8>]],
+    ) -> Result<(usize, SocketAddr)> {
+        let mut slices: Vec<socket2::MaybeUninitSlice<'_>> = bufs
+            .iter_mut()
+            .map(|buf| socket2::MaybeUninitSlice::new(buf))
+            .collect();
+        let (amt, _, addr) = self
+            .with_sock_ref(|s| s.recv_from_vectored(&mut slices))
+            .map_err(io_err_into_net_error)?;
+        let addr = addr.as_socket().ok_or(NetworkError::InvalidData)?;
+        Ok((amt, addr))
+    }
 }
 
 impl VirtualSocket for LocalUdpSocket {
@@ -895,6 +1413,22 @@ impl VirtualSocket for LocalUdpSocket {
 
         Ok(())
     }
+
+    fn take_error(&mut self) -> Result<Option<NetworkError>> {
+        self.with_sock_ref(|s| s.take_error())
+            .map(|err| err.map(io_err_into_net_error))
+            .map_err(io_err_into_net_error)
+    }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        if let Some((data, _)) = self.backlog.front() {
+            return Ok(data.len());
+        }
+        #[cfg(not(target_os = "windows"))]
+        return libc_bytes_available(self.socket.as_raw_fd()).map_err(io_err_into_net_error);
+        #[cfg(target_os = "windows")]
+        return Ok(0);
+    }
 }
 
 impl LocalUdpSocket {
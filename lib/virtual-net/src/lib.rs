@@ -13,20 +13,28 @@ pub mod rx_tx;
 #[cfg(feature = "remote")]
 pub mod server;
 pub mod tcp_pair;
+#[cfg(feature = "remote")]
+pub mod tunnel;
 #[cfg(feature = "tokio")]
 #[cfg(test)]
 mod tests;
+pub mod transcript;
 
 #[cfg(feature = "remote")]
-pub use client::{RemoteNetworkingClient, RemoteNetworkingClientDriver};
-pub use composite::CompositeTcpListener;
+pub use client::{
+    ReconnectPolicy, RemoteNetworkingClient, RemoteNetworkingClientDriver, RemoteTransport,
+};
+pub use composite::{AcceptPolicy, CompositeTcpListener};
 pub use loopback::LoopbackNetworking;
 use pin_project_lite::pin_project;
 #[cfg(feature = "rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "remote")]
 pub use server::{RemoteNetworkingServer, RemoteNetworkingServerDriver};
+#[cfg(feature = "remote")]
+pub use tunnel::{TunneledNetworking, TunneledNetworkingDriver};
 use std::fmt;
+use std::io::IoSlice;
 use std::mem::MaybeUninit;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
@@ -43,6 +51,7 @@ use thiserror::Error;
 use tokio::io::AsyncRead;
 #[cfg(feature = "tokio")]
 use tokio::io::AsyncWrite;
+pub use transcript::TranscriptNetworking;
 
 pub use bytes::Bytes;
 pub use bytes::BytesMut;
@@ -210,6 +219,27 @@ pub trait VirtualNetworking: fmt::Debug + Send + Sync + 'static {
         Err(NetworkError::Unsupported)
     }
 
+    /// Opens a TCP connection like [`VirtualNetworking::connect_tcp`] and
+    /// sends `data` over it, where the platform supports TCP Fast Open the
+    /// data is carried by the connection request itself which saves a round
+    /// trip. Either way all of the data has been sent when this returns.
+    async fn connect_tcp_with_data(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+        data: &[u8],
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let mut socket = self.connect_tcp(addr, peer).await?;
+        let mut sent = 0;
+        while sent < data.len() {
+            match socket.send(&data[sent..]).await? {
+                0 => return Err(NetworkError::ConnectionReset),
+                amt => sent += amt,
+            }
+        }
+        Ok(socket)
+    }
+
     /// Performs DNS resolution for a specific hostname
     async fn resolve(
         &self,
@@ -239,6 +269,13 @@ pub trait VirtualTcpListener: VirtualIoSource + fmt::Debug + Send + Sync + 'stat
 
     /// Returns the maximum number of network hops before packets are dropped
     fn ttl(&self) -> Result<u8>;
+
+    /// Enables TCP Fast Open on this listener, allowing up to `queue_len`
+    /// connections that carry data in their connection request to be
+    /// pending at once
+    fn set_fastopen(&mut self, _queue_len: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
 }
 
 #[async_trait::async_trait]
@@ -294,6 +331,20 @@ pub trait VirtualSocket: VirtualIoSource + fmt::Debug + Send + Sync + 'static {
     /// more data. Uses a stack machine which means more than one waker
     /// can be registered
     fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()>;
+
+    /// Returns and clears the pending asynchronous error of this socket
+    /// (such as the reason that a nonblocking connect failed), this is
+    /// equivalent to `SO_ERROR`
+    fn take_error(&mut self) -> Result<Option<NetworkError>> {
+        Ok(None)
+    }
+
+    /// Returns the number of bytes that can be received right away without
+    /// consuming them or registering for a wake up, this is equivalent to
+    /// `FIONREAD`
+    fn bytes_available(&mut self) -> Result<usize> {
+        Ok(0)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -335,6 +386,42 @@ pub trait VirtualConnectedSocket: VirtualSocket + fmt::Debug + Send + Sync + 'st
 
     /// Tries to read a packet from the socket
     fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize>;
+
+    /// Tries to send out the concatenation of multiple buffers in a single
+    /// send so that datagram boundaries are preserved
+    fn try_send_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        self.try_send(&concat_slices(bufs))
+    }
+
+    /// Tries to read a packet from the socket, scattering it over
+    /// multiple buffers
+    fn try_recv_vectored(&mut self, bufs: &mut [&mut [MaybeUninit<u8>]]) -> Result<usize> {
+        let mut data = vec![MaybeUninit::uninit(); bufs.iter().map(|b| b.len()).sum()];
+        let amt = self.try_recv(&mut data)?;
+        scatter_into(&data[..amt], bufs);
+        Ok(amt)
+    }
+}
+
+/// Joins the buffers of a vectored send into one contiguous buffer
+fn concat_slices(bufs: &[IoSlice<'_>]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(bufs.iter().map(|b| b.len()).sum());
+    for buf in bufs {
+        data.extend_from_slice(buf);
+    }
+    data
+}
+
+/// Spreads received data over the buffers of a vectored receive
+fn scatter_into(mut data: &[MaybeUninit<u8>], bufs: &mut [&mut [MaybeUninit<u8>]]) {
+    for buf in bufs.iter_mut() {
+        if data.is_empty() {
+            break;
+        }
+        let amt = buf.len().min(data.len());
+        buf[..amt].copy_from_slice(&data[..amt]);
+        data = &data[amt..];
+    }
 }
 
 #[async_trait::async_trait]
@@ -449,6 +536,34 @@ pub trait VirtualConnectionlessSocket: VirtualSocket + fmt::Debug + Send + Sync
 
     /// Recv a packet from the socket
     fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)>;
+
+    /// Recv a packet from the socket, also returning the full size of the
+    /// datagram which is larger than the number of bytes copied into `buf`
+    /// when the datagram did not fit and was truncated
+    fn try_recv_from_with_size(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> Result<(usize, usize, SocketAddr)> {
+        let (amt, addr) = self.try_recv_from(buf)?;
+        Ok((amt, amt, addr))
+    }
+
+    /// Sends out the concatenation of multiple buffers as a single
+    /// datagram to a specific address
+    fn try_send_to_vectored(&mut self, bufs: &[IoSlice<'_>], addr: SocketAddr) -> Result<usize> {
+        self.try_send_to(&concat_slices(bufs), addr)
+    }
+
+    /// Recv a packet from the socket, scattering it over multiple buffers
+    fn try_recv_from_vectored(
+        &mut self,
+        bufs: &mut [&mut [MaybeUninit<u8>]],
+    ) -> Result<(usize, SocketAddr)> {
+        let mut data = vec![MaybeUninit::uninit(); bufs.iter().map(|b| b.len()).sum()];
+        let (amt, addr) = self.try_recv_from(&mut data)?;
+        scatter_into(&data[..amt], bufs);
+        Ok((amt, addr))
+    }
 }
 
 #[async_trait::async_trait]
@@ -607,6 +722,20 @@ pub trait VirtualTcpSocket: VirtualConnectedSocket + fmt::Debug + Send + Sync +
     /// the interface bypassing the routing logic.
     fn dontroute(&self) -> Result<bool>;
 
+    /// Sets how long transmitted data may remain unacknowledged before the
+    /// connection is forcibly closed (TCP_USER_TIMEOUT), this detects dead
+    /// peers much faster than keep-alive. [`None`] uses the default of the
+    /// system.
+    fn set_user_timeout(&mut self, _timeout: Option<Duration>) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    /// Returns how long transmitted data may remain unacknowledged before
+    /// the connection is forcibly closed
+    fn user_timeout(&self) -> Result<Option<Duration>> {
+        Err(NetworkError::Unsupported)
+    }
+
     /// Returns the address (IP and Port) of the peer socket that this
     /// is conencted to
     fn addr_peer(&self) -> Result<SocketAddr>;
@@ -133,6 +133,17 @@ impl VirtualNetworking for LoopbackNetworking {
 
         Ok(Box::new(listener))
     }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> crate::Result<Box<dyn VirtualTcpSocket + Sync>> {
+        match self.loopback_connect_to(addr, peer) {
+            Some(socket) => Ok(Box::new(socket)),
+            None => Err(NetworkError::ConnectionRefused),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -173,6 +184,11 @@ impl LoopbackTcpListener {
 
         half2
     }
+
+    /// Returns true if both listeners refer to the same underlying listener
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
 }
 
 impl VirtualIoSource for LoopbackTcpListener {
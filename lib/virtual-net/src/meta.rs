@@ -298,7 +298,10 @@ pub enum MessageRequest {
         addr: SocketAddr,
         req_id: Option<u64>,
     },
-    Reconnect,
+    /// First message on a new connection of a client whose previous
+    /// connection dropped, it lists the sockets the client still holds
+    /// so that the server can resume them
+    Reconnect { sockets: Vec<SocketId> },
 }
 
 /// Message sent by the server back to a client
@@ -335,4 +338,9 @@ pub enum MessageResponse {
     Closed {
         socket_id: SocketId,
     },
+    /// Reply to [`MessageRequest::Reconnect`] with the sockets the server
+    /// no longer holds, the others announced by the client were resumed
+    Reconnected {
+        lost: Vec<SocketId>,
+    },
 }
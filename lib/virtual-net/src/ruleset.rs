@@ -62,6 +62,8 @@ use std::sync::{Arc, RwLock};
 use ipnet::{Ipv4Net, Ipv6Net};
 use iprange::IpRange;
 
+use crate::IpCidr;
+
 /// Represents the errors that could happen during parsing the ruleset
 #[derive(Debug, thiserror::Error)]
 pub enum RuleParseError {
@@ -117,6 +119,20 @@ impl FromStr for Direction {
     }
 }
 
+/// Represents the transport protocol of the network traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Any,
+}
+
+impl Protocol {
+    pub fn matches(&self, protocol: Protocol) -> bool {
+        *self == Protocol::Any || *self == protocol
+    }
+}
+
 /// Specification of a port rule
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PortSpec {
@@ -273,13 +289,28 @@ pub struct IPV4Rule {
     port_spec: PortSpec,
     // Allowed direction of the traffic
     direction: Direction,
+    // Allowed protocol of the traffic
+    protocol: Protocol,
 }
 
 impl IPV4Rule {
     pub fn is_allowed(&self, ip: impl Into<Ipv4Addr>, port: u16, dir: Direction) -> bool {
+        self.is_allowed_for(ip, port, dir, Protocol::Any)
+    }
+
+    pub fn is_allowed_for(
+        &self,
+        ip: impl Into<Ipv4Addr>,
+        port: u16,
+        dir: Direction,
+        protocol: Protocol,
+    ) -> bool {
         let ip = ip.into();
 
-        self.ip_spec.matches(ip) && self.port_spec.matches(port) && self.direction.matches(dir)
+        self.ip_spec.matches(ip)
+            && self.port_spec.matches(port)
+            && self.direction.matches(dir)
+            && self.protocol.matches(protocol)
     }
 }
 
@@ -302,6 +333,17 @@ impl IPV6Spec {
             IPV6Spec::IPRange(allowed_ip_range) => allowed_ip_range.contains(&ip),
         }
     }
+
+    /// Returns `true` if all the IPs lie in the IPv4-mapped range
+    /// (`::ffff:0:0/96`), only those can match IPv4 addresses
+    pub fn is_ipv4_mapped(&self) -> bool {
+        let mapped = Ipv6Net::new(Ipv4Addr::UNSPECIFIED.to_ipv6_mapped(), 96).unwrap();
+        match self {
+            IPV6Spec::All => false,
+            IPV6Spec::IP(ip) => mapped.contains(ip),
+            IPV6Spec::IPRange(ip_range) => ip_range.iter().all(|net| mapped.contains(&net)),
+        }
+    }
 }
 
 impl FromStr for IPV6Spec {
@@ -333,13 +375,28 @@ pub struct IPV6Rule {
     port_spec: PortSpec,
     // Allowed direction of the traffic
     direction: Direction,
+    // Allowed protocol of the traffic
+    protocol: Protocol,
 }
 
 impl IPV6Rule {
     pub fn is_allowed(&self, ip: impl Into<Ipv6Addr>, port: u16, dir: Direction) -> bool {
+        self.is_allowed_for(ip, port, dir, Protocol::Any)
+    }
+
+    pub fn is_allowed_for(
+        &self,
+        ip: impl Into<Ipv6Addr>,
+        port: u16,
+        dir: Direction,
+        protocol: Protocol,
+    ) -> bool {
         let ip = ip.into();
 
-        self.ip_spec.matches(ip) && self.port_spec.matches(port) && self.direction.matches(dir)
+        self.ip_spec.matches(ip)
+            && self.port_spec.matches(port)
+            && self.direction.matches(dir)
+            && self.protocol.matches(protocol)
     }
 }
 
@@ -359,12 +416,35 @@ pub enum Rule {
 impl Rule {
     /// Returns `true` if this rule allows accessing `socket_addr` in the specific `direction`
     pub fn allows_socket(&self, socket_addr: SocketAddr, direction: Direction) -> bool {
-        let ip = socket_addr.ip();
+        self.allows_connection(socket_addr, direction, Protocol::Any)
+    }
+
+    /// Returns `true` if this rule allows accessing `socket_addr` in the specific `direction`
+    /// over `protocol`
+    ///
+    /// IPv4-mapped IPv6 addresses are treated as the IPv4 address they map, which is matched by
+    /// IPv4 rules as is and by IPv6 rules in its mapped form
+    pub fn allows_connection(
+        &self,
+        socket_addr: SocketAddr,
+        direction: Direction,
+        protocol: Protocol,
+    ) -> bool {
+        let ip = unmap_ip(socket_addr.ip());
         let port = socket_addr.port();
 
         match (self, ip) {
-            (Rule::IPV4(rule), IpAddr::V4(ip)) => rule.is_allowed(ip, port, direction),
-            (Rule::IPV6(rule), IpAddr::V6(ip)) => rule.is_allowed(ip, port, direction),
+            (Rule::IPV4(rule), IpAddr::V4(ip)) => {
+                rule.is_allowed_for(ip, port, direction, protocol)
+            }
+            // Only rules for the IPv4-mapped range apply to IPv4 peers, so
+            // that a rule like `::/0` keeps covering IPv6 traffic alone
+            (Rule::IPV6(rule), IpAddr::V4(ip)) if rule.ip_spec.is_ipv4_mapped() => {
+                rule.is_allowed_for(ip.to_ipv6_mapped(), port, direction, protocol)
+            }
+            (Rule::IPV6(rule), IpAddr::V6(ip)) => {
+                rule.is_allowed_for(ip, port, direction, protocol)
+            }
             _ => false,
         }
     }
@@ -380,8 +460,19 @@ impl Rule {
 
     /// Returns `true` if this rule blocks accessing `socket_addr` in the specific `direction`
     pub fn blocks_socket(&self, socket_addr: SocketAddr, direction: Direction) -> bool {
+        self.blocks_connection(socket_addr, direction, Protocol::Any)
+    }
+
+    /// Returns `true` if this rule blocks accessing `socket_addr` in the specific `direction`
+    /// over `protocol`
+    pub fn blocks_connection(
+        &self,
+        socket_addr: SocketAddr,
+        direction: Direction,
+        protocol: Protocol,
+    ) -> bool {
         if let Rule::Neg(rule) = self {
-            rule.allows_socket(socket_addr, direction)
+            rule.allows_connection(socket_addr, direction, protocol)
         } else {
             false
         }
@@ -424,6 +515,17 @@ impl Rule {
     }
 }
 
+/// Turns an IPv4-mapped IPv6 address into the IPv4 address it maps
+fn unmap_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => IpAddr::V6(ip),
+        },
+        ip => ip,
+    }
+}
+
 fn parse_enclosed(s: &str, left: char, right: char) -> Option<&str> {
     match (s.find(left), s.rfind(right)) {
         (Some(left_idx), Some(right_idx)) if left_idx < right_idx => {
@@ -472,6 +574,7 @@ fn parse_ipv4_rule(s: &str) -> Result<Vec<IPV4Rule>, RuleParseError> {
                 ip_spec: ip.clone(),
                 port_spec: port.clone(),
                 direction,
+                protocol: Protocol::Any,
             });
         }
     }
@@ -511,6 +614,7 @@ fn parse_ipv6_rule(s: &str) -> Result<Vec<IPV6Rule>, RuleParseError> {
                 ip_spec: ip.clone(),
                 port_spec: port.clone(),
                 direction,
+                protocol: Protocol::Any,
             });
         }
     }
@@ -671,20 +775,85 @@ pub struct Ruleset {
 }
 
 impl Ruleset {
+    /// Creates a ruleset that denies everything, access has to be granted with [`Ruleset::allow`]
+    pub fn deny_all() -> Self {
+        Self {
+            rules: Default::default(),
+        }
+    }
+
+    /// Allows the traffic over `protocol` (in both directions) with the addresses in `cidr` on
+    /// the ports in `ports`
+    ///
+    /// An IPv4-mapped IPv6 `cidr` is added as the IPv4 network it maps
+    pub fn allow(self, cidr: IpCidr, ports: RangeInclusive<u16>, protocol: Protocol) -> Self {
+        let port_spec = PortSpec::PortRange(ports);
+        let direction = Direction::Bidirectional;
+
+        let rule = match unmap_ip(cidr.ip) {
+            IpAddr::V4(ip) => {
+                // The prefix of a mapped network covers the 96 bits of the mapping
+                let prefix = if cidr.ip.is_ipv6() {
+                    cidr.prefix.saturating_sub(96)
+                } else {
+                    cidr.prefix
+                };
+                let mut ip_range = IpRange::<Ipv4Net>::new();
+                ip_range.add(Ipv4Net::new(ip, prefix.min(32)).unwrap().trunc());
+
+                Rule::IPV4(IPV4Rule {
+                    ip_spec: IPV4Spec::IPRange(ip_range),
+                    port_spec,
+                    direction,
+                    protocol,
+                })
+            }
+            IpAddr::V6(ip) => {
+                let mut ip_range = IpRange::<Ipv6Net>::new();
+                ip_range.add(Ipv6Net::new(ip, cidr.prefix.min(128)).unwrap().trunc());
+
+                Rule::IPV6(IPV6Rule {
+                    ip_spec: IPV6Spec::IPRange(ip_range),
+                    port_spec,
+                    direction,
+                    protocol,
+                })
+            }
+        };
+        self.rules.write().unwrap().push(rule);
+
+        self
+    }
+
     /// Returns `true` if at least one rule allows accessing `socket_addr` in the specific `direction`
-    /// and no rule blocks it
+    /// and no rule blocks it, rules for a specific [`Protocol`] are not considered
     pub fn allows_socket(&self, addr: impl Into<SocketAddr>, dir: Direction) -> bool {
+        self.allows_connection(addr, dir, Protocol::Any)
+    }
+
+    /// Returns `true` if at least one rule allows accessing `socket_addr` in the specific `direction`
+    /// over `protocol` and no rule blocks it
+    pub fn allows_connection(
+        &self,
+        addr: impl Into<SocketAddr>,
+        dir: Direction,
+        protocol: Protocol,
+    ) -> bool {
         let addr = addr.into();
 
         let is_allowed = {
             let ruleset = self.rules.read().unwrap();
 
-            let is_blacklisted = ruleset.iter().any(|r| r.blocks_socket(addr, dir));
+            let is_blacklisted = ruleset
+                .iter()
+                .any(|r| r.blocks_connection(addr, dir, protocol));
             if is_blacklisted {
                 return false;
             }
 
-            ruleset.iter().any(|r| r.allows_socket(addr, dir))
+            ruleset
+                .iter()
+                .any(|r| r.allows_connection(addr, dir, protocol))
         };
 
         is_allowed
@@ -750,11 +919,13 @@ impl Ruleset {
                     ip_spec: IPV4Spec::IP(*ip),
                     port_spec: port_spec.clone(),
                     direction: Direction::Outbound,
+                    protocol: Protocol::Any,
                 }),
                 IpAddr::V6(ip) => Rule::IPV6(IPV6Rule {
                     ip_spec: IPV6Spec::IP(*ip),
                     port_spec: port_spec.clone(),
                     direction: Direction::Outbound,
+                    protocol: Protocol::Any,
                 }),
             };
 
@@ -1490,4 +1661,102 @@ mod tests {
             Direction::Inbound
         ));
     }
+
+    #[test]
+    fn ruleset_deny_all() {
+        let ruleset = Ruleset::deny_all();
+
+        assert!(!ruleset.allows_connection(
+            ([127, 0, 0, 1], 80),
+            Direction::Outbound,
+            Protocol::Tcp
+        ));
+        assert!(!ruleset.allows_domain("a.com"));
+    }
+
+    #[test]
+    fn ruleset_allow_port_range() {
+        let ruleset = Ruleset::deny_all().allow(
+            IpCidr {
+                ip: Ipv4Addr::new(10, 0, 0, 0).into(),
+                prefix: 8,
+            },
+            8000..=8080,
+            Protocol::Tcp,
+        );
+
+        let out = Direction::Outbound;
+        assert!(ruleset.allows_connection(([10, 1, 2, 3], 8000), out, Protocol::Tcp));
+        assert!(ruleset.allows_connection(([10, 1, 2, 3], 8080), out, Protocol::Tcp));
+        assert!(ruleset.allows_connection(
+            ([10, 1, 2, 3], 8080),
+            Direction::Inbound,
+            Protocol::Tcp
+        ));
+        assert!(!ruleset.allows_connection(([10, 1, 2, 3], 7999), out, Protocol::Tcp));
+        assert!(!ruleset.allows_connection(([10, 1, 2, 3], 8081), out, Protocol::Tcp));
+        assert!(!ruleset.allows_connection(([11, 0, 0, 1], 8000), out, Protocol::Tcp));
+        assert!(!ruleset.allows_connection(([10, 1, 2, 3], 8000), out, Protocol::Udp));
+    }
+
+    #[test]
+    fn ruleset_ipv6_rules_do_not_cover_ipv4() {
+        let out = Direction::Outbound;
+        let mapped: Ipv6Addr = "::ffff:10.0.0.1".parse().unwrap();
+
+        let ruleset = Ruleset::from_str("ipv6:allow=[::/0]:*").unwrap();
+        assert!(ruleset.allows_connection((Ipv6Addr::LOCALHOST, 80), out, Protocol::Tcp));
+        assert!(!ruleset.allows_connection(([10, 0, 0, 1], 80), out, Protocol::Tcp));
+        assert!(!ruleset.allows_connection((mapped, 80), out, Protocol::Tcp));
+
+        // Nor does a negated one block IPv4 traffic
+        let ruleset = Ruleset::from_str("ipv6:deny=[::/0]:*,ipv4:allow=[0.0.0.0/0]:*").unwrap();
+        assert!(ruleset.allows_connection(([10, 0, 0, 1], 80), out, Protocol::Tcp));
+        assert!(!ruleset.allows_connection((Ipv6Addr::LOCALHOST, 80), out, Protocol::Tcp));
+    }
+
+    #[test]
+    fn ruleset_allow_ipv4_mapped() {
+        let mapped: Ipv6Addr = "::ffff:10.0.0.1".parse().unwrap();
+        let out = Direction::Outbound;
+
+        // An IPv4 entry allows the mapped form of its addresses
+        let ruleset = Ruleset::deny_all().allow(
+            IpCidr {
+                ip: Ipv4Addr::new(10, 0, 0, 0).into(),
+                prefix: 24,
+            },
+            80..=80,
+            Protocol::Udp,
+        );
+        assert!(ruleset.allows_connection((mapped, 80), out, Protocol::Udp));
+        assert!(ruleset.allows_connection(([10, 0, 0, 1], 80), out, Protocol::Udp));
+
+        // A mapped entry allows the IPv4 addresses it maps
+        let ruleset = Ruleset::deny_all().allow(
+            IpCidr {
+                ip: "::ffff:10.0.0.0".parse::<Ipv6Addr>().unwrap().into(),
+                prefix: 120,
+            },
+            80..=80,
+            Protocol::Udp,
+        );
+        assert!(ruleset.allows_connection(([10, 0, 0, 1], 80), out, Protocol::Udp));
+        assert!(ruleset.allows_connection((mapped, 80), out, Protocol::Udp));
+        assert!(!ruleset.allows_connection(([10, 0, 1, 1], 80), out, Protocol::Udp));
+
+        // Parsed IPv6 rules with mapped addresses behave the same way
+        let ruleset = Ruleset::from_str("ipv6:deny=[::ffff:10.0.0.1]:80").unwrap();
+        let ruleset = ruleset.allow(
+            IpCidr {
+                ip: Ipv4Addr::new(10, 0, 0, 0).into(),
+                prefix: 24,
+            },
+            80..=80,
+            Protocol::Any,
+        );
+        assert!(!ruleset.allows_connection(([10, 0, 0, 1], 80), out, Protocol::Tcp));
+        assert!(!ruleset.allows_connection((mapped, 80), out, Protocol::Tcp));
+        assert!(ruleset.allows_connection(([10, 0, 0, 2], 80), out, Protocol::Tcp));
+    }
 }
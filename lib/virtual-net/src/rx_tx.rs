@@ -19,10 +19,24 @@ use tokio::{
         oneshot,
     },
 };
+use tokio_util::codec::LengthDelimitedCodec;
 use virtual_mio::InlineWaker;
 
 use crate::{io_err_into_net_error, NetworkError};
 
+/// Largest frame that is accepted from the other side of a connection
+/// unless a different limit is configured
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Codec that splits a byte stream into length prefixed frames, frames that
+/// claim to be larger than `max_frame_size` fail with an `InvalidData` error
+/// before anything is allocated for them (which ends the connection).
+pub(crate) fn frame_codec(max_frame_size: usize) -> LengthDelimitedCodec {
+    LengthDelimitedCodec::builder()
+        .max_frame_length(max_frame_size)
+        .new_codec()
+}
+
 #[derive(Debug, Clone, Default)]
 pub(crate) struct RemoteTxWakers {
     wakers: Arc<Mutex<Vec<Waker>>>,
@@ -61,8 +75,9 @@ impl AsyncWrite for FailOnWrite {
 }
 
 pub(crate) type StreamSink<T> = Pin<Box<dyn Sink<T, Error = std::io::Error> + Send + 'static>>;
+pub(crate) type RemoteStream<T> = Pin<Box<dyn Stream<Item = std::io::Result<T>> + Send + 'static>>;
 
-#[derive(derive_more::Debug)]
+#[derive(derive_more::Debug, Clone)]
 pub(crate) enum RemoteTx<T>
 where
     T: Serialize,
@@ -113,6 +128,19 @@ impl<T> RemoteTx<T>
 where
     T: Serialize + Send + Sync + 'static,
 {
+    /// Channel of the background work that the driver of this side of
+    /// the connection processes
+    pub(crate) fn work(&self) -> &mpsc::UnboundedSender<BoxFuture<'static, ()>> {
+        match self {
+            RemoteTx::Mpsc { work, .. } => work,
+            RemoteTx::Stream { work, .. } => work,
+            #[cfg(feature = "hyper")]
+            RemoteTx::HyperWebSocket { work, .. } => work,
+            #[cfg(feature = "tokio-tungstenite")]
+            RemoteTx::TokioWebSocket { work, .. } => work,
+        }
+    }
+
     pub(crate) async fn send(&self, req: T) -> Result<()> {
         match self {
             RemoteTx::Mpsc { tx, .. } => tx
@@ -517,7 +545,7 @@ where
     },
     Stream {
         #[debug(ignore)]
-        rx: Pin<Box<dyn Stream<Item = std::io::Result<T>> + Send + 'static>>,
+        rx: RemoteStream<T>,
     },
     #[cfg(feature = "hyper")]
     HyperWebSocket {
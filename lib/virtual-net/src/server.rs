@@ -1,5 +1,8 @@
 use crate::meta::{FrameSerializationFormat, ResponseType};
-use crate::rx_tx::{RemoteRx, RemoteTx, RemoteTxWakers};
+use crate::rx_tx::{
+    frame_codec, RemoteRx, RemoteStream, RemoteTx, RemoteTxWakers, StreamSink,
+    DEFAULT_MAX_FRAME_SIZE,
+};
 use crate::{
     meta::{MessageRequest, MessageResponse, RequestType, SocketId},
     VirtualNetworking, VirtualRawSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
@@ -9,7 +12,6 @@ use futures_util::stream::FuturesOrdered;
 #[cfg(any(feature = "hyper", feature = "tokio-tungstenite"))]
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{future::BoxFuture, StreamExt};
-use futures_util::{Sink, Stream};
 use std::collections::HashSet;
 use std::mem::MaybeUninit;
 use std::net::IpAddr;
@@ -23,7 +25,10 @@ use std::{
     future::Future,
     net::SocketAddr,
     pin::Pin,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
     task::{Context, Poll},
 };
 use tokio::{
@@ -38,7 +43,7 @@ use tokio_serde::formats::SymmetricalJson;
 #[cfg(feature = "messagepack")]
 use tokio_serde::formats::SymmetricalMessagePack;
 use tokio_serde::SymmetricallyFramed;
-use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
+use tokio_util::codec::{FramedRead, FramedWrite};
 use virtual_mio::InterestHandler;
 
 type BackgroundTask = Option<BoxFuture<'static, ()>>;
@@ -48,6 +53,8 @@ pub struct RemoteNetworkingServer {
     #[allow(dead_code)]
     common: Arc<RemoteAdapterCommon>,
     inner: Arc<dyn VirtualNetworking + Send + Sync + 'static>,
+    // Largest frame accepted from the client, reused when it reconnects
+    max_frame_size: usize,
 }
 
 impl RemoteNetworkingServer {
@@ -58,7 +65,7 @@ impl RemoteNetworkingServer {
         inner: Arc<dyn VirtualNetworking + Send + Sync + 'static>,
     ) -> (Self, RemoteNetworkingServerDriver) {
         let common = RemoteAdapterCommon {
-            tx,
+            tx: RwLock::new(tx),
             rx: Mutex::new(rx),
             sockets: Default::default(),
             socket_accept: Default::default(),
@@ -67,16 +74,44 @@ impl RemoteNetworkingServer {
         };
         let common = Arc::new(common);
 
-        let driver = RemoteNetworkingServerDriver {
-            more_work: work,
-            tasks: Default::default(),
-            common: common.clone(),
-            inner: inner.clone(),
+        let networking = Self {
+            common,
+            inner,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
         };
-        let networking = Self { common, inner };
+        let driver = networking.new_driver(work);
 
         (networking, driver)
     }
+
+    fn new_driver(
+        &self,
+        work: mpsc::UnboundedReceiver<BoxFuture<'static, ()>>,
+    ) -> RemoteNetworkingServerDriver {
+        RemoteNetworkingServerDriver {
+            more_work: work,
+            tasks: Default::default(),
+            common: self.common.clone(),
+            inner: self.inner.clone(),
+            max_in_flight: RemoteNetworkingServerDriver::DEFAULT_MAX_IN_FLIGHT,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// Switches over to a new connection of the client, the sockets of
+    /// the client are kept so that it can resume them (it announces them
+    /// as the first message on the new connection). The driver of the
+    /// previous connection must have finished.
+    fn resume(
+        &self,
+        tx: RemoteTx<MessageResponse>,
+        rx: RemoteRx<MessageRequest>,
+        work: mpsc::UnboundedReceiver<BoxFuture<'static, ()>>,
+    ) -> RemoteNetworkingServerDriver {
+        *self.common.rx.lock().unwrap() = rx;
+        *self.common.tx.write().unwrap() = tx;
+        self.new_driver(work)
+    }
     /// Creates a new interface on the remote location using
     /// a unique interface ID and a pair of channels
     pub fn new_from_mpsc(
@@ -84,19 +119,19 @@ impl RemoteNetworkingServer {
         rx: mpsc::Receiver<MessageRequest>,
         inner: Arc<dyn VirtualNetworking + Send + Sync + 'static>,
     ) -> (Self, RemoteNetworkingServerDriver) {
-        let (tx_work, rx_work) = mpsc::unbounded_channel();
-        let tx_wakers = RemoteTxWakers::default();
+        let (tx, rx, work) = mpsc_parts(tx, rx);
+        Self::new(tx, rx, work, inner)
+    }
 
-        let tx = RemoteTx::Mpsc {
-            tx,
-            work: tx_work,
-            wakers: tx_wakers.clone(),
-        };
-        let rx = RemoteRx::Mpsc {
-            rx,
-            wakers: tx_wakers,
-        };
-        Self::new(tx, rx, rx_work, inner)
+    /// Resumes the sockets of a client that reconnected over a new pair
+    /// of channels (see [`crate::RemoteNetworkingClientDriver::with_reconnect`])
+    pub fn resume_from_mpsc(
+        &self,
+        tx: mpsc::Sender<MessageResponse>,
+        rx: mpsc::Receiver<MessageRequest>,
+    ) -> RemoteNetworkingServerDriver {
+        let (tx, rx, work) = mpsc_parts(tx, rx);
+        self.resume(tx, rx, work)
     }
 
     /// Creates a new interface on the remote location using
@@ -111,57 +146,44 @@ impl RemoteNetworkingServer {
         TX: AsyncWrite + Send + 'static,
         RX: AsyncRead + Send + 'static,
     {
-        let tx = FramedWrite::new(tx, LengthDelimitedCodec::new());
-        let tx: Pin<Box<dyn Sink<MessageResponse, Error = std::io::Error> + Send + 'static>> =
-            match format {
-                FrameSerializationFormat::Bincode => {
-                    Box::pin(SymmetricallyFramed::new(tx, SymmetricalBincode::default()))
-                }
-                #[cfg(feature = "json")]
-                FrameSerializationFormat::Json => {
-                    Box::pin(SymmetricallyFramed::new(tx, SymmetricalJson::default()))
-                }
-                #[cfg(feature = "messagepack")]
-                FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
-                    tx,
-                    SymmetricalMessagePack::default(),
-                )),
-                #[cfg(feature = "cbor")]
-                FrameSerializationFormat::Cbor => {
-                    Box::pin(SymmetricallyFramed::new(tx, SymmetricalCbor::default()))
-                }
-            };
-
-        let rx = FramedRead::new(rx, LengthDelimitedCodec::new());
-        let rx: Pin<Box<dyn Stream<Item = std::io::Result<MessageRequest>> + Send + 'static>> =
-            match format {
-                FrameSerializationFormat::Bincode => {
-                    Box::pin(SymmetricallyFramed::new(rx, SymmetricalBincode::default()))
-                }
-                #[cfg(feature = "json")]
-                FrameSerializationFormat::Json => {
-                    Box::pin(SymmetricallyFramed::new(rx, SymmetricalJson::default()))
-                }
-                #[cfg(feature = "messagepack")]
-                FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
-                    rx,
-                    SymmetricalMessagePack::default(),
-                )),
-                #[cfg(feature = "cbor")]
-                FrameSerializationFormat::Cbor => {
-                    Box::pin(SymmetricallyFramed::new(rx, SymmetricalCbor::default()))
-                }
-            };
+        Self::new_from_async_io_with_max_frame_size(tx, rx, format, DEFAULT_MAX_FRAME_SIZE, inner)
+    }
 
-        let (tx_work, rx_work) = mpsc::unbounded_channel();
+    /// Same as [`Self::new_from_async_io`] but frames from the client that
+    /// are larger than `max_frame_size` close the connection (instead of
+    /// those larger than [`DEFAULT_MAX_FRAME_SIZE`])
+    pub fn new_from_async_io_with_max_frame_size<TX, RX>(
+        tx: TX,
+        rx: RX,
+        format: FrameSerializationFormat,
+        max_frame_size: usize,
+        inner: Arc<dyn VirtualNetworking + Send + Sync + 'static>,
+    ) -> (Self, RemoteNetworkingServerDriver)
+    where
+        TX: AsyncWrite + Send + 'static,
+        RX: AsyncRead + Send + 'static,
+    {
+        let (tx, rx, work) = async_io_parts(tx, rx, format, max_frame_size);
+        let (mut networking, driver) = Self::new(tx, rx, work, inner);
+        networking.max_frame_size = max_frame_size;
+        (networking, driver)
+    }
 
-        let tx = RemoteTx::Stream {
-            tx: Arc::new(tokio::sync::Mutex::new(tx)),
-            work: tx_work,
-            wakers: RemoteTxWakers::default(),
-        };
-        let rx = RemoteRx::Stream { rx };
-        Self::new(tx, rx, rx_work, inner)
+    /// Resumes the sockets of a client that reconnected over a new pair
+    /// of streams (see [`crate::RemoteNetworkingClientDriver::with_reconnect`]),
+    /// the new connection keeps the maximum frame size of the first one
+    pub fn resume_from_async_io<TX, RX>(
+        &self,
+        tx: TX,
+        rx: RX,
+        format: FrameSerializationFormat,
+    ) -> RemoteNetworkingServerDriver
+    where
+        TX: AsyncWrite + Send + 'static,
+        RX: AsyncRead + Send + 'static,
+    {
+        let (tx, rx, work) = async_io_parts(tx, rx, format, self.max_frame_size);
+        self.resume(tx, rx, work)
     }
 
     /// Creates a new interface on the remote location using
@@ -189,6 +211,92 @@ impl RemoteNetworkingServer {
     }
 }
 
+type ConnectionParts = (
+    RemoteTx<MessageResponse>,
+    RemoteRx<MessageRequest>,
+    mpsc::UnboundedReceiver<BoxFuture<'static, ()>>,
+);
+
+fn mpsc_parts(
+    tx: mpsc::Sender<MessageResponse>,
+    rx: mpsc::Receiver<MessageRequest>,
+) -> ConnectionParts {
+    let (tx_work, rx_work) = mpsc::unbounded_channel();
+    let tx_wakers = RemoteTxWakers::default();
+
+    let tx = RemoteTx::Mpsc {
+        tx,
+        work: tx_work,
+        wakers: tx_wakers.clone(),
+    };
+    let rx = RemoteRx::Mpsc {
+        rx,
+        wakers: tx_wakers,
+    };
+    (tx, rx, rx_work)
+}
+
+fn async_io_parts<TX, RX>(
+    tx: TX,
+    rx: RX,
+    format: FrameSerializationFormat,
+    max_frame_size: usize,
+) -> ConnectionParts
+where
+    TX: AsyncWrite + Send + 'static,
+    RX: AsyncRead + Send + 'static,
+{
+    let tx = FramedWrite::new(tx, frame_codec(max_frame_size));
+    let tx: StreamSink<MessageResponse> = match format {
+        FrameSerializationFormat::Bincode => {
+            Box::pin(SymmetricallyFramed::new(tx, SymmetricalBincode::default()))
+        }
+        #[cfg(feature = "json")]
+        FrameSerializationFormat::Json => {
+            Box::pin(SymmetricallyFramed::new(tx, SymmetricalJson::default()))
+        }
+        #[cfg(feature = "messagepack")]
+        FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
+            tx,
+            SymmetricalMessagePack::default(),
+        )),
+        #[cfg(feature = "cbor")]
+        FrameSerializationFormat::Cbor => {
+            Box::pin(SymmetricallyFramed::new(tx, SymmetricalCbor::default()))
+        }
+    };
+
+    let rx = FramedRead::new(rx, frame_codec(max_frame_size));
+    let rx: RemoteStream<MessageRequest> = match format {
+        FrameSerializationFormat::Bincode => {
+            Box::pin(SymmetricallyFramed::new(rx, SymmetricalBincode::default()))
+        }
+        #[cfg(feature = "json")]
+        FrameSerializationFormat::Json => {
+            Box::pin(SymmetricallyFramed::new(rx, SymmetricalJson::default()))
+        }
+        #[cfg(feature = "messagepack")]
+        FrameSerializationFormat::MessagePack => Box::pin(SymmetricallyFramed::new(
+            rx,
+            SymmetricalMessagePack::default(),
+        )),
+        #[cfg(feature = "cbor")]
+        FrameSerializationFormat::Cbor => {
+            Box::pin(SymmetricallyFramed::new(rx, SymmetricalCbor::default()))
+        }
+    };
+
+    let (tx_work, rx_work) = mpsc::unbounded_channel();
+
+    let tx = RemoteTx::Stream {
+        tx: Arc::new(tokio::sync::Mutex::new(tx)),
+        work: tx_work,
+        wakers: RemoteTxWakers::default(),
+    };
+    let rx = RemoteRx::Stream { rx };
+    (tx, rx, rx_work)
+}
+
 #[async_trait::async_trait]
 impl VirtualNetworking for RemoteNetworkingServer {
     async fn bridge(
@@ -313,6 +421,11 @@ pin_project_lite::pin_project! {
         #[pin]
         tasks: FuturesOrdered<BoxFuture<'static, ()>>,
         inner: Arc<dyn VirtualNetworking + Send + Sync + 'static>,
+        max_in_flight: usize,
+        // Number of requests of the client whose tasks have not finished
+        // yet, `tasks` also holds finished tasks (that are queued behind
+        // slower ones) and background work that is not a request
+        in_flight: Arc<AtomicUsize>,
     }
 }
 
@@ -378,6 +491,13 @@ impl Future for RemoteNetworkingServerDriver {
                 Poll::Pending => {}
             };
 
+            // While the window of in-flight requests is full we stop reading
+            // from the transport, it then fills up which pushes back on the
+            // client. The tasks wake us up again as they complete.
+            if self.in_flight.load(Ordering::Acquire) >= self.max_in_flight {
+                return Poll::Pending;
+            }
+
             // We grab the next message sent by the client to us
             let msg = {
                 let mut rx_guard = self.common.rx.lock().unwrap();
@@ -388,7 +508,12 @@ impl Future for RemoteNetworkingServerDriver {
                     if let Some(task) = self.process(msg) {
                         // With some messages we process there are background tasks that need to
                         // be further driver to completion by the driver
-                        self.tasks.push_back(task)
+                        let in_flight = self.in_flight.clone();
+                        in_flight.fetch_add(1, Ordering::AcqRel);
+                        self.tasks.push_back(Box::pin(async move {
+                            task.await;
+                            in_flight.fetch_sub(1, Ordering::AcqRel);
+                        }))
                     };
                     continue;
                 }
@@ -400,6 +525,20 @@ impl Future for RemoteNetworkingServerDriver {
 }
 
 impl RemoteNetworkingServerDriver {
+    /// Default number of requests of a client that are processed at the
+    /// same time
+    pub const DEFAULT_MAX_IN_FLIGHT: usize = 128;
+
+    /// Limits the number of requests of the client that are processed at
+    /// the same time (at least one), further requests are left in the
+    /// transport until some of the requests complete.
+    ///
+    /// Defaults to [`Self::DEFAULT_MAX_IN_FLIGHT`].
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
     fn process(&mut self, msg: MessageRequest) -> BackgroundTask {
         match msg {
             MessageRequest::Send {
@@ -419,10 +558,35 @@ impl RemoteNetworkingServerDriver {
                 req,
                 req_id,
             } => self.process_socket(socket, req, req_id),
-            MessageRequest::Reconnect => None,
+            MessageRequest::Reconnect { sockets } => self.process_reconnect(sockets),
         }
     }
 
+    fn process_reconnect(&mut self, sockets: Vec<SocketId>) -> BackgroundTask {
+        let (resumed, lost): (Vec<_>, Vec<_>) = {
+            let mut guard = self.common.sockets.lock().unwrap();
+
+            // Sockets the client no longer announces were closed while it
+            // was disconnected
+            let announced: HashSet<_> = sockets.iter().copied().collect();
+            guard.retain(|socket_id, _| announced.contains(socket_id));
+
+            sockets
+                .into_iter()
+                .partition(|socket_id| guard.contains_key(socket_id))
+        };
+
+        // Reads that were signalled on the old connection would otherwise
+        // never be drained
+        {
+            let mut guard = self.common.handler.state.lock().unwrap();
+            guard.readable.extend(resumed);
+            guard.driver_wakers.drain(..).for_each(|w| w.wake());
+        }
+
+        self.common.send(MessageResponse::Reconnected { lost })
+    }
+
     fn process_send(
         &mut self,
         socket_id: SocketId,
@@ -1618,7 +1782,7 @@ type SocketMap<T> = HashMap<SocketId, T>;
 
 #[derive(Debug)]
 struct RemoteAdapterCommon {
-    tx: RemoteTx<MessageResponse>,
+    tx: RwLock<RemoteTx<MessageResponse>>,
     rx: Mutex<RemoteRx<MessageRequest>>,
     sockets: Mutex<SocketMap<RemoteAdapterSocket>>,
     socket_accept: Mutex<SocketMap<SocketId>>,
@@ -1632,7 +1796,8 @@ impl RemoteAdapterCommon {
     fn send(self: &Arc<Self>, req: MessageResponse) -> BackgroundTask {
         let this = self.clone();
         Some(Box::pin(async move {
-            if let Err(err) = this.tx.send(req).await {
+            let tx = this.tx.read().unwrap().clone();
+            if let Err(err) = tx.send(req).await {
                 tracing::debug!("failed to send message - {}", err);
             }
         }))
@@ -75,6 +75,11 @@ impl SocketBuffer {
         }
     }
 
+    fn dead_on_drop(mut self) -> Self {
+        self.dead_on_drop = true;
+        self
+    }
+
     pub fn set_push_handler(&self, mut handler: ArcInterestHandler) {
         let mut state = self.state.lock().unwrap();
         if state.state != State::Alive {
@@ -289,6 +294,11 @@ impl SocketBuffer {
         }
     }
 
+    /// Number of bytes waiting to be read
+    pub fn buffered(&self) -> usize {
+        self.state.lock().unwrap().buffer.len()
+    }
+
     pub fn max_size(&self) -> usize {
         let state = self.state.lock().unwrap();
         state.buffer.capacity()
@@ -351,22 +361,22 @@ impl TcpSocketHalf {
         addr1: SocketAddr,
         addr2: SocketAddr,
     ) -> (TcpSocketHalf, TcpSocketHalf) {
-        let mut buffer1 = SocketBuffer::new(max_buffer_size);
-        buffer1.dead_on_drop = true;
-
-        let mut buffer2 = SocketBuffer::new(max_buffer_size);
-        buffer2.dead_on_drop = true;
+        let buffer1 = SocketBuffer::new(max_buffer_size);
+        let buffer2 = SocketBuffer::new(max_buffer_size);
 
+        // Dropping a half closes the direction it sends in, so the peer
+        // reads the rest of the data followed by an EOF, while the direction
+        // it receives in is reset as nobody is left to read from it
         let half1 = Self {
             tx: buffer1.clone(),
-            rx: buffer2.clone(),
+            rx: buffer2.clone().dead_on_drop(),
             addr_local: addr1,
             addr_peer: addr2,
             ttl: 64,
         };
         let half2 = Self {
             tx: buffer2,
-            rx: buffer1,
+            rx: buffer1.dead_on_drop(),
             addr_local: addr2,
             addr_peer: addr1,
             ttl: 64,
@@ -423,6 +433,10 @@ impl VirtualSocket for TcpSocketHalf {
         })
     }
 
+    fn bytes_available(&mut self) -> crate::Result<usize> {
+        Ok(self.rx.buffered())
+    }
+
     fn set_handler(
         &mut self,
         handler: Box<dyn InterestHandler + Send + Sync>,
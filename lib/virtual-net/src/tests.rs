@@ -2,6 +2,7 @@
 use std::{
     net::{Ipv4Addr, SocketAddrV4},
     sync::atomic::{AtomicU16, Ordering},
+    time::Duration,
 };
 
 use tracing_test::traced_test;
@@ -472,3 +473,622 @@ async fn test_google_epoll() {
 
     tracing::info!("done");
 }
+
+#[cfg(feature = "remote")]
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tcp_over_tunnel() {
+    tracing::info!("building duplex stream for the tunnel");
+    let (io1, io2) = tokio::io::duplex(4096);
+
+    let (networking1, driver1) = TunneledNetworking::new(io1);
+    let (networking2, driver2) = TunneledNetworking::new(io2);
+    tokio::task::spawn(driver1);
+    tokio::task::spawn(driver2);
+
+    let mut listener = networking2
+        .listen_tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), false, false, false)
+        .await
+        .unwrap();
+    let addr = listener.addr_local().unwrap();
+    tracing::info!("listening on {addr} at the far end of the tunnel");
+
+    const TEST1: &str = "the cat ran up the wall!";
+    const TEST2: &str = "...and fell off the roof! raise the roof! oop oop";
+
+    let acceptor = tokio::task::spawn(async move {
+        let (mut socket, addr) = listener.accept().await.unwrap();
+        tracing::info!("accepted connection from {addr}");
+
+        let mut buf = [0u8; TEST1.len()];
+        socket.read_exact(&mut buf).await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&buf).as_ref(), TEST1);
+
+        socket.write_all(TEST2.as_bytes()).await.unwrap();
+        socket
+    });
+
+    tracing::info!("connecting through the tunnel");
+    let mut socket = networking1
+        .connect_tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), addr)
+        .await
+        .unwrap();
+    socket.write_all(TEST1.as_bytes()).await.unwrap();
+
+    let mut buf = [0u8; TEST2.len()];
+    socket.read_exact(&mut buf).await.unwrap();
+    assert_eq!(String::from_utf8_lossy(&buf).as_ref(), TEST2);
+
+    acceptor.await.unwrap();
+}
+
+#[cfg(feature = "remote")]
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tcp_over_tunnel_refused_after_listener_closed() {
+    let (io1, io2) = tokio::io::duplex(4096);
+
+    let (networking1, driver1) = TunneledNetworking::new(io1);
+    let (networking2, driver2) = TunneledNetworking::new(io2);
+    tokio::task::spawn(driver1);
+    tokio::task::spawn(driver2);
+
+    let local = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 8080));
+
+    tracing::info!("connecting through the tunnel with nothing listening");
+    let ret = networking1.connect_tcp(local, addr).await;
+    assert!(matches!(ret, Err(NetworkError::ConnectionRefused)));
+
+    tracing::info!("listening on {addr} at the far end of the tunnel");
+    let listener = networking2
+        .listen_tcp(addr, false, false, false)
+        .await
+        .unwrap();
+    networking1.connect_tcp(local, addr).await.unwrap();
+
+    tracing::info!("closing the listener frees up its port");
+    drop(listener);
+    let ret = networking1.connect_tcp(local, addr).await;
+    assert!(matches!(ret, Err(NetworkError::ConnectionRefused)));
+    networking2
+        .listen_tcp(addr, false, false, false)
+        .await
+        .unwrap();
+}
+
+/// Backend that takes a while to resolve names and records how many
+/// resolutions it was running at the same time
+#[cfg(feature = "remote")]
+#[derive(Debug, Default)]
+struct SlowNetworking {
+    running: std::sync::atomic::AtomicUsize,
+    peak: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(feature = "remote")]
+#[async_trait::async_trait]
+impl VirtualNetworking for SlowNetworking {
+    async fn resolve(
+        &self,
+        host: &str,
+        _port: Option<u16>,
+        _dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(running, Ordering::SeqCst);
+        let delay = match host {
+            "stuck.example.com" => Duration::from_secs(60),
+            _ => Duration::from_millis(5),
+        };
+        tokio::time::sleep(delay).await;
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        Ok(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)])
+    }
+}
+
+#[cfg(feature = "remote")]
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_limits_requests_in_flight() {
+    const WINDOW: usize = 4;
+    const REQUESTS: usize = 64;
+
+    let (tx1, rx1) = tokio::sync::mpsc::channel(100);
+    let (tx2, rx2) = tokio::sync::mpsc::channel(100);
+    let (client, client_driver) = RemoteNetworkingClient::new_from_mpsc(tx1, rx2);
+    tokio::task::spawn(client_driver);
+
+    let backend = Arc::new(SlowNetworking::default());
+    let (_server, server_driver) = RemoteNetworkingServer::new_from_mpsc(tx2, rx1, backend.clone());
+    tokio::task::spawn(server_driver.with_max_in_flight(WINDOW));
+
+    // The client fires all of its requests at once
+    let requests = (0..REQUESTS)
+        .map(|_| {
+            let client = client.clone();
+            tokio::task::spawn(async move { client.resolve("example.com", None, None).await })
+        })
+        .collect::<Vec<_>>();
+    for request in requests {
+        let addrs = tokio::time::timeout(Duration::from_secs(10), request)
+            .await
+            .expect("every request completes")
+            .unwrap()
+            .unwrap();
+        assert_eq!(addrs, vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+    }
+
+    let peak = backend.peak.load(Ordering::SeqCst);
+    assert!(peak <= WINDOW, "{peak} requests were in flight at once");
+}
+
+#[cfg(feature = "remote")]
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_window_is_not_held_by_a_slow_request() {
+    let (tx1, rx1) = tokio::sync::mpsc::channel(100);
+    let (tx2, rx2) = tokio::sync::mpsc::channel(100);
+    let (client, client_driver) = RemoteNetworkingClient::new_from_mpsc(tx1, rx2);
+    tokio::task::spawn(client_driver);
+
+    let backend = Arc::new(SlowNetworking::default());
+    let (_server, server_driver) = RemoteNetworkingServer::new_from_mpsc(tx2, rx1, backend.clone());
+    tokio::task::spawn(server_driver.with_max_in_flight(2));
+
+    // One request stays stuck, the ones that finish after it must still
+    // free up their slot of the window
+    let stuck = {
+        let client = client.clone();
+        tokio::task::spawn(async move { client.resolve("stuck.example.com", None, None).await })
+    };
+    for _ in 0..16 {
+        let addrs = tokio::time::timeout(
+            Duration::from_secs(10),
+            client.resolve("example.com", None, None),
+        )
+        .await
+        .expect("requests keep being processed")
+        .unwrap();
+        assert_eq!(addrs, vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+    }
+    stuck.abort();
+}
+
+#[cfg_attr(not(target_os = "linux"), ignore)]
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tcp_fastopen_connect_with_data() {
+    let networking = LocalNetworking::new();
+    let mut listener = networking
+        .listen_tcp(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            false,
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+    listener.set_fastopen(16).unwrap();
+    let addr = listener.addr_local().unwrap();
+
+    const GREETING: &[u8] = b"hello in the SYN";
+
+    let acceptor = tokio::task::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [MaybeUninit::<u8>::uninit(); 64];
+        let amt = socket.recv(&mut buf).await.unwrap();
+        buf[..amt]
+            .iter()
+            .map(|b| unsafe { b.assume_init() })
+            .collect::<Vec<_>>()
+    });
+
+    let _socket = networking
+        .connect_tcp_with_data(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), addr, GREETING)
+        .await
+        .unwrap();
+
+    let received = tokio::time::timeout(Duration::from_secs(10), acceptor)
+        .await
+        .expect("the data should arrive")
+        .unwrap();
+    assert_eq!(received, GREETING);
+}
+
+#[cfg(feature = "remote")]
+#[test]
+fn test_oversized_frame_is_rejected_before_allocating() {
+    use tokio_util::codec::Decoder;
+
+    let mut codec = crate::rx_tx::frame_codec(1024);
+    let mut buf = BytesMut::from(&u32::MAX.to_be_bytes()[..]);
+
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(buf.capacity() < 1024);
+}
+
+#[cfg(feature = "remote")]
+#[traced_test]
+#[tokio::test]
+async fn test_oversized_frame_closes_the_connection() {
+    let (server_tx, _client_rx) = tokio::io::duplex(1024);
+    let (mut client_tx, server_rx) = tokio::io::duplex(1024);
+    let (_server, server_driver) = RemoteNetworkingServer::new_from_async_io_with_max_frame_size(
+        server_tx,
+        server_rx,
+        FrameSerializationFormat::Bincode,
+        1024,
+        Arc::new(LocalNetworking::new()),
+    );
+    let driver = tokio::task::spawn(server_driver);
+
+    // A frame that claims to be 1 GiB large
+    client_tx
+        .write_all(&(1u32 << 30).to_be_bytes())
+        .await
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(10), driver)
+        .await
+        .expect("the server should stop reading")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_tcp_pair_reads_eof_after_the_peer_is_dropped() {
+    use crate::tcp_pair::TcpSocketHalf;
+
+    let addr1 = SocketAddr::from((Ipv4Addr::LOCALHOST, 1));
+    let addr2 = SocketAddr::from((Ipv4Addr::LOCALHOST, 2));
+    let (mut a, mut b) = TcpSocketHalf::channel(1024, addr1, addr2);
+
+    a.try_send(b"bye").unwrap();
+    drop(a);
+
+    // The data that was sent is still delivered before the EOF
+    let mut buf = [MaybeUninit::<u8>::uninit(); 16];
+    assert_eq!(b.try_recv(&mut buf).unwrap(), 3);
+    assert_eq!(b.try_recv(&mut buf).unwrap(), 0);
+    let ready = std::future::poll_fn(|cx| b.poll_read_ready(cx)).await;
+    assert_eq!(ready.unwrap(), 0);
+
+    assert!(b.is_closed());
+    assert!(b.try_send(b"anyone?").is_err());
+}
+
+#[tokio::test]
+async fn test_tcp_pair_half_close() {
+    use crate::tcp_pair::TcpSocketHalf;
+
+    let addr1 = SocketAddr::from((Ipv4Addr::LOCALHOST, 1));
+    let addr2 = SocketAddr::from((Ipv4Addr::LOCALHOST, 2));
+    let (mut a, mut b) = TcpSocketHalf::channel(1024, addr1, addr2);
+
+    a.try_send(b"request").unwrap();
+    a.shutdown(std::net::Shutdown::Write).unwrap();
+
+    let mut buf = [MaybeUninit::<u8>::uninit(); 16];
+    assert_eq!(b.try_recv(&mut buf).unwrap(), 7);
+    assert_eq!(b.try_recv(&mut buf).unwrap(), 0);
+    assert!(!b.is_closed());
+
+    // The other direction keeps working
+    b.try_send(b"response").unwrap();
+    assert_eq!(a.try_recv(&mut buf).unwrap(), 8);
+    assert!(matches!(
+        a.try_recv(&mut buf),
+        Err(NetworkError::WouldBlock)
+    ));
+
+    drop(b);
+    assert_eq!(a.try_recv(&mut buf).unwrap(), 0);
+}
+
+#[derive(Debug, Default)]
+struct CountingResolver {
+    lookups: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl crate::host::DnsResolver for CountingResolver {
+    async fn lookup(&self, _host: &str) -> std::io::Result<Vec<IpAddr>> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))])
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_uses_the_dns_cache() {
+    let resolver = Arc::new(CountingResolver::default());
+    let mut networking = LocalNetworking::with_dns_cache(Duration::from_secs(30), 2);
+    networking.set_resolver(resolver.clone());
+
+    let addrs = networking.resolve("example.com", Some(80), None).await;
+    assert_eq!(addrs.unwrap(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+    let addrs = networking.resolve("example.com", Some(80), None).await;
+    assert_eq!(addrs.unwrap(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+    assert_eq!(resolver.lookups.load(Ordering::SeqCst), 1);
+
+    // Entries are keyed by the port as well and evicted once the cache is
+    // full, the least recently used one goes first
+    networking
+        .resolve("example.com", Some(443), None)
+        .await
+        .unwrap();
+    networking
+        .resolve("example.org", Some(80), None)
+        .await
+        .unwrap();
+    assert_eq!(resolver.lookups.load(Ordering::SeqCst), 3);
+    networking
+        .resolve("example.org", Some(80), None)
+        .await
+        .unwrap();
+    networking
+        .resolve("example.com", Some(80), None)
+        .await
+        .unwrap();
+    assert_eq!(resolver.lookups.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn test_resolve_without_dns_cache() {
+    let resolver = Arc::new(CountingResolver::default());
+    let mut networking = LocalNetworking::new();
+    networking.set_resolver(resolver.clone());
+
+    networking
+        .resolve("example.com", Some(80), None)
+        .await
+        .unwrap();
+    networking
+        .resolve("example.com", Some(80), None)
+        .await
+        .unwrap();
+    assert_eq!(resolver.lookups.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(feature = "host-net")]
+#[cfg_attr(windows, ignore)]
+#[tokio::test]
+async fn test_ip_list_has_loopback() {
+    let networking = LocalNetworking::new();
+    let ips = networking.ip_list().await.unwrap();
+    assert!(
+        ips.iter().any(|cidr| cidr.ip.is_loopback()),
+        "no loopback address in {ips:?}"
+    );
+
+    // The loopback network is directly connected
+    let routes = networking.route_list().await.unwrap();
+    assert!(routes
+        .iter()
+        .any(|route| route.cidr.ip.is_loopback() && route.via_router.is_loopback()));
+}
+
+/// Accepts the waiting connections of a composite listener over two
+/// loopback listeners (with two connections each) and returns the ports of
+/// the peers in the order they were accepted
+fn composite_accept_order(policy: AcceptPolicy) -> Vec<u16> {
+    let a = loopback::LoopbackTcpListener::new("127.0.0.1:80".parse().unwrap());
+    let b = loopback::LoopbackTcpListener::new("127.0.0.1:81".parse().unwrap());
+    let mut listener = CompositeTcpListener::with_policy(policy);
+    listener.add_port(Box::new(a.clone()));
+    listener.add_port(Box::new(b.clone()));
+    for port in [1000, 1001] {
+        a.connect_to(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port));
+        b.connect_to(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port + 1000));
+    }
+
+    let mut order = Vec::new();
+    while let Ok((_, peer)) = listener.try_accept() {
+        order.push(peer.port());
+    }
+    order
+}
+
+#[test]
+fn test_composite_listener_round_robin() {
+    assert_eq!(
+        composite_accept_order(AcceptPolicy::RoundRobin),
+        vec![1000, 2000, 1001, 2001]
+    );
+    assert_eq!(
+        composite_accept_order(AcceptPolicy::Priority),
+        vec![1000, 1001, 2000, 2001]
+    );
+}
+
+#[cfg(all(feature = "host-net", any(target_os = "linux", target_os = "android")))]
+#[tokio::test]
+async fn test_tcp_user_timeout() {
+    let networking = LocalNetworking::new();
+    let listener = networking
+        .listen_tcp((Ipv4Addr::LOCALHOST, 0).into(), false, false, false)
+        .await
+        .unwrap();
+    let mut socket = networking
+        .connect_tcp(
+            (Ipv4Addr::LOCALHOST, 0).into(),
+            listener.addr_local().unwrap(),
+        )
+        .await
+        .unwrap();
+
+    socket
+        .set_user_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    assert_eq!(socket.user_timeout().unwrap(), Some(Duration::from_secs(5)));
+    socket.set_user_timeout(None).unwrap();
+    assert_eq!(socket.user_timeout().unwrap(), None);
+}
+
+/// Channels between a client and a server that pass through relays, aborting
+/// the relays drops the connection
+#[cfg(feature = "remote")]
+#[allow(clippy::type_complexity)]
+fn relayed_channels() -> (
+    RemoteTransport,
+    (
+        tokio::sync::mpsc::Sender<meta::MessageResponse>,
+        tokio::sync::mpsc::Receiver<meta::MessageRequest>,
+    ),
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    fn relay<T: Send + 'static>(
+        mut rx: tokio::sync::mpsc::Receiver<T>,
+        tx: tokio::sync::mpsc::Sender<T>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::task::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if tx.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    let (client_tx, relay_rx1) = tokio::sync::mpsc::channel(100);
+    let (relay_tx1, server_rx) = tokio::sync::mpsc::channel(100);
+    let (server_tx, relay_rx2) = tokio::sync::mpsc::channel(100);
+    let (relay_tx2, client_rx) = tokio::sync::mpsc::channel(100);
+    let relays = vec![relay(relay_rx1, relay_tx1), relay(relay_rx2, relay_tx2)];
+
+    let transport = RemoteTransport::Mpsc {
+        tx: client_tx,
+        rx: client_rx,
+    };
+    (transport, (server_tx, server_rx), relays)
+}
+
+#[cfg(feature = "remote")]
+#[cfg_attr(windows, ignore)]
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_client_reconnects_and_resumes_sockets() {
+    // Echo server on the host that the client connects to through the server
+    let networking = LocalNetworking::new();
+    let mut listener = networking
+        .listen_tcp(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            false,
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+    let addr = listener.addr_local().unwrap();
+    tokio::task::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4];
+        for _ in 0..2 {
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(&buf).await.unwrap();
+        }
+    });
+
+    let (transport, (server_tx, server_rx), relays) = relayed_channels();
+    let (client, client_driver) = RemoteNetworkingClient::new_from_transport(transport);
+    let (server, server_driver) =
+        RemoteNetworkingServer::new_from_mpsc(server_tx, server_rx, Arc::new(networking));
+    tokio::task::spawn(server_driver);
+
+    let relays = Arc::new(std::sync::Mutex::new(relays));
+    let (reconnected_tx, mut reconnected_rx) = tokio::sync::mpsc::unbounded_channel();
+    let policy = ReconnectPolicy {
+        base: Duration::from_millis(10),
+        jitter: Duration::ZERO,
+        ..Default::default()
+    };
+    let client_driver = client_driver.with_reconnect(policy, {
+        let relays = relays.clone();
+        move || {
+            let (transport, (server_tx, server_rx), new_relays) = relayed_channels();
+            tokio::task::spawn(server.resume_from_mpsc(server_tx, server_rx));
+            *relays.lock().unwrap() = new_relays;
+            reconnected_tx.send(()).ok();
+            async move { Ok(transport) }
+        }
+    });
+    tokio::task::spawn(client_driver);
+
+    let mut socket = client
+        .connect_tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), addr)
+        .await
+        .unwrap();
+    let mut buf = [0u8; 4];
+    socket.write_all(b"ping").await.unwrap();
+    socket.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+
+    // The connection between the client and the server drops
+    for relay in relays.lock().unwrap().drain(..) {
+        relay.abort();
+    }
+    tokio::time::timeout(Duration::from_secs(10), reconnected_rx.recv())
+        .await
+        .expect("the client reconnects")
+        .unwrap();
+
+    // The socket that was open before carries on over the new connection
+    socket.write_all(b"pong").await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), socket.read_exact(&mut buf))
+        .await
+        .expect("the echo arrives")
+        .unwrap();
+    assert_eq!(&buf, b"pong");
+}
+
+#[cfg(feature = "host-net")]
+#[cfg_attr(windows, ignore)]
+#[tokio::test]
+async fn test_ruleset_deny_all_with_allow_entries() {
+    use crate::ruleset::{Protocol, Ruleset};
+
+    // Only the port of the listener is allowed
+    let host = LocalNetworking::new();
+    let listener = host
+        .listen_tcp((Ipv4Addr::LOCALHOST, 0).into(), false, false, false)
+        .await
+        .unwrap();
+    let allowed = listener.addr_local().unwrap();
+    let ruleset = Ruleset::deny_all().allow(
+        IpCidr {
+            ip: Ipv4Addr::LOCALHOST.into(),
+            prefix: 32,
+        },
+        allowed.port()..=allowed.port(),
+        Protocol::Tcp,
+    );
+    let networking = LocalNetworking::with_ruleset(ruleset);
+
+    networking
+        .connect_tcp((Ipv4Addr::LOCALHOST, 0).into(), allowed)
+        .await
+        .unwrap();
+
+    // The ports next to it are not
+    for port in [allowed.port() - 1, allowed.port() + 1] {
+        let denied = SocketAddr::new(allowed.ip(), port);
+        assert!(matches!(
+            networking
+                .connect_tcp((Ipv4Addr::LOCALHOST, 0).into(), denied)
+                .await,
+            Err(NetworkError::PermissionDenied)
+        ));
+    }
+
+    // Neither are other protocols or other kinds of sockets
+    assert!(matches!(
+        networking.bind_udp(allowed, false, false).await,
+        Err(NetworkError::PermissionDenied)
+    ));
+    assert!(matches!(
+        networking
+            .listen_tcp((Ipv4Addr::LOCALHOST, 0).into(), false, false, false)
+            .await,
+        Err(NetworkError::PermissionDenied)
+    ));
+}
@@ -0,0 +1,515 @@
+//! Networking decorator that records every byte that is sent and received on
+//! its TCP connections so that tests can assert against the exchange
+
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Shutdown, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+
+use crate::{
+    InterestHandler, IpCidr, IpRoute, NetworkError, Result, SocketStatus, StreamSecurity,
+    VirtualConnectedSocket, VirtualIcmpSocket, VirtualIoSource, VirtualNetworking,
+    VirtualRawSocket, VirtualSocket, VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
+};
+
+/// Direction of a chunk of data in a [`ConnectionTranscript`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TranscriptDirection {
+    /// The data was sent to the peer
+    Sent,
+    /// The data was received from the peer
+    Received,
+}
+
+/// A single chunk of data that was sent or received on a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    pub direction: TranscriptDirection,
+    /// Time at which the data was sent or received, relative to the creation
+    /// of the [`TranscriptNetworking`]
+    pub at: Duration,
+    pub data: Bytes,
+}
+
+/// Ordered record of the data that was exchanged on one connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionTranscript {
+    pub addr_local: SocketAddr,
+    pub addr_peer: SocketAddr,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+#[derive(Debug)]
+struct TranscriptState {
+    started: Instant,
+    connections: Vec<ConnectionTranscript>,
+}
+
+impl TranscriptState {
+    fn record(&mut self, connection: usize, direction: TranscriptDirection, data: &[u8]) {
+        let at = self.started.elapsed();
+        self.connections[connection].entries.push(TranscriptEntry {
+            direction,
+            at,
+            data: Bytes::copy_from_slice(data),
+        });
+    }
+}
+
+/// Wraps another networking implementation and records a transcript of the
+/// data sent and received on every TCP connection that it opens or accepts,
+/// the transcripts can be retrieved with [`TranscriptNetworking::transcripts`]
+#[derive(Debug, Clone)]
+pub struct TranscriptNetworking<N> {
+    inner: N,
+    state: Arc<Mutex<TranscriptState>>,
+}
+
+impl<N> TranscriptNetworking<N>
+where
+    N: VirtualNetworking,
+{
+    pub fn new(inner: N) -> Self {
+        Self {
+            inner,
+            state: Arc::new(Mutex::new(TranscriptState {
+                started: Instant::now(),
+                connections: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn inner(&self) -> &N {
+        &self.inner
+    }
+
+    /// Returns the transcripts of all the connections in the order that the
+    /// connections were established
+    pub fn transcripts(&self) -> Vec<ConnectionTranscript> {
+        self.state.lock().unwrap().connections.clone()
+    }
+}
+
+/// Starts recording a connection and wraps its socket
+fn record_socket(
+    state: &Arc<Mutex<TranscriptState>>,
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+    let connection = {
+        let mut guard = state.lock().unwrap();
+        guard.connections.push(ConnectionTranscript {
+            addr_local: inner.addr_local()?,
+            addr_peer: inner.addr_peer()?,
+            entries: Vec::new(),
+        });
+        guard.connections.len() - 1
+    };
+    Ok(Box::new(TranscriptTcpSocket {
+        inner,
+        state: state.clone(),
+        connection,
+    }))
+}
+
+#[async_trait::async_trait]
+impl<N> VirtualNetworking for TranscriptNetworking<N>
+where
+    N: VirtualNetworking,
+{
+    async fn bridge(
+        &self,
+        network: &str,
+        access_token: &str,
+        security: StreamSecurity,
+    ) -> Result<()> {
+        self.inner.bridge(network, access_token, security).await
+    }
+
+    async fn unbridge(&self) -> Result<()> {
+        self.inner.unbridge().await
+    }
+
+    async fn dhcp_acquire(&self) -> Result<Vec<IpAddr>> {
+        self.inner.dhcp_acquire().await
+    }
+
+    async fn ip_add(&self, ip: IpAddr, prefix: u8) -> Result<()> {
+        self.inner.ip_add(ip, prefix).await
+    }
+
+    async fn ip_remove(&self, ip: IpAddr) -> Result<()> {
+        self.inner.ip_remove(ip).await
+    }
+
+    async fn ip_clear(&self) -> Result<()> {
+        self.inner.ip_clear().await
+    }
+
+    async fn ip_list(&self) -> Result<Vec<IpCidr>> {
+        self.inner.ip_list().await
+    }
+
+    async fn mac(&self) -> Result<[u8; 6]> {
+        self.inner.mac().await
+    }
+
+    async fn gateway_set(&self, ip: IpAddr) -> Result<()> {
+        self.inner.gateway_set(ip).await
+    }
+
+    async fn route_add(
+        &self,
+        cidr: IpCidr,
+        via_router: IpAddr,
+        preferred_until: Option<Duration>,
+        expires_at: Option<Duration>,
+    ) -> Result<()> {
+        self.inner
+            .route_add(cidr, via_router, preferred_until, expires_at)
+            .await
+    }
+
+    async fn route_remove(&self, cidr: IpAddr) -> Result<()> {
+        self.inner.route_remove(cidr).await
+    }
+
+    async fn route_clear(&self) -> Result<()> {
+        self.inner.route_clear().await
+    }
+
+    async fn route_list(&self) -> Result<Vec<IpRoute>> {
+        self.inner.route_list().await
+    }
+
+    async fn bind_raw(&self) -> Result<Box<dyn VirtualRawSocket + Sync>> {
+        self.inner.bind_raw().await
+    }
+
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        only_v6: bool,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let inner = self
+            .inner
+            .listen_tcp(addr, only_v6, reuse_port, reuse_addr)
+            .await?;
+        Ok(Box::new(TranscriptTcpListener {
+            inner,
+            state: self.state.clone(),
+        }))
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        reuse_port: bool,
+        reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        self.inner.bind_udp(addr, reuse_port, reuse_addr).await
+    }
+
+    async fn bind_icmp(&self, addr: IpAddr) -> Result<Box<dyn VirtualIcmpSocket + Sync>> {
+        self.inner.bind_icmp(addr).await
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let inner = self.inner.connect_tcp(addr, peer).await?;
+        record_socket(&self.state, inner)
+    }
+
+    async fn resolve(
+        &self,
+        host: &str,
+        port: Option<u16>,
+        dns_server: Option<IpAddr>,
+    ) -> Result<Vec<IpAddr>> {
+        self.inner.resolve(host, port, dns_server).await
+    }
+}
+
+/// Listener that records the connections that it accepts
+#[derive(Debug)]
+struct TranscriptTcpListener {
+    inner: Box<dyn VirtualTcpListener + Sync>,
+    state: Arc<Mutex<TranscriptState>>,
+}
+
+impl VirtualIoSource for TranscriptTcpListener {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualTcpListener for TranscriptTcpListener {
+    fn try_accept(&mut self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        let (socket, peer) = self.inner.try_accept()?;
+        Ok((record_socket(&self.state, socket)?, peer))
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        self.inner.ttl()
+    }
+
+    fn set_fastopen(&mut self, queue_len: u32) -> Result<()> {
+        self.inner.set_fastopen(queue_len)
+    }
+}
+
+/// Socket that records the data that passes through it
+#[derive(Debug)]
+struct TranscriptTcpSocket {
+    inner: Box<dyn VirtualTcpSocket + Sync>,
+    state: Arc<Mutex<TranscriptState>>,
+    connection: usize,
+}
+
+impl TranscriptTcpSocket {
+    fn record(&self, direction: TranscriptDirection, data: &[u8]) {
+        if !data.is_empty() {
+            let mut state = self.state.lock().unwrap();
+            state.record(self.connection, direction, data);
+        }
+    }
+}
+
+impl VirtualIoSource for TranscriptTcpSocket {
+    fn remove_handler(&mut self) {
+        self.inner.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+impl VirtualSocket for TranscriptTcpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.inner.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        self.inner.ttl()
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.inner.addr_local()
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        self.inner.status()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.inner.set_handler(handler)
+    }
+
+    fn take_error(&mut self) -> Result<Option<NetworkError>> {
+        self.inner.take_error()
+    }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        self.inner.bytes_available()
+    }
+}
+
+impl VirtualConnectedSocket for TranscriptTcpSocket {
+    fn set_linger(&mut self, linger: Option<Duration>) -> Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    fn linger(&self) -> Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    fn try_send(&mut self, data: &[u8]) -> Result<usize> {
+        let amt = self.inner.try_send(data)?;
+        self.record(TranscriptDirection::Sent, &data[..amt]);
+        Ok(amt)
+    }
+
+    fn try_flush(&mut self) -> Result<()> {
+        self.inner.try_flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.inner.close()
+    }
+
+    fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+        let amt = self.inner.try_recv(buf)?;
+        // Safety: the first `amt` bytes were initialized by the read
+        let data = unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, amt) };
+        self.record(TranscriptDirection::Received, data);
+        Ok(amt)
+    }
+}
+
+impl VirtualTcpSocket for TranscriptTcpSocket {
+    fn set_recv_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_recv_buf_size(size)
+    }
+
+    fn recv_buf_size(&self) -> Result<usize> {
+        self.inner.recv_buf_size()
+    }
+
+    fn set_send_buf_size(&mut self, size: usize) -> Result<()> {
+        self.inner.set_send_buf_size(size)
+    }
+
+    fn send_buf_size(&self) -> Result<usize> {
+        self.inner.send_buf_size()
+    }
+
+    fn set_nodelay(&mut self, nodelay: bool) -> Result<()> {
+        self.inner.set_nodelay(nodelay)
+    }
+
+    fn nodelay(&self) -> Result<bool> {
+        self.inner.nodelay()
+    }
+
+    fn set_keepalive(&mut self, keepalive: bool) -> Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    fn keepalive(&self) -> Result<bool> {
+        self.inner.keepalive()
+    }
+
+    fn set_dontroute(&mut self, dontroute: bool) -> Result<()> {
+        self.inner.set_dontroute(dontroute)
+    }
+
+    fn dontroute(&self) -> Result<bool> {
+        self.inner.dontroute()
+    }
+
+    fn set_user_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.inner.set_user_timeout(timeout)
+    }
+
+    fn user_timeout(&self) -> Result<Option<Duration>> {
+        self.inner.user_timeout()
+    }
+
+    fn addr_peer(&self) -> Result<SocketAddr> {
+        self.inner.addr_peer()
+    }
+
+    fn shutdown(&mut self, how: Shutdown) -> Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use crate::{LoopbackNetworking, VirtualConnectedSocketExt, VirtualTcpListenerExt};
+
+    use super::*;
+
+    async fn recv_exact(socket: &mut Box<dyn VirtualTcpSocket + Sync>, len: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        while data.len() < len {
+            let mut buf = [MaybeUninit::<u8>::uninit(); 64];
+            let want = (len - data.len()).min(buf.len());
+            let amt = socket.recv(&mut buf[..want]).await.unwrap();
+            assert_ne!(amt, 0, "unexpected end of stream");
+            data.extend(buf[..amt].iter().map(|b| unsafe { b.assume_init() }));
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn records_request_response_exchange() {
+        let networking = TranscriptNetworking::new(LoopbackNetworking::new());
+        let server_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 8080));
+        let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 50000));
+
+        let mut listener = networking
+            .listen_tcp(server_addr, false, false, false)
+            .await
+            .unwrap();
+        let mut client = networking
+            .connect_tcp(client_addr, server_addr)
+            .await
+            .unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.send(b"ping").await.unwrap();
+        assert_eq!(recv_exact(&mut server, 4).await, b"ping");
+        server.send(b"pong!").await.unwrap();
+        assert_eq!(recv_exact(&mut client, 5).await, b"pong!");
+
+        let transcripts = networking.transcripts();
+        assert_eq!(transcripts.len(), 2);
+
+        let summary = |transcript: &ConnectionTranscript| {
+            transcript
+                .entries
+                .iter()
+                .map(|entry| (entry.direction, entry.data.to_vec()))
+                .collect::<Vec<_>>()
+        };
+
+        let client_transcript = &transcripts[0];
+        assert_eq!(client_transcript.addr_peer, server_addr);
+        assert_eq!(
+            summary(client_transcript),
+            vec![
+                (TranscriptDirection::Sent, b"ping".to_vec()),
+                (TranscriptDirection::Received, b"pong!".to_vec()),
+            ]
+        );
+        assert!(client_transcript.entries[0].at <= client_transcript.entries[1].at);
+
+        let server_transcript = &transcripts[1];
+        assert_eq!(
+            summary(server_transcript),
+            vec![
+                (TranscriptDirection::Received, b"ping".to_vec()),
+                (TranscriptDirection::Sent, b"pong!".to_vec()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,867 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::mem::MaybeUninit;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU16;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::stream::FuturesUnordered;
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::Semaphore;
+use tokio_util::codec::FramedRead;
+use tokio_util::codec::FramedWrite;
+use tokio_util::codec::LengthDelimitedCodec;
+use virtual_mio::InterestHandler;
+use virtual_mio::InterestType;
+
+use crate::loopback::LoopbackTcpListener;
+use crate::tcp_pair::TcpSocketHalf;
+use crate::tcp_pair::TcpSocketHalfRx;
+use crate::tcp_pair::TcpSocketHalfTx;
+use crate::NetworkError;
+use crate::Result;
+use crate::SocketStatus;
+use crate::VirtualConnectionlessSocket;
+use crate::VirtualIoSource;
+use crate::VirtualNetworking;
+use crate::VirtualSocket;
+use crate::VirtualTcpListener;
+use crate::VirtualTcpSocket;
+use crate::VirtualUdpSocket;
+
+const DEFAULT_MAX_BUFFER_SIZE: usize = 1_048_576;
+const DATA_FRAME_SIZE: usize = 16_384;
+const EPHEMERAL_PORT_START: u16 = 49_152;
+/// Number of data frames that may be in flight on a channel before its
+/// sender has to wait for the far end to hand out more credit
+const CHANNEL_WINDOW: usize = DEFAULT_MAX_BUFFER_SIZE / DATA_FRAME_SIZE;
+
+/// Frames that are exchanged between the two ends of a tunnel, each
+/// frame is serialized with `bincode` and prefixed with its length
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum TunnelFrame {
+    /// Opens a new virtual TCP connection from `local` to `peer`
+    Open {
+        channel: u64,
+        local: SocketAddr,
+        peer: SocketAddr,
+    },
+    /// The far end accepted the connection that was opened on `channel`
+    Accepted { channel: u64 },
+    /// Nothing is listening at the far end for the connection that was
+    /// opened on `channel`
+    Refused { channel: u64 },
+    /// Carries bytes for a virtual TCP connection, `initiator` is set
+    /// when the sender of the frame is the end that opened the channel
+    Data {
+        channel: u64,
+        initiator: bool,
+        data: Vec<u8>,
+    },
+    /// The sender will not write any more data on this channel
+    Close { channel: u64, initiator: bool },
+    /// The sender delivered `frames` data frames on this channel and
+    /// the far end may send that many more
+    Credit {
+        channel: u64,
+        initiator: bool,
+        frames: u64,
+    },
+    /// A single UDP datagram
+    Datagram {
+        from: SocketAddr,
+        to: SocketAddr,
+        data: Vec<u8>,
+    },
+}
+
+/// Channels are keyed by their ID and a flag that indicates if they
+/// were opened by this end of the tunnel
+type ChannelKey = (u64, bool);
+
+#[derive(Debug)]
+struct TunnelState {
+    channel_seed: AtomicU64,
+    port_seed: AtomicU16,
+    frames: mpsc::UnboundedSender<TunnelFrame>,
+    work: mpsc::UnboundedSender<BoxFuture<'static, ()>>,
+    channels: Mutex<HashMap<ChannelKey, mpsc::Sender<Bytes>>>,
+    windows: Mutex<HashMap<ChannelKey, Arc<Semaphore>>>,
+    pending: Mutex<HashMap<u64, oneshot::Sender<bool>>>,
+    tcp_listeners: Mutex<HashMap<u16, LoopbackTcpListener>>,
+    udp_sockets: Mutex<HashMap<u16, Arc<Mutex<TunneledUdpSocketState>>>>,
+}
+
+impl TunnelState {
+    fn send(&self, frame: TunnelFrame) {
+        self.frames.send(frame).ok();
+    }
+
+    fn ephemeral_port(&self) -> u16 {
+        let port = self.port_seed.fetch_add(1, Ordering::SeqCst);
+        if port < EPHEMERAL_PORT_START {
+            // The seed wrapped around so we start again from the beginning
+            self.port_seed
+                .store(EPHEMERAL_PORT_START + 1, Ordering::SeqCst);
+            return EPHEMERAL_PORT_START;
+        }
+        port
+    }
+
+    fn resolve_addr(&self, addr: SocketAddr) -> SocketAddr {
+        match addr.port() {
+            0 => SocketAddr::new(addr.ip(), self.ephemeral_port()),
+            _ => addr,
+        }
+    }
+
+    /// Pumps all the data written into the local half of a channel
+    /// over the tunnel until the local half is closed, never sending
+    /// more frames than the far end has given credit for
+    fn spawn_pump(self: &Arc<Self>, channel: u64, initiator: bool, mut rx: TcpSocketHalfRx) {
+        let key = (channel, initiator);
+        let window = Arc::new(Semaphore::new(CHANNEL_WINDOW));
+        self.windows.lock().unwrap().insert(key, window.clone());
+
+        let state = self.clone();
+        self.work
+            .send(Box::pin(async move {
+                let mut buf = vec![0u8; DATA_FRAME_SIZE];
+                loop {
+                    // The permit is handed back by a credit frame once the
+                    // far end has delivered the data to its reader
+                    match window.acquire().await {
+                        Ok(permit) => permit.forget(),
+                        Err(_) => break,
+                    }
+                    match rx.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(amt) => state.send(TunnelFrame::Data {
+                            channel,
+                            initiator,
+                            data: buf[..amt].to_vec(),
+                        }),
+                    }
+                }
+                state.windows.lock().unwrap().remove(&key);
+                state.send(TunnelFrame::Close { channel, initiator });
+            }))
+            .ok();
+    }
+
+    /// Delivers the data received for a channel into its local half from
+    /// a dedicated task, this way a slow reader only stalls its own channel
+    /// rather than all the traffic flowing through the tunnel
+    fn spawn_delivery(self: &Arc<Self>, key: ChannelKey, tx: TcpSocketHalfTx) {
+        let (tx_data, mut rx_data) = mpsc::channel::<Bytes>(CHANNEL_WINDOW);
+        self.channels.lock().unwrap().insert(key, tx_data);
+
+        let state = self.clone();
+        let (channel, initiator) = key;
+        self.work
+            .send(Box::pin(async move {
+                let mut open = true;
+                while let Some(data) = rx_data.recv().await {
+                    // Once the local half is gone the data is dropped but the
+                    // credit is still returned so the far end is not stalled
+                    if open {
+                        if let Err(err) = tx.send(data).await {
+                            tracing::trace!("failed to deliver tunnel data - {}", err);
+                            open = false;
+                        }
+                    }
+                    state.send(TunnelFrame::Credit {
+                        channel,
+                        initiator,
+                        frames: 1,
+                    });
+                }
+                tx.close().ok();
+            }))
+            .ok();
+    }
+
+    fn process(self: &Arc<Self>, frame: TunnelFrame) {
+        match frame {
+            TunnelFrame::Open {
+                channel,
+                local,
+                peer,
+            } => {
+                let listener = self
+                    .tcp_listeners
+                    .lock()
+                    .unwrap()
+                    .get(&peer.port())
+                    .cloned();
+                let Some(listener) = listener else {
+                    tracing::trace!(%peer, "tunnel connection refused as nothing is listening");
+                    self.send(TunnelFrame::Refused { channel });
+                    return;
+                };
+                let (tx, rx) = listener.connect_to(local).split();
+                self.spawn_delivery((channel, false), tx);
+                self.send(TunnelFrame::Accepted { channel });
+                self.spawn_pump(channel, false, rx);
+            }
+            TunnelFrame::Accepted { channel } => {
+                if let Some(tx) = self.pending.lock().unwrap().remove(&channel) {
+                    tx.send(true).ok();
+                }
+            }
+            TunnelFrame::Refused { channel } => {
+                if let Some(tx) = self.pending.lock().unwrap().remove(&channel) {
+                    tx.send(false).ok();
+                }
+            }
+            TunnelFrame::Data {
+                channel,
+                initiator,
+                data,
+            } => {
+                let tx = self
+                    .channels
+                    .lock()
+                    .unwrap()
+                    .get(&(channel, !initiator))
+                    .cloned();
+                let Some(tx) = tx else {
+                    // Nobody is reading this channel anymore so the credit
+                    // goes straight back to the sender
+                    self.send(TunnelFrame::Credit {
+                        channel,
+                        initiator: !initiator,
+                        frames: 1,
+                    });
+                    return;
+                };
+                match tx.try_send(Bytes::from(data)) {
+                    Ok(()) | Err(mpsc::error::TrySendError::Closed(_)) => {}
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        tracing::debug!(
+                            channel,
+                            "tunnel peer overran its window, closing the channel"
+                        );
+                        self.channels.lock().unwrap().remove(&(channel, !initiator));
+                    }
+                }
+            }
+            TunnelFrame::Close { channel, initiator } => {
+                // Dropping the sender closes the local half once all the
+                // data that was already received has been delivered
+                self.channels.lock().unwrap().remove(&(channel, !initiator));
+            }
+            TunnelFrame::Credit {
+                channel,
+                initiator,
+                frames,
+            } => {
+                let window = self
+                    .windows
+                    .lock()
+                    .unwrap()
+                    .get(&(channel, !initiator))
+                    .cloned();
+                if let Some(window) = window {
+                    let room = CHANNEL_WINDOW - window.available_permits();
+                    window.add_permits(room.min(frames as usize));
+                }
+            }
+            TunnelFrame::Datagram { from, to, data } => {
+                let socket = self.udp_sockets.lock().unwrap().get(&to.port()).cloned();
+                if let Some(socket) = socket {
+                    let mut socket = socket.lock().unwrap();
+                    socket.packets.push_back((Bytes::from(data), from));
+                    if let Some(handler) = socket.handler.as_mut() {
+                        handler.push_interest(InterestType::Readable);
+                    }
+                    socket.wakers.drain(..).for_each(|w| w.wake());
+                }
+            }
+        }
+    }
+
+    fn close_all(&self) {
+        self.channels.lock().unwrap().clear();
+        self.pending.lock().unwrap().clear();
+        for (_, window) in self.windows.lock().unwrap().drain() {
+            window.close();
+        }
+    }
+}
+
+/// Forgets about a connection that is being opened if the caller gives
+/// up on it (for instance after a timeout) before the far end answered
+struct PendingConnect {
+    state: Arc<TunnelState>,
+    channel: u64,
+    connected: bool,
+}
+
+impl Drop for PendingConnect {
+    fn drop(&mut self) {
+        if self.connected {
+            return;
+        }
+        self.state.pending.lock().unwrap().remove(&self.channel);
+        self.state
+            .channels
+            .lock()
+            .unwrap()
+            .remove(&(self.channel, true));
+        // The far end may have accepted the connection already
+        self.state.send(TunnelFrame::Close {
+            channel: self.channel,
+            initiator: true,
+        });
+    }
+}
+
+/// Virtual networking that multiplexes TCP connections and UDP datagrams
+/// over a single byte stream (for instance a WebSocket, an SSH channel or
+/// a QUIC stream) that is connected to another [`TunneledNetworking`]
+///
+/// Connections opened on one end of the tunnel are delivered to the
+/// listeners that were opened on the other end, matched by port.
+///
+/// The returned [`TunneledNetworkingDriver`] must be polled (for instance
+/// by spawning it on a runtime) for any data to flow through the tunnel.
+#[derive(Debug, Clone)]
+pub struct TunneledNetworking {
+    state: Arc<TunnelState>,
+}
+
+impl TunneledNetworking {
+    /// Creates a new tunnel over a duplex byte stream
+    pub fn new<T>(io: T) -> (Self, TunneledNetworkingDriver)
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (rx, tx) = tokio::io::split(io);
+        Self::new_from_async_io(tx, rx)
+    }
+
+    /// Creates a new tunnel over a pair of byte streams
+    pub fn new_from_async_io<TX, RX>(tx: TX, rx: RX) -> (Self, TunneledNetworkingDriver)
+    where
+        TX: AsyncWrite + Send + 'static,
+        RX: AsyncRead + Send + 'static,
+    {
+        let (tx_frames, mut rx_frames) = mpsc::unbounded_channel::<TunnelFrame>();
+        let (tx_work, rx_work) = mpsc::unbounded_channel();
+
+        let state = Arc::new(TunnelState {
+            channel_seed: AtomicU64::new(1),
+            port_seed: AtomicU16::new(EPHEMERAL_PORT_START),
+            frames: tx_frames,
+            work: tx_work,
+            channels: Default::default(),
+            windows: Default::default(),
+            pending: Default::default(),
+            tcp_listeners: Default::default(),
+            udp_sockets: Default::default(),
+        });
+
+        let tasks = FuturesUnordered::<BoxFuture<'static, ()>>::new();
+
+        // Writes all the outgoing frames to the tunnel
+        let mut tx = Box::pin(FramedWrite::new(tx, LengthDelimitedCodec::new()));
+        tasks.push(Box::pin(async move {
+            while let Some(frame) = rx_frames.recv().await {
+                let data = match bincode::serialize(&frame) {
+                    Ok(data) => data,
+                    Err(err) => {
+                        tracing::debug!("failed to serialize tunnel frame - {}", err);
+                        continue;
+                    }
+                };
+                if let Err(err) = tx.send(Bytes::from(data)).await {
+                    tracing::debug!("failed to write to the tunnel - {}", err);
+                    break;
+                }
+            }
+        }));
+
+        // Reads all the incoming frames from the tunnel
+        let mut rx = Box::pin(FramedRead::new(rx, LengthDelimitedCodec::new()));
+        let reader_state = state.clone();
+        tasks.push(Box::pin(async move {
+            while let Some(data) = rx.next().await {
+                let data = match data {
+                    Ok(data) => data,
+                    Err(err) => {
+                        tracing::debug!("failed to read from the tunnel - {}", err);
+                        break;
+                    }
+                };
+                let frame: TunnelFrame = match bincode::deserialize(&data) {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        tracing::debug!("failed to deserialize tunnel frame - {}", err);
+                        break;
+                    }
+                };
+                reader_state.process(frame);
+            }
+            reader_state.close_all();
+        }));
+
+        let driver = TunneledNetworkingDriver {
+            more_work: rx_work,
+            tasks,
+        };
+        (Self { state }, driver)
+    }
+}
+
+#[async_trait::async_trait]
+impl VirtualNetworking for TunneledNetworking {
+    async fn listen_tcp(
+        &self,
+        addr: SocketAddr,
+        _only_v6: bool,
+        _reuse_port: bool,
+        _reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualTcpListener + Sync>> {
+        let addr = self.state.resolve_addr(addr);
+        let mut listeners = self.state.tcp_listeners.lock().unwrap();
+        if listeners.contains_key(&addr.port()) {
+            return Err(NetworkError::AddressInUse);
+        }
+        let listener = LoopbackTcpListener::new(addr);
+        listeners.insert(addr.port(), listener.clone());
+        Ok(Box::new(TunneledTcpListener {
+            tunnel: self.state.clone(),
+            listener,
+            port: addr.port(),
+        }))
+    }
+
+    async fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        peer: SocketAddr,
+    ) -> Result<Box<dyn VirtualTcpSocket + Sync>> {
+        let addr = self.state.resolve_addr(addr);
+        let channel = self.state.channel_seed.fetch_add(1, Ordering::SeqCst);
+
+        let (socket, pump) = TcpSocketHalf::channel(DEFAULT_MAX_BUFFER_SIZE, addr, peer);
+        let (tx, rx) = pump.split();
+        self.state.spawn_delivery((channel, true), tx);
+
+        // Wait for the far end to tell us if anything is listening
+        let (tx_accepted, rx_accepted) = oneshot::channel();
+        self.state
+            .pending
+            .lock()
+            .unwrap()
+            .insert(channel, tx_accepted);
+        let mut guard = PendingConnect {
+            state: self.state.clone(),
+            channel,
+            connected: false,
+        };
+        self.state.send(TunnelFrame::Open {
+            channel,
+            local: addr,
+            peer,
+        });
+        match rx_accepted.await {
+            Ok(true) => {}
+            Ok(false) => return Err(NetworkError::ConnectionRefused),
+            Err(_) => return Err(NetworkError::ConnectionAborted),
+        }
+        guard.connected = true;
+        self.state.spawn_pump(channel, true, rx);
+
+        Ok(Box::new(socket))
+    }
+
+    async fn bind_udp(
+        &self,
+        addr: SocketAddr,
+        _reuse_port: bool,
+        _reuse_addr: bool,
+    ) -> Result<Box<dyn VirtualUdpSocket + Sync>> {
+        let addr = self.state.resolve_addr(addr);
+        let mut sockets = self.state.udp_sockets.lock().unwrap();
+        if sockets.contains_key(&addr.port()) {
+            return Err(NetworkError::AddressInUse);
+        }
+        let socket_state = Arc::new(Mutex::new(TunneledUdpSocketState::default()));
+        sockets.insert(addr.port(), socket_state.clone());
+        Ok(Box::new(TunneledUdpSocket {
+            tunnel: self.state.clone(),
+            state: socket_state,
+            addr_local: addr,
+            addr_peer: None,
+            ttl: 64,
+            broadcast: false,
+        }))
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Drives the tunnel by reading and writing frames from the underlying
+    /// byte stream and pumping data between the virtual connections
+    pub struct TunneledNetworkingDriver {
+        more_work: mpsc::UnboundedReceiver<BoxFuture<'static, ()>>,
+        #[pin]
+        tasks: FuturesUnordered<BoxFuture<'static, ()>>,
+    }
+}
+
+impl Future for TunneledNetworkingDriver {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            // Background tasks are sent to this driver whenever new channels are opened
+            let mut closed = false;
+            loop {
+                match Pin::new(&mut self.more_work).poll_recv(cx) {
+                    Poll::Ready(Some(work)) => self.tasks.push(work),
+                    Poll::Ready(None) => {
+                        closed = true;
+                        break;
+                    }
+                    Poll::Pending => break,
+                }
+            }
+
+            return match self.tasks.poll_next_unpin(cx) {
+                Poll::Ready(Some(_)) => continue,
+                Poll::Ready(None) if closed => Poll::Ready(()),
+                Poll::Ready(None) | Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TunneledTcpListener {
+    tunnel: Arc<TunnelState>,
+    listener: LoopbackTcpListener,
+    port: u16,
+}
+
+impl Drop for TunneledTcpListener {
+    fn drop(&mut self) {
+        let mut listeners = self.tunnel.tcp_listeners.lock().unwrap();
+        if let Some(listener) = listeners.get(&self.port) {
+            if listener.ptr_eq(&self.listener) {
+                listeners.remove(&self.port);
+            }
+        }
+    }
+}
+
+impl VirtualIoSource for TunneledTcpListener {
+    fn remove_handler(&mut self) {
+        self.listener.remove_handler()
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.listener.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        self.listener.poll_write_ready(cx)
+    }
+}
+
+impl VirtualTcpListener for TunneledTcpListener {
+    fn try_accept(&mut self) -> Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr)> {
+        self.listener.try_accept()
+    }
+
+    fn set_handler(&mut self, handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        self.listener.set_handler(handler)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        self.listener.addr_local()
+    }
+
+    fn set_ttl(&mut self, ttl: u8) -> Result<()> {
+        self.listener.set_ttl(ttl)
+    }
+
+    fn ttl(&self) -> Result<u8> {
+        self.listener.ttl()
+    }
+}
+
+#[derive(Debug, Default)]
+struct TunneledUdpSocketState {
+    packets: VecDeque<(Bytes, SocketAddr)>,
+    handler: Option<Box<dyn InterestHandler + Send + Sync>>,
+    wakers: Vec<Waker>,
+}
+
+#[derive(Debug)]
+struct TunneledUdpSocket {
+    tunnel: Arc<TunnelState>,
+    state: Arc<Mutex<TunneledUdpSocketState>>,
+    addr_local: SocketAddr,
+    addr_peer: Option<SocketAddr>,
+    ttl: u32,
+    broadcast: bool,
+}
+
+impl Drop for TunneledUdpSocket {
+    fn drop(&mut self) {
+        let mut sockets = self.tunnel.udp_sockets.lock().unwrap();
+        if let Some(state) = sockets.get(&self.addr_local.port()) {
+            if Arc::ptr_eq(state, &self.state) {
+                sockets.remove(&self.addr_local.port());
+            }
+        }
+    }
+}
+
+impl VirtualIoSource for TunneledUdpSocket {
+    fn remove_handler(&mut self) {
+        self.state.lock().unwrap().handler.take();
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some((data, _)) = state.packets.front() {
+            return Poll::Ready(Ok(data.len()));
+        }
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    fn poll_write_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<usize>> {
+        Poll::Ready(Ok(DATA_FRAME_SIZE))
+    }
+}
+
+impl VirtualSocket for TunneledUdpSocket {
+    fn set_ttl(&mut self, ttl: u32) -> Result<()> {
+        self.ttl = ttl;
+        Ok(())
+    }
+
+    fn ttl(&self) -> Result<u32> {
+        Ok(self.ttl)
+    }
+
+    fn addr_local(&self) -> Result<SocketAddr> {
+        Ok(self.addr_local)
+    }
+
+    fn status(&self) -> Result<SocketStatus> {
+        Ok(SocketStatus::Opened)
+    }
+
+    fn set_handler(&mut self, mut handler: Box<dyn InterestHandler + Send + Sync>) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.packets.is_empty() {
+            handler.push_interest(InterestType::Readable);
+        }
+        handler.push_interest(InterestType::Writable);
+        state.handler.replace(handler);
+        Ok(())
+    }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .packets
+            .front()
+            .map(|(data, _)| data.len())
+            .unwrap_or(0))
+    }
+}
+
+impl VirtualConnectionlessSocket for TunneledUdpSocket {
+    fn try_send_to(&mut self, data: &[u8], addr: SocketAddr) -> Result<usize> {
+        self.tunnel.send(TunnelFrame::Datagram {
+            from: self.addr_local,
+            to: addr,
+            data: data.to_vec(),
+        });
+        Ok(data.len())
+    }
+
+    fn try_recv_from(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<(usize, SocketAddr)> {
+        self.try_recv_from_with_size(buf)
+            .map(|(amt, _, addr)| (amt, addr))
+    }
+
+    fn try_recv_from_with_size(
+        &mut self,
+        buf: &mut [MaybeUninit<u8>],
+    ) -> Result<(usize, usize, SocketAddr)> {
+        let mut state = self.state.lock().unwrap();
+        let (data, addr) = state.packets.pop_front().ok_or(NetworkError::WouldBlock)?;
+        let amt = buf.len().min(data.len());
+        let buf: &mut [u8] = unsafe { std::mem::transmute(&mut buf[..amt]) };
+        buf.copy_from_slice(&data[..amt]);
+        Ok((amt, data.len(), addr))
+    }
+}
+
+impl VirtualUdpSocket for TunneledUdpSocket {
+    fn set_broadcast(&mut self, broadcast: bool) -> Result<()> {
+        self.broadcast = broadcast;
+        Ok(())
+    }
+
+    fn broadcast(&self) -> Result<bool> {
+        Ok(self.broadcast)
+    }
+
+    fn set_multicast_loop_v4(&mut self, _val: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_loop_v4(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_multicast_loop_v6(&mut self, _val: bool) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_loop_v6(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn set_multicast_ttl_v4(&mut self, _ttl: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn multicast_ttl_v4(&self) -> Result<u32> {
+        Ok(1)
+    }
+
+    fn join_multicast_v4(&mut self, _multiaddr: Ipv4Addr, _iface: Ipv4Addr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn leave_multicast_v4(&mut self, _multiaddr: Ipv4Addr, _iface: Ipv4Addr) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn join_multicast_v6(&mut self, _multiaddr: Ipv6Addr, _iface: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn leave_multicast_v6(&mut self, _multiaddr: Ipv6Addr, _iface: u32) -> Result<()> {
+        Err(NetworkError::Unsupported)
+    }
+
+    fn addr_peer(&self) -> Result<Option<SocketAddr>> {
+        Ok(self.addr_peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::AsyncWriteExt;
+
+    use crate::VirtualTcpListenerExt;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancelled_connect_is_forgotten() {
+        let (io1, io2) = tokio::io::duplex(4096);
+        let (networking1, driver1) = TunneledNetworking::new(io1);
+        let (networking2, driver2) = TunneledNetworking::new(io2);
+        tokio::task::spawn(driver1);
+
+        let local = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 8080));
+        let mut listener = networking2
+            .listen_tcp(addr, false, false, false)
+            .await
+            .unwrap();
+
+        // The far end is not driven yet so it never answers the connect
+        let ret = tokio::time::timeout(
+            Duration::from_millis(100),
+            networking1.connect_tcp(local, addr),
+        )
+        .await;
+        assert!(ret.is_err());
+        assert!(networking1.state.pending.lock().unwrap().is_empty());
+        assert!(networking1.state.channels.lock().unwrap().is_empty());
+
+        // Once the far end catches up the abandoned connection is closed
+        tokio::task::spawn(driver2);
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(socket.read(&mut buf).await.unwrap(), 0);
+
+        networking1.connect_tcp(local, addr).await.unwrap();
+        listener.accept().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn transfer_larger_than_the_window() {
+        const LEN: usize = 4 * CHANNEL_WINDOW * DATA_FRAME_SIZE;
+
+        let (io1, io2) = tokio::io::duplex(4096);
+        let (networking1, driver1) = TunneledNetworking::new(io1);
+        let (networking2, driver2) = TunneledNetworking::new(io2);
+        tokio::task::spawn(driver1);
+        tokio::task::spawn(driver2);
+
+        let local = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 8080));
+        let mut listener = networking2
+            .listen_tcp(addr, false, false, false)
+            .await
+            .unwrap();
+        let mut client = networking1.connect_tcp(local, addr).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let data = (0..LEN).map(|i| i as u8).collect::<Vec<_>>();
+        let writer = tokio::task::spawn({
+            let data = data.clone();
+            async move {
+                client.write_all(&data).await.unwrap();
+                client
+            }
+        });
+
+        // Nothing is read for a while, the writer has to wait for credit
+        // rather than queueing everything in the tunnel
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!writer.is_finished());
+
+        let mut received = vec![0u8; LEN];
+        server.read_exact(&mut received).await.unwrap();
+        assert!(received == data);
+        writer.await.unwrap();
+    }
+}
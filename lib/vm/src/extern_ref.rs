@@ -1,12 +1,14 @@
 use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, Weak};
 use wasmer_types::RawValue;
 
 use crate::store::InternalStoreHandle;
 
 /// Underlying object referenced by a `VMExternRef`.
-#[derive(Debug)]
 pub struct VMExternObj {
     contents: Box<dyn Any + Send + Sync + 'static>,
+    finalizer: Option<Weak<Finalizer>>,
 }
 
 impl VMExternObj {
@@ -14,9 +16,44 @@ impl VMExternObj {
     pub fn new(val: impl Any + Send + Sync + 'static) -> Self {
         Self {
             contents: Box::new(val),
+            finalizer: None,
         }
     }
 
+    /// Wraps the given value like [`VMExternObj::new`] and returns the first
+    /// counted reference to it. `finalizer` runs exactly once, when the last
+    /// [`VMExternRefGuard`] of the object is dropped.
+    ///
+    /// This allows cleaning up resources behind opaque values (such as raw
+    /// pointers handed out by a foreign library) that have no `Drop` of
+    /// their own. Only the guards are counted: raw `VMExternRef` handles
+    /// (including the ones held by Wasm tables, globals and locals) are
+    /// plain copies, so the host must keep a guard alive for as long as Wasm
+    /// may use the value.
+    pub fn new_with_finalizer(
+        val: impl Any + Send + Sync + 'static,
+        finalizer: Box<dyn FnOnce() + Send + Sync + 'static>,
+    ) -> (Self, VMExternRefGuard) {
+        let finalizer = Arc::new(Finalizer(Some(finalizer)));
+        let obj = Self {
+            contents: Box::new(val),
+            finalizer: Some(Arc::downgrade(&finalizer)),
+        };
+        (obj, VMExternRefGuard(finalizer))
+    }
+
+    /// Takes another counted reference to an object that was created with
+    /// a finalizer.
+    ///
+    /// Returns `None` if the object has no finalizer, or if the finalizer
+    /// already ran because all the previous guards were dropped.
+    pub fn guard(&self) -> Option<VMExternRefGuard> {
+        self.finalizer
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .map(VMExternRefGuard)
+    }
+
     #[allow(clippy::should_implement_trait)]
     /// Returns a reference to the underlying value.
     pub fn as_ref(&self) -> &(dyn Any + Send + Sync + 'static) {
@@ -24,6 +61,40 @@ impl VMExternObj {
     }
 }
 
+impl fmt::Debug for VMExternObj {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMExternObj")
+            .field("contents", &self.contents)
+            .field("has_finalizer", &self.finalizer.is_some())
+            .finish()
+    }
+}
+
+struct Finalizer(Option<Box<dyn FnOnce() + Send + Sync + 'static>>);
+
+impl Drop for Finalizer {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.0.take() {
+            finalizer();
+        }
+    }
+}
+
+/// A counted reference to a [`VMExternObj`] that has a finalizer.
+///
+/// Cloning a guard adds a reference; the finalizer of the object runs when
+/// the last guard is dropped.
+#[derive(Clone)]
+pub struct VMExternRefGuard(Arc<Finalizer>);
+
+impl fmt::Debug for VMExternRefGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMExternRefGuard")
+            .field("refs", &Arc::strong_count(&self.0))
+            .finish()
+    }
+}
+
 /// Represents an opaque reference to any data within WebAssembly.
 #[repr(transparent)]
 #[derive(Debug, Clone, Copy)]
@@ -45,3 +116,37 @@ impl VMExternRef {
         InternalStoreHandle::from_index(raw.externref).map(Self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::StoreObjects;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn finalizer_runs_once_when_the_last_reference_is_dropped() {
+        let finalized = Arc::new(AtomicBool::new(false));
+        let mut objects = StoreObjects::default();
+        let (obj, guard) = VMExternObj::new_with_finalizer(42u32, {
+            let finalized = finalized.clone();
+            Box::new(move || assert!(!finalized.swap(true, Ordering::SeqCst)))
+        });
+        let handle = VMExternRef(InternalStoreHandle::new(&mut objects, obj));
+
+        // Copying the handle or cloning the guard does not run the finalizer
+        let copy = handle;
+        let raw = unsafe { VMExternRef::from_raw(copy.into_raw()) }.unwrap();
+        assert_eq!(raw.0.get(&objects).as_ref().downcast_ref(), Some(&42u32));
+        let other = raw.0.get(&objects).guard().unwrap();
+        drop(guard.clone());
+        drop(guard);
+        assert!(!finalized.load(Ordering::SeqCst));
+
+        drop(other);
+        assert!(finalized.load(Ordering::SeqCst));
+        assert!(raw.0.get(&objects).guard().is_none());
+
+        // Dropping the store afterwards does not run it again
+        drop(objects);
+    }
+}
@@ -20,7 +20,7 @@ use crate::vmcontext::{
     VMMemoryImport, VMSharedSignatureIndex, VMTableDefinition, VMTableImport, VMTrampoline,
 };
 use crate::{FunctionBodyPtr, MaybeInstanceOwned, TrapHandlerFn, VMFunctionBody};
-use crate::{LinearMemory, NotifyLocation};
+use crate::{LinearMemory, NotifyLocation, SignatureRegistration};
 use crate::{VMConfig, VMFuncRef, VMFunction, VMGlobal, VMMemory, VMTable};
 pub use allocator::InstanceAllocator;
 use memoffset::offset_of;
@@ -53,6 +53,9 @@ pub(crate) struct Instance {
     /// The `ModuleInfo` this `Instance` was instantiated from.
     module: Arc<ModuleInfo>,
 
+    /// Keeps the shared signature indices used by this instance registered.
+    _signature_registration: Arc<SignatureRegistration>,
+
     /// Pointer to the object store of the context owning this instance.
     context: *mut StoreObjects,
 
@@ -1024,6 +1027,7 @@ impl VMInstance {
         finished_globals: BoxedSlice<LocalGlobalIndex, InternalStoreHandle<VMGlobal>>,
         imports: Imports,
         vmshared_signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
+        signature_registration: Arc<SignatureRegistration>,
     ) -> Result<Self, Trap> {
         let vmctx_globals = finished_globals
             .values()
@@ -1047,6 +1051,7 @@ impl VMInstance {
             // Create the `Instance`. The unique, the One.
             let instance = Instance {
                 module,
+                _signature_registration: signature_registration,
                 context,
                 offsets,
                 memories: finished_memories,
@@ -35,18 +35,20 @@ pub mod libcalls;
 use std::ptr::NonNull;
 
 pub use crate::export::*;
-pub use crate::extern_ref::{VMExternObj, VMExternRef};
+pub use crate::extern_ref::{VMExternObj, VMExternRef, VMExternRefGuard};
 pub use crate::function_env::VMFunctionEnvironment;
 pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{InstanceAllocator, VMInstance};
+#[cfg(feature = "memory-stats")]
+pub use crate::memory::MemoryStats;
 pub use crate::memory::{
-    initialize_memory_with_data, LinearMemory, NotifyLocation, VMMemory, VMOwnedMemory,
-    VMSharedMemory,
+    initialize_memory_with_data, LinearMemory, NotifyLocation, ProtectedPages, VMMemory,
+    VMOwnedMemory, VMSharedMemory,
 };
 pub use crate::mmap::{Mmap, MmapType};
 pub use crate::probestack::PROBESTACK;
-pub use crate::sig_registry::SignatureRegistry;
+pub use crate::sig_registry::{SignatureRegistration, SignatureRegistry};
 pub use crate::store::{InternalStoreHandle, MaybeInstanceOwned, StoreHandle, StoreObjects};
 pub use crate::table::{TableElement, VMTable};
 #[doc(hidden)]
@@ -62,6 +64,7 @@ pub use wasmer_types::MemoryError;
 pub use wasmer_types::MemoryStyle;
 use wasmer_types::RawValue;
 pub use wasmer_types::TableStyle;
+pub use wasmer_types::ZeroingStrategy;
 pub use wasmer_types::{StoreId, TargetSharedSignatureIndex, VMBuiltinFunctionIndex, VMOffsets};
 
 /// Version number of this crate.
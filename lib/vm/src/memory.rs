@@ -12,13 +12,20 @@ use crate::trap::Trap;
 use crate::{mmap::Mmap, store::MaybeInstanceOwned, vmcontext::VMMemoryDefinition};
 use more_asserts::assert_ge;
 use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::ops::Range;
 use std::ptr::NonNull;
 use std::rc::Rc;
 use std::slice;
-use std::sync::RwLock;
+#[cfg(feature = "memory-stats")]
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use wasmer_types::{Bytes, MemoryError, MemoryStyle, MemoryType, Pages, WASM_PAGE_SIZE};
+use wasmer_types::{
+    Bytes, MemoryError, MemoryProtection, MemoryStyle, MemoryType, Pages, ZeroingStrategy,
+    WASM_PAGE_SIZE,
+};
 
 // The memory mapped area
 #[derive(Debug)]
@@ -27,8 +34,17 @@ struct WasmMmap {
     alloc: Mmap,
     // The current logical size in wasm pages of this linear memory.
     size: Pages,
+    // The number of bytes at the start of the allocation that are accessible,
+    // this runs ahead of `size` when growth is rounded up.
+    accessible: usize,
+    // The host pages (by offset) that the guest protected with something
+    // other than read-write access, shared with the views of the host.
+    protected: Arc<ProtectedPages>,
     /// The owned memory definition used by the generated code
     vm_memory_definition: MaybeInstanceOwned<VMMemoryDefinition>,
+    // Usage statistics of this memory
+    #[cfg(feature = "memory-stats")]
+    counters: MemoryCounters,
 }
 
 impl WasmMmap {
@@ -47,6 +63,8 @@ impl WasmMmap {
     fn grow(&mut self, delta: Pages, conf: VMMemoryConfig) -> Result<Pages, MemoryError> {
         // Optimization of memory.grow 0 calls.
         if delta.0 == 0 {
+            #[cfg(feature = "memory-stats")]
+            self.counters.record_grow(self.size);
             return Ok(self.size);
         }
 
@@ -79,38 +97,57 @@ impl WasmMmap {
             });
         }
 
-        let delta_bytes = delta.bytes().0;
-        let prev_bytes = prev_pages.bytes().0;
         let new_bytes = new_pages.bytes().0;
 
-        if new_bytes > self.alloc.len() - conf.offset_guard_size {
+        // Round the growth up to the growth granularity of the memory (but
+        // never past its maximum) so that many small grows only need a few
+        // map extensions.
+        let granularity = conf.style.growth_granularity().0.max(1) as u64;
+        let limit = conf.maximum.unwrap_or_else(Pages::max_value).0 as u64;
+        let reserve_pages = (new_pages.0 as u64)
+            .div_ceil(granularity)
+            .saturating_mul(granularity)
+            .min(limit)
+            .max(new_pages.0 as u64);
+        let reserve_bytes = Pages(reserve_pages as u32).bytes().0;
+
+        if new_bytes > self.alloc.len() - self.alloc.guard_size() {
             // If the new size is within the declared maximum, but needs more memory than we
             // have on hand, it's a dynamic heap and it can move.
             let guard_bytes = conf.offset_guard_size;
-            let request_bytes =
-                new_bytes
-                    .checked_add(guard_bytes)
-                    .ok_or_else(|| MemoryError::CouldNotGrow {
-                        current: new_pages,
-                        attempted_delta: Bytes(guard_bytes).try_into().unwrap(),
-                    })?;
+            let request_bytes = reserve_bytes.checked_add(guard_bytes).ok_or_else(|| {
+                MemoryError::CouldNotGrow {
+                    current: new_pages,
+                    attempted_delta: Bytes(guard_bytes).try_into().unwrap(),
+                }
+            })?;
 
+            count_map_extension();
             let mut new_mmap =
-                Mmap::accessible_reserved(new_bytes, request_bytes, None, MmapType::Private)
+                Mmap::accessible_reserved(reserve_bytes, request_bytes, None, MmapType::Private)
                     .map_err(MemoryError::Region)?;
+            new_mmap.set_guard_size(guard_bytes);
 
-            let copy_len = self.alloc.len() - conf.offset_guard_size;
+            let copy_len = self.alloc.len() - self.alloc.guard_size();
+            self.apply_protections(&self.alloc, false)?;
             new_mmap.as_mut_slice()[..copy_len].copy_from_slice(&self.alloc.as_slice()[..copy_len]);
+            self.apply_protections(&new_mmap, true)?;
 
             self.alloc = new_mmap;
-        } else if delta_bytes > 0 {
+            self.accessible = reserve_bytes;
+        } else if new_bytes > self.accessible {
             // Make the newly allocated pages accessible.
+            let accessible = reserve_bytes.min(self.alloc.len() - self.alloc.guard_size());
+            count_map_extension();
             self.alloc
-                .make_accessible(prev_bytes, delta_bytes)
+                .make_accessible(self.accessible, accessible - self.accessible)
                 .map_err(MemoryError::Region)?;
+            self.accessible = accessible;
         }
 
         self.size = new_pages;
+        #[cfg(feature = "memory-stats")]
+        self.counters.record_grow(new_pages);
 
         // update memory definition
         unsafe {
@@ -136,20 +173,161 @@ impl WasmMmap {
         Ok(())
     }
 
-    /// Resets the memory down to a zero size
-    fn reset(&mut self) -> Result<(), MemoryError> {
-        self.size.0 = 0;
+    /// Grows the memory to at least `min_pages` in a single step and returns
+    /// the previous size, does nothing when the memory is already big enough
+    fn grow_at_least_pages(
+        &mut self,
+        min_pages: Pages,
+        conf: VMMemoryConfig,
+    ) -> Result<Pages, MemoryError> {
+        if min_pages <= self.size {
+            return Ok(self.size);
+        }
+        self.grow(min_pages - self.size, conf)
+    }
+
+    /// Returns the indices of the host pages (not wasm pages) within the
+    /// used part of the memory that are resident and may hold non-zero data.
+    /// The operating system already tracks which pages were touched since
+    /// they were mapped (or discarded) so the contents never have to be
+    /// scanned. Pages that were swapped out are not included.
+    fn dirty_pages(&self) -> Vec<usize> {
+        let page_size = region::page::size();
+        let used = self.size.bytes().0.min(self.alloc.len());
+        let len = used.next_multiple_of(page_size).min(self.alloc.len());
+        self.alloc
+            .resident_pages(0, len)
+            // Without residency information every used page may be dirty
+            .unwrap_or_else(|_| (0..len / page_size).collect())
+    }
+
+    /// Changes the access guest code has to the host pages in `range`, which
+    /// must start on a host page boundary and lie within the current size of
+    /// the memory (the end is rounded up to the next host page).
+    fn protect(
+        &mut self,
+        range: Range<u64>,
+        protection: MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        let page_size = region::page::size();
+        let invalid = |reason: &str| MemoryError::InvalidMemory {
+            reason: format!("can not protect {range:?}: {reason}"),
+        };
+        let start = usize::try_from(range.start).map_err(|_| invalid("out of bounds"))?;
+        let end = usize::try_from(range.end).map_err(|_| invalid("out of bounds"))?;
+        if start % page_size != 0 {
+            return Err(invalid("the start is not aligned to the host page size"));
+        }
+        if end < start {
+            return Err(invalid("the range is inverted"));
+        }
+        let end = end
+            .checked_next_multiple_of(page_size)
+            .ok_or_else(|| invalid("out of bounds"))?;
+        if end > self.size.bytes().0 {
+            return Err(invalid("the range is not within the memory"));
+        }
+
+        self.alloc
+            .protect(start, end - start, protection)
+            .map_err(MemoryError::Region)?;
+        let protected = &mut Arc::make_mut(&mut self.protected).0;
+        for offset in (start..end).step_by(page_size) {
+            match protection {
+                MemoryProtection::ReadWrite => protected.remove(&offset),
+                protection => protected.insert(offset, protection),
+            };
+        }
         Ok(())
     }
 
+    /// Returns the protected pages, if there are any
+    fn protected_pages(&self) -> Option<Arc<ProtectedPages>> {
+        if self.protected.0.is_empty() {
+            None
+        } else {
+            Some(self.protected.clone())
+        }
+    }
+
+    /// Applies the protections of the guest to `alloc`, or lifts them again
+    /// (so that the host can copy the contents) when `apply` is false.
+    fn apply_protections(&self, alloc: &Mmap, apply: bool) -> Result<(), MemoryError> {
+        let page_size = region::page::size();
+        for (offset, protection) in self.protected.0.iter() {
+            let protection = if apply {
+                *protection
+            } else {
+                MemoryProtection::ReadWrite
+            };
+            alloc
+                .protect(*offset, page_size, protection)
+                .map_err(MemoryError::Region)?;
+        }
+        Ok(())
+    }
+
+    /// Resets the memory down to a zero size, zeroing the old contents
+    /// according to the zeroing strategy. Returns the host pages that
+    /// were explicitly zeroed.
+    fn reset(&mut self, zeroing: ZeroingStrategy) -> Result<Vec<usize>, MemoryError> {
+        let page_size = region::page::size();
+        // The memory starts over, so does the protection of its pages
+        self.apply_protections(&self.alloc, false)?;
+        self.protected = Default::default();
+        let zeroed = match zeroing {
+            ZeroingStrategy::OnAllocate => Vec::new(),
+            ZeroingStrategy::Lazy => {
+                let used = self.size.bytes().0.min(self.alloc.len());
+                let len = (used + page_size - 1) & !(page_size - 1);
+                self.alloc
+                    .discard(0, len.min(self.alloc.len()))
+                    .map_err(MemoryError::Region)?;
+                Vec::new()
+            }
+            ZeroingStrategy::OnReuse => {
+                let used = self.size.bytes().0.min(self.alloc.len());
+                let len = used.next_multiple_of(page_size).min(self.alloc.len());
+                let dirty = self.dirty_pages();
+                // Pages that are not resident may still hold data that was
+                // swapped out (or written back to a backing file), so they
+                // are handed back to the operating system rather than
+                // trusted to read as zeros
+                let mut next = 0;
+                for index in dirty
+                    .iter()
+                    .copied()
+                    .chain(std::iter::once(len / page_size))
+                {
+                    if index > next {
+                        self.alloc
+                            .discard(next * page_size, (index - next) * page_size)
+                            .map_err(MemoryError::Region)?;
+                    }
+                    next = index + 1;
+                }
+                let slice = self.alloc.as_mut_slice();
+                for index in dirty.iter() {
+                    let start = index * page_size;
+                    let end = (start + page_size).min(slice.len());
+                    slice[start..end].fill(0);
+                }
+                dirty
+            }
+        };
+        self.size.0 = 0;
+        Ok(zeroed)
+    }
+
     /// Copies the memory
     /// (in this case it performs a copy-on-write to save memory)
     pub fn copy(&mut self) -> Result<Self, MemoryError> {
         let mem_length = self.size.bytes().0;
-        let mut alloc = self
-            .alloc
-            .copy(Some(mem_length))
-            .map_err(MemoryError::Generic)?;
+        self.apply_protections(&self.alloc, false)?;
+        let alloc = self.alloc.copy(Some(mem_length));
+        self.apply_protections(&self.alloc, true)?;
+        let mut alloc = alloc.map_err(MemoryError::Generic)?;
+        self.apply_protections(&alloc, true)?;
         let base_ptr = alloc.as_mut_ptr();
         Ok(Self {
             vm_memory_definition: MaybeInstanceOwned::Host(Box::new(UnsafeCell::new(
@@ -158,10 +336,133 @@ impl WasmMmap {
                     current_length: mem_length,
                 },
             ))),
+            accessible: alloc.as_slice_accessible().len(),
+            protected: self.protected.clone(),
             alloc,
             size: self.size,
+            #[cfg(feature = "memory-stats")]
+            counters: MemoryCounters::new(self.size),
         })
     }
+
+    /// Takes a copy-on-write snapshot of the memory
+    /// (see [`Mmap::try_clone_cow`] for how this works on each platform)
+    fn try_clone_cow(&self) -> Result<Self, MemoryError> {
+        let mem_length = self.size.bytes().0;
+        self.apply_protections(&self.alloc, false)?;
+        let alloc = self.alloc.try_clone_cow(Some(mem_length));
+        self.apply_protections(&self.alloc, true)?;
+        let mut alloc = alloc.map_err(MemoryError::Generic)?;
+        self.apply_protections(&alloc, true)?;
+        let base_ptr = alloc.as_mut_ptr();
+        Ok(Self {
+            vm_memory_definition: MaybeInstanceOwned::Host(Box::new(UnsafeCell::new(
+                VMMemoryDefinition {
+                    base: base_ptr,
+                    current_length: mem_length,
+                },
+            ))),
+            accessible: alloc.as_slice_accessible().len(),
+            protected: self.protected.clone(),
+            alloc,
+            size: self.size,
+            #[cfg(feature = "memory-stats")]
+            counters: MemoryCounters::new(self.size),
+        })
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Counts the calls that map more memory, so tests can check how often a
+    /// memory was remapped while it grew.
+    static MAP_EXTENSIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+fn count_map_extension() {
+    MAP_EXTENSIONS.with(|count| count.set(count.get() + 1));
+}
+
+#[cfg(not(test))]
+#[inline(always)]
+fn count_map_extension() {}
+
+/// The host pages of a memory whose protection was changed with
+/// [`LinearMemory::protect`], keyed by their offset in the memory.
+///
+/// Host functions check their accesses against these before touching the
+/// memory, as a fault inside the host would unwind through its frames
+/// without running any destructors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtectedPages(BTreeMap<usize, MemoryProtection>);
+
+impl ProtectedPages {
+    /// Returns true if none of the pages are protected
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true if the host may read all bytes in `range`, or also
+    /// write them when `write` is set
+    pub fn allows(&self, range: Range<u64>, write: bool) -> bool {
+        if range.is_empty() {
+            return true;
+        }
+        let page_size = region::page::size() as u64;
+        let first = range.start - range.start % page_size;
+        let (Ok(first), Ok(end)) = (usize::try_from(first), usize::try_from(range.end)) else {
+            return false;
+        };
+        self.0
+            .range(first..end)
+            .all(|(_, protection)| match protection {
+                MemoryProtection::None => false,
+                MemoryProtection::ReadOnly => !write,
+                MemoryProtection::ReadWrite => true,
+            })
+    }
+}
+
+/// Usage statistics of a memory, see [`LinearMemory::stats`].
+#[cfg(feature = "memory-stats")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Number of successful grows (including grows by zero pages)
+    pub grow_count: u64,
+    /// Largest size the memory ever had
+    pub peak_pages: Pages,
+}
+
+/// The counters behind [`MemoryStats`], they are atomics so that the peak
+/// stays consistent when the memory is shared between threads.
+#[cfg(feature = "memory-stats")]
+#[derive(Debug, Default)]
+struct MemoryCounters {
+    grow_count: AtomicU64,
+    peak_pages: AtomicU32,
+}
+
+#[cfg(feature = "memory-stats")]
+impl MemoryCounters {
+    fn new(size: Pages) -> Self {
+        Self {
+            grow_count: AtomicU64::new(0),
+            peak_pages: AtomicU32::new(size.0),
+        }
+    }
+
+    fn record_grow(&self, size: Pages) {
+        self.grow_count.fetch_add(1, Ordering::Relaxed);
+        self.peak_pages.fetch_max(size.0, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> MemoryStats {
+        MemoryStats {
+            grow_count: self.grow_count.load(Ordering::Relaxed),
+            peak_pages: Pages(self.peak_pages.load(Ordering::Relaxed)),
+        }
+    }
 }
 
 /// A linear memory instance.
@@ -176,6 +477,8 @@ struct VMMemoryConfig {
     // Size in bytes of extra guard pages after the end to optimize loads and stores with
     // constant offsets.
     offset_guard_size: usize,
+    /// How the memory is zeroed when it is reset.
+    zeroing: ZeroingStrategy,
 }
 
 impl VMMemoryConfig {
@@ -315,13 +618,31 @@ impl VMOwnedMemory {
             }
         };
         let minimum_bytes = minimum_pages.bytes().0;
-        let request_bytes = minimum_bytes.checked_add(offset_guard_bytes).unwrap();
+        let request_bytes = minimum_bytes.checked_add(offset_guard_bytes).ok_or_else(|| {
+            MemoryError::InsufficientMemory {
+                requested: usize::MAX,
+                reason: format!(
+                    "{minimum_bytes} bytes with {offset_guard_bytes} guard bytes do not fit in the address space"
+                ),
+            }
+        })?;
         let mapped_pages = memory.minimum;
         let mapped_bytes = mapped_pages.bytes();
 
+        // Running out of memory here has to be reported (rather than abort) so
+        // that embedders can turn down modules that ask for too much memory,
+        // file backed memories can also fail because of the file itself.
+        let file_backed = backing_file.is_some();
         let mut alloc =
             Mmap::accessible_reserved(mapped_bytes.0, request_bytes, backing_file, memory_type)
-                .map_err(MemoryError::Region)?;
+                .map_err(|reason| match file_backed {
+                    true => MemoryError::Region(reason),
+                    false => MemoryError::InsufficientMemory {
+                        requested: request_bytes,
+                        reason,
+                    },
+                })?;
+        alloc.set_guard_size(offset_guard_bytes);
 
         let base_ptr = alloc.as_mut_ptr();
         let mem_length = memory
@@ -344,8 +665,12 @@ impl VMOwnedMemory {
                     current_length: mem_length,
                 })))
             },
+            accessible: alloc.as_slice_accessible().len(),
+            protected: Default::default(),
             alloc,
             size: Bytes::from(mem_length).try_into().unwrap(),
+            #[cfg(feature = "memory-stats")]
+            counters: MemoryCounters::new(Bytes::from(mem_length).try_into().unwrap()),
         };
 
         Ok(Self {
@@ -355,6 +680,7 @@ impl VMOwnedMemory {
                 offset_guard_size: offset_guard_bytes,
                 memory: *memory,
                 style: *style,
+                zeroing: ZeroingStrategy::default(),
             },
         })
     }
@@ -375,6 +701,24 @@ impl VMOwnedMemory {
             config: self.config.clone(),
         })
     }
+
+    /// Takes a copy-on-write snapshot of this memory, the pages are only
+    /// copied once they are written to. The snapshot can be grown on its own.
+    ///
+    /// The pages are only shared on Linux, every other platform falls back
+    /// to copying just the dirty pages.
+    pub fn try_clone_cow(&self) -> Result<Self, MemoryError> {
+        Ok(Self {
+            mmap: self.mmap.try_clone_cow()?,
+            config: self.config.clone(),
+        })
+    }
+
+    /// Resets the memory down to a zero size and returns the indices of the
+    /// host pages that were zeroed to honor the zeroing strategy
+    pub fn reset_zeroing(&mut self) -> Result<Vec<usize>, MemoryError> {
+        self.mmap.reset(self.config.zeroing)
+    }
 }
 
 impl LinearMemory for VMOwnedMemory {
@@ -408,12 +752,53 @@ impl LinearMemory for VMOwnedMemory {
         self.mmap.grow_at_least(min_size, self.config.clone())
     }
 
+    /// Grows the memory to at least `min_pages` in a single step
+    fn grow_at_least_pages(&mut self, min_pages: Pages) -> Result<Pages, MemoryError> {
+        self.mmap
+            .grow_at_least_pages(min_pages, self.config.clone())
+    }
+
+    /// Changes the access guest code has to a range of the memory
+    fn protect(
+        &mut self,
+        range: Range<u64>,
+        protection: MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        self.mmap.protect(range, protection)
+    }
+
+    /// Returns the pages of the memory that are protected
+    fn protected_pages(&self) -> Option<Arc<ProtectedPages>> {
+        self.mmap.protected_pages()
+    }
+
     /// Resets the memory down to a zero size
     fn reset(&mut self) -> Result<(), MemoryError> {
-        self.mmap.reset()?;
+        self.reset_zeroing()?;
         Ok(())
     }
 
+    /// Returns how the memory is zeroed when it is reset
+    fn zeroing_strategy(&self) -> ZeroingStrategy {
+        self.config.zeroing
+    }
+
+    /// Changes how the memory is zeroed when it is reset
+    fn set_zeroing_strategy(&mut self, strategy: ZeroingStrategy) {
+        self.config.zeroing = strategy;
+    }
+
+    /// Returns the host pages that currently hold non-zero data
+    fn dirty_pages(&self) -> Vec<usize> {
+        self.mmap.dirty_pages()
+    }
+
+    /// Returns how often the memory grew and the largest size it had
+    #[cfg(feature = "memory-stats")]
+    fn stats(&self) -> MemoryStats {
+        self.mmap.counters.stats()
+    }
+
     /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
         self.mmap.vm_memory_definition.as_ptr()
@@ -429,6 +814,12 @@ impl LinearMemory for VMOwnedMemory {
         let forked = Self::copy(self)?;
         Ok(Box::new(forked))
     }
+
+    /// Takes a copy-on-write snapshot of this memory
+    fn try_clone_cow(&self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        let snapshot = Self::try_clone_cow(self)?;
+        Ok(Box::new(snapshot))
+    }
 }
 
 /// A shared linear memory instance.
@@ -519,6 +910,17 @@ impl VMSharedMemory {
             conditions: ThreadConditions::new(),
         })
     }
+
+    /// Takes a copy-on-write snapshot of this memory
+    /// (see [`VMOwnedMemory::try_clone_cow`])
+    pub fn try_clone_cow(&self) -> Result<Self, MemoryError> {
+        let guard = self.mmap.read().unwrap();
+        Ok(Self {
+            mmap: Rc::new(RwLock::new(guard.try_clone_cow()?)),
+            config: self.config.clone(),
+            conditions: ThreadConditions::new(),
+        })
+    }
 }
 
 impl LinearMemory for VMSharedMemory {
@@ -558,13 +960,58 @@ impl LinearMemory for VMSharedMemory {
         guard.grow_at_least(min_size, self.config.clone())
     }
 
+    /// Grows the memory to at least `min_pages` in a single step
+    fn grow_at_least_pages(&mut self, min_pages: Pages) -> Result<Pages, MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.grow_at_least_pages(min_pages, self.config.clone())
+    }
+
+    /// Changes the access guest code has to a range of the memory
+    fn protect(
+        &mut self,
+        range: Range<u64>,
+        protection: MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        let mut guard = self.mmap.write().unwrap();
+        guard.protect(range, protection)
+    }
+
+    /// Returns the pages of the memory that are protected
+    fn protected_pages(&self) -> Option<Arc<ProtectedPages>> {
+        let guard = self.mmap.read().unwrap();
+        guard.protected_pages()
+    }
+
     /// Resets the memory down to a zero size
     fn reset(&mut self) -> Result<(), MemoryError> {
         let mut guard = self.mmap.write().unwrap();
-        guard.reset()?;
+        guard.reset(self.config.zeroing)?;
         Ok(())
     }
 
+    /// Returns how the memory is zeroed when it is reset
+    fn zeroing_strategy(&self) -> ZeroingStrategy {
+        self.config.zeroing
+    }
+
+    /// Changes how the memory is zeroed when it is reset
+    fn set_zeroing_strategy(&mut self, strategy: ZeroingStrategy) {
+        self.config.zeroing = strategy;
+    }
+
+    /// Returns the host pages that currently hold non-zero data
+    fn dirty_pages(&self) -> Vec<usize> {
+        let guard = self.mmap.read().unwrap();
+        guard.dirty_pages()
+    }
+
+    /// Returns how often the memory grew and the largest size it had
+    #[cfg(feature = "memory-stats")]
+    fn stats(&self) -> MemoryStats {
+        let guard = self.mmap.read().unwrap();
+        guard.counters.stats()
+    }
+
     /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition> {
         let guard = self.mmap.read().unwrap();
@@ -582,6 +1029,12 @@ impl LinearMemory for VMSharedMemory {
         Ok(Box::new(forked))
     }
 
+    /// Takes a copy-on-write snapshot of this memory
+    fn try_clone_cow(&self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        let snapshot = Self::try_clone_cow(self)?;
+        Ok(Box::new(snapshot))
+    }
+
     // Add current thread to waiter list
     fn do_wait(
         &mut self,
@@ -648,12 +1101,52 @@ impl LinearMemory for VMMemory {
         self.0.grow_at_least(min_size)
     }
 
+    /// Grows the memory to at least `min_pages` in a single step
+    fn grow_at_least_pages(&mut self, min_pages: Pages) -> Result<Pages, MemoryError> {
+        self.0.grow_at_least_pages(min_pages)
+    }
+
+    /// Changes the access guest code has to a range of the memory
+    fn protect(
+        &mut self,
+        range: Range<u64>,
+        protection: MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        self.0.protect(range, protection)
+    }
+
+    /// Returns the pages of the memory that are protected
+    fn protected_pages(&self) -> Option<Arc<ProtectedPages>> {
+        self.0.protected_pages()
+    }
+
     /// Resets the memory down to a zero size
     fn reset(&mut self) -> Result<(), MemoryError> {
         self.0.reset()?;
         Ok(())
     }
 
+    /// Returns how the memory is zeroed when it is reset
+    fn zeroing_strategy(&self) -> ZeroingStrategy {
+        self.0.zeroing_strategy()
+    }
+
+    /// Changes how the memory is zeroed when it is reset
+    fn set_zeroing_strategy(&mut self, strategy: ZeroingStrategy) {
+        self.0.set_zeroing_strategy(strategy)
+    }
+
+    /// Returns the host pages that currently hold non-zero data
+    fn dirty_pages(&self) -> Vec<usize> {
+        self.0.dirty_pages()
+    }
+
+    /// Returns how often the memory grew and the largest size it had
+    #[cfg(feature = "memory-stats")]
+    fn stats(&self) -> MemoryStats {
+        self.0.stats()
+    }
+
     /// Returns the memory style for this memory.
     fn style(&self) -> MemoryStyle {
         self.0.style()
@@ -679,6 +1172,11 @@ impl LinearMemory for VMMemory {
         self.0.copy()
     }
 
+    /// Takes a copy-on-write snapshot of this memory
+    fn try_clone_cow(&self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        self.0.try_clone_cow()
+    }
+
     // Add current thread to waiter list
     fn do_wait(
         &mut self,
@@ -804,6 +1302,51 @@ where
         })
     }
 
+    /// Grows the memory to a total size of at least `min_pages` wasm pages
+    /// with a single grow (so callers can coalesce many small grows) and
+    /// returns the previous size. When the memory is already big enough this
+    /// does nothing and returns the current size.
+    ///
+    /// Growing past the maximum of the memory fails with
+    /// [`MemoryError::CouldNotGrow`] just like [`LinearMemory::grow`].
+    fn grow_at_least_pages(&mut self, min_pages: Pages) -> Result<Pages, MemoryError> {
+        let current = self.size();
+        if min_pages <= current {
+            return Ok(current);
+        }
+        self.grow(min_pages - current)
+    }
+
+    /// Changes the access that guest code has to the bytes in `range` of the
+    /// memory, for example making them read-only so that writes trap.
+    ///
+    /// The range must start on a host page boundary and lie within the
+    /// current size of the memory, its end is rounded up to the next host
+    /// page. The guard pages behind the memory are never touched. Pages that
+    /// the memory grows into are always read-write and a reset makes the whole
+    /// memory read-write again.
+    ///
+    /// The protection also applies to the host. Host functions must not touch
+    /// protected bytes directly (see [`LinearMemory::protected_pages`]) as the
+    /// resulting trap would unwind through their frames.
+    fn protect(
+        &mut self,
+        _range: Range<u64>,
+        _protection: MemoryProtection,
+    ) -> Result<(), MemoryError> {
+        Err(MemoryError::UnsupportedOperation {
+            message: "protect() is not supported".to_string(),
+        })
+    }
+
+    /// Returns the pages whose protection was changed with
+    /// [`LinearMemory::protect`], or `None` when all of the memory is
+    /// read-write. The result is a snapshot, it does not follow later calls
+    /// to [`LinearMemory::protect`] (from another thread of a shared memory).
+    fn protected_pages(&self) -> Option<Arc<ProtectedPages>> {
+        None
+    }
+
     /// Resets the memory back to zero length
     fn reset(&mut self) -> Result<(), MemoryError> {
         Err(MemoryError::UnsupportedOperation {
@@ -811,6 +1354,28 @@ where
         })
     }
 
+    /// Returns how the memory is zeroed when it is reset
+    fn zeroing_strategy(&self) -> ZeroingStrategy {
+        ZeroingStrategy::OnAllocate
+    }
+
+    /// Changes how the memory is zeroed when it is reset. Memories that
+    /// do not support other strategies ignore this.
+    fn set_zeroing_strategy(&mut self, _strategy: ZeroingStrategy) {}
+
+    /// Returns the indices of the host pages (not wasm pages) that currently
+    /// hold non-zero data, memories that do not track this return nothing
+    fn dirty_pages(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Returns how often the memory grew and the largest size it had,
+    /// memories that do not track this report nothing
+    #[cfg(feature = "memory-stats")]
+    fn stats(&self) -> MemoryStats {
+        MemoryStats::default()
+    }
+
     /// Return a `VMMemoryDefinition` for exposing the memory to compiled wasm code.
     fn vmmemory(&self) -> NonNull<VMMemoryDefinition>;
 
@@ -830,6 +1395,15 @@ where
     /// Copies this memory to a new memory
     fn copy(&mut self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError>;
 
+    /// Takes a copy-on-write snapshot of this memory: the new memory shares
+    /// the unmodified pages with this one until they are first written to
+    /// and can be grown independently of it.
+    fn try_clone_cow(&self) -> Result<Box<dyn LinearMemory + 'static>, MemoryError> {
+        Err(MemoryError::UnsupportedOperation {
+            message: "try_clone_cow() is not supported".to_string(),
+        })
+    }
+
     /// Add current thread to the waiter hash, and wait until notified or timout.
     /// Return 0 if the waiter has been notified, 2 if the timeout occured, or None if en error happened
     fn do_wait(
@@ -852,3 +1426,306 @@ where
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_pattern(memory: &VMOwnedMemory, offset: usize) {
+        unsafe {
+            let md = memory.vmmemory().as_ref();
+            let slice = slice::from_raw_parts_mut(md.base, md.current_length);
+            slice[offset..offset + 16].fill(0xAB);
+        }
+    }
+
+    fn all_zero(memory: &VMOwnedMemory) -> bool {
+        unsafe {
+            let md = memory.vmmemory().as_ref();
+            slice::from_raw_parts(md.base, md.current_length)
+                .iter()
+                .all(|b| *b == 0)
+        }
+    }
+
+    #[test]
+    fn test_reset_on_reuse_zeroes_dirty_pages() {
+        let page_size = region::page::size();
+        let ty = MemoryType::new(4, None, false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+            growth_granularity: Pages(1),
+        };
+        let mut memory = VMOwnedMemory::new(&ty, &style).unwrap();
+        memory.set_zeroing_strategy(ZeroingStrategy::OnReuse);
+
+        // The first instance leaves data behind in a few pages
+        write_pattern(&memory, 0);
+        write_pattern(&memory, 3 * page_size + 100);
+        write_pattern(&memory, WASM_PAGE_SIZE * 3 + 8);
+
+        // Pages that were never touched do not need to be zeroed
+        let zeroed = memory.reset_zeroing().unwrap();
+        assert!(zeroed.contains(&0));
+        assert!(zeroed.contains(&3));
+        assert!(!zeroed.contains(&2));
+
+        // The instance that reuses the memory must not see any of it
+        memory.grow(Pages(4)).unwrap();
+        assert!(all_zero(&memory));
+
+        // And neither does the one after that
+        write_pattern(&memory, WASM_PAGE_SIZE + 1);
+        memory.reset_zeroing().unwrap();
+        memory.grow(Pages(4)).unwrap();
+        assert!(all_zero(&memory));
+    }
+
+    #[test]
+    fn test_reset_lazy_discards_pages() {
+        let page_size = region::page::size();
+        let ty = MemoryType::new(2, None, false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+            growth_granularity: Pages(1),
+        };
+        let mut memory = VMOwnedMemory::new(&ty, &style).unwrap();
+        memory.set_zeroing_strategy(ZeroingStrategy::Lazy);
+
+        write_pattern(&memory, page_size);
+        assert_eq!(memory.dirty_pages(), vec![1]);
+
+        assert!(memory.reset_zeroing().unwrap().is_empty());
+        memory.grow(Pages(2)).unwrap();
+        assert!(all_zero(&memory));
+    }
+
+    #[test]
+    fn test_growth_granularity_coalesces_grows() {
+        let ty = MemoryType::new(1, Some(1024), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+            growth_granularity: Pages(64),
+        };
+        let mut memory = VMOwnedMemory::new(&ty, &style).unwrap();
+        MAP_EXTENSIONS.with(|count| count.set(0));
+
+        for _ in 0..255 {
+            memory.grow(Pages(1)).unwrap();
+        }
+
+        // The guest still sees the size that it asked for
+        assert_eq!(memory.size(), Pages(256));
+        let current_length = unsafe { memory.vmmemory().as_ref().current_length };
+        assert_eq!(current_length, Pages(256).bytes().0);
+        let extensions = MAP_EXTENSIONS.with(|count| count.get());
+        assert!(extensions <= 4, "{extensions} map extensions for 255 grows");
+    }
+
+    #[cfg(feature = "memory-stats")]
+    #[test]
+    fn test_memory_stats() {
+        let ty = MemoryType::new(1, Some(16), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+            growth_granularity: Pages(1),
+        };
+        let mut memory = VMOwnedMemory::new(&ty, &style).unwrap();
+        assert_eq!(
+            memory.stats(),
+            MemoryStats {
+                grow_count: 0,
+                peak_pages: Pages(1),
+            }
+        );
+
+        memory.grow(Pages(2)).unwrap();
+        memory.grow(Pages(0)).unwrap();
+        memory.grow(Pages(3)).unwrap();
+        // Failed grows are not counted
+        memory.grow(Pages(100)).unwrap_err();
+        assert_eq!(
+            memory.stats(),
+            MemoryStats {
+                grow_count: 3,
+                peak_pages: Pages(6),
+            }
+        );
+
+        // The peak survives a reset, and is shared by clones of a shared memory
+        let mut shared = memory.to_shared();
+        shared.reset().unwrap();
+        let mut clone = shared.try_clone().unwrap();
+        clone.grow(Pages(2)).unwrap();
+        assert_eq!(
+            shared.stats(),
+            MemoryStats {
+                grow_count: 4,
+                peak_pages: Pages(6),
+            }
+        );
+    }
+
+    #[test]
+    fn test_grow_at_least_pages() {
+        let ty = MemoryType::new(4, Some(16), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+            growth_granularity: Pages(1),
+        };
+        let mut memory = VMOwnedMemory::new(&ty, &style).unwrap();
+
+        // Already big enough
+        assert_eq!(memory.grow_at_least_pages(Pages(2)).unwrap(), Pages(4));
+        assert_eq!(memory.grow_at_least_pages(Pages(4)).unwrap(), Pages(4));
+        assert_eq!(memory.size(), Pages(4));
+
+        assert_eq!(memory.grow_at_least_pages(Pages(10)).unwrap(), Pages(4));
+        assert_eq!(memory.size(), Pages(10));
+        let current_length = unsafe { memory.vmmemory().as_ref().current_length };
+        assert_eq!(current_length, Pages(10).bytes().0);
+
+        assert_eq!(
+            memory.grow_at_least_pages(Pages(17)),
+            Err(MemoryError::CouldNotGrow {
+                current: Pages(10),
+                attempted_delta: Pages(7),
+            })
+        );
+        assert_eq!(memory.size(), Pages(10));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_insufficient_memory() {
+        // No host has an address space large enough for this guard region
+        let ty = MemoryType::new(1, None, false);
+        let style = MemoryStyle::Static {
+            bound: Pages(1),
+            offset_guard_size: 1 << 62,
+        };
+        match VMOwnedMemory::new(&ty, &style) {
+            Err(MemoryError::InsufficientMemory { requested, .. }) => {
+                assert_eq!(requested, (1 << 62) + WASM_PAGE_SIZE);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    fn read_byte(memory: &VMOwnedMemory, offset: usize) -> u8 {
+        unsafe {
+            let md = memory.vmmemory().as_ref();
+            slice::from_raw_parts(md.base, md.current_length)[offset]
+        }
+    }
+
+    fn check_cow_snapshot(parent: &mut VMOwnedMemory) {
+        let page_size = region::page::size();
+        write_pattern(parent, 0);
+
+        let mut child = parent.try_clone_cow().unwrap();
+        assert_eq!(child.size(), parent.size());
+        assert_eq!(read_byte(&child, 0), 0xAB);
+
+        // Writes to the parent are not seen by the child
+        write_pattern(parent, WASM_PAGE_SIZE + 32);
+        assert_eq!(read_byte(&child, WASM_PAGE_SIZE + 32), 0);
+
+        // Writes to the child are not seen by the parent
+        write_pattern(&child, page_size);
+        assert_eq!(read_byte(&child, page_size), 0xAB);
+        assert_eq!(read_byte(parent, page_size), 0);
+
+        // The child grows on its own and its new pages start out zeroed
+        let end = child.size().bytes().0;
+        child.grow(Pages(2)).unwrap();
+        assert_eq!(child.size(), Pages(4));
+        assert_eq!(parent.size(), Pages(2));
+        assert_eq!(read_byte(&child, end), 0);
+        write_pattern(&child, end);
+        parent.grow(Pages(2)).unwrap();
+        assert_eq!(read_byte(parent, end), 0);
+        assert_eq!(read_byte(parent, page_size), 0);
+    }
+
+    #[test]
+    fn test_try_clone_cow() {
+        let ty = MemoryType::new(2, Some(16), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+            growth_granularity: Pages(1),
+        };
+        let mut parent = VMOwnedMemory::new(&ty, &style).unwrap();
+        check_cow_snapshot(&mut parent);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_try_clone_cow_shared_file() {
+        let path = std::env::temp_dir().join(format!("wasmer-vm-cow-{}", std::process::id()));
+        std::fs::File::create(&path).unwrap();
+
+        let ty = MemoryType::new(2, Some(16), false);
+        let style = MemoryStyle::Static {
+            bound: Pages(16),
+            offset_guard_size: 0,
+        };
+        let mut parent =
+            VMOwnedMemory::new_with_file(&ty, &style, path.clone(), MmapType::Shared).unwrap();
+        check_cow_snapshot(&mut parent);
+
+        drop(parent);
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(path.with_extension("accessible")).ok();
+    }
+
+    #[test]
+    fn test_protect() {
+        let page_size = region::page::size();
+        let ty = MemoryType::new(1, Some(16), false);
+        let style = MemoryStyle::Dynamic {
+            offset_guard_size: 0,
+            growth_granularity: Pages(1),
+        };
+        let mut memory = VMOwnedMemory::new(&ty, &style).unwrap();
+        write_pattern(&memory, 0);
+        let end = WASM_PAGE_SIZE as u64;
+
+        // The range has to be aligned and within the memory
+        let page = page_size as u64;
+        assert!(memory.protect(1..page, MemoryProtection::ReadOnly).is_err());
+        assert!(memory.protect(0..end + 1, MemoryProtection::None).is_err());
+        assert!(memory
+            .protect(end..end + page, MemoryProtection::None)
+            .is_err());
+
+        memory.protect(0..1, MemoryProtection::ReadOnly).unwrap();
+        memory
+            .protect(page..2 * page, MemoryProtection::None)
+            .unwrap();
+        assert_eq!(read_byte(&memory, 0), 0xAB);
+        assert!(memory.dirty_pages().contains(&0));
+
+        // The host checks its accesses against the protected pages
+        let protected = memory.protected_pages().unwrap();
+        assert!(protected.allows(0..page, false));
+        assert!(!protected.allows(0..page, true));
+        assert!(!protected.allows(page + 1..page + 2, false));
+        assert!(protected.allows(2 * page..3 * page, true));
+
+        // Protected pages survive growing (which moves this memory) and
+        // copying, the host can still copy them
+        memory.grow(Pages(1)).unwrap();
+        assert_eq!(read_byte(&memory, 0), 0xAB);
+        let copy = memory.copy().unwrap();
+        assert_eq!(read_byte(&copy, 0), 0xAB);
+        assert_eq!(copy.mmap.protected, memory.mmap.protected);
+
+        // A reset makes everything accessible again
+        memory.reset_zeroing().unwrap();
+        assert!(memory.mmap.protected.is_empty());
+        assert!(memory.protected_pages().is_none());
+        memory.grow(Pages(2)).unwrap();
+        write_pattern(&memory, page_size);
+    }
+}
@@ -8,6 +8,7 @@ use more_asserts::assert_le;
 use std::io;
 use std::ptr;
 use std::slice;
+use wasmer_types::MemoryProtection;
 
 /// Round `size` up to the nearest multiple of `page_size`.
 fn round_up_to_page_size(size: usize, page_size: usize) -> usize {
@@ -26,6 +27,13 @@ pub struct Mmap {
     total_size: usize,
     accessible_size: usize,
     sync_on_drop: bool,
+    file_backed: bool,
+    // The file descriptor of the backing file (or -1), it is kept open for
+    // as long as the mapping lives so that snapshots can map the same file.
+    backing_fd: i32,
+    // The number of bytes at the end of the mapping that are reserved as
+    // guard pages (they are part of `total_size` but never made accessible).
+    guard_size: usize,
 }
 
 /// The type of mmap to create
@@ -50,6 +58,9 @@ impl Mmap {
             total_size: 0,
             accessible_size: 0,
             sync_on_drop: false,
+            file_backed: false,
+            backing_fd: -1,
+            guard_size: 0,
         }
     }
 
@@ -60,6 +71,31 @@ impl Mmap {
         Self::accessible_reserved(rounded_size, rounded_size, None, MmapType::Private)
     }
 
+    /// Create a new `Mmap` pointing to at least `mapping_size` bytes of page-aligned accessible
+    /// memory followed by at least `guard_size` bytes of inaccessible guard pages, which trap
+    /// any access that runs past the end of the memory. Both sizes are rounded up to the
+    /// native page size.
+    pub fn with_at_least_and_guard(mapping_size: usize, guard_size: usize) -> Result<Self, String> {
+        let page_size = region::page::size();
+        let round_up = |size: usize| {
+            size.checked_add(page_size - 1)
+                .map(|size| size & !(page_size - 1))
+        };
+        let (rounded_size, rounded_guard, total_size) = round_up(mapping_size)
+            .zip(round_up(guard_size))
+            .and_then(|(size, guard)| Some((size, guard, size.checked_add(guard)?)))
+            .ok_or_else(|| {
+                format!(
+                    "a mapping of {mapping_size} bytes with {guard_size} guard bytes is too large"
+                )
+            })?;
+
+        let mut mmap =
+            Self::accessible_reserved(rounded_size, total_size, None, MmapType::Private)?;
+        mmap.set_guard_size(rounded_guard);
+        Ok(mmap)
+    }
+
     /// Create a new `Mmap` pointing to `accessible_size` bytes of page-aligned accessible memory,
     /// within a reserved mapping of `mapping_size` bytes. `accessible_size` and `mapping_size`
     /// must be native page-size multiples.
@@ -149,6 +185,9 @@ impl Mmap {
                 total_size: mapping_size,
                 accessible_size,
                 sync_on_drop: memory_fd != -1 && memory_type == MmapType::Shared,
+                file_backed: memory_fd != -1,
+                backing_fd: memory_fd,
+                guard_size: 0,
             }
         } else {
             // Reserve the mapping size.
@@ -171,6 +210,9 @@ impl Mmap {
                 total_size: mapping_size,
                 accessible_size,
                 sync_on_drop: memory_fd != -1 && memory_type == MmapType::Shared,
+                file_backed: memory_fd != -1,
+                backing_fd: memory_fd,
+                guard_size: 0,
             };
 
             if accessible_size != 0 {
@@ -226,6 +268,9 @@ impl Mmap {
                 total_size: mapping_size,
                 accessible_size,
                 sync_on_drop: false,
+                file_backed: false,
+                backing_fd: -1,
+                guard_size: 0,
             }
         } else {
             // Reserve the mapping size.
@@ -240,6 +285,9 @@ impl Mmap {
                 total_size: mapping_size,
                 accessible_size,
                 sync_on_drop: false,
+                file_backed: false,
+                backing_fd: -1,
+                guard_size: 0,
             };
 
             if accessible_size != 0 {
@@ -299,6 +347,149 @@ impl Mmap {
         Ok(())
     }
 
+    /// Changes the protection of the memory starting at `start` and extending
+    /// for `len` bytes. `start` and `len` must be native page-size multiples
+    /// and describe a range within the part of `self` that is in front of the
+    /// guard pages, which are never touched.
+    ///
+    /// This only needs a shared reference as the contents are not accessed,
+    /// it is up to the caller to make sure nobody is using the range in a way
+    /// that the new protection forbids.
+    pub fn protect(
+        &self,
+        start: usize,
+        len: usize,
+        protection: MemoryProtection,
+    ) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len() - self.guard_size);
+        assert_le!(start, self.len() - self.guard_size - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        let protection = match protection {
+            MemoryProtection::None => region::Protection::NONE,
+            MemoryProtection::ReadOnly => region::Protection::READ,
+            MemoryProtection::ReadWrite => region::Protection::READ_WRITE,
+        };
+        let ptr = self.ptr as *const u8;
+        unsafe { region::protect(ptr.add(start), len, protection) }.map_err(|e| e.to_string())
+    }
+
+    /// Hands the memory starting at `start` and extending for `len` bytes back
+    /// to the operating system so that it reads as zeros the next time it is
+    /// touched. The range stays accessible. `start` and `len` must be native
+    /// page-size multiples and describe a range within `self`'s reserved memory.
+    ///
+    /// Memory backed by a file can not be discarded this way (the pages would
+    /// be reloaded from the file) so it is explicitly zeroed instead.
+    #[cfg(not(target_os = "windows"))]
+    pub fn discard(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.total_size);
+        assert_le!(start, self.total_size - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+        if self.file_backed {
+            self.as_mut_slice()[start..start + len].fill(0);
+            return Ok(());
+        }
+
+        let ptr = self.ptr as *mut u8;
+        let r = unsafe {
+            libc::madvise(
+                ptr.add(start) as *mut libc::c_void,
+                len,
+                libc::MADV_DONTNEED,
+            )
+        };
+        if r != 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    /// Hands the memory starting at `start` and extending for `len` bytes back
+    /// to the operating system so that it reads as zeros the next time it is
+    /// touched. The range stays accessible. `start` and `len` must be native
+    /// page-size multiples and describe a range within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn discard(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len());
+        assert_le!(start, self.len() - len);
+
+        // `MEM_RESET` does not guarantee the pages read as zero afterwards
+        // so they are zeroed explicitly.
+        self.as_mut_slice()[start..start + len].fill(0);
+        Ok(())
+    }
+
+    /// Returns the indices of the native pages within the memory starting at
+    /// `start` and extending for `len` bytes that are resident, that is the
+    /// pages that have been touched since they were mapped or discarded and
+    /// are currently in memory. Pages that are not resident were either
+    /// never touched or have been swapped out (or written back to their
+    /// file), so they can not be assumed to read as zeros. `start` and `len`
+    /// must be native page-size multiples and describe a range within
+    /// `self`'s reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn resident_pages(&self, start: usize, len: usize) -> Result<Vec<usize>, String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.total_size);
+        assert_le!(start, self.total_size - len);
+
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut residency = vec![0u8; len / page_size];
+        let ptr = self.ptr as *mut u8;
+        let r = unsafe {
+            libc::mincore(
+                ptr.add(start) as *mut libc::c_void,
+                len,
+                residency.as_mut_ptr() as *mut _,
+            )
+        };
+        if r != 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        Ok(residency
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| *state & 1 != 0)
+            .map(|(index, _)| start / page_size + index)
+            .collect())
+    }
+
+    /// Returns the indices of the native pages within the memory starting at
+    /// `start` and extending for `len` bytes that are resident. Windows does
+    /// not report this for committed memory so every page in the range is
+    /// returned. `start` and `len` must be native page-size multiples and
+    /// describe a range within `self`'s reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn resident_pages(&self, start: usize, len: usize) -> Result<Vec<usize>, String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len());
+        assert_le!(start, self.len() - len);
+
+        Ok((start / page_size..(start + len) / page_size).collect())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.total_size) }
@@ -351,6 +542,18 @@ impl Mmap {
         self.len() == 0
     }
 
+    /// Returns the number of bytes of guard pages at the end of the mapping,
+    /// they are included in [`Mmap::len`] but can never be made accessible.
+    pub fn guard_size(&self) -> usize {
+        self.guard_size
+    }
+
+    /// Records that the last `guard_size` bytes of the mapping are guard pages.
+    pub(crate) fn set_guard_size(&mut self, guard_size: usize) {
+        assert_le!(guard_size, self.total_size);
+        self.guard_size = guard_size;
+    }
+
     /// Duplicate in a new memory mapping.
     #[deprecated = "use `copy` instead"]
     pub fn duplicate(&mut self, size_hint: Option<usize>) -> Result<Self, String> {
@@ -368,10 +571,109 @@ impl Mmap {
 
         let mut new =
             Self::accessible_reserved(copy_size, self.total_size, None, MmapType::Private)?;
+        new.guard_size = self.guard_size;
         new.as_mut_slice_arbitary(copy_size)
             .copy_from_slice(self.as_slice_arbitary(copy_size));
         Ok(new)
     }
+
+    /// Creates a copy-on-write snapshot of this mapping in a new memory
+    /// mapping, the pages are shared until they are first written to.
+    ///
+    /// On Linux the contents are placed in an anonymous in-memory file
+    /// (`memfd`) which the snapshot maps with `MAP_PRIVATE`. Nothing else
+    /// writes to that file, so neither later writes to the original (even
+    /// when it writes through to a backing file) nor writes to the snapshot
+    /// are seen by the other side. Pages that were never touched are left
+    /// as holes in the file.
+    ///
+    /// On other platforms only the pages that hold non-zero data are copied
+    /// into the new mapping, the remaining pages are left for the OS to
+    /// commit once they are first written to.
+    pub fn try_clone_cow(&self, size_hint: Option<usize>) -> Result<Self, String> {
+        // NOTE: accessible_size != used size as the value is not
+        //       automatically updated when the pre-provisioned space is used
+        let mut copy_size = self.accessible_size;
+        if let Some(size_hint) = size_hint {
+            copy_size = usize::max(copy_size, size_hint);
+        }
+
+        let mut new =
+            Self::accessible_reserved(copy_size, self.total_size, None, MmapType::Private)?;
+        new.guard_size = self.guard_size;
+
+        #[cfg(target_os = "linux")]
+        if copy_size != 0 {
+            let snapshot = self.snapshot_file(copy_size)?;
+            // Replace the accessible part of the reservation with a private
+            // view of the snapshot, the rest stays anonymous so that growing
+            // the new mapping starts out with zeroed pages.
+            let ptr = unsafe {
+                libc::mmap(
+                    new.ptr as *mut libc::c_void,
+                    copy_size,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_FIXED,
+                    std::os::unix::io::AsRawFd::as_raw_fd(&snapshot),
+                    0,
+                )
+            };
+            if ptr as isize == -1_isize {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            // Discarding private pages of a file mapping would reload them
+            // from the file so they must be zeroed explicitly instead
+            new.file_backed = true;
+            return Ok(new);
+        }
+
+        let page_size = region::page::size();
+        let src = self.as_slice_arbitary(copy_size);
+        let dst = new.as_mut_slice_arbitary(copy_size);
+        for (src, dst) in src.chunks(page_size).zip(dst.chunks_mut(page_size)) {
+            if src.iter().any(|b| *b != 0) {
+                dst.copy_from_slice(src);
+            }
+        }
+        Ok(new)
+    }
+
+    /// Copies the first `len` bytes of this mapping into a new `memfd`,
+    /// `len` must be a native page-size multiple.
+    #[cfg(target_os = "linux")]
+    fn snapshot_file(&self, len: usize) -> Result<std::fs::File, String> {
+        use std::os::unix::fs::FileExt;
+        use std::os::unix::io::FromRawFd;
+
+        let page_size = region::page::size();
+        let fd = unsafe {
+            libc::memfd_create(
+                b"wasmer-snapshot\0".as_ptr() as *const libc::c_char,
+                libc::MFD_CLOEXEC,
+            )
+        };
+        if fd == -1 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        let file = unsafe { std::fs::File::from_raw_fd(fd) };
+        file.set_len(len as u64).map_err(|e| e.to_string())?;
+
+        // Resident pages are copied as they are, the other ones were either
+        // never touched (and read as zeros, just like the holes in the file)
+        // or swapped out, so they are only copied when they hold data
+        let mut resident = vec![false; len / page_size];
+        for index in self.resident_pages(0, len)? {
+            resident[index] = true;
+        }
+        let data = self.as_slice_arbitary(len);
+        for (index, page) in data.chunks(page_size).enumerate() {
+            if resident[index] || page.iter().any(|b| *b != 0) {
+                file.write_all_at(page, (index * page_size) as u64)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(file)
+    }
 }
 
 impl Drop for Mmap {
@@ -391,6 +693,9 @@ impl Drop for Mmap {
             let r = unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.total_size) };
             assert_eq!(r, 0, "munmap failed: {}", io::Error::last_os_error());
         }
+        if self.backing_fd != -1 {
+            unsafe { libc::close(self.backing_fd) };
+        }
     }
 
     #[cfg(target_os = "windows")]
@@ -420,4 +725,21 @@ mod tests {
         assert_eq!(round_up_to_page_size(4096, 4096), 4096);
         assert_eq!(round_up_to_page_size(4097, 4096), 8192);
     }
+
+    #[test]
+    fn test_with_at_least_and_guard() {
+        let page_size = region::page::size();
+
+        let mmap = Mmap::with_at_least_and_guard(page_size + 1, 1).unwrap();
+        assert_eq!(mmap.guard_size(), page_size);
+        assert_eq!(mmap.len(), 3 * page_size);
+        assert_eq!(mmap.as_slice_accessible().len(), 2 * page_size);
+
+        // The default is still a mapping without any guard pages
+        let mmap = Mmap::with_at_least(page_size).unwrap();
+        assert_eq!(mmap.guard_size(), 0);
+        assert_eq!(mmap.len(), page_size);
+
+        assert!(Mmap::with_at_least_and_guard(usize::MAX - page_size, page_size).is_err());
+    }
 }
@@ -8,13 +8,19 @@ use crate::vmcontext::VMSharedSignatureIndex;
 use more_asserts::{assert_lt, debug_assert_lt};
 use std::collections::{hash_map, HashMap};
 use std::convert::TryFrom;
-use std::sync::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use wasmer_types::FunctionType;
 
 /// WebAssembly requires that the caller and callee signatures in an indirect
 /// call must match. To implement this efficiently, keep a registry of all
 /// signatures, shared by all instances, so that call sites can just do an
 /// index comparison.
+///
+/// Registrations are reference counted. Signatures registered with
+/// [`SignatureRegistry::register`] stay valid for as long as the registry
+/// lives, those of a [`SignatureRegistration`] are released when it is
+/// dropped and their indices are then handed out again.
 #[derive(Debug, Default)]
 pub struct SignatureRegistry {
     // This structure is stored in an `Engine` and is intended to be shared
@@ -28,7 +34,19 @@ pub struct SignatureRegistry {
 #[derive(Debug, Default)]
 struct Inner {
     signature2index: HashMap<FunctionType, VMSharedSignatureIndex>,
-    index2signature: HashMap<VMSharedSignatureIndex, FunctionType>,
+    // Indexed by the value of the `VMSharedSignatureIndex`, free slots are `None`.
+    index2signature: Vec<Option<Entry>>,
+    // Slots of `index2signature` that can be reused.
+    free: Vec<VMSharedSignatureIndex>,
+}
+
+// The number of signatures registered in all the registries of the process.
+static LIVE_SIGNATURES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+struct Entry {
+    signature: FunctionType,
+    references: usize,
 }
 
 impl SignatureRegistry {
@@ -37,29 +55,91 @@ impl SignatureRegistry {
         Default::default()
     }
 
-    /// Register a signature and return its unique index.
+    /// Register a signature and return its unique index, registering the
+    /// same signature again returns the same index and adds a reference to it.
     pub fn register(&self, sig: &FunctionType) -> VMSharedSignatureIndex {
         let mut inner = self.inner.write().unwrap();
-        let len = inner.signature2index.len();
-        let entry = inner.signature2index.entry(sig.clone());
-        match entry {
-            hash_map::Entry::Occupied(entry) => *entry.get(),
+        let Inner {
+            signature2index,
+            index2signature,
+            free,
+        } = &mut *inner;
+        match signature2index.entry(sig.clone()) {
+            hash_map::Entry::Occupied(entry) => {
+                let sig_id = *entry.get();
+                if let Some(entry) = index2signature[sig_id.as_u32() as usize].as_mut() {
+                    entry.references += 1;
+                }
+                sig_id
+            }
             hash_map::Entry::Vacant(entry) => {
-                // Keep `signature_hash` len under 2**32 -- VMSharedSignatureIndex::new(u32::MAX)
-                // is reserved for VMSharedSignatureIndex::default().
-                debug_assert_lt!(
-                    len,
-                    u32::MAX as usize,
-                    "Invariant check: signature_hash.len() < u32::MAX"
-                );
-                let sig_id = VMSharedSignatureIndex::new(u32::try_from(len).unwrap());
+                let sig_id = free.pop().unwrap_or_else(|| {
+                    let len = index2signature.len();
+                    // Keep `signature_hash` len under 2**32 -- VMSharedSignatureIndex::new(u32::MAX)
+                    // is reserved for VMSharedSignatureIndex::default().
+                    debug_assert_lt!(
+                        len,
+                        u32::MAX as usize,
+                        "Invariant check: signature_hash.len() < u32::MAX"
+                    );
+                    index2signature.push(None);
+                    VMSharedSignatureIndex::new(u32::try_from(len).unwrap())
+                });
                 entry.insert(sig_id);
-                inner.index2signature.insert(sig_id, sig.clone());
+                index2signature[sig_id.as_u32() as usize] = Some(Entry {
+                    signature: sig.clone(),
+                    references: 1,
+                });
+                LIVE_SIGNATURES.fetch_add(1, Ordering::Relaxed);
                 sig_id
             }
         }
     }
 
+    /// Removes a reference to a signature that was added by
+    /// [`SignatureRegistry::register`]. Once the last reference is gone the
+    /// index is freed and may be handed out for another signature, so the
+    /// caller must make sure that nothing still uses it.
+    ///
+    /// Returns `false` if the index is not registered.
+    pub(crate) fn unregister(&self, idx: VMSharedSignatureIndex) -> bool {
+        let mut inner = self.inner.write().unwrap();
+        let slot = match inner.index2signature.get_mut(idx.as_u32() as usize) {
+            Some(slot) => slot,
+            None => return false,
+        };
+        let entry = match slot.as_mut() {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        entry.references -= 1;
+        if entry.references == 0 {
+            let entry = slot.take().unwrap();
+            inner.signature2index.remove(&entry.signature);
+            inner.free.push(idx);
+            LIVE_SIGNATURES.fetch_sub(1, Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// Returns the number of distinct signatures in this registry.
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().signature2index.len()
+    }
+
+    /// Returns `true` if no signature is registered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of signatures registered in all the registries of
+    /// the process, the signatures of a registry are released when it is
+    /// dropped (along with its engine).
+    pub fn live_signatures() -> usize {
+        LIVE_SIGNATURES.load(Ordering::Relaxed)
+    }
+
     /// Looks up a shared signature index within this registry.
     ///
     /// Note that for this operation to be semantically correct the `idx` must
@@ -69,7 +149,126 @@ impl SignatureRegistry {
             .read()
             .unwrap()
             .index2signature
-            .get(&idx)
-            .cloned()
+            .get(idx.as_u32() as usize)?
+            .as_ref()
+            .map(|entry| entry.signature.clone())
+    }
+}
+
+/// The signatures of an artifact registered with a [`SignatureRegistry`],
+/// they are unregistered again when this is dropped.
+///
+/// The artifact and every instance created from it hold on to this, so the
+/// indices are only reclaimed once no code and no table can use them anymore.
+#[derive(Debug)]
+pub struct SignatureRegistration {
+    registry: Arc<SignatureRegistry>,
+    indices: Vec<VMSharedSignatureIndex>,
+}
+
+impl SignatureRegistration {
+    /// Registers all the `signatures` with the `registry`.
+    pub fn new<'a>(
+        registry: &Arc<SignatureRegistry>,
+        signatures: impl IntoIterator<Item = &'a FunctionType>,
+    ) -> Self {
+        let indices = signatures
+            .into_iter()
+            .map(|sig| registry.register(sig))
+            .collect();
+        Self {
+            registry: registry.clone(),
+            indices,
+        }
+    }
+
+    /// Returns the indices of the signatures, in the order they were passed
+    /// to [`SignatureRegistration::new`].
+    pub fn indices(&self) -> &[VMSharedSignatureIndex] {
+        &self.indices
+    }
+}
+
+impl Drop for SignatureRegistration {
+    fn drop(&mut self) {
+        for idx in self.indices.drain(..) {
+            self.registry.unregister(idx);
+        }
+    }
+}
+
+impl Drop for SignatureRegistry {
+    fn drop(&mut self) {
+        let len = match self.inner.get_mut() {
+            Ok(inner) => inner.signature2index.len(),
+            Err(poisoned) => poisoned.into_inner().signature2index.len(),
+        };
+        LIVE_SIGNATURES.fetch_sub(len, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer_types::Type;
+
+    #[test]
+    fn indices_are_reclaimed_once_unreferenced() {
+        let registry = SignatureRegistry::new();
+        let a = FunctionType::new(vec![Type::I32], vec![]);
+        let b = FunctionType::new(vec![Type::I64], vec![Type::I64]);
+        let c = FunctionType::new(vec![], vec![Type::F32]);
+
+        let a_id = registry.register(&a);
+        let b_id = registry.register(&b);
+        assert_ne!(a_id, b_id);
+        assert_eq!(registry.register(&a), a_id);
+
+        // `a` is still referenced once, so its index must not be reused
+        assert!(registry.unregister(a_id));
+        assert_eq!(registry.lookup(a_id), Some(a.clone()));
+        let c_id = registry.register(&c);
+        assert_ne!(c_id, a_id);
+        assert_ne!(c_id, b_id);
+
+        // the last reference frees the index for the next signature
+        assert!(registry.unregister(a_id));
+        assert_eq!(registry.lookup(a_id), None);
+        assert!(!registry.unregister(a_id));
+        let d = FunctionType::new(vec![Type::F64], vec![]);
+        assert_eq!(registry.register(&d), a_id);
+        assert_eq!(registry.lookup(a_id), Some(d));
+
+        // registering `a` again gives it a fresh index
+        let a_id2 = registry.register(&a);
+        assert_ne!(a_id2, a_id);
+        assert_eq!(registry.lookup(a_id2), Some(a));
+        assert_eq!(registry.lookup(b_id), Some(b));
+        assert_eq!(registry.lookup(c_id), Some(c));
+        assert_eq!(registry.len(), 4);
+    }
+
+    #[test]
+    fn registrations_release_their_signatures_on_drop() {
+        let registry = Arc::new(SignatureRegistry::new());
+        let a = FunctionType::new(vec![Type::I32], vec![]);
+        let b = FunctionType::new(vec![Type::I64], vec![Type::I64]);
+        let a_id = registry.register(&a);
+
+        let registration = SignatureRegistration::new(&registry, [&a, &b]);
+        let b_id = registration.indices()[1];
+        assert_eq!(registration.indices(), &[a_id, b_id]);
+        let held = Arc::new(registration);
+        let instance = held.clone();
+
+        // the indices live as long as anything holds the registration
+        drop(held);
+        assert_eq!(registry.lookup(b_id), Some(b.clone()));
+        drop(instance);
+        assert_eq!(registry.lookup(b_id), None);
+
+        // signatures that were registered directly are kept
+        assert_eq!(registry.lookup(a_id), Some(a));
+        assert_eq!(registry.len(), 1);
     }
 }
@@ -263,6 +263,9 @@ impl VMTable {
 
     /// Copy `len` elements from `src_table[src_index..]` into `dst_table[dst_index..]`.
     ///
+    /// Two table indices can refer to the same (imported) table, when
+    /// `src_table` is this table the copy is done by [`VMTable::copy_within`].
+    ///
     /// # Errors
     ///
     /// Returns an error if the range is out of bounds of either the source or
@@ -276,6 +279,10 @@ impl VMTable {
     ) -> Result<(), Trap> {
         // https://webassembly.github.io/bulk-memory-operations/core/exec/instructions.html#exec-table-copy
 
+        if std::ptr::eq(self as *const Self, src_table as *const Self) {
+            return self.copy_within(dst_index, src_index, len);
+        }
+
         if src_index
             .checked_add(len)
             .map_or(true, |n| n > src_table.size())
@@ -317,6 +324,9 @@ impl VMTable {
 
     /// Copy `len` elements from `table[src_index..]` to `table[dst_index..]`.
     ///
+    /// The ranges may overlap, the bounds are checked once and the elements
+    /// are then moved in one go.
+    ///
     /// # Errors
     ///
     /// Returns an error if the range is out of bounds of either the source or
@@ -332,23 +342,84 @@ impl VMTable {
             return Err(Trap::lib(TrapCode::TableAccessOutOfBounds));
         }
 
-        let srcs = src_index..src_index + len;
-        let dsts = dst_index..dst_index + len;
+        let src = src_index as usize;
+        let len = len as usize;
+        // Both ranges were checked against the size of the table above and
+        // the elements are plain references so they can simply be moved
+        // (with memmove semantics for overlapping ranges)
+        self.vec.copy_within(src..src + len, dst_index as usize);
 
-        // Note on the unwraps: the bounds check above means that these will
-        // never panic.
-        //
-        // TODO: investigate replacing this get/set loop with a `memcpy`.
-        if dst_index <= src_index {
-            for (s, d) in (srcs).zip(dsts) {
-                self.set(d, self.get(s).unwrap())?;
-            }
-        } else {
-            for (s, d) in srcs.rev().zip(dsts.rev()) {
-                self.set(d, self.get(s).unwrap())?;
-            }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VMCallerCheckedAnyfunc;
+
+    /// Creates a table of `len` funcrefs that hold the values `1..=len`,
+    /// the references are never called so they don't need to be valid
+    fn numbered_table(len: u32) -> VMTable {
+        let ty = TableType::new(ValType::FuncRef, len, None);
+        let mut table = VMTable::new(&ty, &TableStyle::CallerChecksSignature).unwrap();
+        for i in 0..len {
+            let ptr = ((i as usize + 1) * 16) as *mut VMCallerCheckedAnyfunc;
+            let func_ref = VMFuncRef(NonNull::new(ptr).unwrap());
+            table.set(i, TableElement::FuncRef(Some(func_ref))).unwrap();
         }
+        table
+    }
 
-        Ok(())
+    fn numbers(table: &VMTable) -> Vec<usize> {
+        (0..table.size())
+            .map(|i| match table.get(i).unwrap() {
+                TableElement::FuncRef(Some(func_ref)) => func_ref.0.as_ptr() as usize / 16,
+                other => panic!("unexpected element {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn copy_within_to_lower_index() {
+        let mut table = numbered_table(8);
+        table.copy_within(0, 2, 4).unwrap();
+        assert_eq!(numbers(&table), [3, 4, 5, 6, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn copy_within_to_higher_index() {
+        let mut table = numbered_table(8);
+        table.copy_within(2, 0, 4).unwrap();
+        assert_eq!(numbers(&table), [1, 2, 1, 2, 3, 4, 7, 8]);
+    }
+
+    #[test]
+    fn copy_within_zero_length() {
+        let mut table = numbered_table(8);
+        table.copy_within(8, 0, 0).unwrap();
+        table.copy_within(3, 3, 0).unwrap();
+        assert_eq!(numbers(&table), [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // An index past the end traps even when nothing is copied
+        assert!(table.copy_within(9, 0, 0).is_err());
+    }
+
+    #[test]
+    fn copy_within_out_of_bounds() {
+        let mut table = numbered_table(8);
+        for (dst, src, len) in [(6, 0, 4), (0, 5, 4), (0, u32::MAX, 2)] {
+            let trap = table.copy_within(dst, src, len).unwrap_err();
+            assert!(matches!(
+                trap,
+                Trap::Lib {
+                    trap_code: TrapCode::TableAccessOutOfBounds,
+                    ..
+                }
+            ));
+        }
+
+        // The table is left untouched
+        assert_eq!(numbers(&table), [1, 2, 3, 4, 5, 6, 7, 8]);
     }
 }
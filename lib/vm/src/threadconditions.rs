@@ -3,7 +3,7 @@ use fnv::FnvBuildHasher;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::thread::{current, park, park_timeout, Thread};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// Error that can occur during wait/notify calls.
@@ -36,6 +36,9 @@ pub struct NotifyLocation {
 struct NotifyWaiter {
     thread: Thread,
     notified: bool,
+    // Set when the waiter has to resume (notified or not), any other return
+    // from `park` is spurious and the waiter parks again.
+    woken: bool,
 }
 
 #[derive(Debug, Default)]
@@ -93,11 +96,23 @@ impl ThreadConditions {
         self.inner.map.entry(dst).or_default().push(NotifyWaiter {
             thread: current(),
             notified: false,
+            woken: false,
         });
-        if let Some(timeout) = timeout {
-            park_timeout(timeout);
-        } else {
-            park();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    park_timeout(deadline - now);
+                }
+                None => park(),
+            }
+            if self.is_woken(dst) || self.inner.closed.load(std::sync::atomic::Ordering::Acquire) {
+                break;
+            }
         }
         let mut bindding = self.inner.map.get_mut(&dst).unwrap();
         let v = bindding.value_mut();
@@ -119,13 +134,39 @@ impl ThreadConditions {
         Ok(ret)
     }
 
+    fn is_woken(&self, dst: NotifyLocation) -> bool {
+        let id = current().id();
+        self.inner.map.get(&dst).map_or(false, |waiters| {
+            waiters
+                .iter()
+                .any(|waiter| waiter.thread.id() == id && waiter.woken)
+        })
+    }
+
     /// Notify waiters from the wait list
     pub fn do_notify(&mut self, dst: NotifyLocation, count: u32) -> u32 {
+        self.notify_from_host(dst, count)
+    }
+
+    /// Wakes up to `count` of the waiters parked on `dst` and returns how many
+    /// were woken, just like `memory.atomic.notify` does.
+    ///
+    /// Unlike [`ThreadConditions::do_notify`] this only needs a shared
+    /// reference, so it can be called from any host thread (for instance
+    /// from the completion callback of an asynchronous operation) through
+    /// [`LinearMemory::thread_conditions`](crate::LinearMemory::thread_conditions).
+    ///
+    /// Memory ordering: the waiters are notified under the same lock that
+    /// they take when they resume, so every write made by the notifying
+    /// thread before this call (such as the value the guest is waiting for)
+    /// is visible to the woken waiters.
+    pub fn notify_from_host(&self, dst: NotifyLocation, count: u32) -> u32 {
         let mut count_token = 0u32;
         if let Some(mut v) = self.inner.map.get_mut(&dst) {
             for waiter in v.value_mut() {
                 if count_token < count && !waiter.notified {
                     waiter.notified = true; // waiter was notified, not just an elapsed timeout
+                    waiter.woken = true;
                     waiter.thread.unpark(); // wakeup!
                     count_token += 1;
                 }
@@ -140,6 +181,7 @@ impl ThreadConditions {
     pub fn wake_all_atomic_waiters(&self) {
         for mut item in self.inner.map.iter_mut() {
             for waiter in item.value_mut() {
+                waiter.woken = true;
                 waiter.thread.unpark();
             }
         }
@@ -277,4 +319,39 @@ mod tests {
         let ret = conditions.do_notify(dst, 5);
         assert_eq!(ret, 2);
     }
+
+    #[test]
+    fn threadconditions_notify_from_host_thread() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::thread;
+
+        let mut conditions = ThreadConditions::new();
+        let host = conditions.clone();
+        let value = Arc::new(AtomicU32::new(0));
+        let dst = NotifyLocation { address: 16 };
+
+        let waiter = thread::spawn({
+            let value = value.clone();
+            move || {
+                let ret = conditions.do_wait(dst, None).unwrap();
+                (ret, value.load(Ordering::Relaxed))
+            }
+        });
+
+        // Keep notifying until the waiter is parked
+        let notifier = thread::spawn(move || {
+            value.store(42, Ordering::Relaxed);
+            loop {
+                let woken = host.notify_from_host(dst, 1);
+                if woken > 0 {
+                    return woken;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        assert_eq!(notifier.join().unwrap(), 1);
+        // The write that preceded the notification is seen by the waiter
+        assert_eq!(waiter.join().unwrap(), (0, 42));
+    }
 }
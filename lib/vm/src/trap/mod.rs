@@ -8,7 +8,7 @@
 mod trap;
 mod traphandlers;
 
-pub use trap::Trap;
+pub use trap::{Trap, WasmFrame};
 pub use traphandlers::{
     catch_traps, on_host_stack, raise_lib_trap, raise_user_trap, set_stack_size,
     wasmer_call_trampoline, TrapHandlerFn, VMConfig,
@@ -1,7 +1,25 @@
 use backtrace::Backtrace;
 use std::error::Error;
 use std::fmt;
-use wasmer_types::TrapCode;
+use wasmer_types::{FrameInfo, TrapCode};
+
+/// A wasm function that a trap went through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WasmFrame {
+    /// The index of the function in its module.
+    pub func_index: u32,
+    /// The offset of the instruction within the body of the function.
+    pub code_offset: u32,
+}
+
+impl From<&FrameInfo> for WasmFrame {
+    fn from(frame: &FrameInfo) -> Self {
+        Self {
+            func_index: frame.func_index(),
+            code_offset: frame.func_offset() as u32,
+        }
+    }
+}
 
 /// Stores trace message with backtrace.
 #[derive(Debug)]
@@ -19,6 +37,9 @@ pub enum Trap {
         backtrace: Backtrace,
         /// Optional trapcode associated to the signal that caused the trap
         signal_trap: Option<TrapCode>,
+        /// The wasm functions the trap went through, innermost first (empty
+        /// until they are resolved with [`Trap::set_wasm_frames`])
+        wasm_frames: Vec<WasmFrame>,
     },
 
     /// A trap raised from a wasm libcall
@@ -29,6 +50,9 @@ pub enum Trap {
         trap_code: TrapCode,
         /// Native stack backtrace at the time the trap occurred
         backtrace: Backtrace,
+        /// The wasm functions the trap went through, innermost first (empty
+        /// until they are resolved with [`Trap::set_wasm_frames`])
+        wasm_frames: Vec<WasmFrame>,
     },
 
     /// A trap indicating that the runtime was unable to allocate sufficient memory.
@@ -60,6 +84,7 @@ impl Trap {
             pc,
             backtrace,
             signal_trap,
+            wasm_frames: Vec::new(),
         }
     }
 
@@ -76,6 +101,7 @@ impl Trap {
         Self::Lib {
             trap_code,
             backtrace,
+            wasm_frames: Vec::new(),
         }
     }
 
@@ -87,6 +113,27 @@ impl Trap {
         Self::OOM { backtrace }
     }
 
+    /// Returns the wasm functions that the trap went through, starting with
+    /// the one where it happened. Only traps raised from wasm code have
+    /// frames and only once they have been resolved from the frame info of
+    /// their modules.
+    pub fn wasm_frames(&self) -> &[WasmFrame] {
+        match self {
+            Self::Wasm { wasm_frames, .. } | Self::Lib { wasm_frames, .. } => wasm_frames,
+            Self::User(_) | Self::OOM { .. } => &[],
+        }
+    }
+
+    /// Sets the wasm functions that the trap went through (see
+    /// [`Trap::wasm_frames`]), this does nothing for traps that were not
+    /// raised from wasm code.
+    pub fn set_wasm_frames(&mut self, frames: Vec<WasmFrame>) {
+        match self {
+            Self::Wasm { wasm_frames, .. } | Self::Lib { wasm_frames, .. } => *wasm_frames = frames,
+            Self::User(_) | Self::OOM { .. } => {}
+        }
+    }
+
     /// Attempts to downcast the `Trap` to a concrete type.
     pub fn downcast<T: Error + 'static>(self) -> Result<T, Self> {
         match self {
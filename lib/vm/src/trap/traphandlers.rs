@@ -10,7 +10,7 @@ use crate::vmcontext::{VMFunctionContext, VMTrampoline};
 use crate::{Trap, VMContext, VMFunctionBody};
 use backtrace::Backtrace;
 use core::ptr::{read, read_unaligned};
-use corosensei::stack::DefaultStack;
+use corosensei::stack::{DefaultStack, Stack};
 use corosensei::trap::{CoroutineTrapHandler, TrapHandlerRegs};
 use corosensei::{Coroutine, CoroutineResult, Yielder};
 use scopeguard::defer;
@@ -21,6 +21,7 @@ use std::io;
 use std::mem;
 #[cfg(unix)]
 use std::mem::MaybeUninit;
+use std::ops::Range;
 use std::ptr::{self, NonNull};
 use std::sync::atomic::{compiler_fence, AtomicPtr, AtomicUsize, Ordering};
 use std::sync::Once;
@@ -268,6 +269,14 @@ cfg_if::cfg_if! {
                 return;
             }
 
+            // The host stack can not be recovered from, but at least make it
+            // clear that it was not the Wasm stack that overflowed.
+            if let Some(fault_address) = maybe_fault_address {
+                if is_host_stack_overflow(sp, fault_address) {
+                    report_host_stack_overflow();
+                }
+            }
+
             // This signal is not for any compiled wasm code we expect, so we
             // need to forward the signal to the next handler. If there is no
             // next handler (SIG_IGN or SIG_DFL), then it's time to crash. To do
@@ -293,6 +302,31 @@ cfg_if::cfg_if! {
             }
         }
 
+        /// Returns true when a fault happened right next to the stack pointer
+        /// while a host function called from Wasm was running on the host
+        /// stack.
+        fn is_host_stack_overflow(sp: usize, fault_address: usize) -> bool {
+            // Stack probes touch the stack one page at a time, so a fault
+            // caused by running out of stack is always close to the stack
+            // pointer.
+            const STACK_PROBE_WINDOW: usize = 64 * 1024;
+
+            HOST_CALL_DEPTH.with(|depth| depth.get()) > 0
+                && sp.abs_diff(fault_address) <= STACK_PROBE_WINDOW
+        }
+
+        /// Writes a diagnostic for a host stack overflow, this runs inside
+        /// the signal handler so it only uses `write`.
+        unsafe fn report_host_stack_overflow() {
+            const MESSAGE: &[u8] = b"wasmer: a host function called from WebAssembly \
+                overflowed the host stack (this is not a WebAssembly stack overflow)\n";
+            libc::write(
+                libc::STDERR_FILENO,
+                MESSAGE.as_ptr() as *const libc::c_void,
+                MESSAGE.len(),
+            );
+        }
+
         unsafe fn get_pc_sp(context: &ucontext_t) -> (usize, usize) {
             let (pc, sp);
             cfg_if::cfg_if! {
@@ -730,6 +764,7 @@ where
 thread_local! {
     static YIELDER: Cell<Option<NonNull<Yielder<(), UnwindReason>>>> = const { Cell::new(None) };
     static TRAP_HANDLER: AtomicPtr<TrapHandlerContext> = const { AtomicPtr::new(ptr::null_mut()) };
+    static HOST_CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
 }
 
 /// Read-only information that is used by signal handlers to handle and recover
@@ -751,6 +786,9 @@ struct TrapHandlerContextInner<T> {
     /// Information about the currently running coroutine. This is used to
     /// reset execution to the root of the coroutine when a trap is handled.
     coro_trap_handler: CoroutineTrapHandler<Result<T, UnwindReason>>,
+    /// The guard page that sits right below the Wasm stack, a fault in here
+    /// means that the Wasm stack was exhausted.
+    stack_guard: Range<usize>,
 }
 
 impl TrapHandlerContext {
@@ -759,6 +797,7 @@ impl TrapHandlerContext {
     fn install<T, R>(
         custom_trap: Option<*const TrapHandlerFn<'static>>,
         coro_trap_handler: CoroutineTrapHandler<Result<T, UnwindReason>>,
+        stack_guard: Range<usize>,
         f: impl FnOnce() -> R,
     ) -> R {
         // Type-erase the trap handler function so that it can be placed in TLS.
@@ -780,7 +819,10 @@ impl TrapHandlerContext {
                 )
             }
         }
-        let inner = TrapHandlerContextInner { coro_trap_handler };
+        let inner = TrapHandlerContextInner {
+            coro_trap_handler,
+            stack_guard,
+        };
         let ctx = Self {
             inner: &inner as *const _ as *const u8,
             handle_trap: func::<T>,
@@ -853,7 +895,7 @@ impl<T> TrapHandlerContextInner<T> {
 
         let signal_trap = trap_code.or_else(|| {
             maybe_fault_address.map(|addr| {
-                if self.coro_trap_handler.stack_ptr_in_bounds(addr) {
+                if self.is_wasm_stack_overflow(addr) {
                     TrapCode::StackOverflow
                 } else {
                     TrapCode::HeapAccessOutOfBounds
@@ -886,6 +928,14 @@ impl<T> TrapHandlerContextInner<T> {
         update_regs(regs);
         true
     }
+
+    /// Returns true when a fault at this address was caused by running out
+    /// of Wasm stack, either because the address is on the Wasm stack or
+    /// because it is in the guard page right below it.
+    fn is_wasm_stack_overflow(&self, fault_address: usize) -> bool {
+        self.coro_trap_handler.stack_ptr_in_bounds(fault_address)
+            || self.stack_guard.contains(&fault_address)
+    }
 }
 
 enum UnwindReason {
@@ -948,6 +998,7 @@ fn on_wasm_stack<F: FnOnce() -> T + 'static, T: 'static>(
         .pop()
         .unwrap_or_else(|| DefaultStack::new(stack_size).unwrap());
     let mut stack = scopeguard::guard(stack, |stack| STACK_POOL.push(stack));
+    let stack_guard = stack_guard_range(&stack);
 
     // Create a coroutine with a new stack to run the function on.
     let mut coro = Coroutine::with_stack(&mut *stack, move |yielder, ()| {
@@ -964,7 +1015,7 @@ fn on_wasm_stack<F: FnOnce() -> T + 'static, T: 'static>(
 
     // Set up metadata for the trap handler for the duration of the coroutine
     // execution. This is restored to its previous value afterwards.
-    TrapHandlerContext::install(trap_handler, coro.trap_handler(), || {
+    TrapHandlerContext::install(trap_handler, coro.trap_handler(), stack_guard, || {
         match coro.resume(()) {
             CoroutineResult::Yield(trap) => {
                 // This came from unwind_with which requires that there be only
@@ -979,6 +1030,13 @@ fn on_wasm_stack<F: FnOnce() -> T + 'static, T: 'static>(
     })
 }
 
+/// Returns the address range of the guard page that protects the end of a
+/// Wasm stack.
+fn stack_guard_range(stack: &DefaultStack) -> Range<usize> {
+    let limit = stack.limit().get();
+    limit.saturating_sub(region::page::size())..limit
+}
+
 /// When executing on the Wasm stack, temporarily switch back to the host stack
 /// to perform an operation that should not be constrainted by the Wasm stack
 /// limits.
@@ -1004,6 +1062,13 @@ pub fn on_host_stack<F: FnOnce() -> T, T>(f: F) -> T {
         YIELDER.with(|cell| cell.set(yielder_ptr));
     }
 
+    // Remember that a host function is running so that an overflow of the
+    // host stack can be told apart from an overflow of the Wasm stack.
+    HOST_CALL_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    defer! {
+        HOST_CALL_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+
     // on_parent_stack requires the closure to be Send so that the Yielder
     // cannot be called from the parent stack. This is not a problem for us
     // since we don't expose the Yielder.
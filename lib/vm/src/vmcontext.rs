@@ -571,6 +571,11 @@ impl VMSharedSignatureIndex {
     pub fn new(value: u32) -> Self {
         Self(value)
     }
+
+    /// Returns the raw value of the index.
+    pub(crate) fn as_u32(self) -> u32 {
+        self.0
+    }
 }
 
 impl Default for VMSharedSignatureIndex {
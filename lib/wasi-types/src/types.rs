@@ -13,6 +13,7 @@ pub use crate::types::time::*;
 pub use directory::*;
 pub use file::*;
 pub use io::*;
+pub use memory::*;
 pub use net::*;
 pub use signal::*;
 pub use subscription::*;
@@ -28,6 +29,10 @@ pub mod file {
 
     pub const EVENT_FD_FLAGS_SEMAPHORE: EventFdFlags = 1;
 
+    /// Marks the descriptors of `fd_close_range` as close-on-exec instead of
+    /// closing them (same value as `CLOSE_RANGE_CLOEXEC` on Linux)
+    pub const CLOSE_RANGE_FLAGS_CLOEXEC: u32 = 4;
+
     pub const __WASI_LOOKUP_SYMLINK_FOLLOW: LookupFlags = 1;
 
     /// function for debugging rights issues
@@ -142,6 +147,13 @@ pub mod io {
     }
 }
 
+pub mod memory {
+    /// Flags of `memory_protect` (same values as `PROT_READ` and `PROT_WRITE`
+    /// on Linux), no flags at all makes the memory inaccessible
+    pub const MEMORY_PROT_READ: u32 = 1;
+    pub const MEMORY_PROT_WRITE: u32 = 2;
+}
+
 pub mod time {
     pub use crate::wasi::OptionTimestamp;
 }
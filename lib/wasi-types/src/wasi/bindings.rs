@@ -775,6 +775,8 @@ wai_bindgen_rust::bitflags::bitflags! {
         const EXCL = 1 << 2;
         #[doc = " Truncate file to size 0."]
         const TRUNC = 1 << 3;
+        #[doc = " Do not update the access time of the file when it is read."]
+        const NOATIME = 1 << 4;
     }
 }
 impl Oflags {
@@ -1028,6 +1030,7 @@ pub enum Sockoption {
     MulticastTtlV4,
     Type,
     Proto,
+    UserTimeout,
 }
 impl core::fmt::Debug for Sockoption {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -1059,6 +1062,7 @@ impl core::fmt::Debug for Sockoption {
             Sockoption::MulticastTtlV4 => f.debug_tuple("Sockoption::MulticastTtlV4").finish(),
             Sockoption::Type => f.debug_tuple("Sockoption::Type").finish(),
             Sockoption::Proto => f.debug_tuple("Sockoption::Proto").finish(),
+            Sockoption::UserTimeout => f.debug_tuple("Sockoption::UserTimeout").finish(),
         }
     }
 }
@@ -2972,6 +2976,7 @@ unsafe impl wasmer::FromToNativeWasmType for Sockoption {
             24 => Self::MulticastTtlV4,
             25 => Self::Type,
             26 => Self::Proto,
+            27 => Self::UserTimeout,
 
             q => {
                 tracing::debug!("could not serialize number {q} to enum Sockoption");
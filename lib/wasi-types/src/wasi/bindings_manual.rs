@@ -154,6 +154,7 @@ impl std::fmt::Display for Sockoption {
             Self::MulticastTtlV4 => "Sockoption::MulticastTtlV4",
             Self::Type => "Sockoption::Type",
             Self::Proto => "Sockoption::Proto",
+            Self::UserTimeout => "Sockoption::UserTimeout",
         };
         write!(f, "{}", s)
     }
@@ -194,6 +195,23 @@ unsafe impl wasmer::FromToNativeWasmType for Rights {
     }
 }
 
+// TODO: if necessary, must be implemented in wit-bindgen
+unsafe impl wasmer::FromToNativeWasmType for Subclockflags {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self.bits() as i32
+    }
+    fn from_native(n: Self::Native) -> Self {
+        Self::from_bits_truncate(n as u16)
+    }
+
+    fn is_from_store(&self, _store: &impl wasmer::AsStoreRef) -> bool {
+        // TODO: find correct implementation
+        false
+    }
+}
+
 // TODO: if necessary, must be implemented in wit-bindgen
 unsafe impl wasmer::FromToNativeWasmType for Fstflags {
     type Native = i32;
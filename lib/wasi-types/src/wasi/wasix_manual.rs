@@ -505,3 +505,25 @@ where
     #[inline]
     fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
 }
+
+/// Statistics about the file system that holds a path
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct Statvfs {
+    /// Size of a block in bytes
+    pub f_bsize: u64,
+    /// Total number of blocks in the file system
+    pub f_blocks: u64,
+    /// Number of free blocks
+    pub f_bfree: u64,
+    /// Number of free blocks available to unprivileged users
+    pub f_bavail: u64,
+    /// Total number of inodes
+    pub f_files: u64,
+    /// Number of free inodes
+    pub f_ffree: u64,
+}
+unsafe impl ValueType for Statvfs {
+    #[inline]
+    fn zero_padding_bytes(&self, _bytes: &mut [MaybeUninit<u8>]) {}
+}
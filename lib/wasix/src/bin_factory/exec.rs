@@ -173,6 +173,7 @@ pub fn run_exec(props: TaskWasmRunProperties) {
             .get_function("_initialize")
         {
             let initialize = initialize.clone();
+            ctx.data(&store).start_cpu_time();
             if let Err(err) = initialize.call(&mut store, &[]) {
                 thread.thread.set_status_finished(Err(err.into()));
                 ctx.data(&store)
@@ -267,6 +268,7 @@ fn call_module(
     let ret = {
         // Call the module
         let call_ret = if let Some(start) = get_start(&ctx, &store) {
+            ctx.data(&store).start_cpu_time();
             start.call(&mut store, &[])
         } else {
             debug!("wasi[{}]::exec-failed: missing _start function", pid);
@@ -313,6 +315,10 @@ fn call_module(
                     runtime.on_taint(TaintReason::UnknownWasiVersion);
                     Ok(Errno::Noexec)
                 }
+                Ok(WasiError::CpuBudgetExceeded) => {
+                    debug!("failed as the CPU time budget was exhausted");
+                    Err(WasiError::CpuBudgetExceeded.into())
+                }
                 Err(err) => {
                     runtime.on_taint(TaintReason::RuntimeError(err.clone()));
                     Err(WasiRuntimeError::from(err))
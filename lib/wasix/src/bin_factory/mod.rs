@@ -72,6 +72,18 @@ impl BinFactory {
         name: String,
         store: wasmer::Store,
         env: WasiEnv,
+    ) -> Pin<Box<dyn Future<Output = Result<TaskJoinHandle, SpawnError>> + 'a>> {
+        self.spawn_as(name, None, store, env)
+    }
+
+    /// Spawns the binary found at `name` but reports `argv0` (when set) as
+    /// the first argument of the new process instead of the binary name.
+    pub fn spawn_as<'a>(
+        &'a self,
+        name: String,
+        argv0: Option<String>,
+        store: wasmer::Store,
+        env: WasiEnv,
     ) -> Pin<Box<dyn Future<Output = Result<TaskJoinHandle, SpawnError>> + 'a>> {
         Box::pin(async move {
             // Find the binary (or die trying) and make the spawn type
@@ -89,6 +101,10 @@ impl BinFactory {
             // Execute
             match executable {
                 Executable::Wasm(bytes) => {
+                    if let Some(argv0) = argv0 {
+                        env.set_argv0(argv0);
+                    }
+
                     spawn_exec_wasm(&bytes, name.as_str(), env, &self.runtime).await
                 }
                 Executable::BinaryPackage(pkg) => {
@@ -110,6 +126,9 @@ impl BinFactory {
                     };
 
                     env.prepare_spawn(cmd);
+                    if let Some(argv0) = argv0 {
+                        env.set_argv0(argv0);
+                    }
 
                     spawn_exec(pkg, name.as_str(), store, env, &self.runtime).await
                 }
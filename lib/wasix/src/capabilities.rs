@@ -1,6 +1,14 @@
-use std::time::Duration;
+use std::{collections::BTreeSet, path::PathBuf, time::Duration};
 
-use crate::http::HttpClientCapabilityV1;
+use wasmer::Module;
+
+use crate::{
+    http::HttpClientCapabilityV1,
+    utils::{
+        SNAPSHOT0_NAMESPACE, SNAPSHOT1_NAMESPACE, WASIX_32V1_NAMESPACE, WASIX_64V1_NAMESPACE,
+        WASIX_HTTP_V1_NAMESPACE,
+    },
+};
 
 /// Defines capabilities for a Wasi environment.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -8,6 +16,20 @@ pub struct Capabilities {
     pub insecure_allow_all: bool,
     pub http_client: HttpClientCapabilityV1,
     pub threading: CapabilityThreadingV1,
+
+    /// Allows the `proc_exec_host` syscall to spawn real host processes
+    /// (default = false)
+    pub allow_host_exec: bool,
+
+    /// Host binaries that `proc_exec_host` is allowed to run, anything
+    /// that is not on this list is rejected even when host processes
+    /// are allowed
+    pub host_exec_allowlist: Vec<PathBuf>,
+
+    /// Syscalls that are not available to the guest, calling one of them
+    /// returns `Errno::Nosys` (the names are the import names, such as
+    /// `proc_fork`)
+    pub denied_syscalls: BTreeSet<String>,
 }
 
 impl Capabilities {
@@ -16,6 +38,9 @@ impl Capabilities {
             insecure_allow_all: false,
             http_client: Default::default(),
             threading: Default::default(),
+            allow_host_exec: false,
+            host_exec_allowlist: Vec::new(),
+            denied_syscalls: BTreeSet::new(),
         }
     }
 
@@ -26,10 +51,16 @@ impl Capabilities {
             insecure_allow_all,
             http_client,
             threading,
+            allow_host_exec,
+            host_exec_allowlist,
+            denied_syscalls,
         } = other;
         self.insecure_allow_all |= insecure_allow_all;
         self.http_client.update(http_client);
         self.threading.update(threading);
+        self.allow_host_exec |= allow_host_exec;
+        self.host_exec_allowlist.extend(host_exec_allowlist);
+        self.denied_syscalls.extend(denied_syscalls);
     }
 }
 
@@ -60,6 +91,11 @@ pub struct CapabilityThreadingV1 {
     /// Switches to a blocking sleep implementation instead
     /// of the asynchronous runtime based implementation
     pub enable_blocking_sleep: bool,
+
+    /// Allows processes to raise their priority above the initial
+    /// value with `proc_setpriority`
+    /// (default = false)
+    pub enable_raise_priority: bool,
 }
 
 impl CapabilityThreadingV1 {
@@ -69,6 +105,7 @@ impl CapabilityThreadingV1 {
             enable_asynchronous_threading,
             enable_exponential_cpu_backoff,
             enable_blocking_sleep,
+            enable_raise_priority,
         } = other;
         self.enable_asynchronous_threading |= enable_asynchronous_threading;
         if let Some(val) = enable_exponential_cpu_backoff {
@@ -76,5 +113,175 @@ impl CapabilityThreadingV1 {
         }
         self.max_threads = max_threads.or(self.max_threads);
         self.enable_blocking_sleep |= enable_blocking_sleep;
+        self.enable_raise_priority |= enable_raise_priority;
+    }
+}
+
+/// Groups of syscalls that a module imports, as reported by [`analyze_module`].
+///
+/// This lets a policy engine approve or deny an untrusted module before it is
+/// instantiated. The analysis only looks at the names of the imported
+/// functions, a module that imports a syscall is assumed to use it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequiredCapabilities {
+    /// Opens sockets, resolves names, configures the network ports or
+    /// makes HTTP requests
+    pub network: bool,
+    /// Creates, modifies or removes files and directories (`path_open` is
+    /// included as it can create and truncate files)
+    pub filesystem_write: bool,
+    /// Spawns, forks or executes other processes
+    pub process_spawn: bool,
+    /// Spawns threads
+    pub threads: bool,
+    /// Changes the clocks
+    pub clock_set: bool,
+}
+
+impl RequiredCapabilities {
+    /// Returns true if the module does not need any of the capability groups
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn add_import(&mut self, namespace: &str, name: &str) {
+        match namespace {
+            SNAPSHOT0_NAMESPACE | SNAPSHOT1_NAMESPACE | WASIX_32V1_NAMESPACE
+            | WASIX_64V1_NAMESPACE => {}
+            WASIX_HTTP_V1_NAMESPACE => {
+                self.network = true;
+                return;
+            }
+            // `wasi-threads` imports its spawn function from its own namespace
+            "wasi" if name == "thread-spawn" => {
+                self.threads = true;
+                return;
+            }
+            _ => return,
+        }
+
+        match name {
+            "resolve" => self.network = true,
+            name if name.starts_with("sock_") || name.starts_with("port_") => {
+                self.network = true
+            }
+            "path_open"
+            | "path_create_directory"
+            | "path_remove_directory"
+            | "path_rename"
+            | "path_symlink"
+            | "path_link"
+            | "path_unlink_file"
+            | "path_filestat_set_times"
+            | "path_write_atomic"
+            | "path_copy_tree"
+            | "fd_allocate"
+            | "fd_filestat_set_size"
+            | "fd_filestat_set_times"
+            | "fd_pwrite" => self.filesystem_write = true,
+            "proc_spawn" | "proc_fork" | "proc_exec" | "proc_exec2" | "proc_exec3"
+            | "proc_exec_host" => self.process_spawn = true,
+            "thread_spawn" | "thread_spawn_v2" => self.threads = true,
+            "clock_time_set" => self.clock_set = true,
+            _ => {}
+        }
+    }
+}
+
+/// Inspects the imports of a module and reports which groups of WASI and
+/// WASIX syscalls it requires, without instantiating it.
+pub fn analyze_module(module: &Module) -> RequiredCapabilities {
+    let mut required = RequiredCapabilities::default();
+    for import in module.imports().functions() {
+        required.add_import(import.module(), import.name());
+    }
+    required
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmer::Engine;
+
+    use super::*;
+
+    fn analyze(imports: &str) -> RequiredCapabilities {
+        let wat = format!("(module {imports})");
+        let module = Module::new(&Engine::default(), wat).unwrap();
+        analyze_module(&module)
+    }
+
+    #[test]
+    fn analyze_module_without_capabilities() {
+        let required = analyze(
+            r#"
+            (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "clock_time_get" (func (param i32 i64 i32) (result i32)))
+            (import "env" "sock_open" (func))
+            "#,
+        );
+        assert!(required.is_empty());
+    }
+
+    #[test]
+    fn analyze_module_capability_groups() {
+        let required = analyze(
+            r#"
+            (import "wasix_32v1" "sock_open" (func (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "thread_spawn_v2" (func (param i32 i32) (result i32)))
+            "#,
+        );
+        assert_eq!(
+            required,
+            RequiredCapabilities {
+                network: true,
+                threads: true,
+                ..Default::default()
+            }
+        );
+
+        let required = analyze(
+            r#"
+            (import "wasi_snapshot_preview1" "path_unlink_file" (func (param i32 i32 i32) (result i32)))
+            (import "wasix_64v1" "proc_spawn" (func))
+            (import "wasix_64v1" "clock_time_set" (func (param i32 i64) (result i32)))
+            "#,
+        );
+        assert_eq!(
+            required,
+            RequiredCapabilities {
+                filesystem_write: true,
+                process_spawn: true,
+                clock_set: true,
+                ..Default::default()
+            }
+        );
+
+        let required = analyze(
+            r#"
+            (import "wasix_32v1" "proc_exec3" (func))
+            "#,
+        );
+        assert_eq!(
+            required,
+            RequiredCapabilities {
+                process_spawn: true,
+                ..Default::default()
+            }
+        );
+
+        let required = analyze(
+            r#"
+            (import "wasix_http_client_v1" "open" (func))
+            (import "wasi" "thread-spawn" (func (param i32) (result i32)))
+            "#,
+        );
+        assert_eq!(
+            required,
+            RequiredCapabilities {
+                network: true,
+                threads: true,
+                ..Default::default()
+            }
+        );
     }
 }
@@ -11,14 +11,14 @@ use futures::Future;
 use serde_derive::{Deserialize, Serialize};
 use std::sync::Mutex as StdMutex;
 use tokio::sync::{watch, Mutex as AsyncMutex};
-use virtual_fs::{Pipe, VirtualFile};
-use wasmer_wasix_types::wasi::{EpollType, Fd as WasiFd, Fdflags, Filestat, Rights};
+use virtual_fs::{range_lock::RangeLocks, Pipe, VirtualFile};
+use wasmer_wasix_types::wasi::{EpollType, Fd as WasiFd, Fdflags, Filestat, Filetype, Rights};
 
 use crate::{net::socket::InodeSocket, syscalls::EpollJoinWaker};
 
 use super::{
     InodeGuard, InodeValFilePollGuard, InodeValFilePollGuardJoin, InodeValFilePollGuardMode,
-    InodeWeakGuard, NotificationInner,
+    InodeWeakGuard, NotificationInner, RangeLockOwner,
 };
 
 #[derive(Debug, Clone)]
@@ -44,8 +44,24 @@ pub struct FdInner {
     pub rights_inheriting: Rights,
     pub flags: Fdflags,
     pub offset: Arc<AtomicU64>,
+    /// The descriptor is closed when the process calls `proc_exec`
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub close_on_exec: bool,
+    /// Listing of the directory that is taken when a scan starts (the first
+    /// read, or one at cookie 0) so that the cookies of `fd_readdir` keep
+    /// pointing at the same entries while the directory is being modified
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub dir_snapshot: Arc<StdMutex<Option<DirSnapshot>>>,
+    /// Owner of the byte-range locks taken through this open file
+    /// description, shared with the descriptors duplicated from it
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub lock_owner: Arc<RangeLockOwner>,
 }
 
+/// The entries of a directory as `(name, type, inode)` tuples, the position
+/// of an entry in the list is its cookie
+pub type DirSnapshot = Arc<Vec<(String, Filetype, u64)>>;
+
 impl Fd {
     /// This [`Fd`] can be used with read system calls.
     pub const READ: u16 = 1;
@@ -74,6 +90,9 @@ pub struct InodeVal {
     pub is_preopened: bool,
     pub name: RwLock<Cow<'static, str>>,
     pub kind: RwLock<Kind>,
+    /// Byte-range locks held on the file by its open file descriptions
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub range_locks: StdMutex<RangeLocks>,
 }
 
 impl InodeVal {
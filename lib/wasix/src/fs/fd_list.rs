@@ -257,6 +257,7 @@ mod tests {
                     kind: RwLock::new(Kind::Buffer { buffer: vec![] }),
                     name: RwLock::new(Cow::Borrowed("")),
                     stat: RwLock::new(Default::default()),
+                    range_locks: Default::default(),
                 }),
                 open_handles: Arc::new(AtomicI32::new(0)),
             },
@@ -266,6 +267,9 @@ mod tests {
                 rights: Rights::empty(),
                 rights_inheriting: Rights::empty(),
                 flags: Fdflags::from_bits_preserve(n),
+                close_on_exec: false,
+                dir_snapshot: Default::default(),
+                lock_owner: Default::default(),
             },
         }
     }
@@ -0,0 +1,325 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc, RwLock},
+};
+
+#[cfg(feature = "enable-serde")]
+use serde_derive::{Deserialize, Serialize};
+use virtual_fs::{FileSystem, FsError, VirtualFile};
+use wasmer_wasix_types::wasi::{Errno, Fd as WasiFd, Fdflags, Filetype, Rights};
+
+use super::{Fd, Kind, WasiFs, WasiFsRoot, WasiInodes};
+
+/// Serializable description of the file descriptors of a process which
+/// can be used to recreate them on another host (e.g. when migrating
+/// the process)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct FdTableSnapshot {
+    pub entries: Vec<FdSnapshot>,
+}
+
+/// Description of a single file descriptor in a [`FdTableSnapshot`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct FdSnapshot {
+    pub fd: WasiFd,
+    pub rights: Rights,
+    pub rights_inheriting: Rights,
+    pub flags: Fdflags,
+    pub open_flags: u16,
+    pub kind: FdSnapshotKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum FdSnapshotKind {
+    /// Regular file which can be reopened by its path
+    File { path: PathBuf, offset: u64 },
+    /// Pipes, sockets, directories and other resources that can not simply
+    /// be reopened and must be reconnected by the [`FdTableResolver`]
+    ReconnectNeeded { filetype: Filetype },
+}
+
+/// Recreates the resources behind the file descriptors of a [`FdTableSnapshot`]
+/// on the destination host.
+pub trait FdTableResolver {
+    /// Reopens a regular file that was open on the source host
+    fn reopen(
+        &self,
+        path: &Path,
+        open_flags: u16,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>, FsError>;
+
+    /// Reconnects a file descriptor that could not be reopened by its path
+    /// (such as a socket or a pipe), returning `None` leaves the file
+    /// descriptor closed.
+    fn reconnect(&self, entry: &FdSnapshot) -> Option<Kind> {
+        let _ = entry;
+        None
+    }
+}
+
+impl FdTableResolver for WasiFsRoot {
+    fn reopen(
+        &self,
+        path: &Path,
+        open_flags: u16,
+    ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>, FsError> {
+        self.new_open_options()
+            .read(open_flags & Fd::READ != 0)
+            .write(open_flags & (Fd::WRITE | Fd::APPEND) != 0)
+            .append(open_flags & Fd::APPEND != 0)
+            .open(path)
+    }
+}
+
+impl WasiFs {
+    /// Exports the file descriptors of this file system so that they can
+    /// be recreated elsewhere with [`WasiFs::import_fd_table`].
+    ///
+    /// Stdio and preopened file descriptors are not exported as they are
+    /// recreated when the destination environment is built.
+    pub fn export_fd_table(&self) -> FdTableSnapshot {
+        let preopen_fds = self.preopen_fds.read().unwrap();
+        let fd_map = self.fd_map.read().unwrap();
+
+        let entries = fd_map
+            .iter()
+            .filter(|(fd, entry)| !entry.is_stdio && !preopen_fds.contains(fd))
+            .map(|(fd, entry)| {
+                let guard = entry.inode.read();
+                let kind = match &*guard {
+                    Kind::File { path, fd: None, .. } if !path.as_os_str().is_empty() => {
+                        FdSnapshotKind::File {
+                            path: path.clone(),
+                            offset: entry.inner.offset.load(Ordering::Acquire),
+                        }
+                    }
+                    _ => FdSnapshotKind::ReconnectNeeded {
+                        filetype: entry.inode.stat.read().unwrap().st_filetype,
+                    },
+                };
+                FdSnapshot {
+                    fd,
+                    rights: entry.inner.rights,
+                    rights_inheriting: entry.inner.rights_inheriting,
+                    flags: entry.inner.flags,
+                    open_flags: entry.open_flags,
+                    kind,
+                }
+            })
+            .collect();
+
+        FdTableSnapshot { entries }
+    }
+
+    /// Recreates the file descriptors of a [`FdTableSnapshot`] using the
+    /// `resolver` to reopen files and reconnect other resources.
+    ///
+    /// File descriptors that the resolver declines to reconnect are skipped,
+    /// while an error is returned if a file can not be reopened or its file
+    /// descriptor is already in use. Nothing is imported when an error is
+    /// returned.
+    pub fn import_fd_table(
+        &self,
+        inodes: &WasiInodes,
+        snapshot: &FdTableSnapshot,
+        resolver: &dyn FdTableResolver,
+    ) -> Result<(), Errno> {
+        // Everything is resolved up front so that a failure does not leave
+        // the table with only some of the file descriptors
+        let mut resolved = Vec::with_capacity(snapshot.entries.len());
+        for entry in snapshot.entries.iter() {
+            let (kind, name, offset) = match &entry.kind {
+                FdSnapshotKind::File { path, offset } => {
+                    let handle = resolver
+                        .reopen(path, entry.open_flags)
+                        .map_err(super::fs_error_into_wasi_err)?;
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let kind = Kind::File {
+                        handle: Some(Arc::new(RwLock::new(handle))),
+                        path: path.clone(),
+                        fd: None,
+                    };
+                    (kind, name, *offset)
+                }
+                FdSnapshotKind::ReconnectNeeded { .. } => match resolver.reconnect(entry) {
+                    Some(kind) => (kind, String::new(), 0),
+                    None => {
+                        tracing::debug!(fd = entry.fd, "fd was not reconnected");
+                        continue;
+                    }
+                },
+            };
+            resolved.push((entry, kind, name, offset));
+        }
+
+        let mut imported = Vec::with_capacity(resolved.len());
+        for (entry, kind, name, offset) in resolved {
+            let inode = self.create_inode_with_default_stat(inodes, kind, false, Cow::Owned(name));
+            if let Err(err) = self.with_fd(
+                entry.rights,
+                entry.rights_inheriting,
+                entry.flags,
+                entry.open_flags,
+                inode,
+                entry.fd,
+            ) {
+                for fd in imported {
+                    self.close_fd(fd).ok();
+                }
+                return Err(err);
+            }
+            if let Some(fd) = self.fd_map.read().unwrap().get(entry.fd) {
+                fd.inner.offset.store(offset, Ordering::Release);
+            }
+            imported.push(entry.fd);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::SeekFrom;
+
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+    use virtual_fs::TmpFileSystem;
+
+    use super::*;
+    use crate::{state::PreopenedDir, ALL_RIGHTS};
+
+    fn new_fs(fs: &TmpFileSystem) -> (WasiFs, WasiInodes) {
+        let inodes = WasiInodes::new();
+        let preopen = PreopenedDir {
+            path: PathBuf::from("/"),
+            read: true,
+            write: true,
+            create: true,
+            ..Default::default()
+        };
+        let wasi_fs = WasiFs::new_with_preopen(
+            &inodes,
+            &[preopen],
+            &[],
+            WasiFsRoot::Sandbox(Arc::new(fs.clone())),
+        )
+        .unwrap();
+        (wasi_fs, inodes)
+    }
+
+    fn open_at(wasi_fs: &WasiFs, inodes: &WasiInodes, path: &str, offset: u64) -> WasiFd {
+        let handle = wasi_fs.root_fs.reopen(Path::new(path), Fd::READ).unwrap();
+        let kind = Kind::File {
+            handle: Some(Arc::new(RwLock::new(handle))),
+            path: PathBuf::from(path),
+            fd: None,
+        };
+        let inode = wasi_fs.create_inode_with_default_stat(inodes, kind, false, "file".into());
+        let fd = wasi_fs
+            .create_fd(ALL_RIGHTS, ALL_RIGHTS, Fdflags::empty(), Fd::READ, inode)
+            .unwrap();
+        wasi_fs
+            .get_fd(fd)
+            .unwrap()
+            .inner
+            .offset
+            .store(offset, Ordering::Release);
+        fd
+    }
+
+    #[tokio::test]
+    async fn test_export_import_fd_table() {
+        let fs = TmpFileSystem::new();
+        for (path, contents) in [("/a.txt", "hello world"), ("/b.txt", "0123456789")] {
+            let mut file = fs
+                .new_open_options()
+                .create(true)
+                .write(true)
+                .open(path)
+                .unwrap();
+            file.write_all(contents.as_bytes()).await.unwrap();
+        }
+
+        let (source, source_inodes) = new_fs(&fs);
+        let fd_a = open_at(&source, &source_inodes, "/a.txt", 6);
+        let fd_b = open_at(&source, &source_inodes, "/b.txt", 3);
+
+        let snapshot = source.export_fd_table();
+        assert_eq!(snapshot.entries.len(), 2);
+
+        let (dest, dest_inodes) = new_fs(&fs);
+        dest.import_fd_table(&dest_inodes, &snapshot, &dest.root_fs)
+            .unwrap();
+
+        for (fd, expected) in [(fd_a, "world"), (fd_b, "3456789")] {
+            let entry = dest.get_fd(fd).unwrap();
+            let offset = entry.inner.offset.load(Ordering::Acquire);
+
+            let guard = entry.inode.read();
+            let Kind::File {
+                handle: Some(handle),
+                ..
+            } = &*guard
+            else {
+                panic!("fd {fd} was not reopened as a file");
+            };
+            let mut handle = handle.write().unwrap();
+            handle.seek(SeekFrom::Start(offset)).await.unwrap();
+            let mut contents = String::new();
+            handle.read_to_string(&mut contents).await.unwrap();
+            assert_eq!(contents, expected);
+        }
+    }
+
+    struct FailingResolver {
+        inner: WasiFsRoot,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    impl FdTableResolver for FailingResolver {
+        fn reopen(
+            &self,
+            path: &Path,
+            open_flags: u16,
+        ) -> Result<Box<dyn VirtualFile + Send + Sync + 'static>, FsError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 1 {
+                return Err(FsError::EntryNotFound);
+            }
+            self.inner.reopen(path, open_flags)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_fd_table_is_all_or_nothing() {
+        let fs = TmpFileSystem::new();
+        for path in ["/a.txt", "/b.txt"] {
+            fs.new_open_options()
+                .create(true)
+                .write(true)
+                .open(path)
+                .unwrap();
+        }
+
+        let (source, source_inodes) = new_fs(&fs);
+        let fd_a = open_at(&source, &source_inodes, "/a.txt", 0);
+        let fd_b = open_at(&source, &source_inodes, "/b.txt", 0);
+        let snapshot = source.export_fd_table();
+
+        let (dest, dest_inodes) = new_fs(&fs);
+        let resolver = FailingResolver {
+            inner: dest.root_fs.clone(),
+            calls: Default::default(),
+        };
+        let ret = dest.import_fd_table(&dest_inodes, &snapshot, &resolver);
+        assert_eq!(ret, Err(Errno::Noent));
+        assert_eq!(resolver.calls.load(Ordering::SeqCst), 2);
+        assert!(dest.get_fd(fd_a).is_err());
+        assert!(dest.get_fd(fd_b).is_err());
+    }
+}
@@ -7,8 +7,11 @@
 
 mod fd;
 mod fd_list;
+mod fd_table;
 mod inode_guard;
 mod notification;
+mod range_lock;
+mod uptime_file;
 
 use std::{
     borrow::{Borrow, Cow},
@@ -43,12 +46,17 @@ use wasmer_wasix_types::{
     },
 };
 
-pub use self::fd::{EpollFd, EpollInterest, EpollJoinGuard, Fd, FdInner, InodeVal, Kind};
+pub use self::fd::{
+    DirSnapshot, EpollFd, EpollInterest, EpollJoinGuard, Fd, FdInner, InodeVal, Kind,
+};
+pub use self::fd_table::{FdSnapshot, FdSnapshotKind, FdTableResolver, FdTableSnapshot};
 pub(crate) use self::inode_guard::{
     InodeValFilePollGuard, InodeValFilePollGuardJoin, InodeValFilePollGuardMode,
     InodeValFileReadGuard, InodeValFileWriteGuard, WasiStateFileGuard, POLL_GUARD_MAX_RET,
 };
 pub use self::notification::NotificationInner;
+pub use self::range_lock::RangeLockOwner;
+pub(crate) use self::uptime_file::UptimeFileSystem;
 use crate::syscalls::map_io_err;
 use crate::{bin_factory::BinaryPackage, state::PreopenedDir, ALL_RIGHTS};
 
@@ -370,6 +378,15 @@ impl WasiFsRoot {
             }
         }
     }
+
+    /// Returns `true` when both paths reside on the same file system, hard
+    /// links are not able to cross this boundary.
+    pub(crate) fn is_same_mount(&self, a: &Path, b: &Path) -> bool {
+        match self {
+            WasiFsRoot::Sandbox(fs) => fs.is_same_mount(a, b).unwrap_or(true),
+            WasiFsRoot::Backing(_) => true,
+        }
+    }
 }
 
 impl FileSystem for WasiFsRoot {
@@ -398,6 +415,12 @@ impl FileSystem for WasiFsRoot {
             WasiFsRoot::Backing(fs) => fs.remove_dir(path),
         }
     }
+    fn remove_dir_all(&self, path: &Path) -> virtual_fs::Result<()> {
+        match self {
+            WasiFsRoot::Sandbox(fs) => fs.remove_dir_all(path),
+            WasiFsRoot::Backing(fs) => fs.remove_dir_all(path),
+        }
+    }
     fn rename<'a>(&'a self, from: &Path, to: &Path) -> BoxFuture<'a, virtual_fs::Result<()>> {
         let from = from.to_owned();
         let to = to.to_owned();
@@ -409,6 +432,43 @@ impl FileSystem for WasiFsRoot {
             }
         })
     }
+    fn write_atomic<'a>(
+        &'a self,
+        path: &'a Path,
+        contents: &'a [u8],
+    ) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        Box::pin(async move {
+            match self {
+                WasiFsRoot::Sandbox(fs) => fs.write_atomic(path, contents).await,
+                WasiFsRoot::Backing(fs) => fs.write_atomic(path, contents).await,
+            }
+        })
+    }
+    fn copy_file<'a>(
+        &'a self,
+        from: &'a Path,
+        to: &'a Path,
+    ) -> BoxFuture<'a, virtual_fs::Result<u64>> {
+        Box::pin(async move {
+            match self {
+                WasiFsRoot::Sandbox(fs) => fs.copy_file(from, to).await,
+                WasiFsRoot::Backing(fs) => fs.copy_file(from, to).await,
+            }
+        })
+    }
+    fn copy_tree<'a>(
+        &'a self,
+        from: &'a Path,
+        to: &'a Path,
+        opts: virtual_fs::CopyTreeOpts,
+    ) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        Box::pin(async move {
+            match self {
+                WasiFsRoot::Sandbox(fs) => fs.copy_tree(from, to, opts).await,
+                WasiFsRoot::Backing(fs) => fs.copy_tree(from, to, opts).await,
+            }
+        })
+    }
     fn metadata(&self, path: &Path) -> virtual_fs::Result<virtual_fs::Metadata> {
         match self {
             WasiFsRoot::Sandbox(fs) => fs.metadata(path),
@@ -427,6 +487,23 @@ impl FileSystem for WasiFsRoot {
             WasiFsRoot::Backing(fs) => fs.remove_file(path),
         }
     }
+    fn statvfs(&self, path: &Path) -> virtual_fs::Result<virtual_fs::FsStat> {
+        match self {
+            WasiFsRoot::Sandbox(fs) => fs.statvfs(path),
+            WasiFsRoot::Backing(fs) => fs.statvfs(path),
+        }
+    }
+    fn set_times(
+        &self,
+        path: &Path,
+        atime: Option<u64>,
+        mtime: Option<u64>,
+    ) -> virtual_fs::Result<()> {
+        match self {
+            WasiFsRoot::Sandbox(fs) => fs.set_times(path, atime, mtime),
+            WasiFsRoot::Backing(fs) => fs.set_times(path, atime, mtime),
+        }
+    }
     fn new_open_options(&self) -> OpenOptions {
         match self {
             WasiFsRoot::Sandbox(fs) => fs.new_open_options(),
@@ -493,6 +570,52 @@ async fn merge_filesystems(
     files.try_collect().await
 }
 
+/// The rights granted on a preopened directory (and on everything opened
+/// beneath it) that can be read, written and/or created in
+pub(crate) fn preopen_rights(read: bool, write: bool, create: bool) -> Rights {
+    // TODO: review tell' and fd_readwrite
+    let mut rights = Rights::FD_ADVISE | Rights::FD_TELL | Rights::FD_SEEK;
+    if read {
+        rights |= Rights::FD_READ
+            | Rights::PATH_OPEN
+            | Rights::FD_READDIR
+            | Rights::PATH_READLINK
+            | Rights::PATH_FILESTAT_GET
+            | Rights::FD_FILESTAT_GET
+            | Rights::PATH_LINK_SOURCE
+            | Rights::PATH_RENAME_SOURCE
+            | Rights::POLL_FD_READWRITE
+            | Rights::SOCK_SHUTDOWN;
+    }
+    if write {
+        rights |= Rights::FD_DATASYNC
+            | Rights::FD_FDSTAT_SET_FLAGS
+            | Rights::FD_WRITE
+            | Rights::FD_SYNC
+            | Rights::FD_ALLOCATE
+            | Rights::PATH_OPEN
+            | Rights::PATH_RENAME_TARGET
+            | Rights::PATH_FILESTAT_SET_SIZE
+            | Rights::PATH_FILESTAT_SET_TIMES
+            | Rights::FD_FILESTAT_SET_SIZE
+            | Rights::FD_FILESTAT_SET_TIMES
+            | Rights::PATH_REMOVE_DIRECTORY
+            | Rights::PATH_UNLINK_FILE
+            | Rights::POLL_FD_READWRITE
+            | Rights::SOCK_SHUTDOWN;
+    }
+    if create {
+        rights |= Rights::PATH_CREATE_DIRECTORY
+            | Rights::PATH_CREATE_FILE
+            | Rights::PATH_LINK_TARGET
+            | Rights::PATH_OPEN
+            | Rights::PATH_RENAME_TARGET
+            | Rights::PATH_SYMLINK;
+    }
+
+    rights
+}
+
 fn create_dir_all(fs: &dyn FileSystem, path: &Path) -> Result<(), virtual_fs::FsError> {
     if fs.metadata(path).is_ok() {
         return Ok(());
@@ -574,7 +697,7 @@ impl WasiFs {
 
         let _ = tokio::join!(async {
             for fd in to_close {
-                self.flush(fd).await.ok();
+                self.flush_on_teardown(fd).await.ok();
                 self.close_fd(fd).ok();
             }
         });
@@ -649,6 +772,7 @@ impl WasiFs {
             is_preopened: true,
             name: RwLock::new("/".into()),
             kind: RwLock::new(root_kind),
+            range_locks: Default::default(),
         });
 
         let wasi_fs = Self {
@@ -1377,6 +1501,9 @@ impl WasiFs {
                     rights_inheriting: ALL_RIGHTS,
                     flags: Fdflags::empty(),
                     offset: Arc::new(AtomicU64::new(0)),
+                    close_on_exec: false,
+                    dir_snapshot: Default::default(),
+                    lock_owner: Default::default(),
                 },
                 open_flags: 0,
                 inode: self.root_inode.clone(),
@@ -1530,24 +1657,46 @@ impl WasiFs {
                 };
                 drop(fd);
 
-                struct FlushPoller {
-                    file: Arc<RwLock<Box<dyn VirtualFile + Send + Sync>>>,
-                }
-                impl Future for FlushPoller {
-                    type Output = Result<(), Errno>;
-                    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-                        let mut file = self.file.write().unwrap();
-                        Pin::new(file.as_mut())
-                            .poll_flush(cx)
-                            .map_err(|_| Errno::Io)
-                    }
-                }
                 FlushPoller { file }.await?;
             }
         }
         Ok(())
     }
 
+    /// Flushes a file descriptor that is about to be closed because the file
+    /// system is being torn down. Unlike [`WasiFs::flush`] this ignores the
+    /// rights of the file descriptor and also handles pipes and sockets.
+    async fn flush_on_teardown(&self, fd: WasiFd) -> Result<(), Errno> {
+        if matches!(fd, __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO) {
+            return self.flush(fd).await;
+        }
+
+        let fd = self.get_fd(fd)?;
+        let guard = fd.inode.read();
+        match guard.deref() {
+            Kind::File {
+                handle: Some(file), ..
+            } => {
+                let file = file.clone();
+                drop(guard);
+                FlushPoller { file }.await
+            }
+            Kind::Pipe { pipe } => {
+                let mut pipe = pipe.clone();
+                drop(guard);
+                pipe.flush().await.map_err(map_io_err)
+            }
+            Kind::Socket { socket } => {
+                // Other processes (e.g. forks) may still be using the socket
+                if fd.inode.handle_count() <= 1 {
+                    socket.close()?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Creates an inode and inserts it given a Kind and some extra data
     pub(crate) fn create_inode(
         &self,
@@ -1603,6 +1752,7 @@ impl WasiFs {
             is_preopened,
             name: RwLock::new(name),
             kind: RwLock::new(kind),
+            range_locks: Default::default(),
         })
     }
 
@@ -1667,6 +1817,9 @@ impl WasiFs {
                 rights_inheriting,
                 flags,
                 offset: Arc::new(AtomicU64::new(0)),
+                close_on_exec: false,
+                dir_snapshot: Default::default(),
+                lock_owner: Default::default(),
             },
             open_flags,
             inode,
@@ -1695,6 +1848,9 @@ impl WasiFs {
                 rights_inheriting: fd.inner.rights_inheriting,
                 flags: fd.inner.flags,
                 offset: fd.inner.offset.clone(),
+                close_on_exec: false,
+                dir_snapshot: fd.inner.dir_snapshot.clone(),
+                lock_owner: fd.inner.lock_owner.clone(),
             },
             open_flags: fd.open_flags,
             inode: fd.inode,
@@ -1843,6 +1999,7 @@ impl WasiFs {
             read,
             write,
             create,
+            rights: restricted_rights,
         } in self.init_preopens.iter()
         {
             debug!(
@@ -1868,48 +2025,10 @@ impl WasiFs {
                 ));
             };
 
-            let rights = {
-                // TODO: review tell' and fd_readwrite
-                let mut rights = Rights::FD_ADVISE | Rights::FD_TELL | Rights::FD_SEEK;
-                if *read {
-                    rights |= Rights::FD_READ
-                        | Rights::PATH_OPEN
-                        | Rights::FD_READDIR
-                        | Rights::PATH_READLINK
-                        | Rights::PATH_FILESTAT_GET
-                        | Rights::FD_FILESTAT_GET
-                        | Rights::PATH_LINK_SOURCE
-                        | Rights::PATH_RENAME_SOURCE
-                        | Rights::POLL_FD_READWRITE
-                        | Rights::SOCK_SHUTDOWN;
-                }
-                if *write {
-                    rights |= Rights::FD_DATASYNC
-                        | Rights::FD_FDSTAT_SET_FLAGS
-                        | Rights::FD_WRITE
-                        | Rights::FD_SYNC
-                        | Rights::FD_ALLOCATE
-                        | Rights::PATH_OPEN
-                        | Rights::PATH_RENAME_TARGET
-                        | Rights::PATH_FILESTAT_SET_SIZE
-                        | Rights::PATH_FILESTAT_SET_TIMES
-                        | Rights::FD_FILESTAT_SET_SIZE
-                        | Rights::FD_FILESTAT_SET_TIMES
-                        | Rights::PATH_REMOVE_DIRECTORY
-                        | Rights::PATH_UNLINK_FILE
-                        | Rights::POLL_FD_READWRITE
-                        | Rights::SOCK_SHUTDOWN;
-                }
-                if *create {
-                    rights |= Rights::PATH_CREATE_DIRECTORY
-                        | Rights::PATH_CREATE_FILE
-                        | Rights::PATH_LINK_TARGET
-                        | Rights::PATH_OPEN
-                        | Rights::PATH_RENAME_TARGET
-                        | Rights::PATH_SYMLINK;
-                }
-
-                rights
+            let rights = preopen_rights(*read, *write, *create);
+            let rights = match restricted_rights {
+                Some(restricted_rights) => rights & *restricted_rights,
+                None => rights,
             };
             let inode = if let Some(alias) = &alias {
                 self.create_inode(inodes, kind, true, alias.clone())
@@ -1986,6 +2105,7 @@ impl WasiFs {
                 is_preopened: true,
                 name: RwLock::new(name.to_string().into()),
                 kind: RwLock::new(kind),
+                range_locks: Default::default(),
             })
         };
         self.fd_map.write().unwrap().insert(
@@ -1997,6 +2117,9 @@ impl WasiFs {
                     rights_inheriting: Rights::empty(),
                     flags: fd_flags,
                     offset: Arc::new(AtomicU64::new(0)),
+                    close_on_exec: false,
+                    dir_snapshot: Default::default(),
+                    lock_owner: Default::default(),
                 },
                 // since we're not calling open on this, we don't need open flags
                 open_flags: 0,
@@ -2093,6 +2216,21 @@ impl WasiFs {
     }
 }
 
+/// Flushes a shared file handle without holding its lock across await points
+struct FlushPoller {
+    file: Arc<RwLock<Box<dyn VirtualFile + Send + Sync>>>,
+}
+
+impl Future for FlushPoller {
+    type Output = Result<(), Errno>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut file = self.file.write().unwrap();
+        Pin::new(file.as_mut())
+            .poll_flush(cx)
+            .map_err(|_| Errno::Io)
+    }
+}
+
 impl std::fmt::Debug for WasiFs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Ok(guard) = self.current_dir.try_lock() {
@@ -2241,5 +2379,6 @@ pub fn fs_error_into_wasi_err(fs_error: FsError) -> Errno {
         FsError::StorageFull => Errno::Overflow,
         FsError::Lock | FsError::UnknownError => Errno::Io,
         FsError::Unsupported => Errno::Notsup,
+        FsError::SymlinkLoop => Errno::Loop,
     }
 }
@@ -0,0 +1,66 @@
+use std::sync::Mutex;
+
+use virtual_fs::range_lock::{next_lock_owner, LockOwner};
+
+use super::{InodeGuard, InodeWeakGuard};
+
+/// Holds the byte-range locks taken with `fd_lock_range` through an open
+/// file description, which is shared by the file descriptors that were
+/// duplicated from one another. The locks are kept on the inode so that
+/// they conflict with the locks of the other open file descriptions of
+/// the same file, and they are released once the last file descriptor of
+/// the description is closed.
+#[derive(Debug)]
+pub struct RangeLockOwner {
+    id: LockOwner,
+    // The inode this owner holds locks on, once it took one
+    inode: Mutex<Option<InodeWeakGuard>>,
+}
+
+impl Default for RangeLockOwner {
+    fn default() -> Self {
+        Self {
+            id: next_lock_owner(),
+            inode: Mutex::new(None),
+        }
+    }
+}
+
+impl RangeLockOwner {
+    /// Takes a lock on `start..end` of the file unless it conflicts with a
+    /// lock of another open file description, returns whether it was taken
+    pub fn try_lock(&self, inode: &InodeGuard, start: u64, end: u64, exclusive: bool) -> bool {
+        let locked = inode
+            .range_locks
+            .lock()
+            .unwrap()
+            .try_lock(self.id, start, end, exclusive);
+        if locked {
+            *self.inode.lock().unwrap() = Some(inode.downgrade());
+        }
+        locked
+    }
+
+    /// Releases the locks of this open file description within `start..end`
+    pub fn unlock(&self, inode: &InodeGuard, start: u64, end: u64) {
+        inode
+            .range_locks
+            .lock()
+            .unwrap()
+            .unlock(self.id, start, end);
+    }
+}
+
+impl Drop for RangeLockOwner {
+    fn drop(&mut self) {
+        let inode = match self.inode.get_mut() {
+            Ok(inode) => inode.take().and_then(|inode| inode.upgrade()),
+            Err(_) => None,
+        };
+        if let Some(inode) = inode {
+            if let Ok(mut locks) = inode.range_locks.lock() {
+                locks.unlock_all(self.id);
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! UptimeFile is the file behind `/proc/uptime`, it is rendered from the
+//! clock of the runtime every time it is read from the start. Every open
+//! gets its own file (see [`UptimeFileSystem`]) so readers have their own
+//! cursor.
+
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use virtual_fs::{
+    DirEntry, FileOpener, FileSystem, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, VirtualFile,
+};
+
+use crate::runtime::clock::DynClock;
+
+#[derive(Debug)]
+pub(crate) struct UptimeFile {
+    clock: Arc<DynClock>,
+    contents: Option<Vec<u8>>,
+    cursor: u64,
+}
+
+impl UptimeFile {
+    pub(crate) fn new(clock: Arc<DynClock>) -> Self {
+        Self {
+            clock,
+            contents: None,
+            cursor: 0,
+        }
+    }
+
+    /// Same format as Linux, the uptime and the idle time in seconds (the
+    /// idle time is not tracked).
+    fn render(&self) -> Vec<u8> {
+        let uptime = self.clock.elapsed();
+        format!(
+            "{}.{:02} 0.00\n",
+            uptime.as_secs(),
+            uptime.subsec_millis() / 10
+        )
+        .into_bytes()
+    }
+}
+
+impl AsyncSeek for UptimeFile {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let len = self.size() as i64;
+        let cursor = match position {
+            SeekFrom::Start(pos) => pos as i64,
+            SeekFrom::Current(offset) => self.cursor as i64 + offset,
+            SeekFrom::End(offset) => len + offset,
+        };
+        if cursor < 0 {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        self.cursor = cursor as u64;
+        if self.cursor == 0 {
+            // Rewinding shows the current uptime again
+            self.contents = None;
+        }
+        Ok(())
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.cursor))
+    }
+}
+
+impl AsyncWrite for UptimeFile {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::ErrorKind::PermissionDenied.into()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for UptimeFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.contents.is_none() {
+            let contents = self.render();
+            self.contents = Some(contents);
+        }
+        let this = &mut *self;
+        let contents = this.contents.as_deref().unwrap_or_default();
+        let start = (this.cursor as usize).min(contents.len());
+        let amt = buf.remaining().min(contents.len() - start);
+        buf.put_slice(&contents[start..start + amt]);
+        this.cursor += amt as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl VirtualFile for UptimeFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        match &self.contents {
+            Some(contents) => contents.len() as u64,
+            None => self.render().len() as u64,
+        }
+    }
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let remaining = self.size().saturating_sub(self.cursor);
+        Poll::Ready(Ok(remaining as usize))
+    }
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+/// File system that serves a fresh [`UptimeFile`] at [`UptimeFileSystem::PATH`]
+/// every time it is opened, it is inserted at `/proc/uptime`
+#[derive(Debug, Clone)]
+pub(crate) struct UptimeFileSystem {
+    clock: Arc<DynClock>,
+}
+
+impl UptimeFileSystem {
+    pub(crate) const PATH: &'static str = "/uptime";
+
+    pub(crate) fn new(clock: Arc<DynClock>) -> Self {
+        Self { clock }
+    }
+
+    fn is_root(path: &Path) -> bool {
+        path == Path::new("/") || path == Path::new("")
+    }
+}
+
+impl FileSystem for UptimeFileSystem {
+    fn readlink(&self, _path: &Path) -> virtual_fs::Result<PathBuf> {
+        Err(FsError::InvalidInput)
+    }
+
+    fn read_dir(&self, path: &Path) -> virtual_fs::Result<ReadDir> {
+        if !Self::is_root(path) {
+            return Err(FsError::EntryNotFound);
+        }
+        let path = PathBuf::from(Self::PATH);
+        let metadata = self.metadata(&path);
+        Ok(ReadDir::new(vec![DirEntry { path, metadata }]))
+    }
+
+    fn create_dir(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename<'a>(
+        &'a self,
+        _from: &'a Path,
+        _to: &'a Path,
+    ) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        Box::pin(async { Err(FsError::PermissionDenied) })
+    }
+
+    fn metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        let ft = if path == Path::new(Self::PATH) {
+            FileType::new_file()
+        } else if Self::is_root(path) {
+            FileType::new_dir()
+        } else {
+            return Err(FsError::EntryNotFound);
+        };
+        Ok(Metadata {
+            ft,
+            accessed: 0,
+            created: 0,
+            modified: 0,
+            len: 0,
+        })
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        self.metadata(path)
+    }
+
+    fn remove_file(&self, _path: &Path) -> virtual_fs::Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+
+    fn mount(
+        &self,
+        _name: String,
+        _path: &Path,
+        _fs: Box<dyn FileSystem + Send + Sync>,
+    ) -> virtual_fs::Result<()> {
+        Err(FsError::Unsupported)
+    }
+}
+
+impl FileOpener for UptimeFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> virtual_fs::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        if path != Path::new(Self::PATH) {
+            return Err(match Self::is_root(path) {
+                true => FsError::NotAFile,
+                false => FsError::EntryNotFound,
+            });
+        }
+        if conf.would_mutate() {
+            return Err(FsError::PermissionDenied);
+        }
+        Ok(Box::new(UptimeFile::new(self.clock.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::runtime::clock::ManualClock;
+
+    #[tokio::test]
+    async fn every_open_has_its_own_cursor() {
+        let clock = Arc::new(ManualClock::new());
+        clock.advance(Duration::from_millis(12_340));
+        let fs = UptimeFileSystem::new(clock);
+        let open = || {
+            fs.new_open_options()
+                .read(true)
+                .open(UptimeFileSystem::PATH)
+                .unwrap()
+        };
+
+        let mut first = open();
+        let mut second = open();
+        let mut buf = [0u8; 3];
+        first.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"12.");
+
+        let mut contents = String::new();
+        second.read_to_string(&mut contents).await.unwrap();
+        assert_eq!(contents, "12.34 0.00\n");
+
+        let mut rest = String::new();
+        first.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "34 0.00\n");
+    }
+}
@@ -42,6 +42,7 @@ mod syscalls {
     mod fd_set_size;
     mod fd_set_times;
     mod fd_write;
+    mod path_copy_tree;
     mod path_create_directory;
     mod path_link;
     mod path_open;
@@ -0,0 +1,61 @@
+use virtual_fs::CopyTreeOpts;
+
+use crate::{syscalls::__asyncify_light, VIRTUAL_ROOT_FD};
+
+use super::*;
+
+impl JournalEffector {
+    pub fn save_path_copy_tree(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        old_path: String,
+        new_path: String,
+        opts: CopyTreeOpts,
+    ) -> anyhow::Result<()> {
+        Self::save_event(
+            ctx,
+            JournalEntry::PathCopyTreeV1 {
+                fd,
+                old_path: Cow::Owned(old_path),
+                new_path: Cow::Owned(new_path),
+                follow_symlinks: opts.follow_symlinks,
+                preserve_times: opts.preserve_times,
+            },
+        )
+    }
+
+    pub fn apply_path_copy_tree(
+        ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+        fd: Fd,
+        old_path: &str,
+        new_path: &str,
+        follow_symlinks: bool,
+        preserve_times: bool,
+    ) -> anyhow::Result<()> {
+        let opts = CopyTreeOpts {
+            preserve_times,
+            follow_symlinks,
+        };
+        // see `VIRTUAL_ROOT_FD` for details as to why this exists
+        if fd == VIRTUAL_ROOT_FD {
+            let state = ctx.data().state.clone();
+            let old_path = old_path.to_string();
+            let new_path = new_path.to_string();
+            __asyncify_light(ctx.data(), None, async move {
+                state.fs_copy_tree(old_path, new_path, opts).await
+            })??;
+        } else {
+            let ret = crate::syscalls::path_copy_tree_internal(ctx, fd, old_path, new_path, opts)?;
+            if ret != Errno::Success {
+                bail!(
+                    "journal restore error: failed to copy tree (fd={}, old_path={}, new_path={}) - {}",
+                    fd,
+                    old_path,
+                    new_path,
+                    ret
+                );
+            }
+        }
+        Ok(())
+    }
+}
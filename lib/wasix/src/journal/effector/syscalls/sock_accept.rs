@@ -67,6 +67,7 @@ impl JournalEffector {
                     read_timeout: None,
                     accept_timeout: None,
                     connect_timeout: None,
+                    user_timeout: None,
                     handler: None,
                 },
             }),
@@ -67,8 +67,8 @@ pub use wasmer;
 pub use wasmer_wasix_types;
 
 use wasmer::{
-    imports, namespace, AsStoreMut, Exports, FunctionEnv, Imports, Memory32, MemoryAccessError,
-    MemorySize, RuntimeError,
+    imports, namespace, AsStoreMut, Exports, FromToNativeWasmType, Function, FunctionEnv,
+    FunctionEnvMut, Imports, Memory32, MemoryAccessError, MemorySize, RuntimeError, WasmTypeList,
 };
 
 pub use virtual_fs;
@@ -88,7 +88,8 @@ pub use crate::{
     os::{
         task::{
             control_plane::WasiControlPlane,
-            process::{WasiProcess, WasiProcessId},
+            heap::HeapStats,
+            process::{WasiProcess, WasiProcessId, MAX_NICE, MIN_NICE},
             thread::{WasiThread, WasiThreadError, WasiThreadHandle, WasiThreadId},
         },
         WasiTtyState,
@@ -97,14 +98,14 @@ pub use crate::{
     runtime::{task_manager::VirtualTaskManager, PluggableRuntime, Runtime},
     state::{
         WasiEnv, WasiEnvBuilder, WasiEnvInit, WasiFunctionEnv, WasiInstanceHandles,
-        WasiStateCreationError, ALL_RIGHTS,
+        WasiStateCreationError, ALL_RIGHTS, DEFAULT_DOMAINNAME, DEFAULT_HOSTNAME,
     },
     syscalls::{journal::wait_for_snapshot, rewind, rewind_ext, types, unwind},
     utils::is_wasix_module,
     utils::{
-        get_wasi_version, get_wasi_versions, is_wasi_module,
+        get_exports, get_wasi_version, get_wasi_versions, is_wasi_module,
         store::{capture_store_snapshot, restore_store_snapshot, StoreSnapshot},
-        WasiVersion,
+        ExportInfo, ExportKind, WasiVersion,
     },
 };
 
@@ -120,6 +121,8 @@ pub enum WasiError {
     DeepSleep(DeepSleepWork),
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
+    #[error("WASI CPU time budget exceeded")]
+    CpuBudgetExceeded,
 }
 
 pub type WasiResult<T> = Result<Result<T, Errno>, WasiError>;
@@ -266,21 +269,44 @@ pub enum WasiRuntimeError {
     Anyhow(#[from] Arc<anyhow::Error>),
 }
 
+/// How the execution of an instance came to an end,
+/// see [`WasiRuntimeError::outcome`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasiOutcome {
+    /// The process exited with the given exit code
+    Exited(ExitCode),
+    /// The thread exited without terminating the process
+    ThreadExited,
+    /// The thread went into a deep sleep and needs to be resumed later
+    DeepSleep,
+    /// A general execution error occurred
+    Error,
+}
+
 impl WasiRuntimeError {
     /// Retrieve the concrete exit code returned by an instance.
     ///
     /// Returns [`None`] if a general execution error ocurred.
     pub fn as_exit_code(&self) -> Option<ExitCode> {
-        if let WasiRuntimeError::Wasi(WasiError::Exit(code)) = self {
-            Some(*code)
-        } else if let WasiRuntimeError::Runtime(err) = self {
-            if let Some(WasiError::Exit(code)) = err.downcast_ref() {
-                Some(*code)
-            } else {
-                None
-            }
-        } else {
-            None
+        match self.outcome() {
+            WasiOutcome::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Determines how the execution of an instance ended, which tells
+    /// exits, thread exits and deep sleeps apart from actual errors.
+    pub fn outcome(&self) -> WasiOutcome {
+        let err = match self {
+            WasiRuntimeError::Wasi(err) => Some(err),
+            WasiRuntimeError::Runtime(err) => err.downcast_ref::<WasiError>(),
+            _ => None,
+        };
+        match err {
+            Some(WasiError::Exit(code)) => WasiOutcome::Exited(*code),
+            Some(WasiError::ThreadExit) => WasiOutcome::ThreadExited,
+            Some(WasiError::DeepSleep(_)) => WasiOutcome::DeepSleep,
+            _ => WasiOutcome::Error,
         }
     }
 }
@@ -370,13 +396,26 @@ pub fn generate_import_object_from_env(
 
     imports.extend(&imports_wasi_generic);
 
+    for namespace in [
+        "wasi",
+        "wasi_unstable",
+        "wasi_snapshot_preview1",
+        "wasix_32v1",
+        "wasix_64v1",
+    ] {
+        if let Some(mut exports) = imports.get_namespace_exports(namespace) {
+            deny_syscalls(store, ctx, &mut exports);
+            imports.register_namespace(namespace, exports);
+        }
+    }
+
     imports
 }
 
 fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "thread-spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn::<Memory32>),
+        "thread-spawn" => syscall(&mut store, env, "thread-spawn", thread_spawn::<Memory32>),
     };
     namespace
 }
@@ -384,52 +423,52 @@ fn wasi_exports_generic(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiE
 fn wasi_unstable_exports(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory32>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory32>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory32>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory32>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory32>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory32>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory32>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::fd_filestat_get),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory32>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory32>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory32>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory32>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory32>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory32>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::fd_seek),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::path_filestat_get),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory32>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory32>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory32>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory32>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory32>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory32>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory32>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, legacy::snapshot0::poll_oneoff::<Memory32>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory32>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory32>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory32>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "thread-spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn::<Memory32>),
+        "args_get" => syscall(&mut store, env, "args_get", args_get::<Memory32>),
+        "args_sizes_get" => syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory32>),
+        "clock_res_get" => syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory32>),
+        "clock_time_get" => syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory32>),
+        "environ_get" => syscall(&mut store, env, "environ_get", environ_get::<Memory32>),
+        "environ_sizes_get" => syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory32>),
+        "fd_advise" => syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory32>),
+        "fd_fdstat_set_flags" => syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => syscall(&mut store, env, "fd_filestat_get", legacy::snapshot0::fd_filestat_get),
+        "fd_filestat_set_size" => syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => syscall(&mut store, env, "fd_pread", fd_pread::<Memory32>),
+        "fd_prestat_get" => syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory32>),
+        "fd_prestat_dir_name" => syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory32>),
+        "fd_pwrite" => syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory32>),
+        "fd_read" => syscall(&mut store, env, "fd_read", fd_read::<Memory32>),
+        "fd_readdir" => syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory32>),
+        "fd_renumber" => syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_seek" => syscall(&mut store, env, "fd_seek", legacy::snapshot0::fd_seek),
+        "fd_sync" => syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => syscall(&mut store, env, "fd_tell", fd_tell::<Memory32>),
+        "fd_write" => syscall(&mut store, env, "fd_write", fd_write::<Memory32>),
+        "path_create_directory" => syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory32>),
+        "path_filestat_get" => syscall(&mut store, env, "path_filestat_get", legacy::snapshot0::path_filestat_get),
+        "path_filestat_set_times" => syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory32>),
+        "path_link" => syscall(&mut store, env, "path_link", path_link::<Memory32>),
+        "path_open" => syscall(&mut store, env, "path_open", path_open::<Memory32>),
+        "path_readlink" => syscall(&mut store, env, "path_readlink", path_readlink::<Memory32>),
+        "path_remove_directory" => syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory32>),
+        "path_rename" => syscall(&mut store, env, "path_rename", path_rename::<Memory32>),
+        "path_symlink" => syscall(&mut store, env, "path_symlink", path_symlink::<Memory32>),
+        "path_unlink_file" => syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory32>),
+        "poll_oneoff" => syscall(&mut store, env, "poll_oneoff", legacy::snapshot0::poll_oneoff::<Memory32>),
+        "proc_exit" => syscall(&mut store, env, "proc_exit", proc_exit::<Memory32>),
+        "proc_raise" => syscall(&mut store, env, "proc_raise", proc_raise),
+        "random_get" => syscall(&mut store, env, "random_get", random_get::<Memory32>),
+        "sched_yield" => syscall(&mut store, env, "sched_yield", sched_yield::<Memory32>),
+        "sock_recv" => syscall(&mut store, env, "sock_recv", sock_recv::<Memory32>),
+        "sock_send" => syscall(&mut store, env, "sock_send", sock_send::<Memory32>),
+        "sock_shutdown" => syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "thread-spawn" => syscall(&mut store, env, "thread-spawn", thread_spawn::<Memory32>),
     };
     namespace
 }
@@ -440,53 +479,53 @@ fn wasi_snapshot_preview1_exports(
 ) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory32>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory32>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory32>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory32>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory32>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory32>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory32>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, fd_filestat_get::<Memory32>),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory32>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory32>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory32>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory32>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory32>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory32>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, fd_seek::<Memory32>),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory32>),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory32>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory32>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory32>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory32>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory32>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory32>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory32>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory32>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory32>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
-        "sock_accept" => Function::new_typed_with_env(&mut store, env, sock_accept::<Memory32>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory32>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory32>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "thread-spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn::<Memory32>),
+        "args_get" => syscall(&mut store, env, "args_get", args_get::<Memory32>),
+        "args_sizes_get" => syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory32>),
+        "clock_res_get" => syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory32>),
+        "clock_time_get" => syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory32>),
+        "environ_get" => syscall(&mut store, env, "environ_get", environ_get::<Memory32>),
+        "environ_sizes_get" => syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory32>),
+        "fd_advise" => syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory32>),
+        "fd_fdstat_set_flags" => syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => syscall(&mut store, env, "fd_filestat_get", fd_filestat_get::<Memory32>),
+        "fd_filestat_set_size" => syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => syscall(&mut store, env, "fd_pread", fd_pread::<Memory32>),
+        "fd_prestat_get" => syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory32>),
+        "fd_prestat_dir_name" => syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory32>),
+        "fd_pwrite" => syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory32>),
+        "fd_read" => syscall(&mut store, env, "fd_read", fd_read::<Memory32>),
+        "fd_readdir" => syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory32>),
+        "fd_renumber" => syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_seek" => syscall(&mut store, env, "fd_seek", fd_seek::<Memory32>),
+        "fd_sync" => syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => syscall(&mut store, env, "fd_tell", fd_tell::<Memory32>),
+        "fd_write" => syscall(&mut store, env, "fd_write", fd_write::<Memory32>),
+        "path_create_directory" => syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory32>),
+        "path_filestat_get" => syscall(&mut store, env, "path_filestat_get", path_filestat_get::<Memory32>),
+        "path_filestat_set_times" => syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory32>),
+        "path_link" => syscall(&mut store, env, "path_link", path_link::<Memory32>),
+        "path_open" => syscall(&mut store, env, "path_open", path_open::<Memory32>),
+        "path_readlink" => syscall(&mut store, env, "path_readlink", path_readlink::<Memory32>),
+        "path_remove_directory" => syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory32>),
+        "path_rename" => syscall(&mut store, env, "path_rename", path_rename::<Memory32>),
+        "path_symlink" => syscall(&mut store, env, "path_symlink", path_symlink::<Memory32>),
+        "path_unlink_file" => syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory32>),
+        "poll_oneoff" => syscall(&mut store, env, "poll_oneoff", poll_oneoff::<Memory32>),
+        "proc_exit" => syscall(&mut store, env, "proc_exit", proc_exit::<Memory32>),
+        "proc_raise" => syscall(&mut store, env, "proc_raise", proc_raise),
+        "random_get" => syscall(&mut store, env, "random_get", random_get::<Memory32>),
+        "sched_yield" => syscall(&mut store, env, "sched_yield", sched_yield::<Memory32>),
+        "sock_accept" => syscall(&mut store, env, "sock_accept", sock_accept::<Memory32>),
+        "sock_recv" => syscall(&mut store, env, "sock_recv", sock_recv::<Memory32>),
+        "sock_send" => syscall(&mut store, env, "sock_send", sock_send::<Memory32>),
+        "sock_shutdown" => syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "thread-spawn" => syscall(&mut store, env, "thread-spawn", thread_spawn::<Memory32>),
     };
     namespace
 }
@@ -494,121 +533,144 @@ fn wasi_snapshot_preview1_exports(
 fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory32>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory32>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory32>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory32>),
-        "clock_time_set" => Function::new_typed_with_env(&mut store, env, clock_time_set::<Memory32>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory32>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory32>),
-        "epoll_create" => Function::new_typed_with_env(&mut store, env, epoll_create::<Memory32>),
-        "epoll_ctl" => Function::new_typed_with_env(&mut store, env, epoll_ctl::<Memory32>),
-        "epoll_wait" => Function::new_typed_with_env(&mut store, env, epoll_wait::<Memory32>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory32>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, fd_filestat_get::<Memory32>),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory32>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory32>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory32>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory32>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory32>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory32>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_dup" => Function::new_typed_with_env(&mut store, env, fd_dup::<Memory32>),
-        "fd_event" => Function::new_typed_with_env(&mut store, env, fd_event::<Memory32>),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, fd_seek::<Memory32>),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory32>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory32>),
-        "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory32>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory32>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory32>),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory32>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory32>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory32>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory32>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory32>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory32>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory32>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory32>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory32>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory32>),
-        "proc_fork" => Function::new_typed_with_env(&mut store, env, proc_fork::<Memory32>),
-        "proc_join" => Function::new_typed_with_env(&mut store, env, proc_join::<Memory32>),
-        "proc_signal" => Function::new_typed_with_env(&mut store, env, proc_signal::<Memory32>),
-        "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory32>),
-        "proc_exec2" => Function::new_typed_with_env(&mut store, env, proc_exec2::<Memory32>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "proc_raise_interval" => Function::new_typed_with_env(&mut store, env, proc_raise_interval),
-        "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory32>),
-        "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory32>),
-        "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory32>),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory32>),
-        "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory32>),
-        "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory32>),
-        "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory32>),
-        "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory32>),
-        "callback_signal" => Function::new_typed_with_env(&mut store, env, callback_signal::<Memory32>),
-        "thread_spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory32>),
-        "thread_spawn_v2" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory32>),
-        "thread_sleep" => Function::new_typed_with_env(&mut store, env, thread_sleep::<Memory32>),
-        "thread_id" => Function::new_typed_with_env(&mut store, env, thread_id::<Memory32>),
-        "thread_signal" => Function::new_typed_with_env(&mut store, env, thread_signal),
-        "thread_join" => Function::new_typed_with_env(&mut store, env, thread_join::<Memory32>),
-        "thread_parallelism" => Function::new_typed_with_env(&mut store, env, thread_parallelism::<Memory32>),
-        "thread_exit" => Function::new_typed_with_env(&mut store, env, thread_exit),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory32>),
-        "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory32>),
-        "stack_restore" => Function::new_typed_with_env(&mut store, env, stack_restore::<Memory32>),
-        "futex_wait" => Function::new_typed_with_env(&mut store, env, futex_wait::<Memory32>),
-        "futex_wake" => Function::new_typed_with_env(&mut store, env, futex_wake::<Memory32>),
-        "futex_wake_all" => Function::new_typed_with_env(&mut store, env, futex_wake_all::<Memory32>),
-        "port_bridge" => Function::new_typed_with_env(&mut store, env, port_bridge::<Memory32>),
-        "port_unbridge" => Function::new_typed_with_env(&mut store, env, port_unbridge),
-        "port_dhcp_acquire" => Function::new_typed_with_env(&mut store, env, port_dhcp_acquire),
-        "port_addr_add" => Function::new_typed_with_env(&mut store, env, port_addr_add::<Memory32>),
-        "port_addr_remove" => Function::new_typed_with_env(&mut store, env, port_addr_remove::<Memory32>),
-        "port_addr_clear" => Function::new_typed_with_env(&mut store, env, port_addr_clear),
-        "port_addr_list" => Function::new_typed_with_env(&mut store, env, port_addr_list::<Memory32>),
-        "port_mac" => Function::new_typed_with_env(&mut store, env, port_mac::<Memory32>),
-        "port_gateway_set" => Function::new_typed_with_env(&mut store, env, port_gateway_set::<Memory32>),
-        "port_route_add" => Function::new_typed_with_env(&mut store, env, port_route_add::<Memory32>),
-        "port_route_remove" => Function::new_typed_with_env(&mut store, env, port_route_remove::<Memory32>),
-        "port_route_clear" => Function::new_typed_with_env(&mut store, env, port_route_clear),
-        "port_route_list" => Function::new_typed_with_env(&mut store, env, port_route_list::<Memory32>),
-        "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory32>),
-        "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory32>),
-        "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory32>),
-        "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory32>),
-        "sock_set_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_set_opt_flag),
-        "sock_get_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_get_opt_flag::<Memory32>),
-        "sock_set_opt_time" => Function::new_typed_with_env(&mut store, env, sock_set_opt_time::<Memory32>),
-        "sock_get_opt_time" => Function::new_typed_with_env(&mut store, env, sock_get_opt_time::<Memory32>),
-        "sock_set_opt_size" => Function::new_typed_with_env(&mut store, env, sock_set_opt_size),
-        "sock_get_opt_size" => Function::new_typed_with_env(&mut store, env, sock_get_opt_size::<Memory32>),
-        "sock_join_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v4::<Memory32>),
-        "sock_leave_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v4::<Memory32>),
-        "sock_join_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v6::<Memory32>),
-        "sock_leave_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v6::<Memory32>),
-        "sock_bind" => Function::new_typed_with_env(&mut store, env, sock_bind::<Memory32>),
-        "sock_listen" => Function::new_typed_with_env(&mut store, env, sock_listen::<Memory32>),
-        "sock_accept" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory32>),
-        "sock_accept_v2" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory32>),
-        "sock_connect" => Function::new_typed_with_env(&mut store, env, sock_connect::<Memory32>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory32>),
-        "sock_recv_from" => Function::new_typed_with_env(&mut store, env, sock_recv_from::<Memory32>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory32>),
-        "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory32>),
-        "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory32>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory32>),
+        "args_get" => syscall(&mut store, env, "args_get", args_get::<Memory32>),
+        "args_sizes_get" => syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory32>),
+        "clock_res_get" => syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory32>),
+        "clock_time_get" => syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory32>),
+        "clock_time_set" => syscall(&mut store, env, "clock_time_set", clock_time_set::<Memory32>),
+        "clock_nanosleep" => syscall(&mut store, env, "clock_nanosleep", clock_nanosleep::<Memory32>),
+        "environ_get" => syscall(&mut store, env, "environ_get", environ_get::<Memory32>),
+        "environ_sizes_get" => syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory32>),
+        "epoll_create" => syscall(&mut store, env, "epoll_create", epoll_create::<Memory32>),
+        "epoll_ctl" => syscall(&mut store, env, "epoll_ctl", epoll_ctl::<Memory32>),
+        "epoll_wait" => syscall(&mut store, env, "epoll_wait", epoll_wait::<Memory32>),
+        "fd_advise" => syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory32>),
+        "fd_fdstat_set_flags" => syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => syscall(&mut store, env, "fd_filestat_get", fd_filestat_get::<Memory32>),
+        "fd_filestat_set_size" => syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => syscall(&mut store, env, "fd_pread", fd_pread::<Memory32>),
+        "fd_prestat_get" => syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory32>),
+        "fd_prestat_dir_name" => syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory32>),
+        "fd_pwrite" => syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory32>),
+        "fd_read" => syscall(&mut store, env, "fd_read", fd_read::<Memory32>),
+        "fd_readdir" => syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory32>),
+        "fd_renumber" => syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_dup" => syscall(&mut store, env, "fd_dup", fd_dup::<Memory32>),
+        "fd_event" => syscall(&mut store, env, "fd_event", fd_event::<Memory32>),
+        "fd_seek" => syscall(&mut store, env, "fd_seek", fd_seek::<Memory32>),
+        "fd_sync" => syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => syscall(&mut store, env, "fd_tell", fd_tell::<Memory32>),
+        "fd_write" => syscall(&mut store, env, "fd_write", fd_write::<Memory32>),
+        "fd_pipe" => syscall(&mut store, env, "fd_pipe", fd_pipe::<Memory32>),
+        "fd_bytes_available" => syscall(&mut store, env, "fd_bytes_available", fd_bytes_available::<Memory32>),
+        "fd_close_range" => syscall(&mut store, env, "fd_close_range", fd_close_range),
+        "fd_lock_range" => syscall(&mut store, env, "fd_lock_range", fd_lock_range),
+        "fd_readahead" => syscall(&mut store, env, "fd_readahead", fd_readahead),
+        "fd_readdir_raw" => syscall(&mut store, env, "fd_readdir_raw", fd_readdir_raw::<Memory32>),
+        "fd_sync_batch" => syscall(&mut store, env, "fd_sync_batch", fd_sync_batch::<Memory32>),
+        "fd_unlock_range" => syscall(&mut store, env, "fd_unlock_range", fd_unlock_range),
+        "fs_statvfs" => syscall(&mut store, env, "fs_statvfs", fs_statvfs::<Memory32>),
+        "memory_protect" => syscall(&mut store, env, "memory_protect", memory_protect::<Memory32>),
+        "path_create_directory" => syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory32>),
+        "path_filestat_get" => syscall(&mut store, env, "path_filestat_get", path_filestat_get::<Memory32>),
+        "path_filestat_set_times" => syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory32>),
+        "path_link" => syscall(&mut store, env, "path_link", path_link::<Memory32>),
+        "path_open" => syscall(&mut store, env, "path_open", path_open::<Memory32>),
+        "path_readlink" => syscall(&mut store, env, "path_readlink", path_readlink::<Memory32>),
+        "path_remove_directory" => syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory32>),
+        "path_rename" => syscall(&mut store, env, "path_rename", path_rename::<Memory32>),
+        "path_symlink" => syscall(&mut store, env, "path_symlink", path_symlink::<Memory32>),
+        "path_unlink_file" => syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory32>),
+        "path_copy_tree" => syscall(&mut store, env, "path_copy_tree", path_copy_tree::<Memory32>),
+        "path_write_atomic" => syscall(&mut store, env, "path_write_atomic", path_write_atomic::<Memory32>),
+        "poll_oneoff" => syscall(&mut store, env, "poll_oneoff", poll_oneoff::<Memory32>),
+        "proc_exit" => syscall(&mut store, env, "proc_exit", proc_exit::<Memory32>),
+        "proc_fork" => syscall(&mut store, env, "proc_fork", proc_fork::<Memory32>),
+        "proc_join" => syscall(&mut store, env, "proc_join", proc_join::<Memory32>),
+        "proc_signal" => syscall(&mut store, env, "proc_signal", proc_signal::<Memory32>),
+        "proc_exec" => syscall(&mut store, env, "proc_exec", proc_exec::<Memory32>),
+        "proc_exec2" => syscall(&mut store, env, "proc_exec2", proc_exec2::<Memory32>),
+        "proc_exec3" => syscall(&mut store, env, "proc_exec3", proc_exec3::<Memory32>),
+        "proc_exec_host" => syscall(&mut store, env, "proc_exec_host", proc_exec_host::<Memory32>),
+        "proc_raise" => syscall(&mut store, env, "proc_raise", proc_raise),
+        "proc_raise_interval" => syscall(&mut store, env, "proc_raise_interval", proc_raise_interval),
+        "proc_spawn" => syscall(&mut store, env, "proc_spawn", proc_spawn::<Memory32>),
+        "proc_id" => syscall(&mut store, env, "proc_id", proc_id::<Memory32>),
+        "proc_parent" => syscall(&mut store, env, "proc_parent", proc_parent::<Memory32>),
+        "proc_gethostname" => syscall(&mut store, env, "proc_gethostname", proc_gethostname::<Memory32>),
+        "proc_getdomainname" => syscall(&mut store, env, "proc_getdomainname", proc_getdomainname::<Memory32>),
+        "proc_sysctl" => syscall(&mut store, env, "proc_sysctl", proc_sysctl::<Memory32>),
+        "proc_uptime" => syscall(&mut store, env, "proc_uptime", proc_uptime::<Memory32>),
+        "proc_getpriority" => syscall(&mut store, env, "proc_getpriority", proc_getpriority::<Memory32>),
+        "proc_setpriority" => syscall(&mut store, env, "proc_setpriority", proc_setpriority),
+        "random_get" => syscall(&mut store, env, "random_get", random_get::<Memory32>),
+        "tty_get" => syscall(&mut store, env, "tty_get", tty_get::<Memory32>),
+        "tty_set" => syscall(&mut store, env, "tty_set", tty_set::<Memory32>),
+        "getcwd" => syscall(&mut store, env, "getcwd", getcwd::<Memory32>),
+        "chdir" => syscall(&mut store, env, "chdir", chdir::<Memory32>),
+        "fchdir" => syscall(&mut store, env, "fchdir", fchdir),
+        "callback_signal" => syscall(&mut store, env, "callback_signal", callback_signal::<Memory32>),
+        "thread_spawn" => syscall(&mut store, env, "thread_spawn", thread_spawn_v2::<Memory32>),
+        "thread_spawn_v2" => syscall(&mut store, env, "thread_spawn_v2", thread_spawn_v2::<Memory32>),
+        "thread_sleep" => syscall(&mut store, env, "thread_sleep", thread_sleep::<Memory32>),
+        "thread_id" => syscall(&mut store, env, "thread_id", thread_id::<Memory32>),
+        "thread_signal" => syscall(&mut store, env, "thread_signal", thread_signal),
+        "thread_sigmask" => syscall(&mut store, env, "thread_sigmask", thread_sigmask::<Memory32>),
+        "thread_join" => syscall(&mut store, env, "thread_join", thread_join::<Memory32>),
+        "thread_parallelism" => syscall(&mut store, env, "thread_parallelism", thread_parallelism::<Memory32>),
+        "thread_exit" => syscall(&mut store, env, "thread_exit", thread_exit),
+        "sched_yield" => syscall(&mut store, env, "sched_yield", sched_yield::<Memory32>),
+        "stack_checkpoint" => syscall(&mut store, env, "stack_checkpoint", stack_checkpoint::<Memory32>),
+        "stack_restore" => syscall(&mut store, env, "stack_restore", stack_restore::<Memory32>),
+        "futex_wait" => syscall(&mut store, env, "futex_wait", futex_wait::<Memory32>),
+        "futex_wake" => syscall(&mut store, env, "futex_wake", futex_wake::<Memory32>),
+        "futex_wake_all" => syscall(&mut store, env, "futex_wake_all", futex_wake_all::<Memory32>),
+        "port_bridge" => syscall(&mut store, env, "port_bridge", port_bridge::<Memory32>),
+        "port_unbridge" => syscall(&mut store, env, "port_unbridge", port_unbridge),
+        "port_dhcp_acquire" => syscall(&mut store, env, "port_dhcp_acquire", port_dhcp_acquire),
+        "port_addr_add" => syscall(&mut store, env, "port_addr_add", port_addr_add::<Memory32>),
+        "port_addr_remove" => syscall(&mut store, env, "port_addr_remove", port_addr_remove::<Memory32>),
+        "port_addr_clear" => syscall(&mut store, env, "port_addr_clear", port_addr_clear),
+        "port_addr_list" => syscall(&mut store, env, "port_addr_list", port_addr_list::<Memory32>),
+        "port_mac" => syscall(&mut store, env, "port_mac", port_mac::<Memory32>),
+        "port_gateway_set" => syscall(&mut store, env, "port_gateway_set", port_gateway_set::<Memory32>),
+        "port_route_add" => syscall(&mut store, env, "port_route_add", port_route_add::<Memory32>),
+        "port_route_remove" => syscall(&mut store, env, "port_route_remove", port_route_remove::<Memory32>),
+        "port_route_clear" => syscall(&mut store, env, "port_route_clear", port_route_clear),
+        "port_route_list" => syscall(&mut store, env, "port_route_list", port_route_list::<Memory32>),
+        "sock_status" => syscall(&mut store, env, "sock_status", sock_status::<Memory32>),
+        "sock_addr_local" => syscall(&mut store, env, "sock_addr_local", sock_addr_local::<Memory32>),
+        "sock_addr_peer" => syscall(&mut store, env, "sock_addr_peer", sock_addr_peer::<Memory32>),
+        "sock_open" => syscall(&mut store, env, "sock_open", sock_open::<Memory32>),
+        "sock_set_opt_flag" => syscall(&mut store, env, "sock_set_opt_flag", sock_set_opt_flag),
+        "sock_get_opt_flag" => syscall(&mut store, env, "sock_get_opt_flag", sock_get_opt_flag::<Memory32>),
+        "sock_set_opt_time" => syscall(&mut store, env, "sock_set_opt_time", sock_set_opt_time::<Memory32>),
+        "sock_get_opt_time" => syscall(&mut store, env, "sock_get_opt_time", sock_get_opt_time::<Memory32>),
+        "sock_set_opt_size" => syscall(&mut store, env, "sock_set_opt_size", sock_set_opt_size),
+        "sock_get_opt_size" => syscall(&mut store, env, "sock_get_opt_size", sock_get_opt_size::<Memory32>),
+        "sock_join_multicast_v4" => syscall(&mut store, env, "sock_join_multicast_v4", sock_join_multicast_v4::<Memory32>),
+        "sock_leave_multicast_v4" => syscall(&mut store, env, "sock_leave_multicast_v4", sock_leave_multicast_v4::<Memory32>),
+        "sock_join_multicast_v6" => syscall(&mut store, env, "sock_join_multicast_v6", sock_join_multicast_v6::<Memory32>),
+        "sock_leave_multicast_v6" => syscall(&mut store, env, "sock_leave_multicast_v6", sock_leave_multicast_v6::<Memory32>),
+        "sock_bind" => syscall(&mut store, env, "sock_bind", sock_bind::<Memory32>),
+        "sock_listen" => syscall(&mut store, env, "sock_listen", sock_listen::<Memory32>),
+        "sock_accept" => syscall(&mut store, env, "sock_accept", sock_accept_v2::<Memory32>),
+        "sock_accept_v2" => syscall(&mut store, env, "sock_accept_v2", sock_accept_v2::<Memory32>),
+        "sock_connect" => syscall(&mut store, env, "sock_connect", sock_connect::<Memory32>),
+        "sock_connect_with_data" => syscall(&mut store, env, "sock_connect_with_data", sock_connect_with_data::<Memory32>),
+        "sock_recv" => syscall(&mut store, env, "sock_recv", sock_recv::<Memory32>),
+        "sock_recv_from" => syscall(&mut store, env, "sock_recv_from", sock_recv_from::<Memory32>),
+        "sock_send" => syscall(&mut store, env, "sock_send", sock_send::<Memory32>),
+        "sock_send_to" => syscall(&mut store, env, "sock_send_to", sock_send_to::<Memory32>),
+        "sock_send_file" => syscall(&mut store, env, "sock_send_file", sock_send_file::<Memory32>),
+        "sock_shutdown" => syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "resolve" => syscall(&mut store, env, "resolve", resolve::<Memory32>),
     };
     namespace
 }
@@ -616,121 +678,144 @@ fn wasix_exports_32(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>)
 fn wasix_exports_64(mut store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>) -> Exports {
     use syscalls::*;
     let namespace = namespace! {
-        "args_get" => Function::new_typed_with_env(&mut store, env, args_get::<Memory64>),
-        "args_sizes_get" => Function::new_typed_with_env(&mut store, env, args_sizes_get::<Memory64>),
-        "clock_res_get" => Function::new_typed_with_env(&mut store, env, clock_res_get::<Memory64>),
-        "clock_time_get" => Function::new_typed_with_env(&mut store, env, clock_time_get::<Memory64>),
-        "clock_time_set" => Function::new_typed_with_env(&mut store, env, clock_time_set::<Memory64>),
-        "environ_get" => Function::new_typed_with_env(&mut store, env, environ_get::<Memory64>),
-        "environ_sizes_get" => Function::new_typed_with_env(&mut store, env, environ_sizes_get::<Memory64>),
-        "epoll_create" => Function::new_typed_with_env(&mut store, env, epoll_create::<Memory64>),
-        "epoll_ctl" => Function::new_typed_with_env(&mut store, env, epoll_ctl::<Memory64>),
-        "epoll_wait" => Function::new_typed_with_env(&mut store, env, epoll_wait::<Memory64>),
-        "fd_advise" => Function::new_typed_with_env(&mut store, env, fd_advise),
-        "fd_allocate" => Function::new_typed_with_env(&mut store, env, fd_allocate),
-        "fd_close" => Function::new_typed_with_env(&mut store, env, fd_close),
-        "fd_datasync" => Function::new_typed_with_env(&mut store, env, fd_datasync),
-        "fd_fdstat_get" => Function::new_typed_with_env(&mut store, env, fd_fdstat_get::<Memory64>),
-        "fd_fdstat_set_flags" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_flags),
-        "fd_fdstat_set_rights" => Function::new_typed_with_env(&mut store, env, fd_fdstat_set_rights),
-        "fd_filestat_get" => Function::new_typed_with_env(&mut store, env, fd_filestat_get::<Memory64>),
-        "fd_filestat_set_size" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_size),
-        "fd_filestat_set_times" => Function::new_typed_with_env(&mut store, env, fd_filestat_set_times),
-        "fd_pread" => Function::new_typed_with_env(&mut store, env, fd_pread::<Memory64>),
-        "fd_prestat_get" => Function::new_typed_with_env(&mut store, env, fd_prestat_get::<Memory64>),
-        "fd_prestat_dir_name" => Function::new_typed_with_env(&mut store, env, fd_prestat_dir_name::<Memory64>),
-        "fd_pwrite" => Function::new_typed_with_env(&mut store, env, fd_pwrite::<Memory64>),
-        "fd_read" => Function::new_typed_with_env(&mut store, env, fd_read::<Memory64>),
-        "fd_readdir" => Function::new_typed_with_env(&mut store, env, fd_readdir::<Memory64>),
-        "fd_renumber" => Function::new_typed_with_env(&mut store, env, fd_renumber),
-        "fd_dup" => Function::new_typed_with_env(&mut store, env, fd_dup::<Memory64>),
-        "fd_event" => Function::new_typed_with_env(&mut store, env, fd_event::<Memory64>),
-        "fd_seek" => Function::new_typed_with_env(&mut store, env, fd_seek::<Memory64>),
-        "fd_sync" => Function::new_typed_with_env(&mut store, env, fd_sync),
-        "fd_tell" => Function::new_typed_with_env(&mut store, env, fd_tell::<Memory64>),
-        "fd_write" => Function::new_typed_with_env(&mut store, env, fd_write::<Memory64>),
-        "fd_pipe" => Function::new_typed_with_env(&mut store, env, fd_pipe::<Memory64>),
-        "path_create_directory" => Function::new_typed_with_env(&mut store, env, path_create_directory::<Memory64>),
-        "path_filestat_get" => Function::new_typed_with_env(&mut store, env, path_filestat_get::<Memory64>),
-        "path_filestat_set_times" => Function::new_typed_with_env(&mut store, env, path_filestat_set_times::<Memory64>),
-        "path_link" => Function::new_typed_with_env(&mut store, env, path_link::<Memory64>),
-        "path_open" => Function::new_typed_with_env(&mut store, env, path_open::<Memory64>),
-        "path_readlink" => Function::new_typed_with_env(&mut store, env, path_readlink::<Memory64>),
-        "path_remove_directory" => Function::new_typed_with_env(&mut store, env, path_remove_directory::<Memory64>),
-        "path_rename" => Function::new_typed_with_env(&mut store, env, path_rename::<Memory64>),
-        "path_symlink" => Function::new_typed_with_env(&mut store, env, path_symlink::<Memory64>),
-        "path_unlink_file" => Function::new_typed_with_env(&mut store, env, path_unlink_file::<Memory64>),
-        "poll_oneoff" => Function::new_typed_with_env(&mut store, env, poll_oneoff::<Memory64>),
-        "proc_exit" => Function::new_typed_with_env(&mut store, env, proc_exit::<Memory64>),
-        "proc_fork" => Function::new_typed_with_env(&mut store, env, proc_fork::<Memory64>),
-        "proc_join" => Function::new_typed_with_env(&mut store, env, proc_join::<Memory64>),
-        "proc_signal" => Function::new_typed_with_env(&mut store, env, proc_signal::<Memory64>),
-        "proc_exec" => Function::new_typed_with_env(&mut store, env, proc_exec::<Memory64>),
-        "proc_exec2" => Function::new_typed_with_env(&mut store, env, proc_exec2::<Memory64>),
-        "proc_raise" => Function::new_typed_with_env(&mut store, env, proc_raise),
-        "proc_raise_interval" => Function::new_typed_with_env(&mut store, env, proc_raise_interval),
-        "proc_spawn" => Function::new_typed_with_env(&mut store, env, proc_spawn::<Memory64>),
-        "proc_id" => Function::new_typed_with_env(&mut store, env, proc_id::<Memory64>),
-        "proc_parent" => Function::new_typed_with_env(&mut store, env, proc_parent::<Memory64>),
-        "random_get" => Function::new_typed_with_env(&mut store, env, random_get::<Memory64>),
-        "tty_get" => Function::new_typed_with_env(&mut store, env, tty_get::<Memory64>),
-        "tty_set" => Function::new_typed_with_env(&mut store, env, tty_set::<Memory64>),
-        "getcwd" => Function::new_typed_with_env(&mut store, env, getcwd::<Memory64>),
-        "chdir" => Function::new_typed_with_env(&mut store, env, chdir::<Memory64>),
-        "callback_signal" => Function::new_typed_with_env(&mut store, env, callback_signal::<Memory64>),
-        "thread_spawn" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory64>),
-        "thread_spawn_v2" => Function::new_typed_with_env(&mut store, env, thread_spawn_v2::<Memory64>),
-        "thread_sleep" => Function::new_typed_with_env(&mut store, env, thread_sleep::<Memory64>),
-        "thread_id" => Function::new_typed_with_env(&mut store, env, thread_id::<Memory64>),
-        "thread_signal" => Function::new_typed_with_env(&mut store, env, thread_signal),
-        "thread_join" => Function::new_typed_with_env(&mut store, env, thread_join::<Memory64>),
-        "thread_parallelism" => Function::new_typed_with_env(&mut store, env, thread_parallelism::<Memory64>),
-        "thread_exit" => Function::new_typed_with_env(&mut store, env, thread_exit),
-        "sched_yield" => Function::new_typed_with_env(&mut store, env, sched_yield::<Memory64>),
-        "stack_checkpoint" => Function::new_typed_with_env(&mut store, env, stack_checkpoint::<Memory64>),
-        "stack_restore" => Function::new_typed_with_env(&mut store, env, stack_restore::<Memory64>),
-        "futex_wait" => Function::new_typed_with_env(&mut store, env, futex_wait::<Memory64>),
-        "futex_wake" => Function::new_typed_with_env(&mut store, env, futex_wake::<Memory64>),
-        "futex_wake_all" => Function::new_typed_with_env(&mut store, env, futex_wake_all::<Memory64>),
-        "port_bridge" => Function::new_typed_with_env(&mut store, env, port_bridge::<Memory64>),
-        "port_unbridge" => Function::new_typed_with_env(&mut store, env, port_unbridge),
-        "port_dhcp_acquire" => Function::new_typed_with_env(&mut store, env, port_dhcp_acquire),
-        "port_addr_add" => Function::new_typed_with_env(&mut store, env, port_addr_add::<Memory64>),
-        "port_addr_remove" => Function::new_typed_with_env(&mut store, env, port_addr_remove::<Memory64>),
-        "port_addr_clear" => Function::new_typed_with_env(&mut store, env, port_addr_clear),
-        "port_addr_list" => Function::new_typed_with_env(&mut store, env, port_addr_list::<Memory64>),
-        "port_mac" => Function::new_typed_with_env(&mut store, env, port_mac::<Memory64>),
-        "port_gateway_set" => Function::new_typed_with_env(&mut store, env, port_gateway_set::<Memory64>),
-        "port_route_add" => Function::new_typed_with_env(&mut store, env, port_route_add::<Memory64>),
-        "port_route_remove" => Function::new_typed_with_env(&mut store, env, port_route_remove::<Memory64>),
-        "port_route_clear" => Function::new_typed_with_env(&mut store, env, port_route_clear),
-        "port_route_list" => Function::new_typed_with_env(&mut store, env, port_route_list::<Memory64>),
-        "sock_status" => Function::new_typed_with_env(&mut store, env, sock_status::<Memory64>),
-        "sock_addr_local" => Function::new_typed_with_env(&mut store, env, sock_addr_local::<Memory64>),
-        "sock_addr_peer" => Function::new_typed_with_env(&mut store, env, sock_addr_peer::<Memory64>),
-        "sock_open" => Function::new_typed_with_env(&mut store, env, sock_open::<Memory64>),
-        "sock_set_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_set_opt_flag),
-        "sock_get_opt_flag" => Function::new_typed_with_env(&mut store, env, sock_get_opt_flag::<Memory64>),
-        "sock_set_opt_time" => Function::new_typed_with_env(&mut store, env, sock_set_opt_time::<Memory64>),
-        "sock_get_opt_time" => Function::new_typed_with_env(&mut store, env, sock_get_opt_time::<Memory64>),
-        "sock_set_opt_size" => Function::new_typed_with_env(&mut store, env, sock_set_opt_size),
-        "sock_get_opt_size" => Function::new_typed_with_env(&mut store, env, sock_get_opt_size::<Memory64>),
-        "sock_join_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v4::<Memory64>),
-        "sock_leave_multicast_v4" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v4::<Memory64>),
-        "sock_join_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_join_multicast_v6::<Memory64>),
-        "sock_leave_multicast_v6" => Function::new_typed_with_env(&mut store, env, sock_leave_multicast_v6::<Memory64>),
-        "sock_bind" => Function::new_typed_with_env(&mut store, env, sock_bind::<Memory64>),
-        "sock_listen" => Function::new_typed_with_env(&mut store, env, sock_listen::<Memory64>),
-        "sock_accept" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory64>),
-        "sock_accept_v2" => Function::new_typed_with_env(&mut store, env, sock_accept_v2::<Memory64>),
-        "sock_connect" => Function::new_typed_with_env(&mut store, env, sock_connect::<Memory64>),
-        "sock_recv" => Function::new_typed_with_env(&mut store, env, sock_recv::<Memory64>),
-        "sock_recv_from" => Function::new_typed_with_env(&mut store, env, sock_recv_from::<Memory64>),
-        "sock_send" => Function::new_typed_with_env(&mut store, env, sock_send::<Memory64>),
-        "sock_send_to" => Function::new_typed_with_env(&mut store, env, sock_send_to::<Memory64>),
-        "sock_send_file" => Function::new_typed_with_env(&mut store, env, sock_send_file::<Memory64>),
-        "sock_shutdown" => Function::new_typed_with_env(&mut store, env, sock_shutdown),
-        "resolve" => Function::new_typed_with_env(&mut store, env, resolve::<Memory64>),
+        "args_get" => syscall(&mut store, env, "args_get", args_get::<Memory64>),
+        "args_sizes_get" => syscall(&mut store, env, "args_sizes_get", args_sizes_get::<Memory64>),
+        "clock_res_get" => syscall(&mut store, env, "clock_res_get", clock_res_get::<Memory64>),
+        "clock_time_get" => syscall(&mut store, env, "clock_time_get", clock_time_get::<Memory64>),
+        "clock_time_set" => syscall(&mut store, env, "clock_time_set", clock_time_set::<Memory64>),
+        "clock_nanosleep" => syscall(&mut store, env, "clock_nanosleep", clock_nanosleep::<Memory64>),
+        "environ_get" => syscall(&mut store, env, "environ_get", environ_get::<Memory64>),
+        "environ_sizes_get" => syscall(&mut store, env, "environ_sizes_get", environ_sizes_get::<Memory64>),
+        "epoll_create" => syscall(&mut store, env, "epoll_create", epoll_create::<Memory64>),
+        "epoll_ctl" => syscall(&mut store, env, "epoll_ctl", epoll_ctl::<Memory64>),
+        "epoll_wait" => syscall(&mut store, env, "epoll_wait", epoll_wait::<Memory64>),
+        "fd_advise" => syscall(&mut store, env, "fd_advise", fd_advise),
+        "fd_allocate" => syscall(&mut store, env, "fd_allocate", fd_allocate),
+        "fd_close" => syscall(&mut store, env, "fd_close", fd_close),
+        "fd_datasync" => syscall(&mut store, env, "fd_datasync", fd_datasync),
+        "fd_fdstat_get" => syscall(&mut store, env, "fd_fdstat_get", fd_fdstat_get::<Memory64>),
+        "fd_fdstat_set_flags" => syscall(&mut store, env, "fd_fdstat_set_flags", fd_fdstat_set_flags),
+        "fd_fdstat_set_rights" => syscall(&mut store, env, "fd_fdstat_set_rights", fd_fdstat_set_rights),
+        "fd_filestat_get" => syscall(&mut store, env, "fd_filestat_get", fd_filestat_get::<Memory64>),
+        "fd_filestat_set_size" => syscall(&mut store, env, "fd_filestat_set_size", fd_filestat_set_size),
+        "fd_filestat_set_times" => syscall(&mut store, env, "fd_filestat_set_times", fd_filestat_set_times),
+        "fd_pread" => syscall(&mut store, env, "fd_pread", fd_pread::<Memory64>),
+        "fd_prestat_get" => syscall(&mut store, env, "fd_prestat_get", fd_prestat_get::<Memory64>),
+        "fd_prestat_dir_name" => syscall(&mut store, env, "fd_prestat_dir_name", fd_prestat_dir_name::<Memory64>),
+        "fd_pwrite" => syscall(&mut store, env, "fd_pwrite", fd_pwrite::<Memory64>),
+        "fd_read" => syscall(&mut store, env, "fd_read", fd_read::<Memory64>),
+        "fd_readdir" => syscall(&mut store, env, "fd_readdir", fd_readdir::<Memory64>),
+        "fd_renumber" => syscall(&mut store, env, "fd_renumber", fd_renumber),
+        "fd_dup" => syscall(&mut store, env, "fd_dup", fd_dup::<Memory64>),
+        "fd_event" => syscall(&mut store, env, "fd_event", fd_event::<Memory64>),
+        "fd_seek" => syscall(&mut store, env, "fd_seek", fd_seek::<Memory64>),
+        "fd_sync" => syscall(&mut store, env, "fd_sync", fd_sync),
+        "fd_tell" => syscall(&mut store, env, "fd_tell", fd_tell::<Memory64>),
+        "fd_write" => syscall(&mut store, env, "fd_write", fd_write::<Memory64>),
+        "fd_pipe" => syscall(&mut store, env, "fd_pipe", fd_pipe::<Memory64>),
+        "fd_bytes_available" => syscall(&mut store, env, "fd_bytes_available", fd_bytes_available::<Memory64>),
+        "fd_close_range" => syscall(&mut store, env, "fd_close_range", fd_close_range),
+        "fd_lock_range" => syscall(&mut store, env, "fd_lock_range", fd_lock_range),
+        "fd_readahead" => syscall(&mut store, env, "fd_readahead", fd_readahead),
+        "fd_readdir_raw" => syscall(&mut store, env, "fd_readdir_raw", fd_readdir_raw::<Memory64>),
+        "fd_sync_batch" => syscall(&mut store, env, "fd_sync_batch", fd_sync_batch::<Memory64>),
+        "fd_unlock_range" => syscall(&mut store, env, "fd_unlock_range", fd_unlock_range),
+        "fs_statvfs" => syscall(&mut store, env, "fs_statvfs", fs_statvfs::<Memory64>),
+        "memory_protect" => syscall(&mut store, env, "memory_protect", memory_protect::<Memory64>),
+        "path_create_directory" => syscall(&mut store, env, "path_create_directory", path_create_directory::<Memory64>),
+        "path_filestat_get" => syscall(&mut store, env, "path_filestat_get", path_filestat_get::<Memory64>),
+        "path_filestat_set_times" => syscall(&mut store, env, "path_filestat_set_times", path_filestat_set_times::<Memory64>),
+        "path_link" => syscall(&mut store, env, "path_link", path_link::<Memory64>),
+        "path_open" => syscall(&mut store, env, "path_open", path_open::<Memory64>),
+        "path_readlink" => syscall(&mut store, env, "path_readlink", path_readlink::<Memory64>),
+        "path_remove_directory" => syscall(&mut store, env, "path_remove_directory", path_remove_directory::<Memory64>),
+        "path_rename" => syscall(&mut store, env, "path_rename", path_rename::<Memory64>),
+        "path_symlink" => syscall(&mut store, env, "path_symlink", path_symlink::<Memory64>),
+        "path_unlink_file" => syscall(&mut store, env, "path_unlink_file", path_unlink_file::<Memory64>),
+        "path_copy_tree" => syscall(&mut store, env, "path_copy_tree", path_copy_tree::<Memory64>),
+        "path_write_atomic" => syscall(&mut store, env, "path_write_atomic", path_write_atomic::<Memory64>),
+        "poll_oneoff" => syscall(&mut store, env, "poll_oneoff", poll_oneoff::<Memory64>),
+        "proc_exit" => syscall(&mut store, env, "proc_exit", proc_exit::<Memory64>),
+        "proc_fork" => syscall(&mut store, env, "proc_fork", proc_fork::<Memory64>),
+        "proc_join" => syscall(&mut store, env, "proc_join", proc_join::<Memory64>),
+        "proc_signal" => syscall(&mut store, env, "proc_signal", proc_signal::<Memory64>),
+        "proc_exec" => syscall(&mut store, env, "proc_exec", proc_exec::<Memory64>),
+        "proc_exec2" => syscall(&mut store, env, "proc_exec2", proc_exec2::<Memory64>),
+        "proc_exec3" => syscall(&mut store, env, "proc_exec3", proc_exec3::<Memory64>),
+        "proc_exec_host" => syscall(&mut store, env, "proc_exec_host", proc_exec_host::<Memory64>),
+        "proc_raise" => syscall(&mut store, env, "proc_raise", proc_raise),
+        "proc_raise_interval" => syscall(&mut store, env, "proc_raise_interval", proc_raise_interval),
+        "proc_spawn" => syscall(&mut store, env, "proc_spawn", proc_spawn::<Memory64>),
+        "proc_id" => syscall(&mut store, env, "proc_id", proc_id::<Memory64>),
+        "proc_parent" => syscall(&mut store, env, "proc_parent", proc_parent::<Memory64>),
+        "proc_gethostname" => syscall(&mut store, env, "proc_gethostname", proc_gethostname::<Memory64>),
+        "proc_getdomainname" => syscall(&mut store, env, "proc_getdomainname", proc_getdomainname::<Memory64>),
+        "proc_sysctl" => syscall(&mut store, env, "proc_sysctl", proc_sysctl::<Memory64>),
+        "proc_uptime" => syscall(&mut store, env, "proc_uptime", proc_uptime::<Memory64>),
+        "proc_getpriority" => syscall(&mut store, env, "proc_getpriority", proc_getpriority::<Memory64>),
+        "proc_setpriority" => syscall(&mut store, env, "proc_setpriority", proc_setpriority),
+        "random_get" => syscall(&mut store, env, "random_get", random_get::<Memory64>),
+        "tty_get" => syscall(&mut store, env, "tty_get", tty_get::<Memory64>),
+        "tty_set" => syscall(&mut store, env, "tty_set", tty_set::<Memory64>),
+        "getcwd" => syscall(&mut store, env, "getcwd", getcwd::<Memory64>),
+        "chdir" => syscall(&mut store, env, "chdir", chdir::<Memory64>),
+        "fchdir" => syscall(&mut store, env, "fchdir", fchdir),
+        "callback_signal" => syscall(&mut store, env, "callback_signal", callback_signal::<Memory64>),
+        "thread_spawn" => syscall(&mut store, env, "thread_spawn", thread_spawn_v2::<Memory64>),
+        "thread_spawn_v2" => syscall(&mut store, env, "thread_spawn_v2", thread_spawn_v2::<Memory64>),
+        "thread_sleep" => syscall(&mut store, env, "thread_sleep", thread_sleep::<Memory64>),
+        "thread_id" => syscall(&mut store, env, "thread_id", thread_id::<Memory64>),
+        "thread_signal" => syscall(&mut store, env, "thread_signal", thread_signal),
+        "thread_sigmask" => syscall(&mut store, env, "thread_sigmask", thread_sigmask::<Memory64>),
+        "thread_join" => syscall(&mut store, env, "thread_join", thread_join::<Memory64>),
+        "thread_parallelism" => syscall(&mut store, env, "thread_parallelism", thread_parallelism::<Memory64>),
+        "thread_exit" => syscall(&mut store, env, "thread_exit", thread_exit),
+        "sched_yield" => syscall(&mut store, env, "sched_yield", sched_yield::<Memory64>),
+        "stack_checkpoint" => syscall(&mut store, env, "stack_checkpoint", stack_checkpoint::<Memory64>),
+        "stack_restore" => syscall(&mut store, env, "stack_restore", stack_restore::<Memory64>),
+        "futex_wait" => syscall(&mut store, env, "futex_wait", futex_wait::<Memory64>),
+        "futex_wake" => syscall(&mut store, env, "futex_wake", futex_wake::<Memory64>),
+        "futex_wake_all" => syscall(&mut store, env, "futex_wake_all", futex_wake_all::<Memory64>),
+        "port_bridge" => syscall(&mut store, env, "port_bridge", port_bridge::<Memory64>),
+        "port_unbridge" => syscall(&mut store, env, "port_unbridge", port_unbridge),
+        "port_dhcp_acquire" => syscall(&mut store, env, "port_dhcp_acquire", port_dhcp_acquire),
+        "port_addr_add" => syscall(&mut store, env, "port_addr_add", port_addr_add::<Memory64>),
+        "port_addr_remove" => syscall(&mut store, env, "port_addr_remove", port_addr_remove::<Memory64>),
+        "port_addr_clear" => syscall(&mut store, env, "port_addr_clear", port_addr_clear),
+        "port_addr_list" => syscall(&mut store, env, "port_addr_list", port_addr_list::<Memory64>),
+        "port_mac" => syscall(&mut store, env, "port_mac", port_mac::<Memory64>),
+        "port_gateway_set" => syscall(&mut store, env, "port_gateway_set", port_gateway_set::<Memory64>),
+        "port_route_add" => syscall(&mut store, env, "port_route_add", port_route_add::<Memory64>),
+        "port_route_remove" => syscall(&mut store, env, "port_route_remove", port_route_remove::<Memory64>),
+        "port_route_clear" => syscall(&mut store, env, "port_route_clear", port_route_clear),
+        "port_route_list" => syscall(&mut store, env, "port_route_list", port_route_list::<Memory64>),
+        "sock_status" => syscall(&mut store, env, "sock_status", sock_status::<Memory64>),
+        "sock_addr_local" => syscall(&mut store, env, "sock_addr_local", sock_addr_local::<Memory64>),
+        "sock_addr_peer" => syscall(&mut store, env, "sock_addr_peer", sock_addr_peer::<Memory64>),
+        "sock_open" => syscall(&mut store, env, "sock_open", sock_open::<Memory64>),
+        "sock_set_opt_flag" => syscall(&mut store, env, "sock_set_opt_flag", sock_set_opt_flag),
+        "sock_get_opt_flag" => syscall(&mut store, env, "sock_get_opt_flag", sock_get_opt_flag::<Memory64>),
+        "sock_set_opt_time" => syscall(&mut store, env, "sock_set_opt_time", sock_set_opt_time::<Memory64>),
+        "sock_get_opt_time" => syscall(&mut store, env, "sock_get_opt_time", sock_get_opt_time::<Memory64>),
+        "sock_set_opt_size" => syscall(&mut store, env, "sock_set_opt_size", sock_set_opt_size),
+        "sock_get_opt_size" => syscall(&mut store, env, "sock_get_opt_size", sock_get_opt_size::<Memory64>),
+        "sock_join_multicast_v4" => syscall(&mut store, env, "sock_join_multicast_v4", sock_join_multicast_v4::<Memory64>),
+        "sock_leave_multicast_v4" => syscall(&mut store, env, "sock_leave_multicast_v4", sock_leave_multicast_v4::<Memory64>),
+        "sock_join_multicast_v6" => syscall(&mut store, env, "sock_join_multicast_v6", sock_join_multicast_v6::<Memory64>),
+        "sock_leave_multicast_v6" => syscall(&mut store, env, "sock_leave_multicast_v6", sock_leave_multicast_v6::<Memory64>),
+        "sock_bind" => syscall(&mut store, env, "sock_bind", sock_bind::<Memory64>),
+        "sock_listen" => syscall(&mut store, env, "sock_listen", sock_listen::<Memory64>),
+        "sock_accept" => syscall(&mut store, env, "sock_accept", sock_accept_v2::<Memory64>),
+        "sock_accept_v2" => syscall(&mut store, env, "sock_accept_v2", sock_accept_v2::<Memory64>),
+        "sock_connect" => syscall(&mut store, env, "sock_connect", sock_connect::<Memory64>),
+        "sock_connect_with_data" => syscall(&mut store, env, "sock_connect_with_data", sock_connect_with_data::<Memory64>),
+        "sock_recv" => syscall(&mut store, env, "sock_recv", sock_recv::<Memory64>),
+        "sock_recv_from" => syscall(&mut store, env, "sock_recv_from", sock_recv_from::<Memory64>),
+        "sock_send" => syscall(&mut store, env, "sock_send", sock_send::<Memory64>),
+        "sock_send_to" => syscall(&mut store, env, "sock_send_to", sock_send_to::<Memory64>),
+        "sock_send_file" => syscall(&mut store, env, "sock_send_file", sock_send_file::<Memory64>),
+        "sock_shutdown" => syscall(&mut store, env, "sock_shutdown", sock_shutdown),
+        "resolve" => syscall(&mut store, env, "resolve", resolve::<Memory64>),
     };
     namespace
 }
@@ -756,11 +841,21 @@ fn import_object_for_all_wasi_versions(
     store: &mut impl AsStoreMut,
     env: &FunctionEnv<WasiEnv>,
 ) -> (Imports, ModuleInitializer) {
-    let exports_wasi_generic = wasi_exports_generic(store, env);
-    let exports_wasi_unstable = wasi_unstable_exports(store, env);
-    let exports_wasi_snapshot_preview1 = wasi_snapshot_preview1_exports(store, env);
-    let exports_wasix_32v1 = wasix_exports_32(store, env);
-    let exports_wasix_64v1 = wasix_exports_64(store, env);
+    let mut exports_wasi_generic = wasi_exports_generic(store, env);
+    let mut exports_wasi_unstable = wasi_unstable_exports(store, env);
+    let mut exports_wasi_snapshot_preview1 = wasi_snapshot_preview1_exports(store, env);
+    let mut exports_wasix_32v1 = wasix_exports_32(store, env);
+    let mut exports_wasix_64v1 = wasix_exports_64(store, env);
+
+    for exports in [
+        &mut exports_wasi_generic,
+        &mut exports_wasi_unstable,
+        &mut exports_wasi_snapshot_preview1,
+        &mut exports_wasix_32v1,
+        &mut exports_wasix_64v1,
+    ] {
+        deny_syscalls(store, env, exports);
+    }
 
     // Allowed due to JS feature flag complications.
     #[allow(unused_mut)]
@@ -777,6 +872,157 @@ fn import_object_for_all_wasi_versions(
     (imports, init)
 }
 
+/// Return value of a syscall from which the error that it returned to the
+/// guest can be read
+trait SyscallReturn {
+    type Rets: WasmTypeList;
+
+    fn error(&self) -> Option<Errno>;
+
+    fn into_rets(self) -> Result<Self::Rets, WasiError>;
+}
+
+impl SyscallReturn for Errno {
+    type Rets = Errno;
+
+    fn error(&self) -> Option<Errno> {
+        (*self != Errno::Success).then_some(*self)
+    }
+
+    fn into_rets(self) -> Result<Errno, WasiError> {
+        Ok(self)
+    }
+}
+
+impl SyscallReturn for Result<Errno, WasiError> {
+    type Rets = Errno;
+
+    fn error(&self) -> Option<Errno> {
+        self.as_ref().ok().and_then(|errno| errno.error())
+    }
+
+    fn into_rets(self) -> Result<Errno, WasiError> {
+        self
+    }
+}
+
+impl SyscallReturn for Result<(), WasiError> {
+    type Rets = ();
+
+    fn error(&self) -> Option<Errno> {
+        None
+    }
+
+    fn into_rets(self) -> Result<(), WasiError> {
+        self
+    }
+}
+
+/// `thread-spawn` of wasi-threads returns the thread ID or a negative value
+/// on failure, which does not carry an errno
+impl SyscallReturn for i32 {
+    type Rets = i32;
+
+    fn error(&self) -> Option<Errno> {
+        None
+    }
+
+    fn into_rets(self) -> Result<i32, WasiError> {
+        Ok(self)
+    }
+}
+
+/// Implementation of a syscall that can be imported by the guest, see
+/// [`syscall`]
+trait Syscall<Args, Ret> {
+    fn into_function(
+        self,
+        store: &mut impl AsStoreMut,
+        env: &FunctionEnv<WasiEnv>,
+        name: &'static str,
+    ) -> Function;
+}
+
+macro_rules! impl_syscall {
+    ($($arg:ident),*) => {
+        impl<Func, Ret, $($arg,)*> Syscall<($($arg,)*), Ret> for Func
+        where
+            Func: Fn(FunctionEnvMut<'_, WasiEnv>, $($arg),*) -> Ret + Send + Sync + 'static,
+            Ret: SyscallReturn,
+            $($arg: FromToNativeWasmType,)*
+        {
+            #[allow(non_snake_case)]
+            fn into_function(
+                self,
+                store: &mut impl AsStoreMut,
+                env: &FunctionEnv<WasiEnv>,
+                name: &'static str,
+            ) -> Function {
+                Function::new_typed_with_env(
+                    store,
+                    env,
+                    move |mut ctx: FunctionEnvMut<'_, WasiEnv>, $($arg: $arg),*| {
+                        let ret = self(ctx.as_mut(), $($arg),*);
+                        WasiEnv::syscall_returned(&ctx, name, ret.error());
+                        ret.into_rets()
+                    },
+                )
+            }
+        }
+    };
+}
+
+impl_syscall!();
+impl_syscall!(A1);
+impl_syscall!(A1, A2);
+impl_syscall!(A1, A2, A3);
+impl_syscall!(A1, A2, A3, A4);
+impl_syscall!(A1, A2, A3, A4, A5);
+impl_syscall!(A1, A2, A3, A4, A5, A6);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14);
+
+/// Creates the host function of a syscall, which reports the errors that
+/// the syscall returns and the growth of the memory that it notices as
+/// [`runtime::RuntimeEvent`]s
+fn syscall<Args, Ret>(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<WasiEnv>,
+    name: &'static str,
+    func: impl Syscall<Args, Ret>,
+) -> Function {
+    func.into_function(store, env, name)
+}
+
+/// Replaces the syscalls that are denied by the capabilities of the
+/// environment with stubs that return `Errno::Nosys` (negated for
+/// `thread-spawn`, whose non-negative results are thread IDs)
+fn deny_syscalls(store: &mut impl AsStoreMut, env: &FunctionEnv<WasiEnv>, exports: &mut Exports) {
+    let denied = env.as_ref(&*store).capabilities.denied_syscalls.clone();
+    for name in denied {
+        let ty = match exports.get_function(&name) {
+            Ok(function) => function.ty(&*store),
+            Err(_) => continue,
+        };
+        let results = match ty.results() {
+            [wasmer::Type::I32] if name == "thread-spawn" => {
+                vec![wasmer::Value::I32(-(Errno::Nosys as i32))]
+            }
+            [wasmer::Type::I32] => vec![wasmer::Value::I32(Errno::Nosys as i32)],
+            _ => Vec::new(),
+        };
+        let stub =
+            wasmer::Function::new_with_env(store, env, ty, move |_ctx, _args| Ok(results.clone()));
+        exports.insert(name, stub);
+    }
+}
+
 /// Combines a state generating function with the import list for legacy WASI
 fn generate_import_object_snapshot0(
     store: &mut impl AsStoreMut,
@@ -824,6 +1070,7 @@ fn mem_error_to_wasi(err: MemoryAccessError) -> Errno {
         MemoryAccessError::HeapOutOfBounds => Errno::Memviolation,
         MemoryAccessError::Overflow => Errno::Overflow,
         MemoryAccessError::NonUtf8String => Errno::Inval,
+        MemoryAccessError::Protected => Errno::Fault,
         _ => Errno::Unknown,
     }
 }
@@ -861,3 +1108,71 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use wasmer_wasix_types::wasix::{ThreadStartType, WasiMemoryLayout};
+
+    use super::*;
+
+    fn deep_sleep() -> WasiError {
+        WasiError::DeepSleep(DeepSleepWork {
+            trigger: Box::pin(async { Bytes::new() }),
+            rewind: RewindState {
+                memory_stack: Bytes::new(),
+                rewind_stack: Bytes::new(),
+                store_data: Bytes::new(),
+                start: ThreadStartType::MainThread,
+                layout: WasiMemoryLayout::default(),
+                is_64bit: false,
+            },
+        })
+    }
+
+    #[test]
+    fn outcome_of_an_exit() {
+        let err = WasiRuntimeError::Wasi(WasiError::Exit(ExitCode::from(3u16)));
+        assert_eq!(err.outcome(), WasiOutcome::Exited(ExitCode::from(3u16)));
+        assert_eq!(err.as_exit_code(), Some(ExitCode::from(3u16)));
+
+        // Errors raised from within a host function are wrapped by the runtime
+        let err = WasiRuntimeError::Runtime(RuntimeError::user(Box::new(WasiError::Exit(
+            ExitCode::from(4u16),
+        ))));
+        assert_eq!(err.outcome(), WasiOutcome::Exited(ExitCode::from(4u16)));
+        assert_eq!(err.as_exit_code(), Some(ExitCode::from(4u16)));
+    }
+
+    #[test]
+    fn outcome_of_a_thread_exit() {
+        let err = WasiRuntimeError::Wasi(WasiError::ThreadExit);
+        assert_eq!(err.outcome(), WasiOutcome::ThreadExited);
+        assert_eq!(err.as_exit_code(), None);
+
+        let err = WasiRuntimeError::Runtime(RuntimeError::user(Box::new(WasiError::ThreadExit)));
+        assert_eq!(err.outcome(), WasiOutcome::ThreadExited);
+    }
+
+    #[test]
+    fn outcome_of_a_deep_sleep() {
+        let err = WasiRuntimeError::Wasi(deep_sleep());
+        assert_eq!(err.outcome(), WasiOutcome::DeepSleep);
+        assert_eq!(err.as_exit_code(), None);
+
+        let err = WasiRuntimeError::Runtime(RuntimeError::user(Box::new(deep_sleep())));
+        assert_eq!(err.outcome(), WasiOutcome::DeepSleep);
+    }
+
+    #[test]
+    fn outcome_of_an_error() {
+        let err = WasiRuntimeError::Wasi(WasiError::UnknownWasiVersion);
+        assert_eq!(err.outcome(), WasiOutcome::Error);
+        assert_eq!(err.as_exit_code(), None);
+
+        let err = WasiRuntimeError::Anyhow(Arc::new(anyhow::anyhow!("failed")));
+        assert_eq!(err.outcome(), WasiOutcome::Error);
+
+        let err = WasiRuntimeError::Runtime(RuntimeError::new("trap"));
+        assert_eq!(err.outcome(), WasiOutcome::Error);
+    }
+}
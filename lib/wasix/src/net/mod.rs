@@ -16,8 +16,11 @@ use wasmer_wasix_types::{
     wasi::{Addressfamily, Errno},
 };
 
+mod serve;
 pub mod socket;
 
+pub use self::serve::serve_accept;
+
 #[allow(dead_code)]
 pub(crate) fn read_ip<M: MemorySize>(
     memory: &MemoryView,
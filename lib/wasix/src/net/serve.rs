@@ -0,0 +1,114 @@
+use std::{future::Future, net::SocketAddr};
+
+use futures::{stream::FuturesUnordered, FutureExt, StreamExt};
+use tokio_util::sync::CancellationToken;
+use virtual_net::{NetworkError, VirtualTcpListener, VirtualTcpListenerExt, VirtualTcpSocket};
+
+/// Accepts connections on a listener until the `shutdown` token is
+/// cancelled, invoking the `handler` for every connection that arrives.
+///
+/// The handlers are driven concurrently by the returned future. Once the
+/// token is cancelled no more connections are accepted however the future
+/// only completes after all the in-flight handlers have finished, hence
+/// no connections are dropped mid-flight.
+///
+/// If the listener fails then the error is returned after the in-flight
+/// handlers have completed.
+pub async fn serve_accept<F, Fut>(
+    mut listener: Box<dyn VirtualTcpListener + Sync>,
+    shutdown: CancellationToken,
+    mut handler: F,
+) -> Result<(), NetworkError>
+where
+    F: FnMut(Box<dyn VirtualTcpSocket + Sync>, SocketAddr) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut in_flight = FuturesUnordered::new();
+    let cancelled = shutdown.cancelled().fuse();
+    futures::pin_mut!(cancelled);
+
+    let mut ret = Ok(());
+    loop {
+        let mut accept = listener.accept().fuse();
+        futures::select_biased! {
+            _ = cancelled => break,
+            _ = in_flight.select_next_some() => {}
+            res = accept => match res {
+                Ok((socket, addr)) => {
+                    tracing::trace!(%addr, "accepted connection");
+                    in_flight.push(handler(socket, addr));
+                }
+                Err(err) => {
+                    tracing::debug!("failed to accept connection - {}", err);
+                    ret = Err(err);
+                    break;
+                }
+            },
+        }
+    }
+
+    // Let all the connections that are still being served finish
+    while in_flight.next().await.is_some() {}
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use virtual_net::{LoopbackNetworking, VirtualNetworking};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serve_accept_completes_in_flight_handlers() {
+        let networking = LoopbackNetworking::new();
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+        let listener = networking
+            .listen_tcp(addr, false, false, false)
+            .await
+            .unwrap();
+
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let (release_tx, release_rx) = tokio::sync::watch::channel(false);
+
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn({
+            let accepted = accepted.clone();
+            let completed = completed.clone();
+            serve_accept(listener, shutdown.clone(), move |_socket, _addr| {
+                let completed = completed.clone();
+                let mut release_rx = release_rx.clone();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    release_rx.wait_for(|release| *release).await.unwrap();
+                    completed.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        });
+
+        let client = SocketAddr::from(([127, 0, 0, 1], 0));
+        let _conn1 = networking.loopback_connect_to(client, addr).unwrap();
+        let _conn2 = networking.loopback_connect_to(client, addr).unwrap();
+        while accepted.load(Ordering::SeqCst) < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        // Cancelling stops the accept loop but the handlers are still running
+        shutdown.cancel();
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!server.is_finished());
+        assert_eq!(completed.load(Ordering::SeqCst), 0);
+
+        release_tx.send(true).unwrap();
+        server.await.unwrap().unwrap();
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+        assert_eq!(completed.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,17 +1,17 @@
 use std::{
     future::Future,
-    io,
+    io::{self, IoSlice},
     mem::MaybeUninit,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     pin::Pin,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
     task::{Context, Poll},
     time::Duration,
 };
 
 #[cfg(feature = "enable-serde")]
 use serde_derive::{Deserialize, Serialize};
-use virtual_mio::InterestHandler;
+use virtual_mio::{InterestHandler, InterestType, InterestWakerMap};
 use virtual_net::{
     net_error_into_io_err, NetworkError, VirtualIcmpSocket, VirtualNetworking, VirtualRawSocket,
     VirtualTcpListener, VirtualTcpSocket, VirtualUdpSocket,
@@ -49,6 +49,7 @@ pub struct SocketProperties {
     pub read_timeout: Option<Duration>,
     pub accept_timeout: Option<Duration>,
     pub connect_timeout: Option<Duration>,
+    pub user_timeout: Option<Duration>,
     pub handler: Option<Box<dyn InterestHandler + Send + Sync>>,
 }
 
@@ -112,6 +113,7 @@ pub enum WasiSocketOption {
     MulticastTtlV4,
     Type,
     Proto,
+    UserTimeout,
 }
 
 impl From<Sockoption> for WasiSocketOption {
@@ -145,6 +147,7 @@ impl From<Sockoption> for WasiSocketOption {
             Sockoption::MulticastTtlV4 => MulticastTtlV4,
             Sockoption::Type => Type,
             Sockoption::Proto => Proto,
+            Sockoption::UserTimeout => UserTimeout,
         }
     }
 }
@@ -166,6 +169,7 @@ pub enum TimeType {
     ConnectTimeout,
     BindTimeout,
     Linger,
+    UserTimeout,
 }
 
 impl From<TimeType> for wasmer_journal::SocketOptTimeType {
@@ -177,6 +181,7 @@ impl From<TimeType> for wasmer_journal::SocketOptTimeType {
             TimeType::ConnectTimeout => Self::ConnectTimeout,
             TimeType::BindTimeout => Self::BindTimeout,
             TimeType::Linger => Self::Linger,
+            TimeType::UserTimeout => Self::UserTimeout,
         }
     }
 }
@@ -191,10 +196,34 @@ impl From<wasmer_journal::SocketOptTimeType> for TimeType {
             SocketOptTimeType::ConnectTimeout => TimeType::ConnectTimeout,
             SocketOptTimeType::BindTimeout => TimeType::BindTimeout,
             SocketOptTimeType::Linger => TimeType::Linger,
+            SocketOptTimeType::UserTimeout => TimeType::UserTimeout,
         }
     }
 }
 
+impl InodeSocketKind {
+    /// Whether the addresses of the socket stay the same until it is bound
+    /// or connected again, which makes them safe to cache
+    fn has_stable_addrs(&self) -> bool {
+        matches!(
+            self,
+            InodeSocketKind::Icmp(_)
+                | InodeSocketKind::TcpListener { .. }
+                | InodeSocketKind::TcpStream { .. }
+                | InodeSocketKind::UdpSocket { .. }
+        )
+    }
+}
+
+/// Local and peer addresses of an opened socket, they are kept so that
+/// `sock_addr_local` and `sock_addr_peer` do not have to query the backend
+/// of the socket every time
+#[derive(Debug, Default)]
+pub(crate) struct SocketAddrCache {
+    pub local: Option<SocketAddr>,
+    pub peer: Option<SocketAddr>,
+}
+
 #[derive(Debug)]
 //#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub(crate) struct InodeSocketProtected {
@@ -205,6 +234,11 @@ pub(crate) struct InodeSocketProtected {
 //#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub(crate) struct InodeSocketInner {
     pub protected: RwLock<InodeSocketProtected>,
+    /// Everyone waiting to accept a connection on a listener, there can be
+    /// several of them as forked processes share their listening sockets
+    pub accept_wakers: InterestWakerMap,
+    /// Addresses of the socket, reset whenever it is bound or connected
+    pub addr_cache: Mutex<SocketAddrCache>,
 }
 
 #[derive(Debug, Clone)]
@@ -219,6 +253,8 @@ impl InodeSocket {
         Self {
             inner: Arc::new(InodeSocketInner {
                 protected: RwLock::new(protected),
+                accept_wakers: Default::default(),
+                addr_cache: Default::default(),
             }),
         }
     }
@@ -230,7 +266,15 @@ impl InodeSocket {
 
     pub fn poll_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         let mut inner = self.inner.protected.write().unwrap();
-        inner.poll_read_ready(cx)
+        inner.poll_write_ready(cx)
+    }
+
+    /// Returns the number of bytes that can be received right away (like
+    /// `FIONREAD`), unlike `poll_read_ready` this neither registers for a
+    /// wake up nor accepts a pending connection
+    pub fn bytes_available(&self) -> io::Result<usize> {
+        let mut inner = self.inner.protected.write().unwrap();
+        inner.bytes_available()
     }
 
     pub async fn bind(
@@ -273,6 +317,7 @@ impl InodeSocket {
 
                     addr.replace(set_addr);
                     let addr = (*addr).unwrap();
+                    self.reset_addr_cache();
 
                     match props.ty {
                         Socktype::Stream => {
@@ -319,6 +364,7 @@ impl InodeSocket {
 
                     *addr = set_addr;
                     let addr = *addr;
+                    self.reset_addr_cache();
 
                     match props.ty {
                         Socktype::Stream => {
@@ -456,38 +502,48 @@ impl InodeSocket {
             nonblocking: bool,
             handler_registered: bool,
         }
-        impl<'a> Drop for SocketAccepter<'a> {
-            fn drop(&mut self) {
-                if self.handler_registered {
-                    let mut inner = self.sock.inner.protected.write().unwrap();
-                    inner.remove_handler();
-                }
-            }
-        }
+        // The handler is not removed once the accept finishes, as forked
+        // processes share the listener other accepters may still rely on it.
         impl<'a> Future for SocketAccepter<'a> {
             type Output = Result<(Box<dyn VirtualTcpSocket + Sync>, SocketAddr), Errno>;
             fn poll(
                 mut self: Pin<&mut Self>,
                 cx: &mut std::task::Context<'_>,
             ) -> std::task::Poll<Self::Output> {
+                let sock = self.sock;
+                let wakers = &sock.inner.accept_wakers;
                 loop {
-                    let mut inner = self.sock.inner.protected.write().unwrap();
+                    let mut inner = sock.inner.protected.write().unwrap();
                     return match &mut inner.kind {
                         InodeSocketKind::TcpListener { socket, .. } => match socket.try_accept() {
-                            Ok((child, addr)) => Poll::Ready(Ok((child, addr))),
+                            Ok((child, addr)) => {
+                                // Let the other accepters check whether more
+                                // connections are waiting
+                                wakers.clone().push_interest(InterestType::Readable);
+                                Poll::Ready(Ok((child, addr)))
+                            }
                             Err(NetworkError::WouldBlock) if self.nonblocking => {
                                 Poll::Ready(Err(Errno::Again))
                             }
-                            Err(NetworkError::WouldBlock) if !self.handler_registered => {
-                                let res = socket.set_handler(cx.waker().into());
-                                if let Err(err) = res {
-                                    return Poll::Ready(Err(net_error_into_wasi_err(err)));
+                            Err(NetworkError::WouldBlock) => {
+                                wakers.add(InterestType::Readable, cx.waker());
+                                if !self.handler_registered {
+                                    let res = socket.set_handler(Box::new(wakers.clone()));
+                                    if let Err(err) = res {
+                                        return Poll::Ready(Err(net_error_into_wasi_err(err)));
+                                    }
+                                    drop(inner);
+                                    self.handler_registered = true;
+                                    continue;
+                                }
+                                // A connection may have arrived before the
+                                // waker was added
+                                if wakers.pop(InterestType::Readable) {
+                                    drop(inner);
+                                    continue;
                                 }
-                                drop(inner);
-                                self.handler_registered = true;
-                                continue;
+                                Poll::Pending
                             }
-                            Err(NetworkError::WouldBlock) => Poll::Pending,
                             Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
                         },
                         InodeSocketKind::PreSocket { .. } => Poll::Ready(Err(Errno::Notconn)),
@@ -535,6 +591,7 @@ impl InodeSocket {
         peer: SocketAddr,
         timeout: Option<std::time::Duration>,
         nonblocking: bool,
+        data: Option<&[u8]>,
     ) -> Result<Option<InodeSocket>, Errno> {
         let new_write_timeout;
         let new_read_timeout;
@@ -554,6 +611,7 @@ impl InodeSocket {
                             let no_delay = props.no_delay;
                             let keep_alive = props.keep_alive;
                             let dont_route = props.dont_route;
+                            let user_timeout = props.user_timeout;
                             let addr = match addr {
                                 Some(a) => *a,
                                 None => {
@@ -565,7 +623,12 @@ impl InodeSocket {
                                 }
                             };
                             Box::pin(async move {
-                                let mut ret = net.connect_tcp(addr, peer).await?;
+                                let mut ret = match data {
+                                    Some(data) => {
+                                        net.connect_tcp_with_data(addr, peer, data).await?
+                                    }
+                                    None => net.connect_tcp(addr, peer).await?,
+                                };
                                 if let Some(no_delay) = no_delay {
                                     ret.set_nodelay(no_delay).ok();
                                 }
@@ -575,6 +638,9 @@ impl InodeSocket {
                                 if let Some(dont_route) = dont_route {
                                     ret.set_dontroute(dont_route).ok();
                                 }
+                                if user_timeout.is_some() {
+                                    ret.set_user_timeout(user_timeout).ok();
+                                }
                                 if !nonblocking {
                                     futures::future::poll_fn(|cx| ret.poll_write_ready(cx)).await?;
                                 }
@@ -585,14 +651,18 @@ impl InodeSocket {
                         _ => return Err(Errno::Notsup),
                     }
                 }
+                _ if data.is_some() => return Err(Errno::Notsup),
                 InodeSocketKind::UdpSocket {
                     peer: target_peer, ..
                 } => {
+                    // A UDP socket may be connected to another peer at any time
                     target_peer.replace(peer);
+                    self.reset_addr_cache();
                     return Ok(None);
                 }
                 InodeSocketKind::RemoteSocket { peer_addr, .. } => {
                     *peer_addr = peer;
+                    self.reset_addr_cache();
                     return Ok(None);
                 }
                 _ => return Err(Errno::Notsup),
@@ -634,9 +704,44 @@ impl InodeSocket {
         })
     }
 
+    /// Returns true if this socket is a TCP stream, which is the case as soon
+    /// as a connection has been initiated (even if it has not been established yet)
+    pub fn is_tcp_stream(&self) -> bool {
+        let inner = self.inner.protected.read().unwrap();
+        matches!(inner.kind, InodeSocketKind::TcpStream { .. })
+    }
+
+    /// Takes the pending error of the socket (`SO_ERROR`), which is how the
+    /// outcome of a nonblocking connect is reported
+    pub fn take_error(&self) -> Result<Errno, Errno> {
+        let mut inner = self.inner.protected.write().unwrap();
+        let err = match &mut inner.kind {
+            InodeSocketKind::TcpStream { socket, .. } => socket.take_error(),
+            InodeSocketKind::UdpSocket { socket, .. } => socket.take_error(),
+            InodeSocketKind::Raw(socket) => socket.take_error(),
+            InodeSocketKind::Icmp(socket) => socket.take_error(),
+            _ => Ok(None),
+        };
+        Ok(err
+            .map_err(net_error_into_wasi_err)?
+            .map(net_error_into_wasi_err)
+            .unwrap_or(Errno::Success))
+    }
+
+    /// Forgets the cached addresses of the socket, this must be called after
+    /// the addresses have changed while still holding the protected write lock
+    /// so that a concurrent lookup can not cache the old addresses again
+    fn reset_addr_cache(&self) {
+        *self.inner.addr_cache.lock().unwrap() = SocketAddrCache::default();
+    }
+
     pub fn addr_local(&self) -> Result<SocketAddr, Errno> {
+        if let Some(addr) = self.inner.addr_cache.lock().unwrap().local {
+            return Ok(addr);
+        }
+
         let inner = self.inner.protected.read().unwrap();
-        Ok(match &inner.kind {
+        let addr = match &inner.kind {
             InodeSocketKind::PreSocket { props, addr, .. } => {
                 if let Some(addr) = addr {
                     *addr
@@ -665,12 +770,20 @@ impl InodeSocket {
                 local_addr: addr, ..
             } => *addr,
             _ => return Err(Errno::Notsup),
-        })
+        };
+        if inner.kind.has_stable_addrs() {
+            self.inner.addr_cache.lock().unwrap().local = Some(addr);
+        }
+        Ok(addr)
     }
 
     pub fn addr_peer(&self) -> Result<SocketAddr, Errno> {
+        if let Some(addr) = self.inner.addr_cache.lock().unwrap().peer {
+            return Ok(addr);
+        }
+
         let inner = self.inner.protected.read().unwrap();
-        Ok(match &inner.kind {
+        let addr = match &inner.kind {
             InodeSocketKind::PreSocket { props, .. } => SocketAddr::new(
                 match props.family {
                     Addressfamily::Inet4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
@@ -682,6 +795,9 @@ impl InodeSocket {
             InodeSocketKind::TcpStream { socket, .. } => {
                 socket.addr_peer().map_err(net_error_into_wasi_err)?
             }
+            InodeSocketKind::UdpSocket {
+                peer: Some(peer), ..
+            } => *peer,
             InodeSocketKind::UdpSocket { socket, .. } => socket
                 .addr_peer()
                 .map_err(net_error_into_wasi_err)?
@@ -702,7 +818,11 @@ impl InodeSocket {
                 })?,
             InodeSocketKind::RemoteSocket { peer_addr, .. } => *peer_addr,
             _ => return Err(Errno::Notsup),
-        })
+        };
+        if inner.kind.has_stable_addrs() {
+            self.inner.addr_cache.lock().unwrap().peer = Some(addr);
+        }
+        Ok(addr)
     }
 
     pub fn set_opt_flag(&mut self, option: WasiSocketOption, val: bool) -> Result<(), Errno> {
@@ -893,6 +1013,7 @@ impl InodeSocket {
         let mut inner = self.inner.protected.write().unwrap();
         match &mut inner.kind {
             InodeSocketKind::TcpStream {
+                socket,
                 write_timeout,
                 read_timeout,
                 ..
@@ -900,6 +1021,9 @@ impl InodeSocket {
                 match ty {
                     TimeType::WriteTimeout => *write_timeout = timeout,
                     TimeType::ReadTimeout => *read_timeout = timeout,
+                    TimeType::UserTimeout => socket
+                        .set_user_timeout(timeout)
+                        .map_err(net_error_into_wasi_err)?,
                     _ => return Err(Errno::Inval),
                 }
                 Ok(())
@@ -918,6 +1042,7 @@ impl InodeSocket {
                     TimeType::AcceptTimeout => props.accept_timeout = timeout,
                     TimeType::ReadTimeout => props.read_timeout = timeout,
                     TimeType::WriteTimeout => props.write_timeout = timeout,
+                    TimeType::UserTimeout => props.user_timeout = timeout,
                     _ => return Err(Errno::Io),
                 }
                 Ok(())
@@ -930,12 +1055,14 @@ impl InodeSocket {
         let inner = self.inner.protected.read().unwrap();
         match &inner.kind {
             InodeSocketKind::TcpStream {
+                socket,
                 read_timeout,
                 write_timeout,
                 ..
             } => Ok(match ty {
                 TimeType::ReadTimeout => *read_timeout,
                 TimeType::WriteTimeout => *write_timeout,
+                TimeType::UserTimeout => socket.user_timeout().map_err(net_error_into_wasi_err)?,
                 _ => return Err(Errno::Inval),
             }),
             InodeSocketKind::TcpListener { accept_timeout, .. } => Ok(match ty {
@@ -948,6 +1075,7 @@ impl InodeSocket {
                 TimeType::AcceptTimeout => Ok(props.accept_timeout),
                 TimeType::ReadTimeout => Ok(props.read_timeout),
                 TimeType::WriteTimeout => Ok(props.write_timeout),
+                TimeType::UserTimeout => Ok(props.user_timeout),
                 _ => Err(Errno::Inval),
             },
             _ => Err(Errno::Notsup),
@@ -1065,6 +1193,9 @@ impl InodeSocket {
         }
     }
 
+    /// Sends data on the socket. When `timeout` (normally `SO_SNDTIMEO`)
+    /// expires before the data could be queued the send fails with
+    /// `Errno::Again`, just like a `send` on a socket with a timeout does
     pub async fn send(
         &self,
         tasks: &dyn VirtualTaskManager,
@@ -1072,83 +1203,46 @@ impl InodeSocket {
         timeout: Option<Duration>,
         nonblocking: bool,
     ) -> Result<usize, Errno> {
-        struct SocketSender<'a, 'b> {
-            inner: &'a InodeSocketInner,
-            data: &'b [u8],
-            nonblocking: bool,
-            handler_registered: bool,
-        }
-        impl<'a, 'b> Drop for SocketSender<'a, 'b> {
-            fn drop(&mut self) {
-                if self.handler_registered {
-                    let mut inner = self.inner.protected.write().unwrap();
-                    inner.remove_handler();
+        self.send_vectored(tasks, &[IoSlice::new(buf)], timeout, nonblocking)
+            .await
+    }
+
+    /// Sends the concatenation of multiple buffers in a single send, which
+    /// keeps them in one datagram on connected UDP sockets
+    pub async fn send_vectored(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        bufs: &[IoSlice<'_>],
+        timeout: Option<Duration>,
+        nonblocking: bool,
+    ) -> Result<usize, Errno> {
+        let op = |kind: &mut InodeSocketKind| match kind {
+            InodeSocketKind::Raw(socket) => SocketAttempt::Net(match bufs {
+                [buf] => socket.try_send(buf),
+                bufs => {
+                    let data: Vec<u8> = bufs.iter().flat_map(|b| b.iter().copied()).collect();
+                    socket.try_send(&data)
                 }
+            }),
+            InodeSocketKind::TcpStream { socket, .. } => {
+                SocketAttempt::Net(socket.try_send_vectored(bufs))
             }
-        }
-        impl<'a, 'b> Future for SocketSender<'a, 'b> {
-            type Output = Result<usize, Errno>;
-            fn poll(
-                mut self: Pin<&mut Self>,
-                cx: &mut std::task::Context<'_>,
-            ) -> Poll<Self::Output> {
-                loop {
-                    let mut inner = self.inner.protected.write().unwrap();
-                    let res = match &mut inner.kind {
-                        InodeSocketKind::Raw(socket) => socket.try_send(self.data),
-                        InodeSocketKind::TcpStream { socket, .. } => socket.try_send(self.data),
-                        InodeSocketKind::UdpSocket { socket, peer } => {
-                            if let Some(peer) = peer {
-                                socket.try_send_to(self.data, *peer)
-                            } else {
-                                Err(NetworkError::NotConnected)
-                            }
-                        }
-                        InodeSocketKind::PreSocket { .. } => {
-                            return Poll::Ready(Err(Errno::Notconn))
-                        }
-                        InodeSocketKind::RemoteSocket { is_dead, .. } => {
-                            return match is_dead {
-                                true => Poll::Ready(Err(Errno::Connreset)),
-                                false => Poll::Ready(Ok(self.data.len())),
-                            }
-                        }
-                        _ => return Poll::Ready(Err(Errno::Notsup)),
-                    };
-                    return match res {
-                        Ok(amt) => Poll::Ready(Ok(amt)),
-                        Err(NetworkError::WouldBlock) if self.nonblocking => {
-                            Poll::Ready(Err(Errno::Again))
-                        }
-                        Err(NetworkError::WouldBlock) if !self.handler_registered => {
-                            inner
-                                .set_handler(cx.waker().into())
-                                .map_err(net_error_into_wasi_err)?;
-                            drop(inner);
-                            self.handler_registered = true;
-                            continue;
-                        }
-                        Err(NetworkError::WouldBlock) => Poll::Pending,
-                        Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
-                    };
-                }
+            InodeSocketKind::UdpSocket { socket, peer } => SocketAttempt::Net(match peer {
+                Some(peer) => socket.try_send_to_vectored(bufs, *peer),
+                None => Err(NetworkError::NotConnected),
+            }),
+            InodeSocketKind::PreSocket { .. } => SocketAttempt::Done(Err(Errno::Notconn)),
+            InodeSocketKind::RemoteSocket { is_dead: true, .. } => {
+                SocketAttempt::Done(Err(Errno::Connreset))
             }
-        }
-
-        let poller = SocketSender {
-            inner: &self.inner,
-            data: buf,
-            nonblocking,
-            handler_registered: false,
-        };
-        if let Some(timeout) = timeout {
-            tokio::select! {
-                res = poller => res,
-                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+            InodeSocketKind::RemoteSocket { .. } => {
+                SocketAttempt::Done(Ok(bufs.iter().map(|b| b.len()).sum()))
             }
-        } else {
-            poller.await
-        }
+            _ => SocketAttempt::Done(Err(Errno::Notsup)),
+        };
+        SocketOperation::new(&self.inner, op, nonblocking)
+            .run(tasks, timeout)
+            .await
     }
 
     pub async fn send_to<M: MemorySize>(
@@ -1228,13 +1322,16 @@ impl InodeSocket {
         if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
-                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+                _ = tasks.sleep_now(timeout) => Err(Errno::Again)
             }
         } else {
             poller.await
         }
     }
 
+    /// Receives data from the socket. When `timeout` (normally `SO_RCVTIMEO`)
+    /// expires before any data arrived the receive fails with `Errno::Again`,
+    /// just like a `recv` on a socket with a timeout does
     pub async fn recv(
         &self,
         tasks: &dyn VirtualTaskManager,
@@ -1242,100 +1339,79 @@ impl InodeSocket {
         timeout: Option<Duration>,
         nonblocking: bool,
     ) -> Result<usize, Errno> {
-        struct SocketReceiver<'a, 'b> {
-            inner: &'a InodeSocketInner,
-            data: &'b mut [MaybeUninit<u8>],
-            nonblocking: bool,
-            handler_registered: bool,
-        }
-        impl<'a, 'b> Drop for SocketReceiver<'a, 'b> {
-            fn drop(&mut self) {
-                if self.handler_registered {
-                    let mut inner = self.inner.protected.write().unwrap();
-                    inner.remove_handler();
-                }
-            }
-        }
-        impl<'a, 'b> Future for SocketReceiver<'a, 'b> {
-            type Output = Result<usize, Errno>;
-            fn poll(
-                mut self: Pin<&mut Self>,
-                cx: &mut std::task::Context<'_>,
-            ) -> Poll<Self::Output> {
-                loop {
-                    let mut inner = self.inner.protected.write().unwrap();
-                    let res = match &mut inner.kind {
-                        InodeSocketKind::Raw(socket) => socket.try_recv(self.data),
-                        InodeSocketKind::TcpStream { socket, .. } => socket.try_recv(self.data),
-                        InodeSocketKind::UdpSocket { socket, peer } => {
-                            if let Some(peer) = peer {
-                                match socket.try_recv_from(self.data) {
-                                    Ok((amt, addr)) if addr == *peer => Ok(amt),
-                                    Ok(_) => Err(NetworkError::WouldBlock),
-                                    Err(err) => Err(err),
-                                }
-                            } else {
-                                match socket.try_recv_from(self.data) {
-                                    Ok((amt, _)) => Ok(amt),
-                                    Err(err) => Err(err),
-                                }
-                            }
-                        }
-                        InodeSocketKind::RemoteSocket { is_dead, .. } => {
-                            return match is_dead {
-                                true => Poll::Ready(Ok(0)),
-                                false => Poll::Pending,
-                            };
-                        }
-                        InodeSocketKind::PreSocket { .. } => {
-                            return Poll::Ready(Err(Errno::Notconn))
-                        }
-                        _ => return Poll::Ready(Err(Errno::Notsup)),
-                    };
-                    return match res {
-                        Ok(amt) => Poll::Ready(Ok(amt)),
-                        Err(NetworkError::WouldBlock) if self.nonblocking => {
-                            Poll::Ready(Err(Errno::Again))
-                        }
-                        Err(NetworkError::WouldBlock) if !self.handler_registered => {
-                            inner
-                                .set_handler(cx.waker().into())
-                                .map_err(net_error_into_wasi_err)?;
-                            self.handler_registered = true;
-                            drop(inner);
-                            continue;
-                        }
+        self.recv_vectored(tasks, &mut [buf], timeout, nonblocking)
+            .await
+            .map(|(amt, _)| amt)
+    }
 
-                        Err(NetworkError::WouldBlock) => Poll::Pending,
-                        Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
-                    };
+    /// Receives a packet, scattering it over multiple buffers. The returned
+    /// flag is set when a datagram did not fit into `bufs` and was truncated
+    pub async fn recv_vectored(
+        &self,
+        tasks: &dyn VirtualTaskManager,
+        bufs: &mut [&mut [MaybeUninit<u8>]],
+        timeout: Option<Duration>,
+        nonblocking: bool,
+    ) -> Result<(usize, bool), Errno> {
+        let op = |kind: &mut InodeSocketKind| match kind {
+            InodeSocketKind::Raw(socket) => SocketAttempt::Net(match &mut *bufs {
+                [buf] => socket.try_recv(buf).map(|amt| (amt, false)),
+                bufs => {
+                    let mut data = vec![MaybeUninit::uninit(); bufs.iter().map(|b| b.len()).sum()];
+                    socket.try_recv(&mut data).map(|amt| {
+                        scatter(&data[..amt], bufs);
+                        (amt, false)
+                    })
                 }
+            }),
+            InodeSocketKind::TcpStream { socket, .. } => {
+                SocketAttempt::Net(socket.try_recv_vectored(bufs).map(|amt| (amt, false)))
             }
-        }
-
-        let poller = SocketReceiver {
-            inner: &self.inner,
-            data: buf,
-            nonblocking,
-            handler_registered: false,
-        };
-        if let Some(timeout) = timeout {
-            tokio::select! {
-                res = poller => res,
-                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+            InodeSocketKind::UdpSocket { socket, peer } => {
+                // Received the same way as by `recv_from` so that the
+                // truncation of a datagram is noticed
+                let res = match &mut *bufs {
+                    [buf] => socket.try_recv_from_with_size(buf),
+                    bufs => {
+                        let mut data =
+                            vec![MaybeUninit::uninit(); bufs.iter().map(|b| b.len()).sum()];
+                        socket
+                            .try_recv_from_with_size(&mut data)
+                            .map(|(amt, size, addr)| {
+                                scatter(&data[..amt], bufs);
+                                (amt, size, addr)
+                            })
+                    }
+                };
+                SocketAttempt::Net(match res {
+                    Ok((_, _, addr)) if peer.is_some_and(|peer| peer != addr) => {
+                        Err(NetworkError::WouldBlock)
+                    }
+                    Ok((amt, size, _)) => Ok((amt, size > amt)),
+                    Err(err) => Err(err),
+                })
             }
-        } else {
-            poller.await
-        }
+            InodeSocketKind::RemoteSocket { is_dead: true, .. } => {
+                SocketAttempt::Done(Ok((0, false)))
+            }
+            InodeSocketKind::RemoteSocket { .. } => SocketAttempt::Pending,
+            InodeSocketKind::PreSocket { .. } => SocketAttempt::Done(Err(Errno::Notconn)),
+            _ => SocketAttempt::Done(Err(Errno::Notsup)),
+        };
+        SocketOperation::new(&self.inner, op, nonblocking)
+            .run(tasks, timeout)
+            .await
     }
 
+    /// Receives a datagram, the returned flag is set when the datagram did
+    /// not fit into `buf` and was truncated
     pub async fn recv_from(
         &self,
         tasks: &dyn VirtualTaskManager,
         buf: &mut [MaybeUninit<u8>],
         timeout: Option<Duration>,
         nonblocking: bool,
-    ) -> Result<(usize, SocketAddr), Errno> {
+    ) -> Result<(usize, SocketAddr, bool), Errno> {
         struct SocketReceiver<'a, 'b> {
             inner: &'a InodeSocketInner,
             data: &'b mut [MaybeUninit<u8>],
@@ -1351,7 +1427,7 @@ impl InodeSocket {
             }
         }
         impl<'a, 'b> Future for SocketReceiver<'a, 'b> {
-            type Output = Result<(usize, SocketAddr), Errno>;
+            type Output = Result<(usize, SocketAddr, bool), Errno>;
             fn poll(
                 mut self: Pin<&mut Self>,
                 cx: &mut std::task::Context<'_>,
@@ -1359,15 +1435,15 @@ impl InodeSocket {
                 let mut inner = self.inner.protected.write().unwrap();
                 loop {
                     let res = match &mut inner.kind {
-                        InodeSocketKind::Icmp(socket) => socket.try_recv_from(self.data),
+                        InodeSocketKind::Icmp(socket) => socket.try_recv_from_with_size(self.data),
                         InodeSocketKind::UdpSocket { socket, .. } => {
-                            socket.try_recv_from(self.data)
+                            socket.try_recv_from_with_size(self.data)
                         }
                         InodeSocketKind::RemoteSocket {
                             is_dead, peer_addr, ..
                         } => {
                             return match is_dead {
-                                true => Poll::Ready(Ok((0, *peer_addr))),
+                                true => Poll::Ready(Ok((0, *peer_addr, false))),
                                 false => Poll::Pending,
                             };
                         }
@@ -1377,7 +1453,7 @@ impl InodeSocket {
                         _ => return Poll::Ready(Err(Errno::Notsup)),
                     };
                     return match res {
-                        Ok((amt, addr)) => Poll::Ready(Ok((amt, addr))),
+                        Ok((amt, size, addr)) => Poll::Ready(Ok((amt, addr, size > amt))),
                         Err(NetworkError::WouldBlock) if self.nonblocking => {
                             Poll::Ready(Err(Errno::Again))
                         }
@@ -1404,7 +1480,7 @@ impl InodeSocket {
         if let Some(timeout) = timeout {
             tokio::select! {
                 res = poller => res,
-                _ = tasks.sleep_now(timeout) => Err(Errno::Timedout)
+                _ = tasks.sleep_now(timeout) => Err(Errno::Again)
             }
         } else {
             poller.await
@@ -1473,6 +1549,19 @@ impl InodeSocketProtected {
         .map_err(net_error_into_io_err)
     }
 
+    pub fn bytes_available(&mut self) -> io::Result<usize> {
+        match &mut self.kind {
+            // Listeners have connections to accept rather than bytes
+            InodeSocketKind::TcpListener { .. } => Ok(0),
+            InodeSocketKind::TcpStream { socket, .. } => socket.bytes_available(),
+            InodeSocketKind::UdpSocket { socket, .. } => socket.bytes_available(),
+            InodeSocketKind::Raw(socket) => socket.bytes_available(),
+            InodeSocketKind::Icmp(socket) => socket.bytes_available(),
+            InodeSocketKind::PreSocket { .. } | InodeSocketKind::RemoteSocket { .. } => Ok(0),
+        }
+        .map_err(net_error_into_io_err)
+    }
+
     pub fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
         match &mut self.kind {
             InodeSocketKind::TcpListener { socket, .. } => socket.poll_write_ready(cx),
@@ -1508,6 +1597,107 @@ impl InodeSocketProtected {
     }
 }
 
+/// Outcome of one attempt of a [`SocketOperation`]
+enum SocketAttempt<T> {
+    /// The socket was tried, the attempt is repeated once the socket is
+    /// ready when it would have blocked
+    Net(Result<T, NetworkError>),
+    /// The operation finished without trying the socket
+    Done(Result<T, Errno>),
+    /// The operation can not make progress for now
+    Pending,
+}
+
+/// Future that repeats a non-blocking operation on a socket until it no
+/// longer would block, the socket wakes up the task whenever it is ready
+struct SocketOperation<'a, F> {
+    inner: &'a InodeSocketInner,
+    op: F,
+    nonblocking: bool,
+    handler_registered: bool,
+}
+
+impl<'a, F, T> SocketOperation<'a, F>
+where
+    F: FnMut(&mut InodeSocketKind) -> SocketAttempt<T> + Unpin,
+{
+    fn new(inner: &'a InodeSocketInner, op: F, nonblocking: bool) -> Self {
+        Self {
+            inner,
+            op,
+            nonblocking,
+            handler_registered: false,
+        }
+    }
+
+    /// Runs the operation to completion, it fails with `Errno::Again` when
+    /// `timeout` expires first
+    async fn run(
+        self,
+        tasks: &dyn VirtualTaskManager,
+        timeout: Option<Duration>,
+    ) -> Result<T, Errno> {
+        if let Some(timeout) = timeout {
+            tokio::select! {
+                res = self => res,
+                _ = tasks.sleep_now(timeout) => Err(Errno::Again)
+            }
+        } else {
+            self.await
+        }
+    }
+}
+
+impl<'a, F> Drop for SocketOperation<'a, F> {
+    fn drop(&mut self) {
+        if self.handler_registered {
+            let mut inner = self.inner.protected.write().unwrap();
+            inner.remove_handler();
+        }
+    }
+}
+
+impl<'a, F, T> Future for SocketOperation<'a, F>
+where
+    F: FnMut(&mut InodeSocketKind) -> SocketAttempt<T> + Unpin,
+{
+    type Output = Result<T, Errno>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            let mut inner = this.inner.protected.write().unwrap();
+            let res = match (this.op)(&mut inner.kind) {
+                SocketAttempt::Net(res) => res,
+                SocketAttempt::Done(res) => return Poll::Ready(res),
+                SocketAttempt::Pending => return Poll::Pending,
+            };
+            return match res {
+                Ok(amt) => Poll::Ready(Ok(amt)),
+                Err(NetworkError::WouldBlock) if this.nonblocking => Poll::Ready(Err(Errno::Again)),
+                Err(NetworkError::WouldBlock) if !this.handler_registered => {
+                    inner
+                        .set_handler(cx.waker().into())
+                        .map_err(net_error_into_wasi_err)?;
+                    drop(inner);
+                    this.handler_registered = true;
+                    continue;
+                }
+                Err(NetworkError::WouldBlock) => Poll::Pending,
+                Err(err) => Poll::Ready(Err(net_error_into_wasi_err(err))),
+            };
+        }
+    }
+}
+
+/// Spreads received data over the buffers of a vectored receive
+fn scatter(mut data: &[MaybeUninit<u8>], bufs: &mut [&mut [MaybeUninit<u8>]]) {
+    for buf in bufs.iter_mut() {
+        let amt = buf.len().min(data.len());
+        buf[..amt].copy_from_slice(&data[..amt]);
+        data = &data[amt..];
+    }
+}
+
 #[derive(Default)]
 struct IndefinitePoll {}
 
@@ -1541,3 +1731,244 @@ pub(crate) fn all_socket_rights() -> Rights {
         .union(Rights::SOCK_RECV_FROM)
         .union(Rights::SOCK_SEND_TO)
 }
+
+#[cfg(all(test, feature = "sys-thread"))]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use virtual_net::{
+        LoopbackNetworking, SocketStatus, VirtualConnectedSocket, VirtualIoSource,
+        VirtualNetworking, VirtualSocket,
+    };
+
+    use super::*;
+    use crate::runtime::task_manager::tokio::TokioTaskManager;
+
+    /// Socket that counts how often its addresses are queried
+    #[derive(Debug)]
+    struct CountingTcpSocket {
+        inner: Box<dyn VirtualTcpSocket + Sync>,
+        addr_queries: Arc<AtomicUsize>,
+    }
+
+    impl VirtualIoSource for CountingTcpSocket {
+        fn remove_handler(&mut self) {
+            self.inner.remove_handler()
+        }
+
+        fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+            self.inner.poll_read_ready(cx)
+        }
+
+        fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<virtual_net::Result<usize>> {
+            self.inner.poll_write_ready(cx)
+        }
+    }
+
+    impl VirtualSocket for CountingTcpSocket {
+        fn set_ttl(&mut self, ttl: u32) -> virtual_net::Result<()> {
+            self.inner.set_ttl(ttl)
+        }
+
+        fn ttl(&self) -> virtual_net::Result<u32> {
+            self.inner.ttl()
+        }
+
+        fn addr_local(&self) -> virtual_net::Result<SocketAddr> {
+            self.addr_queries.fetch_add(1, Ordering::SeqCst);
+            self.inner.addr_local()
+        }
+
+        fn status(&self) -> virtual_net::Result<SocketStatus> {
+            self.inner.status()
+        }
+
+        fn set_handler(
+            &mut self,
+            handler: Box<dyn InterestHandler + Send + Sync>,
+        ) -> virtual_net::Result<()> {
+            self.inner.set_handler(handler)
+        }
+    }
+
+    impl VirtualConnectedSocket for CountingTcpSocket {
+        fn set_linger(&mut self, linger: Option<Duration>) -> virtual_net::Result<()> {
+            self.inner.set_linger(linger)
+        }
+
+        fn linger(&self) -> virtual_net::Result<Option<Duration>> {
+            self.inner.linger()
+        }
+
+        fn try_send(&mut self, data: &[u8]) -> virtual_net::Result<usize> {
+            self.inner.try_send(data)
+        }
+
+        fn try_flush(&mut self) -> virtual_net::Result<()> {
+            self.inner.try_flush()
+        }
+
+        fn close(&mut self) -> virtual_net::Result<()> {
+            self.inner.close()
+        }
+
+        fn try_recv(&mut self, buf: &mut [MaybeUninit<u8>]) -> virtual_net::Result<usize> {
+            self.inner.try_recv(buf)
+        }
+    }
+
+    impl VirtualTcpSocket for CountingTcpSocket {
+        fn set_recv_buf_size(&mut self, size: usize) -> virtual_net::Result<()> {
+            self.inner.set_recv_buf_size(size)
+        }
+
+        fn recv_buf_size(&self) -> virtual_net::Result<usize> {
+            self.inner.recv_buf_size()
+        }
+
+        fn set_send_buf_size(&mut self, size: usize) -> virtual_net::Result<()> {
+            self.inner.set_send_buf_size(size)
+        }
+
+        fn send_buf_size(&self) -> virtual_net::Result<usize> {
+            self.inner.send_buf_size()
+        }
+
+        fn set_nodelay(&mut self, nodelay: bool) -> virtual_net::Result<()> {
+            self.inner.set_nodelay(nodelay)
+        }
+
+        fn nodelay(&self) -> virtual_net::Result<bool> {
+            self.inner.nodelay()
+        }
+
+        fn set_keepalive(&mut self, keepalive: bool) -> virtual_net::Result<()> {
+            self.inner.set_keepalive(keepalive)
+        }
+
+        fn keepalive(&self) -> virtual_net::Result<bool> {
+            self.inner.keepalive()
+        }
+
+        fn set_dontroute(&mut self, dontroute: bool) -> virtual_net::Result<()> {
+            self.inner.set_dontroute(dontroute)
+        }
+
+        fn dontroute(&self) -> virtual_net::Result<bool> {
+            self.inner.dontroute()
+        }
+
+        fn addr_peer(&self) -> virtual_net::Result<SocketAddr> {
+            self.addr_queries.fetch_add(1, Ordering::SeqCst);
+            self.inner.addr_peer()
+        }
+
+        fn shutdown(&mut self, how: std::net::Shutdown) -> virtual_net::Result<()> {
+            self.inner.shutdown(how)
+        }
+
+        fn is_closed(&self) -> bool {
+            self.inner.is_closed()
+        }
+    }
+
+    #[tokio::test]
+    async fn addresses_are_served_from_the_cache() {
+        let net = LoopbackNetworking::new();
+        let server = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 8080);
+        let client = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 10000);
+        let _listener = net.listen_tcp(server, false, false, false).await.unwrap();
+
+        let addr_queries = Arc::new(AtomicUsize::new(0));
+        let socket = InodeSocket::new(InodeSocketKind::TcpStream {
+            socket: Box::new(CountingTcpSocket {
+                inner: net.connect_tcp(client, server).await.unwrap(),
+                addr_queries: addr_queries.clone(),
+            }),
+            write_timeout: None,
+            read_timeout: None,
+        });
+
+        assert_eq!(socket.addr_peer().unwrap(), server);
+        assert_eq!(socket.addr_peer().unwrap(), server);
+        assert_eq!(addr_queries.load(Ordering::SeqCst), 1);
+
+        assert_eq!(socket.addr_local().unwrap(), client);
+        assert_eq!(socket.addr_local().unwrap(), client);
+        assert_eq!(addr_queries.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(feature = "host-vnet")]
+    #[tokio::test]
+    async fn reconnected_udp_socket_reports_the_new_peer() {
+        let net = virtual_net::host::LocalNetworking::new();
+        let tasks = TokioTaskManager::new(tokio::runtime::Handle::current());
+        let udp = net
+            .bind_udp((Ipv4Addr::LOCALHOST, 0).into(), false, false)
+            .await
+            .unwrap();
+        let mut socket = InodeSocket::new(InodeSocketKind::UdpSocket {
+            socket: udp,
+            peer: None,
+        });
+
+        for port in [5000, 6000] {
+            let peer = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+            let ret = socket.connect(&tasks, &net, peer, None, false, None).await;
+            assert!(matches!(ret, Ok(None)));
+            assert_eq!(socket.addr_peer().unwrap(), peer);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn forked_listener_accepts_in_both_processes() {
+        const CONNECTIONS: u16 = 16;
+
+        let net = LoopbackNetworking::new();
+        let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 8080);
+        let listener = net.listen_tcp(addr, false, true, true).await.unwrap();
+        let parent = InodeSocket::new(InodeSocketKind::TcpListener {
+            socket: listener,
+            accept_timeout: None,
+        });
+        // A forked process shares the inode of every file descriptor it
+        // inherits, listening sockets included
+        let child = parent.clone();
+
+        // Both sides accept half of the connections, if either of them missed
+        // a wake up the test would time out
+        let acceptors = [parent, child].map(|sock| {
+            tokio::spawn(async move {
+                let tasks = TokioTaskManager::new(tokio::runtime::Handle::current());
+                let mut peers = Vec::new();
+                for _ in 0..CONNECTIONS / 2 {
+                    let (_socket, peer) = sock.accept(&tasks, false, None).await.unwrap();
+                    peers.push(peer);
+                }
+                peers
+            })
+        });
+
+        let mut clients = Vec::new();
+        for port in 0..CONNECTIONS {
+            let local = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 10000 + port);
+            clients.push(net.connect_tcp(local, addr).await.unwrap());
+            tokio::task::yield_now().await;
+        }
+
+        let mut accepted = HashSet::new();
+        for acceptor in acceptors {
+            let peers = tokio::time::timeout(Duration::from_secs(10), acceptor)
+                .await
+                .expect("a connection was lost")
+                .unwrap();
+            for peer in peers {
+                assert!(accepted.insert(peer), "{peer} was accepted twice");
+            }
+        }
+        assert_eq!(accepted.len(), CONNECTIONS as usize);
+    }
+}
@@ -48,6 +48,9 @@ pub struct ControlPlaneConfig {
     /// time that it will pause the CPU)
     /// (default = off)
     pub enable_exponential_cpu_backoff: Option<Duration>,
+    /// Parallelism reported to the guest by `thread_parallelism` in place
+    /// of the number of CPUs of the host (e.g. derived from a CPU quota)
+    pub thread_parallelism: Option<usize>,
 }
 
 impl ControlPlaneConfig {
@@ -56,6 +59,7 @@ impl ControlPlaneConfig {
             max_task_count: None,
             enable_asynchronous_threading: false,
             enable_exponential_cpu_backoff: None,
+            thread_parallelism: None,
         }
     }
 }
@@ -224,6 +228,7 @@ mod tests {
             max_task_count: Some(2),
             enable_asynchronous_threading: false,
             enable_exponential_cpu_backoff: None,
+            thread_parallelism: None,
         });
 
         let p1 = p.new_process(xxhash_random()).unwrap();
@@ -247,6 +252,7 @@ mod tests {
             max_task_count: Some(2),
             enable_asynchronous_threading: false,
             enable_exponential_cpu_backoff: None,
+            thread_parallelism: None,
         });
 
         let p1 = p.new_process(xxhash_random()).unwrap();
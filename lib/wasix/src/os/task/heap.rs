@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use wasmer::{AsStoreMut, ExportError, Function, RuntimeError, Type, Value};
+
+use crate::{WasiEnv, WasiFunctionEnv, WasiInstanceHandles, WasiProcess, WasiRuntimeError};
+
+/// Heap usage of a process as observed through the allocator exports
+/// (`malloc`, `free` and `realloc`) of its module.
+///
+/// Only the allocations that the host makes by calling those exports through
+/// [`WasiFunctionEnv::malloc`] (and friends) are seen. The module calls its
+/// allocator directly rather than through its exports, so the allocations
+/// it makes itself are not counted and the heap limit does not apply to them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Number of bytes that are currently allocated
+    pub current: u64,
+    /// Highest number of bytes that were allocated at any one time
+    pub peak: u64,
+    /// Number of allocations that have not been freed yet
+    pub allocations: u64,
+}
+
+/// Keeps track of the allocations that were made through the
+/// allocator exports of a process
+#[derive(Debug, Default)]
+pub(crate) struct WasiProcessHeap {
+    allocations: HashMap<u64, u64>,
+    stats: HeapStats,
+}
+
+impl WasiProcessHeap {
+    fn record_alloc(&mut self, ptr: u64, size: u64) {
+        if let Some(old) = self.allocations.insert(ptr, size) {
+            self.stats.current -= old;
+            self.stats.allocations -= 1;
+        }
+        self.stats.current += size;
+        self.stats.allocations += 1;
+        self.stats.peak = self.stats.peak.max(self.stats.current);
+    }
+
+    fn record_free(&mut self, ptr: u64) {
+        if let Some(size) = self.allocations.remove(&ptr) {
+            self.stats.current -= size;
+            self.stats.allocations -= 1;
+        }
+    }
+
+    fn size_of(&self, ptr: u64) -> u64 {
+        self.allocations.get(&ptr).copied().unwrap_or_default()
+    }
+}
+
+impl WasiProcess {
+    /// Returns the heap usage of this process, only allocations that were
+    /// made through [`WasiFunctionEnv::malloc`] (and friends) are counted
+    pub fn heap_stats(&self) -> HeapStats {
+        self.heap.lock().unwrap().stats
+    }
+}
+
+impl WasiFunctionEnv {
+    /// Allocates `size` bytes on the heap of the module using its `malloc`
+    /// export and records the allocation in the [`HeapStats`] of the process.
+    ///
+    /// Returns a null pointer without calling into the module when the
+    /// allocation would exceed the heap limit of the environment
+    /// (see [`WasiEnvBuilder::heap_limit`](crate::WasiEnvBuilder::heap_limit)).
+    pub fn malloc(&self, store: &mut impl AsStoreMut, size: u64) -> Result<u64, WasiRuntimeError> {
+        let env = self.data(store);
+        let malloc = allocator_export(env, "malloc", |handles| handles.malloc.clone())?;
+        let process = env.process.clone();
+        if exceeds_heap_limit(env, size) {
+            return Ok(0);
+        }
+
+        let ptr = call_allocator(store, "malloc", &malloc, &[size])?;
+        if ptr != 0 {
+            process.heap.lock().unwrap().record_alloc(ptr, size);
+        }
+        Ok(ptr)
+    }
+
+    /// Frees memory that was allocated with [`WasiFunctionEnv::malloc`]
+    /// using the `free` export of the module.
+    pub fn free(&self, store: &mut impl AsStoreMut, ptr: u64) -> Result<(), WasiRuntimeError> {
+        let env = self.data(store);
+        let free = allocator_export(env, "free", |handles| handles.free.clone())?;
+        let process = env.process.clone();
+
+        call_allocator(store, "free", &free, &[ptr])?;
+        process.heap.lock().unwrap().record_free(ptr);
+        Ok(())
+    }
+
+    /// Resizes an allocation using the `realloc` export of the module.
+    ///
+    /// Like [`WasiFunctionEnv::malloc`] this returns a null pointer (leaving
+    /// the original allocation untouched) when the heap limit would be exceeded.
+    pub fn realloc(
+        &self,
+        store: &mut impl AsStoreMut,
+        ptr: u64,
+        size: u64,
+    ) -> Result<u64, WasiRuntimeError> {
+        let env = self.data(store);
+        let realloc = allocator_export(env, "realloc", |handles| handles.realloc.clone())?;
+        let process = env.process.clone();
+        let old_size = process.heap.lock().unwrap().size_of(ptr);
+        if exceeds_heap_limit(env, size.saturating_sub(old_size)) {
+            return Ok(0);
+        }
+
+        let new_ptr = call_allocator(store, "realloc", &realloc, &[ptr, size])?;
+        if new_ptr != 0 {
+            let mut heap = process.heap.lock().unwrap();
+            heap.record_free(ptr);
+            heap.record_alloc(new_ptr, size);
+        }
+        Ok(new_ptr)
+    }
+}
+
+fn allocator_export(
+    env: &WasiEnv,
+    name: &str,
+    export: impl FnOnce(&WasiInstanceHandles) -> Option<Function>,
+) -> Result<Function, WasiRuntimeError> {
+    env.try_inner()
+        .and_then(|handles| export(&handles))
+        .ok_or_else(|| ExportError::Missing(name.to_string()).into())
+}
+
+fn exceeds_heap_limit(env: &WasiEnv, additional: u64) -> bool {
+    match env.state.heap_limit {
+        Some(limit) => {
+            let current = env.process.heap.lock().unwrap().stats.current;
+            current.saturating_add(additional) > limit
+        }
+        None => false,
+    }
+}
+
+/// Calls an allocator export, the pointers and sizes are passed as either
+/// 32-bit or 64-bit integers depending on the signature of the export and
+/// are rejected when they do not fit
+fn call_allocator(
+    store: &mut impl AsStoreMut,
+    name: &str,
+    func: &Function,
+    args: &[u64],
+) -> Result<u64, WasiRuntimeError> {
+    let ty = func.ty(store);
+    let params = args
+        .iter()
+        .zip(ty.params())
+        .map(|(arg, ty)| match ty {
+            Type::I64 => Ok(Value::I64(*arg as i64)),
+            Type::I32 => u32::try_from(*arg)
+                .map(|arg| Value::I32(arg as i32))
+                .map_err(|_| {
+                    RuntimeError::new(format!(
+                        "{arg} does not fit the 32-bit parameter of `{name}`"
+                    ))
+                }),
+            ty => Err(RuntimeError::new(format!(
+                "`{name}` takes a parameter of type {ty} rather than an integer"
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let ret = func.call(store, &params)?;
+    Ok(match ret.first() {
+        Some(Value::I64(val)) => *val as u64,
+        Some(Value::I32(val)) => *val as u32 as u64,
+        _ => 0,
+    })
+}
@@ -2,6 +2,7 @@
 
 pub mod backoff;
 pub mod control_plane;
+pub mod heap;
 pub mod process;
 pub mod signal;
 mod task_join_handle;
@@ -9,7 +9,7 @@ use std::{
     convert::TryInto,
     ops::Range,
     sync::{
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicI32, AtomicU32, Ordering},
         Arc, Condvar, Mutex, MutexGuard, RwLock, Weak,
     },
     task::Waker,
@@ -32,9 +32,10 @@ use crate::{
 use super::{
     backoff::WasiProcessCpuBackoff,
     control_plane::{ControlPlaneError, WasiControlPlaneHandle},
+    heap::WasiProcessHeap,
     signal::{SignalDeliveryError, SignalHandlerAbi},
     task_join_handle::OwnedTaskStatus,
-    thread::WasiMemoryLayout,
+    thread::{signal_mask_bit, SignalMask, SignalMaskHow, WasiMemoryLayout},
     TaskStatus,
 };
 
@@ -86,6 +87,11 @@ impl std::fmt::Debug for WasiProcessId {
 
 pub type LockableWasiProcessInner = Arc<(Mutex<WasiProcessInner>, Condvar)>;
 
+/// Highest priority (lowest nice value) that a process can have
+pub const MIN_NICE: i32 = -20;
+/// Lowest priority (highest nice value) that a process can have
+pub const MAX_NICE: i32 = 19;
+
 /// Represents a process running within the compute state
 /// TODO: fields should be private and only accessed via methods.
 #[derive(Debug, Clone)]
@@ -111,6 +117,10 @@ pub struct WasiProcess {
     /// the exponential backoff of CPU is halted (as in CPU
     /// is allowed to run freely)
     pub(crate) cpu_run_tokens: Arc<AtomicU32>,
+    /// Allocations made through the allocator exports of the module
+    pub(crate) heap: Arc<Mutex<WasiProcessHeap>>,
+    /// Advisory scheduling priority (nice value) of the process
+    pub(crate) nice: Arc<AtomicI32>,
 }
 
 /// Represents a freeze of all threads to perform some action
@@ -163,6 +173,9 @@ pub struct WasiProcessInner {
     pub thread_count: u32,
     /// Signals that will be triggered at specific intervals
     pub signal_intervals: HashMap<Signal, WasiSignalInterval>,
+    /// Process directed signals that arrived while every thread had them
+    /// blocked, the first thread to unblock one of them handles it
+    pub pending_signals: Vec<Signal>,
     /// List of all the children spawned from this thread
     pub children: Vec<WasiProcess>,
     /// Represents a checkpoint which blocks all the threads
@@ -418,6 +431,7 @@ impl WasiProcess {
                 threads: Default::default(),
                 thread_count: Default::default(),
                 signal_intervals: Default::default(),
+                pending_signals: Default::default(),
                 children: Default::default(),
                 checkpoint: WasiProcessCheckpoint::Execute,
                 wakers: Default::default(),
@@ -457,6 +471,8 @@ impl WasiProcess {
             ),
             waiting,
             cpu_run_tokens: Arc::new(AtomicU32::new(0)),
+            heap: Default::default(),
+            nice: Arc::new(AtomicI32::new(0)),
         }
     }
 
@@ -479,6 +495,27 @@ impl WasiProcess {
             .unwrap_or(WasiProcessId(0))
     }
 
+    /// Gets the advisory scheduling priority (nice value) of the process
+    pub fn nice(&self) -> i32 {
+        self.nice.load(Ordering::Acquire)
+    }
+
+    /// Sets the advisory scheduling priority (nice value) of the process,
+    /// values outside of the range of nice values are clamped
+    pub fn set_nice(&self, nice: i32) {
+        self.nice
+            .store(nice.clamp(MIN_NICE, MAX_NICE), Ordering::Release);
+    }
+
+    /// Returns true if the process with the given ID is a child of this
+    /// process, or a child of one of its children and so on
+    pub fn has_descendant(&self, pid: WasiProcessId) -> bool {
+        let children = self.lock().children.clone();
+        children
+            .iter()
+            .any(|child| child.pid == pid || child.has_descendant(pid))
+    }
+
     /// Gains access to the process internals
     // TODO: Make this private, all inner access should be exposed with methods.
     pub fn lock(&self) -> MutexGuard<'_, WasiProcessInner> {
@@ -580,6 +617,30 @@ impl WasiProcess {
         signal_process_internal(&self.inner, signal);
     }
 
+    /// Changes the signals that a thread of this process has blocked and
+    /// returns the previous mask (see [`WasiThread::update_signal_mask`]),
+    /// process directed signals that were pending because every thread
+    /// blocked them are handed to the thread if it no longer blocks them
+    pub fn update_signal_mask(
+        &self,
+        thread: &WasiThread,
+        how: SignalMaskHow,
+        signals: SignalMask,
+    ) -> SignalMask {
+        let old = thread.update_signal_mask(how, signals);
+
+        let mut inner = self.inner.0.lock().unwrap();
+        let mask = thread.signal_mask();
+        let (unblocked, pending) = std::mem::take(&mut inner.pending_signals)
+            .into_iter()
+            .partition::<Vec<_>, _>(|signal| mask & signal_mask_bit(*signal) == 0);
+        inner.pending_signals = pending;
+        for signal in unblocked {
+            thread.signal(signal);
+        }
+        old
+    }
+
     /// Takes a snapshot of the process and disables journaling returning
     /// a future that can be waited on for the snapshot to complete
     ///
@@ -849,9 +910,27 @@ fn signal_process_internal(process: &LockableWasiProcessInner, signal: Signal) {
         }
     }
 
-    // Otherwise just send the signal to all the threads
-    for thread in guard.threads.values() {
+    // A kill can not be blocked and takes down every thread
+    if signal == Signal::Sigkill {
+        for thread in guard.threads.values() {
+            thread.signal(signal);
+        }
+        return;
+    }
+
+    // Otherwise the signal is handled by a single thread that does not block
+    // it (preferring the main thread), if every thread blocks it then it stays
+    // pending on the process until one of them unblocks it
+    let mut threads: Vec<_> = guard.threads.values().collect();
+    threads.sort_by_key(|thread| (!thread.is_main(), thread.tid()));
+    let thread = threads
+        .into_iter()
+        .find(|thread| !thread.is_signal_blocked(signal));
+    if let Some(thread) = thread {
         thread.signal(signal);
+    } else if !guard.pending_signals.contains(&signal) {
+        trace!(%pid, "signal-pending({:?})", signal);
+        guard.pending_signals.push(signal);
     }
 }
 
@@ -865,3 +944,83 @@ impl SignalHandlerAbi for WasiProcess {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use wasmer_wasix_types::wasix::ThreadStartType;
+
+    use crate::{
+        os::task::{
+            control_plane::{ControlPlaneConfig, WasiControlPlane},
+            thread::{signal_mask_bit, SignalMaskHow, WasiMemoryLayout},
+        },
+        utils::xxhash_random,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_signals_are_routed_to_unblocked_threads() {
+        let plane = WasiControlPlane::new(ControlPlaneConfig::default());
+        let process = plane.new_process(xxhash_random()).unwrap();
+        let main = process
+            .new_thread(WasiMemoryLayout::default(), ThreadStartType::MainThread)
+            .unwrap();
+        let worker = process
+            .new_thread(
+                WasiMemoryLayout::default(),
+                ThreadStartType::ThreadSpawn { start_ptr: 0 },
+            )
+            .unwrap();
+
+        main.update_signal_mask(SignalMaskHow::Block, signal_mask_bit(Signal::Sigusr1));
+
+        // A process wide signal goes to the thread that does not block it
+        process.signal_process(Signal::Sigusr1);
+        assert_eq!(main.pop_signals(), vec![]);
+        assert_eq!(main.pending_signals(), vec![]);
+        assert_eq!(worker.pop_signals(), vec![Signal::Sigusr1]);
+
+        // A signal sent to the blocking thread waits until it is unblocked
+        process.signal_thread(&main.tid(), Signal::Sigusr1);
+        assert_eq!(main.pop_signals(), vec![]);
+        assert_eq!(main.pending_signals(), vec![Signal::Sigusr1]);
+        assert_eq!(worker.pop_signals(), vec![]);
+
+        let old = main.update_signal_mask(SignalMaskHow::Unblock, signal_mask_bit(Signal::Sigusr1));
+        assert_eq!(old, signal_mask_bit(Signal::Sigusr1));
+        assert_eq!(main.pending_signals(), vec![]);
+        assert_eq!(main.pop_signals(), vec![Signal::Sigusr1]);
+
+        // When every thread blocks a process wide signal it is handled by
+        // the first thread that unblocks it
+        let sigusr1 = signal_mask_bit(Signal::Sigusr1);
+        process.update_signal_mask(&main, SignalMaskHow::Block, sigusr1);
+        process.update_signal_mask(&worker, SignalMaskHow::Block, sigusr1);
+        process.signal_process(Signal::Sigusr1);
+        assert_eq!(main.pending_signals(), vec![]);
+        assert_eq!(worker.pending_signals(), vec![]);
+
+        process.update_signal_mask(&worker, SignalMaskHow::Unblock, sigusr1);
+        assert_eq!(worker.pop_signals(), vec![Signal::Sigusr1]);
+        process.update_signal_mask(&main, SignalMaskHow::Unblock, sigusr1);
+        assert_eq!(main.pop_signals(), vec![]);
+    }
+
+    #[test]
+    fn test_descendants_include_grandchildren_only() {
+        let plane = WasiControlPlane::new(ControlPlaneConfig::default());
+        let parent = plane.new_process(xxhash_random()).unwrap();
+        let child = plane.new_process(xxhash_random()).unwrap();
+        let grandchild = plane.new_process(xxhash_random()).unwrap();
+        let stranger = plane.new_process(xxhash_random()).unwrap();
+        parent.lock().children.push(child.clone());
+        child.lock().children.push(grandchild.clone());
+
+        assert!(parent.has_descendant(child.pid()));
+        assert!(parent.has_descendant(grandchild.pid()));
+        assert!(!parent.has_descendant(parent.pid()));
+        assert!(!parent.has_descendant(stranger.pid()));
+        assert!(!child.has_descendant(parent.pid()));
+    }
+}
@@ -241,6 +241,7 @@ struct WasiThreadState {
     pid: WasiProcessId,
     id: WasiThreadId,
     signals: Mutex<(Vec<Signal>, Vec<Waker>)>,
+    signal_mask: Mutex<SignalMaskState>,
     stack: Mutex<ThreadStack>,
     status: Arc<OwnedTaskStatus>,
     #[cfg(feature = "journal")]
@@ -252,6 +253,46 @@ struct WasiThreadState {
     _task_count_guard: TaskCountGuard,
 }
 
+/// The signals that a thread has blocked along with the signals that
+/// arrived while they were blocked
+#[derive(Debug, Default)]
+struct SignalMaskState {
+    mask: SignalMask,
+    pending: Vec<Signal>,
+}
+
+/// Set of signals (the bit for a signal is `signal - 1`, the same
+/// layout that libc uses for a `sigset_t`)
+pub type SignalMask = u64;
+
+/// How [`WasiThread::update_signal_mask`] changes the signal mask
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalMaskHow {
+    /// Adds the signals to the mask
+    Block,
+    /// Removes the signals from the mask
+    Unblock,
+    /// Replaces the mask with the signals
+    SetMask,
+}
+
+/// Returns the bit that represents a signal in a [`SignalMask`]
+pub fn signal_mask_bit(signal: Signal) -> SignalMask {
+    match signal as u8 {
+        0 => 0,
+        n => 1 << (n - 1),
+    }
+}
+
+/// Returns true if the default action of a signal is to ignore it, these
+/// signals do not interrupt blocking calls
+pub fn signal_is_ignored_by_default(signal: Signal) -> bool {
+    matches!(
+        signal,
+        Signal::Signone | Signal::Sigchld | Signal::Sigcont | Signal::Sigurg | Signal::Sigwinch
+    )
+}
+
 static NO_MORE_BYTES: [u8; 0] = [0u8; 0];
 
 impl WasiThread {
@@ -271,6 +312,7 @@ impl WasiThread {
                 id,
                 status,
                 signals: Mutex::new((Vec::new(), Vec::new())),
+                signal_mask: Mutex::new(SignalMaskState::default()),
                 stack: Mutex::new(ThreadStack::default()),
                 #[cfg(feature = "journal")]
                 check_pointing: AtomicBool::new(false),
@@ -343,11 +385,21 @@ impl WasiThread {
         self.state.status.status().into_finished()
     }
 
-    /// Adds a signal for this thread to process
+    /// Adds a signal for this thread to process, if the thread has the
+    /// signal blocked then it stays pending until it is unblocked
     pub fn signal(&self, signal: Signal) {
         let tid = self.tid();
         tracing::trace!(%tid, "signal-thread({:?})", signal);
 
+        let mut mask = self.state.signal_mask.lock().unwrap();
+        if mask.mask & signal_mask_bit(signal) != 0 {
+            tracing::trace!(%tid, "signal-blocked({:?})", signal);
+            if !mask.pending.contains(&signal) {
+                mask.pending.push(signal);
+            }
+            return;
+        }
+
         let mut guard = self.state.signals.lock().unwrap();
         if !guard.0.contains(&signal) {
             guard.0.push(signal);
@@ -355,6 +407,57 @@ impl WasiThread {
         guard.1.drain(..).for_each(|w| w.wake());
     }
 
+    /// Returns true if the signal is blocked by this thread
+    pub fn is_signal_blocked(&self, signal: Signal) -> bool {
+        let mask = self.state.signal_mask.lock().unwrap();
+        mask.mask & signal_mask_bit(signal) != 0
+    }
+
+    /// Returns the signals that this thread has blocked
+    pub fn signal_mask(&self) -> SignalMask {
+        self.state.signal_mask.lock().unwrap().mask
+    }
+
+    /// Changes the signals that this thread has blocked and returns the
+    /// previous mask, any pending signals that are no longer blocked are
+    /// then delivered to the thread
+    ///
+    /// `SIGKILL` and `SIGSTOP` can not be blocked
+    pub fn update_signal_mask(&self, how: SignalMaskHow, signals: SignalMask) -> SignalMask {
+        let signals =
+            signals & !(signal_mask_bit(Signal::Sigkill) | signal_mask_bit(Signal::Sigstop));
+
+        let mut mask = self.state.signal_mask.lock().unwrap();
+        let old = mask.mask;
+        mask.mask = match how {
+            SignalMaskHow::Block => old | signals,
+            SignalMaskHow::Unblock => old & !signals,
+            SignalMaskHow::SetMask => signals,
+        };
+
+        let new = mask.mask;
+        let (unblocked, pending) = std::mem::take(&mut mask.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|signal| new & signal_mask_bit(*signal) == 0);
+        mask.pending = pending;
+
+        if !unblocked.is_empty() {
+            let mut guard = self.state.signals.lock().unwrap();
+            for signal in unblocked {
+                if !guard.0.contains(&signal) {
+                    guard.0.push(signal);
+                }
+            }
+            guard.1.drain(..).for_each(|w| w.wake());
+        }
+        old
+    }
+
+    /// Returns the signals that arrived while they were blocked
+    pub fn pending_signals(&self) -> Vec<Signal> {
+        self.state.signal_mask.lock().unwrap().pending.clone()
+    }
+
     /// Returns all the signals that are waiting to be processed
     pub fn has_signal(&self, signals: &[Signal]) -> bool {
         let guard = self.state.signals.lock().unwrap();
@@ -413,6 +516,21 @@ impl WasiThread {
         has_signals
     }
 
+    /// Returns true if any of the signals that are waiting to be processed
+    /// interrupts blocking calls (see [`signal_is_ignored_by_default`]),
+    /// otherwise the waker is woken when another signal arrives
+    pub fn has_interrupting_signals_or_subscribe(&self, waker: &Waker) -> bool {
+        let mut guard = self.state.signals.lock().unwrap();
+        let interrupted = guard
+            .0
+            .iter()
+            .any(|signal| !signal_is_ignored_by_default(*signal));
+        if !interrupted && !guard.1.iter().any(|w| w.will_wake(waker)) {
+            guard.1.push(waker.clone());
+        }
+        interrupted
+    }
+
     /// Returns all the signals that are waiting to be processed
     pub fn pop_signals(&self) -> Vec<Signal> {
         let mut guard = self.state.signals.lock().unwrap();
@@ -625,6 +743,9 @@ pub enum WasiThreadError {
     /// This will happen if WASM is running in a thread has not been created by the spawn_wasm call
     #[error("WASM context is invalid")]
     InvalidWasmContext,
+    /// The task manager can not accept any more tasks right now
+    #[error("The task queue is full")]
+    QueueFull,
 }
 
 impl From<WasiThreadError> for Errno {
@@ -637,6 +758,7 @@ impl From<WasiThreadError> for Errno {
             WasiThreadError::InstanceCreateFailed(_) => Errno::Noexec,
             WasiThreadError::InitFailed(_) => Errno::Noexec,
             WasiThreadError::InvalidWasmContext => Errno::Noexec,
+            WasiThreadError::QueueFull => Errno::Again,
         }
     }
 }
@@ -420,8 +420,14 @@ pub struct WasiTtyState {
     pub stdout_tty: bool,
     pub stderr_tty: bool,
     pub echo: bool,
+    /// Canonical mode, input is only made available once a full line
+    /// has been entered
     pub line_buffered: bool,
     pub line_feeds: bool,
+    /// Type of the terminal (the `TERM` of the host) when it is known
+    pub term_type: Option<String>,
+    /// Whether the terminal is able to display colors
+    pub colors: bool,
 }
 
 impl Default for WasiTtyState {
@@ -437,10 +443,25 @@ impl Default for WasiTtyState {
             echo: false,
             line_buffered: false,
             line_feeds: true,
+            term_type: None,
+            colors: false,
         }
     }
 }
 
+impl WasiTtyState {
+    /// Returns true when the terminal is in canonical (line buffered) mode
+    pub fn is_canonical(&self) -> bool {
+        self.line_buffered
+    }
+
+    /// Returns true when the terminal is in raw mode, meaning that input is
+    /// neither echoed nor line buffered
+    pub fn is_raw(&self) -> bool {
+        !self.echo && !self.line_buffered
+    }
+}
+
 /// Provides access to a TTY.
 pub trait TtyBridge: std::fmt::Debug {
     /// Resets the values
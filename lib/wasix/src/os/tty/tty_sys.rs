@@ -18,6 +18,8 @@ impl TtyBridge for SysTty {
         let stdout_tty = sys::is_stdout_tty();
         let stderr_tty = sys::is_stderr_tty();
         let (cols, rows) = sys_terminal_size::get_terminal_size();
+        let term_type = std::env::var("TERM").ok().filter(|t| !t.is_empty());
+        let colors = stdout_tty && term_supports_colors(term_type.as_deref());
 
         WasiTtyState {
             cols,
@@ -30,10 +32,18 @@ impl TtyBridge for SysTty {
             echo,
             line_buffered,
             line_feeds,
+            term_type,
+            colors,
         }
     }
 
     fn tty_set(&self, tty_state: WasiTtyState) {
+        // The modes are only changed when the host terminal is inherited,
+        // otherwise stdin is a pipe or a file that has no modes
+        if !sys::is_stdin_tty() {
+            return;
+        }
+
         if tty_state.echo {
             sys::set_mode_echo().ok();
         } else {
@@ -52,6 +62,28 @@ impl TtyBridge for SysTty {
     }
 }
 
+/// Guesses whether a terminal can display colors, `NO_COLOR` always wins
+/// and otherwise `COLORTERM` or a well known `TERM` is needed
+fn term_supports_colors(term_type: Option<&str>) -> bool {
+    if std::env::var_os("NO_COLOR").is_some() {
+        return false;
+    }
+    if std::env::var_os("COLORTERM").is_some() {
+        return true;
+    }
+    match term_type {
+        None | Some("dumb") => false,
+        Some(term) => {
+            term.contains("color")
+                || term.contains("xterm")
+                || term.contains("screen")
+                || term.contains("tmux")
+                || term.contains("linux")
+                || term.contains("ansi")
+        }
+    }
+}
+
 mod sys_terminal_size {
     static DEFAULT_SIZE: (u32, u32) = (80, 25);
 
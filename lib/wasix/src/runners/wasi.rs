@@ -425,6 +425,9 @@ impl crate::runners::Runner for WasiRunner {
                                     WasiRuntimeError::Wasi(WasiError::UnknownWasiVersion) => {
                                         WasiRuntimeError::Wasi(WasiError::UnknownWasiVersion)
                                     }
+                                    WasiRuntimeError::Wasi(WasiError::CpuBudgetExceeded) => {
+                                        WasiRuntimeError::Wasi(WasiError::CpuBudgetExceeded)
+                                    }
                                     WasiRuntimeError::Wasi(WasiError::DeepSleep(_)) => {
                                         WasiRuntimeError::Anyhow(Arc::new(anyhow::format_err!(
                                             "deep-sleep"
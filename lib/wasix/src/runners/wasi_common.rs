@@ -330,6 +330,10 @@ impl<F: FileSystem> virtual_fs::FileSystem for RelativeOrAbsolutePathHack<F> {
         self.execute(path, |fs, p| fs.remove_dir(p))
     }
 
+    fn remove_dir_all(&self, path: &Path) -> virtual_fs::Result<()> {
+        self.execute(path, |fs, p| fs.remove_dir_all(p))
+    }
+
     fn rename<'a>(&'a self, from: &Path, to: &Path) -> BoxFuture<'a, virtual_fs::Result<()>> {
         let from = from.to_owned();
         let to = to.to_owned();
@@ -340,6 +344,10 @@ impl<F: FileSystem> virtual_fs::FileSystem for RelativeOrAbsolutePathHack<F> {
         self.execute(path, |fs, p| fs.metadata(p))
     }
 
+    fn statvfs(&self, path: &Path) -> virtual_fs::Result<virtual_fs::FsStat> {
+        self.execute(path, |fs, p| fs.statvfs(p))
+    }
+
     fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<virtual_fs::Metadata> {
         self.execute(path, |fs, p| fs.symlink_metadata(p))
     }
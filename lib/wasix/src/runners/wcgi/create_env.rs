@@ -35,6 +35,9 @@ pub(crate) async fn default_create_env(conf: CreateEnvConfig) -> anyhow::Result<
             insecure_allow_all: true,
             http_client: HttpClientCapabilityV1::new_allow_all(),
             threading: Default::default(),
+            allow_host_exec: false,
+            host_exec_allowlist: Vec::new(),
+            denied_syscalls: Default::default(),
         });
     let env = builder.build()?;
 
@@ -0,0 +1,174 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use wasmer_wasix_types::wasi::{Errno, Snapshot0Clockid, Timestamp};
+
+use crate::{
+    syscalls::{platform_clock_res_get, platform_clock_time_get},
+    VirtualTaskManager,
+};
+
+/// The clocks of the runtime.
+///
+/// [`Clock::elapsed`] is a monotonic clock that measures how long the runtime
+/// has been up, it is not affected by `clock_time_set` and is used for things
+/// like `proc_uptime` and `/proc/uptime`. The other methods serve the clocks
+/// that the guest reads with `clock_time_get` and `clock_res_get` and sleeps
+/// on with `clock_nanosleep`, they default to the clocks of the host.
+///
+/// Replacing the clock with a controllable implementation makes the time that
+/// a program (or a replayed journal) observes deterministic.
+pub trait Clock
+where
+    Self: fmt::Debug,
+{
+    /// The time that has passed since the clock was started.
+    fn elapsed(&self) -> Duration;
+
+    /// The current value of a clock of the guest in nanoseconds.
+    fn now(&self, id: Snapshot0Clockid) -> Result<Timestamp, Errno> {
+        platform_clock_time_get(id, 1).map(|nanos| nanos as Timestamp)
+    }
+
+    /// The resolution of a clock of the guest in nanoseconds.
+    fn resolution(&self, id: Snapshot0Clockid) -> Result<Timestamp, Errno> {
+        platform_clock_res_get(id).map(|nanos| nanos as Timestamp)
+    }
+
+    /// Returns a future that completes once `duration` has passed on a clock
+    /// of the guest, this is what `clock_nanosleep` waits on.
+    ///
+    /// The default implementation waits on the timer of `tasks` until the
+    /// clock of the host has moved by `duration`, which matches the default
+    /// [`Clock::now`]. Clocks that do not move along with the host must
+    /// override it (a clock that only moves by hand may for instance be
+    /// advanced to the deadline).
+    fn sleep(
+        &self,
+        id: Snapshot0Clockid,
+        duration: Duration,
+        tasks: Arc<dyn VirtualTaskManager>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        let host_now = move || platform_clock_time_get(id, 1).map(|nanos| nanos as Timestamp);
+        Box::pin(async move {
+            let Ok(start) = host_now() else {
+                return;
+            };
+            let deadline = start.saturating_add(duration.as_nanos() as Timestamp);
+
+            // The timer may fire early (or the clock may have been adjusted)
+            // hence the clock is checked again after every wake up
+            while let Ok(now) = host_now() {
+                if now >= deadline {
+                    break;
+                }
+                tasks.sleep_now(Duration::from_nanos(deadline - now)).await;
+            }
+        })
+    }
+}
+
+pub type DynClock = dyn Clock + Send + Sync;
+
+/// The clock used by runtimes that do not provide their own, it is shared by
+/// all of them and started the first time it is used.
+pub(crate) static DEFAULT_CLOCK: Lazy<Arc<DynClock>> =
+    Lazy::new(|| Arc::new(SystemClock::default()));
+
+/// A [`Clock`] backed by the clocks of the host.
+#[derive(Debug)]
+pub struct SystemClock {
+    started: Duration,
+}
+
+impl SystemClock {
+    fn monotonic() -> Duration {
+        let nanos = platform_clock_time_get(Snapshot0Clockid::Monotonic, 1).unwrap_or_default();
+        Duration::from_nanos(nanos.max(0) as u64)
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            started: Self::monotonic(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn elapsed(&self) -> Duration {
+        Self::monotonic().saturating_sub(self.started)
+    }
+}
+
+/// A [`Clock`] that only moves when it is told to, which makes it possible
+/// to test time dependent behaviour deterministically.
+///
+/// All the clocks of the guest read the elapsed time and sleeping on them
+/// advances the clock to the end of the sleep right away.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut elapsed = self.elapsed.lock().unwrap();
+        *elapsed = elapsed.saturating_add(duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+
+    fn now(&self, _id: Snapshot0Clockid) -> Result<Timestamp, Errno> {
+        self.elapsed()
+            .as_nanos()
+            .try_into()
+            .map_err(|_| Errno::Overflow)
+    }
+
+    fn resolution(&self, _id: Snapshot0Clockid) -> Result<Timestamp, Errno> {
+        Ok(1)
+    }
+
+    fn sleep(
+        &self,
+        _id: Snapshot0Clockid,
+        duration: Duration,
+        _tasks: Arc<dyn VirtualTaskManager>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        self.advance(duration);
+        Box::pin(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_only_moves_when_advanced() {
+        let clock = ManualClock::new();
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+
+        clock.advance(Duration::from_secs(10));
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(clock.elapsed(), Duration::from_millis(10_500));
+        assert_eq!(clock.now(Snapshot0Clockid::Monotonic), Ok(10_500_000_000));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod clock;
 pub mod module_cache;
 pub mod package_loader;
 pub mod resolver;
@@ -16,7 +17,7 @@ use std::{
 use futures::future::BoxFuture;
 use virtual_net::{DynVirtualNetworking, VirtualNetworking};
 use wasmer::{Module, RuntimeError};
-use wasmer_wasix_types::wasi::ExitCode;
+use wasmer_wasix_types::wasi::{Errno, ExitCode};
 
 #[cfg(feature = "journal")]
 use crate::journal::DynJournal;
@@ -24,6 +25,7 @@ use crate::{
     http::{DynHttpClient, HttpClient},
     os::TtyBridge,
     runtime::{
+        clock::{DynClock, DEFAULT_CLOCK},
         module_cache::{ModuleCache, ThreadLocalCache},
         package_loader::{PackageLoader, UnsupportedPackageLoader},
         resolver::{BackendSource, MultiSource, Source},
@@ -38,6 +40,51 @@ pub enum TaintReason {
     RuntimeError(RuntimeError),
 }
 
+/// Notable lifecycle events of the programs that are run by a [`Runtime`],
+/// see [`Runtime::on_event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RuntimeEvent {
+    /// A module was instantiated and initialized for the process `pid`
+    InstanceCreated { pid: u32 },
+    /// A module could not be instantiated or initialized for the process `pid`
+    InstantiationFailed { pid: u32, error: String },
+    /// A syscall that the thread `tid` of the process `pid` made returned
+    /// the error `errno` to the guest, `Errno::Again` is not reported as it
+    /// is routine for nonblocking descriptors
+    SyscallFailed {
+        pid: u32,
+        tid: u32,
+        syscall: &'static str,
+        errno: Errno,
+    },
+    /// The linear memory of the process `pid` grew to `pages` pages, which
+    /// is noticed as soon as one of its threads returns from a syscall
+    MemoryGrown { pid: u32, pages: u32 },
+    /// The main thread of the process `pid` exited with `code`
+    ProcessExited { pid: u32, code: ExitCode },
+}
+
+/// Callback that receives the [`RuntimeEvent`]s of a [`PluggableRuntime`].
+#[derive(Clone)]
+pub struct EventSink(Arc<dyn Fn(RuntimeEvent) + Send + Sync>);
+
+impl EventSink {
+    pub fn new(sink: impl Fn(RuntimeEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    pub fn emit(&self, event: RuntimeEvent) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for EventSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSink").finish_non_exhaustive()
+    }
+}
+
 /// Runtime components used when running WebAssembly programs.
 ///
 /// Think of this as the "System" in "WebAssembly Systems Interface".
@@ -97,6 +144,11 @@ where
         None
     }
 
+    /// The monotonic clock that measures the uptime of the runtime.
+    fn clock(&self) -> Arc<DynClock> {
+        DEFAULT_CLOCK.clone()
+    }
+
     /// Load a a Webassembly module, trying to use a pre-compiled version if possible.
     fn load_module<'a>(&'a self, wasm: &'a [u8]) -> BoxFuture<'a, Result<Module, SpawnError>> {
         let engine = self.engine();
@@ -119,6 +171,17 @@ where
     /// for multiple reasons however the most common is a panic within the process
     fn on_taint(&self, _reason: TaintReason) {}
 
+    /// Callback that is invoked for notable lifecycle events of the programs
+    /// that are run, independently of how `tracing` is configured
+    fn on_event(&self, _event: RuntimeEvent) {}
+
+    /// Whether the events passed to [`Runtime::on_event`] are observed at
+    /// all, runtimes that drop them return `false` so that the events are
+    /// not even built
+    fn observes_events(&self) -> bool {
+        true
+    }
+
     /// The list of journals which will be used to restore the state of the
     /// runtime at a particular point in time
     #[cfg(feature = "journal")]
@@ -185,6 +248,16 @@ pub struct DefaultTty {
     state: Mutex<WasiTtyState>,
 }
 
+impl DefaultTty {
+    /// Creates a TTY that starts out with the given state, which is how
+    /// the terminal type and capabilities are reported to the guest
+    pub fn new(state: WasiTtyState) -> Self {
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+}
+
 impl TtyBridge for DefaultTty {
     fn reset(&self) {
         let mut state = self.state.lock().unwrap();
@@ -214,6 +287,8 @@ pub struct PluggableRuntime {
     pub engine: Option<wasmer::Engine>,
     pub module_cache: Arc<dyn ModuleCache + Send + Sync>,
     pub tty: Option<Arc<dyn TtyBridge + Send + Sync>>,
+    pub clock: Arc<DynClock>,
+    pub event_sink: Option<EventSink>,
     #[cfg(feature = "journal")]
     pub journals: Vec<Arc<DynJournal>>,
 }
@@ -250,6 +325,8 @@ impl PluggableRuntime {
             source: Arc::new(source),
             package_loader: Arc::new(loader),
             module_cache: Arc::new(module_cache::in_memory()),
+            clock: DEFAULT_CLOCK.clone(),
+            event_sink: None,
             #[cfg(feature = "journal")]
             journals: Vec::new(),
         }
@@ -302,6 +379,20 @@ impl PluggableRuntime {
         self
     }
 
+    pub fn set_clock(&mut self, clock: Arc<DynClock>) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets the callback that receives the [`RuntimeEvent`]s of this runtime
+    pub fn set_event_sink(
+        &mut self,
+        sink: impl Fn(RuntimeEvent) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.event_sink = Some(EventSink::new(sink));
+        self
+    }
+
     #[cfg(feature = "journal")]
     pub fn add_journal(&mut self, journal: Arc<DynJournal>) -> &mut Self {
         self.journals.push(journal);
@@ -318,6 +409,20 @@ impl Runtime for PluggableRuntime {
         self.http_client.as_ref()
     }
 
+    fn clock(&self) -> Arc<DynClock> {
+        self.clock.clone()
+    }
+
+    fn on_event(&self, event: RuntimeEvent) {
+        if let Some(sink) = self.event_sink.as_ref() {
+            sink.emit(event);
+        }
+    }
+
+    fn observes_events(&self) -> bool {
+        self.event_sink.is_some()
+    }
+
     fn package_loader(&self) -> Arc<dyn PackageLoader + Send + Sync> {
         Arc::clone(&self.package_loader)
     }
@@ -373,6 +478,7 @@ pub struct OverriddenRuntime {
     engine: Option<wasmer::Engine>,
     module_cache: Option<Arc<dyn ModuleCache + Send + Sync>>,
     tty: Option<Arc<dyn TtyBridge + Send + Sync>>,
+    clock: Option<Arc<DynClock>>,
     #[cfg(feature = "journal")]
     journals: Option<Vec<Arc<DynJournal>>>,
 }
@@ -389,6 +495,7 @@ impl OverriddenRuntime {
             engine: None,
             module_cache: None,
             tty: None,
+            clock: None,
             #[cfg(feature = "journal")]
             journals: None,
         }
@@ -438,6 +545,11 @@ impl OverriddenRuntime {
         self
     }
 
+    pub fn with_clock(mut self, clock: Arc<DynClock>) -> Self {
+        self.clock.replace(clock);
+        self
+    }
+
     #[cfg(feature = "journal")]
     pub fn with_journals(mut self, journals: Vec<Arc<DynJournal>>) -> Self {
         self.journals.replace(journals);
@@ -518,6 +630,22 @@ impl Runtime for OverriddenRuntime {
         }
     }
 
+    fn clock(&self) -> Arc<DynClock> {
+        if let Some(clock) = self.clock.as_ref() {
+            clock.clone()
+        } else {
+            self.inner.clock()
+        }
+    }
+
+    fn on_event(&self, event: RuntimeEvent) {
+        self.inner.on_event(event)
+    }
+
+    fn observes_events(&self) -> bool {
+        self.inner.observes_events()
+    }
+
     #[cfg(feature = "journal")]
     fn journals(&self) -> &'_ Vec<Arc<DynJournal>> {
         if let Some(journals) = self.journals.as_ref() {
@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use sha2::{Digest, Sha256};
 use tempfile::NamedTempFile;
 use tokio::io::AsyncWriteExt;
 use wasmer::{Engine, Module};
@@ -9,8 +10,18 @@ use crate::runtime::module_cache::{CacheError, ModuleCache, ModuleHash};
 use crate::runtime::task_manager::tokio::TokioTaskManager;
 use crate::runtime::task_manager::VirtualTaskManagerExt;
 
+/// Marks a cached artifact that is prefixed with the SHA-256 checksum of the
+/// serialized module.
+const CHECKSUM_MAGIC: &[u8; 8] = b"wxcksum1";
+const CHECKSUM_HEADER_LEN: usize = CHECKSUM_MAGIC.len() + 32;
+
 /// A cache that saves modules to a folder on the host filesystem using
 /// [`Module::serialize()`].
+///
+/// Every artifact is stored together with a checksum of its contents. An
+/// artifact that fails the checksum (e.g. because the process crashed while
+/// it was being written) is treated as a cache miss so the module gets
+/// compiled again.
 #[derive(Debug, Clone)]
 pub struct FileSystemCache {
     cache_dir: PathBuf,
@@ -56,29 +67,34 @@ impl ModuleCache for FileSystemCache {
                     task_manager
                     .spawn_await({
 
-                        move || match deserialize(&bytes, &engine) {
-                            Ok(m) => {
-                                tracing::debug!("Cache hit!");
-                                Ok(m)
-                            }
-                            Err(e) => {
+                        move || {
+                            let Some(serialized) = verify_checksum(&bytes) else {
                                 tracing::debug!(
                                     %key,
                                     path=%path.display(),
-                                    error=&e as &dyn std::error::Error,
-                                    "Deleting the cache file because the artifact couldn't be deserialized",
+                                    "Deleting the cache file because its checksum doesn't match",
                                 );
-
-                                if let Err(e) = std::fs::remove_file(&path) {
-                                    tracing::warn!(
+                                remove_corrupted(key, &path);
+                                return Err(CacheError::NotFound);
+                            };
+
+                            match deserialize(serialized, &engine) {
+                                Ok(m) => {
+                                    tracing::debug!("Cache hit!");
+                                    Ok(m)
+                                }
+                                Err(e) => {
+                                    tracing::debug!(
                                         %key,
                                         path=%path.display(),
                                         error=&e as &dyn std::error::Error,
-                                        "Unable to remove the corrupted cache file",
+                                        "Deleting the cache file because the artifact couldn't be deserialized",
                                     );
-                                }
 
-                                Err(e)
+                                    remove_corrupted(key, &path);
+
+                                    Err(e)
+                                }
                             }
                         }
                     })
@@ -131,13 +147,37 @@ impl ModuleCache for FileSystemCache {
                         .await
                         .unwrap()?;
 
+                    let checksum = Sha256::digest(&serialized);
+
                     let mut writer = tokio::io::BufWriter::new(&mut file);
+                    if let Err(error) = writer.write_all(CHECKSUM_MAGIC).await {
+                        return Err(CacheError::FileWrite { path, error });
+                    }
+                    if let Err(error) = writer.write_all(&checksum).await {
+                        return Err(CacheError::FileWrite { path, error });
+                    }
                     if let Err(error) = writer.write_all(&serialized).await {
                         return Err(CacheError::FileWrite { path, error });
                     }
                     if let Err(error) = writer.flush().await {
                         return Err(CacheError::FileWrite { path, error });
                     }
+                    if let Err(error) = file.sync_all().await {
+                        return Err(CacheError::FileWrite { path, error });
+                    }
+
+                    // Only move the artifact into place once everything that
+                    // was written can be read back with a matching checksum
+                    let written = read_file(&temp).await?;
+                    if verify_checksum(&written) != Some(&serialized[..]) {
+                        return Err(CacheError::FileWrite {
+                            path,
+                            error: std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "the written artifact doesn't match its checksum",
+                            ),
+                        });
+                    }
 
                     temp.persist(&path).map_err(CacheError::other)?;
                     tracing::debug!(path=%path.display(), "Saved to disk");
@@ -161,6 +201,38 @@ async fn read_file(path: &Path) -> Result<Vec<u8>, CacheError> {
     }
 }
 
+/// Checks the checksum of a cached artifact and returns the serialized
+/// module, or `None` if the artifact is corrupted.
+///
+/// Artifacts written before checksums were added are returned as they are.
+fn verify_checksum(bytes: &[u8]) -> Option<&[u8]> {
+    if !bytes.starts_with(CHECKSUM_MAGIC) {
+        return Some(bytes);
+    }
+    if bytes.len() < CHECKSUM_HEADER_LEN {
+        return None;
+    }
+
+    let (header, serialized) = bytes.split_at(CHECKSUM_HEADER_LEN);
+    let checksum = &header[CHECKSUM_MAGIC.len()..];
+    if Sha256::digest(serialized).as_slice() == checksum {
+        Some(serialized)
+    } else {
+        None
+    }
+}
+
+fn remove_corrupted(key: ModuleHash, path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        tracing::warn!(
+            %key,
+            path=%path.display(),
+            error=&e as &dyn std::error::Error,
+            "Unable to remove the corrupted cache file",
+        );
+    }
+}
+
 fn deserialize(bytes: &[u8], engine: &Engine) -> Result<Module, CacheError> {
     // We used to compress our compiled modules using LZW encoding in the past.
     // This was removed because it has a negative impact on startup times for
@@ -276,6 +348,39 @@ mod tests {
         assert_eq!(exports, ["add"]);
     }
 
+    #[tokio::test]
+    async fn corrupted_artifact_is_recompiled() {
+        let temp = TempDir::new().unwrap();
+        let engine = Engine::default();
+        let module = Module::new(&engine, ADD_WAT).unwrap();
+        let key = ModuleHash::xxhash_from_bytes([0; 8]);
+        let cache = FileSystemCache::new(temp.path(), create_tokio_task_manager());
+        let expected_path = cache.path(key, engine.deterministic_id());
+        cache.save(key, &engine, &module).await.unwrap();
+
+        // Flip a byte in the middle of the artifact
+        let mut bytes = std::fs::read(&expected_path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        std::fs::write(&expected_path, &bytes).unwrap();
+
+        let err = cache.load(key, &engine).await.unwrap_err();
+        assert!(matches!(err, CacheError::NotFound));
+        assert!(!expected_path.exists());
+
+        let module = crate::runtime::load_module(&engine, &cache, ADD_WAT, key)
+            .await
+            .unwrap();
+
+        let exports: Vec<_> = module
+            .exports()
+            .map(|export| export.name().to_string())
+            .collect();
+        assert_eq!(exports, ["add"]);
+        // The recompiled module was written back to the cache
+        cache.load(key, &engine).await.unwrap();
+    }
+
     /// For backwards compatibility, make sure we can still work with LZW
     /// compressed modules.
     #[tokio::test]
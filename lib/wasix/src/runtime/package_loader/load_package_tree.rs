@@ -573,6 +573,11 @@ where
         self.inner.metadata(&path)
     }
 
+    fn statvfs(&self, path: &Path) -> virtual_fs::Result<virtual_fs::FsStat> {
+        let path = self.path(path)?;
+        self.inner.statvfs(&path)
+    }
+
     fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<virtual_fs::Metadata> {
         let path = self.path(path)?;
         self.inner.symlink_metadata(&path)
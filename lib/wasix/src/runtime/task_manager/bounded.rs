@@ -0,0 +1,220 @@
+use std::{pin::Pin, sync::Arc, time::Duration};
+
+use futures::{future::BoxFuture, Future};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::os::task::thread::WasiThreadError;
+
+use super::{tokio::TokioTaskManager, TaskWasm, VirtualTaskManager};
+
+/// A task manager that owns a dedicated tokio runtime with a fixed number
+/// of worker threads and a bounded queue of tasks.
+///
+/// No more than `workers` asynchronous tasks run at the same time (they are
+/// run by the worker threads). Dedicated threads and the threads of the
+/// WebAssembly processes start straight away, as they may wait on each
+/// other (joins, futexes or barriers) and would deadlock if they had to
+/// wait for a worker. Tasks of every kind that are queued or running count
+/// against the queue size, beyond which new tasks are rejected.
+///
+/// Giving every tenant its own [`BoundedTaskManager`] isolates them from
+/// each other, the work of one tenant can not monopolize the threads that
+/// run the work of another.
+#[derive(Debug)]
+pub struct BoundedTaskManager {
+    inner: TokioTaskManager,
+    workers: usize,
+    queue: Arc<Semaphore>,
+}
+
+impl BoundedTaskManager {
+    /// Maximum number of asynchronous tasks that are queued or running
+    /// unless configured otherwise
+    pub const DEFAULT_QUEUE_SIZE: usize = 1024;
+
+    /// Creates a task manager whose runtime has `workers` worker threads
+    pub fn new(workers: usize) -> std::io::Result<Self> {
+        let workers = workers.max(1);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(workers)
+            .thread_name("BoundedTaskManager Worker")
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            inner: TokioTaskManager::new(runtime),
+            workers,
+            queue: Arc::new(Semaphore::new(Self::DEFAULT_QUEUE_SIZE)),
+        })
+    }
+
+    /// Limits the number of tasks that are queued or running, any task
+    /// beyond this limit is rejected with
+    /// [`WasiThreadError::QueueFull`]
+    pub fn with_queue_size(mut self, queue_size: usize) -> Self {
+        self.set_queue_size(queue_size);
+        self
+    }
+
+    pub fn set_queue_size(&mut self, queue_size: usize) {
+        self.queue = Arc::new(Semaphore::new(queue_size.max(1)));
+    }
+
+    /// Number of worker threads of the runtime
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn runtime_handle(&self) -> tokio::runtime::Handle {
+        self.inner.runtime_handle()
+    }
+
+    /// Takes a place in the queue for a task that is about to be spawned
+    fn enqueue(&self) -> Result<OwnedSemaphorePermit, WasiThreadError> {
+        self.queue
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| WasiThreadError::QueueFull)
+    }
+}
+
+impl VirtualTaskManager for BoundedTaskManager {
+    /// See [`VirtualTaskManager::sleep_now`].
+    fn sleep_now(&self, time: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + Sync>> {
+        self.inner.sleep_now(time)
+    }
+
+    /// See [`VirtualTaskManager::task_shared`].
+    fn task_shared(
+        &self,
+        task: Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        // The permit is held until the task finishes
+        let permit = self.enqueue()?;
+
+        self.inner.task_shared(Box::new(move || {
+            Box::pin(async move {
+                task().await;
+                drop(permit);
+            })
+        }))
+    }
+
+    /// See [`VirtualTaskManager::task_wasm`].
+    fn task_wasm(&self, mut task: TaskWasm) -> Result<(), WasiThreadError> {
+        let queued = self.enqueue()?;
+        let run = task.run;
+        task.run = Box::new(move |props| {
+            run(props);
+            drop(queued);
+        });
+        self.inner.task_wasm(task)
+    }
+
+    /// See [`VirtualTaskManager::task_dedicated`].
+    fn task_dedicated(
+        &self,
+        task: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<(), WasiThreadError> {
+        let queued = self.enqueue()?;
+        self.inner.task_dedicated(Box::new(move || {
+            task();
+            drop(queued);
+        }))
+    }
+
+    /// See [`VirtualTaskManager::thread_parallelism`].
+    fn thread_parallelism(&self) -> Result<usize, WasiThreadError> {
+        Ok(self.workers.min(self.inner.thread_parallelism()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_bounded_workers_limit_concurrency() {
+        let manager = BoundedTaskManager::new(2).unwrap();
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = mpsc::channel();
+
+        for _ in 0..8 {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            let tx = tx.clone();
+            manager
+                .task_shared(Box::new(move || {
+                    Box::pin(async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now, Ordering::SeqCst);
+
+                        // Blocks the worker so the other tasks have to wait for it
+                        std::thread::sleep(Duration::from_millis(50));
+
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        tx.send(()).unwrap();
+                    })
+                }))
+                .unwrap();
+        }
+
+        for _ in 0..8 {
+            rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        }
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_bounded_dedicated_threads_do_not_wait_for_workers() {
+        let manager = BoundedTaskManager::new(2).unwrap();
+        // Every thread waits for all the others, which only works out if
+        // more of them than there are workers run at the same time
+        let barrier = Arc::new(std::sync::Barrier::new(8));
+        let (tx, rx) = mpsc::channel();
+
+        for _ in 0..8 {
+            let barrier = barrier.clone();
+            let tx = tx.clone();
+            manager
+                .task_dedicated(Box::new(move || {
+                    barrier.wait();
+                    tx.send(()).unwrap();
+                }))
+                .unwrap();
+        }
+
+        for _ in 0..8 {
+            rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        }
+        assert!(manager.thread_parallelism().unwrap() <= 2);
+    }
+
+    #[test]
+    fn test_bounded_queue_rejects_excess_tasks() {
+        let manager = BoundedTaskManager::new(1).unwrap().with_queue_size(1);
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let (done_tx, done_rx) = mpsc::channel();
+
+        manager
+            .task_shared(Box::new(move || {
+                Box::pin(async move {
+                    release_rx.recv().unwrap();
+                    done_tx.send(()).unwrap();
+                })
+            }))
+            .unwrap();
+
+        let res = manager.task_shared(Box::new(|| Box::pin(async {})));
+        assert!(matches!(res, Err(WasiThreadError::QueueFull)));
+
+        release_tx.send(()).unwrap();
+        done_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+}
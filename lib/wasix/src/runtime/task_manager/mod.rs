@@ -1,3 +1,5 @@
+#[cfg(feature = "sys-thread")]
+pub mod bounded;
 // TODO: should be behind a different , tokio specific feature flag.
 #[cfg(feature = "sys-thread")]
 pub mod tokio;
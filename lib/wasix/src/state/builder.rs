@@ -1,14 +1,17 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
 use rand::Rng;
 use thiserror::Error;
-use virtual_fs::{ArcFile, FileSystem, FsError, TmpFileSystem, VirtualFile};
+use virtual_fs::{
+    ArcFile, FileSystem, FsError, StaticFile, SysctlFileSystem, TmpFileSystem, VirtualFile,
+};
 use wasmer::{AsStoreMut, Extern, Imports, Instance, Module, Store};
 use wasmer_config::package::PackageId;
 
@@ -17,9 +20,10 @@ use crate::journal::{DynJournal, SnapshotTrigger};
 use crate::{
     bin_factory::{BinFactory, BinaryPackage},
     capabilities::Capabilities,
-    fs::{WasiFs, WasiFsRoot, WasiInodes},
+    fs::{Fd, Kind, UptimeFileSystem, WasiFs, WasiFsRoot, WasiInodes},
     os::task::control_plane::{ControlPlaneConfig, ControlPlaneError, WasiControlPlane},
-    state::WasiState,
+    runtime::{clock::DynClock, DynRuntime, OverriddenRuntime},
+    state::{CpuTimeBudget, WasiState, DEFAULT_DOMAINNAME, DEFAULT_HOSTNAME, DEFAULT_MAX_HOST_OPS},
     syscalls::{
         rewind_ext2,
         types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO},
@@ -28,6 +32,7 @@ use crate::{
     Runtime, WasiEnv, WasiError, WasiFunctionEnv, WasiRuntimeError,
 };
 use wasmer_types::ModuleHash;
+use wasmer_wasix_types::wasi::{Fd as WasiFd, Fdflags, Rights};
 
 use super::env::WasiEnvInit;
 
@@ -65,6 +70,9 @@ pub struct WasiEnvBuilder {
     pub(super) stdout: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stderr: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
     pub(super) stdin: Option<Box<dyn VirtualFile + Send + Sync + 'static>>,
+    /// Files that are installed at fixed file descriptors.
+    #[allow(clippy::type_complexity)]
+    pub(super) preopen_fds: Vec<(WasiFd, Box<dyn VirtualFile + Send + Sync + 'static>, Rights)>,
     pub(super) fs: Option<WasiFsRoot>,
     pub(super) runtime: Option<Arc<dyn crate::Runtime + Send + Sync + 'static>>,
     pub(super) current_dir: Option<PathBuf>,
@@ -82,6 +90,34 @@ pub struct WasiEnvBuilder {
     pub(super) capabilites: Capabilities,
     pub(super) additional_imports: Imports,
 
+    /// Maximum duration of host backed operations and the number of them
+    /// that may be in flight at once.
+    pub(super) host_op_timeout: Option<Duration>,
+    pub(super) max_host_ops: Option<usize>,
+
+    /// Maximum amount of CPU time the instance may consume.
+    pub(super) cpu_time_budget: Option<Duration>,
+
+    /// Host and domain name that are visible to the guest.
+    pub(super) hostname: Option<String>,
+    pub(super) domainname: Option<String>,
+
+    /// Maximum number of bytes allocated through the allocator exports.
+    pub(super) heap_limit: Option<u64>,
+
+    /// Sysctl values that are set on top of the defaults.
+    pub(super) sysctls: BTreeMap<String, String>,
+    /// Whether the sysctl values are mounted at `/proc/sys`.
+    pub(super) mount_proc_sys: bool,
+    /// Whether the uptime is served at `/proc/uptime`.
+    pub(super) mount_proc_uptime: bool,
+
+    /// Clocks that the guest reads, defaults to the clocks of the host.
+    pub(super) clock: Option<Arc<DynClock>>,
+
+    /// Parallelism reported to the guest, defaults to the CPUs of the host.
+    pub(super) thread_parallelism: Option<usize>,
+
     #[cfg(feature = "journal")]
     pub(super) snapshot_on: Vec<SnapshotTrigger>,
 
@@ -138,6 +174,8 @@ pub enum WasiStateCreationError {
     WasiIncludePackageError(String),
     #[error("control plane error")]
     ControlPlane(#[from] ControlPlaneError),
+    #[error("file descriptor `{0}` is already in use")]
+    FdAlreadyInUse(WasiFd),
 }
 
 fn validate_mapped_dir_alias(alias: &str) -> Result<(), WasiStateCreationError> {
@@ -447,6 +485,49 @@ impl WasiEnvBuilder {
         Ok(())
     }
 
+    /// Preopen a directory with a restricted set of rights
+    ///
+    /// This opens the given directory at the virtual root, `/`, like
+    /// [`WasiEnvBuilder::preopen_dir`] but the WASI module is only granted
+    /// `rights` on the directory and everything that is opened beneath it
+    /// (e.g. leaving out [`Rights::FD_WRITE`] makes the directory read-only).
+    pub fn preopen_dir_with_rights<P>(
+        mut self,
+        po_dir: P,
+        rights: Rights,
+    ) -> Result<Self, WasiStateCreationError>
+    where
+        P: AsRef<Path>,
+    {
+        self.add_preopen_dir_with_rights(po_dir, rights)?;
+        Ok(self)
+    }
+
+    /// Adds a preopen a directory with a restricted set of rights
+    ///
+    /// See [`WasiEnvBuilder::preopen_dir_with_rights`].
+    pub fn add_preopen_dir_with_rights<P>(
+        &mut self,
+        po_dir: P,
+        rights: Rights,
+    ) -> Result<(), WasiStateCreationError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut pdb = PreopenDirBuilder::new();
+        let path = po_dir.as_ref();
+        pdb.directory(path)
+            .read(true)
+            .write(true)
+            .create(true)
+            .rights(rights);
+        let preopen = pdb.build()?;
+
+        self.preopens.push(preopen);
+
+        Ok(())
+    }
+
     /// Preopen multiple directories.
     ///
     /// This opens the given directories at the virtual root, `/`, and allows
@@ -633,6 +714,48 @@ impl WasiEnvBuilder {
         self.stdin = Some(new_file);
     }
 
+    /// Feeds a fixed buffer to the WASI `stdin`, reads return the bytes
+    /// and then EOF.
+    pub fn stdin_from_bytes(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.set_stdin_from_bytes(bytes);
+        self
+    }
+
+    /// Feeds a fixed buffer to the WASI `stdin`,
+    /// see [`WasiEnvBuilder::stdin_from_bytes`].
+    pub fn set_stdin_from_bytes(&mut self, bytes: impl Into<Vec<u8>>) {
+        self.set_stdin(Box::new(StaticFile::new(bytes.into())));
+    }
+
+    /// Installs a file at a specific file descriptor with the given rights
+    /// before the program starts (e.g. a config file at fd `5`).
+    ///
+    /// Building the environment fails if the file descriptor collides with
+    /// stdio or a pre-opened directory.
+    pub fn preopen_fd(
+        mut self,
+        fd: WasiFd,
+        file: Box<dyn VirtualFile + Send + Sync + 'static>,
+        rights: Rights,
+    ) -> Self {
+        self.set_preopen_fd(fd, file, rights);
+        self
+    }
+
+    /// Installs a file at a specific file descriptor with the given rights
+    /// before the program starts (e.g. a config file at fd `5`).
+    ///
+    /// Building the environment fails if the file descriptor collides with
+    /// stdio or a pre-opened directory.
+    pub fn set_preopen_fd(
+        &mut self,
+        fd: WasiFd,
+        file: Box<dyn VirtualFile + Send + Sync + 'static>,
+        rights: Rights,
+    ) {
+        self.preopen_fds.push((fd, file, rights));
+    }
+
     /// Sets the FileSystem to be used with this WASI instance.
     ///
     /// This is usually used in case a custom `virtual_fs::FileSystem` is needed.
@@ -685,6 +808,226 @@ impl WasiEnvBuilder {
         self.capabilites = capabilities;
     }
 
+    /// Makes individual syscalls unavailable to the guest, calling one of
+    /// them returns `Errno::Nosys` instead.
+    ///
+    /// The denied syscalls are replaced when the imports of the instance are
+    /// created, the syscalls that are still allowed pay nothing for this.
+    pub fn deny_syscalls(mut self, syscalls: &[&str]) -> Self {
+        self.add_denied_syscalls(syscalls);
+        self
+    }
+
+    /// Makes individual syscalls unavailable to the guest, see
+    /// [`WasiEnvBuilder::deny_syscalls`].
+    pub fn add_denied_syscalls(&mut self, syscalls: &[&str]) {
+        self.capabilites
+            .denied_syscalls
+            .extend(syscalls.iter().map(|name| name.to_string()));
+    }
+
+    /// Limits how long a host backed operation may take before the syscall
+    /// gives up on it and returns `Errno::Timedout`. This covers opening,
+    /// reading, writing, listing, stating and renaming files on the file
+    /// system as well as binding sockets and listening on them.
+    ///
+    /// Asynchronous operations are cancelled when they time out. A blocking
+    /// file system call that is already stuck on the host can not be
+    /// interrupted, a file that it creates after the syscall gave up on it
+    /// is removed again. Such calls count against
+    /// [`WasiEnvBuilder::max_host_ops`] until they return.
+    ///
+    /// Stdio, waiting on a peer (connecting, accepting, sending and
+    /// receiving on sockets) and operations where the guest itself decides
+    /// to block (such as `poll_oneoff` or futexes) are not subject to this
+    /// timeout.
+    pub fn host_op_timeout(mut self, timeout: Duration) -> Self {
+        self.set_host_op_timeout(timeout);
+        self
+    }
+
+    /// Sets the maximum duration of host backed operations,
+    /// see [`WasiEnvBuilder::host_op_timeout`].
+    pub fn set_host_op_timeout(&mut self, timeout: Duration) {
+        self.host_op_timeout = Some(timeout);
+    }
+
+    /// Limits how many host backed operations subject to
+    /// [`WasiEnvBuilder::host_op_timeout`] may be in flight at once for this
+    /// environment (and the processes it forks), including those that timed
+    /// out but are still stuck on the host. Beyond that the syscalls return
+    /// `Errno::Again`. Defaults to 64.
+    pub fn max_host_ops(mut self, max: usize) -> Self {
+        self.set_max_host_ops(max);
+        self
+    }
+
+    /// Sets the maximum number of host backed operations in flight,
+    /// see [`WasiEnvBuilder::max_host_ops`].
+    pub fn set_max_host_ops(&mut self, max: usize) {
+        self.max_host_ops = Some(max);
+    }
+
+    /// Limits the amount of CPU time that the instance may consume across
+    /// all of its threads. The time is checked at every syscall and by a
+    /// watchdog that runs on the task manager. Once the budget is exhausted
+    /// the watchdog raises `SIGXCPU` in the process and the guest is
+    /// terminated with [`WasiError::CpuBudgetExceeded`] when it next returns
+    /// to the host.
+    ///
+    /// Time spent blocked (for instance sleeping or waiting on IO) does not
+    /// count towards the budget. The budget is only enforced on Linux hosts.
+    ///
+    /// [`WasiError::CpuBudgetExceeded`]: crate::WasiError::CpuBudgetExceeded
+    pub fn cpu_time_budget(mut self, budget: Duration) -> Self {
+        self.set_cpu_time_budget(budget);
+        self
+    }
+
+    /// Sets the CPU time budget of the instance,
+    /// see [`WasiEnvBuilder::cpu_time_budget`].
+    pub fn set_cpu_time_budget(&mut self, budget: Duration) {
+        self.cpu_time_budget = Some(budget);
+    }
+
+    /// Sets the host name that the guest sees when it calls `gethostname`
+    /// (or `uname`), defaults to [`DEFAULT_HOSTNAME`].
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.set_hostname(hostname);
+        self
+    }
+
+    /// Sets the host name that is visible to the guest,
+    /// see [`WasiEnvBuilder::hostname`].
+    pub fn set_hostname(&mut self, hostname: impl Into<String>) {
+        self.hostname = Some(hostname.into());
+    }
+
+    /// Sets the domain name that the guest sees when it calls `getdomainname`
+    /// (or `uname`), defaults to [`DEFAULT_DOMAINNAME`].
+    pub fn domainname(mut self, domainname: impl Into<String>) -> Self {
+        self.set_domainname(domainname);
+        self
+    }
+
+    /// Sets the domain name that is visible to the guest,
+    /// see [`WasiEnvBuilder::domainname`].
+    pub fn set_domainname(&mut self, domainname: impl Into<String>) {
+        self.domainname = Some(domainname.into());
+    }
+
+    /// Caps the number of bytes that can be allocated on the heap of the
+    /// module through [`WasiFunctionEnv::malloc`], allocations that would
+    /// exceed the limit return a null pointer instead. The allocations that
+    /// the module makes itself are not limited, see [`HeapStats`](crate::HeapStats).
+    pub fn heap_limit(mut self, limit: u64) -> Self {
+        self.set_heap_limit(limit);
+        self
+    }
+
+    /// Sets the maximum number of bytes that can be allocated on the heap,
+    /// see [`WasiEnvBuilder::heap_limit`].
+    pub fn set_heap_limit(&mut self, limit: u64) {
+        self.heap_limit = Some(limit);
+    }
+
+    /// Sets a sysctl value that the guest can read with `proc_sysctl` (and
+    /// from `/proc/sys`, see [`WasiEnvBuilder::mount_proc_sys`]). Keys are
+    /// dotted like `kernel.ostype` and override the defaults that are derived
+    /// from the configuration of the instance and the host.
+    pub fn sysctl(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set_sysctl(key, value);
+        self
+    }
+
+    /// Sets a sysctl value, see [`WasiEnvBuilder::sysctl`].
+    pub fn set_sysctl(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.sysctls.insert(key.into(), value.into());
+    }
+
+    /// Mounts a read-only file system at `/proc/sys` that serves the sysctl
+    /// values, the key `kernel.ostype` is served as `/proc/sys/kernel/ostype`.
+    /// This requires a sandboxed file system.
+    pub fn mount_proc_sys(mut self, mount: bool) -> Self {
+        self.set_mount_proc_sys(mount);
+        self
+    }
+
+    /// Sets whether the sysctl values are mounted at `/proc/sys`,
+    /// see [`WasiEnvBuilder::mount_proc_sys`].
+    pub fn set_mount_proc_sys(&mut self, mount: bool) {
+        self.mount_proc_sys = mount;
+    }
+
+    /// Serves the uptime of the runtime (see `proc_uptime`) in the Linux
+    /// format as the read-only file `/proc/uptime`. This requires a
+    /// sandboxed file system.
+    pub fn mount_proc_uptime(mut self, mount: bool) -> Self {
+        self.set_mount_proc_uptime(mount);
+        self
+    }
+
+    /// Sets whether the uptime is served at `/proc/uptime`,
+    /// see [`WasiEnvBuilder::mount_proc_uptime`].
+    pub fn set_mount_proc_uptime(&mut self, mount: bool) {
+        self.mount_proc_uptime = mount;
+    }
+
+    /// Sets the clock that the guest reads (with `clock_time_get`,
+    /// `clock_res_get`, `proc_uptime` and so on) in place of the clock of
+    /// the runtime, see [`crate::Runtime::clock`]. A fixed or manually
+    /// advanced clock makes the time that the guest observes reproducible.
+    pub fn with_clock(mut self, clock: Arc<DynClock>) -> Self {
+        self.set_clock(clock);
+        self
+    }
+
+    /// Sets the clocks that the guest reads,
+    /// see [`WasiEnvBuilder::with_clock`].
+    pub fn set_clock(&mut self, clock: Arc<DynClock>) {
+        self.clock = Some(clock);
+    }
+
+    /// Sets the parallelism that `thread_parallelism` reports to the guest
+    /// instead of the number of CPUs of the host, for instance when the
+    /// instance runs with a CPU quota. The value is clamped to at least 1.
+    pub fn with_thread_parallelism(mut self, parallelism: usize) -> Self {
+        self.set_thread_parallelism(parallelism);
+        self
+    }
+
+    /// Sets the parallelism that is reported to the guest,
+    /// see [`WasiEnvBuilder::with_thread_parallelism`].
+    pub fn set_thread_parallelism(&mut self, parallelism: usize) {
+        self.thread_parallelism = Some(parallelism.max(1));
+    }
+
+    /// Returns the default sysctl values with the configured ones applied
+    /// on top of them.
+    fn sysctl_values(&self, hostname: &str, domainname: &str) -> BTreeMap<String, String> {
+        let threads_max = self
+            .capabilites
+            .threading
+            .max_threads
+            .or(self.thread_parallelism)
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1);
+
+        let mut values = BTreeMap::from([
+            ("kernel.ostype".to_string(), "WASIX".to_string()),
+            (
+                "kernel.osrelease".to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+            ),
+            ("kernel.hostname".to_string(), hostname.to_string()),
+            ("kernel.domainname".to_string(), domainname.to_string()),
+            ("kernel.threads-max".to_string(), threads_max.to_string()),
+            ("vm.overcommit_memory".to_string(), "0".to_string()),
+        ]);
+        values.extend(self.sysctls.clone());
+        values
+    }
+
     #[cfg(feature = "journal")]
     pub fn add_snapshot_trigger(&mut self, on: SnapshotTrigger) {
         self.snapshot_on.push(on);
@@ -818,6 +1161,75 @@ impl WasiEnvBuilder {
             .take()
             .unwrap_or_else(|| WasiFsRoot::Sandbox(Arc::new(TmpFileSystem::new())));
 
+        let hostname = self
+            .hostname
+            .take()
+            .unwrap_or_else(|| DEFAULT_HOSTNAME.to_string());
+        let domainname = self
+            .domainname
+            .take()
+            .unwrap_or_else(|| DEFAULT_DOMAINNAME.to_string());
+        let sysctls = self.sysctl_values(&hostname, &domainname);
+
+        if self.mount_proc_sys {
+            match fs_backing.create_dir(Path::new("/proc")) {
+                Ok(()) | Err(FsError::AlreadyExists) => {}
+                Err(err) => return Err(WasiStateCreationError::FileSystemError(err)),
+            }
+            fs_backing
+                .mount(
+                    "proc-sys".to_string(),
+                    Path::new("/proc/sys"),
+                    Box::new(SysctlFileSystem::new(sysctls.clone())),
+                )
+                .map_err(|err| {
+                    WasiStateCreationError::WasiFsSetupError(format!(
+                        "Could not mount the sysctl values at '/proc/sys': {err}"
+                    ))
+                })?;
+        }
+
+        let runtime = self.runtime.take().unwrap_or_else(|| {
+            #[cfg(feature = "sys-thread")]
+            {
+                #[allow(unused_mut)]
+                let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(crate::runtime::task_manager::tokio::TokioTaskManager::default()));
+                #[cfg(feature = "journal")]
+                for journal in self.journals.clone() {
+                    runtime.add_journal(journal);
+                }
+                Arc::new(runtime)
+            }
+
+            #[cfg(not(feature = "sys-thread"))]
+            {
+                panic!("this build does not support a default runtime - specify one with WasiEnvBuilder::runtime()");
+            }
+        });
+        let runtime: Arc<DynRuntime> = match self.clock.take() {
+            Some(clock) => Arc::new(OverriddenRuntime::new(runtime).with_clock(clock)),
+            None => runtime,
+        };
+
+        if self.mount_proc_uptime {
+            let WasiFsRoot::Sandbox(tmp) = &fs_backing else {
+                return Err(WasiStateCreationError::WasiFsSetupError(
+                    "Serving '/proc/uptime' requires a sandboxed file system".to_string(),
+                ));
+            };
+            match tmp.create_dir(Path::new("/proc")) {
+                Ok(()) | Err(FsError::AlreadyExists) => {}
+                Err(err) => return Err(WasiStateCreationError::FileSystemError(err)),
+            }
+            tmp.new_open_options_ext()
+                .insert_arc_file_at(
+                    PathBuf::from("/proc/uptime"),
+                    Arc::new(UptimeFileSystem::new(runtime.clock())),
+                    PathBuf::from(UptimeFileSystem::PATH),
+                )
+                .map_err(WasiStateCreationError::FileSystemError)?;
+        }
+
         if let Some(dir) = &self.current_dir {
             match fs_backing.read_dir(dir) {
                 Ok(_) => {
@@ -865,6 +1277,35 @@ impl WasiEnvBuilder {
                     .map_err(WasiStateCreationError::FileSystemError)?;
             }
 
+            for (fd, file, rights) in self.preopen_fds.drain(..) {
+                if wasi_fs.fd_map.read().unwrap().get(fd).is_some() {
+                    return Err(WasiStateCreationError::FdAlreadyInUse(fd));
+                }
+
+                let mut open_flags = 0;
+                if rights.contains(Rights::FD_READ) {
+                    open_flags |= Fd::READ;
+                }
+                if rights.contains(Rights::FD_WRITE) {
+                    open_flags |= Fd::WRITE;
+                }
+
+                let kind = Kind::File {
+                    handle: Some(Arc::new(std::sync::RwLock::new(file))),
+                    path: PathBuf::new(),
+                    fd: None,
+                };
+                let inode = wasi_fs.create_inode_with_default_stat(
+                    &inodes,
+                    kind,
+                    false,
+                    format!("fd{fd}").into(),
+                );
+                wasi_fs
+                    .with_fd(rights, rights, Fdflags::empty(), open_flags, inode, fd)
+                    .map_err(|_| WasiStateCreationError::FdAlreadyInUse(fd))?;
+            }
+
             if let Some(f) = &self.setup_fs_fn {
                 f(&inodes, &mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
             }
@@ -894,26 +1335,19 @@ impl WasiEnvBuilder {
             futexs: Default::default(),
             clock_offset: Default::default(),
             envs: std::sync::Mutex::new(conv_env_vars(self.envs)),
+            host_op_timeout: self.host_op_timeout,
+            max_host_ops: self.max_host_ops.unwrap_or(DEFAULT_MAX_HOST_OPS),
+            host_ops: Default::default(),
+            cpu_time: self
+                .cpu_time_budget
+                .map(|budget| Arc::new(CpuTimeBudget::new(budget))),
+            hostname,
+            domainname,
+            sysctls,
+            heap_limit: self.heap_limit,
+            memory_pages: Default::default(),
         };
 
-        let runtime = self.runtime.unwrap_or_else(|| {
-            #[cfg(feature = "sys-thread")]
-            {
-                #[allow(unused_mut)]
-                let mut runtime = crate::runtime::PluggableRuntime::new(Arc::new(crate::runtime::task_manager::tokio::TokioTaskManager::default()));
-                #[cfg(feature = "journal")]
-                for journal in self.journals.clone() {
-                    runtime.add_journal(journal);
-                }
-                Arc::new(runtime)
-            }
-
-            #[cfg(not(feature = "sys-thread"))]
-            {
-                panic!("this build does not support a default runtime - specify one with WasiEnvBuilder::runtime()");
-            }
-        });
-
         let uses = self.uses;
         let map_commands = self.map_commands;
 
@@ -925,6 +1359,7 @@ impl WasiEnvBuilder {
             max_task_count: capabilities.threading.max_threads,
             enable_asynchronous_threading: capabilities.threading.enable_asynchronous_threading,
             enable_exponential_cpu_backoff: capabilities.threading.enable_exponential_cpu_backoff,
+            thread_parallelism: self.thread_parallelism,
         };
         let control_plane = WasiControlPlane::new(plane_config);
 
@@ -1136,6 +1571,7 @@ pub struct PreopenDirBuilder {
     read: bool,
     write: bool,
     create: bool,
+    rights: Option<Rights>,
 }
 
 /// The built version of `PreopenDirBuilder`
@@ -1146,6 +1582,8 @@ pub(crate) struct PreopenedDir {
     pub(crate) read: bool,
     pub(crate) write: bool,
     pub(crate) create: bool,
+    /// Restricts the rights derived from `read`, `write` and `create`
+    pub(crate) rights: Option<Rights>,
 }
 
 impl PreopenDirBuilder {
@@ -1201,6 +1639,16 @@ impl PreopenDirBuilder {
         self
     }
 
+    /// Restrict the rights granted on the directory, and on everything that
+    /// is opened beneath it, to `rights`
+    ///
+    /// The rights still have to be allowed by `read`, `write` and `create`.
+    pub fn rights(&mut self, rights: Rights) -> &mut Self {
+        self.rights = Some(rights);
+
+        self
+    }
+
     pub(crate) fn build(&self) -> Result<PreopenedDir, WasiStateCreationError> {
         // ensure at least one is set
         if !(self.read || self.write || self.create) {
@@ -1230,6 +1678,7 @@ impl PreopenDirBuilder {
             read: self.read,
             write: self.write,
             create: self.create,
+            rights: self.rights,
         })
     }
 }
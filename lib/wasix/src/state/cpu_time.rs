@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use wasmer_wasix_types::wasi::Signal;
+
+use crate::{os::task::thread::WasiThreadId, VirtualTaskManager, WasiProcess};
+
+/// Tracks the CPU time that the threads of a process consume against the
+/// CPU time budget of the process
+///
+/// Every thread of the guest is charged for the CPU time of the host thread
+/// that runs it while it runs guest code. Charging stops whenever the thread
+/// waits in a syscall, as the host thread may run other work in the meantime
+/// (and the guest thread may resume on another host thread), and starts
+/// again once it returns to guest code. The time is charged whenever the
+/// thread makes a syscall and periodically by a watchdog (see
+/// [`CpuTimeBudget::watch`]), so a thread that spins without making
+/// syscalls is still accounted for. Once the budget is exhausted the
+/// watchdog raises `SIGXCPU` in the process, which wakes up the threads that
+/// are blocked in a syscall, and every thread of the guest terminates with
+/// [`crate::WasiError::CpuBudgetExceeded`] as soon as it returns to the host.
+#[derive(Debug)]
+pub(crate) struct CpuTimeBudget {
+    budget: Duration,
+    inner: Mutex<CpuTimeBudgetInner>,
+    /// Set once the watchdog of the process has been started
+    watching: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct CpuTimeBudgetInner {
+    /// CPU time that has been charged to the process so far
+    used: Duration,
+    /// Host thread clocks of the guest threads that are running guest code
+    threads: HashMap<WasiThreadId, ThreadCpuClock>,
+}
+
+/// CPU clock of the host thread that runs a guest thread along with the
+/// reading of the clock up to which the thread has been charged
+#[derive(Debug, Clone, Copy)]
+struct ThreadCpuClock {
+    clock: CpuClock,
+    mark: Duration,
+}
+
+impl CpuTimeBudget {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            inner: Default::default(),
+            watching: AtomicBool::new(false),
+        }
+    }
+
+    /// Creates the budget of a forked process, which carries over the CPU
+    /// time that was already charged to this one
+    pub fn fork(&self) -> Self {
+        let inner = self.inner.lock().unwrap();
+        Self {
+            budget: self.budget,
+            inner: Mutex::new(CpuTimeBudgetInner {
+                used: inner.used,
+                threads: Default::default(),
+            }),
+            watching: AtomicBool::new(false),
+        }
+    }
+
+    /// Starts charging the CPU time of the current host thread to the
+    /// guest thread, this is called right before it runs guest code and
+    /// whenever it returns to guest code after waiting
+    pub fn start_thread(&self, tid: WasiThreadId) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(thread) = inner.threads.remove(&tid) {
+            inner.charge(thread);
+        }
+        if let Some(thread) = ThreadCpuClock::current() {
+            inner.threads.insert(tid, thread);
+        }
+    }
+
+    /// Charges the CPU time that the guest thread consumed since it was
+    /// last charged, returning `false` once the budget is exhausted (a
+    /// thread that is waiting is not charged at all)
+    pub fn charge_thread(&self, tid: WasiThreadId) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(thread) = inner.threads.get(&tid).copied() {
+            let thread = inner.charge(thread);
+            inner.threads.insert(tid, thread);
+        }
+        inner.used <= self.budget
+    }
+
+    /// Charges the guest thread for the CPU time it consumed so far and
+    /// stops charging it, this is called when it waits and as it exits
+    pub fn stop_thread(&self, tid: WasiThreadId) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(thread) = inner.threads.remove(&tid) {
+            inner.charge(thread);
+        }
+    }
+
+    /// Charges all the threads that are running guest code, returning
+    /// `false` once the budget is exhausted
+    fn charge_all(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let threads = inner
+            .threads
+            .iter()
+            .map(|(tid, thread)| (*tid, *thread))
+            .collect::<Vec<_>>();
+        for (tid, thread) in threads {
+            let thread = inner.charge(thread);
+            inner.threads.insert(tid, thread);
+        }
+        inner.used <= self.budget
+    }
+
+    /// Returns the CPU time that has been charged to the process so far
+    pub fn used(&self) -> Duration {
+        self.inner.lock().unwrap().used
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Runs a watchdog on the task manager that charges the running threads
+    /// of the process at a regular interval so that the budget is enforced
+    /// even when the guest does not make any syscalls, the watchdog raises
+    /// `SIGXCPU` in the process once the budget is exhausted (only one
+    /// watchdog is started no matter how often this is called)
+    pub fn watch(self: &Arc<Self>, tasks: Arc<dyn VirtualTaskManager>, process: WasiProcess) {
+        if self.watching.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let interval =
+            (self.budget / 10).clamp(Duration::from_millis(1), Duration::from_millis(100));
+        let budget = Arc::downgrade(self);
+        let sleeper = tasks.clone();
+        let ret = tasks.task_shared(Box::new(move || {
+            Box::pin(async move {
+                loop {
+                    sleeper.sleep_now(interval).await;
+                    let Some(budget) = budget.upgrade() else {
+                        break;
+                    };
+                    if process.try_join().is_some() {
+                        break;
+                    }
+                    if !budget.charge_all() {
+                        tracing::debug!(
+                            pid=%process.pid(),
+                            used=?budget.used(),
+                            budget=?budget.budget(),
+                            "CPU time budget exceeded",
+                        );
+                        process.signal_process(Signal::Sigxcpu);
+                        break;
+                    }
+                }
+            })
+        }));
+        if let Err(err) = ret {
+            tracing::warn!("failed to start the CPU time watchdog - {}", err);
+        }
+    }
+}
+
+impl CpuTimeBudgetInner {
+    /// Adds the CPU time that the thread consumed since its mark to the
+    /// used time, returning the thread with its mark moved forward
+    fn charge(&mut self, thread: ThreadCpuClock) -> ThreadCpuClock {
+        match cpu_clock_time(thread.clock) {
+            Some(now) => {
+                self.used += now.saturating_sub(thread.mark);
+                ThreadCpuClock {
+                    clock: thread.clock,
+                    mark: now,
+                }
+            }
+            None => thread,
+        }
+    }
+}
+
+impl ThreadCpuClock {
+    /// Returns the clock of the current host thread
+    fn current() -> Option<Self> {
+        let clock = current_cpu_clock()?;
+        let mark = cpu_clock_time(clock)?;
+        Some(Self { clock, mark })
+    }
+}
+
+#[cfg(target_os = "linux")]
+type CpuClock = libc::clockid_t;
+
+/// Returns the CPU clock of the current host thread, which can be read from
+/// any other thread
+#[cfg(target_os = "linux")]
+fn current_cpu_clock() -> Option<CpuClock> {
+    let mut clock: libc::clockid_t = 0;
+    let ret = unsafe { libc::pthread_getcpuclockid(libc::pthread_self(), &mut clock) };
+    if ret != 0 {
+        return None;
+    }
+    Some(clock)
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_clock_time(clock: CpuClock) -> Option<Duration> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let ret = unsafe { libc::clock_gettime(clock, &mut ts) };
+    if ret != 0 {
+        return None;
+    }
+    Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+#[cfg(not(target_os = "linux"))]
+type CpuClock = ();
+
+#[cfg(not(target_os = "linux"))]
+fn current_cpu_clock() -> Option<CpuClock> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_clock_time(_clock: CpuClock) -> Option<Duration> {
+    None
+}
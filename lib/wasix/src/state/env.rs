@@ -2,7 +2,7 @@ use std::{
     collections::HashMap,
     ops::Deref,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{atomic::Ordering, Arc},
     time::Duration,
 };
 
@@ -11,8 +11,8 @@ use rand::Rng;
 use virtual_fs::{FileSystem, FsError, StaticFile, VirtualFile};
 use virtual_net::DynVirtualNetworking;
 use wasmer::{
-    AsStoreMut, AsStoreRef, FunctionEnvMut, Global, Imports, Instance, Memory, MemoryType,
-    MemoryView, Module, TypedFunction,
+    AsStoreMut, AsStoreRef, Function, FunctionEnvMut, Global, Imports, Instance, Memory,
+    MemoryType, MemoryView, Module, TypedFunction,
 };
 use wasmer_config::package::PackageSource;
 use wasmer_wasix_types::{
@@ -32,9 +32,9 @@ use crate::{
     os::task::{
         control_plane::ControlPlaneError,
         process::{WasiProcess, WasiProcessId},
-        thread::{WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
+        thread::{SignalMaskHow, WasiMemoryLayout, WasiThread, WasiThreadHandle, WasiThreadId},
     },
-    runtime::{task_manager::InlineWaker, SpawnMemoryType},
+    runtime::{task_manager::InlineWaker, RuntimeEvent, SpawnMemoryType},
     syscalls::platform_clock_time_get,
     Runtime, VirtualTaskManager, WasiControlPlane, WasiEnvBuilder, WasiError, WasiFunctionEnv,
     WasiResult, WasiRuntimeError, WasiStateCreationError, WasiVFork,
@@ -133,6 +133,12 @@ pub struct WasiInstanceHandles {
     /// when to propagate results back.
     #[allow(dead_code)]
     pub(crate) asyncify_get_state: Option<TypedFunction<(), i32>>,
+
+    /// Allocator exports of the module (names = "malloc", "free" and "realloc")
+    /// which are used to track the heap usage of the process
+    pub(crate) malloc: Option<Function>,
+    pub(crate) free: Option<Function>,
+    pub(crate) realloc: Option<Function>,
 }
 
 impl WasiInstanceHandles {
@@ -182,6 +188,9 @@ impl WasiInstanceHandles {
                 .exports
                 .get_typed_function(store, "asyncify_get_state")
                 .ok(),
+            malloc: instance.exports.get_function("malloc").cloned().ok(),
+            free: instance.exports.get_function("free").cloned().ok(),
+            realloc: instance.exports.get_function("realloc").cloned().ok(),
             instance,
         }
     }
@@ -272,6 +281,15 @@ impl WasiEnvInit {
                 args: std::sync::Mutex::new(self.state.args.lock().unwrap().clone()),
                 envs: std::sync::Mutex::new(self.state.envs.lock().unwrap().deref().clone()),
                 preopen: self.state.preopen.clone(),
+                host_op_timeout: self.state.host_op_timeout,
+                max_host_ops: self.state.max_host_ops,
+                host_ops: Default::default(),
+                cpu_time: self.state.cpu_time.as_ref().map(|c| Arc::new(c.fork())),
+                hostname: self.state.hostname.clone(),
+                domainname: self.state.domainname.clone(),
+                sysctls: self.state.sysctls.clone(),
+                heap_limit: self.state.heap_limit,
+                memory_pages: Default::default(),
             },
             runtime: self.runtime.clone(),
             webc_dependencies: self.webc_dependencies.clone(),
@@ -303,7 +321,11 @@ pub struct WasiEnv {
     pub layout: WasiMemoryLayout,
     /// Represents a fork of the process that is currently in play
     pub vfork: Option<WasiVFork>,
-    /// Seed used to rotate around the events returned by `poll_oneoff`
+    /// Seed that was used to rotate around the events returned by `poll_oneoff`
+    #[deprecated(
+        note = "poll_oneoff reports events in subscription order, the seed is no longer used",
+        since = "0.35.0"
+    )]
     pub poll_seed: u64,
     /// Shared state of the WASI system. Manages all the data that the
     /// executing WASI program can see.
@@ -355,10 +377,11 @@ impl Clone for WasiEnv {
         Self {
             control_plane: self.control_plane.clone(),
             process: self.process.clone(),
-            poll_seed: self.poll_seed,
             thread: self.thread.clone(),
             layout: self.layout.clone(),
             vfork: self.vfork.clone(),
+            #[allow(deprecated)]
+            poll_seed: self.poll_seed,
             state: self.state.clone(),
             bin_factory: self.bin_factory.clone(),
             inner: Default::default(),
@@ -383,10 +406,12 @@ impl WasiEnv {
     /// Forking the WasiState is used when either fork or vfork is called
     pub fn fork(&self) -> Result<(Self, WasiThreadHandle), ControlPlaneError> {
         let process = self.control_plane.new_process(self.process.module_hash)?;
+        process.set_nice(self.process.nice());
         let handle = process.new_thread(self.layout.clone(), ThreadStartType::MainThread)?;
 
         let thread = handle.as_thread();
         thread.copy_stack_from(&self.thread);
+        thread.update_signal_mask(SignalMaskHow::SetMask, self.thread.signal_mask());
 
         let state = Arc::new(self.state.fork());
 
@@ -398,6 +423,7 @@ impl WasiEnv {
             thread,
             layout: self.layout.clone(),
             vfork: None,
+            #[allow(deprecated)]
             poll_seed: 0,
             bin_factory,
             state,
@@ -526,6 +552,7 @@ impl WasiEnv {
             thread: thread.as_thread(),
             layout,
             vfork: None,
+            #[allow(deprecated)]
             poll_seed: 0,
             state: Arc::new(init.state),
             inner: Default::default(),
@@ -579,6 +606,13 @@ impl WasiEnv {
 
         let env = Self::from_init(init, module_hash)?;
         let pid = env.process.pid();
+        let runtime = env.runtime.clone();
+        let instantiation_failed = |error: &dyn std::error::Error| {
+            runtime.on_event(RuntimeEvent::InstantiationFailed {
+                pid: pid.raw(),
+                error: error.to_string(),
+            });
+        };
 
         let mut store = store.as_store_mut();
 
@@ -627,6 +661,7 @@ impl WasiEnv {
                     error = &err as &dyn std::error::Error,
                     "Instantiation failed",
                 );
+                instantiation_failed(&err);
                 func_env
                     .data(&store)
                     .blocking_on_exit(Some(Errno::Noexec.into()));
@@ -646,6 +681,7 @@ impl WasiEnv {
                 error = &err as &dyn std::error::Error,
                 "Initialization failed",
             );
+            instantiation_failed(&err);
             func_env
                 .data(&store)
                 .blocking_on_exit(Some(Errno::Noexec.into()));
@@ -656,6 +692,7 @@ impl WasiEnv {
         if call_initialize {
             if let Ok(initialize) = instance.exports.get_function("_initialize") {
                 if let Err(err) = crate::run_wasi_func_start(initialize, &mut store) {
+                    instantiation_failed(&err);
                     func_env
                         .data(&store)
                         .blocking_on_exit(Some(Errno::Noexec.into()));
@@ -664,6 +701,8 @@ impl WasiEnv {
             }
         }
 
+        runtime.on_event(RuntimeEvent::InstanceCreated { pid: pid.raw() });
+
         Ok((instance, func_env))
     }
 
@@ -694,8 +733,87 @@ impl WasiEnv {
         self.process.active_threads()
     }
 
+    /// Starts charging the CPU time of the current host thread to this
+    /// thread against the CPU time budget of the instance, this must be
+    /// called right before guest code starts (or resumes) running on the
+    /// host thread
+    pub(crate) fn start_cpu_time(&self) {
+        if let Some(cpu_time) = self.state.cpu_time.as_ref() {
+            cpu_time.start_thread(self.tid());
+            cpu_time.watch(self.tasks().clone(), self.process.clone());
+        }
+    }
+
+    /// Charges the CPU time of this thread and stops charging it, this is
+    /// called as it exits and before it waits on the host so that whatever
+    /// else the host thread runs in the meantime is not charged to it
+    pub(crate) fn stop_cpu_time(&self) {
+        if let Some(cpu_time) = self.state.cpu_time.as_ref() {
+            cpu_time.stop_thread(self.tid());
+        }
+    }
+
+    /// Charges the CPU time that this thread consumed since the last check
+    /// against the CPU time budget of the instance, returning
+    /// [`WasiError::CpuBudgetExceeded`] once the budget is exhausted
+    pub(crate) fn charge_cpu_time(&self) -> Result<(), WasiError> {
+        let Some(cpu_time) = self.state.cpu_time.as_ref() else {
+            return Ok(());
+        };
+        if !cpu_time.charge_thread(self.tid()) {
+            tracing::debug!(
+                pid=%self.pid(),
+                used=?cpu_time.used(),
+                budget=?cpu_time.budget(),
+                "CPU time budget exceeded",
+            );
+            return Err(WasiError::CpuBudgetExceeded);
+        }
+        Ok(())
+    }
+
+    /// Reports what a syscall did as [`RuntimeEvent`]s, this is called
+    /// every time a syscall returns to the guest
+    pub(crate) fn syscall_returned(
+        ctx: &FunctionEnvMut<'_, Self>,
+        syscall: &'static str,
+        error: Option<Errno>,
+    ) {
+        let env = ctx.data();
+        if !env.runtime.observes_events() {
+            return;
+        }
+
+        // Running out of data to read (or room to write) is routine for
+        // nonblocking descriptors so it is not reported as a failure
+        if let Some(errno) = error.filter(|errno| *errno != Errno::Again) {
+            env.runtime.on_event(RuntimeEvent::SyscallFailed {
+                pid: env.pid().raw(),
+                tid: env.tid().raw(),
+                syscall,
+                errno,
+            });
+        }
+
+        // Growing the memory does not involve the host so it is only
+        // noticed here, after the fact
+        if let Some(memory) = env.try_memory_view(ctx) {
+            let pages = memory.size().0;
+            if pages > env.state.memory_pages.load(Ordering::Relaxed)
+                && env.state.memory_pages.fetch_max(pages, Ordering::Relaxed) < pages
+            {
+                env.runtime.on_event(RuntimeEvent::MemoryGrown {
+                    pid: env.pid().raw(),
+                    pages,
+                });
+            }
+        }
+    }
+
     /// Porcesses any signals that are batched up or any forced exit codes
     pub fn process_signals_and_exit(ctx: &mut FunctionEnvMut<'_, Self>) -> WasiResult<bool> {
+        ctx.data().charge_cpu_time()?;
+
         // If a signal handler has never been set then we need to handle signals
         // differently
         let env = ctx.data();
@@ -1223,6 +1341,22 @@ impl WasiEnv {
         Ok(())
     }
 
+    /// Flushes and closes all the open file descriptors (files, pipes and
+    /// sockets) of this environment.
+    ///
+    /// This also happens when the process exits, calling it more than once
+    /// is harmless as every file descriptor is only closed once.
+    pub fn cleanup(&self) -> BoxFuture<'static, ()> {
+        let state = self.state.clone();
+        Box::pin(async move { state.fs.close_all().await })
+    }
+
+    /// Flushes and closes all the open file descriptors of this environment,
+    /// see [`WasiEnv::cleanup`]
+    pub fn blocking_cleanup(&self) {
+        InlineWaker::block_on(self.cleanup());
+    }
+
     /// Cleans up all the open files (if this is the main thread)
     #[allow(clippy::await_holding_lock)]
     pub fn blocking_on_exit(&self, process_exit_code: Option<ExitCode>) {
@@ -1250,6 +1384,17 @@ impl WasiEnv {
             }
         }
 
+        self.stop_cpu_time();
+
+        if let Some(code) = process_exit_code {
+            if self.thread.is_main() {
+                self.runtime.on_event(RuntimeEvent::ProcessExited {
+                    pid: self.pid().raw(),
+                    code,
+                });
+            }
+        }
+
         // If the process wants to exit, also close all files and terminate it
         if let Some(process_exit_code) = process_exit_code {
             let process = self.process.clone();
@@ -1324,4 +1469,13 @@ impl WasiEnv {
             }
         }
     }
+
+    /// Overrides the first argument (`argv[0]`) reported to the process.
+    pub(crate) fn set_argv0(&self, argv0: String) {
+        let mut args = self.state.args.lock().unwrap();
+        match args.first_mut() {
+            Some(arg) => *arg = argv0,
+            None => args.push(argv0),
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::sync::{atomic::Ordering, Arc};
 
 use tracing::trace;
 use wasmer::{
@@ -155,6 +155,7 @@ impl WasiFunctionEnv {
             }
         };
 
+        let memory_pages = memory.view(&*store).size().0;
         let new_inner = WasiInstanceHandles::new(memory, store, instance);
 
         let stack_pointer = new_inner.stack_pointer.clone();
@@ -164,6 +165,9 @@ impl WasiFunctionEnv {
 
         let env = self.data_mut(store);
         env.set_inner(new_inner);
+        env.state
+            .memory_pages
+            .fetch_max(memory_pages, Ordering::Relaxed);
 
         env.state.fs.set_is_wasix(is_wasix_module);
 
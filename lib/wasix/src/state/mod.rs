@@ -16,6 +16,7 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+mod cpu_time;
 mod env;
 mod func_env;
 mod handles;
@@ -25,7 +26,10 @@ mod types;
 use std::{
     collections::{BTreeMap, HashMap},
     path::Path,
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     task::Waker,
     time::Duration,
 };
@@ -33,7 +37,7 @@ use std::{
 use run::*;
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
-use virtual_fs::{FileOpener, FileSystem, FsError, OpenOptions, VirtualFile};
+use virtual_fs::{CopyTreeOpts, FileOpener, FileSystem, FsError, OpenOptions, VirtualFile};
 use wasmer_wasix_types::wasi::{Errno, Fd as WasiFd, Rights, Snapshot0Clockid};
 
 pub use self::{
@@ -48,11 +52,20 @@ use crate::{
     syscalls::types::*,
     utils::WasiParkingLot,
 };
+pub(crate) use cpu_time::CpuTimeBudget;
 pub(crate) use handles::*;
 
 /// all the rights enabled
 pub const ALL_RIGHTS: Rights = Rights::all();
 
+/// Host name that the guest sees unless one is configured
+pub const DEFAULT_HOSTNAME: &str = "localhost";
+/// Domain name that the guest sees unless one is configured
+pub const DEFAULT_DOMAINNAME: &str = "localdomain";
+/// Number of host backed operations that may be in flight at once unless
+/// another limit is configured
+pub const DEFAULT_MAX_HOST_OPS: usize = 64;
+
 #[allow(dead_code)]
 struct WasiStateOpener {
     root_fs: WasiFsRoot,
@@ -111,6 +124,13 @@ impl WasiBusState {
 }
 
 /// Stores the state of the futexes
+///
+/// Futexes are keyed by their address in the linear memory of the process,
+/// all the threads of a process share its memory and thus also this state.
+/// A forked process gets a copy of the memory rather than sharing it (there
+/// is no process-shared memory in WASIX), so it starts with its own empty
+/// futex state and a wake in one process never refers to a waiter of the
+/// other.
 #[derive(Debug, Default)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub(crate) struct WasiFutexState {
@@ -139,6 +159,39 @@ pub(crate) struct WasiState {
     // TODO: should not be here, since this requires active work to resolve.
     // State should only hold active runtime state that can be reproducibly re-created.
     pub preopen: Vec<String>,
+
+    /// Maximum amount of time that a host backed operation may take
+    /// before the syscall gives up on it (see [`crate::syscalls::__host_op`]
+    /// and [`crate::syscalls::__host_op_async`])
+    pub host_op_timeout: Option<Duration>,
+    /// Maximum number of host backed operations that may be in flight at once
+    pub max_host_ops: usize,
+    /// Number of host backed operations that are in flight, including those
+    /// that timed out but are still stuck on the host. Forked processes
+    /// share it as they run against the same host.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub host_ops: Arc<AtomicUsize>,
+
+    /// Maximum amount of CPU time that the instance may consume and the
+    /// time that its threads consumed so far
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub cpu_time: Option<Arc<CpuTimeBudget>>,
+
+    /// Host name that is visible to the guest (see `proc_gethostname`)
+    pub hostname: String,
+    /// Domain name that is visible to the guest (see `proc_getdomainname`)
+    pub domainname: String,
+    /// Sysctl values that are visible to the guest (see `proc_sysctl`)
+    pub sysctls: BTreeMap<String, String>,
+
+    /// Maximum number of bytes that may be allocated through the allocator
+    /// exports of the module (see [`crate::HeapStats`])
+    pub heap_limit: Option<u64>,
+
+    /// Size of the linear memory in pages as it was last seen by a syscall
+    /// (see [`crate::runtime::RuntimeEvent::MemoryGrown`])
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub memory_pages: AtomicU32,
 }
 
 impl WasiState {
@@ -195,6 +248,31 @@ impl WasiState {
             .map_err(fs_error_into_wasi_err)
     }
 
+    pub(crate) async fn fs_write_atomic<P: AsRef<Path>>(
+        &self,
+        path: P,
+        contents: &[u8],
+    ) -> Result<(), Errno> {
+        self.fs
+            .root_fs
+            .write_atomic(path.as_ref(), contents)
+            .await
+            .map_err(fs_error_into_wasi_err)
+    }
+
+    pub(crate) async fn fs_copy_tree<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        from: P,
+        to: Q,
+        opts: CopyTreeOpts,
+    ) -> Result<(), Errno> {
+        self.fs
+            .root_fs
+            .copy_tree(from.as_ref(), to.as_ref(), opts)
+            .await
+            .map_err(fs_error_into_wasi_err)
+    }
+
     pub(crate) fn fs_remove_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Errno> {
         self.fs
             .root_fs
@@ -253,11 +331,21 @@ impl WasiState {
             fs: self.fs.fork(),
             secret: self.secret,
             inodes: self.inodes.clone(),
+            // The memory is copied on fork so no futex can be shared with the child
             futexs: Default::default(),
             clock_offset: Mutex::new(self.clock_offset.lock().unwrap().clone()),
             args: Mutex::new(self.args.lock().unwrap().clone()),
             envs: Mutex::new(self.envs.lock().unwrap().clone()),
             preopen: self.preopen.clone(),
+            host_op_timeout: self.host_op_timeout,
+            max_host_ops: self.max_host_ops,
+            host_ops: self.host_ops.clone(),
+            cpu_time: self.cpu_time.as_ref().map(|c| Arc::new(c.fork())),
+            hostname: self.hostname.clone(),
+            domainname: self.domainname.clone(),
+            sysctls: self.sysctls.clone(),
+            heap_limit: self.heap_limit,
+            memory_pages: AtomicU32::new(self.memory_pages.load(Ordering::Relaxed)),
         }
     }
 }
@@ -140,6 +140,7 @@ fn run_with_deep_sleep(
         }
     };
 
+    env.data(&store).start_cpu_time();
     let result = start.call(&mut store, &[]);
     handle_result(store, env, result, sender);
 }
@@ -19,6 +19,8 @@ impl<'a, 'c> JournalSyscallPlayer<'a, 'c> {
             echo: tty.echo,
             line_buffered: tty.line_buffered,
             line_feeds,
+            term_type: None,
+            colors: false,
         };
 
         JournalEffector::apply_tty_set(&mut self.ctx, state).map_err(anyhow_err_to_runtime_err)?;
@@ -136,6 +136,24 @@ impl<'a, 'c> JournalSyscallPlayer<'a, 'c> {
                 )
                 .map_err(anyhow_err_to_runtime_err)?;
             }
+            JournalEntry::PathCopyTreeV1 {
+                fd,
+                old_path,
+                new_path,
+                follow_symlinks,
+                preserve_times,
+            } => {
+                tracing::trace!("Replay journal - PathCopyTree {}->{}", old_path, new_path);
+                JournalEffector::apply_path_copy_tree(
+                    &mut self.ctx,
+                    fd,
+                    &old_path,
+                    &new_path,
+                    follow_symlinks,
+                    preserve_times,
+                )
+                .map_err(anyhow_err_to_runtime_err)?;
+            }
             JournalEntry::SnapshotV1 { when, trigger } => {
                 self.action_snapshot(when, trigger, differ_ethereal)?;
             }
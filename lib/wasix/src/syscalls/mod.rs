@@ -46,7 +46,7 @@ pub(crate) use std::{
     path::Path,
     pin::Pin,
     sync::{
-        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
         mpsc, Arc, Condvar, Mutex,
     },
     task::{Context, Poll},
@@ -76,8 +76,9 @@ pub(crate) use virtual_fs::{
 pub(crate) use virtual_net::StreamSecurity;
 pub(crate) use wasmer::{
     AsStoreMut, AsStoreRef, Extern, Function, FunctionEnv, FunctionEnvMut, Global, Instance,
-    Memory, Memory32, Memory64, MemoryAccessError, MemoryError, MemorySize, MemoryView, Module,
-    OnCalledAction, Pages, RuntimeError, Store, TypedFunction, Value, WasmPtr, WasmSlice,
+    Memory, Memory32, Memory64, MemoryAccessError, MemoryError, MemoryProtection, MemorySize,
+    MemoryView, Module, OnCalledAction, Pages, RuntimeError, Store, TypedFunction, Value, WasmPtr,
+    WasmSlice,
 };
 pub(crate) use wasmer_wasix_types::{asyncify::__wasi_asyncify_t, wasi::EventUnion};
 #[cfg(target_os = "windows")]
@@ -121,8 +122,8 @@ pub(crate) use crate::{
 };
 use crate::{
     fs::{
-        fs_error_into_wasi_err, virtual_file_type_to_wasi_file_type, Fd, FdInner, InodeVal, Kind,
-        MAX_SYMLINKS,
+        fs_error_into_wasi_err, virtual_file_type_to_wasi_file_type, DirSnapshot, Fd, FdInner,
+        InodeVal, Kind, MAX_SYMLINKS,
     },
     journal::{DynJournal, JournalEffector},
     os::task::{
@@ -187,7 +188,7 @@ pub(crate) fn copy_from_slice<M: MemorySize>(
         let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
             .slice(memory, iovs.buf_len)
             .map_err(mem_error_to_wasi)?
-            .access()
+            .access_mut()
             .map_err(mem_error_to_wasi)?;
 
         let to_read = from_offset::<M>(iovs.buf_len)?;
@@ -219,7 +220,7 @@ pub(crate) fn read_bytes<T: Read, M: MemorySize>(
         let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
             .slice(memory, iovs.buf_len)
             .map_err(mem_error_to_wasi)?
-            .access()
+            .access_mut()
             .map_err(mem_error_to_wasi)?;
 
         let to_read = buf.len();
@@ -300,6 +301,15 @@ where
     InlineWaker::block_on(work)
 }
 
+/// Blocks the host thread until the work is finished, the guest thread is
+/// not charged for CPU time in the meantime as it does not run guest code
+fn block_on_idle<T>(env: &WasiEnv, work: impl Future<Output = T>) -> T {
+    env.stop_cpu_time();
+    let ret = InlineWaker::block_on(work);
+    env.start_cpu_time();
+    ret
+}
+
 /// Asyncify takes the current thread and blocks on the async runtime associated with it
 /// thus allowed for asynchronous operations to execute. It has built in functionality
 /// to (optionally) timeout the IO, force exit the process, callback signals and pump
@@ -347,11 +357,18 @@ where
         }
     }
 
-    // Block on the work
+    // Block on the work, the guest thread is not charged for CPU time
+    // while it waits
     let mut pinned_work = Box::pin(work);
     let tasks = env.tasks().clone();
-    let poller = SignalPoller { ctx, pinned_work };
-    block_on_with_timeout(&tasks, timeout, poller)
+    env.stop_cpu_time();
+    let poller = SignalPoller {
+        ctx: &mut *ctx,
+        pinned_work,
+    };
+    let ret = block_on_with_timeout(&tasks, timeout, poller);
+    ctx.data().start_cpu_time();
+    ret
 }
 
 /// Future that will be polled by asyncify methods
@@ -492,6 +509,9 @@ where
     // Box up the trigger
     let mut trigger = Box::pin(work);
 
+    // The guest thread is not charged for CPU time while it waits
+    ctx.data().stop_cpu_time();
+
     // Define the work
     let tasks = ctx.data().tasks().clone();
     let work = async move {
@@ -548,7 +568,13 @@ where
 
     // Block until the work is finished or until we
     // unload the thread using asyncify
-    InlineWaker::block_on(work)
+    let ret = InlineWaker::block_on(work);
+
+    // A thread that unwinds is only charged again once it is rewound
+    if let Ok(AsyncifyAction::Finish(ctx, _)) = &ret {
+        ctx.data().start_cpu_time();
+    }
+    ret
 }
 
 /// Asyncify takes the current thread and blocks on the async runtime associated with it
@@ -599,7 +625,109 @@ where
 
     // Block until the work is finished or until we
     // unload the thread using asyncify
-    Ok(InlineWaker::block_on(work))
+    Ok(block_on_idle(env, work))
+}
+
+/// Runs a blocking host backed operation (for instance opening a file on
+/// the underlying file system) while honoring the host operation timeout.
+///
+/// When no timeout is configured the operation simply runs inline, otherwise
+/// it runs on the blocking pool of the task manager and `Errno::Timedout` is
+/// returned if it does not complete in time. At most `max_host_ops`
+/// operations of the environment run at once (see
+/// [`crate::WasiEnvBuilder::max_host_ops`]), beyond that `Errno::Again` is
+/// returned.
+///
+/// An operation that timed out before it got to run is cancelled. One that
+/// is already stuck on the host can not be interrupted, so when it
+/// eventually completes its result is handed to `abandoned` which must undo
+/// whatever effects the operation had (or report them when they can not be
+/// undone). Asynchronous operations use [`__host_op_async`] instead.
+pub(crate) fn __host_op<T, F, A>(env: &WasiEnv, op: F, abandoned: A) -> Result<T, Errno>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+    A: FnOnce(T) + Send + 'static,
+{
+    let Some(timeout) = env.state.host_op_timeout else {
+        return Ok(op());
+    };
+
+    let host_ops = env.state.host_ops.clone();
+    if host_ops.fetch_add(1, Ordering::SeqCst) >= env.state.max_host_ops {
+        host_ops.fetch_sub(1, Ordering::SeqCst);
+        tracing::debug!("too many host operations are running");
+        return Err(Errno::Again);
+    }
+
+    let tasks = env.tasks().clone();
+    let (tx, mut rx) = tokio::sync::oneshot::channel();
+    let op_host_ops = host_ops.clone();
+    let ret = tasks.task_dedicated(Box::new(move || {
+        // The syscall already gave up on the operation while it was queued
+        if tx.is_closed() {
+            op_host_ops.fetch_sub(1, Ordering::SeqCst);
+            return;
+        }
+        let res = op();
+        op_host_ops.fetch_sub(1, Ordering::SeqCst);
+        if let Err(res) = tx.send(res) {
+            abandoned(res);
+        }
+    }));
+    if let Err(err) = ret {
+        host_ops.fetch_sub(1, Ordering::SeqCst);
+        return Err(err.into());
+    }
+
+    let deadline = tasks.sleep_now(timeout);
+    block_on_idle(env, async move {
+        tokio::select! {
+            res = &mut rx => res.map_err(|_| Errno::Io),
+            _ = deadline => {
+                // Closing the channel makes sure that the operation either
+                // already completed (in which case its result is used), that
+                // it will not start at all or that it will be handed to
+                // `abandoned`
+                rx.close();
+                match rx.try_recv() {
+                    Ok(res) => Ok(res),
+                    Err(_) => {
+                        tracing::debug!(?timeout, "host operation timed out");
+                        Err(Errno::Timedout)
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Runs an asynchronous host backed operation (for instance a read from a
+/// file on the underlying file system or a send on a socket) and gives up
+/// on it with `Errno::Timedout` when it does not complete within `timeout`.
+///
+/// The operation is cancelled by dropping it. Callers pass the host
+/// operation timeout of the environment, or `None` for operations that are
+/// exempt from it such as stdio.
+pub(crate) async fn __host_op_async<T, Fut>(
+    env: &WasiEnv,
+    timeout: Option<Duration>,
+    work: Fut,
+) -> Result<T, Errno>
+where
+    Fut: Future<Output = Result<T, Errno>>,
+{
+    let Some(timeout) = timeout else {
+        return work.await;
+    };
+
+    tokio::select! {
+        res = work => res,
+        _ = env.tasks().sleep_now(timeout) => {
+            tracing::debug!(?timeout, "host operation timed out");
+            Err(Errno::Timedout)
+        }
+    }
 }
 
 // This should be compiled away, it will simply wait forever however its never
@@ -652,7 +780,7 @@ where
 
     // Block until the work is finished or until we
     // unload the thread using asyncify
-    InlineWaker::block_on(work)
+    block_on_idle(env, work)
 }
 
 /// Performs mutable work on a socket under an asynchronous runtime with
@@ -688,7 +816,7 @@ where
 
             // Otherwise we block on the work and process it
             // using an asynchronou context
-            InlineWaker::block_on(work)
+            block_on_idle(env, work)
         }
         _ => Err(Errno::Notsock),
     }
@@ -769,10 +897,14 @@ where
 /// Replaces a socket with another socket in under an asynchronous runtime.
 /// This is used for opening sockets or connecting sockets which changes
 /// the fundamental state of the socket to another state machine
+///
+/// The work is given up on after `timeout` (see [`__host_op_async`]), which
+/// callers only pass for calls that go to the host rather than to a peer
 pub(crate) fn __sock_upgrade<'a, F, Fut>(
     ctx: &'a mut FunctionEnvMut<'_, WasiEnv>,
     sock: WasiFd,
     rights: Rights,
+    timeout: Option<Duration>,
     actor: F,
 ) -> Result<(), Errno>
 where
@@ -805,7 +937,7 @@ where
                 let work = actor(socket, fd_entry.inner.flags);
 
                 // Block on the work and process it
-                let res = InlineWaker::block_on(work);
+                let res = block_on_idle(env, __host_op_async(env, timeout, work));
                 let new_socket = res?;
 
                 if let Some(mut new_socket) = new_socket {
@@ -1531,7 +1663,8 @@ pub(crate) fn _prepare_wasi(
         drop(guard)
     }
 
-    // Close any files after the STDERR that are not preopened
+    // Close any files after the STDERR that are not preopened, along with
+    // the ones that were marked as close-on-exec
     let close_fds = {
         let preopen_fds = {
             let preopen_fds = wasi_env.state.fs.preopen_fds.read().unwrap();
@@ -1539,8 +1672,9 @@ pub(crate) fn _prepare_wasi(
         };
         let mut fd_map = wasi_env.state.fs.fd_map.read().unwrap();
         fd_map
-            .keys()
-            .filter_map(|a| match a {
+            .iter()
+            .filter_map(|(a, fd)| match a {
+                a if fd.inner.close_on_exec => Some(a),
                 a if a <= __WASI_STDERR_FILENO => None,
                 a if preopen_fds.contains(&a) => None,
                 a => Some(a),
@@ -4,15 +4,11 @@ use libc::{
     clock_getres, clock_gettime, timespec, CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID,
     CLOCK_REALTIME, CLOCK_THREAD_CPUTIME_ID,
 };
-use wasmer::WasmRef;
 use wasmer_wasix_types::wasi::{Errno, Snapshot0Clockid, Timestamp};
 
 use crate::syscalls::types::*;
 
-pub fn platform_clock_res_get(
-    clock_id: Snapshot0Clockid,
-    resolution: WasmRef<Timestamp>,
-) -> Result<i64, Errno> {
+pub fn platform_clock_res_get(clock_id: Snapshot0Clockid) -> Result<i64, Errno> {
     let unix_clock_id = match clock_id {
         Snapshot0Clockid::Monotonic => CLOCK_MONOTONIC,
         Snapshot0Clockid::ProcessCputimeId => CLOCK_PROCESS_CPUTIME_ID,
@@ -18,8 +18,7 @@ pub fn clock_res_get<M: MemorySize>(
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
 
-    let out_addr = resolution.deref(&memory);
-    let t_out = wasi_try!(platform_clock_res_get(clock_id, out_addr));
-    wasi_try_mem!(resolution.write(&memory, t_out as Timestamp));
+    let t_out = wasi_try!(env.runtime.clock().resolution(clock_id));
+    wasi_try_mem!(resolution.write(&memory, t_out));
     Errno::Success
 }
@@ -37,8 +37,7 @@ pub fn clock_time_set_internal(
     let env = ctx.data();
     let memory = unsafe { env.memory_view(&ctx) };
 
-    let precision = 1 as Timestamp;
-    let t_now = wasi_try!(platform_clock_time_get(clock_id, precision));
+    let t_now = wasi_try!(env.runtime.clock().now(clock_id)) as i64;
 
     let t_target = time as i64;
     let t_offset = t_target - t_now;
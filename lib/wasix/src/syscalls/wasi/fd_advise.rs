@@ -41,9 +41,6 @@ pub(crate) fn fd_advise_internal(
     len: Filesize,
     advice: Advice,
 ) -> Result<(), Errno> {
-    // Instead of unconditionally returning OK.  This barebones implementation
-    // only performs basic fd and rights checks.
-
     let env = ctx.data();
     let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
     let fd_entry = state.fs.get_fd(fd)?;
@@ -55,5 +52,27 @@ pub(crate) fn fd_advise_internal(
 
     let _end = offset.checked_add(len).ok_or(Errno::Inval)?;
 
+    let advice = match advice {
+        Advice::Normal => virtual_fs::Advice::Normal,
+        Advice::Sequential => virtual_fs::Advice::Sequential,
+        Advice::Random => virtual_fs::Advice::Random,
+        Advice::Willneed => virtual_fs::Advice::WillNeed,
+        Advice::Dontneed => virtual_fs::Advice::DontNeed,
+        Advice::Noreuse => virtual_fs::Advice::NoReuse,
+        Advice::Unknown => return Ok(()),
+    };
+
+    // The advice is only a hint, so it is passed on to files (which may
+    // ignore it) and accepted for everything else
+    let guard = inode.read();
+    if let Kind::File {
+        handle: Some(handle),
+        ..
+    } = guard.deref()
+    {
+        let mut handle = handle.write().map_err(|_| Errno::Fault)?;
+        handle.advise(offset, len, advice).map_err(map_io_err)?;
+    }
+
     Ok(())
 }
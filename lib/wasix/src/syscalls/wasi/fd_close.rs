@@ -16,6 +16,13 @@ use crate::syscalls::*;
 pub fn fd_close(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno, WasiError> {
     wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
 
+    fd_close_internal(&mut ctx, fd)
+}
+
+pub(crate) fn fd_close_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+) -> Result<Errno, WasiError> {
     let env = ctx.data();
     let (_, mut state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
 
@@ -36,7 +43,7 @@ pub fn fd_close(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errn
 
     #[cfg(feature = "journal")]
     if env.enable_journal {
-        JournalEffector::save_fd_close(&mut ctx, fd).map_err(|err| {
+        JournalEffector::save_fd_close(ctx, fd).map_err(|err| {
             tracing::error!("failed to save close descriptor event - {}", err);
             WasiError::Exit(ExitCode::from(Errno::Fault))
         })?;
@@ -38,12 +38,25 @@ pub(crate) fn fd_filestat_set_size_internal(
     let fd_entry = state.fs.get_fd(fd)?;
     let inode = fd_entry.inode;
 
-    if !fd_entry.inner.rights.contains(Rights::FD_FILESTAT_SET_SIZE) {
-        return Err(Errno::Access);
-    }
-
     {
         let mut guard = inode.write();
+
+        // Only regular files can be truncated, anything else is rejected
+        // before the rights are considered (like `ftruncate` does)
+        match guard.deref() {
+            Kind::File { .. } | Kind::Buffer { .. } => {}
+            Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
+            Kind::Socket { .. }
+            | Kind::Pipe { .. }
+            | Kind::Symlink { .. }
+            | Kind::EventNotifications { .. }
+            | Kind::Epoll { .. } => return Err(Errno::Inval),
+        }
+
+        if !fd_entry.inner.rights.contains(Rights::FD_FILESTAT_SET_SIZE) {
+            return Err(Errno::Access);
+        }
+
         match guard.deref_mut() {
             Kind::File { handle, .. } => {
                 if let Some(handle) = handle {
@@ -56,11 +69,7 @@ pub(crate) fn fd_filestat_set_size_internal(
             Kind::Buffer { buffer } => {
                 buffer.resize(st_size as usize, 0);
             }
-            Kind::Socket { .. } => return Err(Errno::Badf),
-            Kind::Pipe { .. } => return Err(Errno::Badf),
-            Kind::Symlink { .. } => return Err(Errno::Badf),
-            Kind::EventNotifications { .. } | Kind::Epoll { .. } => return Err(Errno::Badf),
-            Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Isdir),
+            _ => unreachable!("only regular files reach this point"),
         }
     }
     inode.stat.write().unwrap().st_size = st_size;
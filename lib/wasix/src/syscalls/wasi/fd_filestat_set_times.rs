@@ -57,35 +57,14 @@ pub(crate) fn fd_filestat_set_times_internal(
         return Err(Errno::Access);
     }
 
-    if (fst_flags.contains(Fstflags::SET_ATIM) && fst_flags.contains(Fstflags::SET_ATIM_NOW))
-        || (fst_flags.contains(Fstflags::SET_MTIM) && fst_flags.contains(Fstflags::SET_MTIM_NOW))
-    {
-        return Err(Errno::Inval);
-    }
+    let (atime, mtime) = fst_times(env, st_atim, st_mtim, fst_flags)?;
 
     let inode = fd_entry.inode;
-
-    let mut atime = None;
-    let mut mtime = None;
-
-    if fst_flags.contains(Fstflags::SET_ATIM) || fst_flags.contains(Fstflags::SET_ATIM_NOW) {
-        let time_to_set = if fst_flags.contains(Fstflags::SET_ATIM) {
-            st_atim
-        } else {
-            get_current_time_in_nanos()?
-        };
-        inode.stat.write().unwrap().st_atim = time_to_set;
-        atime = Some(time_to_set);
+    if let Some(atime) = atime {
+        inode.stat.write().unwrap().st_atim = atime;
     }
-
-    if fst_flags.contains(Fstflags::SET_MTIM) || fst_flags.contains(Fstflags::SET_MTIM_NOW) {
-        let time_to_set = if fst_flags.contains(Fstflags::SET_MTIM) {
-            st_mtim
-        } else {
-            get_current_time_in_nanos()?
-        };
-        inode.stat.write().unwrap().st_mtim = time_to_set;
-        mtime = Some(time_to_set);
+    if let Some(mtime) = mtime {
+        inode.stat.write().unwrap().st_mtim = mtime;
     }
 
     if let Kind::File {
@@ -100,3 +79,37 @@ pub(crate) fn fd_filestat_set_times_internal(
 
     Ok(())
 }
+
+/// Resolves the times that are set by `fd_filestat_set_times` and
+/// `path_filestat_set_times` from their `fst_flags`, the `*_NOW` flags
+/// read the realtime clock of the guest and a time whose flags are both
+/// unset is `None` (which leaves it unchanged)
+pub(crate) fn fst_times(
+    env: &WasiEnv,
+    st_atim: Timestamp,
+    st_mtim: Timestamp,
+    fst_flags: Fstflags,
+) -> Result<(Option<Timestamp>, Option<Timestamp>), Errno> {
+    if (fst_flags.contains(Fstflags::SET_ATIM) && fst_flags.contains(Fstflags::SET_ATIM_NOW))
+        || (fst_flags.contains(Fstflags::SET_MTIM) && fst_flags.contains(Fstflags::SET_MTIM_NOW))
+    {
+        return Err(Errno::Inval);
+    }
+
+    let now = || env.runtime.clock().now(Snapshot0Clockid::Realtime);
+    let atime = if fst_flags.contains(Fstflags::SET_ATIM) {
+        Some(st_atim)
+    } else if fst_flags.contains(Fstflags::SET_ATIM_NOW) {
+        Some(now()?)
+    } else {
+        None
+    };
+    let mtime = if fst_flags.contains(Fstflags::SET_MTIM) {
+        Some(st_mtim)
+    } else if fst_flags.contains(Fstflags::SET_MTIM_NOW) {
+        Some(now()?)
+    } else {
+        None
+    };
+    Ok((atime, mtime))
+}
@@ -1,5 +1,6 @@
 use std::{collections::VecDeque, task::Waker};
 
+use futures::TryFutureExt;
 use virtual_fs::{AsyncReadExt, DeviceFile, ReadBuf};
 
 use super::*;
@@ -154,6 +155,72 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
 
                         drop(guard);
 
+                        let work = async move {
+                            let mut handle = match handle.write() {
+                                Ok(a) => a,
+                                Err(_) => return Err(Errno::Fault),
+                            };
+                            // Reads that leave the cursor alone (`fd_pread`) are
+                            // positional so they do not race with other readers
+                            let positional = !is_stdio && !should_update_cursor;
+                            if !is_stdio && !positional {
+                                handle
+                                    .seek(std::io::SeekFrom::Start(offset as u64))
+                                    .await
+                                    .map_err(map_io_err)?;
+                            }
+
+                            let mut total_read = 0usize;
+
+                            let iovs_arr =
+                                iovs.slice(&memory, iovs_len).map_err(mem_error_to_wasi)?;
+                            let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
+                            for iovs in iovs_arr.iter() {
+                                let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
+                                    .slice(&memory, iovs.buf_len)
+                                    .map_err(mem_error_to_wasi)?
+                                    .access_mut()
+                                    .map_err(mem_error_to_wasi)?;
+                                let r = if positional {
+                                    let offset = (offset + total_read) as u64;
+                                    handle.read_at(buf.as_mut(), offset).await
+                                } else {
+                                    handle.read(buf.as_mut()).await
+                                };
+                                let r = r.map_err(|err| {
+                                    let err = From::<std::io::Error>::from(err);
+                                    match err {
+                                        Errno::Again => {
+                                            if is_stdio {
+                                                Errno::Badf
+                                            } else {
+                                                Errno::Again
+                                            }
+                                        }
+                                        a => a,
+                                    }
+                                });
+                                let local_read = match r {
+                                    Ok(s) => s,
+                                    Err(_) if total_read > 0 => break,
+                                    Err(err) => return Err(err),
+                                };
+                                total_read += local_read;
+                                if local_read != buf.len() {
+                                    break;
+                                }
+                            }
+                            Ok(total_read)
+                        }
+                        .map_err(|err| match err {
+                            Errno::Timedout => Errno::Again,
+                            a => a,
+                        });
+                        // Stdio is exempt from the host operation timeout
+                        let host_op_timeout = match is_stdio {
+                            true => None,
+                            false => env.state.host_op_timeout,
+                        };
                         let res = __asyncify_light(
                             env,
                             if fd_flags.contains(Fdflags::NONBLOCK) {
@@ -161,59 +228,9 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                             } else {
                                 None
                             },
-                            async move {
-                                let mut handle = match handle.write() {
-                                    Ok(a) => a,
-                                    Err(_) => return Err(Errno::Fault),
-                                };
-                                if !is_stdio {
-                                    handle
-                                        .seek(std::io::SeekFrom::Start(offset as u64))
-                                        .await
-                                        .map_err(map_io_err)?;
-                                }
-
-                                let mut total_read = 0usize;
-
-                                let iovs_arr =
-                                    iovs.slice(&memory, iovs_len).map_err(mem_error_to_wasi)?;
-                                let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
-                                for iovs in iovs_arr.iter() {
-                                    let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
-                                        .slice(&memory, iovs.buf_len)
-                                        .map_err(mem_error_to_wasi)?
-                                        .access()
-                                        .map_err(mem_error_to_wasi)?;
-                                    let r = handle.read(buf.as_mut()).await.map_err(|err| {
-                                        let err = From::<std::io::Error>::from(err);
-                                        match err {
-                                            Errno::Again => {
-                                                if is_stdio {
-                                                    Errno::Badf
-                                                } else {
-                                                    Errno::Again
-                                                }
-                                            }
-                                            a => a,
-                                        }
-                                    });
-                                    let local_read = match r {
-                                        Ok(s) => s,
-                                        Err(_) if total_read > 0 => break,
-                                        Err(err) => return Err(err),
-                                    };
-                                    total_read += local_read;
-                                    if local_read != buf.len() {
-                                        break;
-                                    }
-                                }
-                                Ok(total_read)
-                            },
+                            __host_op_async(env, host_op_timeout, work),
                         );
-                        let read = wasi_try_ok_ok!(res?.map_err(|err| match err {
-                            Errno::Timedout => Errno::Again,
-                            a => a,
-                        }));
+                        let read = wasi_try_ok_ok!(res?);
                         (read, true)
                     } else {
                         return Ok(Err(Errno::Badf));
@@ -225,11 +242,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                     drop(guard);
 
                     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::ReadTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
 
                     let tasks = env.tasks().clone();
                     let res = __asyncify_light(
@@ -249,16 +262,11 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                                 let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
                                     .slice(&memory, iovs.buf_len)
                                     .map_err(mem_error_to_wasi)?
-                                    .access()
+                                    .access_mut()
                                     .map_err(mem_error_to_wasi)?;
 
                                 let local_read = socket
-                                    .recv(
-                                        tasks.deref(),
-                                        buf.as_mut_uninit(),
-                                        Some(timeout),
-                                        nonblocking,
-                                    )
+                                    .recv(tasks.deref(), buf.as_mut_uninit(), timeout, nonblocking)
                                     .await?;
                                 total_read += local_read;
                                 if total_read != buf.len() {
@@ -304,7 +312,7 @@ pub(crate) fn fd_read_internal<M: MemorySize>(
                                 let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
                                     .slice(&memory, iovs.buf_len)
                                     .map_err(mem_error_to_wasi)?
-                                    .access()
+                                    .access_mut()
                                     .map_err(mem_error_to_wasi)?;
 
                                 let local_read = match nonblocking {
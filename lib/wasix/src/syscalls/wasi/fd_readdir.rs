@@ -36,79 +36,7 @@ pub fn fd_readdir<M: MemorySize>(
     let mut cur_cookie = cookie;
     let mut buf_idx = 0usize;
 
-    let entries: Vec<(String, Filetype, u64)> = {
-        let guard = working_dir.inode.read();
-        match guard.deref() {
-            Kind::Dir { path, entries, .. } => {
-                trace!("reading dir {:?}", path);
-                // TODO: refactor this code
-                // we need to support multiple calls,
-                // simple and obviously correct implementation for now:
-                // maintain consistent order via lexacographic sorting
-                let fs_info = wasi_try!(wasi_try!(state.fs_read_dir(path))
-                    .collect::<Result<Vec<_>, _>>()
-                    .map_err(fs_error_into_wasi_err));
-                let mut entry_vec = wasi_try!(fs_info
-                    .into_iter()
-                    .map(|entry| {
-                        let filename = entry.file_name().to_string_lossy().to_string();
-                        trace!("getting file: {:?}", filename);
-                        let filetype = virtual_file_type_to_wasi_file_type(
-                            entry.file_type().map_err(fs_error_into_wasi_err)?,
-                        );
-                        Ok((
-                            filename, filetype, 0, // TODO: inode
-                        ))
-                    })
-                    .collect::<Result<Vec<(String, Filetype, u64)>, _>>());
-                entry_vec.extend(entries.iter().filter(|(_, inode)| inode.is_preopened).map(
-                    |(name, inode)| {
-                        let stat = inode.stat.read().unwrap();
-                        (
-                            inode.name.read().unwrap().to_string(),
-                            stat.st_filetype,
-                            stat.st_ino,
-                        )
-                    },
-                ));
-                // adding . and .. special folders
-                // TODO: inode
-                entry_vec.push((".".to_string(), Filetype::Directory, 0));
-                entry_vec.push(("..".to_string(), Filetype::Directory, 0));
-                entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
-                entry_vec
-            }
-            Kind::Root { entries } => {
-                trace!("reading root");
-                let sorted_entries = {
-                    let mut entry_vec: Vec<(String, InodeGuard)> = entries
-                        .iter()
-                        .map(|(a, b)| (a.clone(), b.clone()))
-                        .collect();
-                    entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
-                    entry_vec
-                };
-                sorted_entries
-                    .into_iter()
-                    .map(|(name, inode)| {
-                        let stat = inode.stat.read().unwrap();
-                        (
-                            format!("/{}", inode.name.read().unwrap().as_ref()),
-                            stat.st_filetype,
-                            stat.st_ino,
-                        )
-                    })
-                    .collect()
-            }
-            Kind::File { .. }
-            | Kind::Symlink { .. }
-            | Kind::Buffer { .. }
-            | Kind::Socket { .. }
-            | Kind::Pipe { .. }
-            | Kind::EventNotifications { .. }
-            | Kind::Epoll { .. } => return Errno::Notdir,
-        }
-    };
+    let entries = wasi_try!(read_dir_snapshot(env, &working_dir, cookie));
 
     for (entry_path_str, wasi_file_type, ino) in entries.iter().skip(cookie as usize) {
         cur_cookie += 1;
@@ -147,3 +75,106 @@ pub fn fd_readdir<M: MemorySize>(
     wasi_try_mem!(bufused_ref.write(buf_idx));
     Errno::Success
 }
+
+/// Returns the listing of the directory that the cookies of a scan refer to,
+/// a scan starts at cookie 0 (or with the first read of the descriptor) and
+/// takes a snapshot of the directory that the following reads are served from
+pub(crate) fn read_dir_snapshot(
+    env: &WasiEnv,
+    fd: &Fd,
+    cookie: Dircookie,
+) -> Result<DirSnapshot, Errno> {
+    let mut snapshot = fd.inner.dir_snapshot.lock().unwrap();
+    if cookie != 0 {
+        if let Some(entries) = snapshot.as_ref() {
+            return Ok(entries.clone());
+        }
+    }
+    let entries = Arc::new(read_dir_entries(env, &fd.inode)?);
+    snapshot.replace(entries.clone());
+    Ok(entries)
+}
+
+/// Lists the entries of a directory as `(name, type, inode)` tuples in a
+/// stable order, the position of an entry in the list is its cookie
+pub(crate) fn read_dir_entries(
+    env: &WasiEnv,
+    inode: &InodeGuard,
+) -> Result<Vec<(String, Filetype, u64)>, Errno> {
+    let guard = inode.read();
+    match guard.deref() {
+        Kind::Dir { path, entries, .. } => {
+            trace!("reading dir {:?}", path);
+            // TODO: refactor this code
+            // we need to support multiple calls,
+            // simple and obviously correct implementation for now:
+            // maintain consistent order via lexacographic sorting
+            let root_fs = env.state.fs.root_fs.clone();
+            let dir_path = path.clone();
+            let fs_info = __host_op(
+                env,
+                move || root_fs.read_dir(&dir_path)?.collect::<Result<Vec<_>, _>>(),
+                |_| {},
+            )?
+            .map_err(fs_error_into_wasi_err)?;
+            let mut entry_vec = fs_info
+                .into_iter()
+                .map(|entry| {
+                    let filename = entry.file_name().to_string_lossy().to_string();
+                    trace!("getting file: {:?}", filename);
+                    let filetype = virtual_file_type_to_wasi_file_type(
+                        entry.file_type().map_err(fs_error_into_wasi_err)?,
+                    );
+                    Ok((
+                        filename, filetype, 0, // TODO: inode
+                    ))
+                })
+                .collect::<Result<Vec<(String, Filetype, u64)>, Errno>>()?;
+            entry_vec.extend(entries.iter().filter(|(_, inode)| inode.is_preopened).map(
+                |(name, inode)| {
+                    let stat = inode.stat.read().unwrap();
+                    (
+                        inode.name.read().unwrap().to_string(),
+                        stat.st_filetype,
+                        stat.st_ino,
+                    )
+                },
+            ));
+            // adding . and .. special folders
+            // TODO: inode
+            entry_vec.push((".".to_string(), Filetype::Directory, 0));
+            entry_vec.push(("..".to_string(), Filetype::Directory, 0));
+            entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
+            Ok(entry_vec)
+        }
+        Kind::Root { entries } => {
+            trace!("reading root");
+            let sorted_entries = {
+                let mut entry_vec: Vec<(String, InodeGuard)> = entries
+                    .iter()
+                    .map(|(a, b)| (a.clone(), b.clone()))
+                    .collect();
+                entry_vec.sort_by(|a, b| a.0.cmp(&b.0));
+                entry_vec
+            };
+            Ok(sorted_entries
+                .into_iter()
+                .map(|(name, inode)| {
+                    let stat = inode.stat.read().unwrap();
+                    (
+                        format!("/{}", inode.name.read().unwrap().as_ref()),
+                        stat.st_filetype,
+                        stat.st_ino,
+                    )
+                })
+                .collect())
+        }
+        Kind::File { .. }
+        | Kind::Symlink { .. }
+        | Kind::Buffer { .. }
+        | Kind::Socket { .. }
+        | Kind::Pipe { .. }
+        | Kind::EventNotifications { .. }
+        | Kind::Epoll { .. } => Err(Errno::Notdir),
+    }
+}
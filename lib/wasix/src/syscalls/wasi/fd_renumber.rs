@@ -48,6 +48,9 @@ pub(crate) fn fd_renumber_internal(
         inner: FdInner {
             offset: fd_entry.inner.offset.clone(),
             rights: fd_entry.inner.rights_inheriting,
+            close_on_exec: false,
+            dir_snapshot: fd_entry.inner.dir_snapshot.clone(),
+            lock_owner: fd_entry.inner.lock_owner.clone(),
             ..fd_entry.inner
         },
         inode: fd_entry.inode.clone(),
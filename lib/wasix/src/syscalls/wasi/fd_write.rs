@@ -1,5 +1,7 @@
 use std::task::Waker;
 
+use futures::TryFutureExt;
+
 use super::*;
 #[cfg(feature = "journal")]
 use crate::{
@@ -151,71 +153,103 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                         let handle = handle.clone();
                         drop(guard);
 
-                        let res = __asyncify_light(
-                            env,
-                            if fd_entry.inner.flags.contains(Fdflags::NONBLOCK) {
-                                Some(Duration::ZERO)
-                            } else {
-                                None
-                            },
-                            async {
-                                let mut handle = handle.write().unwrap();
-                                if !is_stdio {
-                                    if fd_entry.inner.flags.contains(Fdflags::APPEND) {
-                                        // `fdflags::append` means we need to seek to the end before writing.
-                                        offset = fd_entry.inode.stat.read().unwrap().st_size;
-                                        fd_entry.inner.offset.store(offset, Ordering::Release);
-                                    }
-
-                                    handle
-                                        .seek(std::io::SeekFrom::Start(offset))
-                                        .await
-                                        .map_err(map_io_err)?;
+                        let work = async {
+                            let mut handle = handle.write().unwrap();
+                            let is_append = fd_entry.inner.flags.contains(Fdflags::APPEND);
+                            // Writes that leave the cursor alone (`fd_pwrite`) are
+                            // positional so they do not race with other writers
+                            let positional = !is_stdio && !should_update_cursor && !is_append;
+                            if !is_stdio && !positional {
+                                if is_append {
+                                    // `fdflags::append` means we need to seek to the end before writing.
+                                    offset = fd_entry.inode.stat.read().unwrap().st_size;
+                                    fd_entry.inner.offset.store(offset, Ordering::Release);
                                 }
 
-                                let mut written = 0usize;
+                                handle
+                                    .seek(std::io::SeekFrom::Start(offset))
+                                    .await
+                                    .map_err(map_io_err)?;
+                            }
+
+                            let mut written = 0usize;
 
-                                match &data {
-                                    FdWriteSource::Iovs { iovs, iovs_len } => {
-                                        let iovs_arr = iovs
-                                            .slice(&memory, *iovs_len)
+                            match &data {
+                                FdWriteSource::Iovs { iovs, iovs_len } => {
+                                    let iovs_arr = iovs
+                                        .slice(&memory, *iovs_len)
+                                        .map_err(mem_error_to_wasi)?;
+                                    let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
+                                    for iovs in iovs_arr.iter() {
+                                        let buf = WasmPtr::<u8, M>::new(iovs.buf)
+                                            .slice(&memory, iovs.buf_len)
+                                            .map_err(mem_error_to_wasi)?
+                                            .access()
                                             .map_err(mem_error_to_wasi)?;
-                                        let iovs_arr =
-                                            iovs_arr.access().map_err(mem_error_to_wasi)?;
-                                        for iovs in iovs_arr.iter() {
-                                            let buf = WasmPtr::<u8, M>::new(iovs.buf)
-                                                .slice(&memory, iovs.buf_len)
-                                                .map_err(mem_error_to_wasi)?
-                                                .access()
-                                                .map_err(mem_error_to_wasi)?;
-                                            let local_written =
-                                                match handle.write(buf.as_ref()).await {
-                                                    Ok(s) => s,
-                                                    Err(_) if written > 0 => break,
-                                                    Err(err) => return Err(map_io_err(err)),
-                                                };
-                                            written += local_written;
-                                            if local_written != buf.len() {
-                                                break;
-                                            }
+                                        let res = if positional {
+                                            let offset = offset + written as u64;
+                                            handle.write_at(buf.as_ref(), offset).await
+                                        } else {
+                                            handle.write(buf.as_ref()).await
+                                        };
+                                        let local_written = match res {
+                                            Ok(s) => s,
+                                            Err(_) if written > 0 => break,
+                                            Err(err) => return Err(map_io_err(err)),
+                                        };
+                                        written += local_written;
+                                        if local_written != buf.len() {
+                                            break;
                                         }
                                     }
-                                    FdWriteSource::Buffer(data) => {
-                                        handle.write_all(data).await?;
-                                        written += data.len();
+                                }
+                                FdWriteSource::Buffer(data) if positional => {
+                                    while written < data.len() {
+                                        let offset = offset + written as u64;
+                                        match handle.write_at(&data[written..], offset).await {
+                                            Ok(0) => return Err(Errno::Io),
+                                            Ok(n) => written += n,
+                                            Err(err) => return Err(map_io_err(err)),
+                                        }
                                     }
                                 }
-
-                                if is_stdio {
-                                    handle.flush().await.map_err(map_io_err)?;
+                                FdWriteSource::Buffer(data) => {
+                                    handle.write_all(data).await?;
+                                    written += data.len();
                                 }
-                                Ok(written)
-                            },
-                        );
-                        let written = wasi_try_ok_ok!(res?.map_err(|err| match err {
+                            }
+
+                            // Synchronous writes (`O_DSYNC`/`O_SYNC`) must be durable
+                            // before the syscall returns, just like stdio
+                            if is_stdio
+                                || fd_entry
+                                    .inner
+                                    .flags
+                                    .intersects(Fdflags::DSYNC | Fdflags::SYNC)
+                            {
+                                handle.flush().await.map_err(map_io_err)?;
+                            }
+                            Ok(written)
+                        }
+                        .map_err(|err| match err {
                             Errno::Timedout => Errno::Again,
                             a => a,
-                        }));
+                        });
+                        // Stdio is exempt from the host operation timeout
+                        let host_op_timeout = match is_stdio {
+                            true => None,
+                            false => env.state.host_op_timeout,
+                        };
+                        let res = __asyncify_light(
+                            env,
+                            if fd_entry.inner.flags.contains(Fdflags::NONBLOCK) {
+                                Some(Duration::ZERO)
+                            } else {
+                                None
+                            },
+                            __host_op_async(env, host_op_timeout, work),
+                        );
+                        let written = wasi_try_ok_ok!(res?);
 
                         (written, true, true)
                     } else {
@@ -227,11 +261,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                     drop(guard);
 
                     let nonblocking = fd_flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::WriteTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::WriteTimeout).ok().flatten();
 
                     let tasks = env.tasks().clone();
 
@@ -250,12 +280,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                                         .access()
                                         .map_err(mem_error_to_wasi)?;
                                     let local_sent = socket
-                                        .send(
-                                            tasks.deref(),
-                                            buf.as_ref(),
-                                            Some(timeout),
-                                            nonblocking,
-                                        )
+                                        .send(tasks.deref(), buf.as_ref(), timeout, nonblocking)
                                         .await?;
                                     sent += local_sent;
                                     if local_sent != buf.len() {
@@ -265,7 +290,7 @@ pub(crate) fn fd_write_internal<M: MemorySize>(
                             }
                             FdWriteSource::Buffer(data) => {
                                 sent += socket
-                                    .send(tasks.deref(), data.as_ref(), Some(timeout), nonblocking)
+                                    .send(tasks.deref(), data.as_ref(), timeout, nonblocking)
                                     .await?;
                             }
                         }
@@ -89,7 +89,7 @@ pub(crate) fn path_create_directory_internal(
             // above, but it was in the code before my refactor and I'm keeping it just in case.
             if path_filestat_get_internal(
                 &memory,
-                state,
+                env,
                 inodes,
                 fd,
                 0,
@@ -45,7 +45,7 @@ pub fn path_filestat_get<M: MemorySize>(
 
     let stat = wasi_try!(path_filestat_get_internal(
         &memory,
-        state,
+        env,
         inodes,
         fd,
         flags,
@@ -61,12 +61,13 @@ pub fn path_filestat_get<M: MemorySize>(
 /// return a Filstat or Errno
 pub(crate) fn path_filestat_get_internal(
     memory: &MemoryView,
-    state: &WasiState,
+    env: &WasiEnv,
     inodes: &crate::WasiInodes,
     fd: WasiFd,
     flags: LookupFlags,
     path_string: &str,
 ) -> Result<Filestat, Errno> {
+    let state = &env.state;
     let root_dir = state.fs.get_fd(fd)?;
 
     if !root_dir.inner.rights.contains(Rights::PATH_FILESTAT_GET) {
@@ -83,8 +84,16 @@ pub(crate) fn path_filestat_get_internal(
     let mut stat = if file_inode.is_preopened {
         *file_inode.stat.read().unwrap().deref()
     } else {
-        let guard = file_inode.read();
-        state.fs.get_stat_for_kind(guard.deref())?
+        let state = state.clone();
+        let inode = file_inode.clone();
+        __host_op(
+            env,
+            move || {
+                let guard = inode.read();
+                state.fs.get_stat_for_kind(guard.deref())
+            },
+            |_| {},
+        )??
     };
     stat.st_ino = st_ino;
     Ok(stat)
@@ -121,7 +130,7 @@ pub fn path_filestat_get_old<M: MemorySize>(
 
     let stat = wasi_try!(path_filestat_get_internal(
         &memory,
-        state,
+        env,
         inodes,
         fd,
         flags,
@@ -78,7 +78,6 @@ pub(crate) fn path_filestat_set_times_internal(
     let env = ctx.data();
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
     let fd_entry = state.fs.get_fd(fd)?;
-    let fd_inode = fd_entry.inode;
     if !fd_entry
         .inner
         .rights
@@ -86,36 +85,44 @@ pub(crate) fn path_filestat_set_times_internal(
     {
         return Err(Errno::Access);
     }
-    if (fst_flags.contains(Fstflags::SET_ATIM) && fst_flags.contains(Fstflags::SET_ATIM_NOW))
-        || (fst_flags.contains(Fstflags::SET_MTIM) && fst_flags.contains(Fstflags::SET_MTIM_NOW))
-    {
-        return Err(Errno::Inval);
-    }
+    let (atime, mtime) = fst_times(env, st_atim, st_mtim, fst_flags)?;
 
     let file_inode =
         state
             .fs
             .get_inode_at_path(inodes, fd, path, flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0)?;
-    let stat = {
+
+    // The times are stored by the file system, files that it can not set
+    // them for by path (or that only exist as an open handle) are updated
+    // through their handle instead
+    {
         let guard = file_inode.read();
-        state.fs.get_stat_for_kind(guard.deref())?
-    };
+        match guard.deref() {
+            Kind::File { handle, path, .. } => {
+                match (state.fs.root_fs.set_times(path, atime, mtime), handle) {
+                    (Ok(()), _) | (Err(FsError::Unsupported), None) => {}
+                    (Err(FsError::Unsupported | FsError::EntryNotFound), Some(handle)) => handle
+                        .write()
+                        .unwrap()
+                        .set_times(atime, mtime)
+                        .map_err(fs_error_into_wasi_err)?,
+                    (Err(err), _) => return Err(fs_error_into_wasi_err(err)),
+                }
+            }
+            Kind::Dir { path, .. } => match state.fs.root_fs.set_times(path, atime, mtime) {
+                Ok(()) | Err(FsError::Unsupported) => {}
+                Err(err) => return Err(fs_error_into_wasi_err(err)),
+            },
+            _ => {}
+        }
+    }
 
-    if fst_flags.contains(Fstflags::SET_ATIM) || fst_flags.contains(Fstflags::SET_ATIM_NOW) {
-        let time_to_set = if fst_flags.contains(Fstflags::SET_ATIM) {
-            st_atim
-        } else {
-            get_current_time_in_nanos()?
-        };
-        fd_inode.stat.write().unwrap().st_atim = time_to_set;
+    let mut stat = file_inode.stat.write().unwrap();
+    if let Some(atime) = atime {
+        stat.st_atim = atime;
     }
-    if fst_flags.contains(Fstflags::SET_MTIM) || fst_flags.contains(Fstflags::SET_MTIM_NOW) {
-        let time_to_set = if fst_flags.contains(Fstflags::SET_MTIM) {
-            st_mtim
-        } else {
-            get_current_time_in_nanos()?
-        };
-        fd_inode.stat.write().unwrap().st_mtim = time_to_set;
+    if let Some(mtime) = mtime {
+        stat.st_mtim = mtime;
     }
 
     Ok(())
@@ -102,16 +102,40 @@ pub(crate) fn path_link_internal(
             .fs
             .get_parent_inode_at_path(inodes, new_fd, &target_path_arg, false)?;
 
+    // Hard links to directories are not permitted
+    let source_path = match source_inode.read().deref() {
+        Kind::Dir { .. } | Kind::Root { .. } => return Err(Errno::Perm),
+        Kind::File { path, .. } => Some(path.clone()),
+        _ => None,
+    };
+
+    // The target must not already exist (this also loads it from the
+    // file system if it has not been seen yet)
+    if state
+        .fs
+        .get_inode_at_path(inodes, new_fd, new_path, false)
+        .is_ok()
+    {
+        return Err(Errno::Exist);
+    }
+
     if source_inode.stat.write().unwrap().st_nlink == Linkcount::MAX {
         return Err(Errno::Mlink);
     }
     {
         let mut guard = target_parent_inode.write();
         match guard.deref_mut() {
-            Kind::Dir { entries, .. } => {
+            Kind::Dir { entries, path, .. } => {
                 if entries.contains_key(&new_entry_name) {
                     return Err(Errno::Exist);
                 }
+                // Hard links can not cross from one file system to another
+                if let Some(source_path) = source_path {
+                    let target_path = path.join(&new_entry_name);
+                    if !state.fs.root_fs.is_same_mount(&source_path, &target_path) {
+                        return Err(Errno::Xdev);
+                    }
+                }
                 entries.insert(new_entry_name, source_inode.clone());
             }
             Kind::Root { .. } => return Err(Errno::Inval),
@@ -138,6 +138,22 @@ pub(crate) fn path_open_internal(
         return Ok(Err(Errno::Access));
     }
 
+    // Rights can only be narrowed on the way down, nothing beneath a
+    // directory can be opened with rights that the directory does not hand
+    // out (rights that never apply to files, like those of sockets, are
+    // dropped instead as WASI allows)
+    let requested_rights = fs_rights_base & crate::fs::preopen_rights(true, true, true);
+    if !working_dir_rights_inheriting.contains(requested_rights) {
+        return Ok(Err(Errno::Notcapable));
+    }
+    // Creating a file is a right of the directory it is created in
+    if o_flags.contains(Oflags::CREATE)
+        && !working_dir.inner.rights.contains(Rights::PATH_CREATE_FILE)
+    {
+        return Ok(Err(Errno::Notcapable));
+    }
+    let fs_rights_inheriting = fs_rights_inheriting & working_dir_rights_inheriting;
+
     let mut open_flags = 0;
     // TODO: traverse rights of dirs properly
     // COMMENTED OUT: WASI isn't giving appropriate rights here when opening
@@ -170,6 +186,7 @@ pub(crate) fn path_open_internal(
                 create: create_permission,
                 append: append_permission,
                 truncate: truncate_permission,
+                no_atime: o_flags.contains(Oflags::NOATIME),
             }
         }
         Err(_) => virtual_fs::OpenOptionsConfig {
@@ -179,6 +196,7 @@ pub(crate) fn path_open_internal(
             create_new: o_flags.contains(Oflags::CREATE) && o_flags.contains(Oflags::EXCL),
             create: o_flags.contains(Oflags::CREATE),
             truncate: o_flags.contains(Oflags::TRUNC),
+            no_atime: o_flags.contains(Oflags::NOATIME),
         },
     };
 
@@ -191,6 +209,7 @@ pub(crate) fn path_open_internal(
         create: true,
         append: true,
         truncate: true,
+        no_atime: false,
     };
 
     let minimum_rights = target_rights.minimum_rights(&parent_rights);
@@ -246,8 +265,26 @@ pub(crate) fn path_open_internal(
                 }
                 // TODO: I strongly suspect that assigning the handle unconditionally
                 // breaks opening the same file multiple times.
+                let conf = open_options.get_config();
+                let root_fs = state.fs.root_fs.clone();
+                let host_path = path.clone();
+                let abandoned_path = path.clone();
+                let file = wasi_try_ok_ok!(__host_op(
+                    env,
+                    move || root_fs.new_open_options().options(conf).open(&host_path),
+                    move |file| {
+                        // Opening an existing file can not be undone (it may
+                        // even have been truncated) so it is only reported
+                        if file.is_ok() {
+                            warn!(
+                                path = %abandoned_path.display(),
+                                "file was opened on the host after the open timed out"
+                            );
+                        }
+                    }
+                ));
                 *handle = Some(Arc::new(std::sync::RwLock::new(wasi_try_ok_ok!(
-                    open_options.open(&path).map_err(fs_error_into_wasi_err)
+                    file.map_err(fs_error_into_wasi_err)
                 ))));
 
                 if let Some(handle) = handle {
@@ -268,14 +305,13 @@ pub(crate) fn path_open_internal(
             }
             Kind::Buffer { .. } => unimplemented!("wasi::path_open for Buffer type files"),
             Kind::Root { .. } => {
+                wasi_try_ok_ok!(check_directory_open(o_flags, fs_rights_base));
                 if !o_flags.contains(Oflags::DIRECTORY) {
                     return Ok(Err(Errno::Notcapable));
                 }
             }
             Kind::Dir { .. } => {
-                if fs_rights_base.contains(Rights::FD_WRITE) {
-                    return Ok(Err(Errno::Isdir));
-                }
+                wasi_try_ok_ok!(check_directory_open(o_flags, fs_rights_base));
             }
             Kind::Socket { .. }
             | Kind::Pipe { .. }
@@ -353,7 +389,30 @@ pub(crate) fn path_open_internal(
                     open_flags |= Fd::TRUNCATE;
                 }
 
-                match open_options.open(&new_file_host_path) {
+                let conf = open_options.get_config();
+                let root_fs = state.fs.root_fs.clone();
+                let host_path = new_file_host_path.clone();
+                let abandoned_fs = state.fs.root_fs.clone();
+                let abandoned_path = new_file_host_path.clone();
+                let file = wasi_try_ok_ok!(__host_op(
+                    env,
+                    move || root_fs.new_open_options().options(conf).open(&host_path),
+                    move |file| {
+                        // The guest was told that the file could not be created
+                        // so the file that got created regardless is removed
+                        if let Ok(file) = file {
+                            drop(file);
+                            if let Err(err) = abandoned_fs.remove_file(&abandoned_path) {
+                                warn!(
+                                    path = %abandoned_path.display(),
+                                    "failed to remove the file that was created on the host after the open timed out - {}",
+                                    err
+                                );
+                            }
+                        }
+                    }
+                ));
+                match file {
                     Ok(handle) => Some(handle),
                     Err(err) => {
                         // Even though the file does not exist, it still failed to create with
@@ -421,3 +480,12 @@ pub(crate) fn path_open_internal(
 
     Ok(Ok(out_fd))
 }
+
+/// Directories can only be opened for reading, asking to write to or to
+/// truncate one fails with `Errno::Isdir` (like `open(2)` does)
+fn check_directory_open(o_flags: Oflags, fs_rights_base: Rights) -> Result<(), Errno> {
+    if fs_rights_base.contains(Rights::FD_WRITE) || o_flags.contains(Oflags::TRUNC) {
+        return Err(Errno::Isdir);
+    }
+    Ok(())
+}
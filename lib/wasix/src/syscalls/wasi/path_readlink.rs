@@ -14,9 +14,12 @@ use crate::syscalls::*;
 ///     Space available pointed to by `buf`
 /// Outputs:
 /// - `char *buf`
-///     Pointer to characters containing the path that the symlink points to
+///     Pointer to characters containing the path that the symlink points to,
+///     truncated to `buf_len` bytes and not NUL terminated
 /// - `u32 buf_used`
-///     The number of bytes written to `buf`
+///     The number of bytes written to `buf`, never more than `buf_len`, when
+///     this is equal to `buf_len` the path may have been truncated and the
+///     call can be retried with a bigger buffer
 #[instrument(level = "trace", skip_all, fields(%dir_fd, path = field::Empty), ret)]
 pub fn path_readlink<M: MemorySize>(
     ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -43,20 +46,15 @@ pub fn path_readlink<M: MemorySize>(
         let guard = inode.read();
         if let Kind::Symlink { relative_path, .. } = guard.deref() {
             let rel_path_str = relative_path.to_string_lossy();
+            let bytes = rel_path_str.as_bytes();
             let buf_len: u64 = buf_len.into();
-            let bytes = rel_path_str.bytes();
-            if bytes.len() as u64 >= buf_len {
-                return Errno::Overflow;
-            }
-            let bytes: Vec<_> = bytes.collect();
+            let copy_len = bytes.len().min(buf_len as usize);
 
-            let out = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(bytes.len()))));
-            wasi_try_mem!(out.write_slice(&bytes));
-            // should we null terminate this?
+            let out = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(copy_len))));
+            wasi_try_mem!(out.write_slice(&bytes[..copy_len]));
 
-            let bytes_len: M::Offset =
-                wasi_try!(bytes.len().try_into().map_err(|_| Errno::Overflow));
-            wasi_try_mem!(buf_used.deref(&memory).write(bytes_len));
+            let copy_len: M::Offset = wasi_try!(copy_len.try_into().map_err(|_| Errno::Overflow));
+            wasi_try_mem!(buf_used.deref(&memory).write(copy_len));
         } else {
             return Errno::Inval;
         }
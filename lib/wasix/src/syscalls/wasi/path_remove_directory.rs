@@ -44,6 +44,9 @@ pub(crate) fn path_remove_directory_internal(
     let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
     let working_dir = state.fs.get_fd(fd)?;
 
+    // this is to be sure the directory is fetched from the filesystem if needed
+    let _ = state.fs.get_inode_at_path(inodes, fd, path, false);
+
     let (parent_inode, dir_name) =
         state
             .fs
@@ -145,11 +145,15 @@ pub fn path_rename_internal(
                     drop(guard);
                     let state = state;
                     let host_adjusted_target_path = host_adjusted_target_path.clone();
-                    __asyncify_light(env, None, async move {
-                        state
-                            .fs_rename(path_clone, &host_adjusted_target_path)
-                            .await
-                    })?
+                    __asyncify_light(
+                        env,
+                        None,
+                        __host_op_async(env, env.state.host_op_timeout, async move {
+                            state
+                                .fs_rename(path_clone, &host_adjusted_target_path)
+                                .await
+                        }),
+                    )?
                 };
                 // if the above operation failed we have to revert the previous change and then fail
                 if let Err(e) = result {
@@ -172,11 +176,15 @@ pub fn path_rename_internal(
                 let res = {
                     let state = state;
                     let host_adjusted_target_path = host_adjusted_target_path.clone();
-                    __asyncify_light(env, None, async move {
-                        state
-                            .fs_rename(cloned_path, &host_adjusted_target_path)
-                            .await
-                    })?
+                    __asyncify_light(
+                        env,
+                        None,
+                        __host_op_async(env, env.state.host_op_timeout, async move {
+                            state
+                                .fs_rename(cloned_path, &host_adjusted_target_path)
+                                .await
+                        }),
+                    )?
                 };
                 if let Err(e) = res {
                     return Ok(e);
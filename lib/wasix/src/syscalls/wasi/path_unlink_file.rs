@@ -54,6 +54,11 @@ pub(crate) fn path_unlink_file_internal(
     let (memory, mut state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
 
     let inode = wasi_try_ok!(state.fs.get_inode_at_path(inodes, fd, path, false));
+    // Directories are removed with `path_remove_directory` (which is what
+    // `unlinkat` with `AT_REMOVEDIR` maps to), they must be left untouched
+    if let Kind::Dir { .. } | Kind::Root { .. } = inode.read().deref() {
+        return Ok(Errno::Isdir);
+    }
     let (parent_inode, childs_name) = wasi_try_ok!(state.fs.get_parent_inode_at_path(
         inodes,
         fd,
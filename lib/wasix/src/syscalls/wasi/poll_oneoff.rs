@@ -56,6 +56,9 @@ impl EventResult {
 /// Output:
 /// - `u32 nevents`
 ///     The number of events seen
+///
+/// Events that are ready at the same time are reported in the same order
+/// as their subscriptions appear in `in`
 //#[instrument(level = "trace", skip_all, fields(timeout_ms = field::Empty, fd_guards = field::Empty, seen = field::Empty), ret)]
 pub fn poll_oneoff<M: MemorySize + 'static>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
@@ -69,15 +72,12 @@ pub fn poll_oneoff<M: MemorySize + 'static>(
     ctx = wasi_try_ok!(maybe_backoff::<M>(ctx)?);
     ctx = wasi_try_ok!(maybe_snapshot::<M>(ctx)?);
 
-    ctx.data_mut().poll_seed += 1;
     let mut env = ctx.data();
     let mut memory = unsafe { env.memory_view(&ctx) };
 
     let subscription_array = wasi_try_mem_ok!(in_.slice(&memory, nsubscriptions));
     let mut subscriptions = Vec::with_capacity(subscription_array.len() as usize);
-    for n in 0..subscription_array.len() {
-        let n = (n + env.poll_seed) % subscription_array.len();
-        let sub = subscription_array.index(n);
+    for sub in subscription_array.iter() {
         let s = wasi_try_mem_ok!(sub.read());
         subscriptions.push((None, PollEventSet::default(), s));
     }
@@ -212,6 +212,8 @@ pub(crate) fn poll_fd_guard(
 /// Output:
 /// - `u32 nevents`
 ///     The number of events seen
+///
+/// The events are passed to `process_events` in the order of `subs`
 pub(crate) fn poll_oneoff_internal<'a, M: MemorySize, After>(
     mut ctx: FunctionEnvMut<'a, WasiEnv>,
     mut subs: Vec<(Option<WasiFd>, PollEventSet, Subscription)>,
@@ -437,11 +439,36 @@ where
         }
     };
 
+    // Signals that arrive while we wait interrupt the poll (like they do
+    // for `ppoll`) so that their handler can run, this is only the case when
+    // a signal handler has been registered and for signals that are not
+    // ignored by default (those are handled once the poll returns)
+    let signaled = {
+        let thread = env.thread.clone();
+        let has_handler = env
+            .try_inner()
+            .map_or(false, |inner| inner.signal.is_some());
+        async move {
+            if has_handler {
+                std::future::poll_fn(|cx| {
+                    match thread.has_interrupting_signals_or_subscribe(cx.waker()) {
+                        true => Poll::Ready(()),
+                        false => Poll::Pending,
+                    }
+                })
+                .await
+            } else {
+                InfiniteSleep::default().await
+            }
+        }
+    };
+
     // Build the trigger using the timeout
     let trigger = async move {
         tokio::select! {
             res = batch => res,
-            _ = timeout => Err(Errno::Timedout)
+            _ = timeout => Err(Errno::Timedout),
+            _ = signaled => Err(Errno::Intr),
         }
     };
 
@@ -477,6 +504,10 @@ where
 
     // If we are rewound then its time to process them
     if let Some(events) = unsafe { handle_rewind::<M, Result<Vec<EventResult>, Errno>>(&mut ctx) } {
+        if let Err(Errno::Intr) = events {
+            wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+            return Ok(Errno::Intr);
+        }
         let events = events.map(|events| events.into_iter().map(EventResult::into_event).collect());
         process_events(&ctx, events);
         return Ok(Errno::Success);
@@ -488,6 +519,12 @@ where
         Box::pin(trigger),
     )?;
     if let AsyncifyAction::Finish(mut ctx, events) = res {
+        // The poll was interrupted by a signal, its handler runs before
+        // returning to the caller
+        if let Err(Errno::Intr) = events {
+            wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+            return Ok(Errno::Intr);
+        }
         let events = events.map(|events| events.into_iter().map(EventResult::into_event).collect());
         process_events(&ctx, events);
     }
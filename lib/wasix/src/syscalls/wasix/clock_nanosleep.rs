@@ -0,0 +1,77 @@
+use wasmer_wasix_types::wasi::Subclockflags;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `clock_nanosleep()`
+/// Sends the current thread to sleep until a deadline on a particular clock
+///
+/// Note: This is similar to `clock_nanosleep` in POSIX
+///
+/// ## Parameters
+///
+/// * `clock_id` - The clock that the deadline is measured against
+/// * `flags` - When `SUBSCRIPTION_CLOCK_ABSTIME` is set the `timeout` is an
+///   absolute time of the clock, otherwise it is relative to the current time
+/// * `timeout` - The deadline (or duration) of the sleep in nanoseconds
+///
+/// ## Return
+///
+/// Returns `Errno::Intr` if the sleep was interrupted by a signal, in which
+/// case the time that was left to sleep is written to `ret_remaining` for
+/// relative sleeps.
+#[instrument(level = "trace", skip_all, fields(?clock_id, ?flags, %timeout), ret)]
+pub fn clock_nanosleep<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    clock_id: Snapshot0Clockid,
+    flags: Subclockflags,
+    timeout: Timestamp,
+    ret_remaining: WasmPtr<Timestamp, M>,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let is_absolute = flags.contains(Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME);
+    let deadline = match is_absolute {
+        true => timeout,
+        false => wasi_try_ok!(clock_now(ctx.data(), clock_id)).saturating_add(timeout),
+    };
+
+    let now = wasi_try_ok!(clock_now(ctx.data(), clock_id));
+    if now >= deadline {
+        return Ok(Errno::Success);
+    }
+
+    // The sleep goes through the clock itself so that it ends when the clock
+    // (which is not necessarily the clock of the host) reaches the deadline
+    let env = ctx.data();
+    let sleep = env.runtime.clock().sleep(
+        clock_id,
+        Duration::from_nanos(deadline - now),
+        env.tasks().clone(),
+    );
+    let res = __asyncify(&mut ctx, None, async move {
+        sleep.await;
+        Ok(())
+    })?;
+
+    if let Err(err) = res {
+        if !is_absolute {
+            let now = wasi_try_ok!(clock_now(ctx.data(), clock_id));
+            let env = ctx.data();
+            let memory = unsafe { env.memory_view(&ctx) };
+            wasi_try_mem_ok!(ret_remaining.write(&memory, deadline.saturating_sub(now)));
+        }
+        return Ok(err);
+    }
+    Ok(Errno::Success)
+}
+
+/// Reads the current time of a clock as seen by the guest (which includes
+/// any offset that was set with `clock_time_set`)
+fn clock_now(env: &WasiEnv, clock_id: Snapshot0Clockid) -> Result<Timestamp, Errno> {
+    let mut now = env.runtime.clock().now(clock_id)? as i64;
+    if let Some(offset) = env.state.clock_offset.lock().unwrap().get(&clock_id) {
+        now += *offset;
+    }
+    Ok(now as Timestamp)
+}
@@ -0,0 +1,50 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fchdir()`
+/// Sets the current working directory to the directory that is
+/// referenced by a file descriptor
+///
+/// ## Parameters
+///
+/// * `fd` - Directory that becomes the current working directory
+#[instrument(level = "trace", skip_all, fields(%fd, path = field::Empty), ret)]
+pub fn fchdir(mut ctx: FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<Errno, WasiError> {
+    let path = wasi_try_ok!(fchdir_internal(&mut ctx, fd));
+    Span::current().record("path", path.as_str());
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        JournalEffector::save_chdir(&mut ctx, path).map_err(|err| {
+            tracing::error!("failed to chdir event - {}", err);
+            WasiError::Exit(ExitCode::from(Errno::Fault))
+        })?;
+    }
+
+    Ok(Errno::Success)
+}
+
+/// Changes the current directory and returns the path it was changed to
+pub fn fchdir_internal(ctx: &mut FunctionEnvMut<'_, WasiEnv>, fd: WasiFd) -> Result<String, Errno> {
+    let env = ctx.data();
+    let fd_entry = env.state.fs.get_fd(fd)?;
+
+    let path = {
+        let guard = fd_entry.inode.read();
+        match guard.deref() {
+            Kind::Dir { path, .. } => path.to_string_lossy().to_string(),
+            Kind::Root { .. } => "/".to_string(),
+            _ => return Err(Errno::Notdir),
+        }
+    };
+
+    // Entering a directory requires being able to look up paths within it
+    if !fd_entry.inner.rights.contains(Rights::PATH_OPEN) {
+        return Err(Errno::Access);
+    }
+
+    // The current directory is swapped in one step so that a concurrent
+    // resolution of a relative path sees either the old or the new one
+    env.state.fs.set_current_dir(&path);
+    Ok(path)
+}
@@ -0,0 +1,65 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_bytes_available()`
+/// Returns the number of bytes that can be read from a file descriptor
+/// without blocking (the equivalent of `ioctl(fd, FIONREAD)`)
+///
+/// Input:
+/// - `Fd fd`
+///     The file descriptor to query
+///
+/// Output:
+/// - `Filesize *ret_bytes`
+///     The number of bytes that are ready to be read, file descriptors
+///     that can not be read from always report zero
+#[instrument(level = "trace", skip_all, fields(%fd, bytes = field::Empty), ret)]
+pub fn fd_bytes_available<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    ret_bytes: WasmPtr<Filesize, M>,
+) -> Errno {
+    let bytes = wasi_try!(fd_bytes_available_internal(&mut ctx, fd));
+    Span::current().record("bytes", bytes);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_bytes.write(&memory, bytes));
+
+    Errno::Success
+}
+
+pub(crate) fn fd_bytes_available_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+) -> Result<Filesize, Errno> {
+    let env = ctx.data();
+    let (_, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    let fd_entry = state.fs.get_fd(fd)?;
+    if !fd_entry.inner.rights.contains(Rights::FD_READ) {
+        return Ok(0);
+    }
+    let offset = fd_entry.inner.offset.load(Ordering::Acquire);
+
+    // Only what is already buffered is counted, the readiness of the file
+    // is not polled as that would replace the waker of a blocked reader
+    let guard = fd_entry.inode.read();
+    let bytes = match guard.deref() {
+        Kind::File {
+            handle: Some(handle),
+            ..
+        } => {
+            let handle = handle.read().unwrap();
+            if fd_entry.is_stdio {
+                handle.bytes_available() as Filesize
+            } else {
+                handle.size().saturating_sub(offset)
+            }
+        }
+        Kind::Pipe { pipe } => pipe.bytes_available() as Filesize,
+        Kind::Socket { socket } => socket.bytes_available().map_err(map_io_err)? as Filesize,
+        Kind::Buffer { buffer } => (buffer.len() as u64).saturating_sub(offset),
+        _ => 0,
+    };
+    Ok(bytes)
+}
@@ -0,0 +1,62 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_close_range()`
+/// Closes all the open file descriptors in a range (like `close_range`),
+/// pre-opened directories are skipped the same way `fd_close` skips them
+///
+/// Inputs:
+/// - `Fd min_fd`
+///     The first file descriptor of the range
+/// - `Fd max_fd`
+///     The last file descriptor of the range (inclusive)
+/// - `u32 flags`
+///     With `CLOSE_RANGE_FLAGS_CLOEXEC` the file descriptors are marked to be
+///     closed by `proc_exec` instead of being closed right away
+/// Errors:
+/// - `Errno::Inval`
+///     If `min_fd` is larger than `max_fd` or the flags are unknown
+#[instrument(level = "trace", skip_all, fields(%min_fd, %max_fd, %flags), ret)]
+pub fn fd_close_range(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    min_fd: WasiFd,
+    max_fd: WasiFd,
+    flags: u32,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    if min_fd > max_fd || flags & !CLOSE_RANGE_FLAGS_CLOEXEC != 0 {
+        return Ok(Errno::Inval);
+    }
+
+    let env = ctx.data();
+    let (_, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    let fds = {
+        let fd_map = state.fs.fd_map.read().unwrap();
+        fd_map
+            .keys()
+            .filter(|fd| (min_fd..=max_fd).contains(fd))
+            .collect::<Vec<_>>()
+    };
+
+    if flags & CLOSE_RANGE_FLAGS_CLOEXEC != 0 {
+        let mut fd_map = state.fs.fd_map.write().unwrap();
+        for fd in fds {
+            if let Some(inner) = fd_map.get_mut(fd) {
+                inner.close_on_exec = true;
+            }
+        }
+        return Ok(Errno::Success);
+    }
+
+    // Like `close_range` this does not stop at descriptors that fail to
+    // close, they are closed anyway
+    for fd in fds {
+        let ret = fd_close_internal(&mut ctx, fd)?;
+        if ret != Errno::Success {
+            trace!(%fd, "failed to close the file descriptor - {}", ret);
+        }
+    }
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,66 @@
+use virtual_fs::range_lock;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_lock_range()`
+/// Tries to take an advisory lock on a range of a file, like `fcntl(F_SETLK)`.
+/// Locks are held by the open file description (which is shared by the
+/// descriptors duplicated from one another) so it never conflicts with its
+/// own locks, while opening the file again gives a description whose locks
+/// conflict. They are released when its last descriptor is closed
+///
+/// Inputs:
+/// - `Fd fd`
+///     The file descriptor of the file to lock
+/// - `Filesize offset`
+///     The offset of the first byte of the range
+/// - `Filesize len`
+///     The number of bytes in the range, zero locks up to the end of the
+///     file however much it grows
+/// - `Bool exclusive`
+///     Whether to take an exclusive (write) lock rather than a shared
+///     (read) lock
+///
+/// Returns `Errno::Again` when the range is already locked through another
+/// open file
+#[instrument(level = "trace", skip_all, fields(%fd, %offset, %len, %exclusive), ret)]
+pub fn fd_lock_range(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    offset: Filesize,
+    len: Filesize,
+    exclusive: Bool,
+) -> Errno {
+    let exclusive = match exclusive {
+        Bool::False => false,
+        Bool::True => true,
+        _ => return Errno::Inval,
+    };
+
+    let env = ctx.data();
+    let fd_entry = wasi_try!(env.state.fs.get_fd(fd));
+    let required = match exclusive {
+        true => Rights::FD_WRITE,
+        false => Rights::FD_READ,
+    };
+    if !fd_entry.inner.rights.contains(required) {
+        return Errno::Badf;
+    }
+
+    match fd_entry.inode.read().deref() {
+        Kind::File { .. } => {}
+        Kind::Dir { .. } | Kind::Root { .. } => return Errno::Isdir,
+        _ => return Errno::Notsup,
+    }
+    let (start, end) =
+        wasi_try!(range_lock::lock_bounds(offset, len).map_err(fs_error_into_wasi_err));
+    match fd_entry
+        .inner
+        .lock_owner
+        .try_lock(&fd_entry.inode, start, end, exclusive)
+    {
+        true => Errno::Success,
+        false => Errno::Again,
+    }
+}
@@ -0,0 +1,55 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_readahead()`
+/// Asks for a range of a file to be loaded ahead of time because it is
+/// about to be read sequentially. This is only a hint, file systems that
+/// can not make use of it simply ignore it
+///
+/// Inputs:
+/// - `Fd fd`
+///     The file descriptor that will be read
+/// - `Filesize offset`
+///     The offset from which the file will be read
+/// - `Filesize len`
+///     The number of bytes that will be read
+#[instrument(level = "trace", skip_all, fields(%fd, %offset, %len), ret)]
+pub fn fd_readahead(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    offset: Filesize,
+    len: Filesize,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let state = env.state.clone();
+    let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
+    if !fd_entry.inner.rights.contains(Rights::FD_READ) {
+        return Ok(Errno::Access);
+    }
+    wasi_try_ok!(offset.checked_add(len).ok_or(Errno::Inval));
+
+    let handle = {
+        let guard = fd_entry.inode.read();
+        match guard.deref() {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => handle.clone(),
+            Kind::File { handle: None, .. } => return Ok(Errno::Badf),
+            Kind::Dir { .. } | Kind::Root { .. } => return Ok(Errno::Isdir),
+            Kind::Socket { .. } | Kind::Pipe { .. } => return Ok(Errno::Spipe),
+            // Nothing needs to be loaded for the remaining kinds
+            _ => return Ok(Errno::Success),
+        }
+    };
+
+    #[allow(clippy::await_holding_lock)]
+    Ok(wasi_try_ok!(__asyncify_light(env, None, async move {
+        let mut handle = handle.write().map_err(|_| Errno::Fault)?;
+        handle
+            .readahead(offset, len)
+            .await
+            .map(|_| Errno::Success)
+            .map_err(map_io_err)
+    })?))
+}
@@ -0,0 +1,101 @@
+use super::*;
+use crate::syscalls::*;
+
+/// Size of the fixed part of a `dirent64` record (`d_ino`, `d_off`,
+/// `d_reclen` and `d_type`), the name follows right after it
+const DIRENT64_HEADER_LEN: usize = 19;
+
+/// ### `fd_readdir_raw()`
+/// Reads the entries of a directory into a buffer of `dirent64` records
+/// (as filled by `getdents64` on Linux), so that a libc can hand the buffer
+/// out without converting every entry
+///
+/// Each record holds the inode (`u64`), the cookie of the next entry
+/// (`i64`), the length of the record (`u16`), the type of the entry
+/// (`u8`, a `DT_*` value) and the null terminated name, padded so that
+/// every record starts on an 8 byte boundary. Only whole records are
+/// written to the buffer.
+///
+/// Inputs:
+/// - `Fd fd`
+///     File descriptor of the directory that is read
+/// - `void *buf`
+///     Buffer where the records are stored
+/// - `u32 buf_len`
+///     Length of the buffer
+/// - `Dircookie cookie`
+///     Where the directory reading should start from
+/// Output:
+/// - `u32 *bufused`
+///     The number of bytes stored in `buf`, zero once the end of the
+///     directory was reached
+/// - `Dircookie *ret_cookie`
+///     The cookie to pass to the next call to resume reading
+#[instrument(level = "trace", skip_all, fields(%fd, %cookie, bufused = field::Empty), ret)]
+pub fn fd_readdir_raw<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    buf: WasmPtr<u8, M>,
+    buf_len: M::Offset,
+    cookie: Dircookie,
+    bufused: WasmPtr<M::Offset, M>,
+    ret_cookie: WasmPtr<Dircookie, M>,
+) -> Errno {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+
+    let working_dir = wasi_try!(state.fs.get_fd(fd));
+    if !working_dir.inner.rights.contains(Rights::FD_READDIR) {
+        return Errno::Access;
+    }
+    let entries = wasi_try!(read_dir_snapshot(env, &working_dir, cookie));
+
+    let buf_len: u64 = buf_len.into();
+    let buf_len = buf_len as usize;
+    let mut records = Vec::new();
+    let mut next_cookie = cookie;
+    for (name, filetype, ino) in entries.iter().skip(cookie as usize) {
+        let reclen = (DIRENT64_HEADER_LEN + name.len() + 1).next_multiple_of(8);
+        if records.len() + reclen > buf_len {
+            break;
+        }
+        next_cookie += 1;
+
+        let start = records.len();
+        records.extend_from_slice(&ino.to_le_bytes());
+        records.extend_from_slice(&(next_cookie as i64).to_le_bytes());
+        records.extend_from_slice(&(reclen as u16).to_le_bytes());
+        records.push(dirent64_type(*filetype));
+        records.extend_from_slice(name.as_bytes());
+        records.resize(start + reclen, 0);
+    }
+
+    // Like `getdents64` the call fails if not even one record fits
+    if records.is_empty() && (next_cookie as usize) < entries.len() {
+        return Errno::Inval;
+    }
+    Span::current().record("bufused", records.len());
+
+    let buf = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(records.len()))));
+    wasi_try_mem!(buf.write_slice(&records));
+    wasi_try_mem!(bufused.write(&memory, wasi_try!(to_offset::<M>(records.len()))));
+    wasi_try_mem!(ret_cookie.write(&memory, next_cookie));
+
+    Errno::Success
+}
+
+/// Converts a file type into the matching `DT_*` value of `dirent64`
+fn dirent64_type(filetype: Filetype) -> u8 {
+    match filetype {
+        Filetype::Unknown => 0,
+        Filetype::BlockDevice => 6,
+        Filetype::CharacterDevice => 2,
+        Filetype::Directory => 4,
+        Filetype::RegularFile => 8,
+        Filetype::SocketDgram
+        | Filetype::SocketStream
+        | Filetype::SocketRaw
+        | Filetype::SocketSeqpacket => 12,
+        Filetype::SymbolicLink => 10,
+    }
+}
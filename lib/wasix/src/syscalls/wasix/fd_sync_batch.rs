@@ -0,0 +1,63 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_sync_batch()`
+/// Synchronizes the data and metadata of multiple files to disk in one call,
+/// the files are flushed concurrently
+///
+/// Inputs:
+/// - `const __wasi_fd_t *fds`
+///     The file descriptors to sync
+/// - `u32 fds_len`
+///     The number of file descriptors in `fds`
+/// Output:
+/// - `__wasi_errno_t *results`
+///     The result of syncing each file descriptor, in the same order as `fds`
+#[instrument(level = "trace", skip_all, fields(fds_len = field::Empty), ret)]
+pub fn fd_sync_batch<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fds: WasmPtr<WasiFd, M>,
+    fds_len: M::Offset,
+    results: WasmPtr<Errno, M>,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let fds = wasi_try_mem_ok!(fds.slice(&memory, fds_len));
+    let fds = wasi_try_mem_ok!(fds.read_to_vec());
+    Span::current().record("fds_len", fds.len());
+
+    // Make sure the results can be written before doing any work
+    wasi_try_mem_ok!(results.slice(&memory, fds_len));
+
+    let state = env.state.clone();
+    let errnos = wasi_try_ok!(__asyncify(&mut ctx, None, async move {
+        let syncs = fds.iter().map(|fd| fd_sync_one(&state, *fd));
+        Ok(futures::future::join_all(syncs).await)
+    })?);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let results = wasi_try_mem_ok!(results.slice(&memory, fds_len));
+    wasi_try_mem_ok!(results.write_slice(&errnos));
+
+    Ok(Errno::Success)
+}
+
+/// Flushes a single file descriptor of the batch
+async fn fd_sync_one(state: &WasiState, fd: WasiFd) -> Errno {
+    let fd_entry = match state.fs.get_fd(fd) {
+        Ok(fd_entry) => fd_entry,
+        Err(err) => return err,
+    };
+    if !fd_entry.inner.rights.contains(Rights::FD_SYNC) {
+        return Errno::Access;
+    }
+    drop(fd_entry);
+
+    match state.fs.flush(fd).await {
+        Ok(()) => Errno::Success,
+        Err(err) => err,
+    }
+}
@@ -0,0 +1,39 @@
+use virtual_fs::range_lock;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fd_unlock_range()`
+/// Releases the advisory locks taken with `fd_lock_range` on a range of
+/// a file, locks that only partly overlap the range are shrunk
+///
+/// Inputs:
+/// - `Fd fd`
+///     The file descriptor of the locked file
+/// - `Filesize offset`
+///     The offset of the first byte of the range
+/// - `Filesize len`
+///     The number of bytes in the range, zero means up to the end of the file
+#[instrument(level = "trace", skip_all, fields(%fd, %offset, %len), ret)]
+pub fn fd_unlock_range(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    offset: Filesize,
+    len: Filesize,
+) -> Errno {
+    let env = ctx.data();
+    let fd_entry = wasi_try!(env.state.fs.get_fd(fd));
+
+    match fd_entry.inode.read().deref() {
+        Kind::File { .. } => {}
+        Kind::Dir { .. } | Kind::Root { .. } => return Errno::Isdir,
+        _ => return Errno::Notsup,
+    }
+    let (start, end) =
+        wasi_try!(range_lock::lock_bounds(offset, len).map_err(fs_error_into_wasi_err));
+    fd_entry
+        .inner
+        .lock_owner
+        .unlock(&fd_entry.inode, start, end);
+    Errno::Success
+}
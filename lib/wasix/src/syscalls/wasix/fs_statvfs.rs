@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use wasmer_wasix_types::wasi::Statvfs;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `fs_statvfs()`
+/// Returns statistics about the file system that holds a path, such as
+/// its size and the amount of free space
+///
+/// Inputs:
+/// - `Fd fd`
+///     The directory that `path` is relative to
+/// - `const char *path`
+///     String containing the file path
+/// - `u32 path_len`
+///     The length of the `path` string
+/// Output:
+/// - `__wasi_statvfs_t *buf`
+///     The location where the statistics will be stored
+#[instrument(level = "trace", skip_all, fields(%fd, path = field::Empty), ret)]
+pub fn fs_statvfs<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    buf: WasmPtr<Statvfs, M>,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let (memory, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    let path_string = unsafe { get_input_str_ok!(&memory, path, path_len) };
+    Span::current().record("path", path_string.as_str());
+
+    let root_dir = wasi_try_ok!(state.fs.get_fd(fd));
+    if !root_dir.inner.rights.contains(Rights::PATH_FILESTAT_GET) {
+        return Ok(Errno::Access);
+    }
+    let inode = wasi_try_ok!(state.fs.get_inode_at_path(inodes, fd, &path_string, true));
+
+    let host_path = {
+        let guard = inode.read();
+        match guard.deref() {
+            Kind::File { path, .. } | Kind::Dir { path, .. } => path.clone(),
+            Kind::Root { .. } => PathBuf::from("/"),
+            _ => return Ok(Errno::Inval),
+        }
+    };
+
+    let stat = wasi_try_ok!(state
+        .fs
+        .root_fs
+        .statvfs(&host_path)
+        .map_err(fs_error_into_wasi_err));
+
+    let block_size = stat.block_size.max(1);
+    let stat = Statvfs {
+        f_bsize: block_size,
+        f_blocks: stat.total_bytes / block_size,
+        f_bfree: stat.free_bytes / block_size,
+        f_bavail: stat.available_bytes / block_size,
+        f_files: stat.total_inodes,
+        f_ffree: stat.free_inodes,
+    };
+    wasi_try_mem_ok!(buf.deref(&memory).write(stat));
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,63 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `memory_protect()`
+/// Changes the access the guest has to a range of its linear memory (like
+/// `mprotect`), accessing the range in a way the protection forbids traps
+///
+/// Syscalls check the buffers they are passed against the protection and
+/// fail with `Errno::Fault` instead of touching protected memory. The check
+/// only sees the protection at the start of the syscall (another thread may
+/// change it concurrently) and memory snapshots of the journal read the
+/// memory directly, so they must not be taken while pages are inaccessible
+///
+/// Inputs:
+/// - `Pointer addr`
+///     The start of the range, which must be aligned to the page size of the
+///     host
+/// - `Pointer len`
+///     The length of the range in bytes, it is rounded up to the page size
+///     of the host
+/// - `u32 prot`
+///     A combination of `MEMORY_PROT_READ` and `MEMORY_PROT_WRITE`, no flags
+///     at all make the range inaccessible
+/// Errors:
+/// - `Errno::Inval`
+///     If the range is not aligned, is not within the memory or the flags
+///     are unknown
+/// - `Errno::Notsup`
+///     If the memory of the guest does not support changing protections
+#[instrument(level = "trace", skip_all, fields(%addr, %len, %prot), ret)]
+pub fn memory_protect<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    addr: M::Offset,
+    len: M::Offset,
+    prot: u32,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let protection = match prot {
+        0 => MemoryProtection::None,
+        MEMORY_PROT_READ => MemoryProtection::ReadOnly,
+        MEMORY_PROT_WRITE => MemoryProtection::ReadWrite,
+        prot if prot == MEMORY_PROT_READ | MEMORY_PROT_WRITE => MemoryProtection::ReadWrite,
+        _ => return Ok(Errno::Inval),
+    };
+    let start: u64 = addr.into();
+    let end = wasi_try_ok!(start.checked_add(len.into()).ok_or(Errno::Inval));
+
+    let (env, mut store) = ctx.data_and_store_mut();
+    let memory = env.memory_clone();
+    wasi_try_ok!(memory
+        .protect(&mut store, start..end, protection)
+        .map_err(|err| match err {
+            MemoryError::InvalidMemory { .. } => Errno::Inval,
+            MemoryError::UnsupportedOperation { .. } => Errno::Notsup,
+            err => {
+                tracing::warn!("failed to protect memory - {}", err);
+                Errno::Nomem
+            }
+        }));
+
+    Ok(Errno::Success)
+}
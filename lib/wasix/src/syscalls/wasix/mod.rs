@@ -1,13 +1,26 @@
 mod callback_signal;
 mod chdir;
+mod clock_nanosleep;
 mod epoll_create;
 mod epoll_ctl;
 mod epoll_wait;
+mod fchdir;
+mod fd_bytes_available;
+mod fd_close_range;
+mod fd_lock_range;
 mod fd_pipe;
+mod fd_readahead;
+mod fd_readdir_raw;
+mod fd_sync_batch;
+mod fd_unlock_range;
+mod fs_statvfs;
 mod futex_wait;
 mod futex_wake;
 mod futex_wake_all;
 mod getcwd;
+mod memory_protect;
+mod path_copy_tree;
+mod path_write_atomic;
 mod port_addr_add;
 mod port_addr_clear;
 mod port_addr_list;
@@ -23,12 +36,20 @@ mod port_route_remove;
 mod port_unbridge;
 mod proc_exec;
 mod proc_exec2;
+mod proc_exec3;
+mod proc_exec_host;
 mod proc_fork;
+mod proc_getdomainname;
+mod proc_gethostname;
+mod proc_getpriority;
 mod proc_id;
 mod proc_join;
 mod proc_parent;
+mod proc_setpriority;
 mod proc_signal;
 mod proc_spawn;
+mod proc_sysctl;
+mod proc_uptime;
 mod resolve;
 mod sched_yield;
 mod sock_accept;
@@ -36,6 +57,7 @@ mod sock_addr_local;
 mod sock_addr_peer;
 mod sock_bind;
 mod sock_connect;
+mod sock_connect_with_data;
 mod sock_get_opt_flag;
 mod sock_get_opt_size;
 mod sock_get_opt_time;
@@ -61,6 +83,7 @@ mod thread_exit;
 mod thread_id;
 mod thread_join;
 mod thread_parallelism;
+mod thread_sigmask;
 mod thread_signal;
 mod thread_sleep;
 mod thread_spawn;
@@ -69,14 +92,27 @@ mod tty_set;
 
 pub use callback_signal::*;
 pub use chdir::*;
+pub use clock_nanosleep::*;
 pub use epoll_create::*;
 pub use epoll_ctl::*;
 pub use epoll_wait::*;
+pub use fchdir::*;
+pub use fd_bytes_available::*;
+pub use fd_close_range::*;
+pub use fd_lock_range::*;
 pub use fd_pipe::*;
+pub use fd_readahead::*;
+pub use fd_readdir_raw::*;
+pub use fd_sync_batch::*;
+pub use fd_unlock_range::*;
+pub use fs_statvfs::*;
 pub use futex_wait::*;
 pub use futex_wake::*;
 pub use futex_wake_all::*;
 pub use getcwd::*;
+pub use memory_protect::*;
+pub use path_copy_tree::*;
+pub use path_write_atomic::*;
 pub use port_addr_add::*;
 pub use port_addr_clear::*;
 pub use port_addr_list::*;
@@ -92,12 +128,20 @@ pub use port_route_remove::*;
 pub use port_unbridge::*;
 pub use proc_exec::*;
 pub use proc_exec2::*;
+pub use proc_exec3::*;
+pub use proc_exec_host::*;
 pub use proc_fork::*;
+pub use proc_getdomainname::*;
+pub use proc_gethostname::*;
+pub use proc_getpriority::*;
 pub use proc_id::*;
 pub use proc_join::*;
 pub use proc_parent::*;
+pub use proc_setpriority::*;
 pub use proc_signal::*;
 pub use proc_spawn::*;
+pub use proc_sysctl::*;
+pub use proc_uptime::*;
 pub use resolve::*;
 pub use sched_yield::*;
 pub use sock_accept::*;
@@ -105,6 +149,7 @@ pub use sock_addr_local::*;
 pub use sock_addr_peer::*;
 pub use sock_bind::*;
 pub use sock_connect::*;
+pub use sock_connect_with_data::*;
 pub use sock_get_opt_flag::*;
 pub use sock_get_opt_size::*;
 pub use sock_get_opt_time::*;
@@ -130,6 +175,7 @@ pub use thread_exit::*;
 pub use thread_id::*;
 pub use thread_join::*;
 pub use thread_parallelism::*;
+pub use thread_sigmask::*;
 pub use thread_signal::*;
 pub use thread_sleep::*;
 pub use thread_spawn::*;
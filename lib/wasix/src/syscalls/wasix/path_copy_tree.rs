@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use virtual_fs::CopyTreeOpts;
+
+use super::*;
+use crate::syscalls::*;
+
+/// ### `path_copy_tree()`
+/// Recursively copies a file or directory within the file system of the
+/// host (like `cp -R`), files are cloned by reference where the file system
+/// supports it
+///
+/// ## Parameters
+///
+/// * `fd` - The base directory for both paths
+/// * `old_flags` - With `SYMLINK_FOLLOW` symbolic links are copied as the
+///   files they point to, otherwise they are copied as links
+/// * `old_path` - Path of the file or directory that is copied
+/// * `new_path` - Path of the copy, it must not exist yet
+/// * `preserve_times` - Whether the copies keep the access and modification
+///   times of the originals
+#[instrument(level = "trace", skip_all, fields(%fd, old_path = field::Empty, new_path = field::Empty), ret)]
+pub fn path_copy_tree<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    old_flags: LookupFlags,
+    old_path: WasmPtr<u8, M>,
+    old_path_len: M::Offset,
+    new_path: WasmPtr<u8, M>,
+    new_path_len: M::Offset,
+    preserve_times: Bool,
+) -> Result<Errno, WasiError> {
+    let preserve_times = match preserve_times {
+        Bool::False => false,
+        Bool::True => true,
+        _ => return Ok(Errno::Inval),
+    };
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let old_path = unsafe { get_input_str_ok!(&memory, old_path, old_path_len) };
+    Span::current().record("old_path", old_path.as_str());
+    let new_path = unsafe { get_input_str_ok!(&memory, new_path, new_path_len) };
+    Span::current().record("new_path", new_path.as_str());
+
+    let opts = CopyTreeOpts {
+        preserve_times,
+        follow_symlinks: old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
+    };
+    let ret = path_copy_tree_internal(&mut ctx, fd, &old_path, &new_path, opts)?;
+    let env = ctx.data();
+
+    if ret == Errno::Success {
+        #[cfg(feature = "journal")]
+        if env.enable_journal {
+            JournalEffector::save_path_copy_tree(&mut ctx, fd, old_path, new_path, opts).map_err(
+                |err| {
+                    tracing::error!("failed to save path copy tree event - {}", err);
+                    WasiError::Exit(ExitCode::from(Errno::Fault))
+                },
+            )?;
+        }
+    }
+    Ok(ret)
+}
+
+pub fn path_copy_tree_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    old_path: &str,
+    new_path: &str,
+    opts: CopyTreeOpts,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
+    let required = Rights::PATH_CREATE_DIRECTORY | Rights::PATH_CREATE_FILE;
+    if !fd_entry.inner.rights.contains(required) {
+        return Ok(Errno::Access);
+    }
+
+    // Both paths are resolved to the paths of the underlying file system
+    let host_path = |path: &str| -> Result<(InodeGuard, String, PathBuf), Errno> {
+        let (parent_inode, entry_name) =
+            state
+                .fs
+                .get_parent_inode_at_path(inodes, fd, Path::new(path), true)?;
+        let host_path = match parent_inode.read().deref() {
+            Kind::Dir { path, .. } => path.join(&entry_name),
+            Kind::Root { .. } => return Err(Errno::Notcapable),
+            _ => return Err(Errno::Notdir),
+        };
+        Ok((parent_inode, entry_name, host_path))
+    };
+    let (_, _, old_host_path) = wasi_try_ok!(host_path(old_path));
+    let (new_parent, new_name, new_host_path) = wasi_try_ok!(host_path(new_path));
+
+    wasi_try_ok!(__asyncify_light(
+        env,
+        None,
+        state.fs_copy_tree(&old_host_path, &new_host_path, opts)
+    )?);
+
+    // Drop any cached entry so the copy is loaded on the next lookup
+    let mut guard = new_parent.write();
+    if let Kind::Dir { entries, .. } = guard.deref_mut() {
+        entries.remove(&new_name);
+    }
+
+    Ok(Errno::Success)
+}
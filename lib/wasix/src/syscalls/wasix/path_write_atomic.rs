@@ -0,0 +1,86 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `path_write_atomic()`
+/// Replaces the whole contents of a file in one step, the file is either
+/// left with its old contents (or does not exist) or holds all the new
+/// contents but it is never partially written
+///
+/// ## Parameters
+///
+/// * `fd` - The base directory for `path`
+/// * `path` - Path of the file that is written, it is created if it does
+///   not exist yet
+/// * `data` - The new contents of the file
+#[instrument(level = "trace", skip_all, fields(%fd, path = field::Empty, data_len = field::Empty), ret)]
+pub fn path_write_atomic<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    data: WasmPtr<u8, M>,
+    data_len: M::Offset,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let path = unsafe { get_input_str_ok!(&memory, path, path_len) };
+    Span::current().record("path", path.as_str());
+    let data = wasi_try_mem_ok!(data.slice(&memory, data_len));
+    let data = wasi_try_mem_ok!(data.read_to_vec());
+    Span::current().record("data_len", data.len());
+
+    path_write_atomic_internal(&mut ctx, fd, &path, data)
+}
+
+pub fn path_write_atomic_internal(
+    ctx: &mut FunctionEnvMut<'_, WasiEnv>,
+    fd: WasiFd,
+    path: &str,
+    data: Vec<u8>,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let (_, state, inodes) = unsafe { env.get_memory_and_wasi_state_and_inodes(&ctx, 0) };
+
+    let fd_entry = wasi_try_ok!(state.fs.get_fd(fd));
+    if !fd_entry.inner.rights.contains(Rights::PATH_CREATE_FILE) {
+        return Ok(Errno::Access);
+    }
+
+    // Make sure an existing entry is loaded so that it can be checked
+    let _ = state.fs.get_inode_at_path(inodes, fd, path, true);
+    let (parent_inode, entry_name) =
+        wasi_try_ok!(state
+            .fs
+            .get_parent_inode_at_path(inodes, fd, Path::new(path), true));
+
+    let host_path = {
+        let guard = parent_inode.read();
+        match guard.deref() {
+            Kind::Dir { entries, path, .. } => {
+                if let Some(entry) = entries.get(&entry_name) {
+                    if let Kind::Dir { .. } = entry.read().deref() {
+                        return Ok(Errno::Isdir);
+                    }
+                }
+                path.join(&entry_name)
+            }
+            Kind::Root { .. } => return Ok(Errno::Notcapable),
+            _ => return Ok(Errno::Notdir),
+        }
+    };
+
+    wasi_try_ok!(__asyncify_light(
+        env,
+        None,
+        state.fs_write_atomic(&host_path, &data)
+    )?);
+
+    // The file was replaced underneath any cached entry so it is dropped
+    // and will be loaded again on the next lookup
+    let mut guard = parent_inode.write();
+    if let Kind::Dir { entries, .. } = guard.deref_mut() {
+        entries.remove(&entry_name);
+    }
+
+    Ok(Errno::Success)
+}
@@ -0,0 +1,253 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+use virtual_fs::Pipe;
+use wasmer_wasix_types::wasi::ProcessHandles;
+
+use super::*;
+use crate::{syscalls::*, WasiProcess};
+
+/// ### `proc_exec_host()`
+/// Spawns a real process on the host that is running this runtime, the
+/// stdio of the host process is connected to pipes that are returned to
+/// the caller and its exit code can be retrieved with `proc_join`
+///
+/// This is only permitted when the `allow_host_exec` capability is enabled
+/// and the binary is on the `host_exec_allowlist`, otherwise `Errno::Perm`
+/// is returned.
+///
+/// ## Parameters
+///
+/// * `path` - Absolute path of the host binary to run
+/// * `args` - List of the arguments that follow the program name
+///   (entries are separated by line feeds)
+/// * `envs` - List of `KEY=VALUE` environment variables, the host process
+///   does not inherit any other variables
+///   (entries are separated by line feeds)
+///
+/// ## Return
+///
+/// Returns the process id and the guest ends of the stdio pipes
+#[instrument(level = "trace", skip_all, fields(path = field::Empty, pid = field::Empty), ret)]
+pub fn proc_exec_host<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    path: WasmPtr<u8, M>,
+    path_len: M::Offset,
+    args: WasmPtr<u8, M>,
+    args_len: M::Offset,
+    envs: WasmPtr<u8, M>,
+    envs_len: M::Offset,
+    ret_handles: WasmPtr<ProcessHandles, M>,
+) -> Result<Errno, WasiError> {
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    let env = ctx.data();
+    if !env.capabilities.allow_host_exec {
+        return Ok(Errno::Perm);
+    }
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    let path = unsafe { get_input_str_ok!(&memory, path, path_len) };
+    let args = unsafe { get_input_str_ok!(&memory, args, args_len) };
+    let envs = unsafe { get_input_str_ok!(&memory, envs, envs_len) };
+    Span::current().record("path", path.as_str());
+
+    let binary = wasi_try_ok!(host_exec_binary(
+        Path::new(&path),
+        &env.capabilities.host_exec_allowlist
+    ));
+
+    let mut command = Command::new(binary);
+    command
+        .args(args.split(&['\n', '\r']).filter(|a| !a.is_empty()))
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for var in envs.split(&['\n', '\r']).filter(|a| !a.is_empty()) {
+        let (key, value) = wasi_try_ok!(var.split_once('=').ok_or(Errno::Inval));
+        command.env(key, value);
+    }
+
+    let process = match env.control_plane.new_process(env.process.module_hash) {
+        Ok(p) => p,
+        Err(err) => {
+            debug!("could not create a process for the host command: {err}");
+            return Ok(Errno::Again);
+        }
+    };
+    Span::current().record("pid", process.pid().raw());
+
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            let err = map_io_err(err);
+            process.finished.set_finished(Ok(err.into()));
+            return Ok(err);
+        }
+    };
+    let handles = wasi_try_ok!(host_exec_bridge(env, &process, child));
+
+    // The host process is joined like any other child
+    ctx.data().process.lock().children.push(process);
+
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem_ok!(ret_handles.write(&memory, handles));
+    Ok(Errno::Success)
+}
+
+/// Resolves the binary that will be executed, the canonical path must be
+/// an entry of the allowlist so that symlinks and relative components can
+/// not be used to escape it
+fn host_exec_binary(path: &Path, allowlist: &[PathBuf]) -> Result<PathBuf, Errno> {
+    if !path.is_absolute() {
+        return Err(Errno::Inval);
+    }
+    let binary = path.canonicalize().map_err(map_io_err)?;
+
+    let allowed = allowlist
+        .iter()
+        .filter_map(|allowed| allowed.canonicalize().ok())
+        .any(|allowed| allowed == binary);
+    if !allowed {
+        warn!("host binary is not on the allowlist - {}", binary.display());
+        return Err(Errno::Perm);
+    }
+    Ok(binary)
+}
+
+/// How often the host process is checked for having exited, or for the
+/// guest having gone away in which case the host process is killed
+const HOST_EXEC_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Owns the host process, if it is still running when this is dropped (for
+/// instance because the guest went away or it could not be wired up) it is
+/// killed and reaped so that it is neither orphaned nor left as a zombie
+struct HostChild(Child);
+
+impl Drop for HostChild {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            self.0.kill().ok();
+            self.0.wait().ok();
+        }
+    }
+}
+
+/// Connects the stdio of the host process to pipes owned by the guest and
+/// marks the process as finished once the host process exits, if anything
+/// fails the host process is killed and the guest ends of the pipes that
+/// were already created are closed again
+fn host_exec_bridge(
+    env: &WasiEnv,
+    process: &WasiProcess,
+    child: Child,
+) -> Result<ProcessHandles, Errno> {
+    let mut fds = Vec::new();
+    let ret = host_exec_bridge_internal(env, process, HostChild(child), &mut fds);
+    if let Err(err) = ret {
+        let (state, _) = env.get_wasi_state_and_inodes();
+        for fd in fds {
+            state.fs.close_fd(fd).ok();
+        }
+        process.finished.set_finished(Ok(err.into()));
+    }
+    ret
+}
+
+fn host_exec_bridge_internal(
+    env: &WasiEnv,
+    process: &WasiProcess,
+    mut child: HostChild,
+    fds: &mut Vec<WasiFd>,
+) -> Result<ProcessHandles, Errno> {
+    let tasks = env.tasks().clone();
+    let mut child_stdin = child.0.stdin.take().ok_or(Errno::Io)?;
+    let mut child_stdout = child.0.stdout.take().ok_or(Errno::Io)?;
+    let mut child_stderr = child.0.stderr.take().ok_or(Errno::Io)?;
+
+    let (stdin, mut stdin_pipe) = host_exec_pipe(env, fds)?;
+    let (stdout, mut stdout_pipe) = host_exec_pipe(env, fds)?;
+    let (stderr, mut stderr_pipe) = host_exec_pipe(env, fds)?;
+
+    tasks
+        .task_dedicated(Box::new(move || {
+            std::io::copy(&mut stdin_pipe, &mut child_stdin).ok();
+        }))
+        .and_then(|_| {
+            tasks.task_dedicated(Box::new(move || {
+                std::io::copy(&mut child_stdout, &mut stdout_pipe).ok();
+            }))
+        })
+        .and_then(|_| {
+            tasks.task_dedicated(Box::new(move || {
+                std::io::copy(&mut child_stderr, &mut stderr_pipe).ok();
+            }))
+        })
+        .map_err(Errno::from)?;
+
+    // The host process is killed when the guest that started it exits, or
+    // when its own process is terminated by the guest
+    let parent = env.process.finished.clone();
+    let finished = process.finished.clone();
+    tasks
+        .task_dedicated(Box::new(move || {
+            let exit_code = loop {
+                match child.0.try_wait() {
+                    Ok(Some(status)) => {
+                        break status
+                            .code()
+                            .map(ExitCode::from)
+                            .unwrap_or_else(|| Errno::Canceled.into())
+                    }
+                    Ok(None)
+                        if parent.status().is_finished() || finished.status().is_finished() =>
+                    {
+                        break Errno::Canceled.into()
+                    }
+                    Ok(None) => std::thread::sleep(HOST_EXEC_POLL_INTERVAL),
+                    Err(err) => break map_io_err(err).into(),
+                }
+            };
+            drop(child);
+            finished.set_finished(Ok(exit_code));
+        }))
+        .map_err(Errno::from)?;
+
+    Ok(ProcessHandles {
+        pid: process.pid().raw(),
+        stdin,
+        stdout,
+        stderr,
+    })
+}
+
+/// Creates a pipe where one end is given to the guest as a new file
+/// descriptor and the other end is returned
+fn host_exec_pipe(env: &WasiEnv, fds: &mut Vec<WasiFd>) -> Result<(OptionFd, Pipe), Errno> {
+    let (guest, host) = Pipe::channel();
+    let (state, inodes) = env.get_wasi_state_and_inodes();
+    let inode = state.fs.create_inode_with_default_stat(
+        inodes,
+        Kind::Pipe { pipe: guest },
+        false,
+        "pipe".into(),
+    );
+
+    let rights = crate::net::socket::all_socket_rights();
+    let fd = state
+        .fs
+        .create_fd(rights, rights, Fdflags::empty(), 0, inode)?;
+    fds.push(fd);
+    Ok((
+        OptionFd {
+            tag: OptionTag::Some,
+            fd,
+        },
+        host,
+    ))
+}
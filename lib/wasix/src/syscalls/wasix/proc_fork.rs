@@ -260,6 +260,7 @@ fn run<M: MemorySize>(
     }
 
     let mut ret: ExitCode = Errno::Success.into();
+    ctx.data(&store).start_cpu_time();
     let err = if ctx.data(&store).thread.is_main() {
         trace!(%pid, %tid, "re-invoking main");
         let start = unsafe { ctx.data(&store).inner() }.start.clone().unwrap();
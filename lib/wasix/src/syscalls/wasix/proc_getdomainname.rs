@@ -0,0 +1,28 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_getdomainname()`
+/// Returns the domain name that is visible to this process as a null
+/// terminated string (see [`WasiEnvBuilder::domainname`](crate::WasiEnvBuilder::domainname))
+///
+/// If the domain name (including its null terminator) does not fit into
+/// the buffer then it is truncated to the size of the buffer and this
+/// function will return ENAMETOOLONG
+///
+/// Inputs:
+/// - `char *name`
+///     Buffer that the domain name is written to
+/// - `size_t name_len`
+///     Length of the buffer
+#[instrument(level = "trace", skip_all, fields(domainname = field::Empty, %name_len), ret)]
+pub fn proc_getdomainname<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+) -> Errno {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    Span::current().record("domainname", state.domainname.as_str());
+
+    write_name_internal(&memory, &state.domainname, name, name_len)
+}
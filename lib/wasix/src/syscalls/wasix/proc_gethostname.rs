@@ -0,0 +1,58 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_gethostname()`
+/// Returns the host name that is visible to this process as a null
+/// terminated string (see [`WasiEnvBuilder::hostname`](crate::WasiEnvBuilder::hostname))
+///
+/// If the host name (including its null terminator) does not fit into
+/// the buffer then it is truncated to the size of the buffer and this
+/// function will return ENAMETOOLONG
+///
+/// Inputs:
+/// - `char *name`
+///     Buffer that the host name is written to
+/// - `size_t name_len`
+///     Length of the buffer
+#[instrument(level = "trace", skip_all, fields(hostname = field::Empty, %name_len), ret)]
+pub fn proc_gethostname<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+) -> Errno {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+    Span::current().record("hostname", state.hostname.as_str());
+
+    write_name_internal(&memory, &state.hostname, name, name_len)
+}
+
+/// Writes `value` as a null terminated string into the guest buffer,
+/// truncating it when the buffer is too small
+pub(crate) fn write_name_internal<M: MemorySize>(
+    memory: &MemoryView,
+    value: &str,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+) -> Errno {
+    let name_len64: u64 = name_len.into();
+    if name.is_null() && name_len64 > 0 {
+        return Errno::Fault;
+    }
+
+    let mut buf = value.as_bytes().to_vec();
+    buf.push(0);
+    let fits = buf.len() as u64 <= name_len64;
+    buf.truncate(name_len64.min(buf.len() as u64) as usize);
+
+    if !buf.is_empty() {
+        let slice = wasi_try_mem!(name.slice(memory, wasi_try!(to_offset::<M>(buf.len()))));
+        wasi_try_mem!(slice.write_slice(&buf));
+    }
+
+    if fits {
+        Errno::Success
+    } else {
+        Errno::Nametoolong
+    }
+}
@@ -0,0 +1,40 @@
+use super::*;
+use crate::{syscalls::*, WasiProcess};
+
+/// ### `proc_getpriority()`
+/// Returns the advisory scheduling priority (nice value) of a process,
+/// a `pid` of zero refers to the calling process
+///
+/// ## Parameters
+///
+/// * `pid` - Process to query
+///
+/// ## Return
+///
+/// The nice value of the process which ranges from -20 (highest priority)
+/// to 19 (lowest priority)
+#[instrument(level = "trace", skip_all, fields(%pid, priority = field::Empty), ret)]
+pub fn proc_getpriority<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    pid: Pid,
+    ret_priority: WasmPtr<i32, M>,
+) -> Errno {
+    let env = ctx.data();
+    let process = wasi_try!(priority_process(env, pid));
+    let priority = process.nice();
+    Span::current().record("priority", priority);
+
+    let memory = unsafe { env.memory_view(&ctx) };
+    wasi_try_mem!(ret_priority.write(&memory, priority));
+    Errno::Success
+}
+
+/// Finds the process that the priority syscalls act on
+pub(crate) fn priority_process(env: &WasiEnv, pid: Pid) -> Result<WasiProcess, Errno> {
+    let pid: WasiProcessId = pid.into();
+    if pid.raw() == 0 || pid == env.process.pid() {
+        Ok(env.process.clone())
+    } else {
+        env.control_plane.get_process(pid).ok_or(Errno::Srch)
+    }
+}
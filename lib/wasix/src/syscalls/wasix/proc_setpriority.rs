@@ -0,0 +1,33 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_setpriority()`
+/// Sets the advisory scheduling priority (nice value) of a process,
+/// a `pid` of zero refers to the calling process
+///
+/// Only the calling process and its descendants can be changed. Lowering
+/// the priority is always allowed while raising it above the initial value
+/// of zero requires the `enable_raise_priority` capability.
+///
+/// ## Parameters
+///
+/// * `pid` - Process to change
+/// * `priority` - New nice value, values outside of -20 to 19 are clamped
+#[instrument(level = "trace", skip_all, fields(%pid, %priority), ret)]
+pub fn proc_setpriority(ctx: FunctionEnvMut<'_, WasiEnv>, pid: Pid, priority: i32) -> Errno {
+    let env = ctx.data();
+    let process = wasi_try!(priority_process(env, pid));
+    if process.pid() != env.process.pid() && !env.process.has_descendant(process.pid()) {
+        return Errno::Perm;
+    }
+
+    if priority < 0
+        && !env.capabilities.threading.enable_raise_priority
+        && !env.capabilities.insecure_allow_all
+    {
+        return Errno::Access;
+    }
+
+    process.set_nice(priority);
+    Errno::Success
+}
@@ -0,0 +1,54 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_sysctl()`
+/// Reads a sysctl value (see [`WasiEnvBuilder::sysctl`](crate::WasiEnvBuilder::sysctl)),
+/// these are the same values that are served under `/proc/sys`
+///
+/// Inputs:
+/// - `const char *name`
+///     Name of the value, either dotted (`kernel.ostype`) or as a path
+///     relative to `/proc/sys` (`kernel/ostype`)
+/// - `u32 name_len`
+///     The number of bytes to read from `name`
+/// - `u32 buf_len`
+///     Space available pointed to by `buf`
+/// Outputs:
+/// - `char *buf`
+///     The value, truncated to `buf_len` bytes and not NUL terminated
+/// - `u32 buf_used`
+///     The full length of the value, when this is larger than `buf_len`
+///     the value was truncated
+///
+/// Returns `Errno::Noent` when there is no value with this name
+#[instrument(level = "trace", skip_all, fields(name = field::Empty), ret)]
+pub fn proc_sysctl<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    name: WasmPtr<u8, M>,
+    name_len: M::Offset,
+    buf: WasmPtr<u8, M>,
+    buf_len: M::Offset,
+    buf_used: WasmPtr<M::Offset, M>,
+) -> Errno {
+    let env = ctx.data();
+    let (memory, state) = unsafe { env.get_memory_and_wasi_state(&ctx, 0) };
+
+    let name = unsafe { get_input_str!(&memory, name, name_len) };
+    Span::current().record("name", name.as_str());
+
+    let key = name.trim_start_matches('/').replace('/', ".");
+    let value = wasi_try!(state.sysctls.get(&key).ok_or(Errno::Noent));
+    let bytes = value.as_bytes();
+    let buf_len: u64 = buf_len.into();
+    let copy_len = bytes.len().min(buf_len as usize);
+
+    if copy_len > 0 {
+        let out = wasi_try_mem!(buf.slice(&memory, wasi_try!(to_offset::<M>(copy_len))));
+        wasi_try_mem!(out.write_slice(&bytes[..copy_len]));
+    }
+
+    let bytes_len: M::Offset = wasi_try!(bytes.len().try_into().map_err(|_| Errno::Overflow));
+    wasi_try_mem!(buf_used.deref(&memory).write(bytes_len));
+
+    Errno::Success
+}
@@ -0,0 +1,26 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `proc_uptime()`
+/// Returns how long the runtime has been up, this is measured by a monotonic
+/// clock that is not affected by `clock_time_set` (the same value is served
+/// in seconds by `/proc/uptime`)
+///
+/// Output:
+/// - `Timestamp *uptime`
+///     The uptime in nanoseconds
+#[instrument(level = "trace", skip_all, fields(uptime = field::Empty), ret)]
+pub fn proc_uptime<M: MemorySize>(
+    ctx: FunctionEnvMut<'_, WasiEnv>,
+    uptime: WasmPtr<Timestamp, M>,
+) -> Errno {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    let elapsed = env.runtime.clock().elapsed();
+    let nanos: Timestamp = wasi_try!(elapsed.as_nanos().try_into().map_err(|_| Errno::Overflow));
+    Span::current().record("uptime", nanos);
+
+    wasi_try_mem!(uptime.write(&memory, nanos));
+    Errno::Success
+}
@@ -42,12 +42,14 @@ pub(crate) fn sock_bind_internal(
 ) -> Result<Result<(), Errno>, WasiError> {
     let env = ctx.data();
     let net = env.net().clone();
+    let host_op_timeout = env.state.host_op_timeout;
 
     let tasks = ctx.data().tasks().clone();
     wasi_try_ok_ok!(__sock_upgrade(
         ctx,
         sock,
         Rights::SOCK_BIND,
+        host_op_timeout,
         move |socket, _| async move { socket.bind(tasks.deref(), net.deref(), addr).await }
     ));
 
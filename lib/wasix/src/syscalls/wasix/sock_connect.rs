@@ -9,6 +9,11 @@ use crate::syscalls::*;
 ///
 /// Note: This is similar to `connect` in POSIX
 ///
+/// When the socket is nonblocking the connection is only initiated and
+/// `Errno::Inprogress` is returned, the socket becomes writable once the
+/// connection is established (or failed) and its outcome can then be
+/// queried with `Sockoption::LastError`
+///
 /// ## Parameters
 ///
 /// * `fd` - Socket descriptor
@@ -25,7 +30,12 @@ pub fn sock_connect<M: MemorySize>(
     let peer_addr = SocketAddr::new(addr.0, addr.1);
     Span::current().record("addr", format!("{:?}", peer_addr));
 
-    wasi_try_ok!(sock_connect_internal(&mut ctx, sock, peer_addr)?);
+    let res = sock_connect_internal(&mut ctx, sock, peer_addr, None)?;
+    if let Err(err) = res {
+        if err != Errno::Inprogress {
+            return Ok(err);
+        }
+    }
 
     #[cfg(feature = "journal")]
     if ctx.data().enable_journal {
@@ -43,13 +53,17 @@ pub fn sock_connect<M: MemorySize>(
         )?;
     }
 
-    Ok(Errno::Success)
+    Ok(match res {
+        Ok(()) => Errno::Success,
+        Err(err) => err,
+    })
 }
 
 pub(crate) fn sock_connect_internal(
     ctx: &mut FunctionEnvMut<'_, WasiEnv>,
     sock: WasiFd,
     addr: SocketAddr,
+    data: Option<&[u8]>,
 ) -> Result<Result<(), Errno>, WasiError> {
     let env = ctx.data();
     let net = env.net().clone();
@@ -58,6 +72,8 @@ pub(crate) fn sock_connect_internal(
         ctx,
         sock,
         Rights::SOCK_CONNECT,
+        // Waiting for the peer is up to the guest
+        None,
         move |mut socket, flags| async move {
             socket
                 .connect(
@@ -66,10 +82,20 @@ pub(crate) fn sock_connect_internal(
                     addr,
                     None,
                     flags.contains(Fdflags::NONBLOCK),
+                    data,
                 )
                 .await
         }
     ));
 
+    // A nonblocking connect only initiates the connection (unless it also
+    // had to send data, which means the connection is established)
+    let in_progress = wasi_try_ok_ok!(__sock_actor(ctx, sock, Rights::empty(), |socket, fd| {
+        Ok(data.is_none() && fd.inner.flags.contains(Fdflags::NONBLOCK) && socket.is_tcp_stream())
+    }));
+    if in_progress {
+        return Ok(Err(Errno::Inprogress));
+    }
+
     Ok(Ok(()))
 }
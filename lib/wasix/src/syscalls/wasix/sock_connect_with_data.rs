@@ -0,0 +1,60 @@
+use super::*;
+use crate::syscalls::*;
+
+/// ### `sock_connect_with_data()`
+/// Initiate a connection on a socket to the specified address and send
+/// some data over it, where the host supports TCP Fast Open the data is
+/// carried by the connection request which saves a round trip
+///
+/// Note: This is similar to `sendto` with `MSG_FASTOPEN` in Linux
+///
+/// Unlike `sock_connect` this also waits for the connection on nonblocking
+/// sockets as all of the data has been sent when it returns
+///
+/// ## Parameters
+///
+/// * `fd` - Socket descriptor
+/// * `addr` - Address of the socket to connect to
+/// * `data` - The data that is sent once connected
+#[instrument(level = "trace", skip_all, fields(%sock, addr = field::Empty, data_len = field::Empty), ret)]
+pub fn sock_connect_with_data<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    sock: WasiFd,
+    addr: WasmPtr<__wasi_addr_port_t, M>,
+    data: WasmPtr<u8, M>,
+    data_len: M::Offset,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+    let addr = wasi_try_ok!(crate::net::read_ip_port(&memory, addr));
+    let peer_addr = SocketAddr::new(addr.0, addr.1);
+    Span::current().record("addr", format!("{:?}", peer_addr));
+    let data = wasi_try_mem_ok!(data.slice(&memory, data_len));
+    let data = wasi_try_mem_ok!(data.read_to_vec());
+    Span::current().record("data_len", data.len());
+
+    wasi_try_ok!(sock_connect_internal(
+        &mut ctx,
+        sock,
+        peer_addr,
+        Some(&data)
+    )?);
+
+    #[cfg(feature = "journal")]
+    if ctx.data().enable_journal {
+        let local_addr = wasi_try_ok!(__sock_actor(
+            &mut ctx,
+            sock,
+            Rights::empty(),
+            |socket, _| socket.addr_local()
+        ));
+        JournalEffector::save_sock_connect(&mut ctx, sock, local_addr, peer_addr).map_err(
+            |err| {
+                tracing::error!("failed to save sock_connected event - {}", err);
+                WasiError::Exit(ExitCode::from(Errno::Fault))
+            },
+        )?;
+    }
+
+    Ok(Errno::Success)
+}
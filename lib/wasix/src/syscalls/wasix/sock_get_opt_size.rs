@@ -5,6 +5,9 @@ use crate::syscalls::*;
 /// Retrieve the size of particular option for this socket
 /// Note: This is similar to `getsockopt` in POSIX for SO_RCVBUF
 ///
+/// `Sockoption::LastError` returns (and clears) the pending error of the
+/// socket as an `Errno`, similar to SO_ERROR in POSIX
+///
 /// ## Parameters
 ///
 /// * `fd` - Socket descriptor
@@ -27,6 +30,7 @@ pub fn sock_get_opt_size<M: MemorySize>(
             Sockoption::MulticastTtlV4 => {
                 socket.multicast_ttl_v4().map(|a| a as Filesize)
             }
+            Sockoption::LastError => socket.take_error().map(|a| a as Filesize),
             _ => Err(Errno::Inval),
         }
     ));
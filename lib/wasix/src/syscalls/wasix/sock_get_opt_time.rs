@@ -21,6 +21,7 @@ pub fn sock_get_opt_time<M: MemorySize>(
         Sockoption::ConnectTimeout => TimeType::ConnectTimeout,
         Sockoption::AcceptTimeout => TimeType::AcceptTimeout,
         Sockoption::Linger => TimeType::Linger,
+        Sockoption::UserTimeout => TimeType::UserTimeout,
         _ => return Errno::Inval,
     };
 
@@ -44,11 +44,13 @@ pub(crate) fn sock_listen_internal(
 ) -> Result<Result<(), Errno>, WasiError> {
     let env = ctx.data();
     let net = env.net().clone();
+    let host_op_timeout = env.state.host_op_timeout;
     let tasks = ctx.data().tasks().clone();
     wasi_try_ok_ok!(__sock_upgrade(
         ctx,
         sock,
         Rights::SOCK_LISTEN,
+        host_op_timeout,
         |socket, _| async move { socket.listen(tasks.deref(), net.deref(), backlog).await }
     ));
 
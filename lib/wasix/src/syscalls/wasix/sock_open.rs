@@ -89,6 +89,7 @@ pub(crate) fn sock_open_internal(
                     read_timeout: None,
                     accept_timeout: None,
                     connect_timeout: None,
+                    user_timeout: None,
                     handler: None,
                 },
                 addr: None,
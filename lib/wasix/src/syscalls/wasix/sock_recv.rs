@@ -53,17 +53,18 @@ pub fn sock_recv<M: MemorySize>(
 
 pub(super) fn sock_recv_internal_handler<M: MemorySize>(
     mut ctx: FunctionEnvMut<'_, WasiEnv>,
-    res: Result<usize, Errno>,
+    res: Result<(usize, bool), Errno>,
     ro_data_len: WasmPtr<M::Offset, M>,
     ro_flags: WasmPtr<RoFlags, M>,
 ) -> Result<Errno, WasiError> {
     let mut ret = Errno::Success;
-    let bytes_read = match res {
-        Ok(bytes_read) => {
+    let (bytes_read, truncated) = match res {
+        Ok((bytes_read, truncated)) => {
             trace!(
                 %bytes_read,
+                %truncated,
             );
-            bytes_read
+            (bytes_read, truncated)
         }
         Err(err) => {
             let socket_err = err.name();
@@ -71,7 +72,7 @@ pub(super) fn sock_recv_internal_handler<M: MemorySize>(
                 %socket_err,
             );
             ret = err;
-            0
+            (0, false)
         }
     };
     Span::current().record("nread", bytes_read);
@@ -80,7 +81,12 @@ pub(super) fn sock_recv_internal_handler<M: MemorySize>(
     let memory = unsafe { env.memory_view(&ctx) };
 
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| Errno::Overflow));
-    wasi_try_mem_ok!(ro_flags.write(&memory, 0));
+    let flags = if truncated {
+        __WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED
+    } else {
+        0
+    };
+    wasi_try_mem_ok!(ro_flags.write(&memory, flags));
     wasi_try_mem_ok!(ro_data_len.write(&memory, bytes_read));
 
     Ok(ret)
@@ -98,7 +104,7 @@ pub(super) fn sock_recv_internal_handler<M: MemorySize>(
 ///
 /// ## Return
 ///
-/// Number of bytes stored in ri_data and message flags.
+/// Number of bytes stored in ri_data and whether a datagram was truncated.
 pub(super) fn sock_recv_internal<M: MemorySize>(
     ctx: &mut FunctionEnvMut<'_, WasiEnv>,
     sock: WasiFd,
@@ -107,7 +113,7 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
     ri_flags: RiFlags,
     ro_data_len: WasmPtr<M::Offset, M>,
     ro_flags: WasmPtr<RoFlags, M>,
-) -> WasiResult<usize> {
+) -> WasiResult<(usize, bool)> {
     wasi_try_ok_ok!(WasiEnv::process_signals_and_exit(ctx)?);
 
     let mut env = ctx.data();
@@ -124,41 +130,64 @@ pub(super) fn sock_recv_internal<M: MemorySize>(
                 .map_err(mem_error_to_wasi)?;
             let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
 
-            let mut total_read = 0;
+            // All the buffers are validated before anything is received
+            let mut slices = Vec::with_capacity(iovs_arr.len());
             for iovs in iovs_arr.iter() {
-                let mut buf = WasmPtr::<u8, M>::new(iovs.buf)
+                let slice = WasmPtr::<u8, M>::new(iovs.buf)
                     .slice(&memory, iovs.buf_len)
-                    .map_err(mem_error_to_wasi)?
-                    .access()
                     .map_err(mem_error_to_wasi)?;
+                slices.push(slice);
+            }
+
+            let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
+            let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
 
-                let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
-                let timeout = socket
-                    .opt_time(TimeType::ReadTimeout)
-                    .ok()
-                    .flatten()
-                    .unwrap_or(Duration::from_secs(30));
-
-                let local_read = match socket
-                    .recv(
-                        env.tasks().deref(),
-                        buf.as_mut_uninit(),
-                        Some(timeout),
-                        nonblocking,
-                    )
+            // The buffers can only be borrowed all at once when they do not
+            // overlap, otherwise the data is received into a buffer of the
+            // host and copied into them one after the other like `readv` does
+            if slices_are_disjoint(&slices) {
+                let mut bufs = slices
+                    .into_iter()
+                    .map(|slice| slice.access_mut())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(mem_error_to_wasi)?;
+                let mut bufs: Vec<_> = bufs.iter_mut().map(|buf| buf.as_mut_uninit()).collect();
+                socket
+                    .recv_vectored(env.tasks().deref(), &mut bufs, timeout, nonblocking)
                     .await
-                {
-                    Ok(s) => s,
-                    Err(_) if total_read > 0 => break,
-                    Err(err) => return Err(err),
-                };
-                total_read += local_read;
-                if local_read != buf.len() {
-                    break;
+            } else {
+                let len = slices
+                    .iter()
+                    .map(|slice| slice.len())
+                    .sum::<u64>()
+                    .min(memory.data_size());
+                let mut data = vec![MaybeUninit::uninit(); len as usize];
+                let (amt, truncated) = socket
+                    .recv_vectored(env.tasks().deref(), &mut [&mut data], timeout, nonblocking)
+                    .await?;
+
+                let mut data = &data[..amt];
+                for slice in slices {
+                    let mut buf = slice.access_mut().map_err(mem_error_to_wasi)?;
+                    let buf = buf.as_mut_uninit();
+                    let n = buf.len().min(data.len());
+                    buf[..n].copy_from_slice(&data[..n]);
+                    data = &data[n..];
                 }
+                Ok((amt, truncated))
             }
-            Ok(total_read)
         }
     ));
     Ok(Ok(data))
 }
+
+/// Checks that none of the buffers of the guest overlap with another one
+fn slices_are_disjoint(slices: &[WasmSlice<'_, u8>]) -> bool {
+    let mut ranges: Vec<_> = slices
+        .iter()
+        .filter(|slice| !slice.is_empty())
+        .map(|slice| (slice.offset(), slice.offset() + slice.len()))
+        .collect();
+    ranges.sort_unstable();
+    ranges.windows(2).all(|pair| pair[0].1 <= pair[1].0)
+}
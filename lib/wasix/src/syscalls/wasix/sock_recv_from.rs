@@ -65,23 +65,19 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
         max_size
     };
 
-    let (bytes_read, peer) = {
+    let (bytes_read, peer, truncated) = {
         if max_size <= 10240 {
             let mut buf: [MaybeUninit<u8>; 10240] = unsafe { MaybeUninit::uninit().assume_init() };
             let writer = &mut buf[..max_size];
-            let (amt, peer) = wasi_try_ok!(__sock_asyncify(
+            let (amt, peer, truncated) = wasi_try_ok!(__sock_asyncify(
                 env,
                 sock,
                 Rights::SOCK_RECV,
                 |socket, fd| async move {
                     let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::ReadTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
                     socket
-                        .recv_from(env.tasks().deref(), writer, Some(timeout), nonblocking)
+                        .recv_from(env.tasks().deref(), writer, timeout, nonblocking)
                         .await
                 },
             ));
@@ -89,36 +85,32 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
             if amt > 0 {
                 let buf: &[MaybeUninit<u8>] = &buf[..amt];
                 let buf: &[u8] = unsafe { std::mem::transmute(buf) };
-                wasi_try_ok!(copy_from_slice(buf, &memory, iovs_arr).map(|_| (amt, peer)))
+                wasi_try_ok!(copy_from_slice(buf, &memory, iovs_arr).map(|_| (amt, peer, truncated)))
             } else {
-                (amt, peer)
+                (amt, peer, truncated)
             }
         } else {
-            let (data, peer) = wasi_try_ok!(__sock_asyncify(
+            let (data, peer, truncated) = wasi_try_ok!(__sock_asyncify(
                 env,
                 sock,
                 Rights::SOCK_RECV_FROM,
                 |socket, fd| async move {
                     let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
-                    let timeout = socket
-                        .opt_time(TimeType::ReadTimeout)
-                        .ok()
-                        .flatten()
-                        .unwrap_or(Duration::from_secs(30));
+                    let timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
 
                     let mut buf = Vec::with_capacity(max_size);
                     unsafe {
                         buf.set_len(max_size);
                     }
                     socket
-                        .recv_from(env.tasks().deref(), &mut buf, Some(timeout), nonblocking)
+                        .recv_from(env.tasks().deref(), &mut buf, timeout, nonblocking)
                         .await
-                        .map(|(amt, addr)| {
+                        .map(|(amt, addr, truncated)| {
                             unsafe {
                                 buf.set_len(amt);
                             }
                             let buf: Vec<u8> = unsafe { std::mem::transmute(buf) };
-                            (buf, addr)
+                            (buf, addr, truncated)
                         })
                 }
             ));
@@ -126,9 +118,11 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
             let data_len = data.len();
             if data_len > 0 {
                 let mut reader = &data[..];
-                wasi_try_ok!(read_bytes(reader, &memory, iovs_arr).map(|_| (data_len, peer)))
+                wasi_try_ok!(
+                    read_bytes(reader, &memory, iovs_arr).map(|_| (data_len, peer, truncated))
+                )
             } else {
-                (0, peer)
+                (0, peer, truncated)
             }
         }
     };
@@ -139,7 +133,12 @@ pub(super) fn sock_recv_from_internal<M: MemorySize>(
     wasi_try_ok!(write_ip_port(&memory, ro_addr, peer.ip(), peer.port()));
 
     let bytes_read: M::Offset = wasi_try_ok!(bytes_read.try_into().map_err(|_| Errno::Overflow));
-    wasi_try_mem_ok!(ro_flags.write(&memory, 0));
+    let flags = if truncated {
+        __WASI_SOCK_RECV_OUTPUT_DATA_TRUNCATED
+    } else {
+        0
+    };
+    wasi_try_mem_ok!(ro_flags.write(&memory, flags));
     wasi_try_mem_ok!(ro_data_len.write(&memory, bytes_read));
 
     Ok(Errno::Success)
@@ -1,4 +1,4 @@
-use std::{mem::MaybeUninit, task::Waker};
+use std::{io::IoSlice, mem::MaybeUninit, task::Waker};
 
 use super::*;
 use crate::{net::socket::TimeType, syscalls::*};
@@ -101,52 +101,33 @@ pub(crate) fn sock_send_internal<M: MemorySize>(
         Rights::SOCK_SEND,
         |socket, fd| async move {
             let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
-            let timeout = socket
-                .opt_time(TimeType::WriteTimeout)
-                .ok()
-                .flatten()
-                .unwrap_or(Duration::from_secs(30));
+            let timeout = socket.opt_time(TimeType::WriteTimeout).ok().flatten();
 
             match si_data {
                 FdWriteSource::Iovs { iovs, iovs_len } => {
                     let iovs_arr = iovs.slice(&memory, iovs_len).map_err(mem_error_to_wasi)?;
                     let iovs_arr = iovs_arr.access().map_err(mem_error_to_wasi)?;
 
-                    let mut sent = 0usize;
+                    // All the buffers are validated before anything is sent
+                    let mut bufs = Vec::with_capacity(iovs_arr.len());
                     for iovs in iovs_arr.iter() {
                         let buf = WasmPtr::<u8, M>::new(iovs.buf)
                             .slice(&memory, iovs.buf_len)
                             .map_err(mem_error_to_wasi)?
                             .access()
                             .map_err(mem_error_to_wasi)?;
-                        let local_sent = match socket
-                            .send(
-                                env.tasks().deref(),
-                                buf.as_ref(),
-                                Some(timeout),
-                                nonblocking,
-                            )
-                            .await
-                        {
-                            Ok(s) => s,
-                            Err(_) if sent > 0 => break,
-                            Err(err) => return Err(err),
-                        };
-                        sent += local_sent;
-                        if local_sent != buf.len() {
-                            break;
-                        }
+                        bufs.push(buf);
                     }
-                    Ok(sent)
+                    let slices: Vec<_> =
+                        bufs.iter().map(|buf| IoSlice::new(buf.as_ref())).collect();
+
+                    socket
+                        .send_vectored(env.tasks().deref(), &slices, timeout, nonblocking)
+                        .await
                 }
                 FdWriteSource::Buffer(data) => {
                     socket
-                        .send(
-                            env.tasks().deref(),
-                            data.as_ref(),
-                            Some(timeout),
-                            nonblocking,
-                        )
+                        .send(env.tasks().deref(), data.as_ref(), timeout, nonblocking)
                         .await
                 }
             }
@@ -134,11 +134,8 @@ pub(crate) fn sock_send_file_internal(
                                 let tasks = tasks.clone();
                                 drop(guard);
 
-                                let read_timeout = socket
-                                    .opt_time(TimeType::WriteTimeout)
-                                    .ok()
-                                    .flatten()
-                                    .unwrap_or(Duration::from_secs(30));
+                                let read_timeout =
+                                    socket.opt_time(TimeType::WriteTimeout).ok().flatten();
 
                                 let data = wasi_try_ok_ok!(__asyncify(ctx, None, async {
                                     let mut buf = Vec::with_capacity(sub_count as usize);
@@ -146,7 +143,7 @@ pub(crate) fn sock_send_file_internal(
                                         buf.set_len(sub_count as usize);
                                     }
                                     socket
-                                        .recv(tasks.deref(), &mut buf, Some(read_timeout), false)
+                                        .recv(tasks.deref(), &mut buf, read_timeout, false)
                                         .await
                                         .map(|amt| {
                                             unsafe {
@@ -217,14 +214,8 @@ pub(crate) fn sock_send_file_internal(
             sock,
             Rights::SOCK_SEND,
             |socket, fd| async move {
-                let write_timeout = socket
-                    .opt_time(TimeType::ReadTimeout)
-                    .ok()
-                    .flatten()
-                    .unwrap_or(Duration::from_secs(30));
-                socket
-                    .send(tasks.deref(), &data, Some(write_timeout), true)
-                    .await
+                let write_timeout = socket.opt_time(TimeType::ReadTimeout).ok().flatten();
+                socket.send(tasks.deref(), &data, write_timeout, true).await
             },
         ));
         env = ctx.data();
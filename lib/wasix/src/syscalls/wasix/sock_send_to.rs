@@ -94,11 +94,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
             Rights::SOCK_SEND_TO,
             |socket, fd| async move {
                 let nonblocking = fd.inner.flags.contains(Fdflags::NONBLOCK);
-                let timeout = socket
-                    .opt_time(TimeType::WriteTimeout)
-                    .ok()
-                    .flatten()
-                    .unwrap_or(Duration::from_secs(30));
+                let timeout = socket.opt_time(TimeType::WriteTimeout).ok().flatten();
 
                 match si_data {
                     FdWriteSource::Iovs { iovs, iovs_len } => {
@@ -117,7 +113,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
                                     env.tasks().deref(),
                                     buf.as_ref(),
                                     addr,
-                                    Some(timeout),
+                                    timeout,
                                     nonblocking,
                                 )
                                 .await
@@ -139,7 +135,7 @@ pub(crate) fn sock_send_to_internal<M: MemorySize>(
                                 env.tasks().deref(),
                                 data.as_ref(),
                                 addr,
-                                Some(timeout),
+                                timeout,
                                 nonblocking,
                             )
                             .await
@@ -32,6 +32,7 @@ pub fn sock_set_opt_time<M: MemorySize>(
         Sockoption::ConnectTimeout => TimeType::ConnectTimeout,
         Sockoption::AcceptTimeout => TimeType::AcceptTimeout,
         Sockoption::Linger => TimeType::Linger,
+        Sockoption::UserTimeout => TimeType::UserTimeout,
         _ => return Ok(Errno::Inval),
     };
 
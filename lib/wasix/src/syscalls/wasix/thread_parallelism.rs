@@ -3,17 +3,21 @@ use crate::syscalls::*;
 
 /// ### `thread_parallelism()`
 /// Returns the available parallelism which is normally the
-/// number of available cores that can run concurrently
+/// number of available cores that can run concurrently, unless
+/// the embedder configured a different value
 #[instrument(level = "trace", skip_all, fields(parallelism = field::Empty), ret)]
 pub fn thread_parallelism<M: MemorySize>(
     ctx: FunctionEnvMut<'_, WasiEnv>,
     ret_parallelism: WasmPtr<M::Offset, M>,
 ) -> Errno {
     let env = ctx.data();
-    let parallelism = wasi_try!(env.tasks().thread_parallelism().map_err(|err| {
-        let err: Errno = err.into();
-        err
-    }));
+    let parallelism = match env.control_plane.config().thread_parallelism {
+        Some(parallelism) => parallelism.max(1),
+        None => wasi_try!(env.tasks().thread_parallelism().map_err(|err| {
+            let err: Errno = err.into();
+            err
+        })),
+    };
     Span::current().record("parallelism", parallelism);
     let parallelism: M::Offset = wasi_try!(parallelism.try_into().map_err(|_| Errno::Overflow));
     let memory = unsafe { env.memory_view(&ctx) };
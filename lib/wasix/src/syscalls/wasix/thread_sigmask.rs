@@ -0,0 +1,52 @@
+use super::*;
+use crate::{
+    os::task::thread::{SignalMask, SignalMaskHow},
+    syscalls::*,
+};
+
+/// ### `thread_sigmask()`
+/// Examines and changes the signals that are blocked by the current thread.
+/// Note: This is similar to `pthread_sigmask` in POSIX.
+///
+/// Signals that are blocked stay pending until they are unblocked again,
+/// `SIGKILL` and `SIGSTOP` can not be blocked.
+///
+/// ## Parameters
+///
+/// * `how` - How the mask is changed, `0` blocks the signals in `set`,
+///   `1` unblocks them and `2` replaces the mask with `set`
+/// * `set` - The signals to change (`signal - 1` is the bit of a signal),
+///   when this is null the mask is left as is
+/// * `oldset` - When not null the previous mask is written here
+#[instrument(level = "trace", skip_all, fields(%how), ret)]
+pub fn thread_sigmask<M: MemorySize>(
+    mut ctx: FunctionEnvMut<'_, WasiEnv>,
+    how: i32,
+    set: WasmPtr<SignalMask, M>,
+    oldset: WasmPtr<SignalMask, M>,
+) -> Result<Errno, WasiError> {
+    let env = ctx.data();
+    let memory = unsafe { env.memory_view(&ctx) };
+
+    let old = if set.is_null() {
+        env.thread.signal_mask()
+    } else {
+        let how = match how {
+            0 => SignalMaskHow::Block,
+            1 => SignalMaskHow::Unblock,
+            2 => SignalMaskHow::SetMask,
+            _ => return Ok(Errno::Inval),
+        };
+        let set = wasi_try_mem_ok!(set.read(&memory));
+        env.process.update_signal_mask(&env.thread, how, set)
+    };
+
+    if !oldset.is_null() {
+        wasi_try_mem_ok!(oldset.write(&memory, old));
+    }
+
+    // Any signals that were just unblocked are handled straight away
+    wasi_try_ok!(WasiEnv::process_signals_and_exit(&mut ctx)?);
+
+    Ok(Errno::Success)
+}
@@ -5,7 +5,7 @@ use super::*;
 use crate::journal::JournalEffector;
 use crate::{
     capture_store_snapshot,
-    os::task::thread::WasiMemoryLayout,
+    os::task::thread::{SignalMaskHow, WasiMemoryLayout},
     runtime::{
         task_manager::{TaskWasm, TaskWasmRunProperties},
         TaintReason,
@@ -103,6 +103,9 @@ pub fn thread_spawn_internal_from_wasi<M: MemorySize>(
     let thread_id: Tid = thread_handle.id().into();
     Span::current().record("tid", thread_id);
 
+    // The new thread starts out with the signal mask of its creator
+    thread_handle.update_signal_mask(SignalMaskHow::SetMask, env.thread.signal_mask());
+
     // Spawn the thread
     thread_spawn_internal_using_layout::<M>(ctx, thread_handle, layout, start_ptr_offset, None)?;
 
@@ -195,6 +198,7 @@ fn call_module<M: MemorySize>(
             .clone()
             .unwrap();
         let tid = env.data(&store).tid();
+        env.data(&store).start_cpu_time();
         let call_ret = spawn.call(
             store,
             tid.raw().try_into().map_err(|_| Errno::Overflow).unwrap(),
@@ -237,6 +241,11 @@ fn call_module<M: MemorySize>(
                     ret = Errno::Noexec;
                     exit_code = Some(ExitCode::from(128 + ret as i32));
                 }
+                Ok(WasiError::CpuBudgetExceeded) => {
+                    debug!("failed as the CPU time budget was exhausted");
+                    ret = Errno::Noexec;
+                    exit_code = Some(ExitCode::from(128 + ret as i32));
+                }
                 Err(err) => {
                     debug!("failed with runtime error: {}", err);
                     env.data(&store)
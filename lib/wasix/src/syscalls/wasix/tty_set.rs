@@ -32,6 +32,8 @@ pub fn tty_set<M: MemorySize>(
         echo,
         line_buffered,
         line_feeds,
+        term_type: None,
+        colors: false,
     };
 
     wasi_try_ok!({
@@ -61,7 +63,15 @@ pub fn tty_set_internal(
     } else {
         return Err(Errno::Notsup);
     };
-    bridge.tty_set(state);
+
+    // The guest has no way to describe the terminal itself so whatever the
+    // runtime reported is kept
+    let current = bridge.tty_get();
+    bridge.tty_set(WasiTtyState {
+        term_type: current.term_type,
+        colors: current.colors,
+        ..state
+    });
 
     Ok(())
 }
@@ -1,17 +1,13 @@
 use std::mem;
 
 use chrono::prelude::*;
-use wasmer::WasmRef;
 
 use crate::syscalls::types::{
     wasi::{Errno, Snapshot0Clockid, Timestamp},
     *,
 };
 
-pub fn platform_clock_res_get(
-    clock_id: Snapshot0Clockid,
-    resolution: WasmRef<Timestamp>,
-) -> Result<i64, Errno> {
+pub fn platform_clock_res_get(clock_id: Snapshot0Clockid) -> Result<i64, Errno> {
     let t_out = match clock_id {
         Snapshot0Clockid::Monotonic => 10_000_000,
         Snapshot0Clockid::Realtime => 1,
@@ -1,12 +1,8 @@
 use tracing::debug;
-use wasmer::WasmRef;
 
 use crate::syscalls::types::wasi::{self, Timestamp};
 
-pub fn platform_clock_res_get(
-    clock_id: wasi::Snapshot0Clockid,
-    resolution: WasmRef<Timestamp>,
-) -> Result<i64, wasi::Errno> {
+pub fn platform_clock_res_get(clock_id: wasi::Snapshot0Clockid) -> Result<i64, wasi::Errno> {
     let resolution_val = match clock_id {
         // resolution of monotonic clock at 10ms, from:
         // https://docs.microsoft.com/en-us/windows/desktop/api/sysinfoapi/nf-sysinfoapi-gettickcount64
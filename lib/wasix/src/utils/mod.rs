@@ -16,7 +16,7 @@ use wasmer_types::ModuleHash;
 
 use std::collections::BTreeSet;
 
-use wasmer::Module;
+use wasmer::{ExternType, FunctionType, Module};
 use wasmer_wasix_types::wasi::Errno;
 
 /// Creates a random xxhash for the module
@@ -141,19 +141,19 @@ impl Ord for WasiVersion {
 }
 
 /// Namespace for the `Snapshot0` version.
-const SNAPSHOT0_NAMESPACE: &str = "wasi_unstable";
+pub(crate) const SNAPSHOT0_NAMESPACE: &str = "wasi_unstable";
 
 /// Namespace for the `Snapshot1` version.
-const SNAPSHOT1_NAMESPACE: &str = "wasi_snapshot_preview1";
+pub(crate) const SNAPSHOT1_NAMESPACE: &str = "wasi_snapshot_preview1";
 
 /// Namespace for the `wasix` version.
-const WASIX_32V1_NAMESPACE: &str = "wasix_32v1";
+pub(crate) const WASIX_32V1_NAMESPACE: &str = "wasix_32v1";
 
 /// Namespace for the `wasix` version.
-const WASIX_64V1_NAMESPACE: &str = "wasix_64v1";
+pub(crate) const WASIX_64V1_NAMESPACE: &str = "wasix_64v1";
 
 /// Namespace for the `wasix` version.
-const WASIX_HTTP_V1_NAMESPACE: &str = "wasix_http_client_v1";
+pub(crate) const WASIX_HTTP_V1_NAMESPACE: &str = "wasix_http_client_v1";
 
 /// Detect the version of WASI being used based on the import
 /// namespaces.
@@ -229,10 +229,90 @@ pub fn get_wasi_versions(module: &Module, strict: bool) -> Option<BTreeSet<WasiV
     }
 }
 
+/// The kind of item that a module exports, see [`ExportInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportKind {
+    Function,
+    Global,
+    Table,
+    Memory,
+}
+
+/// An export of a module, see [`get_exports`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportInfo {
+    pub name: String,
+    pub kind: ExportKind,
+    /// The signature of the export when it is a function
+    pub func_type: Option<FunctionType>,
+}
+
+/// Lists the exports of a module in the order they are declared, which
+/// lets a host present the entrypoints of a module and marshal the
+/// arguments of its functions without instantiating it.
+pub fn get_exports(module: &Module) -> Vec<ExportInfo> {
+    module
+        .exports()
+        .map(|export| {
+            let (kind, func_type) = match export.ty() {
+                ExternType::Function(ty) => (ExportKind::Function, Some(ty.clone())),
+                ExternType::Global(_) => (ExportKind::Global, None),
+                ExternType::Table(_) => (ExportKind::Table, None),
+                ExternType::Memory(_) => (ExportKind::Memory, None),
+            };
+            ExportInfo {
+                name: export.name().to_string(),
+                kind,
+                func_type,
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn exports_with_their_types() {
+        use wasmer::{Store, Type};
+
+        let wat = r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "add") (param i32 i32) (result i32)
+                    (i32.add (local.get 0) (local.get 1)))
+                (func (export "scale") (param f64 i64) (result f64 f64)
+                    (local.get 0) (local.get 0))
+            )
+        "#;
+        let module = Module::new(&Store::default(), wat).unwrap();
+
+        assert_eq!(
+            get_exports(&module),
+            vec![
+                ExportInfo {
+                    name: "memory".to_string(),
+                    kind: ExportKind::Memory,
+                    func_type: None,
+                },
+                ExportInfo {
+                    name: "add".to_string(),
+                    kind: ExportKind::Function,
+                    func_type: Some(FunctionType::new([Type::I32, Type::I32], [Type::I32])),
+                },
+                ExportInfo {
+                    name: "scale".to_string(),
+                    kind: ExportKind::Function,
+                    func_type: Some(FunctionType::new(
+                        [Type::F64, Type::I64],
+                        [Type::F64, Type::F64]
+                    )),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn wasi_version_equality() {
         assert_eq!(WasiVersion::Snapshot0, WasiVersion::Snapshot0);
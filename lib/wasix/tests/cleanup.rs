@@ -0,0 +1,137 @@
+//! Checks that tearing down an environment flushes and closes its files.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use virtual_fs::VirtualFile;
+use wasmer_wasix::{wasmer_wasix_types::wasi::Rights, WasiEnv};
+
+#[derive(Debug, Default)]
+struct Counters {
+    flushed: AtomicUsize,
+    closed: AtomicUsize,
+}
+
+/// File that buffers its writes and records when it is flushed and closed
+#[derive(Debug)]
+struct InstrumentedFile {
+    counters: Arc<Counters>,
+    buffered: Vec<u8>,
+}
+
+impl InstrumentedFile {
+    fn new(counters: &Arc<Counters>) -> Box<Self> {
+        Box::new(Self {
+            counters: counters.clone(),
+            buffered: b"pending".to_vec(),
+        })
+    }
+}
+
+impl Drop for InstrumentedFile {
+    fn drop(&mut self) {
+        self.counters.closed.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl AsyncRead for InstrumentedFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for InstrumentedFile {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.buffered.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.buffered.is_empty() {
+            self.buffered.clear();
+            self.counters.flushed.fetch_add(1, Ordering::SeqCst);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncSeek for InstrumentedFile {
+    fn start_seek(self: Pin<&mut Self>, _position: io::SeekFrom) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl VirtualFile for InstrumentedFile {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        self.buffered.len() as u64
+    }
+    fn set_len(&mut self, _new_size: u64) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> virtual_fs::Result<()> {
+        Ok(())
+    }
+    fn poll_read_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+    fn poll_write_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(8192))
+    }
+}
+
+#[tokio::test]
+async fn test_cleanup_flushes_and_closes_every_fd() {
+    let counters = Arc::new(Counters::default());
+
+    let env = WasiEnv::builder("cleanup")
+        .preopen_fd(5, InstrumentedFile::new(&counters), Rights::FD_WRITE)
+        .preopen_fd(6, InstrumentedFile::new(&counters), Rights::FD_WRITE)
+        .preopen_fd(7, InstrumentedFile::new(&counters), Rights::FD_READ)
+        .build()
+        .unwrap();
+
+    env.blocking_cleanup();
+    assert_eq!(counters.flushed.load(Ordering::SeqCst), 3);
+    assert_eq!(counters.closed.load(Ordering::SeqCst), 3);
+
+    // Cleaning up again must not touch the files a second time
+    env.blocking_cleanup();
+    drop(env);
+    assert_eq!(counters.flushed.load(Ordering::SeqCst), 3);
+    assert_eq!(counters.closed.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,87 @@
+//! Checks the heap statistics that are recorded for the allocator exports.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use wasmer::{Module, Store};
+use wasmer_wasix::{HeapStats, WasiEnv, WasiEnvBuilder};
+
+/// Module with a bump allocator that never reuses memory
+const ALLOCATOR_WAT: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (func (export "malloc") (param $size i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $size)))
+            (local.get $ptr)
+        )
+        (func (export "free") (param $ptr i32))
+    )
+"#;
+
+fn run(
+    builder: WasiEnvBuilder,
+    f: impl FnOnce(&mut Store, &wasmer_wasix::WasiFunctionEnv),
+) -> HeapStats {
+    let mut store = Store::default();
+    let module = Module::new(&store, ALLOCATOR_WAT).unwrap();
+    let (_instance, env) = builder.instantiate(module, &mut store).unwrap();
+
+    f(&mut store, &env);
+    env.data(&store).process.heap_stats()
+}
+
+#[tokio::test]
+async fn test_heap_stats_track_allocations() {
+    let stats = run(WasiEnv::builder("heap"), |store, env| {
+        let a = env.malloc(store, 100).unwrap();
+        let b = env.malloc(store, 200).unwrap();
+        assert_ne!(a, 0);
+        assert_ne!(b, 0);
+        env.free(store, a).unwrap();
+        env.malloc(store, 50).unwrap();
+    });
+
+    assert_eq!(
+        stats,
+        HeapStats {
+            current: 250,
+            peak: 300,
+            allocations: 2,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_heap_limit_fails_allocations() {
+    let stats = run(WasiEnv::builder("heap").heap_limit(256), |store, env| {
+        assert_ne!(env.malloc(store, 200).unwrap(), 0);
+        assert_eq!(
+            env.malloc(store, 100).unwrap(),
+            0,
+            "allocation exceeds the limit"
+        );
+        assert_ne!(env.malloc(store, 56).unwrap(), 0);
+    });
+
+    assert_eq!(
+        stats,
+        HeapStats {
+            current: 256,
+            peak: 256,
+            allocations: 2,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_heap_rejects_sizes_too_large_for_the_allocator() {
+    let stats = run(WasiEnv::builder("heap"), |store, env| {
+        // The allocator of the module takes 32-bit sizes
+        assert!(env.malloc(store, 1 << 32).is_err());
+    });
+
+    assert_eq!(stats, HeapStats::default());
+}
@@ -0,0 +1,3069 @@
+//! Runs small WAT programs that invoke a single syscall against a sandboxed
+//! file system and report the resulting errno as their exit code.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use virtual_fs::{
+    AsyncReadExt, AsyncWriteExt, FileOpener, FileSystem, Metadata, OpenOptions, OpenOptionsConfig,
+    ReadDir, StaticFile, TmpFileSystem, VirtualFile,
+};
+use wasmer::{Module, Store, Value};
+use wasmer_wasix::{
+    capabilities::Capabilities,
+    os::TtyBridge,
+    runtime::{
+        clock::{Clock, ManualClock},
+        task_manager::tokio::TokioTaskManager,
+        DefaultTty, RuntimeEvent,
+    },
+    wasmer_wasix_types::{
+        types::{CLOSE_RANGE_FLAGS_CLOEXEC, MEMORY_PROT_READ, MEMORY_PROT_WRITE},
+        wasi::{Errno, ExitCode, Rights, Snapshot0Clockid, Timestamp},
+    },
+    PluggableRuntime, VirtualTaskManager, WasiEnv, WasiEnvBuilder, WasiError, WasiRuntimeError,
+    WasiTtyState,
+};
+
+/// Runs the `_start` function of the module with `fs` preopened at `/` and
+/// returns its exit code
+fn run_wat(wat: &str, fs: &TmpFileSystem) -> ExitCode {
+    let builder = WasiEnv::builder("syscalls")
+        .sandbox_fs(fs.clone())
+        .preopen_dir("/")
+        .unwrap();
+    run_wat_with(wat, builder)
+}
+
+/// Runs the `_start` function of the module in the environment set up by
+/// `builder` and returns its exit code
+fn run_wat_with(wat: &str, builder: WasiEnvBuilder) -> ExitCode {
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+
+    match builder.run_with_store(module, &mut store) {
+        Ok(()) => ExitCode::from(Errno::Success),
+        Err(err) => err.as_exit_code().expect("the program should exit cleanly"),
+    }
+}
+
+/// Temporary directory on the host that is exposed to the guest through
+/// `host_fs`, along with the runtime that drives the file system
+struct HostDir {
+    runtime: tokio::runtime::Runtime,
+    dir: tempfile::TempDir,
+}
+
+impl HostDir {
+    fn new() -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        Self { runtime, dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Builder with the directory preopened at `/`
+    fn builder(&self) -> WasiEnvBuilder {
+        let fs = virtual_fs::host_fs::FileSystem::new(self.runtime.handle().clone(), self.path())
+            .unwrap();
+        WasiEnv::builder("syscalls")
+            .fs(Box::new(fs))
+            .preopen_dir("/")
+            .unwrap()
+    }
+}
+
+fn create_file(fs: &TmpFileSystem, path: &str) {
+    fs.new_open_options()
+        .create(true)
+        .write(true)
+        .open(path)
+        .unwrap();
+}
+
+/// Builds a program that hard links `old_path` to `new_path` with
+/// `path_link` relative to the preopened root directory (fd 3)
+fn path_link_wat(old_path: &str, new_path: &str) -> String {
+    format!(
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_link" (func $path_link (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "{old_path}")
+            (data (i32.const 256) "{new_path}")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $path_link
+                        (i32.const 3) (i32.const 0) (i32.const 0) (i32.const {old_len})
+                        (i32.const 3) (i32.const 256) (i32.const {new_len})))
+            )
+        )
+        "#,
+        old_len = old_path.len(),
+        new_len = new_path.len(),
+    )
+}
+
+#[test]
+fn test_path_link_across_directories() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/a".as_ref()).unwrap();
+    fs.create_dir("/b".as_ref()).unwrap();
+    create_file(&fs, "/a/file");
+
+    let code = run_wat(&path_link_wat("a/file", "b/link"), &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+#[test]
+fn test_path_link_onto_existing_name() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/a".as_ref()).unwrap();
+    create_file(&fs, "/a/file");
+    create_file(&fs, "/a/other");
+
+    let code = run_wat(&path_link_wat("a/file", "a/other"), &fs);
+    assert_eq!(code.raw(), Errno::Exist as i32);
+}
+
+#[test]
+fn test_path_link_directory() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/a".as_ref()).unwrap();
+    fs.create_dir("/b".as_ref()).unwrap();
+
+    let code = run_wat(&path_link_wat("a", "b/link"), &fs);
+    assert_eq!(code.raw(), Errno::Perm as i32);
+}
+
+/// Builds a program that invokes a syscall which takes a directory fd
+/// and a path (e.g. `path_create_directory`) on the preopened root
+fn path_wat(syscall: &str, path: &str) -> String {
+    format!(
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "{syscall}" (func $syscall (param i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "{path}")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $syscall (i32.const 3) (i32.const 0) (i32.const {len})))
+            )
+        )
+        "#,
+        len = path.len(),
+    )
+}
+
+#[test]
+fn test_path_create_directory_existing() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/a".as_ref()).unwrap();
+
+    let code = run_wat(&path_wat("path_create_directory", "a"), &fs);
+    assert_eq!(code.raw(), Errno::Exist as i32);
+}
+
+#[test]
+fn test_path_remove_directory_not_empty() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/a".as_ref()).unwrap();
+    create_file(&fs, "/a/file");
+
+    let code = run_wat(&path_wat("path_remove_directory", "a"), &fs);
+    assert_eq!(code.raw(), Errno::Notempty as i32);
+    assert!(fs.metadata("/a".as_ref()).is_ok());
+}
+
+#[test]
+fn test_path_remove_directory_empty() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/a".as_ref()).unwrap();
+
+    let code = run_wat(&path_wat("path_remove_directory", "a"), &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+    assert!(fs.metadata("/a".as_ref()).is_err());
+}
+
+// `unlinkat` maps to `path_remove_directory` when `AT_REMOVEDIR` is set
+// and to `path_unlink_file` otherwise
+#[test]
+fn test_path_unlink_file() {
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/file");
+
+    let code = run_wat(&path_wat("path_unlink_file", "file"), &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+    assert!(fs.metadata("/file".as_ref()).is_err());
+}
+
+#[test]
+fn test_path_unlink_file_directory() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/a".as_ref()).unwrap();
+
+    let code = run_wat(&path_wat("path_unlink_file", "a"), &fs);
+    assert_eq!(code.raw(), Errno::Isdir as i32);
+    assert!(fs.metadata("/a".as_ref()).unwrap().is_dir());
+
+    // It can still be removed as a directory
+    let code = run_wat(&path_wat("path_remove_directory", "a"), &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+#[test]
+fn test_path_remove_directory_file() {
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/file");
+
+    let code = run_wat(&path_wat("path_remove_directory", "file"), &fs);
+    assert_eq!(code.raw(), Errno::Notdir as i32);
+    assert!(fs.metadata("/file".as_ref()).unwrap().is_file());
+}
+
+#[test]
+fn test_path_readlink_truncates_to_buffer() {
+    // Links `link` to an 18 byte target and reads it back into an 8 byte
+    // buffer and into a 64 byte buffer, the bytes right after what should
+    // be written are set to 0xff to catch overruns and NUL terminators
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_symlink" (func $path_symlink (param i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "path_readlink" (func $path_readlink (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "target_of_the_link")
+            (data (i32.const 32) "link")
+            (data (i32.const 136) "\ff")
+            (data (i32.const 274) "\ff")
+            (func $check (param $ok i32) (param $code i32)
+                (if (i32.eqz (local.get $ok))
+                    (then (call $proc_exit (local.get $code)))))
+            (func (export "_start")
+                (call $check (i32.eqz (call $path_symlink
+                    (i32.const 0) (i32.const 18) (i32.const 3) (i32.const 32) (i32.const 4))) (i32.const 1))
+
+                ;; undersized buffer
+                (call $check (i32.eqz (call $path_readlink
+                    (i32.const 3) (i32.const 32) (i32.const 4)
+                    (i32.const 128) (i32.const 8) (i32.const 64))) (i32.const 2))
+                (call $check (i32.eq (i32.load (i32.const 64)) (i32.const 8)) (i32.const 3))
+                (call $check (i64.eq (i64.load (i32.const 128)) (i64.load (i32.const 0))) (i32.const 4))
+                (call $check (i32.eq (i32.load8_u (i32.const 136)) (i32.const 0xff)) (i32.const 5))
+
+                ;; adequate buffer
+                (call $check (i32.eqz (call $path_readlink
+                    (i32.const 3) (i32.const 32) (i32.const 4)
+                    (i32.const 256) (i32.const 64) (i32.const 64))) (i32.const 6))
+                (call $check (i32.eq (i32.load (i32.const 64)) (i32.const 18)) (i32.const 7))
+                (call $check (i64.eq (i64.load (i32.const 256)) (i64.load (i32.const 0))) (i32.const 8))
+                (call $check (i64.eq (i64.load (i32.const 264)) (i64.load (i32.const 8))) (i32.const 9))
+                (call $check (i32.eq (i32.load16_u (i32.const 272)) (i32.load16_u (i32.const 16))) (i32.const 10))
+                (call $check (i32.eq (i32.load8_u (i32.const 274)) (i32.const 0xff)) (i32.const 11))
+
+                (call $proc_exit (i32.const 0))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+/// File system that behaves like an unresponsive host mount: opening a file
+/// blocks until the test releases it and renames never complete
+#[derive(Debug, Default, Clone)]
+struct StuckFileSystem {
+    inner: TmpFileSystem,
+    released: Arc<(Mutex<bool>, Condvar)>,
+    opened: Arc<AtomicUsize>,
+}
+
+impl StuckFileSystem {
+    /// Lets the blocked opens through to the inner file system
+    fn release(&self) {
+        let (released, cvar) = &*self.released;
+        *released.lock().unwrap() = true;
+        cvar.notify_all();
+    }
+}
+
+impl FileOpener for StuckFileSystem {
+    fn open(
+        &self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> virtual_fs::Result<Box<dyn VirtualFile + Send + Sync + 'static>> {
+        let (released, cvar) = &*self.released;
+        drop(
+            cvar.wait_while(released.lock().unwrap(), |released| !*released)
+                .unwrap(),
+        );
+        let file = self
+            .inner
+            .new_open_options()
+            .options(conf.clone())
+            .open(path);
+        self.opened.fetch_add(1, Ordering::SeqCst);
+        file
+    }
+}
+
+impl FileSystem for StuckFileSystem {
+    fn readlink(&self, path: &Path) -> virtual_fs::Result<PathBuf> {
+        self.inner.readlink(path)
+    }
+    fn read_dir(&self, path: &Path) -> virtual_fs::Result<ReadDir> {
+        self.inner.read_dir(path)
+    }
+    fn create_dir(&self, path: &Path) -> virtual_fs::Result<()> {
+        self.inner.create_dir(path)
+    }
+    fn remove_dir(&self, path: &Path) -> virtual_fs::Result<()> {
+        self.inner.remove_dir(path)
+    }
+    fn rename<'a>(&'a self, from: &'a Path, to: &'a Path) -> BoxFuture<'a, virtual_fs::Result<()>> {
+        Box::pin(std::future::pending())
+    }
+    fn metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        self.inner.metadata(path)
+    }
+    fn symlink_metadata(&self, path: &Path) -> virtual_fs::Result<Metadata> {
+        self.inner.symlink_metadata(path)
+    }
+    fn remove_file(&self, path: &Path) -> virtual_fs::Result<()> {
+        self.inner.remove_file(path)
+    }
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(self)
+    }
+    fn mount(
+        &self,
+        name: String,
+        path: &Path,
+        fs: Box<dyn FileSystem + Send + Sync>,
+    ) -> virtual_fs::Result<()> {
+        self.inner.mount(name, path, fs)
+    }
+}
+
+/// Builds a program that opens `path` relative to the preopened root
+/// directory (fd 3) and exits with the resulting errno
+fn path_open_wat(path: &str, o_flags: u16, rights: Rights) -> String {
+    format!(
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "{path}")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $path_open
+                        (i32.const 3) (i32.const 0) (i32.const 0) (i32.const {len})
+                        (i32.const {o_flags}) (i64.const {rights}) (i64.const {rights}) (i32.const 0)
+                        (i32.const 256)))
+            )
+        )
+        "#,
+        len = path.len(),
+        rights = rights.bits(),
+    )
+}
+
+/// Builds a program that opens `file` five times, calls `fd_close_range`
+/// on the second to the fourth of those descriptors and exits with a bit
+/// set for each of the five that is still open
+fn fd_close_range_wat(flags: u32) -> String {
+    format!(
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_fdstat_get" (func $fd_fdstat_get (param i32 i32) (result i32)))
+            (import "wasix_32v1" "fd_close_range" (func $fd_close_range (param i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "file")
+            (func $fd (param $i i32) (result i32)
+                (i32.load (i32.add (i32.const 256) (i32.shl (local.get $i) (i32.const 2)))))
+            (func (export "_start")
+                (local $i i32) (local $ret i32) (local $mask i32)
+                (loop $open
+                    (local.set $ret
+                        (call $path_open
+                            (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                            (i32.const 0) (i64.const {rights}) (i64.const {rights}) (i32.const 0)
+                            (i32.add (i32.const 256) (i32.shl (local.get $i) (i32.const 2)))))
+                    (if (local.get $ret) (then (call $proc_exit (i32.add (i32.const 64) (local.get $ret)))))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $open (i32.lt_u (local.get $i) (i32.const 5))))
+                (local.set $ret
+                    (call $fd_close_range (call $fd (i32.const 1)) (call $fd (i32.const 3)) (i32.const {flags})))
+                (if (local.get $ret) (then (call $proc_exit (i32.add (i32.const 64) (local.get $ret)))))
+                (local.set $i (i32.const 0))
+                (loop $check
+                    (if (i32.eqz (call $fd_fdstat_get (call $fd (local.get $i)) (i32.const 512)))
+                        (then (local.set $mask (i32.or (local.get $mask) (i32.shl (i32.const 1) (local.get $i))))))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $check (i32.lt_u (local.get $i) (i32.const 5))))
+                (call $proc_exit (local.get $mask)))
+        )
+        "#,
+        rights = Rights::FD_READ.bits(),
+    )
+}
+
+#[test]
+fn test_fd_close_range() {
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/file");
+
+    // Only the first and the last descriptors are left open
+    let code = run_wat(&fd_close_range_wat(0), &fs);
+    assert_eq!(code.raw(), 0b10001);
+}
+
+#[test]
+fn test_fd_close_range_cloexec() {
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/file");
+
+    // The descriptors are only marked, they stay open until `proc_exec`
+    let code = run_wat(&fd_close_range_wat(CLOSE_RANGE_FLAGS_CLOEXEC), &fs);
+    assert_eq!(code.raw(), 0b11111);
+
+    let code = run_wat(&fd_close_range_wat(1), &fs);
+    assert_eq!(code.raw(), 64 + Errno::Inval as i32);
+}
+
+#[test]
+fn test_fd_lock_range_across_opens() {
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/db");
+
+    // Opens the file as `a` and `b`, and later a third time as `c`, every
+    // step exits with `100 * step + errno` unless it gets the expected errno
+    let wat = format!(
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasix_32v1" "fd_lock_range" (func $fd_lock_range (param i32 i64 i64 i32) (result i32)))
+            (import "wasix_32v1" "fd_unlock_range" (func $fd_unlock_range (param i32 i64 i64) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "db")
+            (func $open (param $fd_ptr i32) (result i32)
+                (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 2)
+                    (i32.const 0) (i64.const {rights}) (i64.const {rights}) (i32.const 0)
+                    (local.get $fd_ptr)))
+            (func $expect (param $step i32) (param $got i32) (param $want i32)
+                (if (i32.ne (local.get $got) (local.get $want))
+                    (then (call $proc_exit (i32.add (i32.mul (local.get $step) (i32.const 100)) (local.get $got))))))
+            (func (export "_start")
+                (local $a i32) (local $b i32) (local $c i32)
+                (call $expect (i32.const 1) (call $open (i32.const 256)) (i32.const 0))
+                (call $expect (i32.const 2) (call $open (i32.const 260)) (i32.const 0))
+                (local.set $a (i32.load (i32.const 256)))
+                (local.set $b (i32.load (i32.const 260)))
+                (call $expect (i32.const 3)
+                    (call $fd_lock_range (local.get $a) (i64.const 0) (i64.const 10) (i32.const 1)) (i32.const 0))
+                (call $expect (i32.const 4)
+                    (call $fd_lock_range (local.get $b) (i64.const 20) (i64.const 10) (i32.const 1)) (i32.const 0))
+                (call $expect (i32.const 5)
+                    (call $fd_lock_range (local.get $b) (i64.const 5) (i64.const 10) (i32.const 1)) (i32.const {again}))
+                ;; Opening the file again keeps the locks of the other opens
+                (call $expect (i32.const 6) (call $open (i32.const 264)) (i32.const 0))
+                (local.set $c (i32.load (i32.const 264)))
+                (call $expect (i32.const 7)
+                    (call $fd_lock_range (local.get $b) (i64.const 5) (i64.const 10) (i32.const 1)) (i32.const {again}))
+                (call $expect (i32.const 8)
+                    (call $fd_unlock_range (local.get $a) (i64.const 0) (i64.const 10)) (i32.const 0))
+                (call $expect (i32.const 9)
+                    (call $fd_lock_range (local.get $b) (i64.const 5) (i64.const 10) (i32.const 1)) (i32.const 0))
+                (call $expect (i32.const 10)
+                    (call $fd_lock_range (local.get $c) (i64.const 0) (i64.const 0) (i32.const 0)) (i32.const {again}))
+                (call $proc_exit (i32.const 0)))
+        )
+        "#,
+        rights = (Rights::FD_READ | Rights::FD_WRITE).bits(),
+        again = Errno::Again as i32,
+    );
+
+    let code = run_wat(&wat, &fs);
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_memory_protect() {
+    let wat = format!(
+        r#"
+        (module
+            (import "wasix_32v1" "memory_protect" (func $memory_protect (param i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 2)
+            (export "memory" (memory 0))
+            (func $expect (param $ret i32) (param $expected i32) (param $code i32)
+                (if (i32.ne (local.get $ret) (local.get $expected))
+                    (then (call $proc_exit (local.get $code)))))
+            (func (export "_start")
+                ;; unaligned, out of bounds and unknown flags
+                (call $expect (call $memory_protect (i32.const 1) (i32.const 1) (i32.const {read})) (i32.const {inval}) (i32.const 1))
+                (call $expect (call $memory_protect (i32.const 65536) (i32.const 131072) (i32.const {read})) (i32.const {inval}) (i32.const 2))
+                (call $expect (call $memory_protect (i32.const 65536) (i32.const 65536) (i32.const 4)) (i32.const {inval}) (i32.const 3))
+
+                ;; an inaccessible page can be made writable again
+                (call $expect (call $memory_protect (i32.const 65536) (i32.const 65536) (i32.const 0)) (i32.const 0) (i32.const 4))
+                (call $expect (call $memory_protect (i32.const 65536) (i32.const 65536) (i32.const {read_write})) (i32.const 0) (i32.const 5))
+                (i32.store (i32.const 65536) (i32.const 42))
+
+                ;; a read-only page can still be read but writing to it traps
+                (call $expect (call $memory_protect (i32.const 65536) (i32.const 65536) (i32.const {read})) (i32.const 0) (i32.const 6))
+                (call $expect (i32.load (i32.const 65536)) (i32.const 42) (i32.const 7))
+                (i32.store (i32.const 65536) (i32.const 43))
+                (call $proc_exit (i32.const 0)))
+        )
+        "#,
+        read = MEMORY_PROT_READ,
+        read_write = MEMORY_PROT_READ | MEMORY_PROT_WRITE,
+        inval = Errno::Inval as i32,
+    );
+
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let builder = WasiEnv::builder("syscalls");
+
+    let err = builder
+        .run_with_store(module, &mut store)
+        .expect_err("writing to the read-only page should trap");
+    match err {
+        WasiRuntimeError::Runtime(err) => assert!(err.to_trap().is_some(), "not a trap: {err:?}"),
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+#[test]
+fn test_memory_protect_syscall_buffers() {
+    let wat = format!(
+        r#"
+        (module
+            (import "wasix_32v1" "memory_protect" (func $memory_protect (param i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "random_get" (func $random_get (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 2)
+            (export "memory" (memory 0))
+            (func $expect (param $ret i32) (param $expected i32) (param $code i32)
+                (if (i32.ne (local.get $ret) (local.get $expected))
+                    (then (call $proc_exit (local.get $code)))))
+            (func (export "_start")
+                ;; an iovec pointing at the first bytes of the second page
+                (i32.store (i32.const 0) (i32.const 65536))
+                (i32.store (i32.const 4) (i32.const 4))
+                (i32.store (i32.const 65536) (i32.const 42))
+                (call $expect (call $memory_protect (i32.const 65536) (i32.const 65536) (i32.const {read})) (i32.const 0) (i32.const 1))
+
+                ;; syscalls can not write into a read-only page but can read from it
+                (call $expect (call $random_get (i32.const 65536) (i32.const 16)) (i32.const {fault}) (i32.const 2))
+                (call $expect (call $clock_time_get (i32.const 0) (i64.const 0) (i32.const 65536)) (i32.const {fault}) (i32.const 3))
+                (call $expect (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16)) (i32.const 0) (i32.const 4))
+                (call $expect (i32.load (i32.const 16)) (i32.const 4) (i32.const 5))
+
+                ;; nor read from an inaccessible page
+                (call $expect (call $memory_protect (i32.const 65536) (i32.const 65536) (i32.const 0)) (i32.const 0) (i32.const 6))
+                (call $expect (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 16)) (i32.const {fault}) (i32.const 7))
+                (call $proc_exit (i32.const 0)))
+        )
+        "#,
+        read = MEMORY_PROT_READ,
+        fault = Errno::Fault as i32,
+    );
+
+    let code = run_wat(&wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+#[test]
+fn test_path_open_invalid_combinations() {
+    const CREATE: u16 = 1;
+    const EXCL: u16 = 4;
+    const TRUNC: u16 = 8;
+
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/dir".as_ref()).unwrap();
+    create_file(&fs, "/file");
+
+    let cases = [
+        ("dir", 0, Rights::FD_READ | Rights::FD_WRITE, Errno::Isdir),
+        ("dir", TRUNC, Rights::FD_READ, Errno::Isdir),
+        ("file", CREATE | EXCL, Rights::FD_READ, Errno::Exist),
+        ("missing", 0, Rights::FD_READ, Errno::Noent),
+    ];
+    for (path, o_flags, rights, expected) in cases {
+        let code = run_wat(&path_open_wat(path, o_flags, rights), &fs);
+        assert_eq!(
+            code.raw(),
+            expected as i32,
+            "opening {path:?} with flags {o_flags}"
+        );
+    }
+
+    // Opening a directory for reading is still fine
+    let code = run_wat(&path_open_wat("dir", 0, Rights::FD_READ), &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+#[test]
+fn test_path_open_read_only_preopen() {
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/file");
+
+    let read_only = Rights::FD_READ
+        | Rights::FD_SEEK
+        | Rights::FD_TELL
+        | Rights::FD_READDIR
+        | Rights::FD_FILESTAT_GET
+        | Rights::PATH_OPEN
+        | Rights::PATH_FILESTAT_GET;
+    let run = |path: &str, o_flags: u16, rights: Rights| {
+        let builder = WasiEnv::builder("syscalls")
+            .sandbox_fs(fs.clone())
+            .preopen_dir_with_rights("/", read_only)
+            .unwrap();
+        run_wat_with(&path_open_wat(path, o_flags, rights), builder)
+    };
+
+    assert_eq!(run("file", 0, Rights::FD_READ).raw(), Errno::Success as i32);
+    assert_eq!(
+        run("file", 0, Rights::FD_READ | Rights::FD_WRITE).raw(),
+        Errno::Notcapable as i32
+    );
+    assert_eq!(
+        run("file", 0, Rights::FD_READ | Rights::FD_FILESTAT_SET_SIZE).raw(),
+        Errno::Notcapable as i32
+    );
+
+    // Creating a file needs the right to create files in the directory
+    const CREATE: u16 = 1;
+    assert_eq!(
+        run("new", CREATE, Rights::FD_READ).raw(),
+        Errno::Notcapable as i32
+    );
+    assert!(fs.metadata("/new".as_ref()).is_err());
+}
+
+#[test]
+fn test_path_open_host_op_timeout() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "file")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $path_open
+                        (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                        (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0)
+                        (i32.const 256)))
+            )
+        )
+    "#;
+
+    let fs = StuckFileSystem::default();
+    let builder = WasiEnv::builder("syscalls")
+        .fs(Box::new(fs.clone()))
+        .preopen_dir("/")
+        .unwrap()
+        .host_op_timeout(Duration::from_millis(100));
+
+    let started = Instant::now();
+    let code = run_wat_with(wat, builder);
+
+    assert_eq!(code.raw(), Errno::Timedout as i32);
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // Once the host lets the open through it creates the file, which must
+    // then be removed again as the guest was told that it failed
+    fs.release();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while fs.opened.load(Ordering::SeqCst) == 0 || fs.inner.metadata("/file".as_ref()).is_ok() {
+        assert!(Instant::now() < deadline, "the late file was not removed");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_max_host_ops_is_per_environment() {
+    // Opens "file" twice, the first open must time out and its result is
+    // checked, the program exits with the result of the second one
+    let wat = format!(
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "file")
+            (func $open (result i32)
+                (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                    (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (i32.const 256)))
+            (func (export "_start")
+                (if (i32.ne (call $open) (i32.const {timedout}))
+                    (then (call $proc_exit (i32.const 255))))
+                (call $proc_exit (call $open))
+            )
+        )
+        "#,
+        timedout = Errno::Timedout as i32,
+    );
+
+    // The open that is stuck on the host uses up the only slot of its
+    // environment, but not that of the other one
+    let stuck = [StuckFileSystem::default(), StuckFileSystem::default()];
+    for fs in &stuck {
+        let builder = WasiEnv::builder("syscalls")
+            .fs(Box::new(fs.clone()))
+            .preopen_dir("/")
+            .unwrap()
+            .host_op_timeout(Duration::from_millis(100))
+            .max_host_ops(1);
+        let code = run_wat_with(&wat, builder);
+        assert_eq!(code.raw(), Errno::Again as i32);
+    }
+
+    for fs in &stuck {
+        fs.release();
+    }
+}
+
+#[test]
+fn test_path_rename_host_op_timeout() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_rename" (func $path_rename (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "old")
+            (data (i32.const 16) "new")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $path_rename
+                        (i32.const 3) (i32.const 0) (i32.const 3)
+                        (i32.const 3) (i32.const 16) (i32.const 3)))
+            )
+        )
+    "#;
+
+    let fs = StuckFileSystem::default();
+    create_file(&fs.inner, "/old");
+    let builder = WasiEnv::builder("syscalls")
+        .fs(Box::new(fs.clone()))
+        .preopen_dir("/")
+        .unwrap()
+        .host_op_timeout(Duration::from_millis(100));
+
+    // The rename never completes on the host so it is cancelled
+    let started = Instant::now();
+    let code = run_wat_with(wat, builder);
+
+    assert_eq!(code.raw(), Errno::Timedout as i32);
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(fs.inner.metadata("/old".as_ref()).is_ok());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_cpu_time_budget_exceeded() {
+    // Spins for far longer than the budget, yielding every 2^20 iterations
+    // so that the runtime gets a chance to check the budget
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "sched_yield" (func $sched_yield (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (local $i i64)
+                (local.set $i (i64.const 0x10000000000))
+                (loop $spin
+                    (if (i64.eqz (i64.and (local.get $i) (i64.const 0xfffff)))
+                        (then (drop (call $sched_yield))))
+                    (local.set $i (i64.sub (local.get $i) (i64.const 1)))
+                    (br_if $spin (i64.ne (local.get $i) (i64.const 0))))
+                (call $proc_exit (i32.const 0))
+            )
+        )
+    "#;
+
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let builder = WasiEnv::builder("syscalls").cpu_time_budget(Duration::from_millis(50));
+
+    let err = builder
+        .run_with_store(module, &mut store)
+        .expect_err("the program should not run to completion");
+    assert!(
+        matches!(err, WasiRuntimeError::Wasi(WasiError::CpuBudgetExceeded)),
+        "unexpected error: {err:?}"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_cpu_time_before_the_first_syscall_is_charged() {
+    // Spins for far longer than the budget without making any syscall and
+    // then makes a single one, which must see that the budget is exhausted
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "sched_yield" (func $sched_yield (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (local $i i64)
+                (local.set $i (i64.const 0x40000000))
+                (loop $spin
+                    (local.set $i (i64.sub (local.get $i) (i64.const 1)))
+                    (br_if $spin (i64.ne (local.get $i) (i64.const 0))))
+                (drop (call $sched_yield))
+                (call $proc_exit (i32.const 0))
+            )
+        )
+    "#;
+
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let builder = WasiEnv::builder("syscalls").cpu_time_budget(Duration::from_millis(20));
+
+    let err = builder
+        .run_with_store(module, &mut store)
+        .expect_err("the program should not run to completion");
+    assert!(
+        matches!(err, WasiRuntimeError::Wasi(WasiError::CpuBudgetExceeded)),
+        "unexpected error: {err:?}"
+    );
+}
+
+#[test]
+fn test_fd_bytes_available_pipe() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+            (import "wasix_32v1" "fd_bytes_available" (func $fd_bytes_available (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 16) "\40\00\00\00\0a\00\00\00")
+            (data (i32.const 64) "0123456789")
+            (func (export "_start")
+                (drop (call $fd_pipe (i32.const 0) (i32.const 4)))
+                (drop (call $fd_write (i32.load (i32.const 0)) (i32.const 16) (i32.const 1) (i32.const 24)))
+                (drop (call $fd_bytes_available (i32.load (i32.const 4)) (i32.const 32)))
+                (call $proc_exit (i32.wrap_i64 (i64.load (i32.const 32))))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 10);
+}
+
+#[test]
+fn test_fd_bytes_available_socket() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_accept_v2" (func $sock_accept (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "fd_bytes_available" (func $fd_bytes_available (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            (data (i32.const 64) "\80\00\00\00\0a\00\00\00")
+            (data (i32.const 128) "0123456789")
+            (func (export "_start")
+                (local $port i32)
+                (local $client i32)
+                ;; TCP listener bound to an ephemeral port
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_listen (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 4)))
+                (local.set $client (i32.load (i32.const 4)))
+                (if (i32.ne (call $sock_connect (local.get $client) (i32.const 16)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1000))))
+
+                ;; a listener with a pending connection has no bytes to read
+                (drop (call $fd_bytes_available (i32.load (i32.const 0)) (i32.const 32)))
+                (if (i32.ne (call $sock_accept (i32.load (i32.const 0)) (i32.const 0) (i32.const 40) (i32.const 200)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1001))))
+
+                (drop (call $sock_send (local.get $client) (i32.const 64) (i32.const 1) (i32.const 0) (i32.const 24)))
+                (drop (call $fd_bytes_available (i32.load (i32.const 40)) (i32.const 240)))
+
+                ;; exit with (listener bytes * 100) + stream bytes
+                (call $proc_exit
+                    (i32.add
+                        (i32.mul (i32.wrap_i64 (i64.load (i32.const 32))) (i32.const 100))
+                        (i32.wrap_i64 (i64.load (i32.const 240)))))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 10);
+}
+
+#[test]
+fn test_fd_bytes_available_file() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "fd_bytes_available" (func $fd_bytes_available (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_seek" (func $fd_seek (param i32 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "file")
+            (func (export "_start")
+                (drop (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                    (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (i32.const 16)))
+                (drop (call $fd_seek (i32.load (i32.const 16)) (i64.const 5) (i32.const 0) (i32.const 24)))
+                (drop (call $fd_bytes_available (i32.load (i32.const 16)) (i32.const 32)))
+                (call $proc_exit (i32.wrap_i64 (i64.load (i32.const 32))))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/file")
+        .unwrap();
+    file.set_len(20).unwrap();
+
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), 15);
+}
+
+#[test]
+fn test_fd_filestat_set_size_pipe() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (drop (call $fd_pipe (i32.const 0) (i32.const 4)))
+                (call $proc_exit
+                    (call $fd_filestat_set_size (i32.load (i32.const 0)) (i64.const 0)))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), Errno::Inval as i32);
+}
+
+#[test]
+fn test_fd_filestat_set_size_directory() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (call $proc_exit (call $fd_filestat_set_size (i32.const 3) (i64.const 0)))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), Errno::Isdir as i32);
+}
+
+#[test]
+fn test_fd_filestat_set_size_file() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_filestat_set_size" (func $fd_filestat_set_size (param i32 i64) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "file")
+            (func (export "_start")
+                (drop (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                    (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (i32.const 16)))
+                (call $proc_exit
+                    (call $fd_filestat_set_size (i32.load (i32.const 16)) (i64.const 10)))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/file");
+
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+    assert_eq!(fs.metadata("/file".as_ref()).unwrap().len, 10);
+}
+
+#[test]
+fn test_fd_write_dsync_is_durable() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "out")
+            (data (i32.const 32) "\40\00\00\00\07\00\00\00")
+            (data (i32.const 64) "durable")
+            (func (export "_start")
+                (drop (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 3)
+                    (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (i32.const 16)))
+                ;; O_DSYNC
+                (drop (call $fd_fdstat_set_flags (i32.load (i32.const 16)) (i32.const 2)))
+                (call $proc_exit
+                    (call $fd_write (i32.load (i32.const 16)) (i32.const 32) (i32.const 1) (i32.const 48)))
+            )
+        )
+    "#;
+
+    let dir = HostDir::new();
+    let code = run_wat_with(wat, dir.builder());
+    assert_eq!(code.raw(), Errno::Success as i32);
+
+    // The file was never closed nor synced explicitly by the guest
+    let contents = std::fs::read(dir.path().join("out")).unwrap();
+    assert_eq!(contents, b"durable");
+}
+
+#[test]
+fn test_fd_sync_batch() {
+    // Writes to the files `a`, `b` and `c`, syncs all three of them in one
+    // call and exits with the result of the call OR-ed with each per-fd result
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "fd_sync_batch" (func $fd_sync_batch (param i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "abc")
+            (data (i32.const 32) "\40\00\00\00\06\00\00\00")
+            (data (i32.const 64) "synced")
+            (func $open_and_write (param $path i32) (param $fd_out i32)
+                (drop (call $path_open
+                    (i32.const 3) (i32.const 0) (local.get $path) (i32.const 1)
+                    (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (local.get $fd_out)))
+                (drop (call $fd_write (i32.load (local.get $fd_out)) (i32.const 32) (i32.const 1) (i32.const 48)))
+            )
+            (func (export "_start")
+                (call $open_and_write (i32.const 0) (i32.const 16))
+                (call $open_and_write (i32.const 1) (i32.const 20))
+                (call $open_and_write (i32.const 2) (i32.const 24))
+                (call $proc_exit
+                    (i32.or
+                        (call $fd_sync_batch (i32.const 16) (i32.const 3) (i32.const 96))
+                        (i32.or
+                            (i32.load16_u (i32.const 96))
+                            (i32.or
+                                (i32.load16_u (i32.const 98))
+                                (i32.load16_u (i32.const 100))))))
+            )
+        )
+    "#;
+
+    let dir = HostDir::new();
+    let code = run_wat_with(wat, dir.builder());
+    assert_eq!(code.raw(), Errno::Success as i32);
+
+    for name in ["a", "b", "c"] {
+        let contents = std::fs::read(dir.path().join(name)).unwrap();
+        assert_eq!(contents, b"synced");
+    }
+}
+
+#[test]
+fn test_fd_readdir_snapshot_survives_directory_changes() {
+    // Reads the first two entries ("." and "..", 25 and 26 bytes) of the
+    // preopened directory, creates "-new" (which sorts before them) and then
+    // continues the scan at cookie 2, which must still return "b" and "d"
+    // (25 bytes each) rather than ".." again. A new scan at cookie 0 sees
+    // the new file.
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_readdir" (func $fd_readdir (param i32 i32 i32 i64 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "-new")
+            (func (export "_start")
+                (if (i32.ne (call $fd_readdir (i32.const 3) (i32.const 1024) (i32.const 51) (i64.const 0) (i32.const 16)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 100))))
+                (if (i32.ne (i32.load (i32.const 16)) (i32.const 51))
+                    (then (call $proc_exit (i32.const 101))))
+
+                (if (i32.ne (call $path_open
+                        (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                        (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0)
+                        (i32.const 8))
+                        (i32.const 0))
+                    (then (call $proc_exit (i32.const 102))))
+
+                (if (i32.ne (call $fd_readdir (i32.const 3) (i32.const 1024) (i32.const 1024) (i64.const 2) (i32.const 16)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 103))))
+                (if (i32.ne (i32.load (i32.const 16)) (i32.const 50))
+                    (then (call $proc_exit (i32.const 104))))
+                (if (i32.ne (i32.load8_u (i32.const 1048)) (i32.const 98))
+                    (then (call $proc_exit (i32.const 105))))
+
+                (if (i32.ne (call $fd_readdir (i32.const 3) (i32.const 1024) (i32.const 1024) (i64.const 0) (i32.const 16)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 106))))
+                (if (i32.ne (i32.load (i32.const 16)) (i32.const 129))
+                    (then (call $proc_exit (i32.const 107))))
+
+                (call $proc_exit (i32.const 0))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/b");
+    create_file(&fs, "/d");
+
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+#[test]
+fn test_fd_readdir_raw() {
+    // Reads the preopened directory with `fd_readdir_raw` into a 1KiB buffer
+    // until it is exhausted and appends every filled buffer to the file `out`
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "fd_readdir_raw" (func $fd_readdir_raw (param i32 i32 i32 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "out")
+            (data (i32.const 32) "\00\04\00\00")
+            (func (export "_start")
+                (local $err i32)
+                (local $calls i32)
+                (local.set $err (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 3)
+                    (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (i32.const 8)))
+                (block $done
+                    (loop $next
+                        (local.set $err (i32.or (local.get $err)
+                            (call $fd_readdir_raw
+                                (i32.const 3) (i32.const 1024) (i32.const 1024)
+                                (i64.load (i32.const 24)) (i32.const 16) (i32.const 24))))
+                        (br_if $done (i32.eqz (i32.load (i32.const 16))))
+                        (i32.store (i32.const 36) (i32.load (i32.const 16)))
+                        (local.set $err (i32.or (local.get $err)
+                            (call $fd_write (i32.load (i32.const 8)) (i32.const 32) (i32.const 1) (i32.const 40))))
+                        (local.set $calls (i32.add (local.get $calls) (i32.const 1)))
+                        (br $next)))
+                (if (i32.lt_u (local.get $calls) (i32.const 2))
+                    (then (call $proc_exit (i32.const 255))))
+                (call $proc_exit (local.get $err))
+            )
+        )
+    "#;
+
+    let dir = HostDir::new();
+    let mut expected = vec![".".to_string(), "..".to_string(), "out".to_string()];
+    for i in 0..50 {
+        let name = format!("file_{i:02}");
+        std::fs::write(dir.path().join(&name), b"").unwrap();
+        expected.push(name);
+    }
+    expected.sort();
+
+    let code = run_wat_with(wat, dir.builder());
+    assert_eq!(code.raw(), Errno::Success as i32);
+
+    // Decode the dirent64 records
+    let out = std::fs::read(dir.path().join("out")).unwrap();
+    let mut names = Vec::new();
+    let mut pos = 0;
+    while pos < out.len() {
+        let record = &out[pos..];
+        let d_off = i64::from_le_bytes(record[8..16].try_into().unwrap());
+        let d_reclen = u16::from_le_bytes(record[16..18].try_into().unwrap()) as usize;
+        let d_type = record[18];
+        let name_len = record[19..d_reclen].iter().position(|b| *b == 0).unwrap();
+        let name = std::str::from_utf8(&record[19..19 + name_len]).unwrap();
+
+        assert_eq!(d_reclen % 8, 0);
+        assert_eq!(d_off, names.len() as i64 + 1);
+        let expected_type = if name.starts_with('.') { 4 } else { 8 };
+        assert_eq!(d_type, expected_type, "type of {name}");
+
+        names.push(name.to_string());
+        pos += d_reclen;
+    }
+    assert_eq!(pos, out.len());
+    assert_eq!(names, expected);
+}
+
+#[test]
+fn test_preopen_fd() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "\40\00\00\00\20\00\00\00")
+            (data (i32.const 128) "wasmer!!")
+            (func (export "_start")
+                (drop (call $fd_read (i32.const 5) (i32.const 0) (i32.const 1) (i32.const 16)))
+                (if (i64.ne (i64.load (i32.const 64)) (i64.load (i32.const 128)))
+                    (then (call $proc_exit (i32.const 255))))
+                (call $proc_exit (i32.load (i32.const 16)))
+            )
+        )
+    "#;
+
+    let builder = WasiEnv::builder("syscalls").preopen_fd(
+        5,
+        Box::new(StaticFile::new(b"wasmer!!".to_vec())),
+        Rights::FD_READ,
+    );
+    let code = run_wat_with(wat, builder);
+
+    assert_eq!(code.raw(), 8);
+}
+
+#[test]
+fn test_preopen_fd_collides_with_stdio() {
+    let builder = WasiEnv::builder("syscalls").preopen_fd(
+        1,
+        Box::new(StaticFile::new(Vec::new())),
+        Rights::FD_READ,
+    );
+
+    assert!(builder.build_init().is_err());
+}
+
+#[test]
+fn test_poll_oneoff_events_in_subscription_order() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "file")
+            ;; three subscriptions on the same file that are all ready at once,
+            ;; with userdata that is not in ascending order
+            (data (i32.const 256) "\03\00\00\00\00\00\00\00\02")
+            (data (i32.const 304) "\01\00\00\00\00\00\00\00\01")
+            (data (i32.const 352) "\02\00\00\00\00\00\00\00\02")
+            (func (export "_start")
+                (local $fd i32)
+                (local $i i32)
+                (drop (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                    (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (i32.const 16)))
+                (local.set $fd (i32.load (i32.const 16)))
+                (i32.store (i32.const 272) (local.get $fd))
+                (i32.store (i32.const 320) (local.get $fd))
+                (i32.store (i32.const 368) (local.get $fd))
+
+                ;; the events come back in the order of the subscriptions every time
+                (loop $again
+                    (drop (call $poll_oneoff (i32.const 256) (i32.const 512) (i32.const 3) (i32.const 640)))
+                    (if (i32.ne (i32.load (i32.const 640)) (i32.const 3))
+                        (then (call $proc_exit (i32.const 1))))
+                    (if (i64.ne (i64.load (i32.const 512)) (i64.const 3))
+                        (then (call $proc_exit (i32.const 2))))
+                    (if (i64.ne (i64.load (i32.const 544)) (i64.const 1))
+                        (then (call $proc_exit (i32.const 2))))
+                    (if (i64.ne (i64.load (i32.const 576)) (i64.const 2))
+                        (then (call $proc_exit (i32.const 2))))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $again (i32.lt_u (local.get $i) (i32.const 8)))
+                )
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/file")
+        .unwrap();
+    futures::executor::block_on(file.write_all(b"data")).unwrap();
+
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_sock_recv_from_truncated_datagram() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_send_to" (func $sock_send_to (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_recv_from" (func $sock_recv_from (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            ;; 100 bytes to send and a 50 byte buffer to receive them into
+            (data (i32.const 64) "\00\01\00\00\64\00\00\00")
+            (data (i32.const 80) "\00\02\00\00\32\00\00\00")
+            (func (export "_start")
+                (local $port i32)
+                ;; UDP socket bound to an ephemeral port
+                (drop (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+                ;; send a datagram to ourselves
+                (drop (call $sock_send_to
+                    (i32.load (i32.const 0)) (i32.const 64) (i32.const 1) (i32.const 0)
+                    (i32.const 16) (i32.const 96)))
+                (drop (call $sock_recv_from
+                    (i32.load (i32.const 0)) (i32.const 80) (i32.const 1) (i32.const 0)
+                    (i32.const 100) (i32.const 104) (i32.const 112)))
+                ;; exit with (flags * 1000) + bytes received
+                (call $proc_exit
+                    (i32.add
+                        (i32.mul (i32.load16_u (i32.const 104)) (i32.const 1000))
+                        (i32.load (i32.const 100))))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 1050);
+}
+
+#[test]
+fn test_sock_recv_truncated_datagram() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_send_to" (func $sock_send_to (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            ;; 100 bytes to send and a 50 byte buffer to receive them into
+            (data (i32.const 64) "\00\01\00\00\64\00\00\00")
+            (data (i32.const 80) "\00\02\00\00\32\00\00\00")
+            (func (export "_start")
+                (local $port i32)
+                ;; UDP socket bound to an ephemeral port
+                (drop (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+                ;; send a datagram to ourselves
+                (drop (call $sock_send_to
+                    (i32.load (i32.const 0)) (i32.const 64) (i32.const 1) (i32.const 0)
+                    (i32.const 16) (i32.const 96)))
+                (drop (call $sock_recv
+                    (i32.load (i32.const 0)) (i32.const 80) (i32.const 1) (i32.const 0)
+                    (i32.const 100) (i32.const 104)))
+                ;; exit with (flags * 1000) + bytes received
+                (call $proc_exit
+                    (i32.add
+                        (i32.mul (i32.load16_u (i32.const 104)) (i32.const 1000))
+                        (i32.load (i32.const 100))))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 1050);
+}
+
+#[test]
+fn test_sock_send_vectored_single_datagram() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_recv_from" (func $sock_recv_from (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            ;; three buffers to send and a 64 byte buffer to receive them into
+            (data (i32.const 64) "\c8\00\00\00\03\00\00\00\d2\00\00\00\02\00\00\00\dc\00\00\00\03\00\00\00")
+            (data (i32.const 96) "\2c\01\00\00\40\00\00\00")
+            (data (i32.const 200) "abc")
+            (data (i32.const 210) "de")
+            (data (i32.const 220) "fgh")
+            (data (i32.const 240) "abcdefgh")
+            (func (export "_start")
+                (local $port i32)
+                ;; UDP socket bound to an ephemeral port and connected to itself
+                (drop (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+                (drop (call $sock_connect (i32.load (i32.const 0)) (i32.const 16)))
+
+                (drop (call $sock_send
+                    (i32.load (i32.const 0)) (i32.const 64) (i32.const 3) (i32.const 0)
+                    (i32.const 112)))
+                (drop (call $sock_recv_from
+                    (i32.load (i32.const 0)) (i32.const 96) (i32.const 1) (i32.const 0)
+                    (i32.const 116) (i32.const 120) (i32.const 128)))
+
+                ;; the datagram holds the concatenation of the buffers
+                (if (i64.ne (i64.load (i32.const 300)) (i64.load (i32.const 240)))
+                    (then (call $proc_exit (i32.const 1))))
+                (call $proc_exit (i32.load (i32.const 116)))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 8);
+}
+
+#[test]
+fn test_sock_recv_overlapping_iovecs() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_send" (func $sock_send (param i32 i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            ;; one buffer to send and two receive buffers at the same address
+            (data (i32.const 64) "\c8\00\00\00\06\00\00\00")
+            (data (i32.const 96) "\2c\01\00\00\03\00\00\00\2c\01\00\00\03\00\00\00")
+            (data (i32.const 200) "abcdef")
+            (data (i32.const 240) "def")
+            (func (export "_start")
+                (local $port i32)
+                ;; UDP socket bound to an ephemeral port and connected to itself
+                (drop (call $sock_open (i32.const 1) (i32.const 2) (i32.const 17) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+                (drop (call $sock_connect (i32.load (i32.const 0)) (i32.const 16)))
+
+                (drop (call $sock_send
+                    (i32.load (i32.const 0)) (i32.const 64) (i32.const 1) (i32.const 0)
+                    (i32.const 112)))
+                (if (call $sock_recv
+                        (i32.load (i32.const 0)) (i32.const 96) (i32.const 2) (i32.const 0)
+                        (i32.const 116) (i32.const 120))
+                    (then (call $proc_exit (i32.const 1))))
+
+                ;; the second buffer overwrites the first one like with readv
+                (if (i32.ne (i32.load (i32.const 300)) (i32.load (i32.const 240)))
+                    (then (call $proc_exit (i32.const 2))))
+                (call $proc_exit (i32.load (i32.const 116)))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 6);
+}
+
+#[test]
+fn test_sock_connect_nonblocking_in_progress() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_get_opt_size" (func $sock_get_opt_size (param i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            ;; wait for the client to become writable (userdata 1, fd_write)
+            (data (i32.const 256) "\01\00\00\00\00\00\00\00\02")
+            ;; or give up after 5 seconds (userdata 2, clock, monotonic)
+            (data (i32.const 304) "\02\00\00\00\00\00\00\00\00")
+            (data (i32.const 320) "\01\00\00\00\00\00\00\00\00\f2\05\2a\01\00\00\00")
+            (func (export "_start")
+                (local $port i32)
+                (local $client i32)
+                ;; TCP listener bound to an ephemeral port
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_listen (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+
+                ;; nonblocking client socket
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 4)))
+                (local.set $client (i32.load (i32.const 4)))
+                (drop (call $fd_fdstat_set_flags (local.get $client) (i32.const 4)))
+                (if (i32.ne (call $sock_connect (local.get $client) (i32.const 16)) (i32.const 26))
+                    (then (call $proc_exit (i32.const 1))))
+
+                ;; the socket becomes writable once the connection is established
+                (i32.store (i32.const 272) (local.get $client))
+                (drop (call $poll_oneoff (i32.const 256) (i32.const 400) (i32.const 2) (i32.const 480)))
+                (if (i64.ne (i64.load (i32.const 400)) (i64.const 1))
+                    (then (call $proc_exit (i32.const 2))))
+                (if (i32.ne (i32.load16_u (i32.const 408)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 3))))
+
+                ;; and the outcome of the connect is reported by SO_ERROR
+                (if (i32.ne (call $sock_get_opt_size (local.get $client) (i32.const 11) (i32.const 496)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 4))))
+                (if (i64.ne (i64.load (i32.const 496)) (i64.const 0))
+                    (then (call $proc_exit (i32.const 5))))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_sock_recv_timeout() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_set_opt_time" (func $sock_set_opt_time (param i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_recv" (func $sock_recv (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            ;; a 16 byte buffer to receive into
+            (data (i32.const 64) "\80\00\00\00\10\00\00\00")
+            ;; Some(100ms)
+            (data (i32.const 512) "\01\00\00\00\00\00\00\00\00\e1\f5\05\00\00\00\00")
+            (func (export "_start")
+                (local $port i32)
+                (local $client i32)
+                ;; TCP listener bound to an ephemeral port
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_listen (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+
+                ;; connected client that never receives anything
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 4)))
+                (local.set $client (i32.load (i32.const 4)))
+                (if (i32.ne (call $sock_connect (local.get $client) (i32.const 16)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1000))))
+
+                ;; SO_RCVTIMEO
+                (if (i32.ne (call $sock_set_opt_time (local.get $client) (i32.const 19) (i32.const 512)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1001))))
+
+                (call $proc_exit
+                    (call $sock_recv
+                        (local.get $client) (i32.const 64) (i32.const 1) (i32.const 0)
+                        (i32.const 96) (i32.const 100)))
+            )
+        )
+    "#;
+
+    let builder = WasiEnv::builder("syscalls");
+
+    let started = Instant::now();
+    let code = run_wat_with(wat, builder);
+    let elapsed = started.elapsed();
+
+    assert_eq!(code.raw(), Errno::Again as i32);
+    assert!(elapsed >= Duration::from_millis(100));
+    // Without the timeout the receive would block for much longer
+    assert!(elapsed < Duration::from_secs(5));
+}
+
+#[test]
+fn test_sock_get_opt_last_error_connection_refused() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "sock_open" (func $sock_open (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_bind" (func $sock_bind (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_listen" (func $sock_listen (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_addr_local" (func $sock_addr_local (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_connect" (func $sock_connect (param i32 i32) (result i32)))
+            (import "wasix_32v1" "sock_get_opt_size" (func $sock_get_opt_size (param i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_fdstat_set_flags" (func $fd_fdstat_set_flags (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            ;; 127.0.0.1:0
+            (data (i32.const 16) "\01\00\00\00\7f\00\00\01")
+            ;; wait for the client to become writable (userdata 1, fd_write)
+            (data (i32.const 256) "\01\00\00\00\00\00\00\00\02")
+            ;; or give up after 5 seconds (userdata 2, clock, monotonic)
+            (data (i32.const 304) "\02\00\00\00\00\00\00\00\00")
+            (data (i32.const 320) "\01\00\00\00\00\00\00\00\00\f2\05\2a\01\00\00\00")
+            (func (export "_start")
+                (local $port i32)
+                (local $client i32)
+                ;; find a free port by binding a listener and closing it again
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 0)))
+                (drop (call $sock_bind (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_listen (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $sock_addr_local (i32.load (i32.const 0)) (i32.const 16)))
+                (drop (call $fd_close (i32.load (i32.const 0))))
+                ;; the local address reports the port in network byte order
+                (local.set $port (i32.load8_u (i32.const 18)))
+                (i32.store8 (i32.const 18) (i32.load8_u (i32.const 19)))
+                (i32.store8 (i32.const 19) (local.get $port))
+
+                ;; nonblocking client socket that connects to nothing
+                (drop (call $sock_open (i32.const 1) (i32.const 1) (i32.const 6) (i32.const 4)))
+                (local.set $client (i32.load (i32.const 4)))
+                (drop (call $fd_fdstat_set_flags (local.get $client) (i32.const 4)))
+                (if (i32.ne (call $sock_connect (local.get $client) (i32.const 16)) (i32.const 26))
+                    (then (call $proc_exit (i32.const 1))))
+
+                ;; wait for the connect to fail
+                (i32.store (i32.const 272) (local.get $client))
+                (drop (call $poll_oneoff (i32.const 256) (i32.const 400) (i32.const 2) (i32.const 480)))
+                (if (i64.ne (i64.load (i32.const 400)) (i64.const 1))
+                    (then (call $proc_exit (i32.const 2))))
+
+                ;; SO_ERROR reports the refused connection once
+                (if (i32.ne (call $sock_get_opt_size (local.get $client) (i32.const 11) (i32.const 496)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 3))))
+                (if (i64.ne (i64.load (i32.const 496)) (i64.const 28))
+                    (then (call $proc_exit (i32.const 4))))
+                ;; and is cleared afterwards
+                (drop (call $sock_get_opt_size (local.get $client) (i32.const 11) (i32.const 496)))
+                (if (i64.ne (i64.load (i32.const 496)) (i64.const 0))
+                    (then (call $proc_exit (i32.const 5))))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_clock_nanosleep_absolute_deadline() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+            (import "wasix_32v1" "clock_nanosleep" (func $clock_nanosleep (param i32 i32 i64 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (local $deadline i64)
+                ;; sleep until 100ms from now on the monotonic clock
+                (drop (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 0)))
+                (local.set $deadline (i64.add (i64.load (i32.const 0)) (i64.const 100000000)))
+                (if (i32.ne (call $clock_nanosleep (i32.const 1) (i32.const 1) (local.get $deadline) (i32.const 16)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1))))
+
+                ;; the sleep must not have returned before the deadline
+                (drop (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 8)))
+                (if (i64.lt_u (i64.load (i32.const 8)) (local.get $deadline))
+                    (then (call $proc_exit (i32.const 2))))
+            )
+        )
+    "#;
+
+    let started = Instant::now();
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 0);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn test_proc_gethostname() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_gethostname" (func $proc_gethostname (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 128) "wasmer-host\00")
+            (data (i32.const 200) "\ff\ff\ff\ff\ff\ff\ff\ff")
+            (func (export "_start")
+                ;; the whole name (and its terminator) fits into the buffer
+                (if (i32.ne (call $proc_gethostname (i32.const 64) (i32.const 32)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1))))
+                (if (i64.ne (i64.load (i32.const 64)) (i64.load (i32.const 128)))
+                    (then (call $proc_exit (i32.const 2))))
+                (if (i32.ne (i32.load (i32.const 72)) (i32.load (i32.const 136)))
+                    (then (call $proc_exit (i32.const 3))))
+
+                ;; the name is truncated to the buffer which is left unterminated
+                (if (i32.ne (call $proc_gethostname (i32.const 200) (i32.const 6)) (i32.const 37))
+                    (then (call $proc_exit (i32.const 4))))
+                (if (i32.ne (i32.load (i32.const 200)) (i32.load (i32.const 128)))
+                    (then (call $proc_exit (i32.const 5))))
+                (if (i32.ne (i32.load16_u (i32.const 204)) (i32.load16_u (i32.const 132)))
+                    (then (call $proc_exit (i32.const 6))))
+                (if (i32.ne (i32.load8_u (i32.const 206)) (i32.const 255))
+                    (then (call $proc_exit (i32.const 7))))
+            )
+        )
+    "#;
+
+    let builder = WasiEnv::builder("syscalls").hostname("wasmer-host");
+    let code = run_wat_with(wat, builder);
+
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_proc_gethostname_defaults_to_localhost() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_gethostname" (func $proc_gethostname (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 128) "localhost\00")
+            (func (export "_start")
+                (drop (call $proc_gethostname (i32.const 64) (i32.const 32)))
+                (if (i64.ne (i64.load (i32.const 64)) (i64.load (i32.const 128)))
+                    (then (call $proc_exit (i32.const 1))))
+                (if (i32.ne (i32.load16_u (i32.const 72)) (i32.load16_u (i32.const 136)))
+                    (then (call $proc_exit (i32.const 2))))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_proc_sysctl() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "proc_sysctl" (func $proc_sysctl (param i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "TestOS\n")
+            (data (i32.const 16) "proc/sys/kernel/ostype")
+            (data (i32.const 48) "kernel.ostype")
+            (data (i32.const 80) "kernel.missing")
+            (data (i32.const 128) "\00\02\00\00\40\00\00\00")
+            (func (export "_start")
+                ;; the value is served by the file system mounted at /proc/sys
+                (if (i32.ne (call $path_open
+                        (i32.const 3) (i32.const 1) (i32.const 16) (i32.const 22)
+                        (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0)
+                        (i32.const 164))
+                        (i32.const 0))
+                    (then (call $proc_exit (i32.const 1))))
+                (if (i32.ne (call $fd_read (i32.load (i32.const 164)) (i32.const 128) (i32.const 1) (i32.const 160))
+                        (i32.const 0))
+                    (then (call $proc_exit (i32.const 2))))
+                (if (i32.ne (i32.load (i32.const 160)) (i32.const 7))
+                    (then (call $proc_exit (i32.const 3))))
+                (if (i32.ne (i32.load (i32.const 512)) (i32.load (i32.const 0)))
+                    (then (call $proc_exit (i32.const 4))))
+                (if (i32.ne (i32.load (i32.const 515)) (i32.load (i32.const 3)))
+                    (then (call $proc_exit (i32.const 5))))
+
+                ;; and by the syscall (without the trailing newline)
+                (if (i32.ne (call $proc_sysctl (i32.const 48) (i32.const 13) (i32.const 600) (i32.const 64) (i32.const 168))
+                        (i32.const 0))
+                    (then (call $proc_exit (i32.const 6))))
+                (if (i32.ne (i32.load (i32.const 168)) (i32.const 6))
+                    (then (call $proc_exit (i32.const 7))))
+                (if (i32.ne (i32.load (i32.const 600)) (i32.load (i32.const 0)))
+                    (then (call $proc_exit (i32.const 8))))
+                (if (i32.ne (i32.load16_u (i32.const 604)) (i32.load16_u (i32.const 4)))
+                    (then (call $proc_exit (i32.const 9))))
+
+                ;; unknown keys do not exist
+                (call $proc_exit (call $proc_sysctl (i32.const 80) (i32.const 14) (i32.const 600) (i32.const 64) (i32.const 168)))
+            )
+        )
+    "#;
+
+    let builder = WasiEnv::builder("syscalls")
+        .sandbox_fs(TmpFileSystem::new())
+        .preopen_dir("/")
+        .unwrap()
+        .sysctl("kernel.ostype", "TestOS")
+        .mount_proc_sys(true);
+    let code = run_wat_with(wat, builder);
+
+    assert_eq!(code.raw(), Errno::Noent as i32);
+}
+
+#[test]
+fn test_proc_setpriority_requires_capability_to_raise() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_getpriority" (func $proc_getpriority (param i32 i32) (result i32)))
+            (import "wasix_32v1" "proc_setpriority" (func $proc_setpriority (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                ;; the priority starts at zero
+                (drop (call $proc_getpriority (i32.const 0) (i32.const 64)))
+                (if (i32.ne (i32.load (i32.const 64)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 101))))
+
+                ;; lowering the priority is allowed
+                (if (i32.ne (call $proc_setpriority (i32.const 0) (i32.const 10)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 102))))
+                (drop (call $proc_getpriority (i32.const 0) (i32.const 64)))
+                (if (i32.ne (i32.load (i32.const 64)) (i32.const 10))
+                    (then (call $proc_exit (i32.const 103))))
+
+                ;; but raising it above the initial value is not
+                (call $proc_exit (call $proc_setpriority (i32.const 0) (i32.const -5)))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), Errno::Access as i32);
+}
+
+#[test]
+fn test_proc_exec_host_denied_by_default() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_exec_host" (func $proc_exec_host (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "/bin/echo")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $proc_exec_host
+                        (i32.const 0) (i32.const 9) (i32.const 0) (i32.const 0)
+                        (i32.const 0) (i32.const 0) (i32.const 64)))
+            )
+        )
+    "#;
+
+    let code = run_wat(wat, &TmpFileSystem::new());
+    assert_eq!(code.raw(), Errno::Perm as i32);
+}
+
+#[test]
+fn test_futex_is_not_shared_with_forked_process() {
+    // Forks (the module implements the asyncify exports by hand as it has no
+    // locals to save), the child then waits on a futex for 200ms and exits
+    // with 1 if it was woken. The parent keeps waking the futex at the same
+    // address until the child has exited and then exits with its exit code,
+    // or with 100 if the child never exited. The child has a copy of the
+    // memory so the wakes of the parent must not reach it.
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+            (import "wasix_32v1" "futex_wait" (func $futex_wait (param i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "futex_wake" (func $futex_wake (param i32 i32) (result i32)))
+            (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+            (import "wasix_32v1" "proc_join" (func $proc_join (param i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 2)
+            (export "memory" (memory 0))
+            (global $state (mut i32) (i32.const 0))
+            (global (export "__stack_pointer") (mut i32) (i32.const 65536))
+            (global (export "__stack_low") i32 (i32.const 4096))
+            (data (i32.const 520) "\01")
+            (data (i32.const 528) "\00\c2\eb\0b\00\00\00\00")
+            (func (export "asyncify_start_unwind") (param i32) (global.set $state (i32.const 1)))
+            (func (export "asyncify_stop_unwind") (global.set $state (i32.const 0)))
+            (func (export "asyncify_start_rewind") (param i32) (global.set $state (i32.const 2)))
+            (func (export "asyncify_stop_rewind") (global.set $state (i32.const 0)))
+            (func (export "asyncify_get_state") (result i32) (global.get $state))
+            (func (export "_start")
+                (local $i i32)
+                (drop (call $proc_fork (i32.const 1) (i32.const 0)))
+                (if (i32.eq (global.get $state) (i32.const 1))
+                    (then (return)))
+                (if (i32.eqz (i32.load (i32.const 0)))
+                    (then
+                        (drop (call $futex_wait (i32.const 512) (i32.const 0) (i32.const 520) (i32.const 540)))
+                        (call $proc_exit (i32.load8_u (i32.const 540)))))
+                (block $joined
+                    (loop $wake
+                        (drop (call $futex_wake (i32.const 512) (i32.const 560)))
+                        (drop (call $thread_sleep (i64.const 10000000)))
+                        (i32.store8 (i32.const 128) (i32.const 1))
+                        (i32.store (i32.const 132) (i32.load (i32.const 0)))
+                        (drop (call $proc_join (i32.const 128) (i32.const 1) (i32.const 144)))
+                        (br_if $joined (i32.load8_u (i32.const 144)))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $wake (i32.lt_u (local.get $i) (i32.const 500)))
+                        (call $proc_exit (i32.const 100))))
+                (call $proc_exit (i32.load16_u (i32.const 146)))
+            )
+        )
+    "#;
+
+    let started = Instant::now();
+    let code = run_wat_with(wat, WasiEnv::builder("syscalls"));
+    assert_eq!(code.raw(), 0);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_deny_syscalls() {
+    // Calls `proc_fork` (which is denied) and then spawns a thread that sets
+    // a flag, exits with 1 if the fork was not denied, 2 if the thread could
+    // not be spawned and 3 if the thread never ran
+    let wat = r#"
+        (module
+            (import "env" "memory" (memory 1 1 shared))
+            (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+            (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (data (i32.const 256) "\00\00\01\00")
+            (data (i32.const 312) "\00\40\00\00")
+            (func (export "wasi_thread_start") (param i32 i32)
+                (i32.atomic.store (i32.const 512) (i32.const 1))
+                (drop (memory.atomic.notify (i32.const 512) (i32.const 1)))
+            )
+            (func (export "_start")
+                (local $i i32)
+                (if (i32.ne (call $proc_fork (i32.const 1) (i32.const 16)) (i32.const 52))
+                    (then (call $proc_exit (i32.const 1))))
+                (if (i32.ne (call $thread_spawn (i32.const 256) (i32.const 20)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 2))))
+                (block $done
+                    (loop $wait
+                        (br_if $done (i32.atomic.load (i32.const 512)))
+                        (br_if $done (i32.ge_u (local.get $i) (i32.const 50)))
+                        (drop (memory.atomic.wait32 (i32.const 512) (i32.const 0) (i64.const 100000000)))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br $wait)))
+                (if (i32.eqz (i32.atomic.load (i32.const 512)))
+                    (then (call $proc_exit (i32.const 3))))
+                (call $proc_exit (i32.const 0))
+            )
+        )
+    "#;
+
+    let builder = WasiEnv::builder("syscalls").deny_syscalls(&["proc_fork"]);
+    let code = run_wat_with(wat, builder);
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_deny_syscalls_import_object() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let _guard = runtime.enter();
+
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_fork" (func $proc_fork (param i32 i32) (result i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+        )
+    "#;
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let env = WasiEnv::builder("syscalls")
+        .deny_syscalls(&["proc_fork", "thread-spawn"])
+        .finalize(&mut store)
+        .unwrap();
+
+    // The imports handed out to embedders must not expose denied syscalls
+    // either, the stub does not touch the (missing) instance
+    let imports = env.import_object(&mut store, &module).unwrap();
+    let exports = imports.get_namespace_exports("wasix_32v1").unwrap();
+    let proc_fork = exports.get_function("proc_fork").unwrap();
+    let ret = proc_fork
+        .call(&mut store, &[Value::I32(1), Value::I32(16)])
+        .unwrap();
+    assert_eq!(&*ret, &[Value::I32(Errno::Nosys as i32)]);
+
+    // `thread-spawn` returns thread IDs, so its errors are negative
+    let exports = imports.get_namespace_exports("wasi").unwrap();
+    let thread_spawn = exports.get_function("thread-spawn").unwrap();
+    let ret = thread_spawn.call(&mut store, &[Value::I32(0)]).unwrap();
+    assert_eq!(&*ret, &[Value::I32(-(Errno::Nosys as i32))]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_proc_exec_host_echo() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_exec_host" (func $proc_exec_host (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "proc_join" (func $proc_join (param i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "/bin/echo")
+            (data (i32.const 32) "hello")
+            (data (i32.const 160) "\00\01\00\00\40\00\00\00")
+            (data (i32.const 300) "hello\n")
+            (func (export "_start")
+                (local $ret i32)
+                (local.set $ret
+                    (call $proc_exec_host
+                        (i32.const 0) (i32.const 9) (i32.const 32) (i32.const 5)
+                        (i32.const 0) (i32.const 0) (i32.const 64)))
+                (if (i32.ne (local.get $ret) (i32.const 0))
+                    (then (call $proc_exit (local.get $ret))))
+
+                ;; wait for the host process to exit successfully
+                (i32.store8 (i32.const 128) (i32.const 1))
+                (i32.store (i32.const 132) (i32.load (i32.const 64)))
+                (if (i32.ne (call $proc_join (i32.const 128) (i32.const 0) (i32.const 144)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 101))))
+                (if (i32.ne (i32.load16_u (i32.const 146)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 102))))
+
+                ;; its output is read from the stdout pipe
+                (drop (call $fd_read (i32.load (i32.const 80)) (i32.const 160) (i32.const 1) (i32.const 176)))
+                (if (i32.ne (i32.load (i32.const 176)) (i32.const 6))
+                    (then (call $proc_exit (i32.const 103))))
+                (if (i32.ne (i32.load (i32.const 256)) (i32.load (i32.const 300)))
+                    (then (call $proc_exit (i32.const 104))))
+                (if (i32.ne (i32.load16_u (i32.const 260)) (i32.load16_u (i32.const 304)))
+                    (then (call $proc_exit (i32.const 105))))
+            )
+        )
+    "#;
+
+    let mut capabilities = Capabilities::default();
+    capabilities.allow_host_exec = true;
+    capabilities.host_exec_allowlist = vec![PathBuf::from("/bin/echo")];
+
+    let builder = WasiEnv::builder("syscalls").capabilities(capabilities);
+    let code = run_wat_with(wat, builder);
+
+    assert_eq!(code.raw(), 0);
+}
+
+/// Returns true if a host process is running with exactly these arguments
+/// after the program name
+#[cfg(target_os = "linux")]
+fn host_process_is_running(args: &[&str]) -> bool {
+    let args = args.iter().map(|a| format!("{a}\0")).collect::<String>();
+    std::fs::read_dir("/proc")
+        .unwrap()
+        .filter_map(|entry| std::fs::read(entry.ok()?.path().join("cmdline")).ok())
+        .any(|cmdline| match cmdline.iter().position(|b| *b == 0) {
+            Some(end) => cmdline[end + 1..] == *args.as_bytes(),
+            None => false,
+        })
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_proc_exec_host_child_is_killed_when_the_guest_exits() {
+    // A distinct duration so that the process can be found again
+    let duration = format!("{}.25", 1000 + std::process::id() % 1000);
+    let wat = format!(
+        r#"
+        (module
+            (import "wasix_32v1" "proc_exec_host" (func $proc_exec_host (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "/bin/sleep")
+            (data (i32.const 32) "{duration}")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $proc_exec_host
+                        (i32.const 0) (i32.const 10) (i32.const 32) (i32.const {len})
+                        (i32.const 0) (i32.const 0) (i32.const 64)))
+            )
+        )
+        "#,
+        len = duration.len(),
+    );
+
+    let mut capabilities = Capabilities::default();
+    capabilities.allow_host_exec = true;
+    capabilities.host_exec_allowlist = vec![PathBuf::from("/bin/sleep")];
+
+    let builder = WasiEnv::builder("syscalls").capabilities(capabilities);
+    let code = run_wat_with(&wat, builder);
+    assert_eq!(code.raw(), 0);
+
+    // The guest is gone so the host process has to go too
+    let started = Instant::now();
+    while host_process_is_running(&[&duration]) {
+        assert!(started.elapsed() < Duration::from_secs(10));
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_fchdir_to_subdirectory() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasix_32v1" "fchdir" (func $fchdir (param i32) (result i32)))
+            (import "wasix_32v1" "getcwd" (func $getcwd (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "sub")
+            (data (i32.const 132) "/file")
+            (func (export "_start")
+                (local $ret i32)
+                ;; O_DIRECTORY
+                (if (i32.ne
+                        (call $path_open
+                            (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 3)
+                            (i32.const 2) (i64.const -1) (i64.const -1) (i32.const 0)
+                            (i32.const 16))
+                        (i32.const 0))
+                    (then (call $proc_exit (i32.const 101))))
+                (local.set $ret (call $fchdir (i32.load (i32.const 16))))
+                (if (i32.ne (local.get $ret) (i32.const 0))
+                    (then (call $proc_exit (local.get $ret))))
+
+                ;; the current directory is now "/sub"
+                (i32.store (i32.const 200) (i32.const 4))
+                (if (i32.ne (call $getcwd (i32.const 128) (i32.const 200)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 102))))
+
+                ;; so the relative path "file" resolves to "/sub/file"
+                (call $proc_exit
+                    (call $path_open
+                        (i32.const 3) (i32.const 0) (i32.const 128) (i32.const 9)
+                        (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0)
+                        (i32.const 24)))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/sub".as_ref()).unwrap();
+    create_file(&fs, "/sub/file");
+
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+#[test]
+fn test_fchdir_regular_file() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasix_32v1" "fchdir" (func $fchdir (param i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "file")
+            (func (export "_start")
+                (drop (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 4)
+                    (i32.const 0) (i64.const -1) (i64.const -1) (i32.const 0)
+                    (i32.const 16)))
+                (call $proc_exit (call $fchdir (i32.load (i32.const 16))))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/file");
+
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), Errno::Notdir as i32);
+}
+
+#[test]
+fn test_tty_set_raw_mode_keeps_term_type() {
+    let tty = Arc::new(DefaultTty::new(WasiTtyState {
+        echo: true,
+        line_buffered: true,
+        term_type: Some("xterm-256color".to_string()),
+        colors: true,
+        ..Default::default()
+    }));
+    let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+    runtime.set_tty(tty.clone());
+
+    // Reads the state, switches off echo and canonical mode (the bools at
+    // offsets 19 and 20) and then reads it back to check it was applied
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "tty_get" (func $tty_get (param i32) (result i32)))
+            (import "wasix_32v1" "tty_set" (func $tty_set (param i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (local $ret i32)
+                (local.set $ret (call $tty_get (i32.const 0)))
+                (if (local.get $ret) (then (call $proc_exit (local.get $ret))))
+                (i32.store8 (i32.const 19) (i32.const 0))
+                (i32.store8 (i32.const 20) (i32.const 0))
+                (local.set $ret (call $tty_set (i32.const 0)))
+                (if (local.get $ret) (then (call $proc_exit (local.get $ret))))
+                (local.set $ret (call $tty_get (i32.const 64)))
+                (if (local.get $ret) (then (call $proc_exit (local.get $ret))))
+                (call $proc_exit
+                    (i32.or (i32.load8_u (i32.const 83)) (i32.load8_u (i32.const 84))))
+            )
+        )
+    "#;
+
+    let builder = WasiEnv::builder("syscalls").runtime(Arc::new(runtime));
+    let code = run_wat_with(wat, builder);
+    assert_eq!(code.raw(), Errno::Success as i32);
+
+    let state = tty.tty_get();
+    assert!(!state.echo);
+    assert!(!state.is_canonical());
+    assert!(state.is_raw());
+    assert_eq!(state.term_type.as_deref(), Some("xterm-256color"));
+    assert!(state.colors);
+}
+
+/// Builds a program that atomically writes `contents` to `path` relative to
+/// the preopened root directory (fd 3)
+fn path_write_atomic_wat(path: &str, contents: &str) -> String {
+    format!(
+        r#"
+        (module
+            (import "wasix_32v1" "path_write_atomic" (func $path_write_atomic (param i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "{path}")
+            (data (i32.const 256) "{contents}")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $path_write_atomic
+                        (i32.const 3) (i32.const 0) (i32.const {path_len})
+                        (i32.const 256) (i32.const {contents_len})))
+            )
+        )
+        "#,
+        path_len = path.len(),
+        contents_len = contents.len(),
+    )
+}
+
+fn read_file(fs: &TmpFileSystem, path: &str) -> String {
+    let mut file = fs.new_open_options().read(true).open(path).unwrap();
+    let mut contents = String::new();
+    futures::executor::block_on(file.read_to_string(&mut contents)).unwrap();
+    contents
+}
+
+#[test]
+fn test_path_write_atomic_replaces_file() {
+    let fs = TmpFileSystem::new();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/file")
+        .unwrap();
+    futures::executor::block_on(file.write_all(b"old contents")).unwrap();
+    drop(file);
+
+    let code = run_wat(&path_write_atomic_wat("file", "new"), &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+    assert_eq!(read_file(&fs, "/file"), "new");
+
+    let code = run_wat(&path_write_atomic_wat("other", "created"), &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+    assert_eq!(read_file(&fs, "/other"), "created");
+}
+
+#[test]
+fn test_path_write_atomic_directory() {
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/dir".as_ref()).unwrap();
+
+    let code = run_wat(&path_write_atomic_wat("dir", "data"), &fs);
+    assert_eq!(code.raw(), Errno::Isdir as i32);
+}
+
+#[test]
+fn test_path_copy_tree() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "path_copy_tree" (func $path_copy_tree (param i32 i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "src")
+            (data (i32.const 16) "dst")
+            (func (export "_start")
+                (call $proc_exit
+                    (call $path_copy_tree
+                        (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 3)
+                        (i32.const 16) (i32.const 3) (i32.const 0)))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    fs.create_dir("/src".as_ref()).unwrap();
+    fs.create_dir("/src/sub".as_ref()).unwrap();
+    for (path, contents) in [("/src/top", "top"), ("/src/sub/deep", "deep")] {
+        let mut file = fs
+            .new_open_options()
+            .create(true)
+            .write(true)
+            .open(path)
+            .unwrap();
+        futures::executor::block_on(file.write_all(contents.as_bytes())).unwrap();
+    }
+
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), Errno::Success as i32);
+    assert_eq!(read_file(&fs, "/dst/top"), "top");
+    assert_eq!(read_file(&fs, "/dst/sub/deep"), "deep");
+
+    // The destination must not exist yet
+    let code = run_wat(wat, &fs);
+    assert_eq!(code.raw(), Errno::Exist as i32);
+}
+
+#[test]
+fn test_poll_oneoff_interrupted_by_signal() {
+    // Installs a handler that records SIGUSR1 and spawns a worker that keeps
+    // sending the signal stored at 132 to the process via `proc_signal`. The
+    // main thread first waits on a timeout while SIGCHLD (which is ignored by
+    // default) is sent, then waits on the read end of an empty pipe (which
+    // never becomes ready) while SIGUSR1 is sent. Exits with 1 if the thread
+    // could not be spawned, 2 if SIGCHLD interrupted the poll, 3 if the
+    // handler never ran and otherwise with the result of the second poll
+    let wat = r#"
+        (module
+            (import "env" "memory" (memory 1 1 shared))
+            (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+            (import "wasix_32v1" "fd_pipe" (func $fd_pipe (param i32 i32) (result i32)))
+            (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+            (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+            (import "wasix_32v1" "proc_id" (func $proc_id (param i32) (result i32)))
+            (import "wasix_32v1" "proc_signal" (func $proc_signal (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (data (i32.const 64) "handler")
+            (data (i32.const 256) "\00\00\01\00")
+            (data (i32.const 312) "\00\40\00\00")
+            ;; a relative timeout of 300ms on the monotonic clock
+            (data (i32.const 616) "\00")
+            (data (i32.const 624) "\01\00\00\00")
+            (data (i32.const 632) "\00\a3\e1\11\00\00\00\00")
+            ;; a read on the pipe (its fd is filled in below)
+            (data (i32.const 664) "\01")
+            (func (export "handler") (param $sig i32)
+                (if (i32.eq (local.get $sig) (i32.const 10))
+                    (then (i32.atomic.store (i32.const 128) (i32.const 1))))
+            )
+            (func (export "wasi_thread_start") (param i32 i32)
+                (local $sig i32)
+                (block $done
+                    (loop $send
+                        (drop (call $thread_sleep (i64.const 20000000)))
+                        (local.set $sig (i32.atomic.load (i32.const 132)))
+                        (br_if $done (i32.eqz (local.get $sig)))
+                        (drop (call $proc_signal (i32.load (i32.const 140)) (local.get $sig)))
+                        (br $send)))
+            )
+            (func (export "_start")
+                (local $ret i32)
+                (call $callback_signal (i32.const 64) (i32.const 7))
+                (drop (call $proc_id (i32.const 140)))
+                (drop (call $fd_pipe (i32.const 0) (i32.const 4)))
+                (i32.store (i32.const 672) (i32.load (i32.const 0)))
+                (i32.atomic.store (i32.const 132) (i32.const 17))
+                (if (i32.ne (call $thread_spawn (i32.const 256) (i32.const 20)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1))))
+                (if (i32.ne (call $poll_oneoff (i32.const 608) (i32.const 800) (i32.const 1) (i32.const 32)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 2))))
+                (i32.atomic.store (i32.const 132) (i32.const 10))
+                (local.set $ret (call $poll_oneoff (i32.const 656) (i32.const 800) (i32.const 1) (i32.const 32)))
+                (i32.atomic.store (i32.const 132) (i32.const 0))
+                (if (i32.eqz (i32.atomic.load (i32.const 128)))
+                    (then (call $proc_exit (i32.const 3))))
+                (call $proc_exit (local.get $ret))
+            )
+        )
+    "#;
+
+    let code = run_wat_with(wat, WasiEnv::builder("syscalls"));
+    assert_eq!(code.raw(), Errno::Intr as i32);
+}
+
+#[test]
+fn test_process_signal_goes_to_the_thread_that_unblocks_it() {
+    // The main thread blocks SIGUSR1 and spawns a worker (which inherits the
+    // mask), the signal is then sent to the process while both threads block
+    // it and the worker unblocks it afterwards. Exits with 1 if the thread
+    // could not be spawned, 2 if the worker never started, 3 if the signal
+    // was never handled, 4 if it was handled by the main thread and 5 if the
+    // worker did not inherit the mask
+    let wat = r#"
+        (module
+            (import "env" "memory" (memory 1 1 shared))
+            (import "wasix_32v1" "callback_signal" (func $callback_signal (param i32 i32)))
+            (import "wasix_32v1" "thread_sigmask" (func $thread_sigmask (param i32 i32 i32) (result i32)))
+            (import "wasix_32v1" "thread_spawn_v2" (func $thread_spawn (param i32 i32) (result i32)))
+            (import "wasix_32v1" "thread_sleep" (func $thread_sleep (param i64) (result i32)))
+            (import "wasix_32v1" "thread_id" (func $thread_id (param i32) (result i32)))
+            (import "wasix_32v1" "proc_id" (func $proc_id (param i32) (result i32)))
+            (import "wasix_32v1" "proc_signal" (func $proc_signal (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (data (i32.const 64) "handler")
+            (data (i32.const 256) "\00\00\01\00")
+            (data (i32.const 312) "\00\40\00\00")
+            (func (export "handler") (param $sig i32)
+                (drop (call $thread_id (i32.const 524)))
+                (i32.atomic.store (i32.const 520) (local.get $sig))
+            )
+            (func (export "wasi_thread_start") (param i32 i32)
+                (call $callback_signal (i32.const 64) (i32.const 7))
+                (drop (call $thread_sigmask (i32.const 0) (i32.const 0) (i32.const 536)))
+                (i32.atomic.store (i32.const 512) (i32.const 1))
+                (drop (memory.atomic.notify (i32.const 512) (i32.const 1)))
+                (block $go
+                    (loop $wait
+                        (br_if $go (i32.atomic.load (i32.const 516)))
+                        (drop (memory.atomic.wait32 (i32.const 516) (i32.const 0) (i64.const 100000000)))
+                        (br $wait)))
+                (drop (call $thread_sigmask (i32.const 1) (i32.const 528) (i32.const 0)))
+            )
+            (func $wait_for (param $addr i32) (result i32)
+                (local $i i32)
+                (block $done
+                    (loop $wait
+                        (br_if $done (i32.atomic.load (local.get $addr)))
+                        (br_if $done (i32.ge_u (local.get $i) (i32.const 500)))
+                        (drop (call $thread_sleep (i64.const 10000000)))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br $wait)))
+                (i32.atomic.load (local.get $addr))
+            )
+            (func (export "_start")
+                (call $callback_signal (i32.const 64) (i32.const 7))
+                (i64.store (i32.const 528) (i64.const 512))
+                (drop (call $thread_sigmask (i32.const 0) (i32.const 528) (i32.const 0)))
+                (if (i32.ne (call $thread_spawn (i32.const 256) (i32.const 20)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 1))))
+                (if (i32.eqz (call $wait_for (i32.const 512)))
+                    (then (call $proc_exit (i32.const 2))))
+                (drop (call $proc_id (i32.const 544)))
+                (drop (call $proc_signal (i32.load (i32.const 544)) (i32.const 10)))
+                (i32.atomic.store (i32.const 516) (i32.const 1))
+                (drop (memory.atomic.notify (i32.const 516) (i32.const 1)))
+                (if (i32.eqz (call $wait_for (i32.const 520)))
+                    (then (call $proc_exit (i32.const 3))))
+                (if (i32.ne (i32.load (i32.const 524)) (i32.load (i32.const 20)))
+                    (then (call $proc_exit (i32.const 4))))
+                (if (i64.ne (i64.load (i32.const 536)) (i64.const 512))
+                    (then (call $proc_exit (i32.const 5))))
+                (call $proc_exit (i32.const 0))
+            )
+        )
+    "#;
+
+    let code = run_wat_with(wat, WasiEnv::builder("syscalls"));
+    assert_eq!(code.raw(), 0);
+}
+
+#[test]
+fn test_proc_uptime_follows_the_runtime_clock() {
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "proc_uptime" (func $proc_uptime (param i32) (result i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "uptime") (result i64)
+                (if (i32.ne (call $proc_uptime (i32.const 0)) (i32.const 0))
+                    (then unreachable))
+                (i64.load (i32.const 0))
+            )
+        )
+    "#;
+
+    let tokio_runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let _guard = tokio_runtime.enter();
+
+    let clock = Arc::new(ManualClock::new());
+    clock.advance(Duration::from_millis(1_500));
+    let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+    runtime.set_clock(clock.clone());
+
+    let fs = TmpFileSystem::new();
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    let (instance, _env) = WasiEnv::builder("syscalls")
+        .runtime(Arc::new(runtime))
+        .sandbox_fs(fs.clone())
+        .mount_proc_uptime(true)
+        .instantiate(module, &mut store)
+        .unwrap();
+    let uptime = instance
+        .exports
+        .get_typed_function::<(), i64>(&store, "uptime")
+        .unwrap();
+
+    let before = uptime.call(&mut store).unwrap();
+    assert_eq!(read_file(&fs, "/proc/uptime"), "1.50 0.00\n");
+
+    clock.advance(Duration::from_secs(10));
+    let after = uptime.call(&mut store).unwrap();
+    assert_eq!(
+        Duration::from_nanos((after - before) as u64),
+        Duration::from_secs(10)
+    );
+    assert_eq!(read_file(&fs, "/proc/uptime"), "11.50 0.00\n");
+}
+
+#[test]
+fn test_event_sink_receives_lifecycle_events() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (call $proc_exit (i32.const 3))
+            )
+        )
+    "#;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+    runtime.set_event_sink({
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event)
+    });
+
+    let builder = WasiEnv::builder("syscalls").runtime(Arc::new(runtime));
+    let code = run_wat_with(wat, builder);
+    assert_eq!(code.raw(), 3);
+
+    let events = events.lock().unwrap();
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, RuntimeEvent::InstanceCreated { .. })));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, RuntimeEvent::ProcessExited { code, .. } if code.raw() == 3)));
+}
+
+#[test]
+fn test_event_sink_receives_syscall_errors_and_memory_growth() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+            (import "wasi_snapshot_preview1" "sched_yield" (func $sched_yield (result i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (drop (call $fd_close (i32.const 1234)))
+                (drop (memory.grow (i32.const 2)))
+                (drop (call $sched_yield))
+            )
+        )
+    "#;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut runtime = PluggableRuntime::new(Arc::new(TokioTaskManager::default()));
+    runtime.set_event_sink({
+        let events = events.clone();
+        move |event| events.lock().unwrap().push(event)
+    });
+
+    let mut store = Store::default();
+    let module = Module::new(&store, wat).unwrap();
+    WasiEnv::builder("syscalls")
+        .runtime(Arc::new(runtime))
+        .run_with_store(module, &mut store)
+        .unwrap();
+
+    let events = events.lock().unwrap();
+    assert!(events.iter().any(|event| matches!(
+        event,
+        RuntimeEvent::SyscallFailed {
+            syscall: "fd_close",
+            errno: Errno::Badf,
+            ..
+        }
+    )));
+    assert!(!events.iter().any(|event| matches!(
+        event,
+        RuntimeEvent::SyscallFailed {
+            syscall: "sched_yield",
+            ..
+        }
+    )));
+    let grown = events
+        .iter()
+        .filter_map(|event| match event {
+            RuntimeEvent::MemoryGrown { pages, .. } => Some(*pages),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(grown, vec![3]);
+}
+
+/// A clock that is stuck at a fixed point in time
+#[derive(Debug)]
+struct FixedClock {
+    now: Timestamp,
+    resolution: Timestamp,
+}
+
+impl Clock for FixedClock {
+    fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn now(&self, _id: Snapshot0Clockid) -> Result<Timestamp, Errno> {
+        Ok(self.now)
+    }
+
+    fn resolution(&self, _id: Snapshot0Clockid) -> Result<Timestamp, Errno> {
+        Ok(self.resolution)
+    }
+
+    fn sleep(
+        &self,
+        _id: Snapshot0Clockid,
+        _duration: Duration,
+        _tasks: Arc<dyn VirtualTaskManager>,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'static>> {
+        // Time never passes on the clock so sleeps are over right away
+        Box::pin(async {})
+    }
+}
+
+#[test]
+fn test_clock_time_get_reads_the_injected_clock() {
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "clock_time_get" (func $clock_time_get (param i32 i64 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "clock_res_get" (func $clock_res_get (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                ;; the realtime and monotonic clocks both read the fixed time
+                (if (i32.ne (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 0)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 100))))
+                (if (i64.ne (i64.load (i32.const 0)) (i64.const 1700000000123456789))
+                    (then (call $proc_exit (i32.const 101))))
+                (if (i32.ne (call $clock_time_get (i32.const 1) (i64.const 1) (i32.const 0)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 102))))
+                (if (i64.ne (i64.load (i32.const 0)) (i64.const 1700000000123456789))
+                    (then (call $proc_exit (i32.const 103))))
+
+                ;; and so does the resolution
+                (if (i32.ne (call $clock_res_get (i32.const 0) (i32.const 8)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 104))))
+                (if (i64.ne (i64.load (i32.const 8)) (i64.const 1000))
+                    (then (call $proc_exit (i32.const 105))))
+
+                (call $proc_exit (i32.const 0))
+            )
+        )
+    "#;
+
+    let code = run_wat_with(
+        wat,
+        WasiEnv::builder("syscalls").with_clock(Arc::new(FixedClock {
+            now: 1_700_000_000_123_456_789,
+            resolution: 1_000,
+        })),
+    );
+    assert_eq!(code.raw(), Errno::Success as i32);
+}
+
+#[test]
+fn test_clock_nanosleep_sleeps_on_the_injected_clock() {
+    // Sleeps for an hour on a clock that never moves
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "clock_nanosleep" (func $clock_nanosleep (param i32 i32 i64 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (call $proc_exit
+                    (call $clock_nanosleep (i32.const 1) (i32.const 0) (i64.const 3600000000000) (i32.const 0)))
+            )
+        )
+    "#;
+
+    let started = Instant::now();
+    let code = run_wat_with(
+        wat,
+        WasiEnv::builder("syscalls").with_clock(Arc::new(FixedClock {
+            now: 1_700_000_000_123_456_789,
+            resolution: 1_000,
+        })),
+    );
+    assert_eq!(code.raw(), Errno::Success as i32);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_path_filestat_set_times_omits_unset_fields() {
+    // Sets both times of "f", then each one on its own (with a bogus value
+    // for the omitted one) and then the modification time to the current
+    // time of the guest, checking the times of the file after every step
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "path_filestat_set_times" (func $set_times (param i32 i32 i32 i32 i64 i64 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "path_filestat_get" (func $filestat_get (param i32 i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "f")
+            (func $set (param $atim i64) (param $mtim i64) (param $flags i32) (param $code i32)
+                (if (i32.ne (call $set_times (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 1)
+                        (local.get $atim) (local.get $mtim) (local.get $flags))
+                        (i32.const 0))
+                    (then (call $proc_exit (local.get $code))))
+            )
+            (func $check (param $atim i64) (param $mtim i64) (param $code i32)
+                (if (i32.ne (call $filestat_get (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 64)) (i32.const 0))
+                    (then (call $proc_exit (local.get $code))))
+                (if (i64.ne (i64.load (i32.const 104)) (local.get $atim))
+                    (then (call $proc_exit (i32.add (local.get $code) (i32.const 1)))))
+                (if (i64.ne (i64.load (i32.const 112)) (local.get $mtim))
+                    (then (call $proc_exit (i32.add (local.get $code) (i32.const 2)))))
+            )
+            (func (export "_start")
+                ;; ATIM | MTIM
+                (call $set (i64.const 1000) (i64.const 2000) (i32.const 5) (i32.const 100))
+                (call $check (i64.const 1000) (i64.const 2000) (i32.const 101))
+                ;; MTIM only, the access time is left alone
+                (call $set (i64.const 9999) (i64.const 3000) (i32.const 4) (i32.const 110))
+                (call $check (i64.const 1000) (i64.const 3000) (i32.const 111))
+                ;; ATIM only, the modification time is left alone
+                (call $set (i64.const 4000) (i64.const 9999) (i32.const 1) (i32.const 120))
+                (call $check (i64.const 4000) (i64.const 3000) (i32.const 121))
+                ;; MTIM_NOW only
+                (call $set (i64.const 9999) (i64.const 9999) (i32.const 8) (i32.const 130))
+                (call $check (i64.const 4000) (i64.const 1700000000123456789) (i32.const 131))
+
+                ;; ATIM and ATIM_NOW contradict each other
+                (call $proc_exit (call $set_times (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 1)
+                    (i64.const 0) (i64.const 0) (i32.const 3)))
+            )
+        )
+    "#;
+
+    let fs = TmpFileSystem::new();
+    create_file(&fs, "/f");
+
+    let code = run_wat_with(
+        wat,
+        WasiEnv::builder("syscalls")
+            .sandbox_fs(fs.clone())
+            .preopen_dir("/")
+            .unwrap()
+            .with_clock(Arc::new(FixedClock {
+                now: 1_700_000_000_123_456_789,
+                resolution: 1_000,
+            })),
+    );
+    assert_eq!(code.raw(), Errno::Inval as i32);
+
+    let metadata = fs.metadata("/f".as_ref()).unwrap();
+    assert_eq!(metadata.accessed, 4000);
+    assert_eq!(metadata.modified, 1_700_000_000_123_456_789);
+}
+
+/// Runs a child with `proc_exec3` that reports `argv0` as its first argument
+/// and returns the first argument that the child actually saw
+fn proc_exec3_argv0(argv0: &str) -> String {
+    // The child writes its argv[0] to `/argv0`
+    let child = r#"
+        (module
+            (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "path_open" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 32) "argv0")
+            (func (export "_start")
+                (if (i32.ne (call $args_sizes_get (i32.const 0) (i32.const 4)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 101))))
+                (if (i32.ne (i32.load (i32.const 0)) (i32.const 1))
+                    (then (call $proc_exit (i32.const 102))))
+                (if (i32.ne (call $args_get (i32.const 16) (i32.const 64)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 103))))
+                ;; O_CREAT
+                (if (i32.ne
+                        (call $path_open
+                            (i32.const 3) (i32.const 0) (i32.const 32) (i32.const 5)
+                            (i32.const 1) (i64.const -1) (i64.const -1) (i32.const 0)
+                            (i32.const 8))
+                        (i32.const 0))
+                    (then (call $proc_exit (i32.const 104))))
+                ;; the argument is written without its nul terminator
+                (i32.store (i32.const 24) (i32.load (i32.const 16)))
+                (i32.store (i32.const 28) (i32.sub (i32.load (i32.const 4)) (i32.const 1)))
+                (if (i32.ne (call $fd_write (i32.load (i32.const 8)) (i32.const 24) (i32.const 1) (i32.const 12)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 105))))
+            )
+        )
+    "#;
+    let parent = format!(
+        r#"
+        (module
+            (import "wasix_32v1" "proc_exec3" (func $proc_exec3 (param i32 i32 i32 i32 i32 i32 i32 i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "/child.wasm")
+            (data (i32.const 32) "{argv0}")
+            (data (i32.const 64) "/child.wasm")
+            (func (export "_start")
+                (call $proc_exec3
+                    (i32.const 0) (i32.const 11) (i32.const 32) (i32.const {len})
+                    (i32.const 64) (i32.const 11) (i32.const 0) (i32.const 0))
+                ;; a successful exec never returns
+                (call $proc_exit (i32.const 100))
+            )
+        )
+        "#,
+        len = argv0.len(),
+    );
+
+    let fs = TmpFileSystem::new();
+    let mut file = fs
+        .new_open_options()
+        .create(true)
+        .write(true)
+        .open("/child.wasm")
+        .unwrap();
+    futures::executor::block_on(file.write_all(child.as_bytes())).unwrap();
+    drop(file);
+
+    assert_eq!(run_wat(&parent, &fs).raw(), 0);
+    read_file(&fs, "/argv0")
+}
+
+#[test]
+fn test_proc_exec3_overrides_argv0() {
+    assert_eq!(proc_exec3_argv0("-sh"), "-sh");
+}
+
+#[test]
+fn test_proc_exec3_empty_argv0() {
+    assert_eq!(proc_exec3_argv0(""), "");
+}
+
+#[test]
+fn test_stdin_from_bytes() {
+    // Reads all of stdin and exits with the number of bytes that were read
+    let wat = r#"
+        (module
+            (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (data (i32.const 0) "\40\00\00\00\10\00\00\00")
+            ;; a read subscription on stdin
+            (data (i32.const 256) "\07\00\00\00\00\00\00\00\01")
+            (func (export "_start")
+                (local $total i32)
+
+                ;; stdin is readable before anything has been read
+                (drop (call $poll_oneoff (i32.const 256) (i32.const 512) (i32.const 1) (i32.const 640)))
+                (if (i32.ne (i32.load (i32.const 640)) (i32.const 1))
+                    (then (call $proc_exit (i32.const 101))))
+                (if (i64.ne (i64.load (i32.const 528)) (i64.const 42))
+                    (then (call $proc_exit (i32.const 102))))
+                (if (i32.ne (i32.load16_u (i32.const 536)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 103))))
+
+                (loop $again
+                    (if (i32.ne (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)) (i32.const 0))
+                        (then (call $proc_exit (i32.const 104))))
+                    (local.set $total (i32.add (local.get $total) (i32.load (i32.const 8))))
+                    (br_if $again (i32.ne (i32.load (i32.const 8)) (i32.const 0)))
+                )
+
+                ;; once everything was read the poll reports the hangup
+                (drop (call $poll_oneoff (i32.const 256) (i32.const 512) (i32.const 1) (i32.const 640)))
+                (if (i32.ne (i32.load (i32.const 640)) (i32.const 1))
+                    (then (call $proc_exit (i32.const 105))))
+                (if (i64.ne (i64.load (i32.const 528)) (i64.const 0))
+                    (then (call $proc_exit (i32.const 106))))
+                ;; FD_READWRITE_HANGUP
+                (if (i32.ne (i32.load16_u (i32.const 536)) (i32.const 1))
+                    (then (call $proc_exit (i32.const 107))))
+
+                (call $proc_exit (local.get $total))
+            )
+        )
+    "#;
+
+    let builder = WasiEnv::builder("syscalls").stdin_from_bytes(vec![b'x'; 42]);
+    let code = run_wat_with(wat, builder);
+    assert_eq!(code.raw(), 42);
+}
+
+#[test]
+fn test_thread_parallelism_reports_the_configured_value() {
+    // Exits with the parallelism that was reported
+    let wat = r#"
+        (module
+            (import "wasix_32v1" "thread_parallelism" (func $thread_parallelism (param i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "_start")
+                (if (i32.ne (call $thread_parallelism (i32.const 0)) (i32.const 0))
+                    (then (call $proc_exit (i32.const 100))))
+                (call $proc_exit (i32.load (i32.const 0)))
+            )
+        )
+    "#;
+
+    let run = |parallelism: usize| {
+        run_wat_with(
+            wat,
+            WasiEnv::builder("syscalls").with_thread_parallelism(parallelism),
+        )
+    };
+
+    assert_eq!(run(3).raw(), 3);
+    // A parallelism of zero is reported as one
+    assert_eq!(run(0).raw(), 1);
+}
@@ -80,6 +80,40 @@ fn test_trap_trace(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_trap_wasm_frames(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module
+            (memory 1)
+            (func (export "unreachable") (call $unreachable))
+            (func $unreachable (unreachable))
+            (func (export "out_of_bounds") (drop (call $load)))
+            (func $load (result i32) (i32.load (i32.const 0x10000)))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+
+    for (export, func_index) in [("unreachable", 1), ("out_of_bounds", 3)] {
+        let e = instance
+            .exports
+            .get_function(export)?
+            .call(&mut store, &[])
+            .expect_err("error calling function");
+
+        let frames = e.wasm_frames();
+        assert_eq!(frames.len(), 2, "{export}");
+        assert_eq!(frames[0].func_index, func_index);
+        assert_eq!(frames[0].code_offset as usize, e.trace()[0].func_offset());
+        assert_eq!(frames[1].func_index, func_index - 1);
+    }
+
+    Ok(())
+}
+
 #[compiler_test(traps)]
 fn test_trap_trace_cb(config: crate::Config) -> Result<()> {
     let mut store = config.store();
@@ -155,6 +189,103 @@ fn test_trap_stack_overflow(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_trap_deep_recursion_is_stack_overflow(config: crate::Config) -> Result<()> {
+    let mut store = config.store();
+    let wat = r#"
+        (module
+            (func $rec (export "run") (param i64) (result i64)
+                (local i64 i64 i64 i64)
+                (i64.add
+                    (call $rec (i64.add (local.get 0) (i64.const 1)))
+                    (local.get 0)))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&mut store, &module, &imports! {})?;
+    let run_func = instance
+        .exports
+        .get_function("run")
+        .expect("expected function export");
+
+    let e = run_func
+        .call(&mut store, &[Value::I64(0)])
+        .expect_err("error calling function");
+    assert_eq!(e.to_trap(), Some(wasmer_types::TrapCode::StackOverflow));
+
+    Ok(())
+}
+
+/// Recurses on the host stack until it overflows
+#[cfg(unix)]
+#[inline(never)]
+fn host_recursion(depth: u64) -> u64 {
+    let frame = std::hint::black_box([depth as u8; 4096]);
+    if depth == u64::MAX {
+        return 0;
+    }
+    host_recursion(depth + 1) + frame[depth as usize % frame.len()] as u64
+}
+
+#[cfg(unix)]
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_trap_host_stack_overflow_is_reported(config: crate::Config) -> Result<()> {
+    const CHILD_ENV: &str = "WASMER_TEST_HOST_STACK_OVERFLOW";
+
+    // The host stack can not be recovered from, so the overflow happens in a
+    // child process that runs only this test
+    if std::env::var_os(CHILD_ENV).is_none() {
+        let test_name = std::thread::current().name().unwrap().to_string();
+        let output = std::process::Command::new(std::env::current_exe()?)
+            .args([
+                test_name.as_str(),
+                "--exact",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .env(CHILD_ENV, "1")
+            .output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(
+            stderr.contains("overflowed the host stack"),
+            "unexpected output: {stderr}"
+        );
+        return Ok(());
+    }
+
+    let mut store = config.store();
+    let wat = r#"
+        (module
+            (func $recurse (import "" "recurse") (result i64))
+            (func (export "run") (result i64) (call $recurse))
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let recurse = Function::new_typed(&mut store, || host_recursion(0) as i64);
+    let instance = Instance::new(
+        &mut store,
+        &module,
+        &imports! {
+            "" => {
+                "recurse" => recurse,
+            }
+        },
+    )?;
+    let run_func = instance
+        .exports
+        .get_function("run")
+        .expect("expected function export");
+
+    run_func.call(&mut store, &[])?;
+    unreachable!("the host stack should have overflowed");
+}
+
 #[cfg_attr(target_env = "musl", ignore)]
 #[compiler_test(traps)]
 fn trap_display_pretty(config: crate::Config) -> Result<()> {